@@ -0,0 +1,163 @@
+//! Decomposition Benchmarks
+//!
+//! Compares solving a large mixed basket as independent components against a
+//! single model spanning every item.
+
+use std::hint::black_box;
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use decimal_percentage::Percentage;
+use good_lp::{Expression, Variable};
+use rusty_money::{Money, iso::GBP};
+use slotmap::SlotMap;
+use smallvec::SmallVec;
+
+use lattice::{
+    discounts::SimpleDiscount,
+    items::{Item, groups::ItemGroup},
+    products::ProductKey,
+    promotions::{
+        Promotion, PromotionKey, PromotionSlotKey,
+        budget::PromotionBudget,
+        promotion,
+        qualification::Qualification,
+        types::{
+            DirectDiscountPromotion, MixAndMatchDiscount, MixAndMatchPromotion,
+            PositionalDiscountPromotion,
+        },
+    },
+    solvers::{
+        Solver,
+        ilp::{ILPObserver, ILPSolver},
+    },
+    tags::string::StringTagCollection,
+    utils::slot,
+};
+
+/// Observer that keeps the whole basket in a single model.
+struct WholeModel;
+
+impl ILPObserver for WholeModel {
+    fn on_presence_variable(&mut self, _: usize, _: Variable, _: i64) {}
+
+    fn on_promotion_variable(
+        &mut self,
+        _: PromotionKey,
+        _: usize,
+        _: Variable,
+        _: i64,
+        _: Option<&str>,
+    ) {
+    }
+
+    fn on_exclusivity_constraint(&mut self, _: usize, _: &Expression) {}
+
+    fn on_promotion_constraint(
+        &mut self,
+        _: PromotionKey,
+        _: &str,
+        _: &Expression,
+        _: &str,
+        _: f64,
+    ) {
+    }
+}
+
+/// A basket of `departments` unrelated departments, each with its own promotions.
+fn mixed_basket(departments: usize) -> (Vec<Promotion<'static>>, ItemGroup<'static>) {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let mut slot_keys = SlotMap::<PromotionSlotKey, ()>::with_key();
+    let mut promotions = Vec::new();
+    let mut items: SmallVec<[Item<'static>; 10]> = SmallVec::new();
+
+    for department in 0..departments {
+        let main = format!("dept-{department}-main");
+        let side = format!("dept-{department}-side");
+
+        promotions.push(promotion(DirectDiscountPromotion::new(
+            keys.insert(()),
+            Qualification::match_any(StringTagCollection::from_strs(&[&main])),
+            SimpleDiscount::PercentageOff(Percentage::from(0.10)),
+            PromotionBudget::unlimited(),
+        )));
+
+        promotions.push(promotion(PositionalDiscountPromotion::new(
+            keys.insert(()),
+            Qualification::match_any(StringTagCollection::from_strs(&[&main, &side])),
+            3,
+            SmallVec::from_slice(&[2]),
+            SimpleDiscount::PercentageOff(Percentage::from(1.0)),
+            PromotionBudget::unlimited(),
+        )));
+
+        promotions.push(promotion(MixAndMatchPromotion::new(
+            keys.insert(()),
+            vec![
+                slot(
+                    &mut slot_keys,
+                    StringTagCollection::from_strs(&[&main]),
+                    1,
+                    Some(1),
+                ),
+                slot(
+                    &mut slot_keys,
+                    StringTagCollection::from_strs(&[&side]),
+                    1,
+                    Some(1),
+                ),
+            ],
+            MixAndMatchDiscount::FixedTotal(Money::from_minor(400, GBP)),
+            PromotionBudget::unlimited(),
+        )));
+
+        for (offset, tag) in [&main, &main, &main, &side, &side, &side]
+            .iter()
+            .enumerate()
+        {
+            let price = 150 + 25 * i64::try_from(offset + department % 5).unwrap_or_default();
+
+            items.push(Item::with_tags(
+                ProductKey::default(),
+                Money::from_minor(price, GBP),
+                StringTagCollection::from_strs(&[tag.as_str()]),
+            ));
+        }
+    }
+
+    (promotions, ItemGroup::new(items, GBP))
+}
+
+fn decomposition(c: &mut Criterion) {
+    let mut group = c.benchmark_group("mixed_basket");
+
+    group.sample_size(10);
+
+    for departments in [2, 8, 16] {
+        let (promotions, item_group) = mixed_basket(departments);
+
+        group.bench_with_input(
+            BenchmarkId::new("decomposed", departments),
+            &departments,
+            |b, _| b.iter(|| ILPSolver::solve(black_box(&promotions), black_box(&item_group))),
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("single_model", departments),
+            &departments,
+            |b, _| {
+                b.iter(|| {
+                    ILPSolver::solve_with_observer(
+                        black_box(&promotions),
+                        black_box(&item_group),
+                        &mut WholeModel,
+                    )
+                });
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, decomposition);
+criterion_main!(benches);
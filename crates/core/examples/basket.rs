@@ -5,16 +5,27 @@
 //! Use `-f` to load a fixture set by name
 //! Use `-n` to limit the number of items
 //! Use `-o` to specify the filename of a typst formatted output file in `target/ilp-formulations`
-
-use std::{fs::create_dir_all, io, io::Write, path::PathBuf, time::Instant};
+//! Use `-e` to specify the filename of LP and MPS files exported to `target/ilp-formulations`
+//! Use `-t` to limit the evaluation time in milliseconds
+
+use std::{
+    fs::create_dir_all,
+    io,
+    io::Write,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
 
 use anyhow::Result;
 use clap::Parser;
 use humanize_duration::{Truncate, prelude::DurationExt};
 
 use lattice::{
-    fixtures::Fixture, items::groups::ItemGroup, receipt::Receipt,
-    solvers::ilp::renderers::typst::MultiLayerRenderer, utils::ExampleBasketArgs,
+    fixtures::Fixture,
+    items::groups::ItemGroup,
+    receipt::Receipt,
+    solvers::ilp::renderers::{lp::LpRenderer, typst::MultiLayerRenderer},
+    utils::ExampleBasketArgs,
 };
 
 /// Processed Basket Receipt Example
@@ -24,7 +35,13 @@ pub fn main() -> Result<()> {
 
     let fixture = Fixture::from_set(&args.fixture)?;
     let basket = fixture.basket(args.n)?;
-    let item_group = ItemGroup::from(&basket);
+    let item_group = ItemGroup::from(&basket).with_context(fixture.context().clone());
+
+    let mut graph = fixture.graph()?.clone().with_solver_backend(args.backend);
+
+    if let Some(millis) = args.time_limit {
+        graph = graph.with_time_limit(Duration::from_millis(millis));
+    }
 
     let start = Instant::now();
 
@@ -40,9 +57,7 @@ pub fn main() -> Result<()> {
             fixture.promotion_meta_map(),
         );
 
-        let result = fixture
-            .graph()?
-            .evaluate_with_observer(&item_group, Some(&mut renderer))?;
+        let result = graph.evaluate_with_observer(&item_group, Some(&mut renderer))?;
 
         renderer.write()?;
 
@@ -51,12 +66,34 @@ pub fn main() -> Result<()> {
             renderer.output_path().display()
         );
 
+        result
+    } else if let Some(export) = args.export.as_deref() {
+        let output_dir = PathBuf::from("target").join("ilp-formulations");
+
+        create_dir_all(&output_dir)?;
+
+        let mut renderer = LpRenderer::new_with_metadata(
+            output_dir.join(export),
+            &item_group,
+            fixture.product_meta_map(),
+            fixture.promotion_meta_map(),
+        );
+
+        let result = graph.evaluate_with_observer(&item_group, Some(&mut renderer))?;
+
+        println!("\nILP models written to:");
+
+        for path in renderer.write()? {
+            println!(" {}", path.display());
+        }
+
         result
     } else {
-        fixture.graph()?.evaluate(&item_group)?
+        graph.evaluate(&item_group)?
     };
 
     let elapsed = start.elapsed();
+    let quality = result.quality;
 
     let receipt = Receipt::from_layered_result(&basket, result)?;
 
@@ -77,5 +114,9 @@ pub fn main() -> Result<()> {
         elapsed.as_secs_f32()
     )?;
 
+    if !quality.is_optimal() {
+        writeln!(handle, " Time limit reached: {quality:?} result")?;
+    }
+
     Ok(())
 }
@@ -1,5 +1,7 @@
 //! Basket
 
+use std::num::NonZeroU32;
+
 use rusty_money::{Money, iso::Currency};
 use thiserror::Error;
 
@@ -66,6 +68,59 @@ impl<'a, T: TagCollection> Basket<'a, T> {
         Ok(Basket { items, currency })
     }
 
+    /// Add an item to the end of the basket, returning its index.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `BasketError::CurrencyMismatch` if the item's currency differs from
+    /// the basket currency.
+    pub fn add_item(&mut self, item: Item<'a, T>) -> Result<usize, BasketError> {
+        let item_currency = item.price().currency();
+
+        if item_currency != self.currency {
+            return Err(BasketError::CurrencyMismatch(
+                self.items.len(),
+                item_currency.iso_alpha_code,
+                self.currency.iso_alpha_code,
+            ));
+        }
+
+        self.items.push(item);
+
+        Ok(self.items.len() - 1)
+    }
+
+    /// Remove an item from the basket, returning it.
+    ///
+    /// Later items move down one index.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `BasketError::ItemNotFound` if the item is not found.
+    pub fn remove_item(&mut self, item: usize) -> Result<Item<'a, T>, BasketError> {
+        if item >= self.items.len() {
+            return Err(BasketError::ItemNotFound(item));
+        }
+
+        Ok(self.items.remove(item))
+    }
+
+    /// Change the number of units on an item line.
+    ///
+    /// Use [`remove_item`](Self::remove_item) to take the line out of the basket.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `BasketError::ItemNotFound` if the item is not found.
+    pub fn set_quantity(&mut self, item: usize, quantity: NonZeroU32) -> Result<(), BasketError> {
+        self.items
+            .get_mut(item)
+            .ok_or(BasketError::ItemNotFound(item))?
+            .set_quantity(quantity);
+
+        Ok(())
+    }
+
     /// Calculate the subtotal of the basket.
     ///
     /// # Errors
@@ -89,7 +144,7 @@ impl<'a, T: TagCollection> Basket<'a, T> {
     }
 
     /// Iterate over the items in the basket.
-    pub fn iter(&self) -> impl Iterator<Item = &Item<'_, T>> {
+    pub fn iter(&self) -> impl Iterator<Item = &Item<'a, T>> {
         self.items.iter()
     }
 
@@ -243,6 +298,70 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn add_item_appends_and_returns_index() -> TestResult {
+        let mut basket = Basket::with_items(test_items(), GBP)?;
+
+        let idx = basket.add_item(Item::new(
+            ProductKey::default(),
+            Money::from_minor(400, GBP),
+        ))?;
+
+        assert_eq!(idx, 3);
+        assert_eq!(basket.get_item(3)?.price().to_minor_units(), 400);
+
+        Ok(())
+    }
+
+    #[test]
+    fn add_item_currency_mismatch_errors() {
+        let mut basket = Basket::<'_, StringTagCollection>::new(GBP);
+
+        let result = basket.add_item(Item::new(
+            ProductKey::default(),
+            Money::from_minor(100, USD),
+        ));
+
+        assert!(matches!(
+            result,
+            Err(BasketError::CurrencyMismatch(0, "USD", "GBP"))
+        ));
+        assert!(basket.is_empty());
+    }
+
+    #[test]
+    fn remove_item_shifts_later_items() -> TestResult {
+        let mut basket = Basket::with_items(test_items(), GBP)?;
+
+        let removed = basket.remove_item(0)?;
+
+        assert_eq!(removed.price().to_minor_units(), 100);
+        assert_eq!(basket.len(), 2);
+        assert_eq!(basket.get_item(0)?.price().to_minor_units(), 200);
+        assert!(matches!(
+            basket.remove_item(2),
+            Err(BasketError::ItemNotFound(2))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn set_quantity_updates_line() -> TestResult {
+        let mut basket = Basket::with_items(test_items(), GBP)?;
+
+        basket.set_quantity(1, NonZeroU32::new(4).expect("non-zero quantity"))?;
+
+        assert_eq!(basket.get_item(1)?.quantity(), 4);
+        assert_eq!(basket.subtotal()?, Money::from_minor(1200, GBP));
+        assert!(matches!(
+            basket.set_quantity(3, NonZeroU32::new(1).expect("non-zero quantity")),
+            Err(BasketError::ItemNotFound(3))
+        ));
+
+        Ok(())
+    }
+
     #[test]
     fn get_item_missing_returns_error() {
         let basket = Basket::<'_, StringTagCollection>::new(GBP);
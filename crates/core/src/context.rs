@@ -0,0 +1,188 @@
+//! Evaluation Context
+//!
+//! Basket-level facts (who is buying, where and when, and which coupon codes
+//! they presented) that promotions can test alongside item tags.
+
+use jiff::Timestamp;
+use rustc_hash::FxHashMap;
+use smallvec::SmallVec;
+
+use crate::tags::{collection::TagCollection, string::StringTagCollection};
+
+/// Basket-level context a promotion set is evaluated in.
+///
+/// An empty context has no customer segments, channel, store, timestamp,
+/// attributes or coupon codes, so rules that test any of them do not match.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvaluationContext {
+    customer_segments: StringTagCollection,
+    channel: Option<String>,
+    store_id: Option<String>,
+    timestamp: Option<Timestamp>,
+    attributes: FxHashMap<String, String>,
+    codes: SmallVec<[String; 2]>,
+}
+
+impl EvaluationContext {
+    /// Create an empty evaluation context.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            customer_segments: StringTagCollection::empty(),
+            channel: None,
+            store_id: None,
+            timestamp: None,
+            attributes: FxHashMap::default(),
+            codes: SmallVec::new(),
+        }
+    }
+
+    /// Sets the segments the customer belongs to (e.g. `"loyalty"`, `"staff"`).
+    #[must_use]
+    pub fn with_customer_segments(mut self, segments: StringTagCollection) -> Self {
+        self.customer_segments = segments;
+        self
+    }
+
+    /// Sets the sales channel (e.g. `"online"` or `"in-store"`).
+    #[must_use]
+    pub fn with_channel(mut self, channel: impl Into<String>) -> Self {
+        self.channel = Some(channel.into());
+        self
+    }
+
+    /// Sets the store the basket is being processed for.
+    #[must_use]
+    pub fn with_store_id(mut self, store_id: impl Into<String>) -> Self {
+        self.store_id = Some(store_id.into());
+        self
+    }
+
+    /// Sets the instant the basket is being evaluated at.
+    #[must_use]
+    pub fn with_timestamp(mut self, timestamp: Timestamp) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Adds an arbitrary key/value attribute, replacing any previous value for the key.
+    #[must_use]
+    pub fn with_attribute(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.attributes.insert(key.into(), value.into());
+        self
+    }
+
+    /// Adds a coupon code presented by the shopper.
+    ///
+    /// Codes are kept in presentation order; a code already presented (ignoring
+    /// ASCII case) is not added twice.
+    #[must_use]
+    pub fn with_code(mut self, code: impl Into<String>) -> Self {
+        let code = code.into();
+
+        if !self
+            .codes
+            .iter()
+            .any(|presented| presented.eq_ignore_ascii_case(&code))
+        {
+            self.codes.push(code);
+        }
+
+        self
+    }
+
+    /// Adds several coupon codes presented by the shopper.
+    #[must_use]
+    pub fn with_codes<S: Into<String>>(self, codes: impl IntoIterator<Item = S>) -> Self {
+        codes.into_iter().fold(self, Self::with_code)
+    }
+
+    /// Returns the customer's segments
+    pub fn customer_segments(&self) -> &StringTagCollection {
+        &self.customer_segments
+    }
+
+    /// Returns the sales channel, if any
+    pub fn channel(&self) -> Option<&str> {
+        self.channel.as_deref()
+    }
+
+    /// Returns the store id, if any
+    pub fn store_id(&self) -> Option<&str> {
+        self.store_id.as_deref()
+    }
+
+    /// Returns the evaluation timestamp, if any
+    pub fn timestamp(&self) -> Option<Timestamp> {
+        self.timestamp
+    }
+
+    /// Returns the value of an attribute, if set
+    pub fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes.get(key).map(String::as_str)
+    }
+
+    /// Returns the presented coupon codes, in presentation order
+    pub fn codes(&self) -> &[String] {
+        &self.codes
+    }
+}
+
+impl Default for EvaluationContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use testresult::TestResult;
+
+    use super::*;
+
+    #[test]
+    fn empty_context_has_no_facts() {
+        let context = EvaluationContext::default();
+
+        assert!(context.customer_segments().is_empty());
+        assert_eq!(context.channel(), None);
+        assert_eq!(context.store_id(), None);
+        assert_eq!(context.timestamp(), None);
+        assert_eq!(context.attribute("tier"), None);
+        assert!(context.codes().is_empty());
+    }
+
+    #[test]
+    fn builder_sets_all_facts() -> TestResult {
+        let timestamp: Timestamp = "2026-03-01T12:00:00Z".parse()?;
+
+        let context = EvaluationContext::new()
+            .with_customer_segments(StringTagCollection::from_strs(&["loyalty"]))
+            .with_channel("online")
+            .with_store_id("42")
+            .with_timestamp(timestamp)
+            .with_attribute("tier", "gold")
+            .with_attribute("tier", "platinum");
+
+        assert_eq!(
+            context.customer_segments(),
+            &StringTagCollection::from_strs(&["loyalty"])
+        );
+        assert_eq!(context.channel(), Some("online"));
+        assert_eq!(context.store_id(), Some("42"));
+        assert_eq!(context.timestamp(), Some(timestamp));
+        assert_eq!(context.attribute("tier"), Some("platinum"));
+        assert!(context.codes().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn codes_keep_presentation_order_without_duplicates() {
+        let context = EvaluationContext::new()
+            .with_code("SAVE10")
+            .with_codes(["welcome", "save10", "FREESHIP"]);
+
+        assert_eq!(context.codes(), ["SAVE10", "welcome", "FREESHIP"]);
+    }
+}
@@ -4,13 +4,13 @@
 //! that can be shared across different promotion types.
 
 use decimal_percentage::Percentage;
-use rust_decimal::{
-    Decimal, RoundingStrategy,
-    prelude::{FromPrimitive, ToPrimitive},
-};
 use rusty_money::{Money, MoneyError, iso::Currency};
 use thiserror::Error;
 
+pub mod rounding;
+
+use rounding::RoundingPolicy;
+
 /// Errors specific to discount calculations.
 #[derive(Debug, Error)]
 pub enum DiscountError {
@@ -43,21 +43,15 @@ pub enum SimpleDiscount<'a> {
 /// Calculate the discount amount in minor units based on a percentage and a minor unit amount.
 ///
 /// This is a utility function that can be used by promotion types when calculating
-/// percentage-based discounts.
+/// percentage-based discounts. It rounds with the default [`RoundingPolicy`],
+/// to the nearest minor unit with halves away from zero.
 ///
 /// # Errors
 ///
 /// Returns an error if:
 /// - The percentage calculation overflows or cannot be safely represented (`DiscountError::PercentConversion`).
 pub fn percent_of_minor(percent: &Percentage, minor: i64) -> Result<i64, DiscountError> {
-    let minor = Decimal::from_i64(minor).ok_or(DiscountError::PercentConversion)?;
-
-    ((*percent) * Decimal::ONE) // decimal_percentage crate doesn't actually expose the underlying Decimal
-        .checked_mul(minor)
-        .ok_or(DiscountError::PercentConversion)?
-        .round_dp_with_strategy(0, RoundingStrategy::MidpointAwayFromZero)
-        .to_i64()
-        .ok_or(DiscountError::PercentConversion)
+    RoundingPolicy::default().discount_of(percent, minor)
 }
 
 #[cfg(test)]
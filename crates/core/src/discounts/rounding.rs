@@ -0,0 +1,406 @@
+//! Rounding Policies
+//!
+//! How percentage discounts are rounded to amounts that can be charged: the
+//! rounding direction, the smallest step a discount can take (cash rounding),
+//! and whether each item or each bundle is rounded.
+
+use decimal_percentage::Percentage;
+use rust_decimal::{
+    Decimal, RoundingStrategy,
+    prelude::{FromPrimitive, ToPrimitive},
+};
+use rusty_money::iso::Currency;
+use serde::Deserialize;
+use smallvec::SmallVec;
+
+use crate::discounts::DiscountError;
+
+/// Direction a percentage discount is rounded in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoundingMode {
+    /// Round to the nearest step, halves away from zero
+    #[default]
+    HalfAwayFromZero,
+
+    /// Round to the nearest step, halves to the even step (bankers' rounding)
+    HalfEven,
+
+    /// Always round the discount down, in the customer's disfavour
+    SmallerDiscount,
+
+    /// Always round the discount up, in the customer's favour
+    LargerDiscount,
+}
+
+impl RoundingMode {
+    fn strategy(self) -> RoundingStrategy {
+        match self {
+            Self::HalfAwayFromZero => RoundingStrategy::MidpointAwayFromZero,
+            Self::HalfEven => RoundingStrategy::MidpointNearestEven,
+            Self::SmallerDiscount => RoundingStrategy::ToZero,
+            Self::LargerDiscount => RoundingStrategy::AwayFromZero,
+        }
+    }
+}
+
+/// What a percentage discount is rounded over
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoundingScope {
+    /// Round the discount on every item separately
+    #[default]
+    PerItem,
+
+    /// Round the discount on all of a bundle's percentage-discounted items once,
+    /// then share it between them in proportion to their prices
+    PerBundle,
+}
+
+/// How percentage discounts are rounded to chargeable amounts
+///
+/// The default rounds every item's discount to the nearest minor unit, halves
+/// away from zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RoundingPolicy {
+    mode: RoundingMode,
+    increment: u32,
+    scope: RoundingScope,
+}
+
+impl Default for RoundingPolicy {
+    fn default() -> Self {
+        Self::new(RoundingMode::HalfAwayFromZero)
+    }
+}
+
+impl RoundingPolicy {
+    /// Create a policy rounding to the minor unit in `mode`, per item
+    #[must_use]
+    pub const fn new(mode: RoundingMode) -> Self {
+        Self {
+            mode,
+            increment: 1,
+            scope: RoundingScope::PerItem,
+        }
+    }
+
+    /// Round discounts to multiples of `increment` minor units, such as 5 for cash
+    /// rounding to 5 cents or rappen.
+    ///
+    /// Zero is treated as one, which is no cash rounding.
+    #[must_use]
+    pub const fn with_increment(mut self, increment: u32) -> Self {
+        self.increment = if increment == 0 { 1 } else { increment };
+        self
+    }
+
+    /// Set what discounts are rounded over.
+    #[must_use]
+    pub const fn with_scope(mut self, scope: RoundingScope) -> Self {
+        self.scope = scope;
+        self
+    }
+
+    /// Return the rounding direction
+    #[must_use]
+    pub const fn mode(&self) -> RoundingMode {
+        self.mode
+    }
+
+    /// Return the step discounts are rounded to, in minor units
+    #[must_use]
+    pub const fn increment(&self) -> u32 {
+        self.increment
+    }
+
+    /// Return what discounts are rounded over
+    #[must_use]
+    pub const fn scope(&self) -> RoundingScope {
+        self.scope
+    }
+
+    /// Calculate `percent` of `minor`, rounded to this policy's increment.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DiscountError::PercentConversion`] if the calculation overflows or
+    /// cannot be safely represented.
+    pub fn discount_of(&self, percent: &Percentage, minor: i64) -> Result<i64, DiscountError> {
+        let minor = Decimal::from_i64(minor).ok_or(DiscountError::PercentConversion)?;
+        let increment = Decimal::from(self.increment);
+
+        ((*percent) * Decimal::ONE) // decimal_percentage crate doesn't actually expose the underlying Decimal
+            .checked_mul(minor)
+            .ok_or(DiscountError::PercentConversion)?
+            .checked_div(increment)
+            .ok_or(DiscountError::PercentConversion)?
+            .round_dp_with_strategy(0, self.mode.strategy())
+            .checked_mul(increment)
+            .ok_or(DiscountError::PercentConversion)?
+            .to_i64()
+            .ok_or(DiscountError::PercentConversion)
+    }
+
+    /// Calculate the `percent` discount on each of a bundle's `unit_prices`.
+    ///
+    /// Per item, every unit is rounded on its own. Per bundle, the discount on the
+    /// bundle's total is rounded once, capped at that total, and shared between
+    /// the units in proportion to their prices, with leftover minor units going
+    /// to the units with the largest remainders.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DiscountError::PercentConversion`] if a discount overflows or
+    /// cannot be safely represented.
+    pub fn bundle_discounts(
+        &self,
+        percent: &Percentage,
+        unit_prices: &[i64],
+    ) -> Result<SmallVec<[i64; 10]>, DiscountError> {
+        if self.scope == RoundingScope::PerItem {
+            return unit_prices
+                .iter()
+                .map(|&price| self.discount_of(percent, price))
+                .collect();
+        }
+
+        let total_price = unit_prices
+            .iter()
+            .try_fold(0_i64, |total, &price| total.checked_add(price))
+            .ok_or(DiscountError::PercentConversion)?;
+
+        if total_price <= 0 {
+            return Ok(unit_prices.iter().map(|_price| 0).collect());
+        }
+
+        let discount = self
+            .discount_of(percent, total_price)?
+            .clamp(0, total_price);
+        let (total, price_sum) = (i128::from(discount), i128::from(total_price));
+
+        let mut shares: SmallVec<[i64; 10]> = SmallVec::with_capacity(unit_prices.len());
+        let mut remainders: SmallVec<[(i128, usize); 10]> = SmallVec::new();
+
+        for (idx, &price) in unit_prices.iter().enumerate() {
+            let scaled = total * i128::from(price.max(0));
+
+            shares.push(
+                i64::try_from(scaled / price_sum)
+                    .map_err(|_err| DiscountError::PercentConversion)?,
+            );
+            remainders.push((scaled % price_sum, idx));
+        }
+
+        let mut leftover = discount - shares.iter().sum::<i64>();
+
+        remainders.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+
+        for &(_remainder, idx) in &remainders {
+            if leftover <= 0 {
+                break;
+            }
+
+            if let Some(share) = shares.get_mut(idx) {
+                *share += 1;
+                leftover -= 1;
+            }
+        }
+
+        Ok(shares)
+    }
+
+    /// Take the `percent` discount off the `unit_prices` flagged in `discounted`,
+    /// rounding them together if this policy rounds per bundle.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DiscountError::PercentConversion`] if a discount overflows or
+    /// cannot be safely represented.
+    pub(crate) fn discount_units(
+        self,
+        percent: &Percentage,
+        unit_prices: &mut [i64],
+        discounted: &[bool],
+    ) -> Result<(), DiscountError> {
+        let discounted_prices: SmallVec<[i64; 10]> = unit_prices
+            .iter()
+            .zip(discounted)
+            .filter_map(|(&price, &is_discounted)| is_discounted.then_some(price))
+            .collect();
+
+        let discounts = self.bundle_discounts(percent, &discounted_prices)?;
+
+        let discounted_units = unit_prices
+            .iter_mut()
+            .zip(discounted)
+            .filter_map(|(price, &is_discounted)| is_discounted.then_some(price));
+
+        for (price, discount) in discounted_units.zip(discounts) {
+            *price = price.saturating_sub(discount).max(0);
+        }
+
+        Ok(())
+    }
+}
+
+/// Rounding policies by currency, with a policy for every other currency
+///
+/// Cash rounding in particular is a property of the currency: Swiss francs are
+/// rounded to 5 rappen while pounds are not.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RoundingPolicies {
+    default: RoundingPolicy,
+    currencies: Vec<(&'static str, RoundingPolicy)>,
+}
+
+/// Policies used when neither the promotion nor the graph sets any.
+pub(crate) static DEFAULT_ROUNDING: RoundingPolicies =
+    RoundingPolicies::new(RoundingPolicy::new(RoundingMode::HalfAwayFromZero));
+
+impl RoundingPolicies {
+    /// Create policies applying `default` to every currency
+    #[must_use]
+    pub const fn new(default: RoundingPolicy) -> Self {
+        Self {
+            default,
+            currencies: Vec::new(),
+        }
+    }
+
+    /// Apply `policy` to amounts in `currency`, replacing any it already had.
+    #[must_use]
+    pub fn with_currency(mut self, currency: &Currency, policy: RoundingPolicy) -> Self {
+        let code = currency.iso_alpha_code;
+
+        match self
+            .currencies
+            .iter_mut()
+            .find(|(existing, _policy)| *existing == code)
+        {
+            Some(entry) => entry.1 = policy,
+            None => self.currencies.push((code, policy)),
+        }
+
+        self
+    }
+
+    /// Return the policy for amounts in `currency`
+    #[must_use]
+    pub fn for_currency(&self, currency: &Currency) -> RoundingPolicy {
+        self.currencies
+            .iter()
+            .find(|(code, _policy)| *code == currency.iso_alpha_code)
+            .map_or(self.default, |&(_code, policy)| policy)
+    }
+
+    /// Return the policy for amounts in `currency`, preferring a promotion's `own`
+    /// policies to the `fallback` of the graph or solver evaluating it.
+    pub(crate) fn resolve(
+        own: Option<&Self>,
+        fallback: &Self,
+        currency: &Currency,
+    ) -> RoundingPolicy {
+        own.unwrap_or(fallback).for_currency(currency)
+    }
+
+    /// Return the policy for currencies without their own
+    #[must_use]
+    pub fn default_policy(&self) -> RoundingPolicy {
+        self.default
+    }
+}
+
+impl From<RoundingPolicy> for RoundingPolicies {
+    fn from(policy: RoundingPolicy) -> Self {
+        Self::new(policy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rusty_money::iso::{CHF, GBP};
+    use testresult::TestResult;
+
+    use super::*;
+
+    #[test]
+    fn modes_round_halves_and_fractions_differently() -> TestResult {
+        let pct = Percentage::from(0.5);
+
+        // Half of 25p is 12.5p.
+        let rounded = |mode| RoundingPolicy::new(mode).discount_of(&pct, 25);
+
+        assert_eq!(rounded(RoundingMode::HalfAwayFromZero)?, 13);
+        assert_eq!(rounded(RoundingMode::HalfEven)?, 12);
+        assert_eq!(rounded(RoundingMode::SmallerDiscount)?, 12);
+        assert_eq!(rounded(RoundingMode::LargerDiscount)?, 13);
+
+        // A third of 100p is 33.3p, which only rounding up takes to 34p.
+        let third = Percentage::try_from("0.3333")?;
+
+        assert_eq!(RoundingPolicy::default().discount_of(&third, 100)?, 33);
+        assert_eq!(
+            RoundingPolicy::new(RoundingMode::LargerDiscount).discount_of(&third, 100)?,
+            34
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn increments_round_to_cash_steps() -> TestResult {
+        let pct = Percentage::from(0.1);
+        let cash = |mode| RoundingPolicy::new(mode).with_increment(5);
+
+        // 10% of 1.37 is 0.137, between 0.10 and 0.15.
+        assert_eq!(
+            cash(RoundingMode::HalfAwayFromZero).discount_of(&pct, 137)?,
+            15
+        );
+        assert_eq!(
+            cash(RoundingMode::SmallerDiscount).discount_of(&pct, 137)?,
+            10
+        );
+        assert_eq!(RoundingPolicy::default().with_increment(0).increment(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn bundle_discounts_round_once_per_bundle() -> TestResult {
+        let pct = Percentage::from(0.5);
+        let prices = [25, 25, 25];
+
+        // Each 12.5p rounds up, but the bundle's 37.5p only gains half a penny.
+        assert_eq!(
+            RoundingPolicy::default()
+                .bundle_discounts(&pct, &prices)?
+                .as_slice(),
+            [13, 13, 13]
+        );
+        assert_eq!(
+            RoundingPolicy::default()
+                .with_scope(RoundingScope::PerBundle)
+                .bundle_discounts(&pct, &prices)?
+                .as_slice(),
+            [13, 13, 12]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn policies_are_chosen_by_currency() {
+        let cash = RoundingPolicy::default().with_increment(5);
+        let policies = RoundingPolicies::new(RoundingPolicy::new(RoundingMode::HalfEven))
+            .with_currency(CHF, RoundingPolicy::default())
+            .with_currency(CHF, cash);
+
+        assert_eq!(policies.for_currency(CHF), cash);
+        assert_eq!(
+            policies.for_currency(GBP),
+            RoundingPolicy::new(RoundingMode::HalfEven)
+        );
+    }
+}
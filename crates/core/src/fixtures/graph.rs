@@ -10,13 +10,14 @@ use serde::Deserialize;
 use slotmap::{SecondaryMap, SlotMap};
 
 use crate::{
-    fixtures::{Fixture, FixtureError},
+    fixtures::{Fixture, FixtureError, products::parse_percentage, promotions::RoundingFixture},
     graph::{
-        PromotionGraph,
+        PromotionGraph, StackingLimit,
         builder::PromotionGraphBuilder,
         node::{OutputMode, PromotionLayerKey},
     },
     promotions::{Promotion, PromotionKey},
+    solvers::ilp::ObjectiveMode,
 };
 
 /// Top-level graph fixture from YAML.
@@ -27,6 +28,19 @@ pub struct GraphFixture {
 
     /// Node definitions keyed by label
     pub nodes: FxHashMap<String, GraphNodeFixture>,
+
+    /// What layers optimise: "lowest-total" (default), "least-generous" or
+    /// "least-generous-mandatory"
+    #[serde(default)]
+    pub objective: ObjectiveMode,
+
+    /// Rounding of percentage discounts for promotions without their own
+    #[serde(default)]
+    pub rounding: Option<RoundingFixture>,
+
+    /// What any item may accumulate across the graph's layers
+    #[serde(default)]
+    pub stacking: Option<StackingFixture>,
 }
 
 /// A single node in the graph fixture.
@@ -48,6 +62,45 @@ pub struct GraphNodeFixture {
 
     /// Target node for all items (only used with "pass-through" output, optional for leaf nodes)
     pub next: Option<String>,
+
+    /// What this layer optimises, if not the graph's objective
+    #[serde(default)]
+    pub objective: Option<ObjectiveMode>,
+
+    /// Tighter stacking caps for items entering this layer
+    #[serde(default)]
+    pub stacking: Option<StackingFixture>,
+}
+
+/// Stacking caps in a graph or node fixture.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct StackingFixture {
+    /// Largest total discount off the basket price (e.g., "50%")
+    #[serde(default, alias = "max_discount")]
+    pub max_discount: Option<String>,
+
+    /// Most promotions an item may be redeemed in
+    #[serde(default, alias = "max_redemptions")]
+    pub max_redemptions: Option<u32>,
+}
+
+impl StackingFixture {
+    /// Convert to a [`StackingLimit`]
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FixtureError::InvalidPercentage`] if the largest discount cannot be parsed.
+    pub fn try_into_limit(self) -> Result<StackingLimit, FixtureError> {
+        Ok(StackingLimit {
+            max_discount: self
+                .max_discount
+                .as_deref()
+                .map(parse_percentage)
+                .transpose()?,
+            max_redemptions: self.max_redemptions,
+        })
+    }
 }
 
 impl Fixture<'_> {
@@ -98,7 +151,29 @@ fn build_graph_from_fixture<'a>(
 
     connect_layer_edges(fixture, &node_indices, &mut builder)?;
 
+    builder.set_budget_pools(loaded.budget_pools.clone());
+
+    let rounding = fixture
+        .rounding
+        .clone()
+        .map(RoundingFixture::try_into_rounding)
+        .transpose()?
+        .unwrap_or_default();
+
+    let stacking = fixture
+        .stacking
+        .clone()
+        .map(StackingFixture::try_into_limit)
+        .transpose()?
+        .unwrap_or_default();
+
     PromotionGraph::from_builder(builder)
+        .map(|graph| {
+            graph
+                .with_objective(fixture.objective)
+                .with_rounding(rounding)
+                .with_stacking_limit(stacking)
+        })
         .map_err(|e| FixtureError::InvalidPromotionData(format!("graph validation error: {e}")))
 }
 
@@ -124,6 +199,22 @@ fn create_layer_nodes<'a>(
             .add_layer_with_key(layer_key, promotions, node_fixture.output)
             .map_err(|e| FixtureError::InvalidPromotionData(format!("graph build error: {e}")))?;
 
+        if let Some(objective) = node_fixture.objective {
+            builder
+                .set_layer_objective(node_idx, objective)
+                .map_err(|e| {
+                    FixtureError::InvalidPromotionData(format!("graph build error: {e}"))
+                })?;
+        }
+
+        if let Some(stacking) = node_fixture.stacking.clone() {
+            builder
+                .set_layer_stacking_limit(node_idx, stacking.try_into_limit()?)
+                .map_err(|e| {
+                    FixtureError::InvalidPromotionData(format!("graph build error: {e}"))
+                })?;
+        }
+
         register_layer_name(loaded, &promotion_keys, layer_key, label)?;
 
         node_indices.insert(label.clone(), node_idx);
@@ -300,13 +391,15 @@ fn lookup_target(
 
 #[cfg(test)]
 mod tests {
+    use decimal_percentage::Percentage;
     use rustc_hash::FxHashMap;
     use testresult::TestResult;
 
-    use super::{GraphFixture, GraphNodeFixture, build_graph_from_fixture};
+    use super::{GraphFixture, GraphNodeFixture, StackingFixture, build_graph_from_fixture};
     use crate::{
         fixtures::{Fixture, FixtureError},
-        graph::OutputMode,
+        graph::{OutputMode, StackingLimit},
+        solvers::ilp::ObjectiveMode,
     };
 
     #[test]
@@ -358,6 +451,8 @@ mod tests {
             participating: None,
             non_participating: None,
             next: None,
+            objective: None,
+            stacking: None,
         }
     }
 
@@ -374,6 +469,9 @@ mod tests {
         let fixture = GraphFixture {
             root: "missing-root".to_string(),
             nodes,
+            objective: ObjectiveMode::default(),
+            rounding: None,
+            stacking: None,
         };
 
         let err = build_graph_from_fixture(&fixture, &mut loaded).expect_err("expected root error");
@@ -396,6 +494,9 @@ mod tests {
         let fixture = GraphFixture {
             root: "root".to_string(),
             nodes,
+            objective: ObjectiveMode::default(),
+            rounding: None,
+            stacking: None,
         };
 
         let err =
@@ -416,6 +517,9 @@ mod tests {
         let fixture = GraphFixture {
             root: "root".to_string(),
             nodes,
+            objective: ObjectiveMode::default(),
+            rounding: None,
+            stacking: None,
         };
 
         let err = build_graph_from_fixture(&fixture, &mut loaded)
@@ -441,6 +545,9 @@ mod tests {
         let fixture = GraphFixture {
             root: "root".to_string(),
             nodes,
+            objective: ObjectiveMode::default(),
+            rounding: None,
+            stacking: None,
         };
 
         assert!(build_graph_from_fixture(&fixture, &mut loaded).is_ok());
@@ -456,6 +563,9 @@ mod tests {
         let fixture = GraphFixture {
             root: "root".to_string(),
             nodes,
+            objective: ObjectiveMode::default(),
+            rounding: None,
+            stacking: None,
         };
 
         assert!(build_graph_from_fixture(&fixture, &mut loaded).is_ok());
@@ -466,6 +576,9 @@ mod tests {
         let fixture = GraphFixture {
             root: "root".to_string(),
             nodes,
+            objective: ObjectiveMode::default(),
+            rounding: None,
+            stacking: None,
         };
 
         let err =
@@ -475,4 +588,71 @@ mod tests {
             matches!(err, FixtureError::InvalidPromotionData(message) if message.contains("must have at least one target"))
         );
     }
+
+    #[test]
+    fn graph_fixture_parses_objectives() -> TestResult {
+        let yaml = r"
+root: root
+objective: least-generous
+nodes:
+  root:
+    promotions: [lunch-deal]
+    output: pass-through
+    objective: lowest-total
+";
+
+        let fixture: GraphFixture = serde_norway::from_str(yaml)?;
+
+        assert_eq!(fixture.objective, ObjectiveMode::LeastGenerous);
+        assert_eq!(
+            fixture.nodes.get("root").and_then(|node| node.objective),
+            Some(ObjectiveMode::LowestTotal)
+        );
+
+        let mut loaded = layered_promotions_fixture();
+        let graph = build_graph_from_fixture(&fixture, &mut loaded)?;
+
+        assert_eq!(graph.objective(), ObjectiveMode::LeastGenerous);
+
+        Ok(())
+    }
+
+    #[test]
+    fn graph_fixture_parses_stacking_limits() -> TestResult {
+        let yaml = r#"
+root: root
+stacking:
+  max-discount: "50%"
+nodes:
+  root:
+    promotions: [lunch-deal]
+    output: pass-through
+    stacking:
+      max-redemptions: 1
+"#;
+
+        let fixture: GraphFixture = serde_norway::from_str(yaml)?;
+
+        let mut loaded = layered_promotions_fixture();
+        let graph = build_graph_from_fixture(&fixture, &mut loaded)?;
+
+        assert_eq!(
+            graph.stacking_limit(),
+            StackingLimit::default().with_max_discount(Percentage::from(0.5))
+        );
+
+        let node_limit = fixture
+            .nodes
+            .get("root")
+            .and_then(|node| node.stacking.clone())
+            .map(StackingFixture::try_into_limit)
+            .transpose()?;
+
+        assert_eq!(
+            node_limit,
+            Some(StackingLimit::default().with_max_redemptions(1))
+        );
+
+        Ok(())
+    }
 }
@@ -1,10 +1,116 @@
 //! Item Fixtures
 
+use std::num::NonZeroU32;
+
+use jiff::Timestamp;
+use rustc_hash::FxHashMap;
 use serde::Deserialize;
 
+use crate::{context::EvaluationContext, tags::string::StringTagCollection};
+
 /// Wrapper for items in YAML
 #[derive(Debug, Deserialize)]
 pub struct ItemsFixture {
-    /// Vector of product key references
-    pub items: Vec<String>,
+    /// Vector of item entries
+    pub items: Vec<ItemFixture>,
+
+    /// Optional basket-level evaluation context
+    #[serde(default)]
+    pub context: Option<ContextFixture>,
+}
+
+/// Basket-level evaluation context in YAML
+#[derive(Debug, Default, Deserialize)]
+pub struct ContextFixture {
+    /// Segments the customer belongs to
+    #[serde(default)]
+    pub customer_segments: Vec<String>,
+
+    /// Sales channel
+    #[serde(default)]
+    pub channel: Option<String>,
+
+    /// Store id
+    #[serde(default)]
+    pub store: Option<String>,
+
+    /// Instant the basket is evaluated at
+    #[serde(default)]
+    pub timestamp: Option<Timestamp>,
+
+    /// Arbitrary key/value attributes
+    #[serde(default)]
+    pub attributes: FxHashMap<String, String>,
+
+    /// Coupon codes presented with the basket
+    #[serde(default)]
+    pub codes: Vec<String>,
+}
+
+impl From<ContextFixture> for EvaluationContext {
+    fn from(fixture: ContextFixture) -> Self {
+        let segments: Vec<&str> = fixture
+            .customer_segments
+            .iter()
+            .map(String::as_str)
+            .collect();
+
+        let mut context = EvaluationContext::new()
+            .with_customer_segments(StringTagCollection::from_strs(&segments));
+
+        if let Some(channel) = fixture.channel {
+            context = context.with_channel(channel);
+        }
+
+        if let Some(store) = fixture.store {
+            context = context.with_store_id(store);
+        }
+
+        if let Some(timestamp) = fixture.timestamp {
+            context = context.with_timestamp(timestamp);
+        }
+
+        for (key, value) in fixture.attributes {
+            context = context.with_attribute(key, value);
+        }
+
+        context.with_codes(fixture.codes)
+    }
+}
+
+/// A single item entry in YAML.
+///
+/// Either a bare product key reference (one unit) or a quantity line.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum ItemFixture {
+    /// Product key reference for a single unit
+    Product(String),
+
+    /// Product key reference with a unit count
+    Line {
+        /// Product key reference
+        product: String,
+
+        /// Number of identical units on the line
+        #[serde(default = "default_quantity")]
+        quantity: NonZeroU32,
+
+        /// Unit of measure label (e.g. "can" or "kg")
+        #[serde(default)]
+        unit: Option<String>,
+    },
+}
+
+impl ItemFixture {
+    /// Product key reference for the entry
+    pub fn product(&self) -> &str {
+        match self {
+            Self::Product(product) | Self::Line { product, .. } => product,
+        }
+    }
+}
+
+fn default_quantity() -> NonZeroU32 {
+    NonZeroU32::MIN
 }
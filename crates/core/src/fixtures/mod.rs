@@ -11,15 +11,23 @@ use thiserror::Error;
 
 use crate::{
     basket::Basket,
+    context::EvaluationContext,
     fixtures::{
-        items::ItemsFixture,
+        items::{ItemFixture, ItemsFixture},
         products::{ProductsFixture, parse_price},
-        promotions::PromotionsFixture,
+        promotions::{
+            BudgetPoolNames, ExclusionGroupNames, PromotionReferences, PromotionsFixture,
+            register_budget_pools, register_exclusion_groups,
+        },
     },
     graph::PromotionGraph,
     items::{Item, groups::ItemGroup},
     products::{Product, ProductKey},
-    promotions::{Promotion, PromotionKey, PromotionMeta},
+    promotions::{
+        Promotion, PromotionKey, PromotionMeta,
+        budget::{BudgetPoolKey, BudgetPools},
+        exclusion::ExclusionGroupKey,
+    },
 };
 
 pub mod graph;
@@ -62,6 +70,10 @@ pub enum FixtureError {
     #[error("Promotion not found: {0}")]
     PromotionNotFound(String),
 
+    /// Budget pool not found
+    #[error("Budget pool not found: {0}")]
+    BudgetPoolNotFound(String),
+
     /// Unsupported promotion type
     #[error("Unsupported promotion type: {0}")]
     UnsupportedPromotionType(String),
@@ -98,6 +110,10 @@ pub enum FixtureError {
     /// No graph loaded
     #[error("No graph loaded; call load_graph first or use from_set")]
     NoGraph,
+
+    /// A product's price floor could not be worked out
+    #[error("Invalid price floor: {0}")]
+    PriceFloor(#[from] crate::discounts::DiscountError),
 }
 
 /// Fixture
@@ -120,11 +136,22 @@ pub struct Fixture<'a> {
     /// Pre-built promotions
     promotions: Vec<Promotion<'a>>,
 
+    /// Shared budget pools and their name -> key lookup
+    budget_pools: BudgetPools<'a>,
+    budget_pool_keys: BudgetPoolNames,
+
+    /// Exclusion group names by key, and their name -> key lookup
+    exclusion_groups: SlotMap<ExclusionGroupKey, String>,
+    exclusion_group_keys: ExclusionGroupNames,
+
     /// Parsed promotion graph
     graph: Option<PromotionGraph<'a>>,
 
     /// Currency for the fixture set
     currency: Option<&'static rusty_money::iso::Currency>,
+
+    /// Basket-level evaluation context from the items fixture
+    context: EvaluationContext,
 }
 
 impl<'a> Fixture<'a> {
@@ -144,8 +171,13 @@ impl<'a> Fixture<'a> {
             promotion_keys: FxHashMap::default(),
             items: Vec::new(),
             promotions: Vec::new(),
+            budget_pools: BudgetPools::default(),
+            budget_pool_keys: BudgetPoolNames::default(),
+            exclusion_groups: SlotMap::with_key(),
+            exclusion_group_keys: ExclusionGroupNames::default(),
             graph: None,
             currency: None,
+            context: EvaluationContext::default(),
         }
     }
 
@@ -195,18 +227,36 @@ impl<'a> Fixture<'a> {
         let contents = fs::read_to_string(&file_path)?;
         let fixture: ItemsFixture = serde_norway::from_str(&contents)?;
 
-        for product_key_str in fixture.items {
+        if let Some(context) = fixture.context {
+            self.context = context.into();
+        }
+
+        for entry in fixture.items {
+            let product_key_str = entry.product();
+
             let product_key = self
                 .product_keys
-                .get(&product_key_str)
-                .ok_or_else(|| FixtureError::ProductNotFound(product_key_str.clone()))?;
+                .get(product_key_str)
+                .ok_or_else(|| FixtureError::ProductNotFound(product_key_str.to_string()))?;
 
             let product = self
                 .product_meta
                 .get(*product_key)
-                .ok_or_else(|| FixtureError::ProductNotFound(product_key_str.clone()))?;
+                .ok_or_else(|| FixtureError::ProductNotFound(product_key_str.to_string()))?;
+
+            let mut item = Item::with_tags(*product_key, product.price, product.tags.clone());
+
+            if let Some(floor) = product.floor.minimum_for(&product.price)? {
+                item = item.with_price_floor(floor);
+            }
 
-            let item = Item::with_tags(*product_key, product.price, product.tags.clone());
+            if let ItemFixture::Line { quantity, unit, .. } = entry {
+                item = item.with_quantity(quantity);
+
+                if let Some(unit) = unit {
+                    item = item.with_unit(unit);
+                }
+            }
 
             self.items.push(item);
         }
@@ -228,6 +278,19 @@ impl<'a> Fixture<'a> {
         let contents = fs::read_to_string(&file_path)?;
         let fixture: PromotionsFixture = serde_norway::from_str(&contents)?;
 
+        let pool_keys = register_budget_pools(fixture.budget_pools, &mut self.budget_pools)?;
+
+        self.budget_pool_keys.extend(pool_keys);
+
+        register_exclusion_groups(
+            fixture.promotions.values(),
+            &mut self.exclusion_groups,
+            &mut self.exclusion_group_keys,
+        );
+
+        // Promotions may exclude each other by key, so every key is assigned first.
+        let mut keyed_fixtures = Vec::with_capacity(fixture.promotions.len());
+
         for (key, promotion_fixture) in fixture.promotions {
             let promotion_key = self.promotion_meta.insert(PromotionMeta {
                 name: String::new(),
@@ -235,14 +298,25 @@ impl<'a> Fixture<'a> {
                 layer_names: SecondaryMap::new(),
             });
 
-            let (meta, promotion) = promotion_fixture.try_into_promotion(promotion_key)?;
+            self.promotion_keys.insert(key, promotion_key);
+            keyed_fixtures.push((promotion_key, promotion_fixture));
+        }
+
+        for (promotion_key, promotion_fixture) in keyed_fixtures {
+            let (meta, promotion) = promotion_fixture.try_into_promotion_with_references(
+                promotion_key,
+                PromotionReferences {
+                    budget_pools: &self.budget_pool_keys,
+                    promotions: &self.promotion_keys,
+                    exclusion_groups: &self.exclusion_group_keys,
+                },
+            )?;
 
             if let Some(meta_slot) = self.promotion_meta.get_mut(promotion_key) {
                 *meta_slot = meta;
             }
 
             self.promotions.push(promotion);
-            self.promotion_keys.insert(key, promotion_key);
         }
 
         Ok(self)
@@ -338,6 +412,24 @@ impl<'a> Fixture<'a> {
         &self.promotions
     }
 
+    /// Get the shared budget pools loaded alongside the promotions
+    #[must_use]
+    pub fn budget_pools(&self) -> &BudgetPools<'a> {
+        &self.budget_pools
+    }
+
+    /// Get a shared budget pool key by its name
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no budget pool with that name was loaded.
+    pub fn budget_pool_key(&self, name: &str) -> Result<BudgetPoolKey, FixtureError> {
+        self.budget_pool_keys
+            .get(name)
+            .copied()
+            .ok_or_else(|| FixtureError::BudgetPoolNotFound(name.to_string()))
+    }
+
     /// Get the loaded promotion graph.
     ///
     /// # Errors
@@ -378,7 +470,14 @@ impl<'a> Fixture<'a> {
         Ok(Basket::with_items(items, currency)?)
     }
 
-    /// Create an item group from the loaded items
+    /// Get the evaluation context loaded with the items
+    #[must_use]
+    pub fn context(&self) -> &EvaluationContext {
+        &self.context
+    }
+
+    /// Create an item group from the loaded items, carrying the fixture's
+    /// evaluation context
     ///
     /// # Errors
     ///
@@ -392,7 +491,7 @@ impl<'a> Fixture<'a> {
 
         let items = self.items.iter().cloned().collect();
 
-        Ok(ItemGroup::new(items, currency))
+        Ok(ItemGroup::new(items, currency).with_context(self.context.clone()))
     }
 
     /// Get the currency
@@ -630,6 +729,78 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn fixture_load_items_reads_quantity_lines() -> TestResult {
+        let unique = format!(
+            "lattice-fixtures-{}-{}",
+            process::id(),
+            SystemTime::now().duration_since(UNIX_EPOCH)?.as_nanos()
+        );
+
+        let base_path = env::temp_dir().join(unique);
+
+        write_fixture(
+            &base_path,
+            "products",
+            "cans",
+            "products:\n  cola:\n    name: Cola\n    tags: []\n    price: 0.50 GBP\n",
+        )?;
+
+        write_fixture(
+            &base_path,
+            "items",
+            "cans",
+            "items:\n  - cola\n  - product: cola\n    quantity: 24\n    unit: can\n",
+        )?;
+
+        let mut fixture = Fixture::with_base_path(&base_path);
+
+        fixture.load_products("cans")?.load_items("cans")?;
+
+        let quantities: Vec<(u32, Option<&str>)> = fixture
+            .items()
+            .iter()
+            .map(|item| (item.quantity(), item.unit()))
+            .collect();
+
+        assert_eq!(quantities, vec![(1, None), (24, Some("can"))]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn fixture_load_items_rejects_zero_quantity() -> TestResult {
+        let unique = format!(
+            "lattice-fixtures-{}-{}",
+            process::id(),
+            SystemTime::now().duration_since(UNIX_EPOCH)?.as_nanos()
+        );
+
+        let base_path = env::temp_dir().join(unique);
+
+        write_fixture(
+            &base_path,
+            "products",
+            "cans",
+            "products:\n  cola:\n    name: Cola\n    tags: []\n    price: 0.50 GBP\n",
+        )?;
+
+        write_fixture(
+            &base_path,
+            "items",
+            "cans",
+            "items:\n  - product: cola\n    quantity: 0\n",
+        )?;
+
+        let mut fixture = Fixture::with_base_path(&base_path);
+
+        fixture.load_products("cans")?;
+
+        assert!(fixture.load_items("cans").is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn fixture_product_key_not_found_returns_error() {
         let fixture = Fixture::new();
@@ -5,11 +5,14 @@ use rust_decimal::{Decimal, prelude::ToPrimitive};
 use rustc_hash::FxHashMap;
 use rusty_money::{
     Money,
-    iso::{Currency, EUR, GBP, USD},
+    iso::{CHF, Currency, EUR, GBP, USD},
 };
 use serde::Deserialize;
 
-use crate::{fixtures::FixtureError, products::Product, tags::string::StringTagCollection};
+use crate::{
+    fixtures::FixtureError, pricing::PriceFloor, products::Product,
+    tags::string::StringTagCollection,
+};
 
 /// Wrapper for products in YAML
 #[derive(Debug, Deserialize)]
@@ -29,6 +32,18 @@ pub struct ProductFixture {
 
     /// Product price (e.g., "299 GBP")
     pub price: String,
+
+    /// Unit cost price the product is never sold below (e.g., "1.50 GBP")
+    #[serde(default)]
+    pub cost_price: Option<String>,
+
+    /// Lowest unit price the product may be sold at (e.g., "1.99 GBP")
+    #[serde(default)]
+    pub minimum_price: Option<String>,
+
+    /// Largest discount promotions may give in total (e.g., "30%")
+    #[serde(default)]
+    pub max_discount: Option<String>,
 }
 
 impl TryFrom<ProductFixture> for Product<'_> {
@@ -41,10 +56,31 @@ impl TryFrom<ProductFixture> for Product<'_> {
         let tag_refs: Vec<&str> = fixture.tags.iter().map(String::as_str).collect();
         let tags = StringTagCollection::from_strs(&tag_refs);
 
+        let parse_money = |price: &str| -> Result<Money<'_, Currency>, FixtureError> {
+            let (minor_units, currency) = parse_price(price)?;
+
+            Ok(Money::from_minor(minor_units, currency))
+        };
+
+        let floor = PriceFloor {
+            cost_price: fixture.cost_price.as_deref().map(parse_money).transpose()?,
+            minimum_price: fixture
+                .minimum_price
+                .as_deref()
+                .map(parse_money)
+                .transpose()?,
+            max_discount: fixture
+                .max_discount
+                .as_deref()
+                .map(parse_percentage)
+                .transpose()?,
+        };
+
         Ok(Product {
             name: fixture.name,
             tags,
             price,
+            floor,
         })
     }
 }
@@ -80,14 +116,22 @@ pub fn parse_price(s: &str) -> Result<(i64, &'static Currency), FixtureError> {
         .get(1)
         .ok_or_else(|| FixtureError::InvalidPrice(s.to_string()))?;
 
-    let currency = match *currency_code {
-        "GBP" => GBP,
-        "USD" => USD,
-        "EUR" => EUR,
-        other => return Err(FixtureError::UnknownCurrency(other.to_string())),
-    };
+    Ok((minor_units, parse_currency(currency_code)?))
+}
 
-    Ok((minor_units, currency))
+/// Parse an ISO currency code (e.g., "GBP")
+///
+/// # Errors
+///
+/// Returns [`FixtureError::UnknownCurrency`] for codes the fixtures do not support.
+pub fn parse_currency(code: &str) -> Result<&'static Currency, FixtureError> {
+    match code {
+        "GBP" => Ok(GBP),
+        "USD" => Ok(USD),
+        "EUR" => Ok(EUR),
+        "CHF" => Ok(CHF),
+        other => Err(FixtureError::UnknownCurrency(other.to_string())),
+    }
 }
 
 /// Parse percentage string (e.g., "15%" or "0.15") into a `Percentage`
@@ -203,4 +247,29 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn product_fixture_parses_price_floor() -> Result<(), FixtureError> {
+        let fixture: ProductFixture = serde_norway::from_str(
+            "name: Socks\ntags: [sock]\nprice: 3.00 GBP\ncost_price: 1.50 GBP\nmax_discount: 40%\n",
+        )?;
+
+        let product = Product::try_from(fixture)?;
+
+        assert_eq!(product.floor.cost_price, Some(Money::from_minor(150, GBP)));
+        assert_eq!(product.floor.minimum_price, None);
+        assert_eq!(product.floor.max_discount, Some(Percentage::from(0.4)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn product_fixture_without_floor_is_unlimited() -> Result<(), FixtureError> {
+        let fixture: ProductFixture =
+            serde_norway::from_str("name: Socks\ntags: [sock]\nprice: 3.00 GBP\n")?;
+
+        assert!(Product::try_from(fixture)?.floor.is_unlimited());
+
+        Ok(())
+    }
 }
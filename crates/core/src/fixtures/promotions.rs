@@ -1,5 +1,12 @@
 //! Promotion Fixtures
 
+use std::collections::BTreeMap;
+
+use jiff::{
+    Timestamp,
+    civil::{Time, Weekday},
+    tz::TimeZone,
+};
 use rustc_hash::FxHashMap;
 use rusty_money::Money;
 use serde::Deserialize;
@@ -7,16 +14,25 @@ use slotmap::{SecondaryMap, SlotMap};
 use smallvec::SmallVec;
 
 use crate::{
-    discounts::SimpleDiscount,
+    discounts::{
+        SimpleDiscount,
+        rounding::{RoundingMode, RoundingPolicies, RoundingPolicy, RoundingScope},
+    },
     fixtures::{
         FixtureError,
-        products::{parse_percentage, parse_price},
+        products::{parse_currency, parse_percentage, parse_price},
     },
     promotions::{
         Promotion, PromotionKey, PromotionMeta, PromotionSlotKey,
-        budget::PromotionBudget,
+        budget::{BudgetPoolKey, BudgetPools, PromotionBudget},
+        coupon::{CouponUsage, PromotionCoupon},
+        exclusion::{
+            Exclusion, ExclusionGroupKey, ExclusionScope, ExclusionTarget, PromotionExclusions,
+        },
+        funding::{PromotionFunding, SupplierShare},
         promotion,
         qualification::{BoolOp, Qualification, QualificationRule},
+        schedule::{PromotionSchedule, RecurringWindow},
         types::{
             DirectDiscountPromotion, MixAndMatchDiscount, MixAndMatchPromotion, MixAndMatchSlot,
             PositionalDiscountPromotion, ThresholdDiscount, ThresholdTier, TierThreshold,
@@ -29,10 +45,119 @@ use crate::{
 /// Wrapper for promotions in YAML
 #[derive(Debug, Deserialize)]
 pub struct PromotionsFixture {
+    /// Map of budget pool name -> shared budget limits
+    #[serde(default)]
+    pub budget_pools: FxHashMap<String, BudgetFixture>,
+
     /// Map of promotion key -> promotion fixture
     pub promotions: FxHashMap<String, PromotionFixture>,
 }
 
+/// Budget pool name -> key lookup used to resolve promotion budget references
+pub type BudgetPoolNames = FxHashMap<String, BudgetPoolKey>;
+
+/// A promotion's own budget and the shared pools it draws on
+type ResolvedBudget = (PromotionBudget<'static>, SmallVec<[BudgetPoolKey; 1]>);
+
+/// Add shared budget pools to `pools`, returning their name -> key lookup.
+///
+/// # Errors
+///
+/// Returns an error if a limit cannot be parsed or a pool references other pools.
+pub fn register_budget_pools(
+    fixtures: impl IntoIterator<Item = (String, BudgetFixture)>,
+    pools: &mut BudgetPools<'_>,
+) -> Result<BudgetPoolNames, FixtureError> {
+    let mut names = BudgetPoolNames::default();
+
+    for (name, fixture) in fixtures {
+        if !fixture.pools.is_empty() {
+            return Err(FixtureError::InvalidPromotionData(format!(
+                "budget pool '{name}' cannot draw on other budget pools"
+            )));
+        }
+
+        names.insert(name, pools.insert(fixture.try_into_budget()?));
+    }
+
+    Ok(names)
+}
+
+/// Exclusion group name -> key lookup used to resolve exclusion rules
+pub type ExclusionGroupNames = FxHashMap<String, ExclusionGroupKey>;
+
+/// Names promotion fixtures may refer to, resolved to their keys
+#[derive(Debug, Clone, Copy)]
+pub struct PromotionReferences<'r> {
+    /// Shared budget pools, by name
+    pub budget_pools: &'r BudgetPoolNames,
+
+    /// Promotions, by fixture key
+    pub promotions: &'r FxHashMap<String, PromotionKey>,
+
+    /// Exclusion groups, by name
+    pub exclusion_groups: &'r ExclusionGroupNames,
+}
+
+/// Add the exclusion groups joined by `fixtures` to `groups`, returning their
+/// name -> key lookup.
+///
+/// Groups already in `names` keep their key.
+pub fn register_exclusion_groups<'f>(
+    fixtures: impl IntoIterator<Item = &'f PromotionFixture>,
+    groups: &mut SlotMap<ExclusionGroupKey, String>,
+    names: &mut ExclusionGroupNames,
+) {
+    for name in fixtures
+        .into_iter()
+        .flat_map(|fixture| &fixture.exclusions().groups)
+    {
+        if !names.contains_key(name) {
+            names.insert(name.clone(), groups.insert(name.clone()));
+        }
+    }
+}
+
+/// Exclusions fixture
+///
+/// ```yaml
+/// exclusions:
+///   groups: [vouchers]
+///   not_with:
+///     - group: vouchers
+///       scope: basket
+///     - promotion: staff-discount
+/// ```
+#[derive(Debug, Default, Deserialize)]
+pub struct ExclusionsFixture {
+    /// Names of the exclusion groups the promotion joins
+    #[serde(default)]
+    pub groups: Vec<String>,
+
+    /// Promotions the promotion cannot be combined with
+    #[serde(default)]
+    pub not_with: Vec<ExclusionFixture>,
+}
+
+/// One "cannot be combined with" rule: exactly one of `any_other`, `promotion`
+/// or `group`
+#[derive(Debug, Deserialize)]
+pub struct ExclusionFixture {
+    /// Exclude every other promotion
+    #[serde(default)]
+    pub any_other: bool,
+
+    /// Fixture key of the excluded promotion
+    pub promotion: Option<String>,
+
+    /// Name of the excluded exclusion group
+    pub group: Option<String>,
+
+    /// `item` (default) or `basket`
+    #[serde(default)]
+    pub scope: ExclusionScope,
+}
+
 /// Budget constraint fixture
 #[derive(Debug, Deserialize)]
 pub struct BudgetFixture {
@@ -41,6 +166,10 @@ pub struct BudgetFixture {
 
     /// Maximum monetary discount value (e.g., "10.00 GBP")
     pub monetary: Option<String>,
+
+    /// Names of shared budget pools the promotion also draws on
+    #[serde(default)]
+    pub pools: Vec<String>,
 }
 
 impl BudgetFixture {
@@ -60,6 +189,381 @@ impl BudgetFixture {
     }
 }
 
+/// Schedule fixture
+#[derive(Debug, Deserialize)]
+pub struct ScheduleFixture {
+    /// First instant the promotion is valid (RFC 3339)
+    #[serde(default)]
+    pub valid_from: Option<Timestamp>,
+
+    /// Instant the promotion stops being valid (RFC 3339, exclusive)
+    #[serde(default)]
+    pub valid_until: Option<Timestamp>,
+
+    /// IANA time zone for recurring windows (defaults to UTC)
+    #[serde(default)]
+    pub time_zone: Option<String>,
+
+    /// Recurring windows
+    #[serde(default)]
+    pub windows: Vec<RecurringWindowFixture>,
+}
+
+/// Recurring window fixture
+#[derive(Debug, Deserialize)]
+pub struct RecurringWindowFixture {
+    /// Weekdays (e.g. `mon` or `monday`); empty means every day
+    #[serde(default)]
+    pub days: Vec<String>,
+
+    /// Local opening time (e.g. "16:00")
+    pub starts_at: String,
+
+    /// Local closing time (e.g. "18:00", exclusive)
+    pub ends_at: String,
+}
+
+impl ScheduleFixture {
+    fn try_into_schedule(self) -> Result<PromotionSchedule, FixtureError> {
+        if let (Some(from), Some(until)) = (self.valid_from, self.valid_until)
+            && from >= until
+        {
+            return Err(FixtureError::InvalidPromotionData(format!(
+                "schedule valid_from ({from}) must be before valid_until ({until})"
+            )));
+        }
+
+        let mut schedule = PromotionSchedule::between(self.valid_from, self.valid_until);
+
+        if let Some(name) = self.time_zone {
+            let time_zone = TimeZone::get(&name).map_err(|error| {
+                FixtureError::InvalidPromotionData(format!("unknown time zone {name}: {error}"))
+            })?;
+
+            schedule = schedule.with_time_zone(time_zone);
+        }
+
+        for window in self.windows {
+            let days = window
+                .days
+                .iter()
+                .map(|day| parse_weekday(day))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            schedule = schedule.with_window(RecurringWindow::new(
+                days,
+                parse_time(&window.starts_at)?,
+                parse_time(&window.ends_at)?,
+            ));
+        }
+
+        Ok(schedule)
+    }
+}
+
+fn parse_weekday(day: &str) -> Result<Weekday, FixtureError> {
+    match day.to_ascii_lowercase().as_str() {
+        "mon" | "monday" => Ok(Weekday::Monday),
+        "tue" | "tuesday" => Ok(Weekday::Tuesday),
+        "wed" | "wednesday" => Ok(Weekday::Wednesday),
+        "thu" | "thursday" => Ok(Weekday::Thursday),
+        "fri" | "friday" => Ok(Weekday::Friday),
+        "sat" | "saturday" => Ok(Weekday::Saturday),
+        "sun" | "sunday" => Ok(Weekday::Sunday),
+        _ => Err(FixtureError::InvalidPromotionData(format!(
+            "unknown weekday: {day}"
+        ))),
+    }
+}
+
+fn parse_time(time: &str) -> Result<Time, FixtureError> {
+    time.parse().map_err(|error| {
+        FixtureError::InvalidPromotionData(format!("invalid time of day {time}: {error}"))
+    })
+}
+
+/// Coupon fixture
+#[derive(Debug, Deserialize)]
+pub struct CouponFixture {
+    /// Accepted codes
+    pub codes: Vec<String>,
+
+    /// `single_use` (default) or `multi_use`
+    #[serde(default)]
+    pub usage: CouponUsageFixture,
+}
+
+/// Coupon usage fixture
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CouponUsageFixture {
+    /// One redemption per basket
+    #[default]
+    SingleUse,
+
+    /// Redemptions limited only by the budget
+    MultiUse,
+}
+
+impl CouponFixture {
+    fn try_into_coupon(self) -> Result<PromotionCoupon, FixtureError> {
+        if self.codes.iter().all(|code| code.trim().is_empty()) {
+            return Err(FixtureError::InvalidPromotionData(
+                "coupon must define at least one code".to_string(),
+            ));
+        }
+
+        let usage = match self.usage {
+            CouponUsageFixture::SingleUse => CouponUsage::SingleUse,
+            CouponUsageFixture::MultiUse => CouponUsage::MultiUse,
+        };
+
+        Ok(PromotionCoupon {
+            codes: self.codes.into_iter().collect(),
+            usage,
+        })
+    }
+}
+
+/// Funding fixture: `retailer` (default), `supplier: <name>` or `split: [<share>, ...]`
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FundingFixture {
+    /// The retailer absorbs the discount
+    #[default]
+    Retailer,
+
+    /// The named supplier reimburses the discount
+    Supplier(String),
+
+    /// Suppliers each reimburse part of every redemption, and the retailer absorbs
+    /// the rest
+    Split(Vec<SupplierShareFixture>),
+}
+
+/// One supplier's part in split funding: exactly one of `percentage` or `amount`
+#[derive(Debug, Deserialize)]
+pub struct SupplierShareFixture {
+    /// Supplier name
+    pub supplier: String,
+
+    /// Percentage of each redemption's whole discount (e.g., "50%")
+    pub percentage: Option<String>,
+
+    /// Fixed amount per redemption (e.g., "1.00 GBP")
+    pub amount: Option<String>,
+}
+
+impl FundingFixture {
+    fn try_into_funding(self) -> Result<PromotionFunding, FixtureError> {
+        match self {
+            FundingFixture::Retailer => Ok(PromotionFunding::Retailer),
+            FundingFixture::Supplier(name) => Ok(PromotionFunding::Supplier(name)),
+            FundingFixture::Split(shares) => {
+                let shares = shares
+                    .into_iter()
+                    .map(SupplierShareFixture::try_into_share)
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                PromotionFunding::split(shares)
+                    .map_err(|err| FixtureError::InvalidPromotionData(err.to_string()))
+            }
+        }
+    }
+}
+
+impl SupplierShareFixture {
+    fn try_into_share(self) -> Result<SupplierShare, FixtureError> {
+        match (self.percentage, self.amount) {
+            (Some(percentage), None) => Ok(SupplierShare::percentage(
+                self.supplier,
+                parse_percentage(&percentage)?,
+            )),
+            (None, Some(amount)) => {
+                let (minor, currency) = parse_price(&amount)?;
+
+                Ok(SupplierShare::amount_per_redemption(
+                    self.supplier,
+                    Money::from_minor(minor, currency),
+                ))
+            }
+            _ => Err(FixtureError::InvalidPromotionData(format!(
+                "funding share for supplier '{}' must set exactly one of percentage or amount",
+                self.supplier
+            ))),
+        }
+    }
+}
+
+/// Rounding fixture for percentage discounts, with optional per-currency overrides
+///
+/// ```yaml
+/// rounding:
+///   mode: half_even
+///   scope: per_bundle
+///   currencies:
+///     CHF: { increment: 5 }
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RoundingFixture {
+    /// Rounding direction: `half_away_from_zero` (default), `half_even`,
+    /// `smaller_discount` or `larger_discount`
+    #[serde(default)]
+    pub mode: RoundingMode,
+
+    /// Step discounts are rounded to, in minor units (default 1)
+    #[serde(default)]
+    pub increment: Option<u32>,
+
+    /// What discounts are rounded over: `per_item` (default) or `per_bundle`
+    #[serde(default)]
+    pub scope: RoundingScope,
+
+    /// Policies replacing the one above for items priced in a currency, keyed by
+    /// ISO code
+    #[serde(default)]
+    pub currencies: BTreeMap<String, RoundingPolicyFixture>,
+}
+
+/// Rounding used for one currency in a [`RoundingFixture`]
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RoundingPolicyFixture {
+    /// Rounding direction (default `half_away_from_zero`)
+    #[serde(default)]
+    pub mode: RoundingMode,
+
+    /// Step discounts are rounded to, in minor units (default 1)
+    #[serde(default)]
+    pub increment: Option<u32>,
+
+    /// What discounts are rounded over (default `per_item`)
+    #[serde(default)]
+    pub scope: RoundingScope,
+}
+
+impl RoundingPolicyFixture {
+    fn into_policy(self) -> RoundingPolicy {
+        RoundingPolicy::new(self.mode)
+            .with_increment(self.increment.unwrap_or(1))
+            .with_scope(self.scope)
+    }
+}
+
+impl RoundingFixture {
+    /// Convert to [`RoundingPolicies`]
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FixtureError::UnknownCurrency`] if a currency code is not supported.
+    pub fn try_into_rounding(self) -> Result<RoundingPolicies, FixtureError> {
+        let default = RoundingPolicyFixture {
+            mode: self.mode,
+            increment: self.increment,
+            scope: self.scope,
+        };
+
+        self.currencies.into_iter().try_fold(
+            RoundingPolicies::new(default.into_policy()),
+            |policies, (code, policy)| {
+                Ok(policies.with_currency(parse_currency(&code)?, policy.into_policy()))
+            },
+        )
+    }
+}
+
+fn resolve_budget(
+    budget: Option<BudgetFixture>,
+    budget_pools: &BudgetPoolNames,
+) -> Result<ResolvedBudget, FixtureError> {
+    let Some(budget) = budget else {
+        return Ok((PromotionBudget::unlimited(), SmallVec::new()));
+    };
+
+    let pools = budget
+        .pools
+        .iter()
+        .map(|name| {
+            budget_pools.get(name).copied().ok_or_else(|| {
+                FixtureError::InvalidPromotionData(format!("unknown budget pool '{name}'"))
+            })
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok((budget.try_into_budget()?, pools))
+}
+
+fn resolve_coupon(coupon: Option<CouponFixture>) -> Result<Option<PromotionCoupon>, FixtureError> {
+    coupon.map(CouponFixture::try_into_coupon).transpose()
+}
+
+fn resolve_rounding(
+    rounding: Option<RoundingFixture>,
+) -> Result<Option<RoundingPolicies>, FixtureError> {
+    rounding.map(RoundingFixture::try_into_rounding).transpose()
+}
+
+fn resolve_exclusions(
+    exclusions: ExclusionsFixture,
+    references: PromotionReferences<'_>,
+) -> Result<PromotionExclusions, FixtureError> {
+    let group_key = |name: &str| {
+        references
+            .exclusion_groups
+            .get(name)
+            .copied()
+            .ok_or_else(|| {
+                FixtureError::InvalidPromotionData(format!("unknown exclusion group '{name}'"))
+            })
+    };
+
+    let joined = exclusions
+        .groups
+        .iter()
+        .map(|name| group_key(name))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let rules = exclusions
+        .not_with
+        .into_iter()
+        .map(|rule| {
+            let target = match (rule.any_other, rule.promotion, rule.group) {
+                (true, None, None) => ExclusionTarget::AnyOther,
+                (false, Some(name), None) => ExclusionTarget::Promotion(
+                    references.promotions.get(&name).copied().ok_or_else(|| {
+                        FixtureError::InvalidPromotionData(format!(
+                            "unknown excluded promotion '{name}'"
+                        ))
+                    })?,
+                ),
+                (false, None, Some(name)) => ExclusionTarget::Group(group_key(&name)?),
+                _ => {
+                    return Err(FixtureError::InvalidPromotionData(
+                        "exclusion must set exactly one of any_other, promotion or group"
+                            .to_string(),
+                    ));
+                }
+            };
+
+            Ok(Exclusion {
+                target,
+                scope: rule.scope,
+            })
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(PromotionExclusions {
+        groups: joined,
+        rules,
+    })
+}
+
+fn resolve_schedule(schedule: Option<ScheduleFixture>) -> Result<PromotionSchedule, FixtureError> {
+    schedule
+        .map(ScheduleFixture::try_into_schedule)
+        .transpose()
+        .map(Option::unwrap_or_default)
+}
+
 /// Promotion fixture from YAML
 #[derive(Debug, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
@@ -83,6 +587,34 @@ pub enum PromotionFixture {
         /// Budget constraints (optional)
         #[serde(default)]
         budget: Option<BudgetFixture>,
+
+        /// Validity period and recurring windows (optional)
+        #[serde(default)]
+        schedule: Option<ScheduleFixture>,
+
+        /// Coupon codes required to unlock the promotion (optional)
+        #[serde(default)]
+        coupon: Option<CouponFixture>,
+
+        /// Priority used by tie-break policies; higher wins (default 0)
+        #[serde(default)]
+        priority: i32,
+
+        /// Who funds the discounts (default `retailer`)
+        #[serde(default)]
+        funding: FundingFixture,
+
+        /// Whether the customer is always entitled to the promotion (default false)
+        #[serde(default)]
+        mandatory: bool,
+
+        /// Rounding of percentage discounts, if not the graph's (optional)
+        #[serde(default)]
+        rounding: Option<RoundingFixture>,
+
+        /// Exclusion groups and "cannot be combined with" rules (optional)
+        #[serde(default)]
+        exclusions: ExclusionsFixture,
     },
 
     /// Mix-and-Match Bundle Promotion
@@ -99,6 +631,34 @@ pub enum PromotionFixture {
         /// Budget constraints (optional)
         #[serde(default)]
         budget: Option<BudgetFixture>,
+
+        /// Validity period and recurring windows (optional)
+        #[serde(default)]
+        schedule: Option<ScheduleFixture>,
+
+        /// Coupon codes required to unlock the promotion (optional)
+        #[serde(default)]
+        coupon: Option<CouponFixture>,
+
+        /// Priority used by tie-break policies; higher wins (default 0)
+        #[serde(default)]
+        priority: i32,
+
+        /// Who funds the discounts (default `retailer`)
+        #[serde(default)]
+        funding: FundingFixture,
+
+        /// Whether the customer is always entitled to the promotion (default false)
+        #[serde(default)]
+        mandatory: bool,
+
+        /// Rounding of percentage discounts, if not the graph's (optional)
+        #[serde(default)]
+        rounding: Option<RoundingFixture>,
+
+        /// Exclusion groups and "cannot be combined with" rules (optional)
+        #[serde(default)]
+        exclusions: ExclusionsFixture,
     },
 
     /// Positional Discount Promotion
@@ -126,6 +686,34 @@ pub enum PromotionFixture {
         /// Budget constraints (optional)
         #[serde(default)]
         budget: Option<BudgetFixture>,
+
+        /// Validity period and recurring windows (optional)
+        #[serde(default)]
+        schedule: Option<ScheduleFixture>,
+
+        /// Coupon codes required to unlock the promotion (optional)
+        #[serde(default)]
+        coupon: Option<CouponFixture>,
+
+        /// Priority used by tie-break policies; higher wins (default 0)
+        #[serde(default)]
+        priority: i32,
+
+        /// Who funds the discounts (default `retailer`)
+        #[serde(default)]
+        funding: FundingFixture,
+
+        /// Whether the customer is always entitled to the promotion (default false)
+        #[serde(default)]
+        mandatory: bool,
+
+        /// Rounding of percentage discounts, if not the graph's (optional)
+        #[serde(default)]
+        rounding: Option<RoundingFixture>,
+
+        /// Exclusion groups and "cannot be combined with" rules (optional)
+        #[serde(default)]
+        exclusions: ExclusionsFixture,
     },
 
     /// Tiered Threshold Promotion
@@ -139,10 +727,48 @@ pub enum PromotionFixture {
         /// Budget constraints (optional)
         #[serde(default)]
         budget: Option<BudgetFixture>,
+
+        /// Validity period and recurring windows (optional)
+        #[serde(default)]
+        schedule: Option<ScheduleFixture>,
+
+        /// Coupon codes required to unlock the promotion (optional)
+        #[serde(default)]
+        coupon: Option<CouponFixture>,
+
+        /// Priority used by tie-break policies; higher wins (default 0)
+        #[serde(default)]
+        priority: i32,
+
+        /// Who funds the discounts (default `retailer`)
+        #[serde(default)]
+        funding: FundingFixture,
+
+        /// Whether the customer is always entitled to the promotion (default false)
+        #[serde(default)]
+        mandatory: bool,
+
+        /// Rounding of percentage discounts, if not the graph's (optional)
+        #[serde(default)]
+        rounding: Option<RoundingFixture>,
+
+        /// Exclusion groups and "cannot be combined with" rules (optional)
+        #[serde(default)]
+        exclusions: ExclusionsFixture,
     },
 }
 
 impl PromotionFixture {
+    /// Return the exclusions fixture
+    pub fn exclusions(&self) -> &ExclusionsFixture {
+        match self {
+            Self::DirectDiscount { exclusions, .. }
+            | Self::MixAndMatch { exclusions, .. }
+            | Self::PositionalDiscount { exclusions, .. }
+            | Self::TieredThreshold { exclusions, .. } => exclusions,
+        }
+    }
+
     /// Convert to `PromotionMeta` and `Promotion`
     ///
     /// # Errors
@@ -151,6 +777,43 @@ impl PromotionFixture {
     pub fn try_into_promotion(
         self,
         key: PromotionKey,
+    ) -> Result<(PromotionMeta, Promotion<'static>), FixtureError> {
+        self.try_into_promotion_with_budget_pools(key, &BudgetPoolNames::default())
+    }
+
+    /// Convert to `PromotionMeta` and `Promotion`, resolving shared budget pool names
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the discount configuration is invalid or the budget
+    /// references a pool missing from `budget_pools`.
+    pub fn try_into_promotion_with_budget_pools(
+        self,
+        key: PromotionKey,
+        budget_pools: &BudgetPoolNames,
+    ) -> Result<(PromotionMeta, Promotion<'static>), FixtureError> {
+        self.try_into_promotion_with_references(
+            key,
+            PromotionReferences {
+                budget_pools,
+                promotions: &FxHashMap::default(),
+                exclusion_groups: &ExclusionGroupNames::default(),
+            },
+        )
+    }
+
+    /// Convert to `PromotionMeta` and `Promotion`, resolving the budget pools,
+    /// promotions and exclusion groups it refers to by name
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the discount configuration is invalid, or it refers to a
+    /// budget pool, promotion or exclusion group missing from `references`.
+    #[expect(clippy::too_many_lines, reason = "one arm per promotion type")]
+    pub fn try_into_promotion_with_references(
+        self,
+        key: PromotionKey,
+        references: PromotionReferences<'_>,
     ) -> Result<(PromotionMeta, Promotion<'static>), FixtureError> {
         match self {
             PromotionFixture::DirectDiscount {
@@ -159,6 +822,13 @@ impl PromotionFixture {
                 qualification,
                 discount,
                 budget,
+                schedule,
+                coupon,
+                priority,
+                funding,
+                mandatory,
+                rounding,
+                exclusions,
             } => {
                 let meta = PromotionMeta {
                     name: name.clone(),
@@ -173,26 +843,64 @@ impl PromotionFixture {
                     "direct_discount.qualification",
                 )?;
 
-                let budget = budget
-                    .map(BudgetFixture::try_into_budget)
-                    .transpose()?
-                    .unwrap_or_else(PromotionBudget::unlimited);
+                let (budget, pools) = resolve_budget(budget, references.budget_pools)?;
 
-                let promotion = promotion(DirectDiscountPromotion::new(
+                let mut direct = DirectDiscountPromotion::new(
                     key,
                     qualification,
                     SimpleDiscount::try_from(discount)?,
                     budget,
-                ));
+                )
+                .with_schedule(resolve_schedule(schedule)?);
+
+                if let Some(coupon) = resolve_coupon(coupon)? {
+                    direct = direct.with_coupon(coupon);
+                }
+
+                let mut direct = pools
+                    .into_iter()
+                    .fold(direct, DirectDiscountPromotion::with_budget_pool)
+                    .with_priority(priority)
+                    .with_funding(funding.try_into_funding()?)
+                    .with_mandatory(mandatory)
+                    .with_exclusions(resolve_exclusions(exclusions, references)?);
+
+                if let Some(rounding) = resolve_rounding(rounding)? {
+                    direct = direct.with_rounding(rounding);
+                }
 
-                Ok((meta, promotion))
+                Ok((meta, promotion(direct)))
             }
             PromotionFixture::MixAndMatch {
                 name,
                 slots,
                 discount,
                 budget,
-            } => convert_mix_and_match(key, name, slots, discount, budget),
+                schedule,
+                coupon,
+                priority,
+                funding,
+                mandatory,
+                rounding,
+                exclusions,
+            } => {
+                let budget = resolve_budget(budget, references.budget_pools)?;
+
+                let (meta, mix_and_match) =
+                    convert_mix_and_match(key, name, slots, discount, budget, schedule, coupon)?;
+
+                let mut mix_and_match = mix_and_match
+                    .with_priority(priority)
+                    .with_funding(funding.try_into_funding()?)
+                    .with_mandatory(mandatory)
+                    .with_exclusions(resolve_exclusions(exclusions, references)?);
+
+                if let Some(rounding) = resolve_rounding(rounding)? {
+                    mix_and_match = mix_and_match.with_rounding(rounding);
+                }
+
+                Ok((meta, promotion(mix_and_match)))
+            }
             Self::PositionalDiscount {
                 name,
                 tags,
@@ -201,6 +909,13 @@ impl PromotionFixture {
                 positions,
                 discount,
                 budget,
+                schedule,
+                coupon,
+                priority,
+                funding,
+                mandatory,
+                rounding,
+                exclusions,
             } => {
                 let meta = PromotionMeta {
                     name: name.clone(),
@@ -215,27 +930,65 @@ impl PromotionFixture {
                     "positional_discount.qualification",
                 )?;
 
-                let budget = budget
-                    .map(BudgetFixture::try_into_budget)
-                    .transpose()?
-                    .unwrap_or_else(PromotionBudget::unlimited);
+                let (budget, pools) = resolve_budget(budget, references.budget_pools)?;
 
-                let promotion = promotion(PositionalDiscountPromotion::new(
+                let mut positional = PositionalDiscountPromotion::new(
                     key,
                     qualification,
                     size,
                     positions.into(),
                     SimpleDiscount::try_from(discount)?,
                     budget,
-                ));
+                )
+                .with_schedule(resolve_schedule(schedule)?);
 
-                Ok((meta, promotion))
+                if let Some(coupon) = resolve_coupon(coupon)? {
+                    positional = positional.with_coupon(coupon);
+                }
+
+                let mut positional = pools
+                    .into_iter()
+                    .fold(positional, PositionalDiscountPromotion::with_budget_pool)
+                    .with_priority(priority)
+                    .with_funding(funding.try_into_funding()?)
+                    .with_mandatory(mandatory)
+                    .with_exclusions(resolve_exclusions(exclusions, references)?);
+
+                if let Some(rounding) = resolve_rounding(rounding)? {
+                    positional = positional.with_rounding(rounding);
+                }
+
+                Ok((meta, promotion(positional)))
             }
             Self::TieredThreshold {
                 name,
                 tiers,
                 budget,
-            } => convert_tiered_threshold(key, &name, tiers, budget),
+                schedule,
+                coupon,
+                priority,
+                funding,
+                mandatory,
+                rounding,
+                exclusions,
+            } => {
+                let budget = resolve_budget(budget, references.budget_pools)?;
+
+                let (meta, tiered) =
+                    convert_tiered_threshold(key, &name, tiers, budget, schedule, coupon)?;
+
+                let mut tiered = tiered
+                    .with_priority(priority)
+                    .with_funding(funding.try_into_funding()?)
+                    .with_mandatory(mandatory)
+                    .with_exclusions(resolve_exclusions(exclusions, references)?);
+
+                if let Some(rounding) = resolve_rounding(rounding)? {
+                    tiered = tiered.with_rounding(rounding);
+                }
+
+                Ok((meta, promotion(tiered)))
+            }
         }
     }
 }
@@ -245,8 +998,10 @@ fn convert_mix_and_match(
     name: String,
     slots: Vec<MixAndMatchSlotFixture>,
     discount: MixAndMatchDiscountFixture,
-    budget: Option<BudgetFixture>,
-) -> Result<(PromotionMeta, Promotion<'static>), FixtureError> {
+    (budget, pools): ResolvedBudget,
+    schedule: Option<ScheduleFixture>,
+    coupon: Option<CouponFixture>,
+) -> Result<(PromotionMeta, MixAndMatchPromotion<'static>), FixtureError> {
     let mut slot_names = SecondaryMap::new();
     let mut slot_keys = SlotMap::<PromotionSlotKey, ()>::with_key();
 
@@ -282,38 +1037,39 @@ fn convert_mix_and_match(
         layer_names: SecondaryMap::new(),
     };
 
-    let budget = budget
-        .map(BudgetFixture::try_into_budget)
-        .transpose()?
-        .unwrap_or_else(PromotionBudget::unlimited);
-
-    let promo = promotion(MixAndMatchPromotion::new(
+    let mut mix_and_match = MixAndMatchPromotion::new(
         key,
         slot_defs,
         MixAndMatchDiscount::try_from(discount)?,
         budget,
-    ));
+    )
+    .with_schedule(resolve_schedule(schedule)?);
+
+    if let Some(coupon) = resolve_coupon(coupon)? {
+        mix_and_match = mix_and_match.with_coupon(coupon);
+    }
 
-    Ok((meta, promo))
+    for pool in pools {
+        mix_and_match = mix_and_match.with_budget_pool(pool);
+    }
+
+    Ok((meta, mix_and_match))
 }
 
 fn convert_tiered_threshold(
     key: PromotionKey,
     name: &str,
     tiers: Vec<ThresholdTierFixture>,
-    budget: Option<BudgetFixture>,
-) -> Result<(PromotionMeta, Promotion<'static>), FixtureError> {
+    (budget, pools): ResolvedBudget,
+    schedule: Option<ScheduleFixture>,
+    coupon: Option<CouponFixture>,
+) -> Result<(PromotionMeta, TieredThresholdPromotion<'static>), FixtureError> {
     let meta = PromotionMeta {
         name: name.to_string(),
         slot_names: SecondaryMap::new(),
         layer_names: SecondaryMap::new(),
     };
 
-    let budget = budget
-        .map(BudgetFixture::try_into_budget)
-        .transpose()?
-        .unwrap_or_else(PromotionBudget::unlimited);
-
     let tier_defs: Vec<ThresholdTier<'static>> = tiers
         .into_iter()
         .map(|tier_fixture| {
@@ -365,9 +1121,18 @@ fn convert_tiered_threshold(
         })
         .collect::<Result<Vec<_>, FixtureError>>()?;
 
-    let promo = promotion(TieredThresholdPromotion::new(key, tier_defs, budget));
+    let mut tiered = TieredThresholdPromotion::new(key, tier_defs, budget)
+        .with_schedule(resolve_schedule(schedule)?);
+
+    if let Some(coupon) = resolve_coupon(coupon)? {
+        tiered = tiered.with_coupon(coupon);
+    }
 
-    Ok((meta, promo))
+    for pool in pools {
+        tiered = tiered.with_budget_pool(pool);
+    }
+
+    Ok((meta, tiered))
 }
 
 /// Boolean operation used in fixture qualifications.
@@ -442,6 +1207,47 @@ pub enum QualificationRuleFixture {
         /// Nested group definition.
         group: QualificationFixture,
     },
+    /// Customer must belong to at least one listed segment.
+    CustomerSegment {
+        /// Accepted customer segments.
+        customer_segments: Vec<String>,
+    },
+    /// Basket must be processed through one of the listed channels.
+    Channel {
+        /// Accepted sales channels.
+        channels: Vec<String>,
+    },
+    /// Basket must be processed at one of the listed stores.
+    Store {
+        /// Accepted store ids.
+        stores: Vec<String>,
+    },
+    /// Basket must be evaluated within a time window.
+    TimeWindow {
+        /// Window bounds.
+        time_window: TimeWindowFixture,
+    },
+    /// Context attribute must be set (optionally to one of the listed values).
+    Attribute {
+        /// Attribute key.
+        attribute: String,
+
+        /// Accepted values; empty accepts any value.
+        #[serde(default)]
+        values: Vec<String>,
+    },
+}
+
+/// Time window bounds from YAML fixtures.
+#[derive(Debug, Deserialize)]
+pub struct TimeWindowFixture {
+    /// Inclusive start of the window.
+    #[serde(default)]
+    pub starts_at: Option<Timestamp>,
+
+    /// Exclusive end of the window.
+    #[serde(default)]
+    pub ends_at: Option<Timestamp>,
 }
 
 impl QualificationRuleFixture {
@@ -459,6 +1265,34 @@ impl QualificationRuleFixture {
             Self::Group { group } => Ok(QualificationRule::Group(Box::new(
                 group.try_into_qualification()?,
             ))),
+            Self::CustomerSegment { customer_segments } => Ok(QualificationRule::CustomerSegment {
+                segments: tags_to_collection(&customer_segments),
+            }),
+            Self::Channel { channels } => Ok(QualificationRule::Channel {
+                channels: channels.into_iter().collect(),
+            }),
+            Self::Store { stores } => Ok(QualificationRule::Store {
+                store_ids: stores.into_iter().collect(),
+            }),
+            Self::TimeWindow { time_window } => {
+                if let (Some(starts_at), Some(ends_at)) =
+                    (time_window.starts_at, time_window.ends_at)
+                    && starts_at >= ends_at
+                {
+                    return Err(FixtureError::InvalidPromotionData(format!(
+                        "time window starts_at ({starts_at}) must be before ends_at ({ends_at})"
+                    )));
+                }
+
+                Ok(QualificationRule::TimeWindow {
+                    starts_at: time_window.starts_at,
+                    ends_at: time_window.ends_at,
+                })
+            }
+            Self::Attribute { attribute, values } => Ok(QualificationRule::Attribute {
+                key: attribute,
+                values: values.into_iter().collect(),
+            }),
         }
     }
 }
@@ -833,11 +1667,11 @@ impl TryFrom<SimpleDiscountFixture> for SimpleDiscount<'_> {
 #[cfg(test)]
 mod tests {
     use decimal_percentage::Percentage;
-    use rusty_money::iso::GBP;
+    use rusty_money::iso::{CHF, GBP};
     use slotmap::SlotMap;
     use testresult::TestResult;
 
-    use crate::{discounts::SimpleDiscount, promotions::PromotionKey};
+    use crate::{context::EvaluationContext, discounts::SimpleDiscount, promotions::PromotionKey};
 
     use super::*;
 
@@ -956,6 +1790,13 @@ value: 0.10
                 amount: "0.50 GBP".to_string(),
             },
             budget: None,
+            schedule: None,
+            coupon: None,
+            priority: 0,
+            funding: FundingFixture::Retailer,
+            mandatory: false,
+            rounding: None,
+            exclusions: ExclusionsFixture::default(),
         };
 
         let key = test_promotion_key();
@@ -979,6 +1820,13 @@ value: 0.10
                 amount: "50%".to_string(),
             },
             budget: None,
+            schedule: None,
+            coupon: None,
+            priority: 0,
+            funding: FundingFixture::Retailer,
+            mandatory: false,
+            rounding: None,
+            exclusions: ExclusionsFixture::default(),
         };
 
         let key = test_promotion_key();
@@ -1014,6 +1862,13 @@ value: 0.10
                 amount: "2.50 GBP".to_string(),
             },
             budget: None,
+            schedule: None,
+            coupon: None,
+            priority: 0,
+            funding: FundingFixture::Retailer,
+            mandatory: false,
+            rounding: None,
+            exclusions: ExclusionsFixture::default(),
         };
 
         let key = test_promotion_key();
@@ -1131,6 +1986,7 @@ value: 0.10
         let budget_fixture = BudgetFixture {
             redemptions: Some(5),
             monetary: None,
+            pools: Vec::new(),
         };
 
         let budget = budget_fixture.try_into_budget()?;
@@ -1146,6 +2002,7 @@ value: 0.10
         let budget_fixture = BudgetFixture {
             redemptions: None,
             monetary: Some("2.50 GBP".to_string()),
+            pools: Vec::new(),
         };
 
         let budget = budget_fixture.try_into_budget()?;
@@ -1161,6 +2018,7 @@ value: 0.10
         let budget_fixture = BudgetFixture {
             redemptions: Some(10),
             monetary: Some("5.00 GBP".to_string()),
+            pools: Vec::new(),
         };
 
         let budget = budget_fixture.try_into_budget()?;
@@ -1176,6 +2034,7 @@ value: 0.10
         let budget_fixture = BudgetFixture {
             redemptions: None,
             monetary: None,
+            pools: Vec::new(),
         };
 
         let budget = budget_fixture.try_into_budget()?;
@@ -1198,7 +2057,15 @@ value: 0.10
             budget: Some(BudgetFixture {
                 redemptions: Some(3),
                 monetary: Some("1.00 GBP".to_string()),
+                pools: Vec::new(),
             }),
+            schedule: None,
+            coupon: None,
+            priority: 0,
+            funding: FundingFixture::Retailer,
+            mandatory: false,
+            rounding: None,
+            exclusions: ExclusionsFixture::default(),
         };
 
         let key = test_promotion_key();
@@ -1223,7 +2090,15 @@ value: 0.10
             budget: Some(BudgetFixture {
                 redemptions: Some(5),
                 monetary: None,
+                pools: Vec::new(),
             }),
+            schedule: None,
+            coupon: None,
+            priority: 0,
+            funding: FundingFixture::Retailer,
+            mandatory: false,
+            rounding: None,
+            exclusions: ExclusionsFixture::default(),
         };
 
         let key = test_promotion_key();
@@ -1253,6 +2128,13 @@ value: 0.10
                 },
             }],
             budget: None,
+            schedule: None,
+            coupon: None,
+            priority: 0,
+            funding: FundingFixture::Retailer,
+            mandatory: false,
+            rounding: None,
+            exclusions: ExclusionsFixture::default(),
         };
 
         let key = test_promotion_key();
@@ -1285,7 +2167,15 @@ value: 0.10
             budget: Some(BudgetFixture {
                 redemptions: Some(3),
                 monetary: Some("10.00 GBP".to_string()),
+                pools: Vec::new(),
             }),
+            schedule: None,
+            coupon: None,
+            priority: 0,
+            funding: FundingFixture::Retailer,
+            mandatory: false,
+            rounding: None,
+            exclusions: ExclusionsFixture::default(),
         };
 
         let key = test_promotion_key();
@@ -1528,6 +2418,345 @@ discount:
         assert!(result.is_err());
     }
 
+    #[test]
+    fn qualification_fixture_supports_context_rules_yaml() -> TestResult {
+        let yaml = r"
+op: and
+rules:
+  - customer_segments: [loyalty]
+  - channels: [online]
+  - stores: ['42']
+  - time_window:
+      starts_at: '2026-03-01T00:00:00Z'
+      ends_at: '2026-03-08T00:00:00Z'
+  - attribute: tier
+    values: [gold]
+";
+        let fixture: QualificationFixture = serde_norway::from_str(yaml)?;
+        let qualification = fixture.try_into_qualification()?;
+
+        let context = EvaluationContext::new()
+            .with_customer_segments(StringTagCollection::from_strs(&["loyalty"]))
+            .with_channel("online")
+            .with_store_id("42")
+            .with_timestamp("2026-03-04T12:00:00Z".parse()?)
+            .with_attribute("tier", "gold");
+
+        let tags = StringTagCollection::from_strs(&[]);
+
+        assert!(qualification.matches_in_context(&tags, &context));
+        assert!(!qualification.matches_in_context(&tags, &context.clone().with_channel("store")));
+        assert!(!qualification.matches(&tags));
+
+        Ok(())
+    }
+
+    #[test]
+    fn qualification_fixture_rejects_inverted_time_window() -> TestResult {
+        let yaml = r"
+rules:
+  - time_window:
+      starts_at: '2026-03-08T00:00:00Z'
+      ends_at: '2026-03-01T00:00:00Z'
+";
+        let fixture: QualificationFixture = serde_norway::from_str(yaml)?;
+
+        assert!(fixture.try_into_qualification().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn schedule_fixture_parses_validity_and_windows_yaml() -> TestResult {
+        let yaml = r"
+valid_from: '2026-03-01T00:00:00Z'
+valid_until: '2026-04-01T00:00:00Z'
+time_zone: Europe/London
+windows:
+  - days: [mon, Tuesday, WED, thu, fri]
+    starts_at: '16:00'
+    ends_at: '18:00'
+";
+        let fixture: ScheduleFixture = serde_norway::from_str(yaml)?;
+        let schedule = fixture.try_into_schedule()?;
+
+        assert!(schedule.is_restricted());
+        assert_eq!(schedule.windows.len(), 1);
+
+        // Wednesday 16:30 in London
+        assert!(schedule.is_active_at("2026-03-04T16:30:00Z".parse()?));
+
+        // Saturday 16:30 in London
+        assert!(!schedule.is_active_at("2026-03-07T16:30:00Z".parse()?));
+
+        // Wednesday 16:30 in London, after the validity period
+        assert!(!schedule.is_active_at("2026-04-01T15:30:00Z".parse()?));
+
+        Ok(())
+    }
+
+    #[test]
+    fn coupon_fixture_parses_codes_and_usage() -> TestResult {
+        let single: CouponFixture = serde_norway::from_str("codes: [SAVE10, WELCOME]")?;
+        let multi: CouponFixture = serde_norway::from_str("codes: [STAFF]\nusage: multi_use")?;
+
+        let single = single.try_into_coupon()?;
+        let multi = multi.try_into_coupon()?;
+
+        assert_eq!(single.usage, CouponUsage::SingleUse);
+        assert!(single.accepts("welcome"));
+        assert_eq!(multi.usage, CouponUsage::MultiUse);
+
+        Ok(())
+    }
+
+    #[test]
+    fn coupon_fixture_requires_a_code() -> TestResult {
+        let fixture: CouponFixture = serde_norway::from_str("codes: []")?;
+
+        assert!(matches!(
+            fixture.try_into_coupon(),
+            Err(FixtureError::InvalidPromotionData(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn promotion_fixture_parses_priority_and_funding() -> TestResult {
+        let yaml = r"
+type: direct_discount
+name: Supplier Deal
+tags: [snacks]
+discount:
+  type: percentage_off
+  amount: 10%
+priority: 5
+funding:
+  supplier: Acme Foods
+";
+
+        let fixture: PromotionFixture = serde_norway::from_str(yaml)?;
+        let (_meta, promotion) = fixture.try_into_promotion(PromotionKey::default())?;
+
+        assert_eq!(promotion.priority(), 5);
+        assert_eq!(
+            promotion.funding(),
+            &PromotionFunding::supplier("Acme Foods")
+        );
+
+        let defaulted: PromotionFixture = serde_norway::from_str(
+            "{ type: direct_discount, name: Plain, discount: { type: percentage_off, amount: 10% }, funding: retailer }",
+        )?;
+        let (_meta, defaulted) = defaulted.try_into_promotion(PromotionKey::default())?;
+
+        assert_eq!(defaulted.priority(), 0);
+        assert_eq!(defaulted.funding(), &PromotionFunding::Retailer);
+
+        Ok(())
+    }
+
+    #[test]
+    fn promotion_fixture_parses_split_funding() -> TestResult {
+        let yaml = r"
+type: direct_discount
+name: Shared Deal
+discount:
+  type: percentage_off
+  amount: 20%
+funding:
+  split:
+    - supplier: Acme Foods
+      amount: 0.50 GBP
+    - supplier: Brewers
+      percentage: 50%
+";
+
+        let fixture: PromotionFixture = serde_norway::from_str(yaml)?;
+        let (_meta, promotion) = fixture.try_into_promotion(PromotionKey::default())?;
+
+        assert_eq!(
+            promotion.funding(),
+            &PromotionFunding::split([
+                SupplierShare::amount_per_redemption("Acme Foods", Money::from_minor(50, GBP)),
+                SupplierShare::percentage("Brewers", Percentage::from(0.5)),
+            ])?
+        );
+
+        let ambiguous: PromotionFixture = serde_norway::from_str(
+            "{ type: direct_discount, name: Bad, discount: { type: percentage_off, amount: 10% }, funding: { split: [{ supplier: Acme }] } }",
+        )?;
+
+        assert!(matches!(
+            ambiguous.try_into_promotion(PromotionKey::default()),
+            Err(FixtureError::InvalidPromotionData(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn promotion_fixture_parses_rounding() -> TestResult {
+        let yaml = r"
+type: direct_discount
+name: Cash Deal
+discount:
+  type: percentage_off
+  amount: 15%
+rounding:
+  mode: half_even
+  scope: per_bundle
+  currencies:
+    CHF: { mode: smaller_discount, increment: 5 }
+";
+
+        let fixture: PromotionFixture = serde_norway::from_str(yaml)?;
+        let (_meta, promotion) = fixture.try_into_promotion(PromotionKey::default())?;
+
+        assert_eq!(
+            promotion.rounding(),
+            Some(
+                &RoundingPolicies::new(
+                    RoundingPolicy::new(RoundingMode::HalfEven)
+                        .with_scope(RoundingScope::PerBundle)
+                )
+                .with_currency(
+                    CHF,
+                    RoundingPolicy::new(RoundingMode::SmallerDiscount).with_increment(5)
+                )
+            )
+        );
+
+        let unknown: PromotionFixture = serde_norway::from_str(
+            "{ type: direct_discount, name: Bad, discount: { type: percentage_off, amount: 10% }, rounding: { currencies: { ABC: {} } } }",
+        )?;
+
+        assert!(matches!(
+            unknown.try_into_promotion(PromotionKey::default()),
+            Err(FixtureError::UnknownCurrency(code)) if code == "ABC"
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn promotion_fixture_parses_exclusions() -> TestResult {
+        let yaml = r"
+type: direct_discount
+name: Staff Discount
+discount:
+  type: percentage_off
+  amount: 20%
+exclusions:
+  groups: [staff]
+  not_with:
+    - promotion: clearance
+      scope: basket
+    - group: vouchers
+    - any_other: true
+";
+
+        let mut promotion_keys = SlotMap::<PromotionKey, ()>::with_key();
+        let clearance = promotion_keys.insert(());
+        let promotions = FxHashMap::from_iter([("clearance".to_string(), clearance)]);
+
+        let mut groups = SlotMap::<ExclusionGroupKey, String>::with_key();
+        let mut group_names = ExclusionGroupNames::default();
+        let vouchers = groups.insert("vouchers".to_string());
+
+        group_names.insert("vouchers".to_string(), vouchers);
+
+        let fixture: PromotionFixture = serde_norway::from_str(yaml)?;
+
+        register_exclusion_groups([&fixture], &mut groups, &mut group_names);
+
+        let staff = group_names
+            .get("staff")
+            .copied()
+            .ok_or("staff group missing")?;
+
+        let (_meta, promotion) = fixture.try_into_promotion_with_references(
+            PromotionKey::default(),
+            PromotionReferences {
+                budget_pools: &BudgetPoolNames::default(),
+                promotions: &promotions,
+                exclusion_groups: &group_names,
+            },
+        )?;
+
+        assert_eq!(
+            promotion.exclusions(),
+            &PromotionExclusions::default()
+                .in_group(staff)
+                .with_rule(Exclusion::promotion(clearance, ExclusionScope::Basket))
+                .with_rule(Exclusion::group(vouchers, ExclusionScope::Item))
+                .with_rule(Exclusion::any_other(ExclusionScope::Item))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn promotion_fixture_rejects_invalid_exclusions() -> TestResult {
+        let ambiguous: PromotionFixture = serde_norway::from_str(
+            "{ type: direct_discount, name: Bad, discount: { type: percentage_off, amount: 10% }, exclusions: { not_with: [{ any_other: true, group: vouchers }] } }",
+        )?;
+
+        assert!(matches!(
+            ambiguous.try_into_promotion(PromotionKey::default()),
+            Err(FixtureError::InvalidPromotionData(message)) if message.contains("exactly one of")
+        ));
+
+        let unknown: PromotionFixture = serde_norway::from_str(
+            "{ type: direct_discount, name: Bad, discount: { type: percentage_off, amount: 10% }, exclusions: { not_with: [{ promotion: missing }] } }",
+        )?;
+
+        assert!(matches!(
+            unknown.try_into_promotion(PromotionKey::default()),
+            Err(FixtureError::InvalidPromotionData(message)) if message.contains("'missing'")
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn promotion_fixture_parses_mandatory() -> TestResult {
+        let mandatory: PromotionFixture = serde_norway::from_str(
+            "{ type: direct_discount, name: Statutory, discount: { type: percentage_off, amount: 10% }, mandatory: true }",
+        )?;
+        let (_meta, mandatory) = mandatory.try_into_promotion(PromotionKey::default())?;
+
+        assert!(mandatory.is_mandatory());
+
+        let optional: PromotionFixture = serde_norway::from_str(
+            "{ type: direct_discount, name: Optional, discount: { type: percentage_off, amount: 10% } }",
+        )?;
+        let (_meta, optional) = optional.try_into_promotion(PromotionKey::default())?;
+
+        assert!(!optional.is_mandatory());
+
+        Ok(())
+    }
+
+    #[test]
+    fn schedule_fixture_rejects_invalid_definitions() -> TestResult {
+        for yaml in [
+            "time_zone: Mars/Olympus_Mons",
+            "windows: [{ days: [funday], starts_at: '16:00', ends_at: '18:00' }]",
+            "windows: [{ starts_at: 'teatime', ends_at: '18:00' }]",
+            "{ valid_from: '2026-04-01T00:00:00Z', valid_until: '2026-03-01T00:00:00Z' }",
+        ] {
+            let fixture: ScheduleFixture = serde_norway::from_str(yaml)?;
+
+            assert!(
+                fixture.try_into_schedule().is_err(),
+                "{yaml} should be rejected"
+            );
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn promotion_fixture_supports_mix_and_match_slot_qualification_yaml() -> TestResult {
         let yaml = r"
@@ -1590,4 +2819,87 @@ tiers:
 
         Ok(())
     }
+
+    #[test]
+    fn promotions_fixture_resolves_budget_pools() -> TestResult {
+        let yaml = r"
+budget_pools:
+  launch:
+    redemptions: 3
+    monetary: '5.00 GBP'
+promotions:
+  pooled:
+    type: direct_discount
+    name: Pooled
+    tags: [fruit]
+    discount:
+      type: percentage_off
+      amount: 10%
+    budget:
+      pools: [launch]
+";
+        let fixture: PromotionsFixture = serde_norway::from_str(yaml)?;
+
+        let mut pools = BudgetPools::default();
+        let names = register_budget_pools(fixture.budget_pools, &mut pools)?;
+        let pool_key = names.get("launch").copied().ok_or("missing pool")?;
+
+        assert_eq!(
+            pools.get(pool_key).and_then(|pool| pool.redemption_limit),
+            Some(3)
+        );
+
+        let promotion_fixture = fixture
+            .promotions
+            .into_values()
+            .next()
+            .ok_or("missing promotion")?;
+
+        let (_meta, promotion) =
+            promotion_fixture.try_into_promotion_with_budget_pools(test_promotion_key(), &names)?;
+
+        assert_eq!(promotion.budget_pools(), [pool_key]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn promotion_fixture_rejects_unknown_budget_pool() -> TestResult {
+        let yaml = r"
+type: direct_discount
+name: Pooled
+tags: [fruit]
+discount:
+  type: percentage_off
+  amount: 10%
+budget:
+  pools: [missing]
+";
+        let fixture: PromotionFixture = serde_norway::from_str(yaml)?;
+
+        let result = fixture.try_into_promotion(test_promotion_key());
+
+        assert!(matches!(
+            result,
+            Err(FixtureError::InvalidPromotionData(message)) if message.contains("missing")
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn budget_pool_cannot_draw_on_other_pools() -> TestResult {
+        let yaml = r"
+outer:
+  redemptions: 1
+  pools: [inner]
+";
+        let fixtures: FxHashMap<String, BudgetFixture> = serde_norway::from_str(yaml)?;
+
+        let result = register_budget_pools(fixtures, &mut BudgetPools::default());
+
+        assert!(matches!(result, Err(FixtureError::InvalidPromotionData(_))));
+
+        Ok(())
+    }
 }
@@ -8,7 +8,7 @@ use petgraph::{
     stable_graph::StableDiGraph,
     visit::Dfs,
 };
-use rustc_hash::FxHashSet;
+use rustc_hash::{FxHashMap, FxHashSet};
 use slotmap::SlotMap;
 use smallvec::SmallVec;
 
@@ -17,10 +17,19 @@ use crate::{
         edge::LayerEdge,
         error::GraphError,
         node::{LayerNode, OutputMode, PromotionLayerKey},
+        stacking::StackingLimit,
     },
-    promotions::Promotion,
+    promotions::{Promotion, PromotionKey, budget::BudgetPools, coupon::CouponUsage},
+    solvers::ilp::ObjectiveMode,
 };
 
+/// A validated graph, its root node and the shared budget pools its promotions draw on.
+pub(crate) type BuiltGraph<'a> = (
+    StableDiGraph<LayerNode<'a>, LayerEdge>,
+    NodeIndex,
+    BudgetPools<'a>,
+);
+
 /// Builder for constructing a validated [`super::PromotionGraph`].
 ///
 /// Ensures the graph satisfies all structural invariants before producing
@@ -30,6 +39,7 @@ pub struct PromotionGraphBuilder<'a> {
     graph: StableDiGraph<LayerNode<'a>, LayerEdge>,
     root: Option<NodeIndex>,
     layer_keys: SlotMap<PromotionLayerKey, ()>,
+    budget_pools: BudgetPools<'a>,
 }
 
 impl<'a> PromotionGraphBuilder<'a> {
@@ -40,6 +50,7 @@ impl<'a> PromotionGraphBuilder<'a> {
             graph: StableDiGraph::new(),
             root: None,
             layer_keys: SlotMap::with_key(),
+            budget_pools: BudgetPools::default(),
         }
     }
 
@@ -81,16 +92,74 @@ impl<'a> PromotionGraphBuilder<'a> {
             key,
             promotions,
             output_mode,
+            objective: None,
+            stacking: StackingLimit::default(),
         };
 
         Ok(self.graph.add_node(node))
     }
 
+    /// Make a layer optimise `objective` instead of the graph's objective.
+    ///
+    /// Only greedy evaluation solves layers separately; joint evaluation requires
+    /// every layer to optimise the same objective.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `node` is not a layer of the graph.
+    pub fn set_layer_objective(
+        &mut self,
+        node: NodeIndex,
+        objective: ObjectiveMode,
+    ) -> Result<(), GraphError> {
+        let layer = self
+            .graph
+            .node_weight_mut(node)
+            .ok_or(GraphError::UnknownNode(node.index()))?;
+
+        layer.objective = Some(objective);
+
+        Ok(())
+    }
+
+    /// Cap what items entering a layer may accumulate, on top of the graph's
+    /// own [stacking limit](super::PromotionGraph::with_stacking_limit).
+    ///
+    /// The tighter of the two caps applies, counting discounts and redemptions
+    /// from every layer up to and including this one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `node` is not a layer of the graph.
+    pub fn set_layer_stacking_limit(
+        &mut self,
+        node: NodeIndex,
+        stacking: StackingLimit,
+    ) -> Result<(), GraphError> {
+        let layer = self
+            .graph
+            .node_weight_mut(node)
+            .ok_or(GraphError::UnknownNode(node.index()))?;
+
+        layer.stacking = stacking;
+
+        Ok(())
+    }
+
     /// Set the root node of the graph (evaluation starts here).
     pub fn set_root(&mut self, node: NodeIndex) {
         self.root = Some(node);
     }
 
+    /// Set the shared budget pools that promotions in the graph draw on.
+    ///
+    /// Each pool's limits are the balance available to a single evaluation; pools
+    /// are shared by every layer, so a promotion in a later layer can only spend
+    /// what earlier layers left.
+    pub fn set_budget_pools(&mut self, pools: BudgetPools<'a>) {
+        self.budget_pools = pools;
+    }
+
     /// Connect a `PassThrough` node to its single successor via an `All` edge.
     ///
     /// # Errors
@@ -195,13 +264,13 @@ impl<'a> PromotionGraphBuilder<'a> {
     /// 4. `PassThrough` nodes must have 0 or 1 outgoing `All` edges
     /// 5. `Split` nodes must have 1 or 2 edges: at least one of `Participating` or `NonParticipating`
     /// 6. No promotion key appears more than once in any single root-to-leaf path
+    /// 7. No single-use coupon code unlocks more than one promotion
+    /// 8. Every budget pool a promotion draws on has been set on the builder
     ///
     /// # Errors
     ///
     /// Returns a [`GraphError`] if any validation rule is violated.
-    pub(crate) fn build(
-        self,
-    ) -> Result<(StableDiGraph<LayerNode<'a>, LayerEdge>, NodeIndex), GraphError> {
+    pub(crate) fn build(self) -> Result<BuiltGraph<'a>, GraphError> {
         // 1. Root must be set
         let root = self.root.ok_or(GraphError::NoRoot)?;
 
@@ -269,10 +338,75 @@ impl<'a> PromotionGraphBuilder<'a> {
         // 6. Per-path promotion uniqueness
         validate_path_promotion_uniqueness(&self.graph, root)?;
 
-        Ok((self.graph, root))
+        // 7. Single-use coupon codes unlock at most one promotion
+        validate_single_use_codes(&self.graph)?;
+
+        // 8. Budget pools drawn on by promotions exist
+        validate_budget_pools(&self.graph, &self.budget_pools)?;
+
+        Ok((self.graph, root, self.budget_pools))
     }
 }
 
+/// Validate that every budget pool referenced by a promotion has been set.
+fn validate_budget_pools(
+    graph: &StableDiGraph<LayerNode<'_>, LayerEdge>,
+    pools: &BudgetPools<'_>,
+) -> Result<(), GraphError> {
+    for promotion in graph.node_weights().flat_map(|node| node.promotions.iter()) {
+        let missing = promotion
+            .budget_pools()
+            .iter()
+            .copied()
+            .find(|&pool_key| !pools.contains_key(pool_key));
+
+        if let Some(pool_key) = missing {
+            return Err(GraphError::UnknownBudgetPool {
+                promotion_key: promotion.key(),
+                pool_key,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Validate that no single-use coupon code is accepted by two different promotions.
+///
+/// The same promotion may sit on several paths, so codes are compared across
+/// promotion keys rather than occurrences.
+fn validate_single_use_codes(
+    graph: &StableDiGraph<LayerNode<'_>, LayerEdge>,
+) -> Result<(), GraphError> {
+    let mut owners: FxHashMap<String, PromotionKey> = FxHashMap::default();
+
+    for promotion in graph.node_weights().flat_map(|node| node.promotions.iter()) {
+        let Some(coupon) = promotion.coupon() else {
+            continue;
+        };
+
+        if coupon.usage != CouponUsage::SingleUse {
+            continue;
+        }
+
+        for code in &coupon.codes {
+            let first = *owners
+                .entry(code.to_ascii_lowercase())
+                .or_insert_with(|| promotion.key());
+
+            if first != promotion.key() {
+                return Err(GraphError::SharedSingleUseCode {
+                    code: code.clone(),
+                    first,
+                    second: promotion.key(),
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
 /// Validate that no promotion key appears more than once in any single path.
 fn validate_path_promotion_uniqueness(
     graph: &StableDiGraph<LayerNode<'_>, LayerEdge>,
@@ -370,7 +504,7 @@ mod tests {
     use crate::{
         discounts::SimpleDiscount,
         promotions::{
-            Promotion, PromotionKey, budget::PromotionBudget, promotion,
+            Promotion, PromotionKey, budget::PromotionBudget, coupon::PromotionCoupon, promotion,
             qualification::Qualification, types::DirectDiscountPromotion,
         },
         tags::string::StringTagCollection,
@@ -830,12 +964,16 @@ mod tests {
             key: PromotionLayerKey::default(),
             promotions: SmallVec::new(),
             output_mode: OutputMode::PassThrough,
+            objective: None,
+            stacking: StackingLimit::default(),
         });
 
         let removed = graph.add_node(LayerNode {
             key: PromotionLayerKey::default(),
             promotions: SmallVec::new(),
             output_mode: OutputMode::PassThrough,
+            objective: None,
+            stacking: StackingLimit::default(),
         });
 
         let removed_idx = removed;
@@ -867,4 +1005,78 @@ mod tests {
         assert!(a.build().is_ok());
         assert!(b.build().is_ok());
     }
+
+    fn coupon_promotion(key: PromotionKey, coupon: PromotionCoupon) -> Promotion<'static> {
+        promotion(
+            DirectDiscountPromotion::new(
+                key,
+                Qualification::match_any(StringTagCollection::from_strs(&["a"])),
+                SimpleDiscount::AmountOverride(Money::from_minor(50, GBP)),
+                PromotionBudget::unlimited(),
+            )
+            .with_coupon(coupon),
+        )
+    }
+
+    #[test]
+    fn build_rejects_single_use_code_shared_across_layers() {
+        let mut builder = PromotionGraphBuilder::new();
+        let mut keys = slotmap::SlotMap::<PromotionKey, ()>::with_key();
+        let first = keys.insert(());
+        let second = keys.insert(());
+
+        let root = builder
+            .add_layer(
+                "Root",
+                [coupon_promotion(
+                    first,
+                    PromotionCoupon::single_use(["SAVE10"]),
+                )],
+                OutputMode::PassThrough,
+            )
+            .expect("layer");
+
+        let next = builder
+            .add_layer(
+                "Next",
+                [coupon_promotion(
+                    second,
+                    PromotionCoupon::single_use(["save10"]),
+                )],
+                OutputMode::PassThrough,
+            )
+            .expect("layer");
+
+        builder.set_root(root);
+        builder.connect_pass_through(root, next).expect("edge");
+
+        assert!(
+            matches!(
+                builder.build(),
+                Err(GraphError::SharedSingleUseCode { ref code, .. }) if code == "save10"
+            ),
+            "a single-use code may only unlock one promotion"
+        );
+    }
+
+    #[test]
+    fn build_allows_shared_multi_use_code() {
+        let mut builder = PromotionGraphBuilder::new();
+        let mut keys = slotmap::SlotMap::<PromotionKey, ()>::with_key();
+
+        let root = builder
+            .add_layer(
+                "Root",
+                [
+                    coupon_promotion(keys.insert(()), PromotionCoupon::multi_use(["STAFF"])),
+                    coupon_promotion(keys.insert(()), PromotionCoupon::multi_use(["STAFF"])),
+                ],
+                OutputMode::PassThrough,
+            )
+            .expect("layer");
+
+        builder.set_root(root);
+
+        assert!(builder.build().is_ok(), "multi-use codes may be shared");
+    }
 }
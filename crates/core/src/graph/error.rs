@@ -4,7 +4,10 @@ use rusty_money::MoneyError;
 use thiserror::Error;
 
 use crate::{
-    graph::PromotionLayerKey, items::groups::ItemGroupError, promotions::PromotionKey,
+    discounts::DiscountError,
+    graph::PromotionLayerKey,
+    items::groups::ItemGroupError,
+    promotions::{PromotionKey, budget::BudgetPoolKey},
     solvers::SolverError,
 };
 
@@ -43,10 +46,65 @@ pub enum GraphError {
     )]
     SplitSuccessorMismatch,
 
+    /// A node index does not refer to a layer of the graph.
+    #[error("node {0} is not a layer of the graph")]
+    UnknownNode(usize),
+
+    /// Layers optimise different objectives, which a joint solve cannot combine.
+    #[error("layers {first:?} and {second:?} optimise different objectives in a joint solve")]
+    JointObjectiveMismatch {
+        /// Key of the first layer
+        first: PromotionLayerKey,
+
+        /// Key of a layer optimising a different objective
+        second: PromotionLayerKey,
+    },
+
     /// A node in the graph is not reachable from the root.
     #[error("graph contains unreachable nodes")]
     UnreachableNode,
 
+    /// A single-use coupon code unlocks more than one promotion.
+    #[error("single-use coupon code {code:?} is shared by promotions {first:?} and {second:?}")]
+    SharedSingleUseCode {
+        /// The shared code
+        code: String,
+
+        /// Key of the first promotion accepting the code
+        first: PromotionKey,
+
+        /// Key of the second promotion accepting the code
+        second: PromotionKey,
+    },
+
+    /// A promotion draws on a budget pool that was not set on the graph.
+    #[error("promotion {promotion_key:?} draws on unknown budget pool {pool_key:?}")]
+    UnknownBudgetPool {
+        /// Key of the promotion referencing the pool
+        promotion_key: PromotionKey,
+
+        /// Key of the missing pool
+        pool_key: BudgetPoolKey,
+    },
+
+    /// A promotion redeemed in a sale is missing from the graph its return is
+    /// frozen to.
+    #[error("promotion {0:?} was redeemed in the sale but is not in the frozen graph")]
+    FrozenPromotionMissing(PromotionKey),
+
+    /// More units of a line are returned than the line holds.
+    #[error("{returned} units of item {item_idx} returned, but the line holds {quantity}")]
+    ReturnExceedsQuantity {
+        /// Index of the line in the original basket
+        item_idx: usize,
+
+        /// Units returned
+        returned: u32,
+
+        /// Units on the line
+        quantity: u32,
+    },
+
     /// The ILP solver returned an error while evaluating a layer.
     #[error("solver error in layer {layer_key:?}: {source}")]
     Solver {
@@ -57,6 +115,23 @@ pub enum GraphError {
         source: SolverError,
     },
 
+    /// A promotion in a non-leaf layer cannot describe per-item final prices, so
+    /// its results cannot be carried into later layers of a joint solve.
+    #[error(
+        "promotion {promotion_key:?} in layer {layer_key:?} cannot be jointly optimised with later layers"
+    )]
+    JointPriceOutcomesUnavailable {
+        /// Key of the layer containing the promotion
+        layer_key: PromotionLayerKey,
+
+        /// Key of the promotion that cannot describe its per-item prices
+        promotion_key: PromotionKey,
+    },
+
+    /// The ILP solver returned an error while solving the joint multi-layer model.
+    #[error("joint solver error: {0}")]
+    JointSolver(#[source] SolverError),
+
     /// Error constructing an item group for a layer.
     #[error(transparent)]
     ItemGroup(#[from] ItemGroupError),
@@ -64,4 +139,8 @@ pub enum GraphError {
     /// Money arithmetic error during evaluation.
     #[error(transparent)]
     Money(#[from] MoneyError),
+
+    /// A stacking limit's largest discount could not be applied to an item.
+    #[error(transparent)]
+    Discount(#[from] DiscountError),
 }
@@ -0,0 +1,264 @@
+//! Basket estimates
+//!
+//! How adding a candidate item would change a basket's total, for every
+//! candidate in a catalogue.
+
+use rustc_hash::{FxHashMap, FxHashSet};
+use rusty_money::{Money, iso::Currency};
+use smallvec::SmallVec;
+
+use crate::{
+    graph::{PromotionGraph, error::GraphError},
+    items::{Item, groups::ItemGroup},
+    promotions::Promotion,
+    solvers::ilp::{ItemSignature, cache::SolveCache},
+};
+
+/// The effect of adding one candidate item to a basket
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AdditionEstimate<'a> {
+    /// Basket total with the candidate added
+    pub total: Money<'a, Currency>,
+
+    /// How much the basket total increases by
+    pub marginal: Money<'a, Currency>,
+
+    /// The candidate's line price less its marginal cost
+    pub savings: Money<'a, Currency>,
+}
+
+/// Candidates with equal classes always produce the same basket total.
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct CandidateClass {
+    price: i64,
+    price_floor: Option<i64>,
+    quantity: u32,
+    view: CandidateView,
+}
+
+/// How the graph's promotions see a candidate.
+#[derive(Debug, PartialEq, Eq, Hash)]
+enum CandidateView {
+    /// One signature per promotion, in graph order
+    Signatures(SmallVec<[ItemSignature; 8]>),
+
+    /// Some promotion gave no signature, so fall back to the tags themselves
+    Tags(SmallVec<[String; 5]>),
+}
+
+impl PromotionGraph<'_> {
+    /// Estimate how adding each candidate would change the basket total.
+    ///
+    /// Returns one estimate per candidate, in order. The current basket is
+    /// solved once. A candidate that no promotion qualifies is priced from that
+    /// solution without solving again, and candidates the promotions cannot tell
+    /// apart (same price, price floor, quantity and qualification matches) share one
+    /// evaluation, so the number of evaluations grows with the distinct kinds of
+    /// candidate rather than the size of the catalogue.
+    ///
+    /// Each of those evaluations only solves the sub-baskets the candidate joins
+    /// (see the README's "Decomposition" section): every other sub-basket takes
+    /// its solution from the current basket's, and which promotions the basket's
+    /// items qualify for is only worked out once. Sub-baskets whose solution a
+    /// [`BasketSession`](crate::graph::BasketSession) cannot reuse are solved again
+    /// for each kind of candidate, set up from the current basket's formulation if
+    /// the candidate does not join them, and every
+    /// [`Joint`](crate::graph::EvaluationMode::Joint) evaluation is solved in full.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`GraphError`] if any solve fails.
+    pub fn estimate_additions<'b>(
+        &self,
+        item_group: &ItemGroup<'b>,
+        candidates: &[Item<'b>],
+    ) -> Result<Vec<AdditionEstimate<'b>>, GraphError> {
+        self.estimate_additions_cached(item_group, candidates, &mut SolveCache::default())
+    }
+
+    /// Estimate additions, sharing `cache` between the current basket's evaluation
+    /// and each candidate's.
+    fn estimate_additions_cached<'b>(
+        &self,
+        item_group: &ItemGroup<'b>,
+        candidates: &[Item<'b>],
+        cache: &mut SolveCache<'b>,
+    ) -> Result<Vec<AdditionEstimate<'b>>, GraphError> {
+        let base = self
+            .evaluate_internal(item_group, None, Some(&mut *cache), None)?
+            .total;
+        let promotions = self.unique_promotions();
+
+        let mut totals: FxHashMap<CandidateClass, Money<'b, Currency>> = FxHashMap::default();
+        let mut estimates = Vec::with_capacity(candidates.len());
+
+        for candidate in candidates {
+            let line_price = candidate.line_price()?;
+            let view = candidate_view(&promotions, candidate, item_group, cache);
+
+            let unaffected = matches!(
+                &view,
+                CandidateView::Signatures(signatures)
+                    if signatures.iter().flatten().all(|matched| !matched)
+            );
+
+            let total = if unaffected {
+                base.add(line_price)?
+            } else {
+                let class = CandidateClass {
+                    price: candidate.price().to_minor_units(),
+                    price_floor: candidate.price_floor().map(Money::to_minor_units),
+                    quantity: candidate.quantity(),
+                    view,
+                };
+
+                if let Some(total) = totals.get(&class) {
+                    *total
+                } else {
+                    let total = self
+                        .evaluate_internal(
+                            &with_candidate(item_group, candidate),
+                            None,
+                            Some(&mut *cache),
+                            None,
+                        )?
+                        .total;
+
+                    totals.insert(class, total);
+
+                    total
+                }
+            };
+
+            let marginal = total.sub(base)?;
+
+            estimates.push(AdditionEstimate {
+                total,
+                marginal,
+                savings: line_price.sub(marginal)?,
+            });
+        }
+
+        Ok(estimates)
+    }
+
+    /// Every distinct promotion in the graph, in layer order.
+    pub(super) fn unique_promotions(&self) -> SmallVec<[&Promotion<'_>; 8]> {
+        let mut seen = FxHashSet::default();
+
+        self.graph
+            .node_weights()
+            .flat_map(|node| node.promotions.iter())
+            .filter(|promotion| seen.insert(promotion.key()))
+            .collect()
+    }
+}
+
+fn candidate_view<'b>(
+    promotions: &[&Promotion<'_>],
+    candidate: &Item<'b>,
+    item_group: &ItemGroup<'b>,
+    cache: &mut SolveCache<'b>,
+) -> CandidateView {
+    promotions
+        .iter()
+        .map(|promotion| cache.item_signature(promotion.as_ref(), candidate, item_group.context()))
+        .collect::<Option<_>>()
+        .map_or_else(
+            || CandidateView::Tags(candidate.tags().to_strs()),
+            CandidateView::Signatures,
+        )
+}
+
+fn with_candidate<'b>(item_group: &ItemGroup<'b>, candidate: &Item<'b>) -> ItemGroup<'b> {
+    let items = item_group
+        .iter()
+        .cloned()
+        .chain([candidate.clone()])
+        .collect();
+
+    ItemGroup::new(items, item_group.currency()).with_context(item_group.context().clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use decimal_percentage::Percentage;
+    use rusty_money::{Money, iso::GBP};
+    use slotmap::SlotMap;
+    use testresult::TestResult;
+
+    use crate::{
+        discounts::SimpleDiscount,
+        products::ProductKey,
+        promotions::{
+            PromotionKey, budget::PromotionBudget, promotion, qualification::Qualification,
+            types::DirectDiscountPromotion,
+        },
+        solvers::ilp::CacheStats,
+        tags::string::StringTagCollection,
+    };
+
+    use super::*;
+
+    fn percent_off(key: PromotionKey, tag: &str, pct: f64) -> Promotion<'static> {
+        promotion(DirectDiscountPromotion::new(
+            key,
+            Qualification::match_any(StringTagCollection::from_strs(&[tag])),
+            SimpleDiscount::PercentageOff(Percentage::from(pct)),
+            PromotionBudget::unlimited(),
+        ))
+    }
+
+    fn tagged(price: i64, tag: &str) -> Item<'static> {
+        Item::with_tags(
+            ProductKey::default(),
+            Money::from_minor(price, GBP),
+            StringTagCollection::from_strs(&[tag]),
+        )
+    }
+
+    #[test]
+    fn candidates_only_solve_the_sub_baskets_they_join() -> TestResult {
+        let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+
+        let graph = PromotionGraph::single_layer([
+            percent_off(keys.insert(()), "food", 0.20),
+            percent_off(keys.insert(()), "drink", 0.50),
+        ])?;
+
+        let item_group = ItemGroup::new(
+            [tagged(1000, "food"), tagged(400, "drink")]
+                .into_iter()
+                .collect(),
+            GBP,
+        );
+
+        let mut cache = SolveCache::default();
+
+        let estimates = graph.estimate_additions_cached(
+            &item_group,
+            &[tagged(200, "drink"), tagged(500, "food")],
+            &mut cache,
+        )?;
+
+        assert_eq!(
+            estimates
+                .iter()
+                .map(|estimate| estimate.total.to_minor_units())
+                .collect::<Vec<_>>(),
+            vec![1100, 1400]
+        );
+
+        // The basket solves both sub-baskets; each candidate re-solves only its own.
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                solved: 4,
+                reused: 2,
+                ..CacheStats::default()
+            }
+        );
+
+        Ok(())
+    }
+}
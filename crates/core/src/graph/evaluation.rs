@@ -1,22 +1,34 @@
 //! DFS graph evaluation engine.
 
+use std::num::NonZeroU32;
+
 use petgraph::graph::NodeIndex;
 use petgraph::stable_graph::StableDiGraph;
 use petgraph::visit::EdgeRef;
+use rustc_hash::FxHashSet;
 use rusty_money::{Money, iso::Currency};
 use smallvec::SmallVec;
 
 use crate::{
+    context::EvaluationContext,
     graph::{
         edge::LayerEdge,
         error::GraphError,
         node::{LayerNode, OutputMode},
+        stacking::StackingLimit,
     },
     items::{Item, groups::ItemGroup},
-    promotions::redemptions::PromotionRedemption,
+    promotions::{
+        Promotion, PromotionKey,
+        budget::BudgetPools,
+        exclusion::{ExclusionRules, ExclusionScope},
+        redemptions::PromotionRedemption,
+    },
     solvers::{
-        Solver,
-        ilp::{ILPSolver, observer::ILPObserver},
+        SolutionQuality,
+        ilp::{
+            ILPSolver, NoopObserver, cache::SolveCache, observer::ILPObserver, options::SolveRun,
+        },
     },
 };
 
@@ -35,6 +47,48 @@ pub(super) struct TrackedItem<'b> {
     pub redemptions: SmallVec<[PromotionRedemption<'b>; 3]>,
 }
 
+impl<'b> TrackedItem<'b> {
+    /// The item's unit price in the basket, before any layer discounted it.
+    fn basket_price(&self) -> &Money<'b, Currency> {
+        self.redemptions
+            .first()
+            .map_or(self.item.price(), |redemption| &redemption.original_price)
+    }
+
+    /// How many promotions the item has been redeemed in so far.
+    fn stacked(&self) -> u32 {
+        u32::try_from(self.redemptions.len()).unwrap_or(u32::MAX)
+    }
+}
+
+/// Evaluation state shared by every layer visited in a greedy evaluation.
+#[derive(Debug, Default)]
+pub(super) struct GreedyState<'p, 's, 'c, 'b> {
+    /// Next redemption index to assign across layers
+    pub next_redemption_idx: usize,
+
+    /// Remaining balances of the graph's shared budget pools
+    pub budget_pools: BudgetPools<'p>,
+
+    /// Backend, deadline and stability preference shared by every layer's solve
+    pub run: SolveRun<'s>,
+
+    /// Worst solution quality of the layers solved so far
+    pub quality: SolutionQuality,
+
+    /// Solutions kept from earlier evaluations of the same basket, if any
+    pub cache: Option<&'c mut SolveCache<'b>>,
+
+    /// Graph-wide stacking caps, tightened by each layer's own
+    pub stacking: StackingLimit,
+
+    /// Pairs of promotions anywhere in the graph that cannot be combined
+    pub exclusions: ExclusionRules,
+
+    /// Promotions redeemed by the layers solved so far
+    pub redeemed: FxHashSet<PromotionKey>,
+}
+
 /// Evaluate a single node in the promotion graph.
 ///
 /// Solves the ILP for the node's promotions, then routes items to successors
@@ -48,7 +102,8 @@ pub fn evaluate_node<'b>(
     node_idx: NodeIndex,
     tracked_items: TrackedItems<'b>,
     currency: &'b Currency,
-    next_redemption_idx: &mut usize,
+    context: &EvaluationContext,
+    state: &mut GreedyState<'_, '_, '_, 'b>,
     mut observer: Option<&mut dyn ILPObserver>,
 ) -> Result<TrackedItems<'b>, GraphError> {
     if tracked_items.is_empty() {
@@ -65,115 +120,287 @@ pub fn evaluate_node<'b>(
         return route_to_successors(
             graph,
             node_idx,
-            node.output_mode,
             tracked_items,
             currency,
-            next_redemption_idx,
+            context,
+            state,
             observer,
         );
     }
 
-    // Build a temporary ItemGroup from the tracked items' current prices
-    let temp_items: SmallVec<[Item<'b, _>; 10]> =
-        tracked_items.iter().map(|ti| ti.item.clone()).collect();
-
-    let temp_group = ItemGroup::new(temp_items, currency);
-
     // Notify observer of layer entry
     if let Some(obs) = observer.as_deref_mut() {
         obs.on_layer_begin(node.key, node_idx);
     }
 
     // Solve the ILP for this layer.
-    let redemptions = solve_layer(node, &temp_group, observer.as_deref_mut())?;
+    let redemptions = solve_layer(
+        node,
+        &tracked_items,
+        currency,
+        context,
+        state,
+        observer.as_deref_mut(),
+    )?;
 
     // Notify observer of layer completion
     if let Some(obs) = observer.as_deref_mut() {
         obs.on_layer_end();
     }
 
-    // Update tracked items with the solver results
-    let mut updated_items = tracked_items;
-
-    let redemption_idx_offset = *next_redemption_idx;
+    // Group this layer's redemptions by local line index
+    let redemption_idx_offset = state.next_redemption_idx;
 
     let mut max_redemption: Option<usize> = None;
 
+    let mut line_redemptions: SmallVec<[SmallVec<[PromotionRedemption<'b>; 2]>; 8]> =
+        SmallVec::from_elem(SmallVec::new(), tracked_items.len());
+
     for redemption in redemptions {
         max_redemption = Some(max_redemption.map_or(redemption.redemption_idx, |max| {
             max.max(redemption.redemption_idx)
         }));
-        let local_idx = redemption.item_idx;
-        let final_price_minor = redemption.final_price.to_minor_units();
 
-        let Some(tracked) = updated_items.get_mut(local_idx) else {
+        if let Some(line) = line_redemptions.get_mut(redemption.item_idx) {
+            line.push(redemption);
+        }
+    }
+
+    // Update tracked items with the solver results. A quantity line whose units
+    // were priced differently is split into one tracked line per price.
+    let mut updated_items = TrackedItems::with_capacity(tracked_items.len());
+
+    for (tracked, redemptions) in tracked_items.into_iter().zip(line_redemptions) {
+        if redemptions.is_empty() {
+            updated_items.push(tracked);
             continue;
-        };
+        }
 
-        // Update item price to the discounted price
-        tracked.item = Item::with_tags(
-            tracked.item.product(),
-            Money::from_minor(final_price_minor, currency),
-            tracked.item.tags().clone(),
-        );
+        let mut remaining_units = tracked.item.quantity();
+
+        for redemption in redemptions {
+            let Some(units) = NonZeroU32::new(redemption.quantity.min(remaining_units)) else {
+                continue;
+            };
+
+            remaining_units -= units.get();
+
+            let mut history = scaled_redemptions(&tracked.redemptions, units.get());
+
+            // Record the redemption with remapped indices
+            history.push(PromotionRedemption {
+                item_idx: tracked.original_basket_idx,
+                redemption_idx: redemption
+                    .redemption_idx
+                    .saturating_add(redemption_idx_offset),
+                quantity: units.get(),
+                ..redemption.clone()
+            });
+
+            // Update item price to the discounted price
+            updated_items.push(TrackedItem {
+                original_basket_idx: tracked.original_basket_idx,
+                item: tracked
+                    .item
+                    .clone()
+                    .with_price(Money::from_minor(
+                        redemption.final_price.to_minor_units(),
+                        currency,
+                    ))
+                    .with_quantity(units),
+                redemptions: history,
+            });
+        }
 
-        // Record the redemption with remapped indices
-        tracked.redemptions.push(PromotionRedemption {
-            promotion_key: redemption.promotion_key,
-            item_idx: tracked.original_basket_idx,
-            redemption_idx: redemption
-                .redemption_idx
-                .saturating_add(redemption_idx_offset),
-            original_price: redemption.original_price,
-            final_price: redemption.final_price,
-        });
+        if let Some(remaining_units) = NonZeroU32::new(remaining_units) {
+            updated_items.push(TrackedItem {
+                original_basket_idx: tracked.original_basket_idx,
+                item: tracked.item.clone().with_quantity(remaining_units),
+                redemptions: scaled_redemptions(&tracked.redemptions, remaining_units.get()),
+            });
+        }
     }
 
     // Advance next_redemption_idx past all redemptions used in this layer
     if let Some(max) = max_redemption {
-        *next_redemption_idx = redemption_idx_offset.saturating_add(max).saturating_add(1);
+        state.next_redemption_idx = redemption_idx_offset.saturating_add(max).saturating_add(1);
     }
 
     // Route items to successors based on output mode
     route_to_successors(
         graph,
         node_idx,
-        node.output_mode,
         updated_items,
         currency,
-        next_redemption_idx,
+        context,
+        state,
         observer,
     )
 }
 
-/// Solve the ILP for a layer.
+/// Copy a line's redemption history onto a sub-line of `units` units.
+fn scaled_redemptions<'b>(
+    redemptions: &[PromotionRedemption<'b>],
+    units: u32,
+) -> SmallVec<[PromotionRedemption<'b>; 3]> {
+    redemptions
+        .iter()
+        .map(|redemption| PromotionRedemption {
+            quantity: units,
+            ..redemption.clone()
+        })
+        .collect()
+}
+
+/// Solve the ILP for a layer, drawing on the remaining shared budget pools.
+///
+/// Items without stacking headroom left are kept out of the layer, and the rest
+/// may not be discounted past their remaining largest discount. Promotions that
+/// cannot be combined with one redeemed by an earlier layer are left out of the
+/// layer, or kept off the items it was redeemed on, depending on the exclusion's
+/// scope.
 fn solve_layer<'b>(
     node: &LayerNode<'_>,
-    temp_group: &ItemGroup<'b>,
+    tracked_items: &[TrackedItem<'b>],
+    currency: &'b Currency,
+    context: &EvaluationContext,
+    state: &mut GreedyState<'_, '_, '_, 'b>,
     observer: Option<&mut dyn ILPObserver>,
 ) -> Result<SmallVec<[PromotionRedemption<'b>; 10]>, GraphError> {
-    let result = match observer {
-        Some(obs) => ILPSolver::solve_with_observer(&node.promotions, temp_group, obs),
-        None => ILPSolver::solve(&node.promotions, temp_group),
+    let mut noop_observer = NoopObserver;
+
+    let observer: &mut dyn ILPObserver = match observer {
+        Some(observer) => observer,
+        None => &mut noop_observer,
+    };
+
+    let stacking = state.stacking.tightest(node.stacking);
+
+    // Tracked line index of each item in the layer's item group
+    let eligible: SmallVec<[usize; 10]> = tracked_items
+        .iter()
+        .enumerate()
+        .filter(|(_idx, ti)| stacking.allows_another(ti.stacked()))
+        .map(|(idx, _ti)| idx)
+        .collect();
+
+    if eligible.is_empty() {
+        return Ok(SmallVec::new());
+    }
+
+    // Build a temporary ItemGroup from the tracked items' current prices
+    let mut temp_items: SmallVec<[Item<'b, _>; 10]> = SmallVec::with_capacity(eligible.len());
+
+    for &idx in &eligible {
+        let Some(ti) = tracked_items.get(idx) else {
+            continue;
+        };
+
+        temp_items.push(stacking.limit_item(ti.item.clone(), ti.basket_price())?);
+    }
+
+    let temp_group = ItemGroup::new(temp_items, currency).with_context(context.clone());
+
+    let promotions: SmallVec<[Promotion<'_>; 5]> = node
+        .promotions
+        .iter()
+        .filter(|promotion| {
+            !state.redeemed.iter().any(|&redeemed| {
+                state.exclusions.scope(promotion.key(), redeemed) == Some(ExclusionScope::Basket)
+            })
+        })
+        .cloned()
+        .collect();
+
+    // Items already redeemed in a promotion that excludes one of the layer's.
+    let mut excluded: SmallVec<[(usize, PromotionKey); 4]> = SmallVec::new();
+
+    if !state.exclusions.is_empty() {
+        for (local_idx, ti) in eligible
+            .iter()
+            .filter_map(|&idx| tracked_items.get(idx))
+            .enumerate()
+        {
+            for promotion in &promotions {
+                if ti.redemptions.iter().any(|redemption| {
+                    state
+                        .exclusions
+                        .scope(redemption.promotion_key, promotion.key())
+                        .is_some()
+                }) {
+                    excluded.push((local_idx, promotion.key()));
+                }
+            }
+        }
     }
+
+    // The stability preference knows items by their original basket index.
+    let basket_idxs: SmallVec<[usize; 10]> = eligible
+        .iter()
+        .filter_map(|&idx| tracked_items.get(idx))
+        .map(|ti| ti.original_basket_idx)
+        .collect();
+
+    let result = ILPSolver::solve_run(
+        &promotions,
+        &temp_group,
+        &mut state.budget_pools,
+        observer,
+        state
+            .run
+            .with_stability(
+                state
+                    .run
+                    .stability
+                    .map(|stability| stability.with_basket_idxs(&basket_idxs)),
+            )
+            .with_objective(node.objective.unwrap_or(state.run.objective))
+            .with_excluded(&excluded),
+        state.cache.as_deref_mut(),
+    )
     .map_err(|source| GraphError::Solver {
         layer_key: node.key,
         source,
     })?;
 
-    Ok(result.promotion_redemptions)
+    state.quality = state.quality.max(result.quality);
+
+    state.redeemed.extend(
+        result
+            .promotion_redemptions
+            .iter()
+            .map(|redemption| redemption.promotion_key),
+    );
+
+    // Report redemptions against the tracked lines they were solved for.
+    Ok(result
+        .promotion_redemptions
+        .into_iter()
+        .filter_map(|redemption| {
+            eligible
+                .get(redemption.item_idx)
+                .map(|&idx| PromotionRedemption {
+                    item_idx: idx,
+                    ..redemption
+                })
+        })
+        .collect())
 }
 
-/// Route items to successor nodes based on output mode.
+/// Route items to successor nodes based on the node's output mode.
 fn route_to_successors<'b>(
     graph: &StableDiGraph<LayerNode<'_>, LayerEdge>,
     node_idx: NodeIndex,
-    output_mode: OutputMode,
     updated_items: TrackedItems<'b>,
     currency: &'b Currency,
-    next_redemption_idx: &mut usize,
+    context: &EvaluationContext,
+    state: &mut GreedyState<'_, '_, '_, 'b>,
     mut observer: Option<&mut dyn ILPObserver>,
 ) -> Result<TrackedItems<'b>, GraphError> {
+    let Some(output_mode) = graph.node_weight(node_idx).map(|node| node.output_mode) else {
+        return Ok(updated_items);
+    };
+
     let edges: SmallVec<[(NodeIndex, LayerEdge); 2]> = graph
         .edges(node_idx)
         .map(|e| (e.target(), *e.weight()))
@@ -189,7 +416,8 @@ fn route_to_successors<'b>(
                     *target,
                     updated_items,
                     currency,
-                    next_redemption_idx,
+                    context,
+                    state,
                     observer.as_deref_mut(),
                 ),
                 None => Ok(updated_items),
@@ -229,7 +457,8 @@ fn route_to_successors<'b>(
                     target,
                     promoted_items,
                     currency,
-                    next_redemption_idx,
+                    context,
+                    state,
                     observer.as_deref_mut(),
                 )?;
                 final_items.extend(result_items);
@@ -245,7 +474,8 @@ fn route_to_successors<'b>(
                     target,
                     unpromoted_items,
                     currency,
-                    next_redemption_idx,
+                    context,
+                    state,
                     observer,
                 )?;
                 final_items.extend(result_items);
@@ -345,14 +575,15 @@ mod tests {
         let graph: StableDiGraph<LayerNode<'_>, LayerEdge> = StableDiGraph::new();
         let items: TrackedItems<'static> = SmallVec::from_vec(vec![tracked_item(100)]);
 
-        let mut next_redemption_idx = 0;
+        let mut state = GreedyState::default();
 
         let result = evaluate_node(
             &graph,
             NodeIndex::new(999),
             items,
             GBP,
-            &mut next_redemption_idx,
+            &EvaluationContext::default(),
+            &mut state,
             None,
         )
         .expect("evaluation should succeed");
@@ -369,18 +600,21 @@ mod tests {
             key: expected_layer_key,
             promotions: SmallVec::from_vec(vec![direct_discount_promotion()]),
             output_mode: OutputMode::PassThrough,
+            objective: None,
+            stacking: StackingLimit::default(),
         });
 
         let mut observer = CountingObserver::default();
 
-        let mut next_redemption_idx = 0;
+        let mut state = GreedyState::default();
 
         let _ = evaluate_node(
             &graph,
             node,
             SmallVec::from_vec(vec![tracked_item(100)]),
             GBP,
-            &mut next_redemption_idx,
+            &EvaluationContext::default(),
+            &mut state,
             Some(&mut observer),
         )
         .expect("evaluation should succeed");
@@ -398,16 +632,19 @@ mod tests {
             key: layer_key,
             promotions: SmallVec::from_vec(vec![direct_discount_promotion()]),
             output_mode: OutputMode::PassThrough,
+            objective: None,
+            stacking: StackingLimit::default(),
         });
 
-        let mut next_redemption_idx = 0;
+        let mut state = GreedyState::default();
 
         let err = evaluate_node(
             &graph,
             node,
             SmallVec::from_vec(vec![tracked_item(9_007_199_254_740_993)]),
             GBP,
-            &mut next_redemption_idx,
+            &EvaluationContext::default(),
+            &mut state,
             None,
         )
         .expect_err("expected solver error");
@@ -429,17 +666,19 @@ mod tests {
             key: PromotionLayerKey::default(),
             promotions: SmallVec::new(),
             output_mode: OutputMode::PassThrough,
+            objective: None,
+            stacking: StackingLimit::default(),
         });
 
-        let mut next_redemption_idx = 0;
+        let mut state = GreedyState::default();
 
         let result = route_to_successors(
             &graph,
             node,
-            OutputMode::PassThrough,
             SmallVec::from_vec(vec![tracked_item(100)]),
             GBP,
-            &mut next_redemption_idx,
+            &EvaluationContext::default(),
+            &mut state,
             None,
         )
         .expect("routing should succeed");
@@ -455,6 +694,8 @@ mod tests {
             key: PromotionLayerKey::default(),
             promotions: SmallVec::new(),
             output_mode: OutputMode::Split,
+            objective: None,
+            stacking: StackingLimit::default(),
         });
 
         let mut discounted = tracked_item(100);
@@ -465,17 +706,20 @@ mod tests {
             redemption_idx: 0,
             original_price: Money::from_minor(100, GBP),
             final_price: Money::from_minor(90, GBP),
+            quantity: 1,
+            funding: SmallVec::new(),
+            floor_limited: false,
         });
 
-        let mut next_redemption_idx = 0;
+        let mut state = GreedyState::default();
 
         let result = route_to_successors(
             &graph,
             node,
-            OutputMode::Split,
             SmallVec::from_vec(vec![discounted, tracked_item(200)]),
             GBP,
-            &mut next_redemption_idx,
+            &EvaluationContext::default(),
+            &mut state,
             None,
         )
         .expect("routing should succeed");
@@ -0,0 +1,254 @@
+//! Promotion explanations
+//!
+//! Why each promotion in a graph did or did not redeem for an evaluated basket.
+
+use petgraph::{algo::toposort, graph::NodeIndex};
+use rustc_hash::{FxHashMap, FxHashSet};
+use rusty_money::Money;
+use smallvec::SmallVec;
+
+use crate::{
+    graph::{PromotionGraph, PromotionLayerKey, node::LayerNode, result::LayeredSolverResult},
+    items::groups::ItemGroup,
+    promotions::{
+        Promotion, PromotionKey,
+        budget::BudgetPools,
+        explain::{MissReason, Shortfall, is_spent},
+    },
+};
+
+/// What happened to a promotion during evaluation
+#[derive(Debug, Clone, PartialEq)]
+pub enum PromotionOutcome<'a> {
+    /// The promotion redeemed at least once
+    Redeemed {
+        /// Number of distinct redemptions
+        redemptions: usize,
+
+        /// Smallest change that would unlock a better reward, if any
+        upsell: Option<Shortfall<'a>>,
+    },
+
+    /// The promotion did not redeem
+    Missed(MissReason<'a>),
+}
+
+/// Explanation for a single promotion in a graph
+#[derive(Debug, Clone, PartialEq)]
+pub struct PromotionExplanation<'a> {
+    /// The promotion explained
+    pub promotion_key: PromotionKey,
+
+    /// First layer containing the promotion
+    pub layer_key: PromotionLayerKey,
+
+    /// What happened to the promotion
+    pub outcome: PromotionOutcome<'a>,
+}
+
+/// What each promotion redeemed in an evaluated basket.
+#[derive(Debug, Default)]
+struct Redeemed {
+    /// Distinct redemption indices
+    redemptions: FxHashSet<usize>,
+
+    /// Basket indices of the items redeemed
+    items: FxHashSet<usize>,
+
+    /// Total savings, in minor units
+    savings: i64,
+}
+
+impl PromotionGraph<'_> {
+    /// Explain the outcome of every promotion in the graph for an evaluated basket.
+    ///
+    /// `result` must come from evaluating `item_group` against this graph.
+    /// Promotions are listed once each, in layer order. Requirements are
+    /// measured against the whole basket at its original prices, so a promotion
+    /// in a later layer is judged as if every item reached it undiscounted.
+    ///
+    /// Shared budget pools are judged by the balance the earlier layers left
+    /// them. A promotion whose requirements are met but which did not redeem is
+    /// reported as outcompeted by the promotions in its layer that redeemed
+    /// items it could have used. If none did, the promotion could only have been
+    /// held back by a shared pool, and the first pool with a monetary limit (or
+    /// one its layer used up) is reported as exhausted.
+    pub fn explain<'b>(
+        &self,
+        item_group: &ItemGroup<'b>,
+        result: &LayeredSolverResult<'b>,
+    ) -> Vec<PromotionExplanation<'b>> {
+        let mut redeemed: FxHashMap<PromotionKey, Redeemed> = FxHashMap::default();
+
+        for (&item_idx, item_redemptions) in &result.item_redemptions {
+            for redemption in item_redemptions {
+                let entry = redeemed.entry(redemption.promotion_key).or_default();
+
+                entry.redemptions.insert(redemption.redemption_idx);
+                entry.items.insert(item_idx);
+                entry.savings += redemption
+                    .total_savings()
+                    .map_or(0, |savings| savings.to_minor_units());
+            }
+        }
+
+        let balances = self.pool_balances(&redeemed);
+        let no_pools = BudgetPools::default();
+
+        let mut seen = FxHashSet::default();
+        let mut explanations = Vec::new();
+
+        for (node_idx, node) in self
+            .graph
+            .node_indices()
+            .filter_map(|node_idx| Some((node_idx, self.graph.node_weight(node_idx)?)))
+        {
+            let (before, after) = balances
+                .get(&node_idx)
+                .map_or((&no_pools, &no_pools), |(before, after)| (before, after));
+
+            for promotion in &node.promotions {
+                let key = promotion.key();
+
+                if !seen.insert(key) {
+                    continue;
+                }
+
+                let outcome = if let Some(redeemed) = redeemed.get(&key) {
+                    PromotionOutcome::Redeemed {
+                        redemptions: redeemed.redemptions.len(),
+                        upsell: promotion.upsell(item_group),
+                    }
+                } else {
+                    let reason = promotion.near_miss(item_group, before).unwrap_or_else(|| {
+                        beaten_reason(promotion, node, item_group, &redeemed, before, after)
+                    });
+
+                    PromotionOutcome::Missed(reason)
+                };
+
+                explanations.push(PromotionExplanation {
+                    promotion_key: key,
+                    layer_key: node.key,
+                    outcome,
+                });
+            }
+        }
+
+        explanations
+    }
+
+    /// Remaining balances of the shared budget pools before and after each layer.
+    ///
+    /// Layers are debited in evaluation order with what their promotions redeemed.
+    fn pool_balances(
+        &self,
+        redeemed: &FxHashMap<PromotionKey, Redeemed>,
+    ) -> FxHashMap<NodeIndex, (BudgetPools<'_>, BudgetPools<'_>)> {
+        let mut balances = FxHashMap::default();
+
+        if self.budget_pools.is_empty() {
+            return balances;
+        }
+
+        let order = toposort(&self.graph, None)
+            .unwrap_or_else(|_cycle| self.graph.node_indices().collect());
+        let mut pools = self.budget_pools.clone();
+
+        for node_idx in order {
+            let Some(node) = self.graph.node_weight(node_idx) else {
+                continue;
+            };
+
+            let before = pools.clone();
+
+            for promotion in &node.promotions {
+                let Some(redeemed) = redeemed.get(&promotion.key()) else {
+                    continue;
+                };
+
+                let used = i64::try_from(redeemed.redemptions.len()).unwrap_or(i64::MAX);
+
+                for &pool_key in promotion.budget_pools() {
+                    let Some(pool) = pools.get_mut(pool_key) else {
+                        continue;
+                    };
+
+                    if let Some(limit) = pool.redemption_limit {
+                        let remaining = i64::from(limit).saturating_sub(used).max(0);
+
+                        pool.redemption_limit = Some(u32::try_from(remaining).unwrap_or(limit));
+                    }
+
+                    if let Some(limit) = pool.monetary_limit {
+                        let remaining = limit
+                            .to_minor_units()
+                            .saturating_sub(redeemed.savings)
+                            .max(0);
+
+                        pool.monetary_limit = Some(Money::from_minor(remaining, limit.currency()));
+                    }
+                }
+            }
+
+            balances.insert(node_idx, (before, pools.clone()));
+        }
+
+        balances
+    }
+}
+
+/// Why a promotion whose requirements are met did not redeem.
+///
+/// Either promotions in its layer redeemed items it could have used, or nothing
+/// took its items and a shared pool it draws on could not cover a redemption.
+fn beaten_reason<'b>(
+    promotion: &Promotion<'_>,
+    node: &LayerNode<'_>,
+    item_group: &ItemGroup<'b>,
+    redeemed: &FxHashMap<PromotionKey, Redeemed>,
+    before: &BudgetPools<'_>,
+    after: &BudgetPools<'_>,
+) -> MissReason<'b> {
+    let eligible: FxHashSet<usize> = item_group
+        .iter()
+        .enumerate()
+        .filter(|(_item_idx, item)| {
+            promotion
+                .item_signature(item, item_group.context())
+                .is_none_or(|signature| signature.contains(&true))
+        })
+        .map(|(item_idx, _item)| item_idx)
+        .collect();
+
+    let by: SmallVec<[PromotionKey; 2]> = node
+        .promotions
+        .iter()
+        .map(|other| other.key())
+        .filter(|other| {
+            redeemed
+                .get(other)
+                .is_some_and(|redeemed| !redeemed.items.is_disjoint(&eligible))
+        })
+        .collect();
+
+    if !by.is_empty() {
+        return MissReason::Outcompeted { by };
+    }
+
+    let pools = promotion.budget_pools();
+
+    pools
+        .iter()
+        .find(|&&pool_key| after.get(pool_key).is_some_and(is_spent))
+        .or_else(|| {
+            pools.iter().find(|&&pool_key| {
+                before
+                    .get(pool_key)
+                    .is_some_and(|pool| pool.monetary_limit.is_some())
+            })
+        })
+        .map_or(MissReason::Outcompeted { by }, |&pool_key| {
+            MissReason::PoolExhausted(pool_key)
+        })
+}
@@ -0,0 +1,1047 @@
+//! Joint multi-layer evaluation engine.
+//!
+//! Builds a single ILP spanning every layer of the graph instead of solving each
+//! layer in isolation. Every price an item can reach a layer at becomes its own row
+//! in that layer's item group; a row is only "active" when the upstream variables
+//! route the item to the layer at that price. Minimising the final basket total
+//! over the combined model lets early layers give up a locally better allocation
+//! when doing so unlocks a larger saving further down the graph.
+
+use good_lp::{Expression, IntoAffineExpression, ProblemVariables, ResolutionError, Solution};
+use petgraph::{graph::NodeIndex, stable_graph::StableDiGraph, visit::EdgeRef};
+use rustc_hash::FxHashMap;
+use rusty_money::{Money, iso::Currency};
+use smallvec::SmallVec;
+
+use crate::{
+    context::EvaluationContext,
+    discounts::rounding::RoundingPolicies,
+    graph::{
+        edge::LayerEdge,
+        error::GraphError,
+        exclusion_rules, merge_redemption,
+        node::{LayerNode, OutputMode, PromotionLayerKey},
+        result::LayeredSolverResult,
+        stacking::StackingLimit,
+    },
+    items::{Item, groups::ItemGroup},
+    promotions::{
+        PromotionKey, budget::BudgetPools, coupon::CouponCodeReport, exclusion::ExclusionRules,
+        redemptions::PromotionRedemption,
+    },
+    solvers::{
+        SolutionQuality, SolverError,
+        ilp::{
+            ILPObserver, ILPPromotion, ILPState, NoopObserver,
+            budget_pools::BudgetPoolUsage,
+            exclusions::{self, ExclusionUsage},
+            i64_to_f64_exact,
+            objective::ObjectiveMode,
+            objective_value_to_integral_minor_units,
+            options::SolveRun,
+            promotions::{PromotionInstances, price_floor_minor},
+            recorded_constraints,
+            state::ILPConstraint,
+            tie_break::TieBreakRule,
+        },
+    },
+};
+
+type JointRows<'b> = SmallVec<[JointRow<'b>; 10]>;
+
+/// A state an item can be in when it enters a layer.
+#[derive(Debug)]
+struct JointRow<'b> {
+    /// Index of the item in the original basket/item group
+    original_basket_idx: usize,
+
+    /// The item at the price it enters the layer with
+    item: Item<'b>,
+
+    /// The item's unit price in the basket, before any layer discounted it
+    basket_price: Money<'b, Currency>,
+
+    /// Whether the item has participated in any promotion on the way here
+    participated: bool,
+
+    /// Promotions the item was redeemed in on the way here, counted up to the
+    /// largest redemption cap in the graph
+    stacked: u32,
+
+    /// Promotions with exclusion rules the item was redeemed in on the way here,
+    /// in key order
+    redeemed: SmallVec<[PromotionKey; 2]>,
+
+    /// Expression counting the units of the line that enter in this state.
+    ///
+    /// `None` means the whole line always enters in this state (root layer rows).
+    activation: Option<Expression>,
+}
+
+/// A layer's expanded item group and compiled promotions, kept for result extraction.
+#[derive(Debug)]
+struct JointLayer<'g, 'b> {
+    /// Key of the layer this instance belongs to
+    key: PromotionLayerKey,
+
+    /// One entry per row (item state) that can reach the layer
+    item_group: ItemGroup<'b>,
+
+    /// Original basket index for each row of `item_group`
+    original_indices: SmallVec<[usize; 10]>,
+
+    /// Whether each row of `item_group` had already participated upstream
+    participated: SmallVec<[bool; 10]>,
+
+    /// Promotions compiled against `item_group`
+    promotion_instances: PromotionInstances<'g>,
+}
+
+/// A complete joint formulation across all layers reachable from the root.
+struct JointFormulation<'g, 'b> {
+    pb: ProblemVariables,
+    objective: Expression,
+    constraints: Vec<ILPConstraint>,
+    layers: Vec<JointLayer<'g, 'b>>,
+}
+
+/// Incrementally builds the joint formulation by walking the graph from the root.
+struct JointFormulationBuilder<'g, 'a, 'b, 'o> {
+    graph: &'g StableDiGraph<LayerNode<'a>, LayerEdge>,
+    currency: &'b Currency,
+    context: EvaluationContext,
+    state: ILPState,
+    objective: Expression,
+    layers: Vec<JointLayer<'g, 'b>>,
+    observer: &'o mut dyn ILPObserver,
+    stacking: StackingLimit,
+    stacked_cap: u32,
+    exclusions: ExclusionRules,
+    exclusion_usage: ExclusionUsage,
+}
+
+/// Evaluate the graph as a single ILP spanning every layer.
+///
+/// # Errors
+///
+/// Returns a [`GraphError`] if a layer cannot be formulated (including promotions that
+/// cannot describe their per-item prices to later layers), or if the solver fails.
+pub(super) fn evaluate_joint<'b>(
+    graph: &StableDiGraph<LayerNode<'_>, LayerEdge>,
+    root: NodeIndex,
+    budget_pools: &BudgetPools<'_>,
+    stacking: StackingLimit,
+    item_group: &ItemGroup<'b>,
+    observer: Option<&mut dyn ILPObserver>,
+    run: SolveRun<'_>,
+) -> Result<LayeredSolverResult<'b>, GraphError> {
+    if item_group.is_empty() {
+        return Ok(LayeredSolverResult {
+            total: Money::from_minor(0, item_group.currency()),
+            item_redemptions: FxHashMap::default(),
+            full_price_items: SmallVec::new(),
+            coupon_codes: CouponCodeReport::default(),
+            quality: SolutionQuality::Optimal,
+        });
+    }
+
+    let run = run.with_objective(joint_objective(graph, run.objective)?);
+
+    let mut noop_observer = NoopObserver;
+
+    let observer: &mut dyn ILPObserver = match observer {
+        Some(observer) => observer,
+        None => &mut noop_observer,
+    };
+
+    let JointFormulation {
+        pb,
+        objective,
+        constraints,
+        layers,
+    } = build_joint_formulation(
+        graph,
+        root,
+        budget_pools,
+        stacking,
+        item_group,
+        observer,
+        run.rounding,
+    )?;
+
+    // Same lexicographic tie-break as the per-layer solver: only run further passes
+    // when the objective has an entitlement, a tie-break policy is set or some
+    // promotion contributes secondary objective terms.
+    let secondary_objective = joint_pass_objective(&layers, JointPass::Secondary)?;
+
+    let has_secondary_objective_terms =
+        IntoAffineExpression::linear_coefficients(&secondary_objective)
+            .next()
+            .is_some();
+
+    let entitled = joint_entitled_redemptions(&layers, run.objective)?;
+
+    let primary_objective = match &entitled {
+        Some(entitled) => -entitled.clone(),
+        None => run.objective.signed_cost(objective),
+    };
+
+    let primary_solution = match run.solve(
+        pb.minimise(primary_objective.clone()),
+        recorded_constraints(constraints),
+    ) {
+        Ok(solution) => solution,
+        // Nothing feasible was found in time, so every item stays at full price.
+        // Leaving every item at full price is always feasible, so infeasibility is
+        // numerical trouble in the backend and is handled the same way.
+        Err(
+            SolverError::TimeLimitReached
+            | SolverError::ResolutionError(ResolutionError::Infeasible),
+        ) => return fallback_result(item_group),
+        Err(err) => return Err(GraphError::JointSolver(err)),
+    };
+
+    let quality = SolutionQuality::from(primary_solution.status());
+
+    // A time-limited incumbent leaves no time for the follow-up passes.
+    if (has_secondary_objective_terms || !run.tie_break.is_empty() || entitled.is_some())
+        && quality.is_optimal()
+    {
+        let primary_optimal_value = primary_solution.eval(&primary_objective);
+
+        let face = match entitled {
+            Some(_) => JointFace {
+                cost: None,
+                min_entitled: Some(-primary_optimal_value),
+                rule_bounds: SmallVec::new(),
+            },
+            None => JointFace {
+                cost: Some(pinned_cost(primary_optimal_value)?),
+                min_entitled: None,
+                rule_bounds: SmallVec::new(),
+            },
+        };
+
+        let inputs = JointInputs {
+            graph,
+            root,
+            budget_pools,
+            stacking,
+            item_group,
+        };
+
+        if let Some(result) =
+            solve_joint_tie_breaks(&inputs, run, face, has_secondary_objective_terms)?
+        {
+            return Ok(result);
+        }
+    }
+
+    let mut result = build_joint_result(&layers, &primary_solution, item_group)?;
+
+    result.quality = quality;
+
+    Ok(result)
+}
+
+/// What is needed to rebuild the joint formulation for a follow-up pass.
+struct JointInputs<'g, 'a, 'p, 'b> {
+    graph: &'g StableDiGraph<LayerNode<'a>, LayerEdge>,
+    root: NodeIndex,
+    budget_pools: &'p BudgetPools<'p>,
+    stacking: StackingLimit,
+    item_group: &'g ItemGroup<'b>,
+}
+
+/// What a follow-up pass of the joint model minimises.
+#[derive(Debug, Clone, Copy)]
+enum JointPass {
+    /// The final total, signed as the objective mode minimises it
+    Cost,
+
+    /// The measure of a tie-break rule from the run's policy
+    Rule(TieBreakRule),
+
+    /// The promotions' own tie-break terms
+    Secondary,
+}
+
+/// Bounds keeping a follow-up pass on the optimum face of the passes before it.
+struct JointFace {
+    /// Optimal final total in minor units, signed as the objective mode minimises
+    /// it, once known
+    cost: Option<f64>,
+
+    /// Fewest entitled redemptions, under a least-generous objective
+    min_entitled: Option<f64>,
+
+    /// Best measure found by each tie-break rule pass so far
+    rule_bounds: SmallVec<[(TieBreakRule, f64); 3]>,
+}
+
+/// The objective every layer optimises in a joint solve.
+///
+/// A joint solve has a single objective, so layers may only override the graph's
+/// objective with the same one.
+fn joint_objective(
+    graph: &StableDiGraph<LayerNode<'_>, LayerEdge>,
+    objective: ObjectiveMode,
+) -> Result<ObjectiveMode, GraphError> {
+    let mut layers = graph
+        .node_weights()
+        .map(|layer| (layer.key, layer.objective.unwrap_or(objective)));
+
+    let Some((first, first_objective)) = layers.next() else {
+        return Ok(objective);
+    };
+
+    match layers.find(|(_key, layer_objective)| *layer_objective != first_objective) {
+        Some((second, _objective)) => Err(GraphError::JointObjectiveMismatch { first, second }),
+        None => Ok(first_objective),
+    }
+}
+
+/// Convert a solved total back to the whole minor units it must be.
+fn pinned_cost(value: f64) -> Result<f64, GraphError> {
+    let minor_units =
+        objective_value_to_integral_minor_units(value, "joint objective value is non-integral")
+            .map_err(GraphError::JointSolver)?;
+
+    i64_to_f64_exact(minor_units).ok_or(GraphError::JointSolver(
+        SolverError::MinorUnitsNotRepresentable(minor_units),
+    ))
+}
+
+/// Apply the least-generous pass, the run's tie-break rules and then the
+/// promotions' own tie-break terms, each on the optimum face of the passes before
+/// it.
+///
+/// Returns `None` if no pass produced a solution.
+fn solve_joint_tie_breaks<'b>(
+    inputs: &JointInputs<'_, '_, '_, 'b>,
+    run: SolveRun<'_>,
+    mut face: JointFace,
+    tie_break: bool,
+) -> Result<Option<LayeredSolverResult<'b>>, GraphError> {
+    let mut settled = None;
+
+    // Keep the entitlement while giving the least discount.
+    if face.cost.is_none() {
+        let Some((result, cost)) = solve_joint_pass(inputs, run, &face, JointPass::Cost)? else {
+            return Ok(None);
+        };
+
+        face.cost = Some(pinned_cost(cost)?);
+
+        settled = Some(result);
+    }
+
+    for rule in run.tie_break.rules() {
+        if let Some((result, best)) = solve_joint_pass(inputs, run, &face, JointPass::Rule(rule))? {
+            face.rule_bounds.push((rule, best));
+
+            settled = Some(result);
+        }
+    }
+
+    if tie_break
+        && let Some((result, _secondary)) =
+            solve_joint_pass(inputs, run, &face, JointPass::Secondary)?
+    {
+        return Ok(Some(result));
+    }
+
+    Ok(settled)
+}
+
+/// Re-solve the joint model on `face`, minimising the measure of `pass`.
+///
+/// Returns the result with the best measure, or `None` if the pass has nothing to
+/// choose between or the time limit passes first.
+fn solve_joint_pass<'b>(
+    inputs: &JointInputs<'_, '_, '_, 'b>,
+    run: SolveRun<'_>,
+    face: &JointFace,
+    pass: JointPass,
+) -> Result<Option<(LayeredSolverResult<'b>, f64)>, GraphError> {
+    // Rebuild the identical formulation, pin the earlier passes to their optimum
+    // and minimise the pass's measure instead.
+    let mut observer = NoopObserver;
+
+    let JointFormulation {
+        pb,
+        objective,
+        constraints,
+        layers,
+    } = build_joint_formulation(
+        inputs.graph,
+        inputs.root,
+        inputs.budget_pools,
+        inputs.stacking,
+        inputs.item_group,
+        &mut observer,
+        run.rounding,
+    )?;
+
+    let cost = run.objective.signed_cost(objective);
+
+    let measure = match pass {
+        JointPass::Cost => cost.clone(),
+        JointPass::Rule(_) | JointPass::Secondary => joint_pass_objective(&layers, pass)?,
+    };
+
+    if IntoAffineExpression::linear_coefficients(&measure)
+        .next()
+        .is_none()
+    {
+        return Ok(None);
+    }
+
+    let mut face_constraints = Vec::new();
+
+    if let Some(optimal_cost) = face.cost {
+        face_constraints.push(cost.eq(optimal_cost));
+    }
+
+    // Redemptions and rule measures are whole (units or redemptions, scaled by
+    // whole priorities), so half a unit of slack pins the same face without an
+    // exact float equality.
+    if let (Some(min_entitled), Some(entitled)) = (
+        face.min_entitled,
+        joint_entitled_redemptions(&layers, run.objective)?,
+    ) {
+        face_constraints.push(entitled.geq(min_entitled - 0.5));
+    }
+
+    for &(rule, best) in &face.rule_bounds {
+        face_constraints
+            .push(joint_pass_objective(&layers, JointPass::Rule(rule))?.leq(best + 0.5));
+    }
+
+    match run.solve(
+        pb.minimise(measure.clone()),
+        recorded_constraints(constraints).chain(face_constraints),
+    ) {
+        Ok(solution) => {
+            let best = solution.eval(&measure);
+
+            build_joint_result(&layers, &solution, inputs.item_group)
+                .map(|result| Some((result, best)))
+        }
+        Err(SolverError::TimeLimitReached) => Ok(None),
+        Err(err) => Err(GraphError::JointSolver(err)),
+    }
+}
+
+/// Sum the redemptions every layer's entitlement covers, if the objective has one.
+fn joint_entitled_redemptions(
+    layers: &[JointLayer<'_, '_>],
+    objective: ObjectiveMode,
+) -> Result<Option<Expression>, GraphError> {
+    let mut entitled: Option<Expression> = None;
+
+    for layer in layers {
+        let layer_entitled = objective
+            .entitled_redemptions(&layer.promotion_instances)
+            .map_err(|source| GraphError::Solver {
+                layer_key: layer.key,
+                source,
+            })?;
+
+        if let Some(layer_entitled) = layer_entitled {
+            entitled = Some(entitled.unwrap_or_default() + layer_entitled);
+        }
+    }
+
+    Ok(entitled)
+}
+
+/// Sum the measure a rule or secondary pass minimises over every layer.
+fn joint_pass_objective(
+    layers: &[JointLayer<'_, '_>],
+    pass: JointPass,
+) -> Result<Expression, GraphError> {
+    let mut measure = Expression::default();
+
+    for layer in layers {
+        let instances = &layer.promotion_instances;
+
+        measure = match pass {
+            JointPass::Cost => Ok(measure),
+            JointPass::Rule(rule) => {
+                Ok(rule.add_objective_terms(measure, instances, &layer.item_group))
+            }
+            JointPass::Secondary => {
+                instances.add_secondary_objective_terms(measure, &layer.item_group)
+            }
+        }
+        .map_err(|source| GraphError::Solver {
+            layer_key: layer.key,
+            source,
+        })?;
+    }
+
+    Ok(measure)
+}
+
+/// Full-price result used when the time limit passes before any solution is found,
+/// or the backend wrongly reports the model infeasible.
+fn fallback_result<'b>(item_group: &ItemGroup<'b>) -> Result<LayeredSolverResult<'b>, GraphError> {
+    let mut total = Money::from_minor(0, item_group.currency());
+
+    for item in item_group.iter() {
+        total = total.add(item.line_price()?)?;
+    }
+
+    Ok(LayeredSolverResult {
+        total,
+        item_redemptions: FxHashMap::default(),
+        full_price_items: (0..item_group.len()).collect(),
+        coupon_codes: CouponCodeReport::default(),
+        quality: SolutionQuality::Fallback,
+    })
+}
+
+/// The largest redemption cap any layer applies, beyond which stacked
+/// redemptions no longer need counting.
+fn stacked_cap(graph: &StableDiGraph<LayerNode<'_>, LayerEdge>, stacking: StackingLimit) -> u32 {
+    graph
+        .node_weights()
+        .map(|layer| stacking.tightest(layer.stacking).max_redemptions)
+        .chain([stacking.max_redemptions])
+        .flatten()
+        .max()
+        .unwrap_or(0)
+}
+
+/// Build the joint formulation for all layers reachable from `root`.
+fn build_joint_formulation<'g, 'b>(
+    graph: &'g StableDiGraph<LayerNode<'_>, LayerEdge>,
+    root: NodeIndex,
+    budget_pools: &BudgetPools<'_>,
+    stacking: StackingLimit,
+    item_group: &ItemGroup<'b>,
+    observer: &mut dyn ILPObserver,
+    rounding: &RoundingPolicies,
+) -> Result<JointFormulation<'g, 'b>, GraphError> {
+    let mut state = ILPState::empty();
+
+    state.set_rounding(rounding);
+
+    // Rows for the same item are mutually exclusive, so "identical" items are
+    // no longer interchangeable and symmetry-breaking rows would cut off optima.
+    state.disable_symmetry_breaking();
+
+    let mut builder = JointFormulationBuilder {
+        graph,
+        currency: item_group.currency(),
+        context: item_group.context().clone(),
+        state,
+        objective: Expression::default(),
+        layers: Vec::new(),
+        observer,
+        stacking,
+        stacked_cap: stacked_cap(graph, stacking),
+        exclusions: exclusion_rules(graph),
+        exclusion_usage: ExclusionUsage::default(),
+    };
+
+    // Every unit enters the root layer exactly once, at its original price.
+    let mut rows = JointRows::new();
+
+    for original_basket_idx in 0..item_group.len() {
+        let item = item_group.get_item(original_basket_idx)?;
+        let price_minor = item.price().to_minor_units();
+        let coeff = i64_to_f64_exact(price_minor).ok_or(GraphError::JointSolver(
+            SolverError::MinorUnitsNotRepresentable(price_minor),
+        ))?;
+
+        // The objective is the original basket total plus, for each layer, the
+        // change in price of every unit passing through it.
+        builder.objective += coeff * f64::from(item.quantity());
+
+        rows.push(JointRow {
+            original_basket_idx,
+            item: item.clone(),
+            basket_price: *item.price(),
+            participated: false,
+            stacked: 0,
+            redeemed: SmallVec::new(),
+            activation: None,
+        });
+    }
+
+    builder.add_node(root, rows)?;
+
+    // Shared budget pools limit the combined usage of their promotions across every layer.
+    let mut budget_pool_usage = BudgetPoolUsage::default();
+
+    for layer in &builder.layers {
+        budget_pool_usage
+            .add_instances(&layer.promotion_instances, &layer.item_group, budget_pools)
+            .map_err(|source| GraphError::Solver {
+                layer_key: layer.key,
+                source,
+            })?;
+    }
+
+    budget_pool_usage
+        .add_constraints(budget_pools, &mut builder.state, &mut *builder.observer)
+        .map_err(GraphError::JointSolver)?;
+
+    // Promotions that cannot share a basket are kept apart across every layer.
+    builder.exclusion_usage.add_constraints(
+        &builder.exclusions,
+        &mut builder.state,
+        &mut *builder.observer,
+    );
+
+    let JointFormulationBuilder {
+        state,
+        objective,
+        layers,
+        observer,
+        ..
+    } = builder;
+
+    let (pb, _cost, _presence, constraints) = state.into_parts_with_constraints();
+
+    for (var, definition) in pb.iter_variables_with_def() {
+        observer.on_variable_definition(var, definition);
+    }
+
+    Ok(JointFormulation {
+        pb,
+        objective,
+        constraints,
+        layers,
+    })
+}
+
+impl<'g, 'b> JointFormulationBuilder<'g, '_, 'b, '_> {
+    /// Add a node's layer for the given incoming rows, then recurse into its successors.
+    fn add_node(&mut self, node_idx: NodeIndex, rows: JointRows<'b>) -> Result<(), GraphError> {
+        if rows.is_empty() {
+            return Ok(());
+        }
+
+        let graph = self.graph;
+
+        let Some(node) = graph.node_weight(node_idx) else {
+            return Ok(());
+        };
+
+        // Layers without promotions only route items, exactly as in greedy evaluation.
+        let outgoing_rows = if node.promotions.is_empty() {
+            rows
+        } else {
+            let stacking = self.stacking.tightest(node.stacking);
+
+            // Items redeemed in as many promotions as allowed skip the layer.
+            let (eligible, exhausted): (JointRows<'b>, JointRows<'b>) = rows
+                .into_iter()
+                .partition(|row| stacking.allows_another(row.stacked));
+
+            let mut outgoing_rows = if eligible.is_empty() {
+                JointRows::new()
+            } else {
+                self.add_layer(node, node_idx, &eligible, stacking)?
+            };
+
+            outgoing_rows.extend(exhausted);
+
+            outgoing_rows
+        };
+
+        self.route_to_successors(node_idx, node.output_mode, outgoing_rows)
+    }
+
+    /// Add a layer's variables and constraints, returning the rows leaving it.
+    ///
+    /// Leaving rows are only built when the node has successors, since leaf layers
+    /// do not need to describe their per-item prices to anyone.
+    fn add_layer(
+        &mut self,
+        node: &'g LayerNode<'_>,
+        node_idx: NodeIndex,
+        rows: &JointRows<'b>,
+        stacking: StackingLimit,
+    ) -> Result<JointRows<'b>, GraphError> {
+        let layer_key = node.key;
+        let solver_error = |source| GraphError::Solver { layer_key, source };
+
+        // Each row may only be discounted as far as its remaining headroom allows.
+        let items = rows
+            .iter()
+            .map(|row| stacking.limit_item(row.item.clone(), &row.basket_price))
+            .collect::<Result<SmallVec<[Item<'b>; 10]>, _>>()?;
+
+        let item_group = ItemGroup::new(items, self.currency).with_context(self.context.clone());
+
+        let promotions: SmallVec<[&'g dyn ILPPromotion; 5]> =
+            node.promotions.iter().map(AsRef::as_ref).collect();
+
+        self.observer.on_layer_begin(layer_key, node_idx);
+
+        let presence = self
+            .state
+            .add_presence_variables(&item_group, &mut *self.observer)
+            .map_err(solver_error)?;
+
+        let promotion_instances = PromotionInstances::from_promotions(
+            &promotions,
+            &item_group,
+            &mut self.state,
+            &mut *self.observer,
+        )
+        .map_err(solver_error)?;
+
+        // Rows already redeemed in a promotion that excludes one of the layer's
+        // are kept off it, and basket exclusions are tracked across layers.
+        let excluded: SmallVec<[(usize, PromotionKey); 4]> = rows
+            .iter()
+            .enumerate()
+            .flat_map(|(row_idx, row)| {
+                promotions
+                    .iter()
+                    .map(|promotion| promotion.key())
+                    .filter(|&key| {
+                        row.redeemed
+                            .iter()
+                            .any(|&redeemed| self.exclusions.scope(redeemed, key).is_some())
+                    })
+                    .map(move |key| (row_idx, key))
+            })
+            .collect();
+
+        exclusions::exclude_items(
+            &promotion_instances,
+            &excluded,
+            &mut self.state,
+            &mut *self.observer,
+        );
+
+        self.exclusion_usage
+            .add_instances(
+                &self.exclusions,
+                &promotion_instances,
+                &item_group,
+                &mut self.state,
+                &mut *self.observer,
+            )
+            .map_err(solver_error)?;
+
+        // The layer's own cost is the total of every active row's outgoing price.
+        self.objective += self.state.take_objective();
+
+        for (row_idx, (row, z)) in rows.iter().zip(presence.iter().copied()).enumerate() {
+            let presence_expr =
+                promotion_instances.add_item_presence_term(Expression::from(z), row_idx);
+
+            let price_minor = row.item.price().to_minor_units();
+            let coeff = i64_to_f64_exact(price_minor)
+                .ok_or(SolverError::MinorUnitsNotRepresentable(price_minor))
+                .map_err(solver_error)?;
+
+            // Each active unit is bought exactly once (full price or one promotion),
+            // and its incoming price is replaced by its outgoing price in the total.
+            if let Some(activation) = &row.activation {
+                let link = presence_expr - activation.clone();
+
+                self.objective -= activation.clone() * coeff;
+                self.observer
+                    .on_linking_constraint(row_idx, &link, activation, price_minor);
+                self.state.add_eq_constraint(link, 0.0);
+            } else {
+                let quantity = f64::from(row.item.quantity());
+
+                self.objective -= coeff * quantity;
+                self.observer
+                    .on_exclusivity_constraint(row_idx, &presence_expr);
+                self.state.add_eq_constraint(presence_expr, quantity);
+            }
+        }
+
+        self.observer.on_layer_end();
+
+        let has_successors = self.graph.edges(node_idx).next().is_some();
+
+        let outgoing_rows = if has_successors {
+            self.outgoing_rows(
+                layer_key,
+                rows,
+                &presence,
+                &item_group,
+                &promotion_instances,
+            )?
+        } else {
+            JointRows::new()
+        };
+
+        self.layers.push(JointLayer {
+            key: layer_key,
+            original_indices: rows.iter().map(|row| row.original_basket_idx).collect(),
+            participated: rows.iter().map(|row| row.participated).collect(),
+            item_group,
+            promotion_instances,
+        });
+
+        Ok(outgoing_rows)
+    }
+
+    /// Enumerate the states items can leave a layer in.
+    fn outgoing_rows(
+        &self,
+        layer_key: PromotionLayerKey,
+        rows: &[JointRow<'b>],
+        presence: &[good_lp::Variable],
+        item_group: &ItemGroup<'b>,
+        promotion_instances: &PromotionInstances<'_>,
+    ) -> Result<JointRows<'b>, GraphError> {
+        let mut outgoing = JointRows::new();
+
+        for (row_idx, (row, z)) in rows.iter().zip(presence.iter().copied()).enumerate() {
+            // Full price: the item leaves exactly as it entered.
+            self.push_outgoing_row(
+                &mut outgoing,
+                row,
+                Expression::from(z),
+                row.item.price().to_minor_units(),
+                None,
+            );
+
+            // Items leave at no less than their floor (including the layer's
+            // stacking headroom), however deep the discount.
+            let floor_minor = price_floor_minor(item_group.get_item(row_idx)?);
+
+            for instance in promotion_instances.iter() {
+                let outcomes = instance
+                    .item_price_outcomes(item_group, row_idx)
+                    .map_err(|source| GraphError::Solver { layer_key, source })?
+                    .ok_or(GraphError::JointPriceOutcomesUnavailable {
+                        layer_key,
+                        promotion_key: instance.promotion_key(),
+                    })?;
+
+                for (expr, final_minor) in outcomes {
+                    let final_minor =
+                        floor_minor.map_or(final_minor, |floor| final_minor.max(floor));
+
+                    self.push_outgoing_row(
+                        &mut outgoing,
+                        row,
+                        expr,
+                        final_minor,
+                        Some(instance.promotion_key()),
+                    );
+                }
+            }
+        }
+
+        Ok(outgoing)
+    }
+
+    /// Add an outcome to the outgoing rows, merging it with an identical state.
+    ///
+    /// `promotion_key` is the promotion the outcome redeems the row in, or `None`
+    /// if the row leaves at full price.
+    fn push_outgoing_row(
+        &self,
+        outgoing: &mut JointRows<'b>,
+        row: &JointRow<'b>,
+        expr: Expression,
+        price_minor: i64,
+        promotion_key: Option<PromotionKey>,
+    ) {
+        let mut redeemed = row.redeemed.clone();
+
+        let (participated, stacked) = match promotion_key {
+            Some(key) => {
+                // Only promotions some other promotion excludes need remembering.
+                if self.exclusions.excludes(key)
+                    && let Err(position) = redeemed.binary_search(&key)
+                {
+                    redeemed.insert(position, key);
+                }
+
+                (true, row.stacked.saturating_add(1).min(self.stacked_cap))
+            }
+            None => (row.participated, row.stacked),
+        };
+
+        let existing = outgoing.iter_mut().find(|other| {
+            other.original_basket_idx == row.original_basket_idx
+                && other.participated == participated
+                && other.stacked == stacked
+                && other.redeemed == redeemed
+                && other.item.price().to_minor_units() == price_minor
+        });
+
+        if let Some(other) = existing {
+            if let Some(activation) = other.activation.as_mut() {
+                *activation += expr;
+            }
+
+            return;
+        }
+
+        outgoing.push(JointRow {
+            original_basket_idx: row.original_basket_idx,
+            item: row
+                .item
+                .clone()
+                .with_price(Money::from_minor(price_minor, self.currency)),
+            basket_price: row.basket_price,
+            participated,
+            stacked,
+            redeemed,
+            activation: Some(expr),
+        });
+    }
+
+    /// Route leaving rows to successor nodes based on the node's output mode.
+    fn route_to_successors(
+        &mut self,
+        node_idx: NodeIndex,
+        output_mode: OutputMode,
+        rows: JointRows<'b>,
+    ) -> Result<(), GraphError> {
+        let edges: SmallVec<[(NodeIndex, LayerEdge); 2]> = self
+            .graph
+            .edges(node_idx)
+            .map(|e| (e.target(), *e.weight()))
+            .collect();
+
+        let target_for = |edge: LayerEdge| {
+            edges
+                .iter()
+                .find(|(_, weight)| *weight == edge)
+                .map(|(target, _)| *target)
+        };
+
+        match output_mode {
+            OutputMode::PassThrough => match target_for(LayerEdge::All) {
+                Some(target) => self.add_node(target, rows),
+                None => Ok(()),
+            },
+            OutputMode::Split => {
+                let (participating, non_participating): (JointRows<'b>, JointRows<'b>) =
+                    rows.into_iter().partition(|row| row.participated);
+
+                if let Some(target) = target_for(LayerEdge::Participating) {
+                    self.add_node(target, participating)?;
+                }
+
+                if let Some(target) = target_for(LayerEdge::NonParticipating) {
+                    self.add_node(target, non_participating)?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Translate a joint solution into per-item redemptions across all layers.
+fn build_joint_result<'b, S: Solution>(
+    layers: &[JointLayer<'_, 'b>],
+    solution: &S,
+    item_group: &ItemGroup<'b>,
+) -> Result<LayeredSolverResult<'b>, GraphError> {
+    let mut item_redemptions: FxHashMap<usize, SmallVec<[PromotionRedemption<'b>; 3]>> =
+        FxHashMap::default();
+
+    // Units of each line that received their first promotion, and the total
+    // change in price across all layers.
+    let mut redeemed_units: SmallVec<[u32; 10]> = SmallVec::from_elem(0, item_group.len());
+    let mut price_change_minor: i64 = 0;
+
+    let mut next_redemption_idx: usize = 0;
+
+    // Layers are stored in depth-first order, so each item's redemptions are
+    // appended in the order it passed through the layers.
+    for layer in layers {
+        let redemption_idx_offset = next_redemption_idx;
+        let mut layer_redemption_idx: usize = 0;
+        let mut max_redemption: Option<usize> = None;
+
+        for instance in layer.promotion_instances.iter() {
+            let redemptions = instance
+                .calculate_item_redemptions(solution, &layer.item_group, &mut layer_redemption_idx)
+                .map_err(|source| GraphError::Solver {
+                    layer_key: layer.key,
+                    source,
+                })?;
+
+            for redemption in redemptions {
+                let Some(&original_basket_idx) = layer.original_indices.get(redemption.item_idx)
+                else {
+                    continue;
+                };
+
+                max_redemption = Some(max_redemption.map_or(redemption.redemption_idx, |max| {
+                    max.max(redemption.redemption_idx)
+                }));
+
+                if !layer
+                    .participated
+                    .get(redemption.item_idx)
+                    .copied()
+                    .unwrap_or(true)
+                    && let Some(units) = redeemed_units.get_mut(original_basket_idx)
+                {
+                    *units = units.saturating_add(redemption.quantity);
+                }
+
+                price_change_minor = price_change_minor.saturating_add(
+                    (redemption.final_price.to_minor_units()
+                        - redemption.original_price.to_minor_units())
+                    .saturating_mul(i64::from(redemption.quantity)),
+                );
+
+                // Rows of the same line reaching a layer at the same price are
+                // reported as one redemption.
+                merge_redemption(
+                    item_redemptions.entry(original_basket_idx).or_default(),
+                    &PromotionRedemption {
+                        promotion_key: redemption.promotion_key,
+                        item_idx: original_basket_idx,
+                        redemption_idx: redemption
+                            .redemption_idx
+                            .saturating_add(redemption_idx_offset),
+                        original_price: redemption.original_price,
+                        final_price: redemption.final_price,
+                        quantity: redemption.quantity,
+                        funding: SmallVec::new(),
+                        floor_limited: redemption.floor_limited,
+                    },
+                );
+            }
+        }
+
+        if let Some(max) = max_redemption {
+            next_redemption_idx = redemption_idx_offset.saturating_add(max).saturating_add(1);
+        }
+    }
+
+    let mut total = Money::from_minor(price_change_minor, item_group.currency());
+    let mut full_price_items: SmallVec<[usize; 10]> = SmallVec::new();
+
+    for (idx, &units) in redeemed_units.iter().enumerate() {
+        let item = item_group.get_item(idx)?;
+
+        total = total.add(item.line_price()?)?;
+
+        if units < item.quantity() {
+            full_price_items.push(idx);
+        }
+    }
+
+    Ok(LayeredSolverResult {
+        total,
+        item_redemptions,
+        full_price_items,
+        coupon_codes: CouponCodeReport::default(),
+        quality: SolutionQuality::Optimal,
+    })
+}
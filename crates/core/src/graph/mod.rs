@@ -5,45 +5,98 @@
 //! Items flow between layers with updated prices, allowing discounts to stack
 //! across layers.
 
+use std::time::Duration;
+
+use jiff::Timestamp;
 use petgraph::{graph::NodeIndex, stable_graph::StableDiGraph};
-use rustc_hash::FxHashMap;
+use rustc_hash::{FxHashMap, FxHashSet};
 use rusty_money::Money;
 use smallvec::SmallVec;
 
 use self::{
     edge::LayerEdge,
-    evaluation::{TrackedItem, evaluate_node},
+    evaluation::{GreedyState, TrackedItem, evaluate_node},
+    joint::evaluate_joint,
     node::LayerNode,
 };
 use crate::{
+    basket::Basket,
+    context::EvaluationContext,
+    discounts::rounding::RoundingPolicies,
     items::groups::ItemGroup,
-    promotions::{Promotion, redemptions::PromotionRedemption},
-    solvers::ilp::ILPObserver,
+    promotions::{
+        Promotion, PromotionKey, budget::BudgetPools, coupon::CouponCodeReport,
+        exclusion::ExclusionRules, funding::attribute_funding, redemptions::PromotionRedemption,
+    },
+    solvers::{
+        SolutionQuality,
+        ilp::{
+            ILPObserver, ILPPromotion, ObjectiveMode, SolverBackend, SolverOptions, Stability,
+            TieBreakPolicy, cache::SolveCache, options::SolveRun, stability::StabilityScope,
+        },
+    },
 };
 
 pub mod builder;
 pub mod error;
+pub mod estimate;
+pub mod explain;
+pub mod refund;
 pub mod result;
+pub mod session;
+pub mod stacking;
 
 pub(crate) mod edge;
 pub(crate) mod node;
 
 pub use builder::PromotionGraphBuilder;
 pub use error::GraphError;
+pub use estimate::AdditionEstimate;
+pub use explain::{PromotionExplanation, PromotionOutcome};
 pub use node::{OutputMode, PromotionLayerKey};
+pub use refund::{
+    BudgetRelease, ItemRefund, PoolRelease, ReturnPricing, ReturnResult, ReturnedItem,
+};
 pub use result::LayeredSolverResult;
+pub use session::BasketSession;
+pub use stacking::StackingLimit;
 
 mod evaluation;
+mod joint;
+
+/// How a [`PromotionGraph`] is evaluated.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EvaluationMode {
+    /// Solve each layer's ILP in isolation, in graph order.
+    ///
+    /// Each layer picks its best allocation for the prices it receives, which is
+    /// fast but can block a better stacked result in later layers.
+    #[default]
+    Greedy,
+
+    /// Solve a single ILP spanning every layer so the final total is globally minimal.
+    ///
+    /// Items are modelled at every price they can reach each layer at, so the model
+    /// grows with the number of distinct discounted prices per item. Promotions in
+    /// layers with successors must describe their per-item prices (see
+    /// [`ILPPromotionVars::item_price_outcomes`](crate::solvers::ilp::ILPPromotionVars::item_price_outcomes));
+    /// bundle-total discounts can only be used in leaf layers.
+    Joint,
+}
 
 /// A validated promotion graph ready for evaluation.
 ///
 /// Wraps a directed acyclic graph where each node is a promotion layer.
 /// Items flow from the root node through the graph, accumulating discounts
 /// as they pass through each layer.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct PromotionGraph<'a> {
     graph: StableDiGraph<LayerNode<'a>, LayerEdge>,
     root: NodeIndex,
+    mode: EvaluationMode,
+    options: SolverOptions,
+    budget_pools: BudgetPools<'a>,
+    stacking: StackingLimit,
 }
 
 impl<'a> PromotionGraph<'a> {
@@ -53,9 +106,120 @@ impl<'a> PromotionGraph<'a> {
     ///
     /// Returns a [`GraphError`] if the graph fails validation.
     pub fn from_builder(builder: PromotionGraphBuilder<'a>) -> Result<Self, GraphError> {
-        let (graph, root) = builder.build()?;
+        let (graph, root, budget_pools) = builder.build()?;
+
+        Ok(Self {
+            graph,
+            root,
+            mode: EvaluationMode::default(),
+            options: SolverOptions::default(),
+            budget_pools,
+            stacking: StackingLimit::default(),
+        })
+    }
+
+    /// Set how the graph is evaluated.
+    #[must_use]
+    pub fn with_evaluation_mode(mut self, mode: EvaluationMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Return how the graph is evaluated.
+    pub fn evaluation_mode(&self) -> EvaluationMode {
+        self.mode
+    }
+
+    /// Set the MILP backend used to solve each layer (or the joint model).
+    #[must_use]
+    pub fn with_solver_backend(mut self, backend: SolverBackend) -> Self {
+        self.options.backend = backend;
+        self
+    }
+
+    /// Return the MILP backend used to solve the graph.
+    pub fn solver_backend(&self) -> SolverBackend {
+        self.options.backend
+    }
+
+    /// Limit each evaluation to `time_limit` of wall-clock time.
+    ///
+    /// The limit covers the whole evaluation: in greedy mode every layer solves
+    /// within what the earlier layers left over. A layer (or joint model) that runs
+    /// out of time uses the best allocation found so far, or leaves its items at
+    /// full price, and the result's
+    /// [`quality`](LayeredSolverResult::quality) records that it is not optimal.
+    #[must_use]
+    pub fn with_time_limit(mut self, time_limit: Duration) -> Self {
+        self.options.time_limit = Some(time_limit);
+        self
+    }
+
+    /// Return the wall-clock limit for each evaluation, if any.
+    pub fn time_limit(&self) -> Option<Duration> {
+        self.options.time_limit
+    }
+
+    /// Decide between allocations that give the same total with `tie_break`.
+    ///
+    /// The policy applies within each layer in greedy mode, and across the whole
+    /// graph in joint mode.
+    #[must_use]
+    pub fn with_tie_break(mut self, tie_break: TieBreakPolicy) -> Self {
+        self.options.tie_break = tie_break;
+        self
+    }
+
+    /// Return the tie-break policy used for each evaluation.
+    pub fn tie_break(&self) -> TieBreakPolicy {
+        self.options.tie_break
+    }
 
-        Ok(Self { graph, root })
+    /// Set what each layer optimises, unless the layer sets its own objective.
+    #[must_use]
+    pub fn with_objective(mut self, objective: ObjectiveMode) -> Self {
+        self.options.objective = objective;
+        self
+    }
+
+    /// Return what layers without their own objective optimise.
+    pub fn objective(&self) -> ObjectiveMode {
+        self.options.objective
+    }
+
+    /// Round percentage discounts with `rounding`, unless a promotion sets its own.
+    #[must_use]
+    pub fn with_rounding(mut self, rounding: impl Into<RoundingPolicies>) -> Self {
+        self.options.rounding = rounding.into();
+        self
+    }
+
+    /// Return the rounding used for promotions without their own policy.
+    pub fn rounding(&self) -> &RoundingPolicies {
+        &self.options.rounding
+    }
+
+    /// Cap what any item may accumulate across the graph's layers.
+    ///
+    /// Each layer only sees the headroom the earlier layers left: an item is not
+    /// discounted below its basket price less the largest total discount, and
+    /// an item already redeemed in the most promotions allowed passes later
+    /// layers at its current price. Layers may tighten the limit with
+    /// [`PromotionGraphBuilder::set_layer_stacking_limit`].
+    #[must_use]
+    pub fn with_stacking_limit(mut self, stacking: StackingLimit) -> Self {
+        self.stacking = stacking;
+        self
+    }
+
+    /// Return the stacking limit applied to every layer.
+    pub fn stacking_limit(&self) -> StackingLimit {
+        self.stacking
+    }
+
+    /// Return the shared budget pools available to each evaluation.
+    pub fn budget_pools(&self) -> &BudgetPools<'a> {
+        &self.budget_pools
     }
 
     /// Create a single-layer graph equivalent to the flat solver.
@@ -80,7 +244,8 @@ impl<'a> PromotionGraph<'a> {
     ///
     /// Starting from the root, each layer solves its ILP formulation and routes
     /// items to successor layers with updated prices. Returns the accumulated
-    /// result across all layers.
+    /// result across all layers. In [`EvaluationMode::Joint`] all layers are
+    /// solved together as one ILP instead.
     ///
     /// # Errors
     ///
@@ -93,6 +258,46 @@ impl<'a> PromotionGraph<'a> {
         self.evaluate_with_observer(item_group, None)
     }
 
+    /// Evaluate the promotion graph in a basket-level context.
+    ///
+    /// Same as [`evaluate()`](Self::evaluate), but qualification rules that test the
+    /// customer, channel, store, time or attributes are matched against `context`
+    /// instead of the item group's own context.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`GraphError`] if any layer's solver fails or if item group
+    /// construction fails.
+    pub fn evaluate_with_context<'b>(
+        &self,
+        item_group: &ItemGroup<'b>,
+        context: &EvaluationContext,
+    ) -> Result<LayeredSolverResult<'b>, GraphError> {
+        let item_group = item_group.clone().with_context(context.clone());
+
+        self.evaluate_with_observer(&item_group, None)
+    }
+
+    /// Evaluate the promotion graph at a given instant.
+    ///
+    /// Same as [`evaluate()`](Self::evaluate), but with the item group's context
+    /// timestamp set to `timestamp`, so promotion schedules and time window rules
+    /// are checked against that instant. Useful for replaying historical baskets.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`GraphError`] if any layer's solver fails or if item group
+    /// construction fails.
+    pub fn evaluate_at<'b>(
+        &self,
+        item_group: &ItemGroup<'b>,
+        timestamp: Timestamp,
+    ) -> Result<LayeredSolverResult<'b>, GraphError> {
+        let context = item_group.context().clone().with_timestamp(timestamp);
+
+        self.evaluate_with_context(item_group, &context)
+    }
+
     /// Evaluate the promotion graph with an observer.
     ///
     /// Same as [`evaluate()`](Self::evaluate), but passes an observer through to capture
@@ -106,6 +311,94 @@ impl<'a> PromotionGraph<'a> {
         &self,
         item_group: &ItemGroup<'b>,
         observer: Option<&mut dyn ILPObserver>,
+    ) -> Result<LayeredSolverResult<'b>, GraphError> {
+        self.evaluate_internal(item_group, observer, None, None)
+    }
+
+    /// Re-price the promotion graph with a preference for keeping a previous allocation.
+    ///
+    /// Same as [`evaluate()`](Self::evaluate), but each layer, once it knows its
+    /// cheapest total, keeps as many units as possible on the promotions
+    /// `stability` records for them, giving up at most the stability threshold per
+    /// solved model to do so. Items are identified by their index in `item_group`.
+    ///
+    /// [`Joint`](EvaluationMode::Joint) evaluations ignore the preference.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`GraphError`] if any layer's solver fails or if item group
+    /// construction fails.
+    pub fn evaluate_stable<'b>(
+        &self,
+        item_group: &ItemGroup<'b>,
+        stability: &Stability,
+    ) -> Result<LayeredSolverResult<'b>, GraphError> {
+        self.evaluate_internal(item_group, None, None, Some(stability))
+    }
+
+    /// Start an incremental pricing session for `basket`.
+    ///
+    /// See [`BasketSession`] for how re-pricing after each change reuses earlier work.
+    pub fn session<'b>(&self, basket: Basket<'b>) -> BasketSession<'_, 'a, 'b> {
+        BasketSession::new(self, basket)
+    }
+
+    /// Evaluate with an optional observer, reusing the solutions and formulations
+    /// kept in `cache` and
+    /// keeping to the allocation in `stability`.
+    ///
+    /// Only greedy evaluations use the cache or the stability preference; a joint
+    /// evaluation solves one model spanning every layer, so it is always built
+    /// afresh.
+    pub(crate) fn evaluate_internal<'b>(
+        &self,
+        item_group: &ItemGroup<'b>,
+        observer: Option<&mut dyn ILPObserver>,
+        cache: Option<&mut SolveCache<'b>>,
+        stability: Option<&Stability>,
+    ) -> Result<LayeredSolverResult<'b>, GraphError> {
+        let run = self
+            .options
+            .start()
+            .with_stability(stability.map(StabilityScope::new));
+
+        let mut result = match self.mode {
+            EvaluationMode::Greedy => self.evaluate_greedy(item_group, observer, run, cache)?,
+            EvaluationMode::Joint => {
+                let result = evaluate_joint(
+                    &self.graph,
+                    self.root,
+                    &self.budget_pools,
+                    self.stacking,
+                    item_group,
+                    observer,
+                    run,
+                )?;
+
+                // Nothing feasible was found in time. With the deadline gone, each
+                // layer solved on its own falls back to the greedy heuristic.
+                if result.quality == SolutionQuality::Fallback {
+                    self.evaluate_greedy(item_group, None, run, None)?
+                } else {
+                    result
+                }
+            }
+        };
+
+        result.coupon_codes = self.coupon_code_report(item_group.context(), &result);
+
+        self.attribute_funding(&mut result)?;
+
+        Ok(result)
+    }
+
+    /// Evaluate each layer in isolation, routing items between layers.
+    fn evaluate_greedy<'b>(
+        &self,
+        item_group: &ItemGroup<'b>,
+        observer: Option<&mut dyn ILPObserver>,
+        run: SolveRun<'_>,
+        cache: Option<&mut SolveCache<'b>>,
     ) -> Result<LayeredSolverResult<'b>, GraphError> {
         let currency = item_group.currency();
 
@@ -122,7 +415,17 @@ impl<'a> PromotionGraph<'a> {
             });
         }
 
-        let mut next_redemption_idx: usize = 0;
+        // Layers draw on shared budget pools in evaluation order.
+        let mut state = GreedyState {
+            next_redemption_idx: 0,
+            budget_pools: self.budget_pools.clone(),
+            run,
+            quality: SolutionQuality::Optimal,
+            cache,
+            stacking: self.stacking,
+            exclusions: exclusion_rules(&self.graph),
+            redeemed: FxHashSet::default(),
+        };
 
         // Evaluate the graph starting from the root
         let final_items = evaluate_node(
@@ -130,7 +433,8 @@ impl<'a> PromotionGraph<'a> {
             self.root,
             tracked_items,
             currency,
-            &mut next_redemption_idx,
+            item_group.context(),
+            &mut state,
             observer,
         )?;
 
@@ -143,12 +447,21 @@ impl<'a> PromotionGraph<'a> {
         let mut full_price_items: SmallVec<[usize; 10]> = SmallVec::new();
 
         for tracked in &final_items {
-            total = total.add(*tracked.item.price())?;
+            total = total.add(tracked.item.line_price()?)?;
 
             if tracked.redemptions.is_empty() {
-                full_price_items.push(tracked.original_basket_idx);
+                if !full_price_items.contains(&tracked.original_basket_idx) {
+                    full_price_items.push(tracked.original_basket_idx);
+                }
             } else {
-                item_redemptions.insert(tracked.original_basket_idx, tracked.redemptions.clone());
+                // Quantity lines split across layers are merged back per basket line.
+                let merged = item_redemptions
+                    .entry(tracked.original_basket_idx)
+                    .or_default();
+
+                for redemption in &tracked.redemptions {
+                    merge_redemption(merged, redemption);
+                }
             }
         }
 
@@ -156,8 +469,89 @@ impl<'a> PromotionGraph<'a> {
             total,
             item_redemptions,
             full_price_items,
+            coupon_codes: CouponCodeReport::default(),
+            quality: state.quality,
         })
     }
+
+    /// Classify the presented coupon codes against the graph's promotions.
+    fn coupon_code_report(
+        &self,
+        context: &EvaluationContext,
+        result: &LayeredSolverResult<'_>,
+    ) -> CouponCodeReport {
+        if context.codes().is_empty() {
+            return CouponCodeReport::default();
+        }
+
+        let redeemed: FxHashSet<PromotionKey> = result
+            .item_redemptions
+            .values()
+            .flatten()
+            .map(|redemption| redemption.promotion_key)
+            .collect();
+
+        let coupons = self
+            .graph
+            .node_weights()
+            .flat_map(|node| node.promotions.iter())
+            .filter_map(|promotion| {
+                promotion
+                    .coupon()
+                    .map(|coupon| (coupon, redeemed.contains(&promotion.key())))
+            });
+
+        CouponCodeReport::new(context, coupons)
+    }
+
+    /// Attribute each redemption's discount to the parties funding its promotion.
+    ///
+    /// Layers split and merge item lines, so funding is attributed once the
+    /// redemptions across every layer are known.
+    fn attribute_funding(&self, result: &mut LayeredSolverResult<'_>) -> Result<(), GraphError> {
+        let promotions: FxHashMap<PromotionKey, &dyn ILPPromotion> = self
+            .graph
+            .node_weights()
+            .flat_map(|node| node.promotions.iter())
+            .map(|promotion| (promotion.key(), &**promotion as &dyn ILPPromotion))
+            .collect();
+
+        attribute_funding(result.item_redemptions.values_mut().flatten(), |key| {
+            promotions.get(&key).copied()
+        })?;
+
+        Ok(())
+    }
+}
+
+/// Add `redemption` to `merged`, summing quantities with an identical entry.
+fn merge_redemption<'b>(
+    merged: &mut SmallVec<[PromotionRedemption<'b>; 3]>,
+    redemption: &PromotionRedemption<'b>,
+) {
+    let existing = merged.iter_mut().find(|candidate| {
+        candidate.promotion_key == redemption.promotion_key
+            && candidate.redemption_idx == redemption.redemption_idx
+            && candidate.original_price == redemption.original_price
+            && candidate.final_price == redemption.final_price
+            && candidate.floor_limited == redemption.floor_limited
+    });
+
+    match existing {
+        Some(candidate) => {
+            candidate.quantity = candidate.quantity.saturating_add(redemption.quantity);
+        }
+        None => merged.push(redemption.clone()),
+    }
+}
+
+/// Resolve the exclusion rules of every promotion in the graph against each other.
+fn exclusion_rules(graph: &StableDiGraph<LayerNode<'_>, LayerEdge>) -> ExclusionRules {
+    ExclusionRules::from_promotions(
+        graph
+            .node_weights()
+            .flat_map(|layer| layer.promotions.iter().map(AsRef::as_ref)),
+    )
 }
 
 #[cfg(test)]
@@ -490,4 +884,160 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn evaluation_mode_defaults_to_greedy() -> TestResult {
+        let graph = PromotionGraph::single_layer(std::iter::empty())?;
+
+        assert_eq!(graph.evaluation_mode(), EvaluationMode::Greedy);
+
+        let graph = graph.with_evaluation_mode(EvaluationMode::Joint);
+
+        assert_eq!(graph.evaluation_mode(), EvaluationMode::Joint);
+
+        Ok(())
+    }
+
+    #[test]
+    fn joint_mode_matches_greedy_when_layers_do_not_compete() -> TestResult {
+        let items = tagged_items();
+        let item_group = ItemGroup::new(items, GBP);
+
+        let mut keys = slotmap::SlotMap::<PromotionKey, ()>::with_key();
+        let k1 = keys.insert(());
+        let k2 = keys.insert(());
+        let k3 = keys.insert(());
+
+        let food_promo = make_promo(k1, &["food"], 0.50);
+        let loyalty_promo = make_promo(k2, &[], 0.10);
+        let coupon_promo = make_promo(k3, &[], 0.20);
+
+        let mut builder = PromotionGraphBuilder::new();
+        let root = builder.add_layer("Food Deals", [food_promo], OutputMode::Split)?;
+        let promoted = builder.add_layer("Loyalty", [loyalty_promo], OutputMode::PassThrough)?;
+        let unpromoted = builder.add_layer("Coupons", [coupon_promo], OutputMode::PassThrough)?;
+
+        builder.set_root(root);
+        builder.connect_split(root, promoted, unpromoted)?;
+
+        let graph = PromotionGraph::from_builder(builder)?;
+
+        let greedy = graph.evaluate(&item_group)?;
+
+        let graph = graph.with_evaluation_mode(EvaluationMode::Joint);
+        let joint = graph.evaluate(&item_group)?;
+
+        // Taking the food deal first is also globally optimal here:
+        // 1000 -> 500 -> 450, 300 -> 150 -> 135, and 500 -> 400 = 985
+        assert_eq!(greedy.total.to_minor_units(), 985);
+        assert_eq!(
+            joint.total.to_minor_units(),
+            greedy.total.to_minor_units(),
+            "joint should agree with greedy when greedy is already optimal"
+        );
+
+        for idx in 0..item_group.len() {
+            assert_eq!(
+                joint.item_redemptions.get(&idx).map(SmallVec::len),
+                greedy.item_redemptions.get(&idx).map(SmallVec::len),
+                "item {idx} should pass through the same number of layers"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn joint_mode_gives_up_upstream_discount_for_better_split_branch() -> TestResult {
+        let items = tagged_items();
+        let item_group = ItemGroup::new(items, GBP);
+
+        let mut keys = slotmap::SlotMap::<PromotionKey, ()>::with_key();
+        let k1 = keys.insert(());
+        let k2 = keys.insert(());
+
+        let food_promo = make_promo(k1, &["food"], 0.10); // 10% off food
+        let coupon_promo = make_promo(k2, &[], 0.50); // 50% off, unpromoted items only
+
+        let mut builder = PromotionGraphBuilder::new();
+        let root = builder.add_layer("Food Deals", [food_promo], OutputMode::Split)?;
+        let coupons = builder.add_layer("Coupons", [coupon_promo], OutputMode::PassThrough)?;
+
+        builder.set_root(root);
+        builder.connect_split_non_participating_only(root, coupons)?;
+
+        let graph = PromotionGraph::from_builder(builder)?;
+
+        // Greedy takes 10% off the food items, which then skip the coupon layer:
+        // 900 + 270 + (500 -> 250) = 1420
+        let greedy = graph.evaluate(&item_group)?;
+
+        assert_eq!(greedy.total.to_minor_units(), 1420);
+
+        // Jointly, every item skips the food deal and takes 50% off instead:
+        // 500 + 250 + 150 = 900
+        let graph = graph.with_evaluation_mode(EvaluationMode::Joint);
+        let joint = graph.evaluate(&item_group)?;
+
+        assert_eq!(joint.total.to_minor_units(), 900);
+
+        for idx in 0..item_group.len() {
+            let redemptions = joint.item_redemptions.get(&idx);
+
+            assert_eq!(
+                redemptions.map(SmallVec::len),
+                Some(1),
+                "item {idx} should only be redeemed by the coupon"
+            );
+            assert_eq!(
+                redemptions.and_then(|r| r.first()).map(|r| r.promotion_key),
+                Some(k2),
+                "item {idx} should be redeemed by the coupon"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn joint_mode_skips_layers_without_promotions() -> TestResult {
+        let items = tagged_items();
+        let item_group = ItemGroup::new(items, GBP);
+
+        let mut keys = slotmap::SlotMap::<PromotionKey, ()>::with_key();
+        let k1 = keys.insert(());
+        let k2 = keys.insert(());
+        let k3 = keys.insert(());
+
+        let food_promo = make_promo(k1, &["food"], 0.50);
+        let loyalty_promo = make_promo(k2, &[], 0.10);
+        let coupon_promo = make_promo(k3, &[], 0.20);
+
+        let mut builder = PromotionGraphBuilder::new();
+        let root = builder.add_layer("Food Deals", [food_promo], OutputMode::PassThrough)?;
+        let router = builder.add_layer(
+            "Router",
+            std::iter::empty::<Promotion<'static>>(),
+            OutputMode::Split,
+        )?;
+        let promoted = builder.add_layer("Loyalty", [loyalty_promo], OutputMode::PassThrough)?;
+        let unpromoted = builder.add_layer("Coupons", [coupon_promo], OutputMode::PassThrough)?;
+
+        builder.set_root(root);
+        builder.connect_pass_through(root, router)?;
+        builder.connect_split(router, promoted, unpromoted)?;
+
+        let graph =
+            PromotionGraph::from_builder(builder)?.with_evaluation_mode(EvaluationMode::Joint);
+
+        let result = graph.evaluate(&item_group)?;
+
+        assert_eq!(
+            result.total.to_minor_units(),
+            985,
+            "routing-only layers should carry prior participation"
+        );
+
+        Ok(())
+    }
 }
@@ -4,7 +4,7 @@ use serde::Deserialize;
 use slotmap::new_key_type;
 use smallvec::SmallVec;
 
-use crate::promotions::Promotion;
+use crate::{graph::StackingLimit, promotions::Promotion, solvers::ilp::ObjectiveMode};
 
 /// How items are routed to successor nodes after solving a layer.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
@@ -38,4 +38,10 @@ pub(crate) struct LayerNode<'a> {
 
     /// How items are routed to successor nodes
     pub output_mode: OutputMode,
+
+    /// What the layer optimises, if not the graph's objective
+    pub objective: Option<ObjectiveMode>,
+
+    /// Stacking caps for items entering the layer, on top of the graph's
+    pub stacking: StackingLimit,
 }
@@ -0,0 +1,810 @@
+//! Returns and refunds
+//!
+//! When a customer brings back part of a basket, the items they keep are priced
+//! again without the returned ones. Anything the kept items no longer save (a
+//! bundle that is now broken, a threshold that is no longer met) is clawed back
+//! from the refund of the returned items that earned it.
+
+use std::num::NonZeroU32;
+
+use rustc_hash::{FxHashMap, FxHashSet};
+use rusty_money::{Money, iso::Currency};
+use smallvec::SmallVec;
+
+use crate::{
+    graph::{PromotionGraph, error::GraphError, result::LayeredSolverResult},
+    items::groups::{ItemGroup, ItemGroupError},
+    promotions::{PromotionKey, budget::BudgetPoolKey, redemptions::PromotionRedemption},
+    receipt::Receipt,
+    solvers::greedy::promotions::allocate_total,
+};
+
+/// Redemptions of a sale, per original basket index.
+type SaleRedemptions<'b> = FxHashMap<usize, SmallVec<[PromotionRedemption<'b>; 3]>>;
+
+/// Which promotions the kept items are priced with
+#[derive(Debug, Clone, Copy, Default)]
+pub enum ReturnPricing<'g, 'p> {
+    /// Every promotion in the graph the return is priced on
+    #[default]
+    Current,
+
+    /// Only the promotions the original sale redeemed, as they are defined in
+    /// the given graph: the one the sale was evaluated against. The kept items
+    /// never pick up an offer they did not get at the till, and a promotion
+    /// edited since the sale is priced as it was then.
+    Frozen(&'g PromotionGraph<'p>),
+}
+
+/// Units of a basket line brought back
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReturnedItem {
+    /// Index of the line in the original basket
+    pub item_idx: usize,
+
+    /// Units brought back, or `None` for the whole line
+    pub quantity: Option<NonZeroU32>,
+}
+
+impl ReturnedItem {
+    /// Return every unit on a line.
+    #[must_use]
+    pub fn line(item_idx: usize) -> Self {
+        Self {
+            item_idx,
+            quantity: None,
+        }
+    }
+
+    /// Return some of the units on a line.
+    #[must_use]
+    pub fn units(item_idx: usize, quantity: NonZeroU32) -> Self {
+        Self {
+            item_idx,
+            quantity: Some(quantity),
+        }
+    }
+}
+
+impl From<usize> for ReturnedItem {
+    fn from(item_idx: usize) -> Self {
+        Self::line(item_idx)
+    }
+}
+
+/// Refund owed for the units returned from one line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ItemRefund<'a> {
+    /// Index of the line in the original basket
+    pub item_idx: usize,
+
+    /// Units returned from the line
+    pub quantity: u32,
+
+    /// What the customer paid for the returned units in the original sale: the
+    /// whole line, or its share of the line in proportion to units
+    pub paid: Money<'a, Currency>,
+
+    /// Discount the kept items lose, charged against this line's refund
+    ///
+    /// Negative when the units kept from a partly returned line now cost less
+    /// than their share of what was paid for it.
+    pub clawback: Money<'a, Currency>,
+
+    /// Amount refunded: what was paid less the clawback
+    pub refund: Money<'a, Currency>,
+}
+
+/// Budget a promotion no longer uses once the return is priced
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BudgetRelease<'a> {
+    /// Promotion whose usage changed
+    pub promotion_key: PromotionKey,
+
+    /// Redemptions given back (negative if the kept items redeem more)
+    pub redemptions: i64,
+
+    /// Discount given back (negative if the kept items save more)
+    pub savings: Money<'a, Currency>,
+}
+
+/// Budget a shared pool no longer uses once the return is priced
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolRelease<'a> {
+    /// Pool whose usage changed
+    pub pool_key: BudgetPoolKey,
+
+    /// Redemptions given back (negative if the kept items redeem more)
+    pub redemptions: i64,
+
+    /// Discount given back (negative if the kept items save more)
+    pub savings: Money<'a, Currency>,
+}
+
+/// Outcome of pricing a return against the original sale
+#[derive(Debug, Clone)]
+pub struct ReturnResult<'a> {
+    /// The kept items priced again, keyed by their original basket indices
+    pub kept: LayeredSolverResult<'a>,
+
+    /// One refund per returned line, in basket order
+    pub refunds: Vec<ItemRefund<'a>>,
+
+    /// Sum of the refunds
+    pub total_refund: Money<'a, Currency>,
+
+    /// Promotions whose budget usage changed, in graph order
+    pub budget_releases: Vec<BudgetRelease<'a>>,
+
+    /// Shared budget pools whose usage changed, in pool order
+    pub pool_releases: Vec<PoolRelease<'a>>,
+}
+
+/// Distinct redemptions and total savings of one promotion (or pool).
+#[derive(Debug, Default, Clone, Copy)]
+struct Usage {
+    redemptions: i64,
+    savings: i64,
+}
+
+/// Units returned from one line, out of the line's quantity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ReturnedUnits {
+    item_idx: usize,
+    units: u32,
+    quantity: u32,
+}
+
+impl ReturnedUnits {
+    fn kept(self) -> u32 {
+        self.quantity - self.units
+    }
+}
+
+impl<'a> PromotionGraph<'a> {
+    /// Price the return of items from a sale evaluated against this graph.
+    ///
+    /// `item_group` must be the basket as it was evaluated for `original`,
+    /// context included, and `returned` lists the lines brought back, as
+    /// indices for whole lines or [`ReturnedItem::units`] for part of a
+    /// quantity line. The remaining units are evaluated again with
+    /// [`evaluate()`](Self::evaluate).
+    ///
+    /// A change in what a kept line costs is charged to the returned lines that
+    /// shared a redemption with it, in proportion to their full prices, or to
+    /// every returned line if none did. A returned line's clawback is capped at
+    /// what was paid for it, so no refund is negative. Kept lines that become
+    /// cheaper do not add to the refund, except for the units kept from a partly
+    /// returned line, whose change in either direction is settled with the
+    /// units returned from it.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`GraphError`] if a returned index is not in `item_group`, if
+    /// more units are returned from a line than it holds, if a promotion the
+    /// sale redeemed is missing from a [`ReturnPricing::Frozen`] graph, or if
+    /// evaluating the kept lines fails.
+    pub fn price_return<'b, R: Into<ReturnedItem>>(
+        &self,
+        item_group: &ItemGroup<'b>,
+        original: &LayeredSolverResult<'b>,
+        returned: impl IntoIterator<Item = R>,
+        pricing: ReturnPricing<'_, 'a>,
+    ) -> Result<ReturnResult<'b>, GraphError> {
+        self.price_sale_return(item_group, &original.item_redemptions, returned, pricing)
+    }
+
+    /// Price the return of items from a receipt.
+    ///
+    /// Same as [`price_return()`](Self::price_return), with the original sale
+    /// read from its receipt.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`GraphError`] if a returned index is not in `item_group`, if
+    /// more units are returned from a line than it holds, if a promotion the
+    /// sale redeemed is missing from a [`ReturnPricing::Frozen`] graph, or if
+    /// evaluating the kept lines fails.
+    pub fn price_receipt_return<'b, R: Into<ReturnedItem>>(
+        &self,
+        item_group: &ItemGroup<'b>,
+        receipt: &Receipt<'b>,
+        returned: impl IntoIterator<Item = R>,
+        pricing: ReturnPricing<'_, 'a>,
+    ) -> Result<ReturnResult<'b>, GraphError> {
+        self.price_sale_return(
+            item_group,
+            receipt.promotion_redemptions(),
+            returned,
+            pricing,
+        )
+    }
+
+    fn price_sale_return<'b, R: Into<ReturnedItem>>(
+        &self,
+        item_group: &ItemGroup<'b>,
+        sale: &SaleRedemptions<'b>,
+        returned: impl IntoIterator<Item = R>,
+        pricing: ReturnPricing<'_, 'a>,
+    ) -> Result<ReturnResult<'b>, GraphError> {
+        let currency = item_group.currency();
+        let returned = returned_units(item_group, returned)?;
+
+        let mut kept_indices = Vec::with_capacity(item_group.len());
+        let mut kept_items = SmallVec::with_capacity(item_group.len());
+
+        for (item_idx, item) in item_group.iter().enumerate() {
+            let kept_units = match find_returned(&returned, item_idx) {
+                Some(line) => line.kept(),
+                None => item.quantity(),
+            };
+
+            if let Some(kept_units) = NonZeroU32::new(kept_units) {
+                kept_indices.push(item_idx);
+                kept_items.push(item.clone().with_quantity(kept_units));
+            }
+        }
+
+        let kept_group =
+            ItemGroup::new(kept_items, currency).with_context(item_group.context().clone());
+
+        let frozen = match pricing {
+            ReturnPricing::Current => None,
+            ReturnPricing::Frozen(sale_graph) => Some(sale_graph.frozen_to(sale)?),
+        };
+
+        let pricing_graph = frozen.as_ref().unwrap_or(self);
+        let kept = remap_kept(pricing_graph.evaluate(&kept_group)?, &kept_indices)?;
+
+        let clawbacks = clawbacks(item_group, sale, &kept.item_redemptions, &returned)?;
+
+        let mut refunds = Vec::with_capacity(returned.len());
+        let mut total_refund = 0;
+
+        for (line, clawback) in returned.iter().zip(clawbacks) {
+            let line_paid = paid_minor(item_group, line.item_idx, line.quantity, sale)?;
+            let (paid, kept_share) = split_paid(line_paid, line);
+            let clawback = clawback.clamp(-kept_share.max(0), paid.max(0));
+            let refund = paid - clawback;
+
+            total_refund += refund;
+
+            refunds.push(ItemRefund {
+                item_idx: line.item_idx,
+                quantity: line.units,
+                paid: Money::from_minor(paid, currency),
+                clawback: Money::from_minor(clawback, currency),
+                refund: Money::from_minor(refund, currency),
+            });
+        }
+
+        let (budget_releases, pool_releases) =
+            pricing_graph.budget_releases(sale, &kept, currency)?;
+
+        Ok(ReturnResult {
+            kept,
+            refunds,
+            total_refund: Money::from_minor(total_refund, currency),
+            budget_releases,
+            pool_releases,
+        })
+    }
+
+    /// This graph with only the promotions redeemed in `sale` left in its layers.
+    fn frozen_to(&self, sale: &SaleRedemptions<'_>) -> Result<Self, GraphError> {
+        let redeemed: FxHashSet<PromotionKey> = sale
+            .values()
+            .flatten()
+            .map(|redemption| redemption.promotion_key)
+            .collect();
+
+        let mut frozen = self.clone();
+        let mut found = FxHashSet::default();
+
+        for node in frozen.graph.node_weights_mut() {
+            node.promotions
+                .retain(|promotion| redeemed.contains(&promotion.key()));
+
+            found.extend(node.promotions.iter().map(|promotion| promotion.key()));
+        }
+
+        if let Some(&missing) = redeemed.iter().find(|key| !found.contains(key)) {
+            return Err(GraphError::FrozenPromotionMissing(missing));
+        }
+
+        Ok(frozen)
+    }
+
+    /// Budget each promotion and shared pool no longer uses after the return.
+    fn budget_releases<'b>(
+        &self,
+        sale: &SaleRedemptions<'b>,
+        kept: &LayeredSolverResult<'b>,
+        currency: &'b Currency,
+    ) -> Result<(Vec<BudgetRelease<'b>>, Vec<PoolRelease<'b>>), GraphError> {
+        let before = usage_by_promotion(sale)?;
+        let after = usage_by_promotion(&kept.item_redemptions)?;
+
+        let mut budget_releases = Vec::new();
+        let mut pool_usage: FxHashMap<BudgetPoolKey, Usage> = FxHashMap::default();
+
+        for promotion in self.unique_promotions() {
+            let key = promotion.key();
+            let before = before.get(&key).copied().unwrap_or_default();
+            let after = after.get(&key).copied().unwrap_or_default();
+
+            let released = Usage {
+                redemptions: before.redemptions - after.redemptions,
+                savings: before.savings - after.savings,
+            };
+
+            if released.redemptions == 0 && released.savings == 0 {
+                continue;
+            }
+
+            for &pool_key in promotion.budget_pools() {
+                let pool = pool_usage.entry(pool_key).or_default();
+
+                pool.redemptions += released.redemptions;
+                pool.savings += released.savings;
+            }
+
+            budget_releases.push(BudgetRelease {
+                promotion_key: key,
+                redemptions: released.redemptions,
+                savings: Money::from_minor(released.savings, currency),
+            });
+        }
+
+        let pool_releases = self
+            .budget_pools
+            .keys()
+            .filter_map(|pool_key| {
+                let released = pool_usage.get(&pool_key)?;
+
+                (released.redemptions != 0 || released.savings != 0).then(|| PoolRelease {
+                    pool_key,
+                    redemptions: released.redemptions,
+                    savings: Money::from_minor(released.savings, currency),
+                })
+            })
+            .collect();
+
+        Ok((budget_releases, pool_releases))
+    }
+}
+
+/// Units returned from each line, in basket order.
+///
+/// A line listed more than once has its units added up, and a whole-line
+/// return covers every unit.
+fn returned_units<R: Into<ReturnedItem>>(
+    item_group: &ItemGroup<'_>,
+    returned: impl IntoIterator<Item = R>,
+) -> Result<Vec<ReturnedUnits>, GraphError> {
+    let mut lines: Vec<ReturnedUnits> = Vec::new();
+
+    for returned in returned {
+        let ReturnedItem { item_idx, quantity } = returned.into();
+        let line_quantity = item_group.get_item(item_idx)?.quantity();
+        let units = quantity.map_or(line_quantity, NonZeroU32::get);
+
+        let position = match lines.binary_search_by_key(&item_idx, |line| line.item_idx) {
+            Ok(position) => position,
+            Err(position) => {
+                lines.insert(
+                    position,
+                    ReturnedUnits {
+                        item_idx,
+                        units: 0,
+                        quantity: line_quantity,
+                    },
+                );
+
+                position
+            }
+        };
+
+        if let Some(line) = lines.get_mut(position) {
+            line.units = if quantity.is_none() {
+                line_quantity
+            } else {
+                line.units.saturating_add(units)
+            };
+
+            if line.units > line_quantity {
+                return Err(GraphError::ReturnExceedsQuantity {
+                    item_idx,
+                    returned: line.units,
+                    quantity: line_quantity,
+                });
+            }
+        }
+    }
+
+    Ok(lines)
+}
+
+/// Units returned from a line, if any were.
+fn find_returned(returned: &[ReturnedUnits], item_idx: usize) -> Option<ReturnedUnits> {
+    let position = returned
+        .binary_search_by_key(&item_idx, |line| line.item_idx)
+        .ok()?;
+
+    returned.get(position).copied()
+}
+
+/// Point the kept result's indices back at the original basket.
+fn remap_kept<'b>(
+    mut kept: LayeredSolverResult<'b>,
+    kept_indices: &[usize],
+) -> Result<LayeredSolverResult<'b>, GraphError> {
+    let original_idx = |idx: usize| {
+        kept_indices
+            .get(idx)
+            .copied()
+            .ok_or(GraphError::ItemGroup(ItemGroupError::ItemNotFound(idx)))
+    };
+
+    kept.item_redemptions = kept
+        .item_redemptions
+        .into_iter()
+        .map(|(idx, mut redemptions)| {
+            for redemption in &mut redemptions {
+                redemption.item_idx = original_idx(redemption.item_idx)?;
+            }
+
+            Ok((original_idx(idx)?, redemptions))
+        })
+        .collect::<Result<_, GraphError>>()?;
+
+    for idx in &mut kept.full_price_items {
+        *idx = original_idx(*idx)?;
+    }
+
+    Ok(kept)
+}
+
+/// What was paid for `units` units of a line: their full price less the savings
+/// of every redemption in `redemptions` on the line.
+fn paid_minor(
+    item_group: &ItemGroup<'_>,
+    item_idx: usize,
+    units: u32,
+    redemptions: &SaleRedemptions<'_>,
+) -> Result<i64, GraphError> {
+    let mut paid = item_group
+        .get_item(item_idx)?
+        .price()
+        .to_minor_units()
+        .saturating_mul(i64::from(units));
+
+    for redemption in redemptions.get(&item_idx).into_iter().flatten() {
+        paid -= redemption.total_savings()?.to_minor_units();
+    }
+
+    Ok(paid)
+}
+
+/// Split what was paid for a whole line between its returned and kept units, in
+/// proportion to units.
+fn split_paid(line_paid: i64, line: &ReturnedUnits) -> (i64, i64) {
+    let units = [(0, i64::from(line.units)), (1, i64::from(line.kept()))];
+
+    match allocate_total(&units, line_paid).as_slice() {
+        &[returned, kept] => (returned, kept),
+        _ => (line_paid, 0),
+    }
+}
+
+/// Change in what each kept line costs, charged to the returned lines.
+///
+/// Returns one clawback per entry of `returned`, in minor units.
+fn clawbacks(
+    item_group: &ItemGroup<'_>,
+    sale: &SaleRedemptions<'_>,
+    kept: &SaleRedemptions<'_>,
+    returned: &[ReturnedUnits],
+) -> Result<Vec<i64>, GraphError> {
+    // Returned lines in each redemption of the sale.
+    let mut returned_by_redemption: FxHashMap<usize, SmallVec<[usize; 4]>> = FxHashMap::default();
+
+    for (position, line) in returned.iter().enumerate() {
+        for redemption in sale.get(&line.item_idx).into_iter().flatten() {
+            let lines = returned_by_redemption
+                .entry(redemption.redemption_idx)
+                .or_default();
+
+            if !lines.contains(&position) {
+                lines.push(position);
+            }
+        }
+    }
+
+    let weights = returned
+        .iter()
+        .map(|line| {
+            Ok(item_group
+                .get_item(line.item_idx)?
+                .price()
+                .to_minor_units()
+                .saturating_mul(i64::from(line.units)))
+        })
+        .collect::<Result<Vec<i64>, GraphError>>()?;
+
+    let mut clawbacks = vec![0_i64; returned.len()];
+
+    for (item_idx, item) in item_group.iter().enumerate() {
+        let partly_returned = returned
+            .binary_search_by_key(&item_idx, |line| line.item_idx)
+            .ok();
+
+        let (kept_units, sale_share) = match partly_returned.and_then(|p| returned.get(p)) {
+            Some(line) if line.kept() == 0 => continue,
+            Some(line) => (
+                line.kept(),
+                split_paid(paid_minor(item_group, item_idx, line.quantity, sale)?, line).1,
+            ),
+            None => (
+                item.quantity(),
+                paid_minor(item_group, item_idx, item.quantity(), sale)?,
+            ),
+        };
+
+        let change = paid_minor(item_group, item_idx, kept_units, kept)? - sale_share;
+
+        if change == 0 {
+            continue;
+        }
+
+        // Units kept from a partly returned line settle with the units returned from it.
+        if let Some(clawback) = partly_returned.and_then(|position| clawbacks.get_mut(position)) {
+            *clawback += change;
+
+            continue;
+        }
+
+        let mut partners: SmallVec<[usize; 4]> = SmallVec::new();
+
+        for redemption in sale.get(&item_idx).into_iter().flatten() {
+            for &position in returned_by_redemption
+                .get(&redemption.redemption_idx)
+                .into_iter()
+                .flatten()
+            {
+                if !partners.contains(&position) {
+                    partners.push(position);
+                }
+            }
+        }
+
+        if partners.is_empty() {
+            partners.extend(0..returned.len());
+        }
+
+        partners.sort_unstable();
+
+        let units: Vec<(usize, i64)> = partners
+            .iter()
+            .map(|&position| (position, weights.get(position).copied().unwrap_or(0)))
+            .collect();
+
+        for (&(position, _weight), share) in units.iter().zip(allocate_total(&units, change)) {
+            if let Some(clawback) = clawbacks.get_mut(position) {
+                *clawback += share;
+            }
+        }
+    }
+
+    Ok(clawbacks)
+}
+
+/// Distinct redemptions and total savings of each promotion in `redemptions`.
+fn usage_by_promotion(
+    redemptions: &SaleRedemptions<'_>,
+) -> Result<FxHashMap<PromotionKey, Usage>, GraphError> {
+    let mut seen: FxHashSet<usize> = FxHashSet::default();
+    let mut usage: FxHashMap<PromotionKey, Usage> = FxHashMap::default();
+
+    for redemption in redemptions.values().flatten() {
+        let entry = usage.entry(redemption.promotion_key).or_default();
+
+        if seen.insert(redemption.redemption_idx) {
+            entry.redemptions += 1;
+        }
+
+        entry.savings += redemption.total_savings()?.to_minor_units();
+    }
+
+    Ok(usage)
+}
+
+#[cfg(test)]
+mod tests {
+    use rusty_money::iso::GBP;
+    use slotmap::SlotMap;
+    use smallvec::smallvec;
+    use testresult::TestResult;
+
+    use crate::{
+        items::Item, products::ProductKey, promotions::coupon::CouponCodeReport,
+        solvers::SolutionQuality,
+    };
+
+    use super::*;
+
+    fn item_group(prices: &[i64]) -> ItemGroup<'static> {
+        let items: SmallVec<[Item<'static>; 10]> = prices
+            .iter()
+            .map(|&price| Item::new(ProductKey::default(), Money::from_minor(price, GBP)))
+            .collect();
+
+        ItemGroup::new(items, GBP)
+    }
+
+    fn redemption(
+        promotion_key: PromotionKey,
+        item_idx: usize,
+        redemption_idx: usize,
+        prices: (i64, i64),
+    ) -> PromotionRedemption<'static> {
+        PromotionRedemption {
+            promotion_key,
+            item_idx,
+            redemption_idx,
+            original_price: Money::from_minor(prices.0, GBP),
+            final_price: Money::from_minor(prices.1, GBP),
+            quantity: 1,
+            funding: SmallVec::new(),
+            floor_limited: false,
+        }
+    }
+
+    #[test]
+    fn clawback_falls_on_returned_lines_sharing_the_redemption() -> TestResult {
+        let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+        let key = keys.insert(());
+
+        let item_group = item_group(&[100, 300, 200]);
+
+        let mut sale = SaleRedemptions::default();
+        sale.insert(1, smallvec![redemption(key, 1, 0, (300, 270))]);
+        sale.insert(2, smallvec![redemption(key, 2, 0, (200, 160))]);
+
+        let returned = returned_units(&item_group, [0, 1])?;
+        let clawbacks = clawbacks(&item_group, &sale, &SaleRedemptions::default(), &returned)?;
+
+        assert_eq!(clawbacks, [0, 40]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn unshared_clawback_is_spread_by_full_price() -> TestResult {
+        let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+        let key = keys.insert(());
+
+        let item_group = item_group(&[100, 300, 200]);
+
+        let mut sale = SaleRedemptions::default();
+        sale.insert(2, smallvec![redemption(key, 2, 4, (200, 160))]);
+
+        let returned = returned_units(&item_group, [0, 1])?;
+        let clawbacks = clawbacks(&item_group, &sale, &SaleRedemptions::default(), &returned)?;
+
+        assert_eq!(clawbacks, [10, 30]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn usage_counts_each_redemption_once() -> TestResult {
+        let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+        let key = keys.insert(());
+
+        let mut sale = SaleRedemptions::default();
+        sale.insert(0, smallvec![redemption(key, 0, 3, (100, 0))]);
+        sale.insert(1, smallvec![redemption(key, 1, 3, (100, 100))]);
+        sale.insert(2, smallvec![redemption(key, 2, 5, (100, 50))]);
+
+        let usage = usage_by_promotion(&sale)?;
+        let usage = usage.get(&key).copied().unwrap_or_default();
+
+        assert_eq!(usage.redemptions, 2);
+        assert_eq!(usage.savings, 150);
+
+        Ok(())
+    }
+
+    #[test]
+    fn returned_units_add_up_per_line() -> TestResult {
+        let three = NonZeroU32::new(3).ok_or("non-zero quantity")?;
+        let one = NonZeroU32::MIN;
+
+        let item_group = ItemGroup::new(
+            smallvec![
+                Item::new(ProductKey::default(), Money::from_minor(100, GBP)).with_quantity(three),
+                Item::new(ProductKey::default(), Money::from_minor(200, GBP)),
+            ],
+            GBP,
+        );
+
+        let returned = returned_units(
+            &item_group,
+            [
+                ReturnedItem::line(1),
+                ReturnedItem::units(0, one),
+                ReturnedItem::units(0, one),
+            ],
+        )?;
+
+        assert_eq!(
+            returned,
+            [
+                ReturnedUnits {
+                    item_idx: 0,
+                    units: 2,
+                    quantity: 3
+                },
+                ReturnedUnits {
+                    item_idx: 1,
+                    units: 1,
+                    quantity: 1
+                },
+            ]
+        );
+
+        let too_many = returned_units(&item_group, [ReturnedItem::units(1, three)]);
+
+        assert!(
+            matches!(
+                too_many,
+                Err(GraphError::ReturnExceedsQuantity {
+                    item_idx: 1,
+                    returned: 3,
+                    quantity: 1
+                })
+            ),
+            "expected ReturnExceedsQuantity, got {too_many:?}"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn remap_points_kept_indices_at_the_original_basket() -> TestResult {
+        let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+        let key = keys.insert(());
+
+        let mut item_redemptions = FxHashMap::default();
+        item_redemptions.insert(1, smallvec![redemption(key, 1, 0, (100, 50))]);
+
+        let kept = LayeredSolverResult {
+            total: Money::from_minor(150, GBP),
+            item_redemptions,
+            full_price_items: smallvec![0],
+            coupon_codes: CouponCodeReport::default(),
+            quality: SolutionQuality::default(),
+        };
+
+        let remapped = remap_kept(kept.clone(), &[2, 4])?;
+
+        assert_eq!(remapped.full_price_items.as_slice(), &[2]);
+        assert_eq!(
+            remapped
+                .item_redemptions
+                .get(&4)
+                .and_then(|redemptions| redemptions.first())
+                .map(|redemption| redemption.item_idx),
+            Some(4)
+        );
+
+        // An index past the kept lines is an error rather than left as it was.
+        assert!(matches!(
+            remap_kept(kept, &[2]),
+            Err(GraphError::ItemGroup(ItemGroupError::ItemNotFound(1)))
+        ));
+
+        Ok(())
+    }
+}
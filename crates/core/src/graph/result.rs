@@ -4,7 +4,10 @@ use rustc_hash::FxHashMap;
 use rusty_money::{Money, iso::Currency};
 use smallvec::SmallVec;
 
-use crate::promotions::redemptions::PromotionRedemption;
+use crate::{
+    promotions::{PromotionKey, coupon::CouponCodeReport, redemptions::PromotionRedemption},
+    solvers::SolutionQuality,
+};
 
 /// Result of evaluating a promotion graph across all layers.
 ///
@@ -16,9 +19,39 @@ pub struct LayeredSolverResult<'a> {
     pub total: Money<'a, Currency>,
 
     /// Per original-basket-index: ordered list of promotion redemptions
-    /// (one per layer that touched this item)
+    /// (one per layer and price that touched this item line)
     pub item_redemptions: FxHashMap<usize, SmallVec<[PromotionRedemption<'a>; 3]>>,
 
     /// Original basket indices of items that received no promotion in any layer
+    ///
+    /// A quantity line with only some units redeemed appears here as well as in
+    /// `item_redemptions`.
     pub full_price_items: SmallVec<[usize; 10]>,
+
+    /// Which presented coupon codes were used, gave no benefit, or are unknown
+    pub coupon_codes: CouponCodeReport,
+
+    /// Whether the allocation is proven optimal or was cut short by a time limit
+    ///
+    /// In greedy evaluation this is the worst quality of any layer.
+    pub quality: SolutionQuality,
+}
+
+impl LayeredSolverResult<'_> {
+    /// Promotions that an item's price floor kept from giving their full discount,
+    /// in key order.
+    pub fn floor_limited_promotions(&self) -> SmallVec<[PromotionKey; 5]> {
+        let mut keys: SmallVec<[PromotionKey; 5]> = self
+            .item_redemptions
+            .values()
+            .flatten()
+            .filter(|redemption| redemption.floor_limited)
+            .map(|redemption| redemption.promotion_key)
+            .collect();
+
+        keys.sort_unstable();
+        keys.dedup();
+
+        keys
+    }
 }
@@ -0,0 +1,569 @@
+//! Basket sessions
+//!
+//! A till re-prices the basket after every scan. A session keeps the basket
+//! together with what earlier evaluations worked out, so each re-price only
+//! solves the parts of the basket the change touched.
+
+use std::num::NonZeroU32;
+
+use rusty_money::{Money, iso::Currency};
+
+use crate::{
+    basket::{Basket, BasketError},
+    context::EvaluationContext,
+    graph::{PromotionGraph, error::GraphError, result::LayeredSolverResult},
+    items::{Item, groups::ItemGroup},
+    solvers::ilp::{Allocation, CacheStats, Stability, cache::SolveCache},
+};
+
+/// A basket being built up item by item, priced incrementally against a graph.
+///
+/// Every evaluation prices the basket exactly as
+/// [`PromotionGraph::evaluate`] would, but reuses earlier work:
+///
+/// - Evaluating again without changing the basket returns the previous result.
+/// - Each layer is split into independent sub-baskets (see the README's
+///   "Decomposition" section), and a sub-basket whose items and promotions are
+///   the same as in one of the last two evaluations takes its previous solution
+///   instead of building and solving its model again. Adding a drink to a basket
+///   only re-solves the drinks, and removing it again re-solves nothing.
+/// - Which promotions each item qualifies for is only worked out once per item.
+///
+/// - A sub-basket whose solution cannot be reused, because it draws on a shared
+///   budget pool or has items kept off a promotion by an exclusion with an
+///   earlier layer, is solved again from its previous formulation rather than
+///   formulated again. If the pools' balances are also unchanged its previous
+///   first pass is kept, since the solver would find it again; otherwise that
+///   solution is the solver's starting point (used by HiGHS; microlp cannot take
+///   one).
+///
+/// Every layer of a [`Joint`](crate::graph::EvaluationMode::Joint) evaluation is
+/// formulated and solved afresh each time. Solutions cut short by a time limit
+/// are never reused.
+///
+/// With [`with_stability()`](Self::with_stability) each evaluation prefers the
+/// allocation of the one before it, so promotions do not jump between items on
+/// the till display for a marginal saving. Every model is then solved again,
+/// since its result depends on what was shown before, but unchanged sub-baskets
+/// still reuse their formulation and first pass.
+#[derive(Debug)]
+pub struct BasketSession<'g, 'a, 'b> {
+    graph: &'g PromotionGraph<'a>,
+    basket: Basket<'b>,
+    context: EvaluationContext,
+    cache: SolveCache<'b>,
+
+    /// Preference for the allocation of the previous evaluation, if enabled
+    stability: Option<Stability>,
+
+    /// Result of the last evaluation, kept until the basket next changes
+    last_result: Option<LayeredSolverResult<'b>>,
+}
+
+impl<'g, 'a, 'b> BasketSession<'g, 'a, 'b> {
+    /// Start a session pricing `basket` against `graph`.
+    #[must_use]
+    pub fn new(graph: &'g PromotionGraph<'a>, basket: Basket<'b>) -> Self {
+        Self {
+            graph,
+            basket,
+            context: EvaluationContext::default(),
+            cache: SolveCache::default(),
+            stability: None,
+            last_result: None,
+        }
+    }
+
+    /// Set the basket-level context qualification rules are evaluated in.
+    #[must_use]
+    pub fn with_context(mut self, context: EvaluationContext) -> Self {
+        self.set_context(context);
+        self
+    }
+
+    /// Keep each evaluation's allocation unless changing it saves more than `threshold`.
+    ///
+    /// With a zero threshold prices are unaffected, and the previous allocation only
+    /// decides between equally cheap ones.
+    #[must_use]
+    pub fn with_stability(mut self, threshold: &Money<'_, Currency>) -> Self {
+        let previous = self
+            .last_result
+            .as_ref()
+            .map(|result| {
+                Allocation::new(
+                    &self.item_group(),
+                    result.item_redemptions.values().flatten(),
+                )
+            })
+            .unwrap_or_default();
+
+        self.stability = Some(Stability::new(previous, threshold));
+        self
+    }
+
+    /// Change the basket-level context, e.g. when a loyalty card is scanned.
+    ///
+    /// Everything learned under the previous context is discarded.
+    pub fn set_context(&mut self, context: EvaluationContext) {
+        self.context = context;
+        self.cache.clear();
+        self.last_result = None;
+    }
+
+    /// Get the basket-level context.
+    #[must_use]
+    pub fn context(&self) -> &EvaluationContext {
+        &self.context
+    }
+
+    /// Get the basket being priced.
+    #[must_use]
+    pub fn basket(&self) -> &Basket<'b> {
+        &self.basket
+    }
+
+    /// End the session, returning the basket.
+    #[must_use]
+    pub fn into_basket(self) -> Basket<'b> {
+        self.basket
+    }
+
+    /// Add an item to the end of the basket, returning its index.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `BasketError::CurrencyMismatch` if the item's currency differs from
+    /// the basket currency.
+    pub fn add_item(&mut self, item: Item<'b>) -> Result<usize, BasketError> {
+        let item_idx = self.basket.add_item(item)?;
+
+        self.last_result = None;
+
+        Ok(item_idx)
+    }
+
+    /// Remove an item from the basket, returning it.
+    ///
+    /// Later items move down one index.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `BasketError::ItemNotFound` if the item is not found.
+    pub fn remove_item(&mut self, item: usize) -> Result<Item<'b>, BasketError> {
+        let removed = self.basket.remove_item(item)?;
+
+        if let Some(stability) = &mut self.stability {
+            stability.previous_mut().remove_item(item);
+        }
+
+        self.last_result = None;
+
+        Ok(removed)
+    }
+
+    /// Change the number of units on an item line.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `BasketError::ItemNotFound` if the item is not found.
+    pub fn set_quantity(&mut self, item: usize, quantity: NonZeroU32) -> Result<(), BasketError> {
+        self.basket.set_quantity(item, quantity)?;
+
+        self.last_result = None;
+
+        Ok(())
+    }
+
+    /// Price the basket as it stands.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`GraphError`] if any layer's solver fails.
+    pub fn evaluate(&mut self) -> Result<LayeredSolverResult<'b>, GraphError> {
+        if let Some(result) = &self.last_result {
+            return Ok(result.clone());
+        }
+
+        let item_group = self.item_group();
+
+        self.cache.next_generation();
+
+        let result = self.graph.evaluate_internal(
+            &item_group,
+            None,
+            Some(&mut self.cache),
+            self.stability.as_ref(),
+        )?;
+
+        if let Some(stability) = &mut self.stability {
+            *stability.previous_mut() =
+                Allocation::new(&item_group, result.item_redemptions.values().flatten());
+        }
+
+        if result.quality.is_optimal() {
+            self.last_result = Some(result.clone());
+        }
+
+        Ok(result)
+    }
+
+    /// The basket as an item group in the session's context.
+    fn item_group(&self) -> ItemGroup<'b> {
+        ItemGroup::new(
+            self.basket.iter().cloned().collect(),
+            self.basket.currency(),
+        )
+        .with_context(self.context.clone())
+    }
+
+    /// How many models the session's evaluations solved, and how much earlier work
+    /// they reused.
+    #[must_use]
+    pub fn stats(&self) -> CacheStats {
+        self.cache.stats()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use decimal_percentage::Percentage;
+    use rusty_money::{Money, iso::GBP};
+    use slotmap::SlotMap;
+    use testresult::TestResult;
+
+    use crate::{
+        discounts::SimpleDiscount,
+        graph::{OutputMode, PromotionGraphBuilder},
+        products::ProductKey,
+        promotions::{
+            Promotion, PromotionKey,
+            budget::{BudgetPoolKey, BudgetPools, PromotionBudget},
+            promotion,
+            qualification::Qualification,
+            types::DirectDiscountPromotion,
+        },
+        tags::string::StringTagCollection,
+    };
+
+    use super::*;
+
+    fn percent_off(key: PromotionKey, tag: &str, pct: f64) -> Promotion<'static> {
+        promotion(DirectDiscountPromotion::new(
+            key,
+            Qualification::match_any(StringTagCollection::from_strs(&[tag])),
+            SimpleDiscount::PercentageOff(Percentage::from(pct)),
+            PromotionBudget::unlimited(),
+        ))
+    }
+
+    fn pooled_percent_off(
+        key: PromotionKey,
+        tag: &str,
+        pct: f64,
+        pool: BudgetPoolKey,
+    ) -> Promotion<'static> {
+        promotion(
+            DirectDiscountPromotion::new(
+                key,
+                Qualification::match_any(StringTagCollection::from_strs(&[tag])),
+                SimpleDiscount::PercentageOff(Percentage::from(pct)),
+                PromotionBudget::unlimited(),
+            )
+            .with_budget_pool(pool),
+        )
+    }
+
+    /// Layers of promotions, each passing every item to the next, drawing on one
+    /// pool of `redemption_limit` redemptions.
+    fn pooled_graph(
+        layers: &[&[(&str, f64, bool)]],
+        redemption_limit: u32,
+    ) -> Result<PromotionGraph<'static>, GraphError> {
+        let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+        let mut pools = BudgetPools::default();
+        let pool = pools.insert(PromotionBudget {
+            redemption_limit: Some(redemption_limit),
+            monetary_limit: None,
+        });
+
+        let mut builder = PromotionGraphBuilder::new();
+        let mut previous = None;
+
+        for (layer_idx, layer) in layers.iter().enumerate() {
+            let promotions = layer.iter().map(|&(tag, pct, pooled)| {
+                if pooled {
+                    pooled_percent_off(keys.insert(()), tag, pct, pool)
+                } else {
+                    percent_off(keys.insert(()), tag, pct)
+                }
+            });
+            let node = builder.add_layer(
+                format!("Layer {layer_idx}"),
+                promotions,
+                OutputMode::PassThrough,
+            )?;
+
+            match previous {
+                Some(previous) => builder.connect_pass_through(previous, node)?,
+                None => builder.set_root(node),
+            }
+
+            previous = Some(node);
+        }
+
+        builder.set_budget_pools(pools);
+
+        PromotionGraph::from_builder(builder)
+    }
+
+    /// Each item's redemptions as (item, promotion, final price), in item order.
+    fn redeemed(result: &LayeredSolverResult<'_>) -> Vec<(usize, PromotionKey, i64)> {
+        let mut redeemed: Vec<_> = result
+            .item_redemptions
+            .values()
+            .flatten()
+            .map(|redemption| {
+                (
+                    redemption.item_idx,
+                    redemption.promotion_key,
+                    redemption.final_price.to_minor_units(),
+                )
+            })
+            .collect();
+
+        redeemed.sort_unstable();
+
+        redeemed
+    }
+
+    fn tagged(price: i64, tag: &str) -> Item<'static> {
+        Item::with_tags(
+            ProductKey::default(),
+            Money::from_minor(price, GBP),
+            StringTagCollection::from_strs(&[tag]),
+        )
+    }
+
+    fn graph() -> Result<PromotionGraph<'static>, GraphError> {
+        let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+
+        PromotionGraph::single_layer([
+            percent_off(keys.insert(()), "food", 0.20),
+            percent_off(keys.insert(()), "drink", 0.50),
+        ])
+    }
+
+    #[test]
+    fn unchanged_sub_baskets_reuse_their_solution() -> TestResult {
+        let graph = graph()?;
+        let mut session = graph.session(Basket::new(GBP));
+
+        session.add_item(tagged(1000, "food"))?;
+        session.add_item(tagged(400, "drink"))?;
+
+        assert_eq!(session.evaluate()?.total.to_minor_units(), 1000);
+        assert_eq!(
+            session.stats(),
+            CacheStats {
+                solved: 2,
+                reused: 0,
+                ..CacheStats::default()
+            }
+        );
+
+        // Only the drinks are solved again.
+        session.add_item(tagged(200, "drink"))?;
+
+        assert_eq!(session.evaluate()?.total.to_minor_units(), 1100);
+        assert_eq!(
+            session.stats(),
+            CacheStats {
+                solved: 3,
+                reused: 1,
+                ..CacheStats::default()
+            }
+        );
+
+        // Undoing the change is answered entirely from earlier solutions.
+        session.remove_item(2)?;
+
+        assert_eq!(session.evaluate()?.total.to_minor_units(), 1000);
+        assert_eq!(
+            session.stats(),
+            CacheStats {
+                solved: 3,
+                reused: 3,
+                ..CacheStats::default()
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn pooled_sub_baskets_reuse_their_formulation_and_first_pass() -> TestResult {
+        let graph = pooled_graph(&[&[("food", 0.20, true), ("drink", 0.50, false)]], 1)?;
+        let mut session = graph.session(Basket::new(GBP));
+
+        session.add_item(tagged(1000, "food"))?;
+        session.add_item(tagged(500, "food"))?;
+        session.add_item(tagged(400, "drink"))?;
+
+        // The pool allows one food discount.
+        assert_eq!(session.evaluate()?.total.to_minor_units(), 1500);
+
+        // The food draws on the pool, so it is solved again, but from its previous
+        // formulation, and with the pool unchanged its first pass is kept as is.
+        session.add_item(tagged(200, "drink"))?;
+
+        let cold = graph.evaluate(&ItemGroup::new(
+            session.basket().iter().cloned().collect(),
+            GBP,
+        ))?;
+        let warm = session.evaluate()?;
+
+        assert_eq!(warm.total, cold.total);
+        assert_eq!(redeemed(&warm), redeemed(&cold));
+        assert_eq!(cold.total.to_minor_units(), 1600);
+        assert_eq!(
+            session.stats(),
+            CacheStats {
+                solved: 4,
+                reused: 0,
+                reformulations_avoided: 1,
+                warm_starts: 1,
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn pool_balance_changes_re_solve_from_the_previous_solution() -> TestResult {
+        let graph = pooled_graph(&[&[("drink", 0.50, true)], &[("food", 0.20, true)]], 2)?;
+        let mut session = graph.session(Basket::new(GBP));
+
+        session.add_item(tagged(400, "drink"))?;
+        session.add_item(tagged(1000, "food"))?;
+        session.add_item(tagged(500, "food"))?;
+
+        // One redemption is left for the food after the drink.
+        assert_eq!(session.evaluate()?.total.to_minor_units(), 1500);
+
+        // A second drink uses up the pool, so the food's first pass is solved
+        // again under the new balance, starting from its previous solution.
+        session.add_item(tagged(300, "drink"))?;
+
+        let cold = graph.evaluate(&ItemGroup::new(
+            session.basket().iter().cloned().collect(),
+            GBP,
+        ))?;
+        let warm = session.evaluate()?;
+
+        assert_eq!(warm.total, cold.total);
+        assert_eq!(redeemed(&warm), redeemed(&cold));
+        assert_eq!(cold.total.to_minor_units(), 1850);
+        assert_eq!(
+            session.stats(),
+            CacheStats {
+                solved: 4,
+                reused: 0,
+                reformulations_avoided: 1,
+                warm_starts: 1,
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn stable_sessions_reuse_formulations_of_unchanged_sub_baskets() -> TestResult {
+        let graph = graph()?;
+        let mut session = graph
+            .session(Basket::new(GBP))
+            .with_stability(&Money::from_minor(0, GBP));
+
+        session.add_item(tagged(1000, "food"))?;
+        session.add_item(tagged(400, "drink"))?;
+        session.evaluate()?;
+
+        // Stability re-solves the food, but its model and first pass are unchanged.
+        session.add_item(tagged(200, "drink"))?;
+
+        assert_eq!(session.evaluate()?.total.to_minor_units(), 1100);
+        assert_eq!(
+            session.stats(),
+            CacheStats {
+                solved: 4,
+                reused: 0,
+                reformulations_avoided: 1,
+                warm_starts: 1,
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn evaluating_an_unchanged_basket_returns_the_previous_result() -> TestResult {
+        let graph = graph()?;
+        let mut session = graph.session(Basket::with_items([tagged(1000, "food")], GBP)?);
+
+        let first = session.evaluate()?;
+        let second = session.evaluate()?;
+
+        assert_eq!(first.total, second.total);
+        assert_eq!(
+            session.stats(),
+            CacheStats {
+                solved: 1,
+                reused: 0,
+                ..CacheStats::default()
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn quantity_changes_are_priced_like_a_fresh_basket() -> TestResult {
+        let graph = graph()?;
+        let mut session = graph.session(Basket::with_items([tagged(1000, "food")], GBP)?);
+
+        session.evaluate()?;
+        session.set_quantity(0, NonZeroU32::new(3).expect("non-zero quantity"))?;
+
+        let cold = graph.evaluate(&ItemGroup::new(
+            session.basket().iter().cloned().collect(),
+            GBP,
+        ))?;
+
+        assert_eq!(session.evaluate()?.total, cold.total);
+        assert_eq!(cold.total.to_minor_units(), 2400);
+
+        Ok(())
+    }
+
+    #[test]
+    fn changing_context_discards_cached_work() -> TestResult {
+        let graph = graph()?;
+        let mut session = graph.session(Basket::with_items([tagged(1000, "food")], GBP)?);
+
+        session.evaluate()?;
+        session.set_context(EvaluationContext::default().with_channel("online"));
+        session.evaluate()?;
+
+        assert_eq!(
+            session.stats(),
+            CacheStats {
+                solved: 2,
+                reused: 0,
+                ..CacheStats::default()
+            }
+        );
+        assert_eq!(session.context().channel(), Some("online"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,157 @@
+//! Stacking limits
+//!
+//! Caps on how much an item may accumulate across the layers of a graph: a
+//! largest total discount off its basket price and a largest number of stacked
+//! redemptions. Each layer only sees the headroom the earlier layers left.
+
+use decimal_percentage::Percentage;
+use rusty_money::{Money, iso::Currency};
+
+use crate::{
+    discounts::DiscountError, items::Item, pricing::PriceFloor, tags::collection::TagCollection,
+};
+
+/// Limits on what an item may accumulate across stacked layers
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StackingLimit {
+    /// Largest share of an item's basket price all layers may take off together
+    pub max_discount: Option<Percentage>,
+
+    /// Most promotions an item may be redeemed in across layers
+    pub max_redemptions: Option<u32>,
+}
+
+impl StackingLimit {
+    /// Never take more than `max_discount` off an item's basket price in total.
+    #[must_use]
+    pub fn with_max_discount(mut self, max_discount: Percentage) -> Self {
+        self.max_discount = Some(max_discount);
+        self
+    }
+
+    /// Never redeem an item in more than `max_redemptions` promotions in total.
+    #[must_use]
+    pub fn with_max_redemptions(mut self, max_redemptions: u32) -> Self {
+        self.max_redemptions = Some(max_redemptions);
+        self
+    }
+
+    /// Whether the limit caps nothing.
+    pub fn is_unlimited(&self) -> bool {
+        self.max_discount.is_none() && self.max_redemptions.is_none()
+    }
+
+    /// The tighter of each cap in `self` and `other`.
+    #[must_use]
+    pub fn tightest(self, other: Self) -> Self {
+        Self {
+            max_discount: tighter(self.max_discount, other.max_discount),
+            max_redemptions: tighter(self.max_redemptions, other.max_redemptions),
+        }
+    }
+
+    /// Whether an item already redeemed in `stacked` promotions may take another.
+    pub(crate) fn allows_another(&self, stacked: u32) -> bool {
+        self.max_redemptions
+            .is_none_or(|max_redemptions| stacked < max_redemptions)
+    }
+
+    /// Raise `item`'s price floor so the layer cannot take it further below its
+    /// `basket_price` than the largest total discount allows.
+    pub(crate) fn limit_item<'b, T: TagCollection>(
+        &self,
+        item: Item<'b, T>,
+        basket_price: &Money<'b, Currency>,
+    ) -> Result<Item<'b, T>, DiscountError> {
+        let Some(max_discount) = self.max_discount else {
+            return Ok(item);
+        };
+
+        let Some(minimum) = PriceFloor::default()
+            .with_max_discount(max_discount)
+            .minimum_for(basket_price)?
+        else {
+            return Ok(item);
+        };
+
+        Ok(match item.price_floor() {
+            Some(floor) if floor.to_minor_units() >= minimum.to_minor_units() => item,
+            _ => item.with_price_floor(minimum),
+        })
+    }
+}
+
+fn tighter<T: PartialOrd>(first: Option<T>, second: Option<T>) -> Option<T> {
+    match (first, second) {
+        (Some(first), Some(second)) => Some(if second < first { second } else { first }),
+        (first, second) => first.or(second),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rusty_money::iso::GBP;
+    use testresult::TestResult;
+
+    use crate::{products::ProductKey, tags::string::StringTagCollection};
+
+    use super::*;
+
+    fn item(price: i64) -> Item<'static> {
+        Item::with_tags(
+            ProductKey::default(),
+            Money::from_minor(price, GBP),
+            StringTagCollection::from_strs(&[]),
+        )
+    }
+
+    #[test]
+    fn tightest_takes_the_smaller_of_each_cap() {
+        let graph = StackingLimit::default()
+            .with_max_discount(Percentage::from(0.5))
+            .with_max_redemptions(3);
+
+        let layer = StackingLimit::default().with_max_discount(Percentage::from(0.2));
+
+        assert_eq!(
+            graph.tightest(layer),
+            StackingLimit {
+                max_discount: Some(Percentage::from(0.2)),
+                max_redemptions: Some(3),
+            }
+        );
+    }
+
+    #[test]
+    fn allows_another_below_the_redemption_cap() {
+        let limit = StackingLimit::default().with_max_redemptions(2);
+
+        assert!(limit.allows_another(1));
+        assert!(!limit.allows_another(2));
+        assert!(StackingLimit::default().allows_another(u32::MAX));
+    }
+
+    #[test]
+    fn limit_item_floors_against_the_basket_price() -> TestResult {
+        let limit = StackingLimit::default().with_max_discount(Percentage::from(0.5));
+
+        // Already 20p off a 100p item: the layer may go no lower than 50p.
+        let limited = limit.limit_item(item(80), &Money::from_minor(100, GBP))?;
+
+        assert_eq!(limited.price_floor(), Some(&Money::from_minor(50, GBP)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn limit_item_keeps_a_higher_floor() -> TestResult {
+        let limit = StackingLimit::default().with_max_discount(Percentage::from(0.5));
+
+        let floored = item(100).with_price_floor(Money::from_minor(70, GBP));
+        let limited = limit.limit_item(floored, &Money::from_minor(100, GBP))?;
+
+        assert_eq!(limited.price_floor(), Some(&Money::from_minor(70, GBP)));
+
+        Ok(())
+    }
+}
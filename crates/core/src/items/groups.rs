@@ -6,6 +6,7 @@ use thiserror::Error;
 
 use crate::{
     basket::Basket,
+    context::EvaluationContext,
     items::Item,
     tags::{collection::TagCollection, string::StringTagCollection},
 };
@@ -23,20 +24,32 @@ pub enum ItemGroupError {
 }
 
 /// Item Group
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ItemGroup<'a, T: TagCollection = StringTagCollection> {
     items: SmallVec<[Item<'a, T>; 10]>,
     currency: &'a Currency,
+    context: EvaluationContext,
 }
 
 impl<'a, T: TagCollection> ItemGroup<'a, T> {
     /// Create a new item group with items and currency.
     pub fn new(items: SmallVec<[Item<'a, T>; 10]>, currency: &'a Currency) -> Self {
-        ItemGroup { items, currency }
+        ItemGroup {
+            items,
+            currency,
+            context: EvaluationContext::default(),
+        }
+    }
+
+    /// Sets the basket-level context qualification rules are evaluated in.
+    #[must_use]
+    pub fn with_context(mut self, context: EvaluationContext) -> Self {
+        self.context = context;
+        self
     }
 
     /// Iterate over the items in the item group.
-    pub fn iter(&self) -> impl Iterator<Item = &Item<'_, T>> {
+    pub fn iter(&self) -> impl Iterator<Item = &Item<'a, T>> {
         self.items.iter()
     }
 
@@ -56,6 +69,11 @@ impl<'a, T: TagCollection> ItemGroup<'a, T> {
         self.currency
     }
 
+    /// Get the evaluation context of the item group.
+    pub fn context(&self) -> &EvaluationContext {
+        &self.context
+    }
+
     /// Get the number of items in the item group.
     pub fn len(&self) -> usize {
         self.items.len()
@@ -72,6 +90,7 @@ impl<'a> From<&'a Basket<'a>> for ItemGroup<'a> {
         ItemGroup {
             items: basket.iter().cloned().collect(),
             currency: basket.currency(),
+            context: EvaluationContext::default(),
         }
     }
 }
@@ -1,6 +1,8 @@
 //! Items
 
-use rusty_money::{Money, iso::Currency};
+use std::num::NonZeroU32;
+
+use rusty_money::{Money, MoneyError, iso::Currency};
 
 use crate::{
     products::ProductKey,
@@ -10,11 +12,17 @@ use crate::{
 pub mod groups;
 
 /// An unprocessed item with a price and tags.
+///
+/// An item is a basket line: `price` is the unit price and `quantity` is the
+/// number of identical units on the line (one by default).
 #[derive(Clone, Debug, PartialEq)]
 pub struct Item<'a, T: TagCollection = StringTagCollection> {
     product: ProductKey,
     price: Money<'a, Currency>,
     tags: T,
+    quantity: NonZeroU32,
+    unit: Option<String>,
+    price_floor: Option<Money<'a, Currency>>,
 }
 
 impl<'a, T: TagCollection> Item<'a, T> {
@@ -30,19 +38,84 @@ impl<'a, T: TagCollection> Item<'a, T> {
             product,
             price,
             tags,
+            quantity: NonZeroU32::MIN,
+            unit: None,
+            price_floor: None,
         }
     }
 
+    /// Sets the number of identical units on the line.
+    #[must_use]
+    pub fn with_quantity(mut self, quantity: NonZeroU32) -> Self {
+        self.set_quantity(quantity);
+        self
+    }
+
+    /// Changes the number of identical units on the line.
+    pub fn set_quantity(&mut self, quantity: NonZeroU32) {
+        self.quantity = quantity;
+    }
+
+    /// Replaces the unit price of the item.
+    #[must_use]
+    pub fn with_price(mut self, price: Money<'a, Currency>) -> Self {
+        self.price = price;
+        self
+    }
+
+    /// Sets the unit of measure label for the line (e.g. `"can"` or `"kg"`).
+    #[must_use]
+    pub fn with_unit(mut self, unit: impl Into<String>) -> Self {
+        self.unit = Some(unit.into());
+        self
+    }
+
+    /// Sets the lowest unit price promotions may discount the item to.
+    ///
+    /// The floor is absolute: it stays the same as promotions in earlier layers
+    /// lower the item's price. Use
+    /// [`PriceFloor::minimum_for`](crate::pricing::PriceFloor::minimum_for) to
+    /// work it out from a product's cost price, minimum price or largest discount.
+    #[must_use]
+    pub fn with_price_floor(mut self, floor: Money<'a, Currency>) -> Self {
+        self.price_floor = Some(floor);
+        self
+    }
+
     /// Returns the product of the item
     pub fn product(&self) -> ProductKey {
         self.product
     }
 
-    /// Returns the price of the item
+    /// Returns the unit price of the item
     pub fn price(&self) -> &Money<'a, Currency> {
         &self.price
     }
 
+    /// Returns the number of units on the line
+    pub fn quantity(&self) -> u32 {
+        self.quantity.get()
+    }
+
+    /// Returns the unit of measure label, if any
+    pub fn unit(&self) -> Option<&str> {
+        self.unit.as_deref()
+    }
+
+    /// Returns the price of the whole line (unit price multiplied by quantity).
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`MoneyError`] if the multiplication overflows.
+    pub fn line_price(&self) -> Result<Money<'a, Currency>, MoneyError> {
+        self.price.mul(self.quantity.get())
+    }
+
+    /// Returns the lowest unit price promotions may discount the item to, if any
+    pub fn price_floor(&self) -> Option<&Money<'a, Currency>> {
+        self.price_floor.as_ref()
+    }
+
     /// Returns the tags for the item.
     pub fn tags(&self) -> &T {
         &self.tags
@@ -100,4 +173,30 @@ mod tests {
 
         assert_eq!(item.product(), key);
     }
+
+    #[test]
+    fn item_defaults_to_single_unit_line() -> Result<(), MoneyError> {
+        let item: Item<'_, StringTagCollection> =
+            Item::new(ProductKey::default(), Money::from_minor(150, GBP));
+
+        assert_eq!(item.quantity(), 1);
+        assert_eq!(item.unit(), None);
+        assert_eq!(item.line_price()?, Money::from_minor(150, GBP));
+
+        Ok(())
+    }
+
+    #[test]
+    fn item_quantity_scales_line_price() -> Result<(), MoneyError> {
+        let item: Item<'_, StringTagCollection> =
+            Item::new(ProductKey::default(), Money::from_minor(65, GBP))
+                .with_quantity(NonZeroU32::new(500).expect("non-zero quantity"))
+                .with_unit("can");
+
+        assert_eq!(item.quantity(), 500);
+        assert_eq!(item.unit(), Some("can"));
+        assert_eq!(item.line_price()?, Money::from_minor(32_500, GBP));
+
+        Ok(())
+    }
 }
@@ -2,7 +2,11 @@
 //!
 //! Latice is a high-performance, general-purpose pricing, promotion and basket optimisation engine written in Rust.
 
+#[cfg(not(any(feature = "solver-microlp", feature = "solver-highs")))]
+compile_error!("enable at least one MILP backend: `solver-microlp` or `solver-highs`");
+
 pub mod basket;
+pub mod context;
 pub mod discounts;
 pub mod fixtures;
 pub mod graph;
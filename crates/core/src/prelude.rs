@@ -4,8 +4,12 @@
 
 pub use crate::{
     basket::{Basket, BasketError},
+    context::EvaluationContext,
     discounts::{DiscountError, SimpleDiscount},
-    graph::{GraphError, LayeredSolverResult, OutputMode, PromotionGraph, PromotionGraphBuilder},
+    graph::{
+        AdditionEstimate, EvaluationMode, GraphError, LayeredSolverResult, OutputMode,
+        PromotionExplanation, PromotionGraph, PromotionGraphBuilder, PromotionOutcome,
+    },
     items::{
         Item,
         groups::{ItemGroup, ItemGroupError},
@@ -14,8 +18,11 @@ pub use crate::{
     promotions::{
         Promotion, PromotionKey, PromotionMeta, PromotionSlotKey,
         budget::PromotionBudget,
+        coupon::{CouponCodeReport, CouponUsage, PromotionCoupon},
+        explain::{MissReason, Shortfall, SlotShortfall},
         promotion,
         qualification::{BoolOp, Qualification, QualificationRule},
+        schedule::{PromotionSchedule, RecurringWindow},
         types::{
             DirectDiscountPromotion, MixAndMatchDiscount, MixAndMatchPromotion, MixAndMatchSlot,
             PositionalDiscountPromotion,
@@ -24,6 +31,8 @@ pub use crate::{
     receipt::{Receipt, ReceiptError},
     solvers::{
         Solver, SolverError, SolverResult,
+        exhaustive::ExhaustiveSolver,
+        greedy::GreedySolver,
         ilp::{
             ILPObserver, ILPSolver, NoopObserver,
             renderers::typst::{MultiLayerRenderer, TypstRenderError, TypstRenderer},
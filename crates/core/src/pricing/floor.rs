@@ -0,0 +1,155 @@
+//! Price floors
+//!
+//! Margin protection for products: a cost price, a minimum selling price and a
+//! largest discount. Promotions may discount an item down to its floor but never
+//! below it, however many layers they are stacked across.
+
+use decimal_percentage::Percentage;
+use rusty_money::{Money, iso::Currency};
+
+use crate::discounts::{
+    DiscountError,
+    rounding::{RoundingMode, RoundingPolicy},
+};
+
+/// Limits on how far promotions may discount a product
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PriceFloor<'a> {
+    /// Unit cost price; the item is never sold below it
+    pub cost_price: Option<Money<'a, Currency>>,
+
+    /// Lowest unit price the item may be sold at
+    pub minimum_price: Option<Money<'a, Currency>>,
+
+    /// Largest share of the unit price promotions may take off, in total
+    pub max_discount: Option<Percentage>,
+}
+
+impl<'a> PriceFloor<'a> {
+    /// Never sell below `cost_price`.
+    #[must_use]
+    pub fn with_cost_price(mut self, cost_price: Money<'a, Currency>) -> Self {
+        self.cost_price = Some(cost_price);
+        self
+    }
+
+    /// Never sell below `minimum_price`.
+    #[must_use]
+    pub fn with_minimum_price(mut self, minimum_price: Money<'a, Currency>) -> Self {
+        self.minimum_price = Some(minimum_price);
+        self
+    }
+
+    /// Never take more than `max_discount` off the unit price.
+    #[must_use]
+    pub fn with_max_discount(mut self, max_discount: Percentage) -> Self {
+        self.max_discount = Some(max_discount);
+        self
+    }
+
+    /// Whether the floor sets no limit at all.
+    pub fn is_unlimited(&self) -> bool {
+        self.cost_price.is_none() && self.minimum_price.is_none() && self.max_discount.is_none()
+    }
+
+    /// Lowest unit price an item at `price` may be discounted to, if limited.
+    ///
+    /// This is the highest of the cost price, the minimum price and `price` less
+    /// the largest discount (rounded down, so the discount never exceeds it),
+    /// and never more than `price` itself.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DiscountError::PercentConversion`] if the largest discount
+    /// overflows or cannot be safely represented.
+    pub fn minimum_for(
+        &self,
+        price: &Money<'a, Currency>,
+    ) -> Result<Option<Money<'a, Currency>>, DiscountError> {
+        let price_minor = price.to_minor_units();
+
+        let from_discount = self
+            .max_discount
+            .map(|max_discount| {
+                RoundingPolicy::new(RoundingMode::SmallerDiscount)
+                    .discount_of(&max_discount, price_minor)
+                    .map(|discount| price_minor - discount)
+            })
+            .transpose()?;
+
+        let floor_minor = [
+            self.cost_price.map(|cost| cost.to_minor_units()),
+            self.minimum_price.map(|minimum| minimum.to_minor_units()),
+            from_discount,
+        ]
+        .into_iter()
+        .flatten()
+        .max();
+
+        Ok(floor_minor
+            .map(|floor_minor| Money::from_minor(floor_minor.min(price_minor), price.currency())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rusty_money::iso::GBP;
+    use testresult::TestResult;
+
+    use super::*;
+
+    #[test]
+    fn unlimited_floor_has_no_minimum() -> TestResult {
+        let floor = PriceFloor::default();
+
+        assert!(floor.is_unlimited());
+        assert_eq!(floor.minimum_for(&Money::from_minor(100, GBP))?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn minimum_is_the_highest_limit() -> TestResult {
+        let floor = PriceFloor::default()
+            .with_cost_price(Money::from_minor(60, GBP))
+            .with_minimum_price(Money::from_minor(50, GBP))
+            .with_max_discount(Percentage::from(0.25));
+
+        assert_eq!(
+            floor.minimum_for(&Money::from_minor(100, GBP))?,
+            Some(Money::from_minor(75, GBP))
+        );
+
+        assert_eq!(
+            floor.minimum_for(&Money::from_minor(70, GBP))?,
+            Some(Money::from_minor(60, GBP))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn largest_discount_rounds_in_the_retailers_favour() -> TestResult {
+        // A third off 100p allows 33p off, not 34p.
+        let floor = PriceFloor::default().with_max_discount(Percentage::try_from("0.3333")?);
+
+        assert_eq!(
+            floor.minimum_for(&Money::from_minor(100, GBP))?,
+            Some(Money::from_minor(67, GBP))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn minimum_never_exceeds_the_price() -> TestResult {
+        let floor = PriceFloor::default().with_cost_price(Money::from_minor(120, GBP));
+
+        assert_eq!(
+            floor.minimum_for(&Money::from_minor(100, GBP))?,
+            Some(Money::from_minor(100, GBP))
+        );
+
+        Ok(())
+    }
+}
@@ -5,6 +5,10 @@ use thiserror::Error;
 
 use crate::{items::Item, tags::collection::TagCollection};
 
+pub mod floor;
+
+pub use floor::PriceFloor;
+
 /// Errors that can occur while calculating total price.
 #[derive(Debug, Error, PartialEq)]
 pub enum TotalPriceError {
@@ -17,7 +21,7 @@ pub enum TotalPriceError {
     Money(#[from] MoneyError),
 }
 
-/// Calculates the total price of a list of items
+/// Calculates the total price of a list of items, accounting for line quantities
 ///
 /// # Errors
 ///
@@ -30,7 +34,7 @@ pub fn total_price<'a, T: TagCollection>(
 
     let total = items.iter().try_fold(
         Money::from_minor(0, first.price().currency()),
-        |acc, item| acc.add(*item.price()),
+        |acc, item| acc.add(item.line_price()?),
     )?;
 
     Ok(total)
@@ -38,6 +42,8 @@ pub fn total_price<'a, T: TagCollection>(
 
 #[cfg(test)]
 mod tests {
+    use std::num::NonZeroU32;
+
     use rusty_money::iso::GBP;
     use testresult::TestResult;
 
@@ -57,6 +63,19 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn total_price_multiplies_line_quantities() -> TestResult {
+        let items: [Item<'_, StringTagCollection>; 2] = [
+            Item::new(ProductKey::default(), Money::from_minor(100, GBP))
+                .with_quantity(NonZeroU32::new(3).expect("non-zero quantity")),
+            Item::new(ProductKey::default(), Money::from_minor(200, GBP)),
+        ];
+
+        assert_eq!(total_price(&items)?, Money::from_minor(500, GBP));
+
+        Ok(())
+    }
+
     #[test]
     fn test_total_price_empty() {
         let items: [Item<'static>; 0] = [];
@@ -3,7 +3,10 @@
 use rusty_money::{Money, iso::Currency};
 use slotmap::new_key_type;
 
-use crate::tags::{collection::TagCollection, string::StringTagCollection};
+use crate::{
+    pricing::PriceFloor,
+    tags::{collection::TagCollection, string::StringTagCollection},
+};
 
 new_key_type! {
     /// Product Key
@@ -21,4 +24,7 @@ pub struct Product<'a, T: TagCollection = StringTagCollection> {
 
     /// Product price
     pub price: Money<'a, Currency>,
+
+    /// Limits on how far promotions may discount the product
+    pub floor: PriceFloor<'a>,
 }
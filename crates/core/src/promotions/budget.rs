@@ -1,6 +1,19 @@
 //! Promotion Budget Constraints
 
 use rusty_money::{Money, iso::Currency};
+use slotmap::{SlotMap, new_key_type};
+
+new_key_type! {
+    /// Budget Pool Key
+    pub struct BudgetPoolKey;
+}
+
+/// Shared budget pools, each holding the limits drawn on by every promotion
+/// that references the pool's key.
+///
+/// A pool's limits apply to the combined redemptions and discount value of all
+/// its promotions, across every layer of a promotion graph.
+pub type BudgetPools<'a> = SlotMap<BudgetPoolKey, PromotionBudget<'a>>;
 
 /// Budget constraints for a promotion
 #[derive(Debug, Clone, Copy, Default)]
@@ -0,0 +1,193 @@
+//! Coupon Codes
+//!
+//! Promotions gated on a code the shopper presents with the basket.
+
+use smallvec::SmallVec;
+
+use crate::context::EvaluationContext;
+
+/// How often a coupon code can be redeemed within one basket
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CouponUsage {
+    /// The code unlocks a single redemption of the promotion
+    #[default]
+    SingleUse,
+
+    /// The code unlocks the promotion for as many redemptions as its budget allows
+    MultiUse,
+}
+
+/// Codes that unlock a promotion.
+///
+/// Any one of the codes is enough. Codes are matched ASCII case-insensitively,
+/// so `SAVE10` and `save10` are the same code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PromotionCoupon {
+    /// Accepted codes
+    pub codes: SmallVec<[String; 2]>,
+
+    /// How often the promotion can be redeemed per basket
+    pub usage: CouponUsage,
+}
+
+impl PromotionCoupon {
+    /// Create a coupon whose codes unlock a single redemption per basket
+    pub fn single_use<S: Into<String>>(codes: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            codes: codes.into_iter().map(Into::into).collect(),
+            usage: CouponUsage::SingleUse,
+        }
+    }
+
+    /// Create a coupon whose codes unlock any number of redemptions
+    pub fn multi_use<S: Into<String>>(codes: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            codes: codes.into_iter().map(Into::into).collect(),
+            usage: CouponUsage::MultiUse,
+        }
+    }
+
+    /// Check whether `code` is one of the accepted codes
+    #[must_use]
+    pub fn accepts(&self, code: &str) -> bool {
+        self.codes
+            .iter()
+            .any(|accepted| accepted.eq_ignore_ascii_case(code))
+    }
+
+    /// Return the first presented code (in presentation order) this coupon accepts
+    #[must_use]
+    pub fn presented_code<'c>(&self, context: &'c EvaluationContext) -> Option<&'c str> {
+        context
+            .codes()
+            .iter()
+            .map(String::as_str)
+            .find(|code| self.accepts(code))
+    }
+
+    /// Check whether the context presents one of the accepted codes
+    #[must_use]
+    pub fn is_presented_in(&self, context: &EvaluationContext) -> bool {
+        self.presented_code(context).is_some()
+    }
+
+    /// Cap a budget redemption limit by the coupon's usage.
+    #[must_use]
+    pub fn cap_redemption_limit(&self, limit: Option<u32>) -> Option<u32> {
+        match self.usage {
+            CouponUsage::SingleUse => Some(limit.map_or(1, |limit| limit.min(1))),
+            CouponUsage::MultiUse => limit,
+        }
+    }
+}
+
+/// What happened to each code presented with a basket
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CouponCodeReport {
+    /// Codes that unlocked at least one redemption
+    pub used: SmallVec<[String; 2]>,
+
+    /// Codes accepted by a promotion that gave no benefit
+    pub no_benefit: SmallVec<[String; 2]>,
+
+    /// Codes no promotion accepts
+    pub unknown: SmallVec<[String; 2]>,
+}
+
+impl CouponCodeReport {
+    /// Classify the presented codes.
+    ///
+    /// `coupons` yields each promotion's coupon with whether that promotion was
+    /// redeemed. A redeemed promotion uses the first code it accepts in
+    /// presentation order; any other presented code it accepts gave no benefit
+    /// unless another redeemed promotion used it.
+    pub fn new<'p>(
+        context: &EvaluationContext,
+        coupons: impl IntoIterator<Item = (&'p PromotionCoupon, bool)>,
+    ) -> Self {
+        let coupons: SmallVec<[(&PromotionCoupon, bool); 4]> = coupons.into_iter().collect();
+
+        let used_codes: SmallVec<[&str; 2]> = coupons
+            .iter()
+            .filter(|(_, redeemed)| *redeemed)
+            .filter_map(|(coupon, _)| coupon.presented_code(context))
+            .collect();
+
+        let mut report = Self::default();
+
+        for code in context.codes() {
+            if used_codes.contains(&code.as_str()) {
+                report.used.push(code.clone());
+            } else if coupons.iter().any(|(coupon, _)| coupon.accepts(code)) {
+                report.no_benefit.push(code.clone());
+            } else {
+                report.unknown.push(code.clone());
+            }
+        }
+
+        report
+    }
+
+    /// Check whether no codes were presented
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.used.is_empty() && self.no_benefit.is_empty() && self.unknown.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coupon_accepts_codes_case_insensitively() {
+        let coupon = PromotionCoupon::single_use(["SAVE10", "WELCOME"]);
+
+        assert!(coupon.accepts("save10"));
+        assert!(coupon.accepts("Welcome"));
+        assert!(!coupon.accepts("SAVE20"));
+    }
+
+    #[test]
+    fn presented_code_follows_presentation_order() {
+        let coupon = PromotionCoupon::multi_use(["SAVE10", "WELCOME"]);
+
+        let context = EvaluationContext::new()
+            .with_code("other")
+            .with_code("welcome")
+            .with_code("save10");
+
+        assert_eq!(coupon.presented_code(&context), Some("welcome"));
+        assert!(!coupon.is_presented_in(&EvaluationContext::default()));
+    }
+
+    #[test]
+    fn single_use_caps_redemption_limit() {
+        let single = PromotionCoupon::single_use(["A"]);
+        let multi = PromotionCoupon::multi_use(["A"]);
+
+        assert_eq!(single.cap_redemption_limit(None), Some(1));
+        assert_eq!(single.cap_redemption_limit(Some(5)), Some(1));
+        assert_eq!(single.cap_redemption_limit(Some(0)), Some(0));
+        assert_eq!(multi.cap_redemption_limit(None), None);
+        assert_eq!(multi.cap_redemption_limit(Some(5)), Some(5));
+    }
+
+    #[test]
+    fn report_classifies_presented_codes() {
+        let redeemed = PromotionCoupon::single_use(["SAVE10", "ALT10"]);
+        let not_redeemed = PromotionCoupon::multi_use(["FREESHIP"]);
+
+        let context = EvaluationContext::new()
+            .with_code("alt10")
+            .with_code("SAVE10")
+            .with_code("FREESHIP")
+            .with_code("BOGUS");
+
+        let report = CouponCodeReport::new(&context, [(&redeemed, true), (&not_redeemed, false)]);
+
+        assert_eq!(report.used.as_slice(), ["alt10"]);
+        assert_eq!(report.no_benefit.as_slice(), ["SAVE10", "FREESHIP"]);
+        assert_eq!(report.unknown.as_slice(), ["BOGUS"]);
+    }
+}
@@ -0,0 +1,308 @@
+//! Promotion Exclusions
+//!
+//! "Cannot be combined with" terms on promotions. A promotion may rule out being
+//! combined with every other offer, with particular promotions, or with the
+//! members of an exclusion group, either on the same item or anywhere in the
+//! basket.
+
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+use slotmap::new_key_type;
+
+use crate::{promotions::PromotionKey, solvers::ilp::ILPPromotion};
+
+new_key_type! {
+    /// Key identifying a group of promotions that exclusion rules can refer to.
+    pub struct ExclusionGroupKey;
+}
+
+/// How far apart two excluded promotions must be kept
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExclusionScope {
+    /// The promotions may not both be redeemed on the same item
+    #[default]
+    Item,
+
+    /// The promotions may not both be redeemed anywhere in the basket
+    Basket,
+}
+
+/// The promotions an exclusion rule rules out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExclusionTarget {
+    /// Every other promotion ("not valid with any other offer")
+    AnyOther,
+
+    /// One particular promotion
+    Promotion(PromotionKey),
+
+    /// Every other member of an exclusion group
+    Group(ExclusionGroupKey),
+}
+
+/// A single "cannot be combined with" rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Exclusion {
+    /// The promotions that cannot be combined with the rule's owner
+    pub target: ExclusionTarget,
+
+    /// Whether the rule applies per item or to the whole basket
+    pub scope: ExclusionScope,
+}
+
+impl Exclusion {
+    /// Never combine with any other promotion within `scope`.
+    pub fn any_other(scope: ExclusionScope) -> Self {
+        Self {
+            target: ExclusionTarget::AnyOther,
+            scope,
+        }
+    }
+
+    /// Never combine with the promotion `key` within `scope`.
+    pub fn promotion(key: PromotionKey, scope: ExclusionScope) -> Self {
+        Self {
+            target: ExclusionTarget::Promotion(key),
+            scope,
+        }
+    }
+
+    /// Never combine with another member of `group` within `scope`.
+    pub fn group(group: ExclusionGroupKey, scope: ExclusionScope) -> Self {
+        Self {
+            target: ExclusionTarget::Group(group),
+            scope,
+        }
+    }
+}
+
+/// A promotion's exclusion groups and "cannot be combined with" rules
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PromotionExclusions {
+    /// Groups the promotion belongs to
+    pub groups: Vec<ExclusionGroupKey>,
+
+    /// Promotions the promotion cannot be combined with
+    pub rules: Vec<Exclusion>,
+}
+
+/// Exclusions of promotions that do not set any.
+pub(crate) static NO_EXCLUSIONS: PromotionExclusions = PromotionExclusions {
+    groups: Vec::new(),
+    rules: Vec::new(),
+};
+
+impl PromotionExclusions {
+    /// Join the exclusion group `group`.
+    #[must_use]
+    pub fn in_group(mut self, group: ExclusionGroupKey) -> Self {
+        if !self.groups.contains(&group) {
+            self.groups.push(group);
+        }
+
+        self
+    }
+
+    /// Add a "cannot be combined with" rule.
+    #[must_use]
+    pub fn with_rule(mut self, rule: Exclusion) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Whether the promotion neither joins a group nor sets a rule.
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty() && self.rules.is_empty()
+    }
+}
+
+/// The pairs of promotions that cannot be combined, resolved from their rules.
+///
+/// Exclusions are symmetric: if either promotion of a pair rules the other out,
+/// neither may be combined with the other. Where rules disagree on the scope,
+/// the wider one applies.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct ExclusionRules {
+    /// Ordered promotion key pair -> widest scope ruling it out
+    pairs: BTreeMap<(PromotionKey, PromotionKey), ExclusionScope>,
+}
+
+impl ExclusionRules {
+    /// Resolve the rules of `promotions` against each other.
+    pub(crate) fn from_promotions<'p, P>(promotions: impl IntoIterator<Item = &'p P>) -> Self
+    where
+        P: ILPPromotion + ?Sized + 'p,
+    {
+        let promotions: Vec<&P> = promotions.into_iter().collect();
+        let mut rules = Self::default();
+
+        for promotion in &promotions {
+            let key = promotion.key();
+
+            for rule in &promotion.exclusions().rules {
+                for other in &promotions {
+                    let other_key = other.key();
+
+                    let excluded = other_key != key
+                        && match rule.target {
+                            ExclusionTarget::AnyOther => true,
+                            ExclusionTarget::Promotion(target) => target == other_key,
+                            ExclusionTarget::Group(group) => {
+                                other.exclusions().groups.contains(&group)
+                            }
+                        };
+
+                    if excluded {
+                        rules.insert(key, other_key, rule.scope);
+                    }
+                }
+            }
+        }
+
+        rules
+    }
+
+    fn insert(&mut self, first: PromotionKey, second: PromotionKey, scope: ExclusionScope) {
+        let pair = if first < second {
+            (first, second)
+        } else {
+            (second, first)
+        };
+
+        let widest = self.pairs.entry(pair).or_insert(scope);
+
+        *widest = (*widest).max(scope);
+    }
+
+    /// Whether no pair of promotions is excluded.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+
+    /// The widest scope `first` and `second` cannot be combined within, if any.
+    pub(crate) fn scope(
+        &self,
+        first: PromotionKey,
+        second: PromotionKey,
+    ) -> Option<ExclusionScope> {
+        let pair = if first < second {
+            (first, second)
+        } else {
+            (second, first)
+        };
+
+        self.pairs.get(&pair).copied()
+    }
+
+    /// Whether `key` cannot be combined with some other promotion.
+    pub(crate) fn excludes(&self, key: PromotionKey) -> bool {
+        self.pairs
+            .keys()
+            .any(|&(first, second)| first == key || second == key)
+    }
+
+    /// Pairs that may not both be redeemed anywhere in the basket, in key order.
+    pub(crate) fn basket_pairs(&self) -> impl Iterator<Item = (PromotionKey, PromotionKey)> + '_ {
+        self.pairs
+            .iter()
+            .filter(|&(_pair, &scope)| scope == ExclusionScope::Basket)
+            .map(|(&pair, _scope)| pair)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use decimal_percentage::Percentage;
+    use slotmap::SlotMap;
+
+    use crate::{
+        discounts::SimpleDiscount,
+        promotions::{
+            budget::PromotionBudget, qualification::Qualification, types::DirectDiscountPromotion,
+        },
+    };
+
+    use super::*;
+
+    fn percent_off(key: PromotionKey) -> DirectDiscountPromotion<'static> {
+        DirectDiscountPromotion::new(
+            key,
+            Qualification::match_all(),
+            SimpleDiscount::PercentageOff(Percentage::from(0.1)),
+            PromotionBudget::unlimited(),
+        )
+    }
+
+    #[test]
+    fn rules_are_symmetric_and_take_the_widest_scope() {
+        let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+        let (first, second) = (keys.insert(()), keys.insert(()));
+
+        let promotions = [
+            percent_off(first).with_exclusions(
+                PromotionExclusions::default()
+                    .with_rule(Exclusion::promotion(second, ExclusionScope::Item)),
+            ),
+            percent_off(second).with_exclusions(
+                PromotionExclusions::default()
+                    .with_rule(Exclusion::promotion(first, ExclusionScope::Basket)),
+            ),
+        ];
+
+        let rules = ExclusionRules::from_promotions(&promotions);
+
+        assert_eq!(rules.scope(first, second), Some(ExclusionScope::Basket));
+        assert_eq!(rules.scope(second, first), Some(ExclusionScope::Basket));
+        assert_eq!(rules.basket_pairs().count(), 1);
+    }
+
+    #[test]
+    fn any_other_excludes_every_other_promotion() {
+        let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+        let (solo, first, second) = (keys.insert(()), keys.insert(()), keys.insert(()));
+
+        let promotions = [
+            percent_off(solo).with_exclusions(
+                PromotionExclusions::default()
+                    .with_rule(Exclusion::any_other(ExclusionScope::Item)),
+            ),
+            percent_off(first),
+            percent_off(second),
+        ];
+
+        let rules = ExclusionRules::from_promotions(&promotions);
+
+        assert_eq!(rules.scope(solo, first), Some(ExclusionScope::Item));
+        assert_eq!(rules.scope(solo, second), Some(ExclusionScope::Item));
+        assert_eq!(rules.scope(first, second), None);
+        assert!(rules.excludes(solo));
+        assert!(rules.basket_pairs().next().is_none());
+    }
+
+    #[test]
+    fn group_rules_exclude_other_members_only() {
+        let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+        let (first, second, outsider) = (keys.insert(()), keys.insert(()), keys.insert(()));
+
+        let mut groups = SlotMap::<ExclusionGroupKey, ()>::with_key();
+        let vouchers = groups.insert(());
+
+        let member = PromotionExclusions::default()
+            .in_group(vouchers)
+            .with_rule(Exclusion::group(vouchers, ExclusionScope::Basket));
+
+        let promotions = [
+            percent_off(first).with_exclusions(member.clone()),
+            percent_off(second).with_exclusions(member),
+            percent_off(outsider),
+        ];
+
+        let rules = ExclusionRules::from_promotions(&promotions);
+
+        assert_eq!(rules.scope(first, second), Some(ExclusionScope::Basket));
+        assert_eq!(rules.scope(first, outsider), None);
+        assert!(!rules.excludes(outsider));
+    }
+}
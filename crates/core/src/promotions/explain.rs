@@ -0,0 +1,268 @@
+//! Near-miss Explanations
+//!
+//! Why a promotion did not redeem against a basket, and the smallest change to
+//! the basket that would let it.
+
+use rusty_money::{Money, iso::Currency};
+use smallvec::SmallVec;
+
+use crate::{
+    context::EvaluationContext,
+    items::{Item, groups::ItemGroup},
+    promotions::{
+        PromotionKey, PromotionSlotKey,
+        budget::{BudgetPoolKey, BudgetPools, PromotionBudget},
+        coupon::PromotionCoupon,
+        qualification::Qualification,
+        schedule::PromotionSchedule,
+    },
+    tags::collection::TagCollection,
+};
+
+/// How far a basket is from triggering a promotion.
+///
+/// Each variant describes the smallest change to the basket that would let the
+/// promotion apply, assuming nothing else claims the added items.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Shortfall<'a> {
+    /// No item in the basket qualifies; adding one qualifying item would help
+    NoQualifyingItems,
+
+    /// More qualifying items are needed to complete a bundle
+    Items {
+        /// Qualifying units needed
+        required: u32,
+
+        /// Qualifying units in the basket
+        available: u32,
+    },
+
+    /// Mix-and-match slots that cannot be filled
+    Slots(SmallVec<[SlotShortfall; 2]>),
+
+    /// The nearest tier's spend and/or item threshold is not reached
+    Threshold {
+        /// Index of the nearest unreached tier
+        tier: usize,
+
+        /// Additional spend needed on contributing items
+        spend: Option<Money<'a, Currency>>,
+
+        /// Additional contributing units needed
+        items: Option<u32>,
+    },
+}
+
+/// An unfilled mix-and-match slot.
+///
+/// Slots are counted independently, so an item that qualifies for two slots is
+/// counted towards both.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlotShortfall {
+    /// Slot that cannot be filled
+    pub slot: PromotionSlotKey,
+
+    /// Minimum units the slot needs
+    pub required: usize,
+
+    /// Qualifying units in the basket
+    pub available: usize,
+}
+
+/// Why a promotion did not redeem.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MissReason<'a> {
+    /// The promotion's schedule is not active at the evaluation instant
+    NotScheduled,
+
+    /// None of the promotion's coupon codes were presented
+    CouponNotPresented,
+
+    /// The promotion's budget allows no further redemptions
+    BudgetExhausted,
+
+    /// A shared budget pool the promotion draws on has too little left for a
+    /// redemption
+    PoolExhausted(BudgetPoolKey),
+
+    /// The basket does not satisfy the promotion's requirements
+    Shortfall(Shortfall<'a>),
+
+    /// The promotion could apply, but other promotions gave a better result
+    Outcompeted {
+        /// Promotions redeemed in the same layer on items this one could have used
+        by: SmallVec<[PromotionKey; 2]>,
+    },
+}
+
+/// Report the gate that stops a promotion applying at all, if any.
+///
+/// `pools` holds the remaining balances of the shared budget pools, keyed as in
+/// `budget_pools`. Shared by the built-in promotion types before they look for
+/// a shortfall.
+pub(crate) fn gate_reason(
+    schedule: &PromotionSchedule,
+    coupon: Option<&PromotionCoupon>,
+    budget: &PromotionBudget<'_>,
+    redemption_limit: Option<u32>,
+    budget_pools: &[BudgetPoolKey],
+    pools: &BudgetPools<'_>,
+    context: &EvaluationContext,
+) -> Option<MissReason<'static>> {
+    if !schedule.is_active_in(context) {
+        return Some(MissReason::NotScheduled);
+    }
+
+    if !coupon.is_none_or(|coupon| coupon.is_presented_in(context)) {
+        return Some(MissReason::CouponNotPresented);
+    }
+
+    if redemption_limit == Some(0) || is_spent(budget) {
+        return Some(MissReason::BudgetExhausted);
+    }
+
+    budget_pools
+        .iter()
+        .find(|&&pool_key| pools.get(pool_key).is_some_and(is_spent))
+        .map(|&pool_key| MissReason::PoolExhausted(pool_key))
+}
+
+/// Whether a budget's remaining balance allows no redemption at all.
+pub(crate) fn is_spent(budget: &PromotionBudget<'_>) -> bool {
+    budget.redemption_limit == Some(0)
+        || budget
+            .monetary_limit
+            .as_ref()
+            .is_some_and(|limit| limit.to_minor_units() <= 0)
+}
+
+/// Items in the group matching a qualification in the group's context.
+pub(crate) fn qualifying_items<'g, 'b, T: TagCollection>(
+    item_group: &'g ItemGroup<'b, T>,
+    qualification: &'g Qualification<T>,
+) -> impl Iterator<Item = &'g Item<'b, T>> {
+    item_group
+        .iter()
+        .filter(|item| qualification.matches_in_context(item.tags(), item_group.context()))
+}
+
+/// Total units in the group matching a qualification.
+pub(crate) fn qualifying_units<T: TagCollection>(
+    item_group: &ItemGroup<'_, T>,
+    qualification: &Qualification<T>,
+) -> u32 {
+    qualifying_items(item_group, qualification)
+        .fold(0_u32, |count, item| count.saturating_add(item.quantity()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+
+    use rusty_money::iso::GBP;
+    use testresult::TestResult;
+
+    use crate::{products::ProductKey, tags::string::StringTagCollection};
+
+    use super::*;
+
+    #[test]
+    fn gate_reason_checks_schedule_coupon_then_budget() -> TestResult {
+        let context = EvaluationContext::new();
+        let pools = BudgetPools::default();
+        let always = PromotionSchedule::always();
+        let unlimited = PromotionBudget::unlimited();
+
+        let expired = PromotionSchedule::between(None, Some("2020-01-01T00:00:00Z".parse()?));
+
+        assert_eq!(
+            gate_reason(&expired, None, &unlimited, None, &[], &pools, &context),
+            Some(MissReason::NotScheduled)
+        );
+
+        let coupon = PromotionCoupon::single_use(["SAVE10"]);
+
+        assert_eq!(
+            gate_reason(
+                &always,
+                Some(&coupon),
+                &unlimited,
+                Some(1),
+                &[],
+                &pools,
+                &context
+            ),
+            Some(MissReason::CouponNotPresented)
+        );
+
+        assert_eq!(
+            gate_reason(&always, None, &unlimited, Some(0), &[], &pools, &context),
+            Some(MissReason::BudgetExhausted)
+        );
+
+        let spent = PromotionBudget::with_monetary_limit(Money::from_minor(0, GBP));
+
+        assert_eq!(
+            gate_reason(&always, None, &spent, None, &[], &pools, &context),
+            Some(MissReason::BudgetExhausted)
+        );
+
+        assert_eq!(
+            gate_reason(&always, None, &unlimited, None, &[], &pools, &context),
+            None
+        );
+
+        // A shared pool with nothing left stops the promotion too.
+        let mut pools = BudgetPools::default();
+        let drained = pools.insert(PromotionBudget::with_both_limits(
+            3,
+            Money::from_minor(0, GBP),
+        ));
+        let open = pools.insert(PromotionBudget::with_redemption_limit(1));
+
+        assert_eq!(
+            gate_reason(&always, None, &unlimited, None, &[open], &pools, &context),
+            None
+        );
+        assert_eq!(
+            gate_reason(
+                &always,
+                None,
+                &unlimited,
+                None,
+                &[open, drained],
+                &pools,
+                &context
+            ),
+            Some(MissReason::PoolExhausted(drained))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn qualifying_units_counts_quantities() {
+        let item_group = ItemGroup::new(
+            [
+                Item::with_tags(
+                    ProductKey::default(),
+                    Money::from_minor(100, GBP),
+                    StringTagCollection::from_strs(&["a"]),
+                )
+                .with_quantity(NonZeroU32::new(3).expect("non-zero quantity")),
+                Item::with_tags(
+                    ProductKey::default(),
+                    Money::from_minor(100, GBP),
+                    StringTagCollection::from_strs(&["b"]),
+                ),
+            ]
+            .into_iter()
+            .collect(),
+            GBP,
+        );
+
+        let qualification = Qualification::match_any(StringTagCollection::from_strs(&["a"]));
+
+        assert_eq!(qualifying_units(&item_group, &qualification), 3);
+    }
+}
@@ -0,0 +1,524 @@
+//! Promotion Funding
+//!
+//! Who pays for the discounts a promotion gives, and how much each party pays
+//! towards each redemption.
+
+use std::fmt;
+
+use decimal_percentage::Percentage;
+use rustc_hash::FxHashMap;
+use rusty_money::{Money, MoneyError, iso::Currency};
+use smallvec::SmallVec;
+use thiserror::Error;
+
+use crate::{
+    discounts::percent_of_minor,
+    promotions::{PromotionKey, redemptions::PromotionRedemption},
+    solvers::ilp::promotions::ILPPromotion,
+};
+
+/// Errors building promotion funding
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum FundingError {
+    /// Percentage shares add up to more than the whole discount
+    #[error("supplier percentage shares add up to {0}, more than the whole discount")]
+    PercentagesOverWhole(Percentage),
+}
+
+/// The party funding a promotion's discounts
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum PromotionFunding {
+    /// The retailer absorbs the discount
+    #[default]
+    Retailer,
+
+    /// The named supplier reimburses the discount
+    Supplier(String),
+
+    /// Suppliers each reimburse part of every redemption, and the retailer absorbs
+    /// the rest
+    ///
+    /// Percentages are of the whole discount. Shares are taken in order, each
+    /// capped at what is left of it. Build with [`PromotionFunding::split`], which
+    /// checks the percentages.
+    Split(SmallVec<[SupplierShare; 2]>),
+}
+
+/// Funding of promotions that do not say who pays for them.
+pub(crate) static RETAILER_FUNDED: PromotionFunding = PromotionFunding::Retailer;
+
+impl PromotionFunding {
+    /// Create funding by the named supplier
+    pub fn supplier(name: impl Into<String>) -> Self {
+        Self::Supplier(name.into())
+    }
+
+    /// Create funding split between suppliers, with the retailer paying the rest
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FundingError::PercentagesOverWhole`] if the percentage shares add
+    /// up to more than 100%.
+    pub fn split(shares: impl IntoIterator<Item = SupplierShare>) -> Result<Self, FundingError> {
+        let shares: SmallVec<[SupplierShare; 2]> = shares.into_iter().collect();
+
+        let percent = shares
+            .iter()
+            .filter_map(|share| match share.contribution {
+                FundingContribution::Percentage(percent) => Some(percent),
+                FundingContribution::AmountPerRedemption(_) => None,
+            })
+            .fold(Percentage::from(0.0), |total, percent| total + percent);
+
+        if percent > Percentage::from(1.0) {
+            return Err(FundingError::PercentagesOverWhole(percent));
+        }
+
+        Ok(Self::Split(shares))
+    }
+
+    /// Check whether a supplier pays for any of the discount
+    #[must_use]
+    pub fn is_supplier_funded(&self) -> bool {
+        match self {
+            Self::Retailer => false,
+            Self::Supplier(_) => true,
+            Self::Split(shares) => !shares.is_empty(),
+        }
+    }
+
+    /// Return the name of the supplier paying for the whole discount, if any
+    #[must_use]
+    pub fn supplier_name(&self) -> Option<&str> {
+        match self {
+            Self::Retailer | Self::Split(_) => None,
+            Self::Supplier(name) => Some(name),
+        }
+    }
+
+    /// Split the `discount` given by `redemptions` redemptions between the parties
+    /// funding them.
+    ///
+    /// Fixed contributions are paid once per redemption. Parties with nothing to
+    /// pay are left out, so a redemption without a discount has no funding at all.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`MoneyError`] if a fixed contribution is in a different currency
+    /// to the discount, or a percentage of it cannot be calculated.
+    pub fn attribute<'a>(
+        &self,
+        discount: Money<'a, Currency>,
+        redemptions: u32,
+    ) -> Result<SmallVec<[FundedAmount<'a>; 2]>, MoneyError> {
+        let currency = discount.currency();
+        let whole = discount.to_minor_units().max(0);
+        let mut remaining = whole;
+        let mut funded: SmallVec<[FundedAmount<'a>; 2]> = SmallVec::new();
+
+        let shares: &[SupplierShare] = match self {
+            Self::Retailer => &[],
+            Self::Supplier(name) => {
+                if remaining > 0 {
+                    funded.push(FundedAmount {
+                        party: FundingParty::Supplier(name.clone()),
+                        amount: Money::from_minor(remaining, currency),
+                    });
+                }
+
+                return Ok(funded);
+            }
+            Self::Split(shares) => shares,
+        };
+
+        for share in shares {
+            let contribution = match &share.contribution {
+                FundingContribution::Percentage(percent) => {
+                    percent_of_minor(percent, whole).map_err(|_err| MoneyError::Overflow)?
+                }
+                FundingContribution::AmountPerRedemption(amount) => {
+                    if amount.currency() != currency {
+                        return Err(MoneyError::CurrencyMismatch {
+                            expected: currency.iso_alpha_code,
+                            actual: amount.currency().iso_alpha_code,
+                        });
+                    }
+
+                    amount
+                        .to_minor_units()
+                        .saturating_mul(i64::from(redemptions))
+                }
+            }
+            .clamp(0, remaining);
+
+            if contribution > 0 {
+                funded.push(FundedAmount {
+                    party: FundingParty::Supplier(share.supplier.clone()),
+                    amount: Money::from_minor(contribution, currency),
+                });
+
+                remaining -= contribution;
+            }
+        }
+
+        if remaining > 0 {
+            funded.push(FundedAmount {
+                party: FundingParty::Retailer,
+                amount: Money::from_minor(remaining, currency),
+            });
+        }
+
+        Ok(funded)
+    }
+}
+
+/// One supplier's part in split funding
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SupplierShare {
+    supplier: String,
+    contribution: FundingContribution,
+}
+
+impl SupplierShare {
+    /// The named supplier pays `percent` of each redemption's discount
+    pub fn percentage(supplier: impl Into<String>, percent: Percentage) -> Self {
+        Self {
+            supplier: supplier.into(),
+            contribution: FundingContribution::Percentage(percent),
+        }
+    }
+
+    /// The named supplier pays a fixed `amount` towards each redemption
+    pub fn amount_per_redemption(
+        supplier: impl Into<String>,
+        amount: Money<'static, Currency>,
+    ) -> Self {
+        Self {
+            supplier: supplier.into(),
+            contribution: FundingContribution::AmountPerRedemption(amount),
+        }
+    }
+
+    /// Return the supplier's name
+    #[must_use]
+    pub fn supplier(&self) -> &str {
+        &self.supplier
+    }
+
+    /// Return how much the supplier pays
+    #[must_use]
+    pub fn contribution(&self) -> &FundingContribution {
+        &self.contribution
+    }
+}
+
+/// How much a supplier pays towards each redemption
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FundingContribution {
+    /// A percentage of what is left of the redemption's discount
+    Percentage(Percentage),
+
+    /// A fixed amount per redemption, capped at what is left of its discount
+    AmountPerRedemption(Money<'static, Currency>),
+}
+
+/// A party paying for discounts
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum FundingParty {
+    /// The retailer
+    Retailer,
+
+    /// The named supplier
+    Supplier(String),
+}
+
+impl fmt::Display for FundingParty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Retailer => f.write_str("Retailer"),
+            Self::Supplier(name) => f.write_str(name),
+        }
+    }
+}
+
+/// An amount of discount funded by one party
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FundedAmount<'a> {
+    /// Party paying the amount
+    pub party: FundingParty,
+
+    /// Amount of the discount they pay
+    pub amount: Money<'a, Currency>,
+}
+
+/// Attribute the discount of every redemption in `redemptions` to its funders.
+///
+/// Each redemption's discount is split between its promotion's funders as a
+/// whole, then the amounts are handed out across the item lines in the
+/// redemption in item order, so each line's funding adds up to its savings.
+/// A promotion that redeems each unit on its own counts every unit of the line
+/// as a redemption. Promotions `promotion` does not know are treated as retailer
+/// funded.
+///
+/// # Errors
+///
+/// Returns a [`MoneyError`] if a redemption's savings or funding cannot be
+/// calculated.
+pub(crate) fn attribute_funding<'a, 'r, 'f>(
+    redemptions: impl IntoIterator<Item = &'r mut PromotionRedemption<'a>>,
+    promotion: impl Fn(PromotionKey) -> Option<&'f (dyn ILPPromotion + 'f)>,
+) -> Result<(), MoneyError>
+where
+    'a: 'r,
+{
+    let mut groups: FxHashMap<
+        (PromotionKey, usize),
+        SmallVec<[&'r mut PromotionRedemption<'a>; 4]>,
+    > = FxHashMap::default();
+
+    for redemption in redemptions {
+        groups
+            .entry((redemption.promotion_key, redemption.redemption_idx))
+            .or_default()
+            .push(redemption);
+    }
+
+    for ((promotion_key, _redemption_idx), mut entries) in groups {
+        entries.sort_by_key(|entry| entry.item_idx);
+
+        let Some(first) = entries.first() else {
+            continue;
+        };
+
+        let currency = first.original_price.currency();
+        let mut savings: SmallVec<[i64; 4]> = SmallVec::with_capacity(entries.len());
+
+        for entry in &entries {
+            savings.push(entry.total_savings()?.to_minor_units().max(0));
+        }
+
+        let promotion = promotion(promotion_key);
+
+        // Lines of a per-unit promotion carry one redemption for each unit.
+        let redemptions = if promotion.is_some_and(ILPPromotion::redeems_each_unit) {
+            entries.iter().map(|entry| entry.quantity).sum()
+        } else {
+            1
+        };
+
+        let discount = Money::from_minor(savings.iter().sum(), currency);
+        let mut shares = promotion
+            .map_or(&RETAILER_FUNDED, ILPPromotion::funding)
+            .attribute(discount, redemptions)?
+            .into_iter()
+            .map(|share| (share.party, share.amount.to_minor_units()))
+            .peekable();
+
+        for (entry, mut unfunded) in entries.into_iter().zip(savings) {
+            entry.funding.clear();
+
+            while unfunded > 0 {
+                let Some((party, left)) = shares.peek_mut() else {
+                    break;
+                };
+
+                let amount = (*left).min(unfunded);
+
+                entry.funding.push(FundedAmount {
+                    party: party.clone(),
+                    amount: Money::from_minor(amount, currency),
+                });
+
+                unfunded -= amount;
+                *left -= amount;
+
+                if *left == 0 {
+                    shares.next();
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use rusty_money::iso::{GBP, USD};
+    use smallvec::smallvec;
+    use testresult::TestResult;
+
+    use crate::{
+        discounts::SimpleDiscount,
+        promotions::{
+            budget::PromotionBudget, qualification::Qualification,
+            types::PositionalDiscountPromotion,
+        },
+    };
+
+    use super::*;
+
+    fn supplier(name: &str, minor: i64) -> FundedAmount<'static> {
+        FundedAmount {
+            party: FundingParty::Supplier(name.to_string()),
+            amount: Money::from_minor(minor, GBP),
+        }
+    }
+
+    fn retailer(minor: i64) -> FundedAmount<'static> {
+        FundedAmount {
+            party: FundingParty::Retailer,
+            amount: Money::from_minor(minor, GBP),
+        }
+    }
+
+    #[test]
+    fn single_parties_fund_the_whole_discount() -> TestResult {
+        let discount = Money::from_minor(250, GBP);
+
+        assert_eq!(
+            PromotionFunding::Retailer
+                .attribute(discount, 1)?
+                .as_slice(),
+            [retailer(250)]
+        );
+        assert_eq!(
+            PromotionFunding::supplier("Acme")
+                .attribute(discount, 1)?
+                .as_slice(),
+            [supplier("Acme", 250)]
+        );
+        assert!(
+            PromotionFunding::supplier("Acme")
+                .attribute(Money::from_minor(0, GBP), 1)?
+                .is_empty()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn split_shares_are_taken_in_order_and_capped() -> TestResult {
+        let funding = PromotionFunding::split([
+            SupplierShare::amount_per_redemption("Acme", Money::from_minor(100, GBP)),
+            SupplierShare::percentage("Brewers", Percentage::from(0.5)),
+        ])?;
+
+        // £1 from Acme, then half of the whole £3 from Brewers.
+        assert_eq!(
+            funding
+                .attribute(Money::from_minor(300, GBP), 1)?
+                .as_slice(),
+            [
+                supplier("Acme", 100),
+                supplier("Brewers", 150),
+                retailer(50)
+            ]
+        );
+
+        // Brewers' half of £1.50 is capped at the 50p Acme leaves.
+        assert_eq!(
+            funding
+                .attribute(Money::from_minor(150, GBP), 1)?
+                .as_slice(),
+            [supplier("Acme", 100), supplier("Brewers", 50)]
+        );
+
+        // The fixed amount covers the whole of a smaller discount.
+        assert_eq!(
+            funding.attribute(Money::from_minor(60, GBP), 1)?.as_slice(),
+            [supplier("Acme", 60)]
+        );
+
+        // Two redemptions get the fixed amount twice.
+        assert_eq!(
+            funding
+                .attribute(Money::from_minor(600, GBP), 2)?
+                .as_slice(),
+            [
+                supplier("Acme", 200),
+                supplier("Brewers", 300),
+                retailer(100)
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn split_percentages_cannot_exceed_the_whole_discount() {
+        let over = PromotionFunding::split([
+            SupplierShare::percentage("Acme", Percentage::from(0.6)),
+            SupplierShare::amount_per_redemption("Brewers", Money::from_minor(100, GBP)),
+            SupplierShare::percentage("Hatters", Percentage::from(0.5)),
+        ]);
+
+        assert!(matches!(over, Err(FundingError::PercentagesOverWhole(_))));
+
+        assert!(
+            PromotionFunding::split([
+                SupplierShare::percentage("Acme", Percentage::from(0.5)),
+                SupplierShare::percentage("Hatters", Percentage::from(0.5)),
+            ])
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn fixed_contributions_must_match_the_discount_currency() -> TestResult {
+        let funding = PromotionFunding::split([SupplierShare::amount_per_redemption(
+            "Acme",
+            Money::from_minor(100, USD),
+        )])?;
+
+        assert!(matches!(
+            funding.attribute(Money::from_minor(300, GBP), 1),
+            Err(MoneyError::CurrencyMismatch { .. })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn redemption_funding_is_spread_over_its_lines() -> TestResult {
+        let key = PromotionKey::default();
+        let line = |item_idx, original, final_price| PromotionRedemption {
+            promotion_key: key,
+            item_idx,
+            redemption_idx: 0,
+            original_price: Money::from_minor(original, GBP),
+            final_price: Money::from_minor(final_price, GBP),
+            quantity: 1,
+            funding: SmallVec::new(),
+            floor_limited: false,
+        };
+
+        let mut redemptions = [line(1, 300, 100), line(0, 200, 100)];
+        let funding = PromotionFunding::split([SupplierShare::amount_per_redemption(
+            "Acme",
+            Money::from_minor(150, GBP),
+        )])?;
+
+        // Every two items, the second for a fixed price.
+        let pairs = PositionalDiscountPromotion::new(
+            key,
+            Qualification::match_all(),
+            2,
+            smallvec![1],
+            SimpleDiscount::AmountOverride(Money::from_minor(100, GBP)),
+            PromotionBudget::unlimited(),
+        )
+        .with_funding(funding);
+
+        attribute_funding(&mut redemptions, |_key| Some(&pairs))?;
+
+        // Item 0 saves £1, all from Acme; item 1 saves £2, 50p from Acme.
+        assert_eq!(redemptions[1].funding.as_slice(), [supplier("Acme", 100)]);
+        assert_eq!(
+            redemptions[0].funding.as_slice(),
+            [supplier("Acme", 50), retailer(150)]
+        );
+
+        Ok(())
+    }
+}
@@ -7,9 +7,14 @@ use slotmap::{SecondaryMap, new_key_type};
 use crate::{graph::PromotionLayerKey, solvers::ilp::ILPPromotion};
 
 pub mod budget;
+pub mod coupon;
+pub mod exclusion;
+pub mod explain;
+pub mod funding;
 pub mod prelude;
 pub mod qualification;
 pub mod redemptions;
+pub mod schedule;
 pub mod types;
 
 new_key_type! {
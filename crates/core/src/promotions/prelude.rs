@@ -2,6 +2,11 @@
 //!
 //! Use this when implementing custom promotion types.
 
-pub use crate::solvers::ilp::{
-    ILPPromotion, ILPPromotionVars, ILPState, PromotionVars, i64_to_f64_exact,
+pub use crate::solvers::{
+    exhaustive::ExhaustivePromotion,
+    greedy::{Allowance, FreeUnit, GreedyPromotion, GreedyRedemption},
+    ilp::{
+        ILPPromotion, ILPPromotionVars, ILPState, ItemSignature, PriceOutcomes, PromotionVars,
+        i64_to_f64_exact,
+    },
 };
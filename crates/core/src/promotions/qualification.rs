@@ -1,12 +1,19 @@
 //! Promotion Qualification Rules
 //!
 //! Nested boolean tag qualification rules used by promotions and slots.
+//!
+//! Besides item tags, rules can test the basket-level [`EvaluationContext`]
+//! (customer segments, channel, store, time and arbitrary attributes).
 
+use jiff::Timestamp;
 use smallvec::{SmallVec, smallvec};
 
-use crate::tags::{collection::TagCollection, string::StringTagCollection};
+use crate::{
+    context::EvaluationContext,
+    tags::{collection::TagCollection, string::StringTagCollection},
+};
 
-/// Qualification expression for item-tag matching.
+/// Qualification expression for item-tag and context matching.
 #[derive(Debug, Clone)]
 pub struct Qualification<T: TagCollection = StringTagCollection> {
     /// How `rules` are combined.
@@ -47,6 +54,42 @@ pub enum QualificationRule<T: TagCollection = StringTagCollection> {
         tags: T,
     },
 
+    /// Customer must belong to at least one listed segment.
+    CustomerSegment {
+        /// Segments where any one can match.
+        segments: StringTagCollection,
+    },
+
+    /// Basket must be evaluated on one of the listed channels.
+    Channel {
+        /// Accepted channels.
+        channels: SmallVec<[String; 2]>,
+    },
+
+    /// Basket must be evaluated for one of the listed stores.
+    Store {
+        /// Accepted store ids.
+        store_ids: SmallVec<[String; 2]>,
+    },
+
+    /// Basket must be evaluated within a time window.
+    TimeWindow {
+        /// Inclusive start of the window; `None` is unbounded.
+        starts_at: Option<Timestamp>,
+
+        /// Exclusive end of the window; `None` is unbounded.
+        ends_at: Option<Timestamp>,
+    },
+
+    /// Context attribute must be set, and equal one of `values` when any are listed.
+    Attribute {
+        /// Attribute key.
+        key: String,
+
+        /// Accepted values; empty means any value.
+        values: SmallVec<[String; 2]>,
+    },
+
     /// Nested qualification group.
     Group(Box<Qualification<T>>),
 }
@@ -80,16 +123,28 @@ impl<T: TagCollection> Qualification<T> {
         }
     }
 
-    /// Evaluate the qualification against an item's tags.
+    /// Evaluate the qualification against an item's tags in an empty context.
     #[must_use]
     pub fn matches(&self, item_tags: &T) -> bool {
+        self.matches_in_context(item_tags, &EvaluationContext::default())
+    }
+
+    /// Evaluate the qualification against an item's tags and the evaluation context.
+    #[must_use]
+    pub fn matches_in_context(&self, item_tags: &T, context: &EvaluationContext) -> bool {
         if self.rules.is_empty() {
             return true;
         }
 
         match self.op {
-            BoolOp::And => self.rules.iter().all(|rule| rule.matches(item_tags)),
-            BoolOp::Or => self.rules.iter().any(|rule| rule.matches(item_tags)),
+            BoolOp::And => self
+                .rules
+                .iter()
+                .all(|rule| rule.matches(item_tags, context)),
+            BoolOp::Or => self
+                .rules
+                .iter()
+                .any(|rule| rule.matches(item_tags, context)),
         }
     }
 }
@@ -102,7 +157,7 @@ impl<T: TagCollection> Default for Qualification<T> {
 
 impl<T: TagCollection> QualificationRule<T> {
     #[must_use]
-    fn matches(&self, item_tags: &T) -> bool {
+    fn matches(&self, item_tags: &T, context: &EvaluationContext) -> bool {
         match self {
             Self::HasAll { tags } => {
                 if tags.is_empty() {
@@ -113,7 +168,25 @@ impl<T: TagCollection> QualificationRule<T> {
             }
             Self::HasAny { tags } => !tags.is_empty() && item_tags.intersects(tags),
             Self::HasNone { tags } => tags.is_empty() || !item_tags.intersects(tags),
-            Self::Group(group) => group.matches(item_tags),
+            Self::CustomerSegment { segments } => {
+                !segments.is_empty() && context.customer_segments().intersects(segments)
+            }
+            Self::Channel { channels } => context
+                .channel()
+                .is_some_and(|channel| channels.iter().any(|c| c == channel)),
+            Self::Store { store_ids } => context
+                .store_id()
+                .is_some_and(|store_id| store_ids.iter().any(|s| s == store_id)),
+            Self::TimeWindow { starts_at, ends_at } => {
+                context.timestamp().is_some_and(|timestamp| {
+                    starts_at.is_none_or(|start| timestamp >= start)
+                        && ends_at.is_none_or(|end| timestamp < end)
+                })
+            }
+            Self::Attribute { key, values } => context
+                .attribute(key)
+                .is_some_and(|value| values.is_empty() || values.iter().any(|v| v == value)),
+            Self::Group(group) => group.matches_in_context(item_tags, context),
         }
     }
 }
@@ -121,6 +194,7 @@ impl<T: TagCollection> QualificationRule<T> {
 #[cfg(test)]
 mod tests {
     use smallvec::smallvec;
+    use testresult::TestResult;
 
     use crate::tags::string::StringTagCollection;
 
@@ -165,4 +239,108 @@ mod tests {
             "peak", "snack", "excluded"
         ])));
     }
+
+    #[test]
+    fn context_rules_do_not_match_in_empty_context() {
+        let tags = StringTagCollection::from_strs(&["snack"]);
+
+        for rule in [
+            QualificationRule::CustomerSegment {
+                segments: StringTagCollection::from_strs(&["loyalty"]),
+            },
+            QualificationRule::Channel {
+                channels: smallvec!["online".to_string()],
+            },
+            QualificationRule::Store {
+                store_ids: smallvec!["42".to_string()],
+            },
+            QualificationRule::TimeWindow {
+                starts_at: None,
+                ends_at: None,
+            },
+            QualificationRule::Attribute {
+                key: "tier".to_string(),
+                values: SmallVec::new(),
+            },
+        ] {
+            let qualification = Qualification::new(BoolOp::And, smallvec![rule]);
+
+            assert!(!qualification.matches(&tags));
+        }
+    }
+
+    #[test]
+    fn context_rules_match_against_context() -> TestResult {
+        let tags = StringTagCollection::from_strs(&["snack"]);
+
+        let context = EvaluationContext::new()
+            .with_customer_segments(StringTagCollection::from_strs(&["loyalty", "staff"]))
+            .with_channel("online")
+            .with_store_id("42")
+            .with_timestamp("2026-03-01T12:00:00Z".parse()?)
+            .with_attribute("tier", "gold");
+
+        let matches = |rule: QualificationRule| {
+            Qualification::new(BoolOp::And, smallvec![rule]).matches_in_context(&tags, &context)
+        };
+
+        assert!(matches(QualificationRule::CustomerSegment {
+            segments: StringTagCollection::from_strs(&["loyalty"]),
+        }));
+        assert!(!matches(QualificationRule::CustomerSegment {
+            segments: StringTagCollection::from_strs(&["trade"]),
+        }));
+        assert!(matches(QualificationRule::Channel {
+            channels: smallvec!["in-store".to_string(), "online".to_string()],
+        }));
+        assert!(!matches(QualificationRule::Store {
+            store_ids: smallvec!["7".to_string()],
+        }));
+        assert!(matches(QualificationRule::TimeWindow {
+            starts_at: Some("2026-03-01T00:00:00Z".parse()?),
+            ends_at: Some("2026-03-02T00:00:00Z".parse()?),
+        }));
+        assert!(!matches(QualificationRule::TimeWindow {
+            starts_at: None,
+            ends_at: Some("2026-03-01T12:00:00Z".parse()?),
+        }));
+        assert!(matches(QualificationRule::Attribute {
+            key: "tier".to_string(),
+            values: SmallVec::new(),
+        }));
+        assert!(!matches(QualificationRule::Attribute {
+            key: "tier".to_string(),
+            values: smallvec!["silver".to_string()],
+        }));
+
+        Ok(())
+    }
+
+    #[test]
+    fn context_rules_combine_with_tag_rules() {
+        let qualification = Qualification::new(
+            BoolOp::And,
+            smallvec![
+                QualificationRule::HasAny {
+                    tags: StringTagCollection::from_strs(&["snack"])
+                },
+                QualificationRule::Group(Box::new(Qualification::new(
+                    BoolOp::Or,
+                    smallvec![QualificationRule::Channel {
+                        channels: smallvec!["online".to_string()],
+                    }]
+                )))
+            ],
+        );
+
+        let online = EvaluationContext::new().with_channel("online");
+        let in_store = EvaluationContext::new().with_channel("in-store");
+
+        let snack = StringTagCollection::from_strs(&["snack"]);
+        let drink = StringTagCollection::from_strs(&["drink"]);
+
+        assert!(qualification.matches_in_context(&snack, &online));
+        assert!(!qualification.matches_in_context(&snack, &in_store));
+        assert!(!qualification.matches_in_context(&drink, &online));
+    }
 }
@@ -4,8 +4,9 @@ use decimal_percentage::Percentage;
 use num_traits::FromPrimitive;
 use rust_decimal::Decimal;
 use rusty_money::{Money, MoneyError, iso::Currency};
+use smallvec::SmallVec;
 
-use crate::promotions::PromotionKey;
+use crate::promotions::{PromotionKey, funding::FundedAmount};
 
 /// Result of applying a promotion to an item
 #[derive(Debug, Clone)]
@@ -19,23 +20,54 @@ pub struct PromotionRedemption<'a> {
     /// ID assigned to a redemption of items in the same promotion
     pub redemption_idx: usize,
 
-    /// Original price of the item
+    /// Original unit price of the item
     pub original_price: Money<'a, Currency>,
 
-    /// Final price after discount
+    /// Final unit price after discount
     pub final_price: Money<'a, Currency>,
+
+    /// Number of units of the item line claimed by this redemption
+    pub quantity: u32,
+
+    /// Who pays for the savings across every unit claimed by this redemption
+    ///
+    /// Filled in once the whole result is known, since a redemption's funding
+    /// is split across all of its item lines.
+    pub funding: SmallVec<[FundedAmount<'a>; 2]>,
+
+    /// Whether the item's price floor kept the final price above what the
+    /// promotion would otherwise have given
+    pub floor_limited: bool,
 }
 
-impl<'a> PromotionRedemption<'_> {
-    /// Calculate the item savings from this promotion redemption
+impl<'a> PromotionRedemption<'a> {
+    /// Calculate the per-unit item savings from this promotion redemption
     ///
     /// # Errors
     ///
     /// Returns an error if the original price or final price cannot be subtracted.
-    pub fn savings(&'a self) -> Result<Money<'a, Currency>, MoneyError> {
+    pub fn savings(&self) -> Result<Money<'a, Currency>, MoneyError> {
         self.original_price.sub(self.final_price)
     }
 
+    /// Calculate the savings across every unit claimed by this redemption
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the savings cannot be calculated or multiplied.
+    pub fn total_savings(&self) -> Result<Money<'a, Currency>, MoneyError> {
+        self.savings()?.mul(self.quantity)
+    }
+
+    /// Calculate the final price across every unit claimed by this redemption
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the final price cannot be multiplied.
+    pub fn total_final_price(&self) -> Result<Money<'a, Currency>, MoneyError> {
+        self.final_price.mul(self.quantity)
+    }
+
     /// Calculates the savings made by applying the promotions as a percentage
     ///
     /// # Errors
@@ -75,6 +107,9 @@ mod tests {
             redemption_idx: 0,
             original_price: Money::from_minor(200, GBP),
             final_price: Money::from_minor(150, GBP),
+            quantity: 1,
+            funding: SmallVec::new(),
+            floor_limited: false,
         };
 
         assert_eq!(app.savings(), Ok(Money::from_minor(50, GBP)));
@@ -88,6 +123,9 @@ mod tests {
             redemption_idx: 0,
             original_price: Money::from_minor(200, USD),
             final_price: Money::from_minor(150, GBP),
+            quantity: 1,
+            funding: SmallVec::new(),
+            floor_limited: false,
         };
 
         assert_eq!(
@@ -107,6 +145,9 @@ mod tests {
             redemption_idx: 0,
             original_price: Money::from_minor(0, GBP),
             final_price: Money::from_minor(0, GBP),
+            quantity: 1,
+            funding: SmallVec::new(),
+            floor_limited: false,
         };
 
         assert_eq!(app.savings_percent(), Ok(Percentage::from(0.0)));
@@ -120,6 +161,9 @@ mod tests {
             redemption_idx: 0,
             original_price: Money::from_minor(200, GBP),
             final_price: Money::from_minor(150, GBP),
+            quantity: 1,
+            funding: SmallVec::new(),
+            floor_limited: false,
         };
 
         let percent = app.savings_percent()?;
@@ -0,0 +1,277 @@
+//! Promotion Schedules
+//!
+//! When a promotion is live: an optional validity period plus optional
+//! recurring weekly windows (e.g. happy hour 16:00–18:00 on weekdays),
+//! interpreted in the schedule's time zone.
+
+use jiff::{
+    Timestamp,
+    civil::{Time, Weekday},
+    tz::TimeZone,
+};
+use smallvec::SmallVec;
+
+use crate::context::EvaluationContext;
+
+/// A recurring daily window on selected weekdays.
+///
+/// The start is inclusive and the end exclusive. When `ends_at` is not after
+/// `starts_at` the window runs past midnight into the following day, so
+/// 22:00–02:00 on Friday covers Friday night and the early hours of Saturday.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecurringWindow {
+    /// Weekdays the window opens on; empty means every day
+    pub days: SmallVec<[Weekday; 7]>,
+
+    /// Local time the window opens
+    pub starts_at: Time,
+
+    /// Local time the window closes
+    pub ends_at: Time,
+}
+
+impl RecurringWindow {
+    /// Create a window opening on the given weekdays.
+    pub fn new(days: impl IntoIterator<Item = Weekday>, starts_at: Time, ends_at: Time) -> Self {
+        Self {
+            days: days.into_iter().collect(),
+            starts_at,
+            ends_at,
+        }
+    }
+
+    /// Create a window opening every day.
+    #[must_use]
+    pub fn daily(starts_at: Time, ends_at: Time) -> Self {
+        Self {
+            days: SmallVec::new(),
+            starts_at,
+            ends_at,
+        }
+    }
+
+    /// Check whether a local weekday and time fall inside the window.
+    #[must_use]
+    pub fn contains(&self, weekday: Weekday, time: Time) -> bool {
+        let opens_on = |day: Weekday| self.days.is_empty() || self.days.contains(&day);
+
+        if self.starts_at < self.ends_at {
+            return opens_on(weekday) && time >= self.starts_at && time < self.ends_at;
+        }
+
+        // Overnight: the tail end belongs to the window that opened the day before.
+        (opens_on(weekday) && time >= self.starts_at)
+            || (opens_on(weekday.previous()) && time < self.ends_at)
+    }
+}
+
+/// When a promotion may be applied.
+///
+/// An unrestricted schedule (the default) is always active. Otherwise the
+/// promotion is active at instants inside the validity period (start
+/// inclusive, end exclusive) and, if any windows are set, inside at least one
+/// of them. Restricted schedules need an evaluation timestamp; without one
+/// the promotion is treated as inactive.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PromotionSchedule {
+    /// First instant the promotion is valid
+    pub valid_from: Option<Timestamp>,
+
+    /// Instant the promotion stops being valid
+    pub valid_until: Option<Timestamp>,
+
+    /// Time zone the recurring windows are interpreted in
+    pub time_zone: TimeZone,
+
+    /// Recurring windows; empty means no recurring restriction
+    pub windows: SmallVec<[RecurringWindow; 2]>,
+}
+
+impl PromotionSchedule {
+    /// Create a schedule with no restrictions
+    #[must_use]
+    pub fn always() -> Self {
+        Self {
+            valid_from: None,
+            valid_until: None,
+            time_zone: TimeZone::UTC,
+            windows: SmallVec::new(),
+        }
+    }
+
+    /// Create a schedule valid between two instants
+    #[must_use]
+    pub fn between(valid_from: Option<Timestamp>, valid_until: Option<Timestamp>) -> Self {
+        Self {
+            valid_from,
+            valid_until,
+            ..Self::always()
+        }
+    }
+
+    /// Set the time zone recurring windows are interpreted in
+    #[must_use]
+    pub fn with_time_zone(mut self, time_zone: TimeZone) -> Self {
+        self.time_zone = time_zone;
+        self
+    }
+
+    /// Add a recurring window
+    #[must_use]
+    pub fn with_window(mut self, window: RecurringWindow) -> Self {
+        self.windows.push(window);
+        self
+    }
+
+    /// Check if this schedule has any restrictions
+    #[must_use]
+    pub fn is_restricted(&self) -> bool {
+        self.valid_from.is_some() || self.valid_until.is_some() || !self.windows.is_empty()
+    }
+
+    /// Check whether the promotion is active at the given instant.
+    #[must_use]
+    pub fn is_active_at(&self, timestamp: Timestamp) -> bool {
+        if self.valid_from.is_some_and(|from| timestamp < from)
+            || self.valid_until.is_some_and(|until| timestamp >= until)
+        {
+            return false;
+        }
+
+        if self.windows.is_empty() {
+            return true;
+        }
+
+        let local = timestamp.to_zoned(self.time_zone.clone());
+        let (weekday, time) = (local.weekday(), local.time());
+
+        self.windows
+            .iter()
+            .any(|window| window.contains(weekday, time))
+    }
+
+    /// Check whether the promotion is active for an evaluation context.
+    #[must_use]
+    pub fn is_active_in(&self, context: &EvaluationContext) -> bool {
+        if !self.is_restricted() {
+            return true;
+        }
+
+        context
+            .timestamp()
+            .is_some_and(|timestamp| self.is_active_at(timestamp))
+    }
+}
+
+impl Default for PromotionSchedule {
+    fn default() -> Self {
+        Self::always()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jiff::civil::time;
+    use testresult::TestResult;
+
+    use super::*;
+
+    fn happy_hour() -> RecurringWindow {
+        RecurringWindow::new(
+            [
+                Weekday::Monday,
+                Weekday::Tuesday,
+                Weekday::Wednesday,
+                Weekday::Thursday,
+                Weekday::Friday,
+            ],
+            time(16, 0, 0, 0),
+            time(18, 0, 0, 0),
+        )
+    }
+
+    #[test]
+    fn unrestricted_schedule_is_always_active() -> TestResult {
+        let schedule = PromotionSchedule::always();
+
+        assert!(!schedule.is_restricted());
+        assert!(schedule.is_active_at("2026-03-04T12:00:00Z".parse()?));
+        assert!(schedule.is_active_in(&EvaluationContext::default()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn validity_period_includes_start_and_excludes_end() -> TestResult {
+        let from: Timestamp = "2026-03-01T00:00:00Z".parse()?;
+        let until: Timestamp = "2026-03-08T00:00:00Z".parse()?;
+
+        let schedule = PromotionSchedule::between(Some(from), Some(until));
+
+        assert!(!schedule.is_active_at("2026-02-28T23:59:59Z".parse()?));
+        assert!(schedule.is_active_at(from));
+        assert!(schedule.is_active_at("2026-03-07T23:59:59Z".parse()?));
+        assert!(!schedule.is_active_at(until));
+
+        Ok(())
+    }
+
+    #[test]
+    fn recurring_window_uses_schedule_time_zone() -> TestResult {
+        let schedule = PromotionSchedule::always()
+            .with_time_zone(TimeZone::get("America/New_York")?)
+            .with_window(happy_hour());
+
+        // Wednesday 16:30 in New York (EST, UTC-5)
+        assert!(schedule.is_active_at("2026-03-04T21:30:00Z".parse()?));
+
+        // Wednesday 16:30 UTC is 11:30 in New York
+        assert!(!schedule.is_active_at("2026-03-04T16:30:00Z".parse()?));
+
+        // Saturday 16:30 in New York
+        assert!(!schedule.is_active_at("2026-03-07T21:30:00Z".parse()?));
+
+        // Closing time is exclusive
+        assert!(!schedule.is_active_at("2026-03-04T23:00:00Z".parse()?));
+
+        Ok(())
+    }
+
+    #[test]
+    fn recurring_window_follows_daylight_saving_changes() -> TestResult {
+        let schedule = PromotionSchedule::always()
+            .with_time_zone(TimeZone::get("Europe/London")?)
+            .with_window(happy_hour());
+
+        // 16:30 local is 16:30 UTC in winter and 15:30 UTC in summer.
+        assert!(schedule.is_active_at("2026-03-04T16:30:00Z".parse()?));
+        assert!(schedule.is_active_at("2026-07-08T15:30:00Z".parse()?));
+        assert!(!schedule.is_active_at("2026-07-08T17:30:00Z".parse()?));
+
+        Ok(())
+    }
+
+    #[test]
+    fn overnight_window_spills_into_next_day() {
+        let window = RecurringWindow::new([Weekday::Friday], time(22, 0, 0, 0), time(2, 0, 0, 0));
+
+        assert!(window.contains(Weekday::Friday, time(23, 0, 0, 0)));
+        assert!(window.contains(Weekday::Saturday, time(1, 59, 0, 0)));
+        assert!(!window.contains(Weekday::Saturday, time(2, 0, 0, 0)));
+        assert!(!window.contains(Weekday::Friday, time(1, 0, 0, 0)));
+        assert!(!window.contains(Weekday::Saturday, time(23, 0, 0, 0)));
+    }
+
+    #[test]
+    fn restricted_schedule_requires_a_timestamp() -> TestResult {
+        let schedule = PromotionSchedule::always()
+            .with_window(RecurringWindow::daily(time(9, 0, 0, 0), time(17, 0, 0, 0)));
+
+        assert!(!schedule.is_active_in(&EvaluationContext::default()));
+        assert!(schedule.is_active_in(
+            &EvaluationContext::new().with_timestamp("2026-03-04T12:00:00Z".parse()?)
+        ));
+
+        Ok(())
+    }
+}
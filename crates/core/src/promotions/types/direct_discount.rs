@@ -3,12 +3,25 @@
 //! A direct percentage discount, amount discount, or amount override on all qualifying items
 
 use crate::{
-    discounts::{DiscountError, SimpleDiscount, percent_of_minor},
-    items::Item,
-    promotions::{PromotionKey, budget::PromotionBudget, qualification::Qualification},
+    discounts::{
+        DiscountError, SimpleDiscount,
+        rounding::{DEFAULT_ROUNDING, RoundingPolicies, RoundingPolicy},
+    },
+    items::{Item, groups::ItemGroup},
+    promotions::{
+        PromotionKey,
+        budget::{BudgetPoolKey, PromotionBudget},
+        coupon::PromotionCoupon,
+        exclusion::PromotionExclusions,
+        explain::{Shortfall, qualifying_units},
+        funding::PromotionFunding,
+        qualification::Qualification,
+        schedule::PromotionSchedule,
+    },
     tags::{collection::TagCollection, string::StringTagCollection},
 };
 use rusty_money::{Money, iso::Currency};
+use smallvec::SmallVec;
 
 /// A discount applied directly to all participating items
 #[derive(Debug, Clone)]
@@ -17,6 +30,14 @@ pub struct DirectDiscountPromotion<'a, T: TagCollection = StringTagCollection> {
     qualification: Qualification<T>,
     discount: SimpleDiscount<'a>,
     budget: PromotionBudget<'a>,
+    schedule: PromotionSchedule,
+    coupon: Option<PromotionCoupon>,
+    budget_pools: SmallVec<[BudgetPoolKey; 1]>,
+    priority: i32,
+    funding: PromotionFunding,
+    mandatory: bool,
+    rounding: Option<RoundingPolicies>,
+    exclusions: PromotionExclusions,
 }
 
 impl<'a, T: TagCollection> DirectDiscountPromotion<'a, T> {
@@ -32,6 +53,14 @@ impl<'a, T: TagCollection> DirectDiscountPromotion<'a, T> {
             qualification,
             discount,
             budget,
+            schedule: PromotionSchedule::always(),
+            coupon: None,
+            budget_pools: SmallVec::new(),
+            priority: 0,
+            funding: PromotionFunding::Retailer,
+            mandatory: false,
+            rounding: None,
+            exclusions: PromotionExclusions::default(),
         }
     }
 
@@ -55,6 +84,122 @@ impl<'a, T: TagCollection> DirectDiscountPromotion<'a, T> {
         &self.budget
     }
 
+    /// Attach a schedule restricting when the promotion is active.
+    #[must_use]
+    pub fn with_schedule(mut self, schedule: PromotionSchedule) -> Self {
+        self.schedule = schedule;
+        self
+    }
+
+    /// Return the schedule
+    pub fn schedule(&self) -> &PromotionSchedule {
+        &self.schedule
+    }
+
+    /// Require a coupon code for the promotion to apply.
+    #[must_use]
+    pub fn with_coupon(mut self, coupon: PromotionCoupon) -> Self {
+        self.coupon = Some(coupon);
+        self
+    }
+
+    /// Return the coupon required to unlock the promotion, if any
+    pub fn coupon(&self) -> Option<&PromotionCoupon> {
+        self.coupon.as_ref()
+    }
+
+    /// Draw on a shared budget pool in addition to the promotion's own budget.
+    #[must_use]
+    pub fn with_budget_pool(mut self, pool: BudgetPoolKey) -> Self {
+        if !self.budget_pools.contains(&pool) {
+            self.budget_pools.push(pool);
+        }
+
+        self
+    }
+
+    /// Return the shared budget pools the promotion draws on
+    pub fn budget_pools(&self) -> &[BudgetPoolKey] {
+        &self.budget_pools
+    }
+
+    /// Set the priority used to prefer this promotion when totals tie.
+    #[must_use]
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Return the priority (higher numbers are preferred, default 0)
+    pub fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    /// Record who funds the promotion's discounts.
+    #[must_use]
+    pub fn with_funding(mut self, funding: PromotionFunding) -> Self {
+        self.funding = funding;
+        self
+    }
+
+    /// Return who funds the promotion's discounts
+    pub fn funding(&self) -> &PromotionFunding {
+        &self.funding
+    }
+
+    /// Mark the promotion as one the customer is always entitled to.
+    #[must_use]
+    pub fn with_mandatory(mut self, mandatory: bool) -> Self {
+        self.mandatory = mandatory;
+        self
+    }
+
+    /// Return whether the customer is always entitled to the promotion
+    pub fn is_mandatory(&self) -> bool {
+        self.mandatory
+    }
+
+    /// Round the promotion's percentage discounts with `rounding`, instead of the
+    /// policies of the graph or solver it is evaluated by.
+    #[must_use]
+    pub fn with_rounding(mut self, rounding: impl Into<RoundingPolicies>) -> Self {
+        self.rounding = Some(rounding.into());
+        self
+    }
+
+    /// Return the promotion's own rounding policies, if it has any
+    pub fn rounding(&self) -> Option<&RoundingPolicies> {
+        self.rounding.as_ref()
+    }
+
+    /// Set the exclusion groups the promotion joins and the promotions it cannot
+    /// be combined with.
+    #[must_use]
+    pub fn with_exclusions(mut self, exclusions: PromotionExclusions) -> Self {
+        self.exclusions = exclusions;
+        self
+    }
+
+    /// Return the promotion's exclusion groups and rules
+    pub fn exclusions(&self) -> &PromotionExclusions {
+        &self.exclusions
+    }
+
+    /// Return the redemption limit, combining the budget with a single-use coupon
+    pub fn redemption_limit(&self) -> Option<u32> {
+        self.coupon
+            .as_ref()
+            .map_or(self.budget.redemption_limit, |coupon| {
+                coupon.cap_redemption_limit(self.budget.redemption_limit)
+            })
+    }
+
+    /// Describe what the item group lacks for this promotion to apply, if anything.
+    pub fn shortfall<'b>(&self, item_group: &ItemGroup<'b, T>) -> Option<Shortfall<'b>> {
+        (qualifying_units(item_group, &self.qualification) == 0)
+            .then_some(Shortfall::NoQualifyingItems)
+    }
+
     /// Calculate the discounted price for a single item.
     ///
     /// # Errors
@@ -65,6 +210,25 @@ impl<'a, T: TagCollection> DirectDiscountPromotion<'a, T> {
     pub fn calculate_discounted_price(
         &self,
         item: &Item<'a, T>,
+    ) -> Result<Money<'a, Currency>, DiscountError> {
+        let rounding =
+            RoundingPolicies::resolve(self.rounding(), &DEFAULT_ROUNDING, item.price().currency());
+
+        self.calculate_rounded_price(item, rounding)
+    }
+
+    /// Calculate the discounted price for an item, rounding a percentage discount
+    /// with `rounding`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`DiscountError`] if:
+    /// - Percentage calculation overflows or cannot be safely represented.
+    /// - Money arithmetic fails (e.g., currency mismatch, negative result).
+    pub(crate) fn calculate_rounded_price(
+        &self,
+        item: &Item<'a, T>,
+        rounding: RoundingPolicy,
     ) -> Result<Money<'a, Currency>, DiscountError> {
         let discounted_minor = match &self.discount {
             SimpleDiscount::PercentageOff(pct) => {
@@ -72,7 +236,7 @@ impl<'a, T: TagCollection> DirectDiscountPromotion<'a, T> {
                 let original_minor = item.price().to_minor_units();
 
                 original_minor
-                    .checked_sub(percent_of_minor(pct, original_minor)?)
+                    .checked_sub(rounding.discount_of(pct, original_minor)?)
                     .ok_or(DiscountError::PercentConversion)?
             }
             SimpleDiscount::AmountOverride(amount) => {
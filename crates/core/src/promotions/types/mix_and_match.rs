@@ -6,10 +6,20 @@
 
 use decimal_percentage::Percentage;
 use rusty_money::{Money, iso::Currency};
+use smallvec::SmallVec;
 
 use crate::{
+    discounts::rounding::RoundingPolicies,
+    items::groups::ItemGroup,
     promotions::{
-        PromotionKey, PromotionSlotKey, budget::PromotionBudget, qualification::Qualification,
+        PromotionKey, PromotionSlotKey,
+        budget::{BudgetPoolKey, PromotionBudget},
+        coupon::PromotionCoupon,
+        exclusion::PromotionExclusions,
+        explain::{Shortfall, SlotShortfall, qualifying_units},
+        funding::PromotionFunding,
+        qualification::Qualification,
+        schedule::PromotionSchedule,
     },
     tags::{collection::TagCollection, string::StringTagCollection},
 };
@@ -99,6 +109,14 @@ pub struct MixAndMatchPromotion<'a, T: TagCollection = StringTagCollection> {
     slots: Vec<MixAndMatchSlot<T>>,
     discount: MixAndMatchDiscount<'a>,
     budget: PromotionBudget<'a>,
+    schedule: PromotionSchedule,
+    coupon: Option<PromotionCoupon>,
+    budget_pools: SmallVec<[BudgetPoolKey; 1]>,
+    priority: i32,
+    funding: PromotionFunding,
+    mandatory: bool,
+    rounding: Option<RoundingPolicies>,
+    exclusions: PromotionExclusions,
 }
 
 impl<'a, T: TagCollection> MixAndMatchPromotion<'a, T> {
@@ -115,6 +133,14 @@ impl<'a, T: TagCollection> MixAndMatchPromotion<'a, T> {
             slots,
             discount,
             budget,
+            schedule: PromotionSchedule::always(),
+            coupon: None,
+            budget_pools: SmallVec::new(),
+            priority: 0,
+            funding: PromotionFunding::Retailer,
+            mandatory: false,
+            rounding: None,
+            exclusions: PromotionExclusions::default(),
         }
     }
 
@@ -142,6 +168,143 @@ impl<'a, T: TagCollection> MixAndMatchPromotion<'a, T> {
         &self.budget
     }
 
+    /// Attach a schedule restricting when the promotion is active.
+    #[must_use]
+    pub fn with_schedule(mut self, schedule: PromotionSchedule) -> Self {
+        self.schedule = schedule;
+        self
+    }
+
+    /// Return the schedule
+    #[must_use]
+    pub fn schedule(&self) -> &PromotionSchedule {
+        &self.schedule
+    }
+
+    /// Require a coupon code for the promotion to apply.
+    #[must_use]
+    pub fn with_coupon(mut self, coupon: PromotionCoupon) -> Self {
+        self.coupon = Some(coupon);
+        self
+    }
+
+    /// Return the coupon required to unlock the promotion, if any
+    #[must_use]
+    pub fn coupon(&self) -> Option<&PromotionCoupon> {
+        self.coupon.as_ref()
+    }
+
+    /// Draw on a shared budget pool in addition to the promotion's own budget.
+    #[must_use]
+    pub fn with_budget_pool(mut self, pool: BudgetPoolKey) -> Self {
+        if !self.budget_pools.contains(&pool) {
+            self.budget_pools.push(pool);
+        }
+
+        self
+    }
+
+    /// Return the shared budget pools the promotion draws on
+    #[must_use]
+    pub fn budget_pools(&self) -> &[BudgetPoolKey] {
+        &self.budget_pools
+    }
+
+    /// Set the priority used to prefer this promotion when totals tie.
+    #[must_use]
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Return the priority (higher numbers are preferred, default 0)
+    pub fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    /// Record who funds the promotion's discounts.
+    #[must_use]
+    pub fn with_funding(mut self, funding: PromotionFunding) -> Self {
+        self.funding = funding;
+        self
+    }
+
+    /// Return who funds the promotion's discounts
+    pub fn funding(&self) -> &PromotionFunding {
+        &self.funding
+    }
+
+    /// Mark the promotion as one the customer is always entitled to.
+    #[must_use]
+    pub fn with_mandatory(mut self, mandatory: bool) -> Self {
+        self.mandatory = mandatory;
+        self
+    }
+
+    /// Return whether the customer is always entitled to the promotion
+    pub fn is_mandatory(&self) -> bool {
+        self.mandatory
+    }
+
+    /// Round the promotion's percentage discounts with `rounding`, instead of the
+    /// policies of the graph or solver it is evaluated by.
+    #[must_use]
+    pub fn with_rounding(mut self, rounding: impl Into<RoundingPolicies>) -> Self {
+        self.rounding = Some(rounding.into());
+        self
+    }
+
+    /// Return the promotion's own rounding policies, if it has any
+    pub fn rounding(&self) -> Option<&RoundingPolicies> {
+        self.rounding.as_ref()
+    }
+
+    /// Set the exclusion groups the promotion joins and the promotions it cannot
+    /// be combined with.
+    #[must_use]
+    pub fn with_exclusions(mut self, exclusions: PromotionExclusions) -> Self {
+        self.exclusions = exclusions;
+        self
+    }
+
+    /// Return the promotion's exclusion groups and rules
+    pub fn exclusions(&self) -> &PromotionExclusions {
+        &self.exclusions
+    }
+
+    /// Return the redemption limit, combining the budget with a single-use coupon
+    #[must_use]
+    pub fn redemption_limit(&self) -> Option<u32> {
+        self.coupon
+            .as_ref()
+            .map_or(self.budget.redemption_limit, |coupon| {
+                coupon.cap_redemption_limit(self.budget.redemption_limit)
+            })
+    }
+
+    /// Describe which slots the item group cannot fill, if any.
+    ///
+    /// Each slot is counted independently, so items qualifying for several
+    /// slots count towards all of them.
+    pub fn shortfall<'b>(&self, item_group: &ItemGroup<'b, T>) -> Option<Shortfall<'b>> {
+        let slots: SmallVec<[SlotShortfall; 2]> = self
+            .slots
+            .iter()
+            .filter_map(|slot| {
+                let available = usize::try_from(qualifying_units(item_group, slot.qualification()))
+                    .unwrap_or(usize::MAX);
+
+                (available < slot.min()).then_some(SlotShortfall {
+                    slot: slot.key,
+                    required: slot.min(),
+                    available,
+                })
+            })
+            .collect();
+
+        (!slots.is_empty()).then_some(Shortfall::Slots(slots))
+    }
+
     /// True if all slots have fixed arity (min == max).
     #[must_use]
     pub fn has_fixed_arity(&self) -> bool {
@@ -159,11 +322,15 @@ impl<'a, T: TagCollection> MixAndMatchPromotion<'a, T> {
 
 #[cfg(test)]
 mod tests {
+    use std::num::NonZeroU32;
+
     use decimal_percentage::Percentage;
     use rusty_money::{Money, iso::GBP};
     use slotmap::SlotMap;
 
-    use crate::{tags::string::StringTagCollection, utils::slot};
+    use crate::{
+        items::Item, products::ProductKey, tags::string::StringTagCollection, utils::slot,
+    };
 
     use super::*;
 
@@ -229,4 +396,53 @@ mod tests {
         assert_eq!(slot.min(), 3);
         assert_eq!(slot.max(), Some(5));
     }
+
+    #[test]
+    fn shortfall_reports_unfilled_slots() {
+        let mut slot_keys = SlotMap::<PromotionSlotKey, ()>::with_key();
+        let main = slot(
+            &mut slot_keys,
+            StringTagCollection::from_strs(&["main"]),
+            1,
+            Some(1),
+        );
+        let drinks = slot(
+            &mut slot_keys,
+            StringTagCollection::from_strs(&["drink"]),
+            2,
+            Some(2),
+        );
+        let drinks_key = *drinks.key();
+
+        let promo = MixAndMatchPromotion::new(
+            PromotionKey::default(),
+            vec![main, drinks],
+            MixAndMatchDiscount::FixedTotal(Money::from_minor(500, GBP)),
+            PromotionBudget::unlimited(),
+        );
+
+        let item_group = ItemGroup::new(
+            [("main", 1), ("drink", 1)]
+                .into_iter()
+                .map(|(tag, quantity)| {
+                    Item::with_tags(
+                        ProductKey::default(),
+                        Money::from_minor(300, GBP),
+                        StringTagCollection::from_strs(&[tag]),
+                    )
+                    .with_quantity(NonZeroU32::new(quantity).expect("non-zero quantity"))
+                })
+                .collect(),
+            GBP,
+        );
+
+        assert_eq!(
+            promo.shortfall(&item_group),
+            Some(Shortfall::Slots(SmallVec::from_slice(&[SlotShortfall {
+                slot: drinks_key,
+                required: 2,
+                available: 1,
+            }])))
+        );
+    }
 }
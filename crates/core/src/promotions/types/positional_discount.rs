@@ -7,8 +7,18 @@
 use smallvec::SmallVec;
 
 use crate::{
-    discounts::SimpleDiscount,
-    promotions::{PromotionKey, budget::PromotionBudget, qualification::Qualification},
+    discounts::{SimpleDiscount, rounding::RoundingPolicies},
+    items::groups::ItemGroup,
+    promotions::{
+        PromotionKey,
+        budget::{BudgetPoolKey, PromotionBudget},
+        coupon::PromotionCoupon,
+        exclusion::PromotionExclusions,
+        explain::{Shortfall, qualifying_units},
+        funding::PromotionFunding,
+        qualification::Qualification,
+        schedule::PromotionSchedule,
+    },
     tags::{collection::TagCollection, string::StringTagCollection},
 };
 
@@ -21,6 +31,14 @@ pub struct PositionalDiscountPromotion<'a, T: TagCollection = StringTagCollectio
     positions: SmallVec<[u16; 5]>,
     discount: SimpleDiscount<'a>,
     budget: PromotionBudget<'a>,
+    schedule: PromotionSchedule,
+    coupon: Option<PromotionCoupon>,
+    budget_pools: SmallVec<[BudgetPoolKey; 1]>,
+    priority: i32,
+    funding: PromotionFunding,
+    mandatory: bool,
+    rounding: Option<RoundingPolicies>,
+    exclusions: PromotionExclusions,
 }
 
 impl<'a, T: TagCollection> PositionalDiscountPromotion<'a, T> {
@@ -40,6 +58,14 @@ impl<'a, T: TagCollection> PositionalDiscountPromotion<'a, T> {
             positions,
             discount,
             budget,
+            schedule: PromotionSchedule::always(),
+            coupon: None,
+            budget_pools: SmallVec::new(),
+            priority: 0,
+            funding: PromotionFunding::Retailer,
+            mandatory: false,
+            rounding: None,
+            exclusions: PromotionExclusions::default(),
         }
     }
 
@@ -72,6 +98,133 @@ impl<'a, T: TagCollection> PositionalDiscountPromotion<'a, T> {
     pub const fn budget(&self) -> &PromotionBudget<'a> {
         &self.budget
     }
+
+    /// Attach a schedule restricting when the promotion is active.
+    #[must_use]
+    pub fn with_schedule(mut self, schedule: PromotionSchedule) -> Self {
+        self.schedule = schedule;
+        self
+    }
+
+    /// Return the schedule
+    pub fn schedule(&self) -> &PromotionSchedule {
+        &self.schedule
+    }
+
+    /// Require a coupon code for the promotion to apply.
+    #[must_use]
+    pub fn with_coupon(mut self, coupon: PromotionCoupon) -> Self {
+        self.coupon = Some(coupon);
+        self
+    }
+
+    /// Return the coupon required to unlock the promotion, if any
+    pub fn coupon(&self) -> Option<&PromotionCoupon> {
+        self.coupon.as_ref()
+    }
+
+    /// Draw on a shared budget pool in addition to the promotion's own budget.
+    #[must_use]
+    pub fn with_budget_pool(mut self, pool: BudgetPoolKey) -> Self {
+        if !self.budget_pools.contains(&pool) {
+            self.budget_pools.push(pool);
+        }
+
+        self
+    }
+
+    /// Return the shared budget pools the promotion draws on
+    pub fn budget_pools(&self) -> &[BudgetPoolKey] {
+        &self.budget_pools
+    }
+
+    /// Set the priority used to prefer this promotion when totals tie.
+    #[must_use]
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Return the priority (higher numbers are preferred, default 0)
+    pub fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    /// Record who funds the promotion's discounts.
+    #[must_use]
+    pub fn with_funding(mut self, funding: PromotionFunding) -> Self {
+        self.funding = funding;
+        self
+    }
+
+    /// Return who funds the promotion's discounts
+    pub fn funding(&self) -> &PromotionFunding {
+        &self.funding
+    }
+
+    /// Mark the promotion as one the customer is always entitled to.
+    #[must_use]
+    pub fn with_mandatory(mut self, mandatory: bool) -> Self {
+        self.mandatory = mandatory;
+        self
+    }
+
+    /// Return whether the customer is always entitled to the promotion
+    pub fn is_mandatory(&self) -> bool {
+        self.mandatory
+    }
+
+    /// Round the promotion's percentage discounts with `rounding`, instead of the
+    /// policies of the graph or solver it is evaluated by.
+    #[must_use]
+    pub fn with_rounding(mut self, rounding: impl Into<RoundingPolicies>) -> Self {
+        self.rounding = Some(rounding.into());
+        self
+    }
+
+    /// Return the promotion's own rounding policies, if it has any
+    pub fn rounding(&self) -> Option<&RoundingPolicies> {
+        self.rounding.as_ref()
+    }
+
+    /// Set the exclusion groups the promotion joins and the promotions it cannot
+    /// be combined with.
+    #[must_use]
+    pub fn with_exclusions(mut self, exclusions: PromotionExclusions) -> Self {
+        self.exclusions = exclusions;
+        self
+    }
+
+    /// Return the promotion's exclusion groups and rules
+    pub fn exclusions(&self) -> &PromotionExclusions {
+        &self.exclusions
+    }
+
+    /// Return the redemption limit, combining the budget with a single-use coupon
+    pub fn redemption_limit(&self) -> Option<u32> {
+        self.coupon
+            .as_ref()
+            .map_or(self.budget.redemption_limit, |coupon| {
+                coupon.cap_redemption_limit(self.budget.redemption_limit)
+            })
+    }
+
+    /// Describe what the item group lacks to complete a bundle, if anything.
+    pub fn shortfall<'b>(&self, item_group: &ItemGroup<'b, T>) -> Option<Shortfall<'b>> {
+        let required = u32::from(self.size);
+        let available = qualifying_units(item_group, &self.qualification);
+
+        if available == 0 {
+            Some(Shortfall::NoQualifyingItems)
+        } else if available < required {
+            Some(Shortfall::Items {
+                required,
+                available,
+            })
+        } else {
+            None
+        }
+    }
 }
 
 #[cfg(test)]
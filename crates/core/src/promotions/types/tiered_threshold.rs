@@ -8,11 +8,24 @@
 
 use decimal_percentage::Percentage;
 use rusty_money::{Money, iso::Currency};
+use smallvec::SmallVec;
 
 use crate::{
-    discounts::{DiscountError, percent_of_minor},
-    items::Item,
-    promotions::{PromotionKey, budget::PromotionBudget, qualification::Qualification},
+    discounts::{
+        DiscountError,
+        rounding::{RoundingPolicies, RoundingPolicy},
+    },
+    items::{Item, groups::ItemGroup},
+    promotions::{
+        PromotionKey,
+        budget::{BudgetPoolKey, PromotionBudget},
+        coupon::PromotionCoupon,
+        exclusion::PromotionExclusions,
+        explain::{Shortfall, qualifying_items, qualifying_units},
+        funding::PromotionFunding,
+        qualification::Qualification,
+        schedule::PromotionSchedule,
+    },
     tags::{collection::TagCollection, string::StringTagCollection},
 };
 
@@ -176,6 +189,14 @@ pub struct TieredThresholdPromotion<'a, T: TagCollection = StringTagCollection>
     key: PromotionKey,
     tiers: Vec<ThresholdTier<'a, T>>,
     budget: PromotionBudget<'a>,
+    schedule: PromotionSchedule,
+    coupon: Option<PromotionCoupon>,
+    budget_pools: SmallVec<[BudgetPoolKey; 1]>,
+    priority: i32,
+    funding: PromotionFunding,
+    mandatory: bool,
+    rounding: Option<RoundingPolicies>,
+    exclusions: PromotionExclusions,
 }
 
 impl<'a, T: TagCollection> TieredThresholdPromotion<'a, T> {
@@ -186,7 +207,19 @@ impl<'a, T: TagCollection> TieredThresholdPromotion<'a, T> {
         tiers: Vec<ThresholdTier<'a, T>>,
         budget: PromotionBudget<'a>,
     ) -> Self {
-        Self { key, tiers, budget }
+        Self {
+            key,
+            tiers,
+            budget,
+            schedule: PromotionSchedule::always(),
+            coupon: None,
+            budget_pools: SmallVec::new(),
+            priority: 0,
+            funding: PromotionFunding::Retailer,
+            mandatory: false,
+            rounding: None,
+            exclusions: PromotionExclusions::default(),
+        }
     }
 
     /// Return the promotion key.
@@ -207,6 +240,197 @@ impl<'a, T: TagCollection> TieredThresholdPromotion<'a, T> {
         &self.budget
     }
 
+    /// Attach a schedule restricting when the promotion is active.
+    #[must_use]
+    pub fn with_schedule(mut self, schedule: PromotionSchedule) -> Self {
+        self.schedule = schedule;
+        self
+    }
+
+    /// Return the schedule
+    #[must_use]
+    pub fn schedule(&self) -> &PromotionSchedule {
+        &self.schedule
+    }
+
+    /// Require a coupon code for the promotion to apply.
+    #[must_use]
+    pub fn with_coupon(mut self, coupon: PromotionCoupon) -> Self {
+        self.coupon = Some(coupon);
+        self
+    }
+
+    /// Return the coupon required to unlock the promotion, if any
+    #[must_use]
+    pub fn coupon(&self) -> Option<&PromotionCoupon> {
+        self.coupon.as_ref()
+    }
+
+    /// Draw on a shared budget pool in addition to the promotion's own budget.
+    #[must_use]
+    pub fn with_budget_pool(mut self, pool: BudgetPoolKey) -> Self {
+        if !self.budget_pools.contains(&pool) {
+            self.budget_pools.push(pool);
+        }
+
+        self
+    }
+
+    /// Return the shared budget pools the promotion draws on
+    #[must_use]
+    pub fn budget_pools(&self) -> &[BudgetPoolKey] {
+        &self.budget_pools
+    }
+
+    /// Set the priority used to prefer this promotion when totals tie.
+    #[must_use]
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Return the priority (higher numbers are preferred, default 0)
+    pub fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    /// Record who funds the promotion's discounts.
+    #[must_use]
+    pub fn with_funding(mut self, funding: PromotionFunding) -> Self {
+        self.funding = funding;
+        self
+    }
+
+    /// Return who funds the promotion's discounts
+    pub fn funding(&self) -> &PromotionFunding {
+        &self.funding
+    }
+
+    /// Mark the promotion as one the customer is always entitled to.
+    #[must_use]
+    pub fn with_mandatory(mut self, mandatory: bool) -> Self {
+        self.mandatory = mandatory;
+        self
+    }
+
+    /// Return whether the customer is always entitled to the promotion
+    pub fn is_mandatory(&self) -> bool {
+        self.mandatory
+    }
+
+    /// Round the promotion's percentage discounts with `rounding`, instead of the
+    /// policies of the graph or solver it is evaluated by.
+    #[must_use]
+    pub fn with_rounding(mut self, rounding: impl Into<RoundingPolicies>) -> Self {
+        self.rounding = Some(rounding.into());
+        self
+    }
+
+    /// Return the promotion's own rounding policies, if it has any
+    pub fn rounding(&self) -> Option<&RoundingPolicies> {
+        self.rounding.as_ref()
+    }
+
+    /// Set the exclusion groups the promotion joins and the promotions it cannot
+    /// be combined with.
+    #[must_use]
+    pub fn with_exclusions(mut self, exclusions: PromotionExclusions) -> Self {
+        self.exclusions = exclusions;
+        self
+    }
+
+    /// Return the promotion's exclusion groups and rules
+    pub fn exclusions(&self) -> &PromotionExclusions {
+        &self.exclusions
+    }
+
+    /// Return the redemption limit, combining the budget with a single-use coupon
+    #[must_use]
+    pub fn redemption_limit(&self) -> Option<u32> {
+        self.coupon
+            .as_ref()
+            .map_or(self.budget.redemption_limit, |coupon| {
+                coupon.cap_redemption_limit(self.budget.redemption_limit)
+            })
+    }
+
+    /// Describe the nearest tier the item group does not reach, if no tier can apply.
+    ///
+    /// Returns `None` when a reached tier has items to discount.
+    pub fn shortfall<'b>(&self, item_group: &ItemGroup<'b, T>) -> Option<Shortfall<'b>> {
+        if self.tiers.is_empty() {
+            return None;
+        }
+
+        let gaps: SmallVec<[TierGap; 4]> = self.tier_gaps(item_group);
+
+        if gaps.iter().any(|gap| gap.is_reached() && gap.discountable) {
+            return None;
+        }
+
+        Some(
+            nearest_tier(gaps.iter().filter(|gap| !gap.is_reached()))
+                .map_or(Shortfall::NoQualifyingItems, |gap| {
+                    gap.shortfall(item_group.currency())
+                }),
+        )
+    }
+
+    /// Describe the nearest tier above the highest reached tier, if any.
+    ///
+    /// This is the upsell for a promotion that already applies: e.g. spend
+    /// another £5 to move from 10% off to 20% off.
+    pub fn next_tier_shortfall<'b>(&self, item_group: &ItemGroup<'b, T>) -> Option<Shortfall<'b>> {
+        let gaps: SmallVec<[TierGap; 4]> = self.tier_gaps(item_group);
+
+        let highest_reached = gaps
+            .iter()
+            .filter(|gap| gap.is_reached() && gap.discountable)
+            .map(|gap| gap.tier)
+            .max()?;
+
+        nearest_tier(
+            gaps.iter()
+                .filter(|gap| gap.tier > highest_reached && !gap.is_reached()),
+        )
+        .map(|gap| gap.shortfall(item_group.currency()))
+    }
+
+    fn tier_gaps(&self, item_group: &ItemGroup<'_, T>) -> SmallVec<[TierGap; 4]> {
+        self.tiers
+            .iter()
+            .enumerate()
+            .map(|(tier, threshold_tier)| {
+                let threshold = threshold_tier.lower_threshold();
+                let qualification = threshold_tier.contribution_qualification();
+
+                let spend: i64 = qualifying_items(item_group, qualification)
+                    .map(|item| {
+                        item.price()
+                            .to_minor_units()
+                            .saturating_mul(i64::from(item.quantity()))
+                    })
+                    .sum();
+
+                let count = qualifying_units(item_group, qualification);
+
+                TierGap {
+                    tier,
+                    spend: threshold
+                        .monetary_threshold()
+                        .map_or(0, |required| (required.to_minor_units() - spend).max(0)),
+                    items: threshold
+                        .item_count_threshold()
+                        .map_or(0, |required| required.saturating_sub(count)),
+                    discountable: qualifying_units(
+                        item_group,
+                        threshold_tier.discount_qualification(),
+                    ) > 0,
+                }
+            })
+            .collect()
+    }
+
     /// Calculate the discounted price for a single item under a per-item discount.
     ///
     /// For per-item discount variants ([`PercentEachItem`](ThresholdDiscount::PercentEachItem),
@@ -224,13 +448,29 @@ impl<'a, T: TagCollection> TieredThresholdPromotion<'a, T> {
     pub fn calculate_discounted_price(
         tier: &ThresholdTier<'a, T>,
         item: &Item<'a, T>,
+    ) -> Result<Money<'a, Currency>, DiscountError> {
+        Self::calculate_rounded_price(tier, item, RoundingPolicy::default())
+    }
+
+    /// Calculate the discounted price for an item under `tier`, rounding a
+    /// percentage discount with `rounding`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`DiscountError`] if:
+    /// - Percentage calculation overflows or cannot be safely represented.
+    /// - Money arithmetic fails (e.g., currency mismatch, negative result).
+    pub(crate) fn calculate_rounded_price(
+        tier: &ThresholdTier<'a, T>,
+        item: &Item<'a, T>,
+        rounding: RoundingPolicy,
     ) -> Result<Money<'a, Currency>, DiscountError> {
         let discounted_minor = match &tier.discount {
             ThresholdDiscount::PercentEachItem(pct) => {
                 let original_minor = item.price().to_minor_units();
 
                 original_minor
-                    .checked_sub(percent_of_minor(pct, original_minor)?)
+                    .checked_sub(rounding.discount_of(pct, original_minor)?)
                     .ok_or(DiscountError::PercentConversion)?
             }
             ThresholdDiscount::FixedPriceEachItem(amount) => amount.to_minor_units(),
@@ -251,6 +491,34 @@ impl<'a, T: TagCollection> TieredThresholdPromotion<'a, T> {
     }
 }
 
+/// How far an item group is from a tier's lower threshold.
+#[derive(Debug, Clone, Copy)]
+struct TierGap {
+    tier: usize,
+    spend: i64,
+    items: u32,
+    discountable: bool,
+}
+
+impl TierGap {
+    fn is_reached(&self) -> bool {
+        self.spend == 0 && self.items == 0
+    }
+
+    fn shortfall<'a>(&self, currency: &'a Currency) -> Shortfall<'a> {
+        Shortfall::Threshold {
+            tier: self.tier,
+            spend: (self.spend > 0).then(|| Money::from_minor(self.spend, currency)),
+            items: (self.items > 0).then_some(self.items),
+        }
+    }
+}
+
+/// Pick the tier needing the least extra spend, then the fewest extra items.
+fn nearest_tier<'g>(gaps: impl Iterator<Item = &'g TierGap>) -> Option<&'g TierGap> {
+    gaps.min_by_key(|gap| (gap.spend, gap.items, gap.tier))
+}
+
 #[cfg(test)]
 mod tests {
     use decimal_percentage::Percentage;
@@ -258,7 +526,10 @@ mod tests {
     use slotmap::SlotMap;
     use testresult::TestResult;
 
-    use crate::{items::Item, products::ProductKey};
+    use crate::{
+        items::{Item, groups::ItemGroup},
+        products::ProductKey,
+    };
 
     use super::*;
 
@@ -536,4 +807,86 @@ mod tests {
 
         Ok(())
     }
+
+    fn basket(items: &[(i64, &str)]) -> ItemGroup<'static> {
+        ItemGroup::new(
+            items
+                .iter()
+                .map(|&(price, tag)| {
+                    Item::with_tags(
+                        ProductKey::default(),
+                        Money::from_minor(price, GBP),
+                        StringTagCollection::from_strs(&[tag]),
+                    )
+                })
+                .collect(),
+            GBP,
+        )
+    }
+
+    fn two_tier_promotion() -> TieredThresholdPromotion<'static> {
+        TieredThresholdPromotion::new(
+            PromotionKey::default(),
+            vec![
+                make_tier(
+                    2000,
+                    ThresholdDiscount::PercentEachItem(Percentage::from(0.10)),
+                ),
+                make_tier(
+                    5000,
+                    ThresholdDiscount::PercentEachItem(Percentage::from(0.20)),
+                ),
+            ],
+            PromotionBudget::unlimited(),
+        )
+    }
+
+    #[test]
+    fn shortfall_reports_spend_needed_for_nearest_tier() {
+        let promo = two_tier_promotion();
+
+        assert_eq!(
+            promo.shortfall(&basket(&[(1500, "wine"), (400, "cheese")])),
+            Some(Shortfall::Threshold {
+                tier: 0,
+                spend: Some(Money::from_minor(500, GBP)),
+                items: None,
+            })
+        );
+
+        assert_eq!(
+            promo.shortfall(&basket(&[(2500, "wine"), (400, "cheese")])),
+            None
+        );
+    }
+
+    #[test]
+    fn shortfall_without_discountable_items_asks_for_one() {
+        let promo = two_tier_promotion();
+
+        assert_eq!(
+            promo.shortfall(&basket(&[(6000, "wine")])),
+            Some(Shortfall::NoQualifyingItems)
+        );
+    }
+
+    #[test]
+    fn next_tier_shortfall_reports_upsell() {
+        let promo = two_tier_promotion();
+        let item_group = basket(&[(3500, "wine"), (400, "cheese")]);
+
+        assert_eq!(
+            promo.next_tier_shortfall(&item_group),
+            Some(Shortfall::Threshold {
+                tier: 1,
+                spend: Some(Money::from_minor(1500, GBP)),
+                items: None,
+            })
+        );
+
+        assert_eq!(
+            promo.next_tier_shortfall(&basket(&[(5000, "wine"), (400, "cheese")])),
+            None
+        );
+    }
 }
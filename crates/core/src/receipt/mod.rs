@@ -1,6 +1,6 @@
 //! Receipt
 
-use std::{fmt::Write, io};
+use std::{collections::BTreeMap, fmt::Write, io};
 
 use decimal_percentage::Percentage;
 use rust_decimal::{Decimal, prelude::FromPrimitive};
@@ -23,7 +23,11 @@ use crate::{
     graph::result::LayeredSolverResult,
     pricing::TotalPriceError,
     products::{Product, ProductKey},
-    promotions::{PromotionKey, PromotionMeta, redemptions::PromotionRedemption},
+    promotions::{
+        PromotionKey, PromotionMeta,
+        funding::{FundedAmount, FundingParty},
+        redemptions::PromotionRedemption,
+    },
     solvers::SolverResult,
 };
 
@@ -142,17 +146,24 @@ impl<'a> Receipt<'a> {
         basket: &'a Basket<'a>,
         result: SolverResult<'a>,
     ) -> Result<Self, ReceiptError> {
-        let mut promotion_redemptions = FxHashMap::default();
+        let mut promotion_redemptions: FxHashMap<usize, SmallVec<[PromotionRedemption<'a>; 3]>> =
+            FxHashMap::default();
 
         for app in result.promotion_redemptions {
-            // Solver invariants say this can't happen, but map insertion makes it explicit.
+            let item_apps = promotion_redemptions.entry(app.item_idx).or_default();
+
+            // Quantity lines may be split across redemptions, but the solver merges
+            // units of a line sharing a redemption and price into one entry.
             debug_assert!(
-                !promotion_redemptions.contains_key(&app.item_idx),
+                !item_apps.iter().any(|other| {
+                    other.redemption_idx == app.redemption_idx
+                        && other.final_price == app.final_price
+                }),
                 "duplicate promotion redemption for item_idx={}",
                 app.item_idx
             );
 
-            promotion_redemptions.insert(app.item_idx, smallvec![app]);
+            item_apps.push(app);
         }
 
         Ok(Receipt {
@@ -217,6 +228,37 @@ impl<'a> Receipt<'a> {
         self.currency
     }
 
+    /// Total discount funded by each party, retailer first and then suppliers by
+    /// name.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`MoneyError`] if the funded amounts cannot be added.
+    pub fn funding_totals(&self) -> Result<Vec<FundedAmount<'a>>, MoneyError> {
+        let mut totals: BTreeMap<&FundingParty, Money<'a, Currency>> = BTreeMap::new();
+
+        for funded in self
+            .promotion_redemptions
+            .values()
+            .flatten()
+            .flat_map(|app| &app.funding)
+        {
+            let total = totals
+                .entry(&funded.party)
+                .or_insert_with(|| Money::from_minor(0, self.currency));
+
+            *total = total.add(funded.amount)?;
+        }
+
+        Ok(totals
+            .into_iter()
+            .map(|(party, amount)| FundedAmount {
+                party: party.clone(),
+                amount,
+            })
+            .collect())
+    }
+
     /// Prints the receipt to the console.
     ///
     /// # Errors
@@ -283,6 +325,18 @@ fn append_item_rows(
         item_boundary_rows.push(row_writer.current_row);
 
         match receipt.promotion_redemptions.get(&item_idx) {
+            Some(apps)
+                if item.quantity() > 1 && !is_whole_line_redemption(item.quantity(), apps) =>
+            {
+                row_writer.append_quantity_rows(
+                    item_idx,
+                    &product_name,
+                    &product_tags,
+                    item.price(),
+                    item.quantity(),
+                    apps,
+                )?;
+            }
             Some(apps) if apps.len() == 1 => row_writer.append_single_redemption_row(
                 item_idx,
                 &product_name,
@@ -293,7 +347,7 @@ fn append_item_rows(
                 item_idx,
                 &product_name,
                 &product_tags,
-                item.price(),
+                &quantity_label(item.quantity(), item.price()),
                 apps,
             )?,
             _ => row_writer.append_full_price_row(
@@ -301,6 +355,7 @@ fn append_item_rows(
                 &product_name,
                 &product_tags,
                 item.price(),
+                item.quantity(),
             ),
         }
     }
@@ -384,14 +439,14 @@ impl RowWriter<'_> {
         item_idx: usize,
         product_name: &str,
         product_tags: &str,
-        item_price: &Money<'_, Currency>,
+        base_price: &str,
         apps: &[PromotionRedemption<'_>],
     ) -> Result<(), ReceiptError> {
         self.builder.push_record([
             format!("#{:<3}", item_idx + 1),
             product_name.to_string(),
             product_tags.to_string(),
-            format!("{item_price}"),
+            base_price.to_string(),
             String::new(),
             String::new(),
             String::new(),
@@ -434,18 +489,66 @@ impl RowWriter<'_> {
         Ok(())
     }
 
+    /// Rows for a quantity line whose units were priced differently: a header row
+    /// for the whole line, one row per redemption and one for any full-price units.
+    fn append_quantity_rows(
+        &mut self,
+        item_idx: usize,
+        product_name: &str,
+        product_tags: &str,
+        item_price: &Money<'_, Currency>,
+        quantity: u32,
+        apps: &[PromotionRedemption<'_>],
+    ) -> Result<(), ReceiptError> {
+        self.append_multi_layer_rows(
+            item_idx,
+            product_name,
+            product_tags,
+            &quantity_label(quantity, item_price),
+            apps,
+        )?;
+
+        // Units entering their first promotion are redeemed at the basket price.
+        let redeemed_units = apps
+            .iter()
+            .filter(|app| app.original_price == *item_price)
+            .fold(0_u32, |units, app| units.saturating_add(app.quantity));
+
+        let full_price_units = quantity.saturating_sub(redeemed_units);
+
+        if full_price_units > 0 {
+            self.builder.push_record([
+                String::new(),
+                String::new(),
+                String::new(),
+                quantity_label(full_price_units, item_price),
+                String::new(),
+                String::new(),
+                String::new(),
+            ]);
+
+            self.color_ops
+                .push((self.current_row, 3, color_dark_grey()));
+
+            self.current_row += 1;
+        }
+
+        Ok(())
+    }
+
     fn append_full_price_row(
         &mut self,
         item_idx: usize,
         product_name: &str,
         product_tags: &str,
         item_price: &Money<'_, Currency>,
+        quantity: u32,
     ) {
         self.builder.push_record([
             format!("#{:<3}", item_idx + 1),
             product_name.to_string(),
             product_tags.to_string(),
-            format!("{item_price}"),
+            quantity_label(quantity, item_price),
             String::new(),
             String::new(),
             String::new(),
@@ -505,15 +608,28 @@ fn write_receipt_summary(
     let subtotal_val = format!("{}  ", receipt.subtotal());
     let total_val = format!("{}  ", receipt.total());
     let savings_val = format!("({savings_percent_points:.2}%) {savings}  ");
+    let funding_lines = supplier_funding_lines(receipt)?;
+
+    let label_width = funding_lines
+        .iter()
+        .map(|(label, _value)| visible_width(label))
+        .fold(
+            visible_width(subtotal_label)
+                .max(visible_width(total_label))
+                .max(visible_width(savings_label)),
+            usize::max,
+        );
 
-    let label_width = visible_width(subtotal_label)
-        .max(visible_width(total_label))
-        .max(visible_width(savings_label));
-
-    let value_width = subtotal_val
-        .len()
-        .max(total_val.len())
-        .max(savings_val.len());
+    let value_width = funding_lines
+        .iter()
+        .map(|(_label, value)| value.len())
+        .fold(
+            subtotal_val
+                .len()
+                .max(total_val.len())
+                .max(savings_val.len()),
+            usize::max,
+        );
 
     write_summary_line(out, subtotal_label, &subtotal_val, label_width, value_width)?;
 
@@ -527,9 +643,28 @@ fn write_receipt_summary(
 
     write_summary_line(out, savings_label, &savings_val, label_width, value_width)?;
 
+    for (label, value) in &funding_lines {
+        write_summary_line(out, label, value, label_width, value_width)?;
+    }
+
     writeln!(out).map_err(|_err| ReceiptError::IO)
 }
 
+/// Summary lines for the savings each supplier funds, if any.
+fn supplier_funding_lines(receipt: &Receipt<'_>) -> Result<Vec<(String, String)>, ReceiptError> {
+    Ok(receipt
+        .funding_totals()?
+        .into_iter()
+        .filter(|funded| funded.party != FundingParty::Retailer)
+        .map(|funded| {
+            (
+                format!(" Funded by {}:", funded.party),
+                format!("{}  ", funded.amount),
+            )
+        })
+        .collect())
+}
+
 /// Cell contents for a single promotion redemption row.
 struct PromotionCells {
     base_price: String,
@@ -557,7 +692,7 @@ fn promotion_cells(
 
     let savings_str = format!(
         "({savings_percent_points}%) -{}",
-        app.savings().map_err(ReceiptError::Money)?,
+        app.total_savings().map_err(ReceiptError::Money)?,
     );
 
     let redemption_idx = format!("#{:<3}", app.redemption_idx + 1);
@@ -572,11 +707,11 @@ fn promotion_cells(
         if price_is_unchanged(app.original_price, app.final_price) {
             (String::new(), String::new())
         } else {
-            (format!("{}", app.final_price), savings_str)
+            (quantity_label(app.quantity, &app.final_price), savings_str)
         };
 
     Ok(PromotionCells {
-        base_price: format!("{}", app.original_price),
+        base_price: quantity_label(app.quantity, &app.original_price),
         final_price: final_price_display,
         savings: savings_display,
         promotion: format!("{redemption_idx} {promo_name}"),
@@ -584,6 +719,20 @@ fn promotion_cells(
     })
 }
 
+/// Formats a unit price, prefixed with the unit count when there is more than one.
+fn quantity_label(quantity: u32, price: &Money<'_, Currency>) -> String {
+    if quantity > 1 {
+        format!("{quantity} × {price}")
+    } else {
+        format!("{price}")
+    }
+}
+
+/// Returns true if every unit of the line went through a single redemption.
+fn is_whole_line_redemption(quantity: u32, apps: &[PromotionRedemption<'_>]) -> bool {
+    matches!(apps, [app] if app.quantity == quantity)
+}
+
 /// Converts a fractional percentage to percent points for display.
 fn percent_points_from_fractional_percentage(percentage: Percentage) -> Decimal {
     // `Percentage` is a fraction (e.g. 0.25), so multiply by 100 to print percent points.
@@ -684,6 +833,8 @@ fn color_dark_green() -> Color {
 
 #[cfg(test)]
 mod tests {
+    use std::num::NonZeroU32;
+
     use num_traits::FromPrimitive;
     use rustc_hash::FxHashMap;
     use rusty_money::{
@@ -696,8 +847,10 @@ mod tests {
 
     use crate::{
         items::Item,
+        pricing::PriceFloor,
         products::{Product, ProductKey},
-        promotions::{PromotionKey, PromotionMeta},
+        promotions::{PromotionKey, PromotionMeta, coupon::CouponCodeReport},
+        solvers::SolutionQuality,
         tags::string::StringTagCollection,
     };
 
@@ -822,6 +975,9 @@ mod tests {
                 redemption_idx: 0,
                 original_price: Money::from_minor(100, GBP),
                 final_price: Money::from_minor(75, GBP),
+                quantity: 1,
+                funding: SmallVec::new(),
+                floor_limited: false,
             },
             PromotionRedemption {
                 promotion_key: PromotionKey::default(),
@@ -829,6 +985,9 @@ mod tests {
                 redemption_idx: 1,
                 original_price: Money::from_minor(300, GBP),
                 final_price: Money::from_minor(225, GBP),
+                quantity: 1,
+                funding: SmallVec::new(),
+                floor_limited: false,
             },
         ];
 
@@ -837,6 +996,7 @@ mod tests {
             unaffected_items: smallvec![1],
             total: Money::from_minor(500, GBP), // 75 + 200 + 225
             promotion_redemptions: promotion_apps,
+            quality: SolutionQuality::Optimal,
         };
 
         let receipt = Receipt::from_solver_result(&basket, solver_result)?;
@@ -864,6 +1024,7 @@ mod tests {
             unaffected_items: smallvec![0, 1],
             total: Money::from_minor(300, GBP),
             promotion_redemptions: smallvec![],
+            quality: SolutionQuality::Optimal,
         };
 
         let receipt = Receipt::from_solver_result(&basket, solver_result)?;
@@ -892,6 +1053,9 @@ mod tests {
             redemption_idx: 42,
             original_price: Money::from_minor(100, GBP),
             final_price: Money::from_minor(50, GBP),
+            quantity: 1,
+            funding: SmallVec::new(),
+            floor_limited: false,
         }];
 
         let solver_result = SolverResult {
@@ -899,6 +1063,7 @@ mod tests {
             unaffected_items: smallvec![],
             total: Money::from_minor(50, GBP),
             promotion_redemptions: promotion_apps,
+            quality: SolutionQuality::Optimal,
         };
 
         let receipt = Receipt::from_solver_result(&basket, solver_result)?;
@@ -931,6 +1096,9 @@ mod tests {
                 redemption_idx: 0,
                 original_price: Money::from_minor(200, GBP),
                 final_price: Money::from_minor(150, GBP),
+                quantity: 1,
+                funding: SmallVec::new(),
+                floor_limited: false,
             }],
         );
 
@@ -959,12 +1127,14 @@ mod tests {
             name: "Apple".to_string(),
             tags: StringTagCollection::from_strs(&["fruit"]),
             price: apple_price,
+            floor: PriceFloor::default(),
         });
 
         let banana_key = product_meta.insert(Product {
             name: "Banana".to_string(),
             tags: StringTagCollection::from_strs(&["fruit"]),
             price: banana_price,
+            floor: PriceFloor::default(),
         });
 
         let promo_key = promotion_meta.insert(PromotionMeta {
@@ -987,6 +1157,9 @@ mod tests {
                 redemption_idx: 0,
                 original_price: apple_price,
                 final_price: Money::from_minor(80, GBP),
+                quantity: 1,
+                funding: SmallVec::new(),
+                floor_limited: false,
             }],
         );
 
@@ -1011,6 +1184,142 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn write_to_renders_supplier_funding_totals() -> TestResult {
+        let mut product_meta = SlotMap::<ProductKey, Product<'_>>::with_key();
+        let mut promotion_meta = SlotMap::<PromotionKey, PromotionMeta>::with_key();
+
+        let apple_price = Money::from_minor(100, GBP);
+
+        let apple_key = product_meta.insert(Product {
+            name: "Apple".to_string(),
+            tags: StringTagCollection::from_strs(&["fruit"]),
+            price: apple_price,
+            floor: PriceFloor::default(),
+        });
+
+        let promo_key = promotion_meta.insert(PromotionMeta {
+            name: "Fruit Sale".to_string(),
+            ..Default::default()
+        });
+
+        let basket = Basket::with_items([Item::new(apple_key, apple_price)], GBP)?;
+        let acme = FundingParty::Supplier("Acme Orchards".to_string());
+
+        let mut promotion_redemptions = FxHashMap::default();
+        promotion_redemptions.insert(
+            0,
+            smallvec![PromotionRedemption {
+                promotion_key: promo_key,
+                item_idx: 0,
+                redemption_idx: 0,
+                original_price: apple_price,
+                final_price: Money::from_minor(80, GBP),
+                quantity: 1,
+                funding: smallvec![
+                    FundedAmount {
+                        party: acme.clone(),
+                        amount: Money::from_minor(15, GBP),
+                    },
+                    FundedAmount {
+                        party: FundingParty::Retailer,
+                        amount: Money::from_minor(5, GBP),
+                    },
+                ],
+                floor_limited: false,
+            }],
+        );
+
+        let receipt = Receipt::new(
+            SmallVec::new(),
+            promotion_redemptions,
+            Money::from_minor(100, GBP),
+            Money::from_minor(80, GBP),
+            GBP,
+        );
+
+        assert_eq!(
+            receipt.funding_totals()?,
+            [
+                FundedAmount {
+                    party: FundingParty::Retailer,
+                    amount: Money::from_minor(5, GBP),
+                },
+                FundedAmount {
+                    party: acme,
+                    amount: Money::from_minor(15, GBP),
+                },
+            ]
+        );
+
+        let mut out = Vec::new();
+        receipt.write_to(&mut out, &basket, &product_meta, &promotion_meta)?;
+
+        let output = String::from_utf8(out)?;
+        assert!(output.contains("Funded by Acme Orchards:"));
+        assert!(!output.contains("Funded by Retailer"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn write_to_renders_partially_redeemed_quantity_line() -> TestResult {
+        let mut product_meta = SlotMap::<ProductKey, Product<'_>>::with_key();
+        let mut promotion_meta = SlotMap::<PromotionKey, PromotionMeta>::with_key();
+
+        let can_price = Money::from_minor(50, GBP);
+
+        let can_key = product_meta.insert(Product {
+            name: "Cola".to_string(),
+            tags: StringTagCollection::from_strs(&["drink"]),
+            price: can_price,
+            floor: PriceFloor::default(),
+        });
+
+        let promo_key = promotion_meta.insert(PromotionMeta {
+            name: "Cola Sale".to_string(),
+            ..Default::default()
+        });
+
+        let items = [Item::new(can_key, can_price)
+            .with_quantity(NonZeroU32::new(5).expect("non-zero quantity"))];
+        let basket = Basket::with_items(items, GBP)?;
+
+        let solver_result = SolverResult {
+            affected_items: smallvec![0],
+            unaffected_items: smallvec![0],
+            total: Money::from_minor(210, GBP),
+            promotion_redemptions: smallvec![PromotionRedemption {
+                promotion_key: promo_key,
+                item_idx: 0,
+                redemption_idx: 0,
+                original_price: can_price,
+                final_price: Money::from_minor(30, GBP),
+                quantity: 2,
+                funding: SmallVec::new(),
+                floor_limited: false,
+            }],
+            quality: SolutionQuality::Optimal,
+        };
+
+        let receipt = Receipt::from_solver_result(&basket, solver_result)?;
+
+        assert_eq!(receipt.subtotal().to_minor_units(), 250);
+        assert_eq!(receipt.savings()?.to_minor_units(), 40);
+
+        let mut out = Vec::new();
+        receipt.write_to(&mut out, &basket, &product_meta, &promotion_meta)?;
+
+        let output = String::from_utf8(out)?;
+
+        assert!(output.contains("5 × £0.50"));
+        assert!(output.contains("2 × £0.30"));
+        assert!(output.contains("3 × £0.50"));
+        assert!(output.contains("-£0.40"));
+
+        Ok(())
+    }
+
     #[test]
     fn write_to_errors_on_missing_product() -> TestResult {
         let items = [Item::new(
@@ -1048,12 +1357,14 @@ mod tests {
             name: "Drink".to_string(),
             tags: StringTagCollection::from_strs(&["fruit"]),
             price: drink_price,
+            floor: PriceFloor::default(),
         });
 
         let snack_key = product_meta.insert(Product {
             name: "Snack".to_string(),
             tags: StringTagCollection::from_strs(&["fruit"]),
             price: snack_price,
+            floor: PriceFloor::default(),
         });
 
         let items = [
@@ -1073,6 +1384,9 @@ mod tests {
                 redemption_idx: 0,
                 original_price: drink_price,
                 final_price: drink_price,
+                quantity: 1,
+                funding: SmallVec::new(),
+                floor_limited: false,
             }],
         );
 
@@ -1106,6 +1420,7 @@ mod tests {
             name: "Apple".to_string(),
             tags: StringTagCollection::from_strs(&["fruit"]),
             price: apple_price,
+            floor: PriceFloor::default(),
         });
 
         let promo_key = promotion_meta.insert(PromotionMeta {
@@ -1126,6 +1441,9 @@ mod tests {
                 redemption_idx: 0,
                 original_price: apple_price,
                 final_price: Money::from_minor(50, GBP),
+                quantity: 1,
+                funding: SmallVec::new(),
+                floor_limited: false,
             }],
         );
 
@@ -1166,6 +1484,9 @@ mod tests {
             redemption_idx: 0,
             original_price: Money::from_minor(100, GBP),
             final_price: Money::from_minor(50, GBP),
+            quantity: 1,
+            funding: SmallVec::new(),
+            floor_limited: false,
         };
 
         let solver_result = SolverResult {
@@ -1173,6 +1494,7 @@ mod tests {
             unaffected_items: smallvec![],
             total: Money::from_minor(50, GBP),
             promotion_redemptions: smallvec![redemption.clone(), redemption],
+            quality: SolutionQuality::Optimal,
         };
 
         let _ = Receipt::from_solver_result(&basket, solver_result).expect("receipt should build");
@@ -1190,12 +1512,14 @@ mod tests {
             name: "Chicken Wrap".to_string(),
             tags: StringTagCollection::from_strs(&["main", "hot"]),
             price: wrap_price,
+            floor: PriceFloor::default(),
         });
 
         let drink_key = product_meta.insert(Product {
             name: "Water".to_string(),
             tags: StringTagCollection::from_strs(&["drink", "cold"]),
             price: drink_price,
+            floor: PriceFloor::default(),
         });
 
         let promo_key = promotion_meta.insert(PromotionMeta {
@@ -1227,6 +1551,9 @@ mod tests {
                 redemption_idx: 5,
                 original_price: wrap_price,
                 final_price: Money::from_minor(300, GBP),
+                quantity: 1,
+                funding: SmallVec::new(),
+                floor_limited: false,
             }],
         );
 
@@ -1238,6 +1565,9 @@ mod tests {
                 redemption_idx: 5,
                 original_price: drink_price,
                 final_price: Money::from_minor(100, GBP),
+                quantity: 1,
+                funding: SmallVec::new(),
+                floor_limited: false,
             }],
         );
 
@@ -1277,6 +1607,7 @@ mod tests {
             name: "Item".to_string(),
             tags: StringTagCollection::from_strs(&["test"]),
             price: item_price,
+            floor: PriceFloor::default(),
         });
 
         let items = [Item::new(item_key, item_price)];
@@ -1321,6 +1652,9 @@ mod tests {
                     redemption_idx: 0,
                     original_price: Money::from_minor(400, GBP),
                     final_price: Money::from_minor(300, GBP),
+                    quantity: 1,
+                    funding: SmallVec::new(),
+                    floor_limited: false,
                 },
                 PromotionRedemption {
                     promotion_key: PromotionKey::default(),
@@ -1328,6 +1662,9 @@ mod tests {
                     redemption_idx: 1,
                     original_price: Money::from_minor(300, GBP),
                     final_price: Money::from_minor(270, GBP),
+                    quantity: 1,
+                    funding: SmallVec::new(),
+                    floor_limited: false,
                 },
             ],
         );
@@ -1336,6 +1673,8 @@ mod tests {
             total: Money::from_minor(470, GBP),
             item_redemptions,
             full_price_items: smallvec![1],
+            coupon_codes: CouponCodeReport::default(),
+            quality: SolutionQuality::Optimal,
         };
 
         let receipt = Receipt::from_layered_result(&basket, layered_result)?;
@@ -1364,6 +1703,7 @@ mod tests {
             name: "Chicken Wrap".to_string(),
             tags: StringTagCollection::from_strs(&["main", "hot"]),
             price: wrap_price,
+            floor: PriceFloor::default(),
         });
 
         let food_sale_key = promotion_meta.insert(PromotionMeta {
@@ -1393,6 +1733,9 @@ mod tests {
                     redemption_idx: 0,
                     original_price: Money::from_minor(400, GBP),
                     final_price: Money::from_minor(300, GBP),
+                    quantity: 1,
+                    funding: SmallVec::new(),
+                    floor_limited: false,
                 },
                 PromotionRedemption {
                     promotion_key: loyalty_key,
@@ -1400,6 +1743,9 @@ mod tests {
                     redemption_idx: 2,
                     original_price: Money::from_minor(300, GBP),
                     final_price: Money::from_minor(270, GBP),
+                    quantity: 1,
+                    funding: SmallVec::new(),
+                    floor_limited: false,
                 },
             ],
         );
@@ -1441,6 +1787,9 @@ mod tests {
                     redemption_idx: 0,
                     original_price: Money::from_minor(100, GBP),
                     final_price: Money::from_minor(80, GBP),
+                    quantity: 1,
+                    funding: SmallVec::new(),
+                    floor_limited: false,
                 },
                 PromotionRedemption {
                     promotion_key: PromotionKey::default(),
@@ -1448,6 +1797,9 @@ mod tests {
                     redemption_idx: 1,
                     original_price: Money::from_minor(80, GBP),
                     final_price: Money::from_minor(72, GBP),
+                    quantity: 1,
+                    funding: SmallVec::new(),
+                    floor_limited: false,
                 },
             ],
         );
@@ -1482,6 +1834,7 @@ mod tests {
             name: "Snack".to_string(),
             tags: StringTagCollection::from_strs(&["snack"]),
             price: Money::from_minor(100, GBP),
+            floor: PriceFloor::default(),
         });
 
         let items = [Item::new(product_key, Money::from_minor(100, GBP))];
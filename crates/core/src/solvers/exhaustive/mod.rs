@@ -0,0 +1,438 @@
+//! Exhaustive Solver
+//!
+//! Prices a basket by trying every way of sharing its units between the
+//! applicable promotions, asking each promotion for every way it could price the
+//! units it was given, and keeping the cheapest combination within budgets. The
+//! work grows exponentially with the number of units, so it only accepts small
+//! baskets; it exists as an independent reference for checking that the ILP
+//! formulations find the true optimum.
+
+use crate::{
+    items::{Item, groups::ItemGroup},
+    promotions::{
+        Promotion, PromotionKey,
+        budget::{BudgetPoolKey, BudgetPools},
+    },
+    solvers::{
+        SolutionQuality, Solver, SolverError, SolverResult,
+        greedy::{
+            Allowance, FreeUnit, GreedyRedemption, debit_pools, promotions::free_units_by_price,
+            solver_result,
+        },
+        ilp::ILPPromotion,
+    },
+};
+
+pub mod promotions;
+
+pub use promotions::ExhaustivePromotion;
+
+/// Solver enumerating every allocation of a small basket's units to promotions
+#[derive(Debug)]
+pub struct ExhaustiveSolver;
+
+impl ExhaustiveSolver {
+    /// Most units, counting each unit of a quantity line, a basket may hold.
+    pub const MAX_UNITS: usize = 10;
+
+    /// Solve with shared budget pools, deducting the allocation's usage from them.
+    ///
+    /// Promotions drawing on a pool are jointly limited by the pool's remaining
+    /// redemptions and discount value, as with
+    /// [`ILPSolver::solve_with_budget_pools()`](crate::solvers::ilp::ILPSolver::solve_with_budget_pools).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SolverError::TooManyUnits`] if the basket holds more than
+    /// [`ExhaustiveSolver::MAX_UNITS`] units, [`SolverError::PriceFloorsUnenforced`]
+    /// if an item has a price floor, [`SolverError::ExhaustiveUnsupported`]
+    /// if an applicable promotion cannot be enumerated,
+    /// [`SolverError::UnknownBudgetPool`] if a promotion draws on a pool missing
+    /// from `pools`, or another [`SolverError`] if pricing fails.
+    pub fn solve_with_budget_pools<'b>(
+        promotions: &[Promotion<'_>],
+        item_group: &ItemGroup<'b>,
+        pools: &mut BudgetPools<'_>,
+    ) -> Result<SolverResult<'b>, SolverError> {
+        let quantities: Vec<u32> = item_group.iter().map(Item::quantity).collect();
+        let units = free_units_by_price(item_group, &quantities, |_item| true);
+
+        if units.len() > Self::MAX_UNITS {
+            return Err(SolverError::TooManyUnits {
+                units: units.len(),
+                limit: Self::MAX_UNITS,
+            });
+        }
+
+        if let Some(item_idx) = item_group
+            .iter()
+            .position(|item| item.price_floor().is_some())
+        {
+            return Err(SolverError::PriceFloorsUnenforced(item_idx, "exhaustive"));
+        }
+
+        let search = Search::new(promotions, item_group, &units, pools)?;
+
+        let mut picks = Vec::with_capacity(search.entries.len());
+        let mut best = Best::default();
+
+        search.visit(0, 0, &mut picks, &mut best);
+
+        let redeemed = search
+            .entries
+            .iter()
+            .zip(&best.picks)
+            .flat_map(|(entry, choice)| {
+                choice
+                    .redemptions
+                    .iter()
+                    .map(|redemption| (entry.key, entry.promotion, redemption))
+            });
+
+        let result = solver_result(item_group, redeemed, SolutionQuality::Optimal)?;
+
+        let usages: Vec<Usage> = best.picks.iter().map(|choice| choice.usage).collect();
+
+        debit_pools(pools, |pool_key| search.pool_usage(pool_key, &usages));
+
+        Ok(result)
+    }
+}
+
+impl Solver for ExhaustiveSolver {
+    fn solve<'b>(
+        promotions: &[Promotion<'_>],
+        item_group: &ItemGroup<'b>,
+    ) -> Result<SolverResult<'b>, SolverError> {
+        Self::solve_with_budget_pools(promotions, item_group, &mut BudgetPools::default())
+    }
+}
+
+/// Redemptions counted and discount given, in minor units.
+type Usage = (u32, i64);
+
+/// One way a promotion can price a set of units.
+#[derive(Debug, Clone, Default)]
+struct Choice {
+    redemptions: Vec<GreedyRedemption>,
+    usage: Usage,
+}
+
+/// An applicable promotion with its choices for every set of units it can claim.
+#[derive(Debug)]
+struct Entry<'p> {
+    key: PromotionKey,
+    pools: &'p [BudgetPoolKey],
+    promotion: &'p dyn ILPPromotion,
+
+    /// Choices keyed by the bit set of unit positions they claim
+    choices: Vec<(u64, Vec<Choice>)>,
+}
+
+/// Cheapest combination found so far: one choice per entry.
+#[derive(Debug, Default)]
+struct Best {
+    saving: Option<i64>,
+    picks: Vec<Choice>,
+}
+
+/// The entries and pools an exhaustive solve enumerates over.
+#[derive(Debug)]
+struct Search<'p> {
+    entries: Vec<Entry<'p>>,
+    pools: Vec<(BudgetPoolKey, Allowance)>,
+}
+
+impl<'p> Search<'p> {
+    fn new(
+        promotions: &'p [Promotion<'_>],
+        item_group: &ItemGroup<'_>,
+        units: &[FreeUnit],
+        pools: &BudgetPools<'_>,
+    ) -> Result<Self, SolverError> {
+        let mut entries = Vec::with_capacity(promotions.len());
+
+        for promotion in promotions {
+            if !promotion.is_applicable(item_group) {
+                continue;
+            }
+
+            let key = promotion.key();
+            let exhaustive = promotion
+                .exhaustive()
+                .ok_or(SolverError::ExhaustiveUnsupported(key))?;
+
+            for &pool_key in promotion.budget_pools() {
+                if !pools.contains_key(pool_key) {
+                    return Err(SolverError::UnknownBudgetPool {
+                        promotion_key: key,
+                        pool_key,
+                    });
+                }
+            }
+
+            entries.push(Entry {
+                key,
+                pools: promotion.budget_pools(),
+                promotion: &**promotion,
+                choices: choices(exhaustive, item_group, units)?,
+            });
+        }
+
+        let pools = pools
+            .iter()
+            .map(|(pool_key, pool)| {
+                (
+                    pool_key,
+                    Allowance::from_budget(pool, pool.redemption_limit),
+                )
+            })
+            .collect();
+
+        Ok(Self { entries, pools })
+    }
+
+    /// Combined usage of a pool across the entries drawing on it.
+    fn pool_usage(&self, pool_key: BudgetPoolKey, usages: &[Usage]) -> Usage {
+        self.entries
+            .iter()
+            .zip(usages)
+            .filter(|(entry, _)| entry.pools.contains(&pool_key))
+            .fold((0, 0), |total, (_, usage)| {
+                (total.0 + usage.0, total.1 + usage.1)
+            })
+    }
+
+    /// Try every choice of the entry at `entry_idx` that avoids the `taken` units.
+    fn visit<'s>(
+        &'s self,
+        entry_idx: usize,
+        taken: u64,
+        picks: &mut Vec<&'s Choice>,
+        best: &mut Best,
+    ) {
+        let Some(entry) = self.entries.get(entry_idx) else {
+            self.consider(picks, best);
+
+            return;
+        };
+
+        for (mask, choices) in &entry.choices {
+            if mask & taken != 0 {
+                continue;
+            }
+
+            for choice in choices {
+                picks.push(choice);
+                self.visit(entry_idx + 1, taken | mask, picks, best);
+                picks.pop();
+            }
+        }
+    }
+
+    /// Keep a complete combination if it fits every pool and saves the most so far.
+    fn consider(&self, picks: &[&Choice], best: &mut Best) {
+        let usages: Vec<Usage> = picks.iter().map(|choice| choice.usage).collect();
+
+        let within_pools = self.pools.iter().all(|&(pool_key, pool)| {
+            let (redemptions, saving) = self.pool_usage(pool_key, &usages);
+
+            pool.admits(redemptions, saving)
+        });
+
+        let saving = usages.iter().map(|&(_, saving)| saving).sum();
+
+        if within_pools && best.saving.is_none_or(|best_saving| saving > best_saving) {
+            best.saving = Some(saving);
+            best.picks = picks.iter().map(|&choice| choice.clone()).collect();
+        }
+    }
+}
+
+/// Every distinct way the promotion can price each set of units it can claim,
+/// within its own budget.
+///
+/// Claiming nothing is always a choice. Pricings with the same usage are
+/// interchangeable to the search, so only the first is kept.
+fn choices(
+    exhaustive: &dyn ExhaustivePromotion,
+    item_group: &ItemGroup<'_>,
+    units: &[FreeUnit],
+) -> Result<Vec<(u64, Vec<Choice>)>, SolverError> {
+    let allowance = exhaustive.allowance();
+    let claimable = (0..item_group.len())
+        .map(|item_idx| exhaustive.can_claim(item_group, item_idx))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let eligible = units
+        .iter()
+        .enumerate()
+        .filter(|&(_, &(item_idx, _price))| claimable.get(item_idx).copied().unwrap_or(false))
+        .fold(0_u64, |eligible, (position, _)| eligible | 1 << position);
+
+    let mut all = vec![(0, vec![Choice::default()])];
+    let mut mask = eligible;
+
+    while mask != 0 {
+        let claimed: Vec<FreeUnit> = units
+            .iter()
+            .enumerate()
+            .filter(|&(position, _)| mask & (1 << position) != 0)
+            .map(|(_, &unit)| unit)
+            .collect();
+
+        let mut distinct: Vec<Choice> = Vec::new();
+
+        for redemptions in exhaustive.pricings(item_group, &claimed)? {
+            let mut usage = (0, 0);
+
+            for redemption in &redemptions {
+                usage.0 += redemption.redemptions;
+                usage.1 += redemption.saving(item_group)?;
+            }
+
+            if allowance.admits(usage.0, usage.1)
+                && !distinct.iter().any(|choice| choice.usage == usage)
+            {
+                distinct.push(Choice { redemptions, usage });
+            }
+        }
+
+        if !distinct.is_empty() {
+            all.push((mask, distinct));
+        }
+
+        mask = (mask - 1) & eligible;
+    }
+
+    Ok(all)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+
+    use decimal_percentage::Percentage;
+    use rusty_money::{Money, iso::GBP};
+    use slotmap::SlotMap;
+    use testresult::TestResult;
+
+    use crate::{
+        discounts::SimpleDiscount,
+        items::Item,
+        products::ProductKey,
+        promotions::{
+            PromotionKey, PromotionSlotKey,
+            budget::PromotionBudget,
+            promotion,
+            qualification::Qualification,
+            types::{DirectDiscountPromotion, MixAndMatchDiscount, MixAndMatchPromotion},
+        },
+        solvers::{ilp::ILPSolver, ilp::promotions::test_support::item_group_from_items},
+        tags::string::StringTagCollection,
+        utils::slot,
+    };
+
+    use super::*;
+
+    fn tagged(price: i64, tag: &str) -> Item<'static> {
+        Item::with_tags(
+            ProductKey::default(),
+            Money::from_minor(price, GBP),
+            StringTagCollection::from_strs(&[tag]),
+        )
+    }
+
+    #[test]
+    fn empty_item_group_costs_nothing() -> TestResult {
+        let item_group = item_group_from_items([]);
+
+        let result = ExhaustiveSolver::solve(&[], &item_group)?;
+
+        assert_eq!(result.total.to_minor_units(), 0);
+        assert_eq!(result.quality, SolutionQuality::Optimal);
+
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_baskets_over_the_unit_limit() {
+        let item_group = item_group_from_items([
+            tagged(100, "a").with_quantity(NonZeroU32::new(6).expect("non-zero quantity")),
+            tagged(200, "b").with_quantity(NonZeroU32::new(5).expect("non-zero quantity")),
+        ]);
+
+        let result = ExhaustiveSolver::solve(&[], &item_group);
+
+        assert!(matches!(
+            result,
+            Err(SolverError::TooManyUnits {
+                units: 11,
+                limit: ExhaustiveSolver::MAX_UNITS,
+            })
+        ));
+    }
+
+    #[test]
+    fn rejects_price_floors() {
+        let item_group =
+            item_group_from_items([tagged(100, "a").with_price_floor(Money::from_minor(80, GBP))]);
+
+        let result = ExhaustiveSolver::solve(&[], &item_group);
+
+        assert!(matches!(
+            result,
+            Err(SolverError::PriceFloorsUnenforced(0, "exhaustive"))
+        ));
+    }
+
+    #[test]
+    fn finds_the_same_optimum_as_the_ilp_solver() -> TestResult {
+        let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+        let mut slot_keys = SlotMap::<PromotionSlotKey, ()>::with_key();
+
+        // Half price on the main saves 300, but the meal deal saves 350 using both.
+        let item_group = item_group_from_items([tagged(600, "main"), tagged(250, "drink")]);
+
+        let promotions = [
+            promotion(DirectDiscountPromotion::new(
+                keys.insert(()),
+                Qualification::match_any(StringTagCollection::from_strs(&["main"])),
+                SimpleDiscount::PercentageOff(Percentage::from(0.5)),
+                PromotionBudget::unlimited(),
+            )),
+            promotion(MixAndMatchPromotion::new(
+                keys.insert(()),
+                vec![
+                    slot(
+                        &mut slot_keys,
+                        StringTagCollection::from_strs(&["main"]),
+                        1,
+                        Some(1),
+                    ),
+                    slot(
+                        &mut slot_keys,
+                        StringTagCollection::from_strs(&["drink"]),
+                        1,
+                        Some(1),
+                    ),
+                ],
+                MixAndMatchDiscount::FixedTotal(Money::from_minor(500, GBP)),
+                PromotionBudget::unlimited(),
+            )),
+        ];
+
+        let exhaustive = ExhaustiveSolver::solve(&promotions, &item_group)?;
+        let optimal = ILPSolver::solve(&promotions, &item_group)?;
+
+        assert_eq!(exhaustive.total, optimal.total);
+        assert_eq!(exhaustive.total.to_minor_units(), 500);
+        assert_eq!(
+            exhaustive.promotion_redemptions.len(),
+            2,
+            "both in the deal"
+        );
+
+        Ok(())
+    }
+}
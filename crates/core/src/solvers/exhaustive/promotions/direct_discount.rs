@@ -0,0 +1,108 @@
+//! Direct Discount Promotions Exhaustive
+
+use crate::{
+    items::groups::ItemGroup,
+    promotions::types::DirectDiscountPromotion,
+    solvers::{
+        SolverError,
+        exhaustive::promotions::ExhaustivePromotion,
+        greedy::{Allowance, FreeUnit, GreedyRedemption},
+    },
+};
+
+impl ExhaustivePromotion for DirectDiscountPromotion<'_> {
+    fn allowance(&self) -> Allowance {
+        Allowance::from_budget(self.budget(), self.redemption_limit())
+    }
+
+    fn can_claim(&self, item_group: &ItemGroup<'_>, item_idx: usize) -> Result<bool, SolverError> {
+        let item = item_group.get_item(item_idx)?;
+
+        Ok(self
+            .qualification()
+            .matches_in_context(item.tags(), item_group.context()))
+    }
+
+    fn pricings(
+        &self,
+        item_group: &ItemGroup<'_>,
+        units: &[FreeUnit],
+    ) -> Result<Vec<Vec<GreedyRedemption>>, SolverError> {
+        // Every unit is discounted on its own, so there is only one pricing.
+        let mut redemptions = Vec::with_capacity(units.len());
+
+        for &(item_idx, _price) in units {
+            let item = item_group.get_item(item_idx)?;
+            let mut redemption = GreedyRedemption::new(1);
+
+            redemption.claim(
+                item_idx,
+                1,
+                self.calculate_discounted_price(item)?.to_minor_units(),
+            );
+
+            redemptions.push(redemption);
+        }
+
+        Ok(vec![redemptions])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+
+    use decimal_percentage::Percentage;
+    use rusty_money::{Money, iso::GBP};
+    use testresult::TestResult;
+
+    use crate::{
+        discounts::SimpleDiscount,
+        items::Item,
+        products::ProductKey,
+        promotions::{PromotionKey, budget::PromotionBudget, qualification::Qualification},
+        solvers::ilp::promotions::test_support::item_group_from_items,
+        tags::string::StringTagCollection,
+    };
+
+    use super::*;
+
+    #[test]
+    fn discounts_each_unit_as_its_own_redemption() -> TestResult {
+        let item_group = item_group_from_items([
+            Item::with_tags(
+                ProductKey::default(),
+                Money::from_minor(200, GBP),
+                StringTagCollection::from_strs(&["sale"]),
+            )
+            .with_quantity(NonZeroU32::new(2).expect("non-zero quantity")),
+            Item::new(ProductKey::default(), Money::from_minor(300, GBP)),
+        ]);
+
+        let promotion = DirectDiscountPromotion::new(
+            PromotionKey::default(),
+            Qualification::match_any(StringTagCollection::from_strs(&["sale"])),
+            SimpleDiscount::PercentageOff(Percentage::from(0.25)),
+            PromotionBudget::unlimited(),
+        );
+
+        assert!(promotion.can_claim(&item_group, 0)?, "tagged for sale");
+        assert!(!promotion.can_claim(&item_group, 1)?, "not on sale");
+
+        let pricings = promotion.pricings(&item_group, &[(0, 200), (0, 200)])?;
+
+        assert_eq!(pricings.len(), 1, "only one way to price the units");
+
+        let redemptions = pricings.first().ok_or("expected a pricing")?;
+
+        assert_eq!(redemptions.len(), 2, "one redemption per unit");
+        assert!(
+            redemptions
+                .iter()
+                .all(|redemption| redemption.saving(&item_group).ok() == Some(50)),
+            "25% off each unit"
+        );
+
+        Ok(())
+    }
+}
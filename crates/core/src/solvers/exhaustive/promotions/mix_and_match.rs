@@ -0,0 +1,303 @@
+//! Mix-and-Match Promotions Exhaustive
+
+use crate::{
+    discounts::rounding::RoundingPolicy,
+    items::groups::ItemGroup,
+    promotions::types::MixAndMatchPromotion,
+    solvers::{
+        SolverError,
+        exhaustive::promotions::ExhaustivePromotion,
+        greedy::{
+            Allowance, FreeUnit, GreedyRedemption,
+            promotions::{promotion_rounding, redemption_from_units},
+        },
+    },
+};
+
+/// Slot bounds and the slots each unit of a bundle may fill.
+#[derive(Debug)]
+struct SlotFit {
+    bounds: Vec<(usize, Option<usize>)>,
+    matching: Vec<Vec<usize>>,
+}
+
+impl SlotFit {
+    /// Whether the units can be shared between the slots within their bounds.
+    fn fits(&self, units: &[usize]) -> bool {
+        let mut counts = vec![0; self.bounds.len()];
+
+        self.assign(units, &mut counts)
+    }
+
+    fn assign(&self, units: &[usize], counts: &mut [usize]) -> bool {
+        let Some((&unit, rest)) = units.split_first() else {
+            return self
+                .bounds
+                .iter()
+                .zip(counts.iter())
+                .all(|(&(min, _max), &count)| count >= min);
+        };
+
+        for &slot_idx in self.matching.get(unit).map_or(&[][..], Vec::as_slice) {
+            let (Some(&(_min, max)), Some(count)) =
+                (self.bounds.get(slot_idx), counts.get(slot_idx).copied())
+            else {
+                continue;
+            };
+
+            if max.is_some_and(|max| count >= max) {
+                continue;
+            }
+
+            if let Some(slot_count) = counts.get_mut(slot_idx) {
+                *slot_count += 1;
+            }
+
+            let fits = self.assign(rest, counts);
+
+            if let Some(slot_count) = counts.get_mut(slot_idx) {
+                *slot_count -= 1;
+            }
+
+            if fits {
+                return true;
+            }
+        }
+
+        false
+    }
+}
+
+impl MixAndMatchPromotion<'_> {
+    fn slot_fit(
+        &self,
+        item_group: &ItemGroup<'_>,
+        units: &[FreeUnit],
+    ) -> Result<SlotFit, SolverError> {
+        let context = item_group.context();
+        let mut matching = Vec::with_capacity(units.len());
+
+        for &(item_idx, _price) in units {
+            let item = item_group.get_item(item_idx)?;
+
+            matching.push(
+                self.slots()
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, slot)| {
+                        slot.qualification()
+                            .matches_in_context(item.tags(), context)
+                    })
+                    .map(|(slot_idx, _)| slot_idx)
+                    .collect(),
+            );
+        }
+
+        Ok(SlotFit {
+            bounds: self
+                .slots()
+                .iter()
+                .map(|slot| (slot.min(), slot.max()))
+                .collect(),
+            matching,
+        })
+    }
+
+    fn bundle_redemption(
+        &self,
+        units: &[FreeUnit],
+        bundle: &[usize],
+        rounding: RoundingPolicy,
+    ) -> Result<GreedyRedemption, SolverError> {
+        let bundle_units: Vec<FreeUnit> = bundle
+            .iter()
+            .filter_map(|&position| units.get(position).copied())
+            .collect();
+
+        let prices = self.bundle_prices(&bundle_units, rounding)?;
+
+        Ok(redemption_from_units(&bundle_units, &prices, 1))
+    }
+}
+
+/// Every way to split `positions` into bundles of `size` units that fill the slots.
+fn partitions(fit: &SlotFit, positions: &[usize], size: usize) -> Vec<Vec<Vec<usize>>> {
+    let Some((&first, rest)) = positions.split_first() else {
+        return vec![Vec::new()];
+    };
+
+    let mut found = Vec::new();
+
+    // The first unit must share a bundle with `size - 1` of the rest.
+    for mask in 0_u64..(1 << rest.len()) {
+        if mask.count_ones() as usize + 1 != size {
+            continue;
+        }
+
+        let mut bundle = vec![first];
+        let mut remaining = Vec::with_capacity(rest.len());
+
+        for (bit, &position) in rest.iter().enumerate() {
+            if mask & (1 << bit) == 0 {
+                remaining.push(position);
+            } else {
+                bundle.push(position);
+            }
+        }
+
+        if !fit.fits(&bundle) {
+            continue;
+        }
+
+        for mut bundles in partitions(fit, &remaining, size) {
+            bundles.insert(0, bundle.clone());
+            found.push(bundles);
+        }
+    }
+
+    found
+}
+
+impl ExhaustivePromotion for MixAndMatchPromotion<'_> {
+    fn allowance(&self) -> Allowance {
+        Allowance::from_budget(self.budget(), self.redemption_limit())
+    }
+
+    fn can_claim(&self, item_group: &ItemGroup<'_>, item_idx: usize) -> Result<bool, SolverError> {
+        let item = item_group.get_item(item_idx)?;
+
+        Ok(self.slots().iter().any(|slot| {
+            slot.qualification()
+                .matches_in_context(item.tags(), item_group.context())
+        }))
+    }
+
+    fn pricings(
+        &self,
+        item_group: &ItemGroup<'_>,
+        units: &[FreeUnit],
+    ) -> Result<Vec<Vec<GreedyRedemption>>, SolverError> {
+        if self.slots().is_empty() || units.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let fit = self.slot_fit(item_group, units)?;
+        let positions: Vec<usize> = (0..units.len()).collect();
+
+        // Variable-arity promotions form a single bundle from every unit.
+        let bundlings = if self.has_fixed_arity() {
+            let size = self.bundle_size();
+
+            if size == 0 || !units.len().is_multiple_of(size) {
+                return Ok(Vec::new());
+            }
+
+            partitions(&fit, &positions, size)
+        } else if fit.fits(&positions) {
+            vec![vec![positions]]
+        } else {
+            Vec::new()
+        };
+
+        let rounding = promotion_rounding(self.rounding(), item_group);
+
+        bundlings
+            .iter()
+            .map(|bundles| {
+                bundles
+                    .iter()
+                    .map(|bundle| self.bundle_redemption(units, bundle, rounding))
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use decimal_percentage::Percentage;
+    use rusty_money::{Money, iso::GBP};
+    use slotmap::SlotMap;
+    use testresult::TestResult;
+
+    use crate::{
+        items::Item,
+        products::ProductKey,
+        promotions::{
+            PromotionKey, PromotionSlotKey, budget::PromotionBudget, types::MixAndMatchDiscount,
+        },
+        solvers::ilp::promotions::test_support::item_group_from_items,
+        tags::string::StringTagCollection,
+        utils::slot,
+    };
+
+    use super::*;
+
+    #[test]
+    fn fixed_arity_prices_every_way_of_pairing_units() -> TestResult {
+        let item_group = item_group_from_items([
+            Item::with_tags(
+                ProductKey::default(),
+                Money::from_minor(1000, GBP),
+                StringTagCollection::from_strs(&["main"]),
+            ),
+            Item::with_tags(
+                ProductKey::default(),
+                Money::from_minor(300, GBP),
+                StringTagCollection::from_strs(&["drink"]),
+            ),
+            Item::with_tags(
+                ProductKey::default(),
+                Money::from_minor(200, GBP),
+                StringTagCollection::from_strs(&["drink"]),
+            ),
+            Item::with_tags(
+                ProductKey::default(),
+                Money::from_minor(100, GBP),
+                StringTagCollection::from_strs(&["main"]),
+            ),
+        ]);
+
+        let mut slot_keys = SlotMap::<PromotionSlotKey, ()>::with_key();
+
+        let promotion = MixAndMatchPromotion::new(
+            PromotionKey::default(),
+            vec![
+                slot(
+                    &mut slot_keys,
+                    StringTagCollection::from_strs(&["main"]),
+                    1,
+                    Some(1),
+                ),
+                slot(
+                    &mut slot_keys,
+                    StringTagCollection::from_strs(&["drink"]),
+                    1,
+                    Some(1),
+                ),
+            ],
+            MixAndMatchDiscount::PercentCheapest(Percentage::from(1.0)),
+            PromotionBudget::unlimited(),
+        );
+
+        let pricings =
+            promotion.pricings(&item_group, &[(0, 1000), (1, 300), (2, 200), (3, 100)])?;
+
+        let mut savings = pricings
+            .iter()
+            .map(|redemptions| {
+                redemptions
+                    .iter()
+                    .map(|redemption| redemption.saving(&item_group))
+                    .sum::<Result<i64, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        savings.sort_unstable();
+
+        // Each main pairs with either drink; the cheapest of each pair is free.
+        assert_eq!(savings, vec![300, 400]);
+
+        Ok(())
+    }
+}
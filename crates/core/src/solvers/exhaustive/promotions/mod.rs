@@ -0,0 +1,50 @@
+//! Exhaustive Promotions
+
+use std::fmt::Debug;
+
+use crate::{
+    items::groups::ItemGroup,
+    solvers::{
+        SolverError,
+        greedy::{Allowance, FreeUnit, GreedyRedemption},
+    },
+};
+
+mod direct_discount;
+mod mix_and_match;
+mod positional_discount;
+mod tiered_threshold;
+
+/// Prices a promotion by enumeration, for checking other solvers against.
+///
+/// [`ExhaustiveSolver`](crate::solvers::exhaustive::ExhaustiveSolver) tries every
+/// way of sharing the basket's units between promotions and asks each promotion
+/// how it could price the units it was given. Implementations should follow the
+/// promotion's rules directly rather than any solver's model of them.
+pub trait ExhaustivePromotion: Debug + Send + Sync {
+    /// Return the promotion's own budget, before any redemptions.
+    fn allowance(&self) -> Allowance;
+
+    /// Whether the promotion could ever claim units of the item at `item_idx`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SolverError::ItemGroup`] if the item is missing from the item group.
+    fn can_claim(&self, item_group: &ItemGroup<'_>, item_idx: usize) -> Result<bool, SolverError>;
+
+    /// Every way the promotion can redeem using exactly `units`.
+    ///
+    /// `units` come only from lines [`ExhaustivePromotion::can_claim`] accepts,
+    /// sorted most expensive first with ties in item group order. Each pricing
+    /// lists the redemptions that together claim all of `units`; return no
+    /// pricings when the units cannot all be used.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SolverError`] if a discounted price cannot be computed.
+    fn pricings(
+        &self,
+        item_group: &ItemGroup<'_>,
+        units: &[FreeUnit],
+    ) -> Result<Vec<Vec<GreedyRedemption>>, SolverError>;
+}
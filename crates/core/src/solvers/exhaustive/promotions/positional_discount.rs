@@ -0,0 +1,109 @@
+//! Positional Discount Promotions Exhaustive
+
+use crate::{
+    items::groups::ItemGroup,
+    promotions::types::PositionalDiscountPromotion,
+    solvers::{
+        SolverError,
+        exhaustive::promotions::ExhaustivePromotion,
+        greedy::{
+            Allowance, FreeUnit, GreedyRedemption,
+            promotions::{promotion_rounding, redemption_from_units},
+        },
+    },
+};
+
+impl ExhaustivePromotion for PositionalDiscountPromotion<'_> {
+    fn allowance(&self) -> Allowance {
+        Allowance::from_budget(self.budget(), self.redemption_limit())
+    }
+
+    fn can_claim(&self, item_group: &ItemGroup<'_>, item_idx: usize) -> Result<bool, SolverError> {
+        let item = item_group.get_item(item_idx)?;
+
+        Ok(self
+            .qualification()
+            .matches_in_context(item.tags(), item_group.context()))
+    }
+
+    fn pricings(
+        &self,
+        item_group: &ItemGroup<'_>,
+        units: &[FreeUnit],
+    ) -> Result<Vec<Vec<GreedyRedemption>>, SolverError> {
+        let size = usize::from(self.size());
+
+        if size == 0 || !units.len().is_multiple_of(size) {
+            return Ok(Vec::new());
+        }
+
+        // Bundles are filled in price order, so the units decide the bundles.
+        let rounding = promotion_rounding(self.rounding(), item_group);
+        let mut redemptions = Vec::with_capacity(units.len() / size);
+
+        for bundle in units.chunks_exact(size) {
+            let prices = self.bundle_prices(bundle, rounding)?;
+
+            redemptions.push(redemption_from_units(bundle, &prices, 1));
+        }
+
+        Ok(vec![redemptions])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use decimal_percentage::Percentage;
+    use rusty_money::{Money, iso::GBP};
+    use smallvec::SmallVec;
+    use testresult::TestResult;
+
+    use crate::{
+        discounts::SimpleDiscount,
+        items::Item,
+        products::ProductKey,
+        promotions::{PromotionKey, budget::PromotionBudget, qualification::Qualification},
+        solvers::ilp::promotions::test_support::item_group_from_items,
+    };
+
+    use super::*;
+
+    #[test]
+    fn only_whole_bundles_in_price_order_are_priced() -> TestResult {
+        let item_group = item_group_from_items([
+            Item::new(ProductKey::default(), Money::from_minor(400, GBP)),
+            Item::new(ProductKey::default(), Money::from_minor(300, GBP)),
+            Item::new(ProductKey::default(), Money::from_minor(200, GBP)),
+            Item::new(ProductKey::default(), Money::from_minor(100, GBP)),
+        ]);
+
+        let promotion = PositionalDiscountPromotion::new(
+            PromotionKey::default(),
+            Qualification::match_all(),
+            2,
+            SmallVec::from_slice(&[1]),
+            SimpleDiscount::PercentageOff(Percentage::from(1.0)),
+            PromotionBudget::unlimited(),
+        );
+
+        let partial = promotion.pricings(&item_group, &[(0, 400), (1, 300), (2, 200)])?;
+
+        assert!(partial.is_empty(), "three units cannot fill pairs");
+
+        let pricings =
+            promotion.pricings(&item_group, &[(0, 400), (1, 300), (2, 200), (3, 100)])?;
+
+        assert_eq!(pricings.len(), 1, "only one way to price the units");
+
+        let redemptions = pricings.first().ok_or("expected a pricing")?;
+
+        let savings = redemptions
+            .iter()
+            .map(|redemption| redemption.saving(&item_group))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        assert_eq!(savings, vec![300, 100], "{{400, 300}} and {{200, 100}}");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,277 @@
+//! Tiered Threshold Promotions Exhaustive
+
+use crate::{
+    discounts::rounding::RoundingPolicy,
+    items::groups::ItemGroup,
+    promotions::types::{
+        ThresholdDiscount, ThresholdTier, TierThreshold, TieredThresholdPromotion,
+    },
+    solvers::{
+        SolverError,
+        exhaustive::promotions::ExhaustivePromotion,
+        greedy::{
+            Allowance, FreeUnit, GreedyRedemption,
+            promotions::{allocate_total, promotion_rounding, redemption_from_units},
+        },
+    },
+};
+
+/// Spend and unit count of the units playing one role in a tier.
+#[derive(Debug, Default, Clone, Copy)]
+struct RoleTotals {
+    spend: i64,
+    count: u32,
+}
+
+impl RoleTotals {
+    fn add(&mut self, price: i64) {
+        self.spend += price;
+        self.count += 1;
+    }
+
+    fn reaches(self, threshold: &TierThreshold<'_>) -> bool {
+        threshold
+            .monetary_threshold()
+            .is_none_or(|spend| self.spend >= spend.to_minor_units())
+            && threshold
+                .item_count_threshold()
+                .is_none_or(|count| self.count >= count)
+    }
+
+    fn within(self, threshold: &TierThreshold<'_>) -> bool {
+        threshold
+            .monetary_threshold()
+            .is_none_or(|spend| self.spend <= spend.to_minor_units())
+            && threshold
+                .item_count_threshold()
+                .is_none_or(|count| self.count <= count)
+    }
+}
+
+/// Final prices of the tier's discounted units, or `None` if the discount cannot apply.
+fn discounted_prices(
+    tier: &ThresholdTier<'_>,
+    rounding: RoundingPolicy,
+    item_group: &ItemGroup<'_>,
+    discounted: &[FreeUnit],
+) -> Result<Option<Vec<i64>>, SolverError> {
+    let mut prices: Vec<i64> = discounted.iter().map(|&(_, price)| price).collect();
+    let spend: i64 = prices.iter().sum();
+
+    match tier.discount() {
+        ThresholdDiscount::PercentEachItem(_)
+        | ThresholdDiscount::AmountOffEachItem(_)
+        | ThresholdDiscount::FixedPriceEachItem(_) => {
+            for (price, &(item_idx, _)) in prices.iter_mut().zip(discounted) {
+                let item = item_group.get_item(item_idx)?;
+
+                *price = TieredThresholdPromotion::calculate_rounded_price(tier, item, rounding)?
+                    .to_minor_units();
+            }
+        }
+        ThresholdDiscount::AmountOffTotal(amount) => {
+            if discounted.is_empty() {
+                return Ok(None);
+            }
+
+            prices = allocate_total(discounted, (spend - amount.to_minor_units()).max(0));
+        }
+        ThresholdDiscount::FixedTotal(amount) => {
+            if discounted.is_empty() {
+                return Ok(None);
+            }
+
+            prices = allocate_total(discounted, amount.to_minor_units().max(0));
+        }
+        ThresholdDiscount::PercentCheapest(pct) => {
+            if let Some(cheapest) = prices.last_mut() {
+                let saving = rounding
+                    .discount_of(pct, *cheapest)
+                    .map_err(SolverError::Discount)?;
+
+                *cheapest = (*cheapest - saving).max(0);
+            }
+        }
+        ThresholdDiscount::FixedCheapest(amount) => {
+            if let Some(cheapest) = prices.last_mut() {
+                *cheapest = amount.to_minor_units().max(0);
+            }
+        }
+    }
+
+    Ok(Some(prices))
+}
+
+/// Price `units` as one redemption of `tier`, if they reach it without passing its caps.
+fn tier_redemption(
+    tier: &ThresholdTier<'_>,
+    rounding: RoundingPolicy,
+    item_group: &ItemGroup<'_>,
+    units: &[FreeUnit],
+) -> Result<Option<GreedyRedemption>, SolverError> {
+    let context = item_group.context();
+
+    let mut contribution = RoleTotals::default();
+    let mut discountable = RoleTotals::default();
+    let mut discounted = Vec::new();
+    let mut full_price = Vec::new();
+
+    for &unit in units {
+        let item = item_group.get_item(unit.0)?;
+
+        let contributes = tier
+            .contribution_qualification()
+            .matches_in_context(item.tags(), context);
+
+        let is_discountable = tier
+            .discount_qualification()
+            .matches_in_context(item.tags(), context);
+
+        if contributes {
+            contribution.add(unit.1);
+        }
+
+        if is_discountable {
+            discountable.add(unit.1);
+            discounted.push(unit);
+        } else if contributes {
+            full_price.push(unit);
+        } else {
+            return Ok(None);
+        }
+    }
+
+    let within_caps = tier
+        .upper_threshold()
+        .is_none_or(|upper| contribution.within(upper) && discountable.within(upper));
+
+    if !contribution.reaches(tier.lower_threshold()) || !within_caps {
+        return Ok(None);
+    }
+
+    let Some(prices) = discounted_prices(tier, rounding, item_group, &discounted)? else {
+        return Ok(None);
+    };
+
+    let mut redemption = redemption_from_units(&discounted, &prices, 1);
+
+    for (item_idx, price) in full_price {
+        redemption.claim(item_idx, 1, price);
+    }
+
+    Ok(Some(redemption))
+}
+
+impl ExhaustivePromotion for TieredThresholdPromotion<'_> {
+    fn allowance(&self) -> Allowance {
+        Allowance::from_budget(self.budget(), self.redemption_limit())
+    }
+
+    fn can_claim(&self, item_group: &ItemGroup<'_>, item_idx: usize) -> Result<bool, SolverError> {
+        let item = item_group.get_item(item_idx)?;
+        let context = item_group.context();
+
+        Ok(self.tiers().iter().any(|tier| {
+            tier.contribution_qualification()
+                .matches_in_context(item.tags(), context)
+                || tier
+                    .discount_qualification()
+                    .matches_in_context(item.tags(), context)
+        }))
+    }
+
+    fn pricings(
+        &self,
+        item_group: &ItemGroup<'_>,
+        units: &[FreeUnit],
+    ) -> Result<Vec<Vec<GreedyRedemption>>, SolverError> {
+        if units.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // At most one tier applies, redeemed once over all of its units.
+        let rounding = promotion_rounding(self.rounding(), item_group);
+
+        let mut pricings = Vec::new();
+
+        for tier in self.tiers() {
+            if let Some(redemption) = tier_redemption(tier, rounding, item_group, units)? {
+                pricings.push(vec![redemption]);
+            }
+        }
+
+        Ok(pricings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+
+    use rusty_money::{Money, iso::GBP};
+    use testresult::TestResult;
+
+    use crate::{
+        items::Item,
+        products::ProductKey,
+        promotions::{PromotionKey, budget::PromotionBudget, qualification::Qualification},
+        solvers::ilp::promotions::test_support::item_group_from_items,
+        tags::string::StringTagCollection,
+    };
+
+    use super::*;
+
+    #[test]
+    fn units_must_reach_the_tier_without_passing_its_cap() -> TestResult {
+        let item_group = item_group_from_items([
+            Item::with_tags(
+                ProductKey::default(),
+                Money::from_minor(500, GBP),
+                StringTagCollection::from_strs(&["food"]),
+            )
+            .with_quantity(NonZeroU32::new(2).expect("non-zero quantity")),
+            Item::with_tags(
+                ProductKey::default(),
+                Money::from_minor(300, GBP),
+                StringTagCollection::from_strs(&["wine"]),
+            ),
+        ]);
+
+        let promotion = TieredThresholdPromotion::new(
+            PromotionKey::default(),
+            vec![ThresholdTier::new(
+                TierThreshold::with_monetary_threshold(Money::from_minor(500, GBP)),
+                Some(TierThreshold::with_item_count_threshold(1)),
+                Qualification::match_any(StringTagCollection::from_strs(&["food"])),
+                Qualification::match_any(StringTagCollection::from_strs(&["wine"])),
+                ThresholdDiscount::AmountOffTotal(Money::from_minor(100, GBP)),
+            )],
+            PromotionBudget::unlimited(),
+        );
+
+        let food_only = promotion.pricings(&item_group, &[(0, 500)])?;
+
+        assert!(food_only.is_empty(), "nothing to discount");
+
+        let pricings = promotion.pricings(&item_group, &[(0, 500), (1, 300)])?;
+
+        assert_eq!(pricings.len(), 1, "only one way to price the units");
+
+        let redemptions = pricings.first().ok_or("expected a pricing")?;
+
+        assert_eq!(
+            redemptions
+                .iter()
+                .map(|redemption| redemption.saving(&item_group))
+                .sum::<Result<i64, _>>()?,
+            100,
+            "100 off the wine"
+        );
+
+        let over_cap = promotion.pricings(&item_group, &[(0, 500), (0, 500), (1, 300)])?;
+
+        assert!(over_cap.is_empty(), "two contributing units pass the cap");
+
+        Ok(())
+    }
+}
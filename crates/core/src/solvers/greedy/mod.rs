@@ -0,0 +1,840 @@
+//! Greedy Solver
+//!
+//! Prices a basket without an ILP: promotions propose redemptions one at a time
+//! and the one saving the most is applied, until none save anything. A few rounds
+//! of local improvement then give each redemption up in turn and refill, keeping
+//! any change that saves more. The result is fast to compute on very large
+//! baskets but carries no bound on its distance from the optimum.
+
+use rusty_money::Money;
+use smallvec::SmallVec;
+
+use crate::{
+    items::{Item, groups::ItemGroup},
+    promotions::{
+        Promotion, PromotionKey,
+        budget::{BudgetPoolKey, BudgetPools},
+        funding::attribute_funding,
+        redemptions::PromotionRedemption,
+    },
+    solvers::{SolutionQuality, Solver, SolverError, SolverResult, ilp::ILPPromotion},
+};
+
+pub mod promotions;
+
+pub use promotions::{Allowance, ClaimedUnits, FreeUnit, GreedyPromotion, GreedyRedemption};
+
+/// Rounds of local improvement after the initial greedy fill.
+const IMPROVEMENT_PASSES: usize = 2;
+
+/// Solver applying the redemption with the largest marginal saving until none is left
+#[derive(Debug)]
+pub struct GreedySolver;
+
+impl GreedySolver {
+    /// Solve with shared budget pools, deducting the allocation's usage from them.
+    ///
+    /// Promotions drawing on a pool are jointly limited by the pool's remaining
+    /// redemptions and discount value, as with
+    /// [`ILPSolver::solve_with_budget_pools()`](crate::solvers::ilp::ILPSolver::solve_with_budget_pools).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SolverError::PriceFloorsUnenforced`] if an item has a price floor,
+    /// [`SolverError::GreedyUnsupported`] if an applicable promotion has no
+    /// greedy heuristic, [`SolverError::UnknownBudgetPool`] if a promotion draws on a
+    /// pool missing from `pools`, or another [`SolverError`] if pricing fails.
+    pub fn solve_with_budget_pools<'b>(
+        promotions: &[Promotion<'_>],
+        item_group: &ItemGroup<'b>,
+        pools: &mut BudgetPools<'_>,
+    ) -> Result<SolverResult<'b>, SolverError> {
+        let promotion_refs: SmallVec<[&dyn ILPPromotion; 5]> =
+            promotions.iter().map(AsRef::as_ref).collect();
+
+        Self::solve_promotions(&promotion_refs, item_group, pools)
+    }
+
+    /// Solve borrowed promotions with shared budget pools.
+    ///
+    /// The ILP solver uses this to price a basket it ran out of time on.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`GreedySolver::solve_with_budget_pools()`].
+    pub(crate) fn solve_promotions<'b>(
+        promotions: &[&dyn ILPPromotion],
+        item_group: &ItemGroup<'b>,
+        pools: &mut BudgetPools<'_>,
+    ) -> Result<SolverResult<'b>, SolverError> {
+        if item_group.is_empty() {
+            return Ok(SolverResult {
+                affected_items: SmallVec::with_capacity(0),
+                unaffected_items: SmallVec::with_capacity(0),
+                total: Money::from_minor(0, item_group.currency()),
+                promotion_redemptions: SmallVec::with_capacity(0),
+                quality: SolutionQuality::Optimal,
+            });
+        }
+
+        if let Some(item_idx) = item_group
+            .iter()
+            .position(|item| item.price_floor().is_some())
+        {
+            return Err(SolverError::PriceFloorsUnenforced(item_idx, "greedy"));
+        }
+
+        let search = Search::new(promotions, item_group, pools)?;
+
+        let mut allocation = Allocation::new(item_group, search.entries.len());
+
+        search.fill(&mut allocation, None)?;
+        search.improve(&mut allocation)?;
+
+        let result = search.result(&allocation)?;
+
+        search.debit(&allocation, pools)?;
+
+        Ok(result)
+    }
+}
+
+impl Solver for GreedySolver {
+    fn solve<'b>(
+        promotions: &[Promotion<'_>],
+        item_group: &ItemGroup<'b>,
+    ) -> Result<SolverResult<'b>, SolverError> {
+        Self::solve_with_budget_pools(promotions, item_group, &mut BudgetPools::default())
+    }
+}
+
+/// An applicable promotion taking part in the search.
+#[derive(Debug)]
+struct Entry<'p> {
+    key: PromotionKey,
+    greedy: &'p dyn GreedyPromotion,
+    allowance: Allowance,
+    pools: &'p [BudgetPoolKey],
+    promotion: &'p dyn ILPPromotion,
+}
+
+/// Redemptions chosen so far and the units they leave free.
+#[derive(Debug, Clone)]
+struct Allocation {
+    /// Unclaimed units of each item line
+    free: Vec<u32>,
+
+    /// Redemptions of each entry, in the order entries were added
+    redeemed: Vec<Vec<GreedyRedemption>>,
+}
+
+impl Allocation {
+    fn new(item_group: &ItemGroup<'_>, entries: usize) -> Self {
+        Self {
+            free: item_group.iter().map(Item::quantity).collect(),
+            redeemed: vec![Vec::new(); entries],
+        }
+    }
+
+    fn claim(&mut self, redemption: &GreedyRedemption) {
+        for units in &redemption.units {
+            if let Some(free) = self.free.get_mut(units.item_idx) {
+                *free = free.saturating_sub(units.quantity);
+            }
+        }
+    }
+
+    fn release(&mut self, redemption: &GreedyRedemption) {
+        for units in &redemption.units {
+            if let Some(free) = self.free.get_mut(units.item_idx) {
+                *free += units.quantity;
+            }
+        }
+    }
+
+    /// Whether every unit `redemption` claims is still free.
+    fn has_units_for(&self, redemption: &GreedyRedemption) -> bool {
+        let mut needed: SmallVec<[(usize, u32); 4]> = SmallVec::new();
+
+        for units in &redemption.units {
+            match needed.iter_mut().find(|(idx, _)| *idx == units.item_idx) {
+                Some((_, quantity)) => *quantity += units.quantity,
+                None => needed.push((units.item_idx, units.quantity)),
+            }
+        }
+
+        needed.iter().all(|&(item_idx, quantity)| {
+            self.free
+                .get(item_idx)
+                .is_some_and(|&free| free >= quantity)
+        })
+    }
+}
+
+/// Redemptions counted and discount given, in minor units.
+type Usage = (u32, i64);
+
+/// The promotions, item group and pools a greedy solve works over.
+#[derive(Debug)]
+struct Search<'p, 'g, 'b> {
+    entries: Vec<Entry<'p>>,
+    item_group: &'g ItemGroup<'b>,
+    pools: Vec<(BudgetPoolKey, Allowance)>,
+}
+
+impl<'p, 'g, 'b> Search<'p, 'g, 'b> {
+    fn new(
+        promotions: &[&'p dyn ILPPromotion],
+        item_group: &'g ItemGroup<'b>,
+        pools: &BudgetPools<'_>,
+    ) -> Result<Self, SolverError> {
+        let mut entries = Vec::with_capacity(promotions.len());
+
+        for &promotion in promotions {
+            if !promotion.is_applicable(item_group) {
+                continue;
+            }
+
+            let key = promotion.key();
+            let greedy = promotion
+                .greedy()
+                .ok_or(SolverError::GreedyUnsupported(key))?;
+
+            for &pool_key in promotion.budget_pools() {
+                if !pools.contains_key(pool_key) {
+                    return Err(SolverError::UnknownBudgetPool {
+                        promotion_key: key,
+                        pool_key,
+                    });
+                }
+            }
+
+            entries.push(Entry {
+                key,
+                greedy,
+                allowance: greedy.allowance(),
+                pools: promotion.budget_pools(),
+                promotion,
+            });
+        }
+
+        let pools = pools
+            .iter()
+            .map(|(pool_key, pool)| {
+                let allowance = Allowance {
+                    redemptions: pool.redemption_limit,
+                    discount_minor: pool.monetary_limit.map(|limit| limit.to_minor_units()),
+                };
+
+                (pool_key, allowance)
+            })
+            .collect();
+
+        Ok(Self {
+            entries,
+            item_group,
+            pools,
+        })
+    }
+
+    fn usage(&self, redeemed: &[GreedyRedemption]) -> Result<Usage, SolverError> {
+        let mut usage = (0, 0);
+
+        for redemption in redeemed {
+            usage.0 += redemption.redemptions;
+            usage.1 += redemption.saving(self.item_group)?;
+        }
+
+        Ok(usage)
+    }
+
+    fn usages(&self, allocation: &Allocation) -> Result<Vec<Usage>, SolverError> {
+        allocation
+            .redeemed
+            .iter()
+            .map(|redeemed| self.usage(redeemed))
+            .collect()
+    }
+
+    /// Combined usage of a pool across the entries drawing on it.
+    fn pool_usage(&self, pool_key: BudgetPoolKey, usages: &[Usage]) -> Usage {
+        self.entries
+            .iter()
+            .zip(usages)
+            .filter(|(entry, _)| entry.pools.contains(&pool_key))
+            .fold((0, 0), |total, (_, usage)| {
+                (total.0 + usage.0, total.1 + usage.1)
+            })
+    }
+
+    /// What an entry may still redeem, given its own and its pools' usage.
+    fn allowance(&self, entry_idx: usize, usages: &[Usage]) -> Allowance {
+        let (Some(entry), Some(&(redemptions, saving))) =
+            (self.entries.get(entry_idx), usages.get(entry_idx))
+        else {
+            return Allowance::unlimited();
+        };
+
+        let mut allowance = entry.allowance.after(redemptions, saving);
+
+        for &(pool_key, pool) in &self.pools {
+            if entry.pools.contains(&pool_key) {
+                let (redemptions, saving) = self.pool_usage(pool_key, usages);
+
+                allowance = allowance.min(pool.after(redemptions, saving));
+            }
+        }
+
+        allowance
+    }
+
+    /// Whether every entry and pool stays within its limits.
+    fn within_budgets(&self, usages: &[Usage]) -> bool {
+        self.entries
+            .iter()
+            .zip(usages)
+            .all(|(entry, &(redemptions, saving))| entry.allowance.admits(redemptions, saving))
+            && self.pools.iter().all(|&(pool_key, pool)| {
+                let (redemptions, saving) = self.pool_usage(pool_key, usages);
+
+                pool.admits(redemptions, saving)
+            })
+    }
+
+    fn total_saving(usages: &[Usage]) -> i64 {
+        usages.iter().map(|&(_, saving)| saving).sum()
+    }
+
+    /// The best valid proposal across entries: `(saving, entry_idx, redemption)`.
+    fn best_candidate(
+        &self,
+        allocation: &Allocation,
+        usages: &[Usage],
+        skipped: &[bool],
+    ) -> Result<Option<(i64, usize, GreedyRedemption)>, SolverError> {
+        let mut best: Option<(i64, usize, GreedyRedemption)> = None;
+
+        for (entry_idx, entry) in self.entries.iter().enumerate() {
+            if skipped.get(entry_idx).copied().unwrap_or(true) {
+                continue;
+            }
+
+            let allowance = self.allowance(entry_idx, usages);
+
+            if allowance.is_exhausted() {
+                continue;
+            }
+
+            let redeemed = allocation
+                .redeemed
+                .get(entry_idx)
+                .map_or(&[][..], Vec::as_slice);
+
+            let Some(redemption) = entry.greedy.best_redemption(
+                self.item_group,
+                &allocation.free,
+                redeemed,
+                allowance,
+            )?
+            else {
+                continue;
+            };
+
+            let saving = redemption.saving(self.item_group)?;
+
+            let valid = saving > 0
+                && !redemption.units.is_empty()
+                && allowance.admits(redemption.redemptions, saving)
+                && allocation.has_units_for(&redemption);
+
+            if valid
+                && best
+                    .as_ref()
+                    .is_none_or(|(best_saving, ..)| saving > *best_saving)
+            {
+                best = Some((saving, entry_idx, redemption));
+            }
+        }
+
+        Ok(best)
+    }
+
+    /// Apply the best proposal until no entry can save anything more.
+    ///
+    /// An entry whose repriced redemptions break a budget or stop saving more
+    /// is rolled back and sits out the rest of the fill, as does `banned`.
+    fn fill(&self, allocation: &mut Allocation, banned: Option<usize>) -> Result<(), SolverError> {
+        let mut skipped: Vec<bool> = (0..self.entries.len())
+            .map(|entry_idx| Some(entry_idx) == banned)
+            .collect();
+
+        let mut usages = self.usages(allocation)?;
+
+        while let Some((_saving, entry_idx, redemption)) =
+            self.best_candidate(allocation, &usages, &skipped)?
+        {
+            let (Some(entry), Some(redeemed)) = (
+                self.entries.get(entry_idx),
+                allocation.redeemed.get_mut(entry_idx),
+            ) else {
+                break;
+            };
+
+            let previous = redeemed.clone();
+
+            redeemed.push(redemption.clone());
+            entry.greedy.reprice(self.item_group, redeemed)?;
+
+            let mut trial = usages.clone();
+
+            if let Some(usage) = trial.get_mut(entry_idx) {
+                *usage = self.usage(redeemed)?;
+            }
+
+            if self.within_budgets(&trial)
+                && Self::total_saving(&trial) > Self::total_saving(&usages)
+            {
+                allocation.claim(&redemption);
+                usages = trial;
+            } else {
+                *redeemed = previous;
+
+                if let Some(skip) = skipped.get_mut(entry_idx) {
+                    *skip = true;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Give each redemption up in turn and refill, keeping strict improvements.
+    fn improve(&self, allocation: &mut Allocation) -> Result<(), SolverError> {
+        for _pass in 0..IMPROVEMENT_PASSES {
+            let mut improved = false;
+
+            for entry_idx in 0..self.entries.len() {
+                let mut redemption_idx = 0;
+
+                while redemption_idx < allocation.redeemed.get(entry_idx).map_or(0, Vec::len) {
+                    if let Some(better) =
+                        self.without_redemption(allocation, entry_idx, redemption_idx)?
+                    {
+                        *allocation = better;
+                        improved = true;
+                    }
+
+                    redemption_idx += 1;
+                }
+            }
+
+            if !improved {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Refill after giving up one redemption, if that saves strictly more.
+    fn without_redemption(
+        &self,
+        allocation: &Allocation,
+        entry_idx: usize,
+        redemption_idx: usize,
+    ) -> Result<Option<Allocation>, SolverError> {
+        let mut trial = allocation.clone();
+
+        let (Some(entry), Some(redeemed)) = (
+            self.entries.get(entry_idx),
+            trial.redeemed.get_mut(entry_idx),
+        ) else {
+            return Ok(None);
+        };
+
+        if redemption_idx >= redeemed.len() {
+            return Ok(None);
+        }
+
+        let removed = redeemed.remove(redemption_idx);
+
+        entry.greedy.reprice(self.item_group, redeemed)?;
+        trial.release(&removed);
+
+        self.fill(&mut trial, Some(entry_idx))?;
+        self.fill(&mut trial, None)?;
+
+        let before = self.usages(allocation)?;
+        let after = self.usages(&trial)?;
+
+        Ok((self.within_budgets(&after)
+            && Self::total_saving(&after) > Self::total_saving(&before))
+        .then_some(trial))
+    }
+
+    fn result(&self, allocation: &Allocation) -> Result<SolverResult<'b>, SolverError> {
+        let redeemed =
+            self.entries
+                .iter()
+                .zip(&allocation.redeemed)
+                .flat_map(|(entry, redeemed)| {
+                    redeemed
+                        .iter()
+                        .map(|redemption| (entry.key, entry.promotion, redemption))
+                });
+
+        solver_result(self.item_group, redeemed, SolutionQuality::Heuristic)
+    }
+
+    /// Deduct the allocation's usage from each pool's remaining limits.
+    fn debit(
+        &self,
+        allocation: &Allocation,
+        pools: &mut BudgetPools<'_>,
+    ) -> Result<(), SolverError> {
+        let usages = self.usages(allocation)?;
+
+        debit_pools(pools, |pool_key| self.pool_usage(pool_key, &usages));
+
+        Ok(())
+    }
+}
+
+/// Price a basket from the redemptions chosen for each promotion.
+///
+/// Units no redemption claims stay at full price. Each redemption gets its own
+/// redemption index, in the order given, and its discount is attributed to the
+/// promotion's funders.
+pub(crate) fn solver_result<'b, 'r>(
+    item_group: &ItemGroup<'b>,
+    redeemed: impl IntoIterator<Item = (PromotionKey, &'r dyn ILPPromotion, &'r GreedyRedemption)>,
+    quality: SolutionQuality,
+) -> Result<SolverResult<'b>, SolverError> {
+    let currency = item_group.currency();
+
+    let mut free: Vec<u32> = item_group.iter().map(Item::quantity).collect();
+    let mut total_minor = 0_i64;
+    let mut affected_items: SmallVec<[usize; 10]> = SmallVec::new();
+    let mut promotion_redemptions: SmallVec<[PromotionRedemption<'b>; 10]> = SmallVec::new();
+
+    for (redemption_idx, (promotion_key, promotion, redemption)) in redeemed.into_iter().enumerate()
+    {
+        let first_line = promotion_redemptions.len();
+
+        for units in &redemption.units {
+            let item = item_group.get_item(units.item_idx)?;
+
+            total_minor += units.final_minor * i64::from(units.quantity);
+
+            if let Some(free) = free.get_mut(units.item_idx) {
+                *free = free.saturating_sub(units.quantity);
+            }
+
+            if !affected_items.contains(&units.item_idx) {
+                affected_items.push(units.item_idx);
+            }
+
+            promotion_redemptions.push(PromotionRedemption {
+                promotion_key,
+                item_idx: units.item_idx,
+                redemption_idx,
+                original_price: *item.price(),
+                final_price: Money::from_minor(units.final_minor, currency),
+                quantity: units.quantity,
+                funding: SmallVec::new(),
+                floor_limited: false,
+            });
+        }
+
+        attribute_funding(promotion_redemptions.iter_mut().skip(first_line), |_key| {
+            Some(promotion)
+        })?;
+    }
+
+    let mut unaffected_items: SmallVec<[usize; 10]> = SmallVec::new();
+
+    for (item_idx, (item, &free)) in item_group.iter().zip(&free).enumerate() {
+        if free > 0 {
+            total_minor += item.price().to_minor_units() * i64::from(free);
+            unaffected_items.push(item_idx);
+        }
+    }
+
+    Ok(SolverResult {
+        affected_items,
+        unaffected_items,
+        total: Money::from_minor(total_minor, currency),
+        promotion_redemptions,
+        quality,
+    })
+}
+
+/// Deduct each pool's usage, as `(redemptions, discount)`, from its remaining limits.
+pub(crate) fn debit_pools(
+    pools: &mut BudgetPools<'_>,
+    usage: impl Fn(BudgetPoolKey) -> (u32, i64),
+) {
+    for (pool_key, pool) in pools.iter_mut() {
+        let (redemptions, saving) = usage(pool_key);
+
+        if let Some(limit) = pool.redemption_limit {
+            pool.redemption_limit = Some(limit.saturating_sub(redemptions));
+        }
+
+        if let Some(limit) = pool.monetary_limit {
+            let remaining = limit.to_minor_units().saturating_sub(saving).max(0);
+
+            pool.monetary_limit = Some(Money::from_minor(remaining, limit.currency()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+
+    use decimal_percentage::Percentage;
+    use rusty_money::{Money, iso::GBP};
+    use slotmap::SlotMap;
+    use smallvec::SmallVec;
+    use testresult::TestResult;
+
+    use crate::{
+        discounts::SimpleDiscount,
+        items::Item,
+        products::ProductKey,
+        promotions::{
+            PromotionKey, PromotionSlotKey,
+            budget::PromotionBudget,
+            promotion,
+            qualification::Qualification,
+            types::{
+                DirectDiscountPromotion, MixAndMatchDiscount, MixAndMatchPromotion,
+                PositionalDiscountPromotion,
+            },
+        },
+        solvers::{ilp::ILPSolver, ilp::promotions::test_support::item_group_from_items},
+        tags::string::StringTagCollection,
+        utils::slot,
+    };
+
+    use super::*;
+
+    fn tagged(price: i64, tag: &str) -> Item<'static> {
+        Item::with_tags(
+            ProductKey::default(),
+            Money::from_minor(price, GBP),
+            StringTagCollection::from_strs(&[tag]),
+        )
+    }
+
+    #[test]
+    fn empty_item_group_costs_nothing() -> TestResult {
+        let item_group = item_group_from_items([]);
+
+        let result = GreedySolver::solve(&[], &item_group)?;
+
+        assert_eq!(result.total.to_minor_units(), 0);
+        assert!(result.unaffected_items.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn applies_the_largest_saving_first() -> TestResult {
+        let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+
+        let item_group = item_group_from_items([tagged(100, "a"), tagged(200, "a")]);
+
+        let promotions = [
+            promotion(DirectDiscountPromotion::new(
+                keys.insert(()),
+                Qualification::match_all(),
+                SimpleDiscount::PercentageOff(Percentage::from(0.10)),
+                PromotionBudget::unlimited(),
+            )),
+            promotion(PositionalDiscountPromotion::new(
+                keys.insert(()),
+                Qualification::match_all(),
+                2,
+                SmallVec::from_slice(&[1]),
+                SimpleDiscount::PercentageOff(Percentage::from(1.0)),
+                PromotionBudget::unlimited(),
+            )),
+        ];
+
+        let result = GreedySolver::solve(&promotions, &item_group)?;
+
+        assert_eq!(result.total.to_minor_units(), 200, "cheaper item free");
+        assert_eq!(result.quality, SolutionQuality::Heuristic);
+        assert_eq!(result.affected_items.as_slice(), &[1, 0]);
+        assert!(result.unaffected_items.is_empty());
+        assert!(
+            result
+                .promotion_redemptions
+                .iter()
+                .all(|redemption| redemption.redemption_idx == 0),
+            "one bundle"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn local_improvement_undoes_a_greedy_trap() -> TestResult {
+        let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+        let mut slot_keys = SlotMap::<PromotionSlotKey, ()>::with_key();
+
+        // Greedy first spends the main on the 50% direct discount (saving 300),
+        // stranding the drink; the meal deal saves 350 using both.
+        let item_group = item_group_from_items([tagged(600, "main"), tagged(250, "drink")]);
+
+        let promotions = [
+            promotion(DirectDiscountPromotion::new(
+                keys.insert(()),
+                Qualification::match_any(StringTagCollection::from_strs(&["main"])),
+                SimpleDiscount::PercentageOff(Percentage::from(0.5)),
+                PromotionBudget::unlimited(),
+            )),
+            promotion(DirectDiscountPromotion::new(
+                keys.insert(()),
+                Qualification::match_any(StringTagCollection::from_strs(&["drink"])),
+                SimpleDiscount::AmountOff(Money::from_minor(10, GBP)),
+                PromotionBudget::unlimited(),
+            )),
+            promotion(MixAndMatchPromotion::new(
+                keys.insert(()),
+                vec![
+                    slot(
+                        &mut slot_keys,
+                        StringTagCollection::from_strs(&["main"]),
+                        1,
+                        Some(1),
+                    ),
+                    slot(
+                        &mut slot_keys,
+                        StringTagCollection::from_strs(&["drink"]),
+                        1,
+                        Some(1),
+                    ),
+                ],
+                MixAndMatchDiscount::FixedTotal(Money::from_minor(500, GBP)),
+                PromotionBudget::unlimited(),
+            )),
+        ];
+
+        let greedy = GreedySolver::solve(&promotions, &item_group)?;
+        let optimal = ILPSolver::solve(&promotions, &item_group)?;
+
+        assert_eq!(greedy.total, optimal.total, "swap reaches the optimum");
+        assert_eq!(greedy.total.to_minor_units(), 500);
+
+        Ok(())
+    }
+
+    #[test]
+    fn budget_pools_are_shared_and_debited() -> TestResult {
+        let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+        let mut pools = BudgetPools::default();
+
+        let pool = pools.insert(PromotionBudget {
+            redemption_limit: Some(3),
+            monetary_limit: Some(Money::from_minor(250, GBP)),
+        });
+
+        let item_group = item_group_from_items([
+            tagged(100, "a").with_quantity(NonZeroU32::new(4).expect("non-zero quantity")),
+            tagged(200, "b"),
+        ]);
+
+        let promotions = [
+            promotion(
+                DirectDiscountPromotion::new(
+                    keys.insert(()),
+                    Qualification::match_any(StringTagCollection::from_strs(&["a"])),
+                    SimpleDiscount::PercentageOff(Percentage::from(0.5)),
+                    PromotionBudget::unlimited(),
+                )
+                .with_budget_pool(pool),
+            ),
+            promotion(
+                DirectDiscountPromotion::new(
+                    keys.insert(()),
+                    Qualification::match_any(StringTagCollection::from_strs(&["b"])),
+                    SimpleDiscount::PercentageOff(Percentage::from(0.5)),
+                    PromotionBudget::unlimited(),
+                )
+                .with_budget_pool(pool),
+            ),
+        ];
+
+        let result = GreedySolver::solve_with_budget_pools(&promotions, &item_group, &mut pools)?;
+
+        // Three half-price "a" units use up the redemptions; swapping one for
+        // the "b" unit saves more.
+        assert_eq!(
+            result.total.to_minor_units(),
+            400,
+            "200 of discount in total"
+        );
+        assert_eq!(
+            pools.get(pool).map(|budget| budget.redemption_limit),
+            Some(Some(0)),
+            "three redemptions used"
+        );
+        assert_eq!(
+            pools
+                .get(pool)
+                .and_then(|budget| budget.monetary_limit)
+                .map(|limit| limit.to_minor_units()),
+            Some(50),
+            "discount value left"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn price_floors_are_rejected() {
+        let item_group = item_group_from_items([
+            tagged(100, "a"),
+            tagged(100, "a").with_price_floor(Money::from_minor(80, GBP)),
+        ]);
+
+        let result = GreedySolver::solve(&[], &item_group);
+
+        assert!(matches!(
+            result,
+            Err(SolverError::PriceFloorsUnenforced(1, "greedy"))
+        ));
+    }
+
+    #[test]
+    fn unknown_budget_pool_is_rejected() {
+        let mut other_pools = BudgetPools::default();
+        let missing = other_pools.insert(PromotionBudget::unlimited());
+
+        let item_group = item_group_from_items([tagged(100, "a")]);
+
+        let promotions = [promotion(
+            DirectDiscountPromotion::new(
+                PromotionKey::default(),
+                Qualification::match_all(),
+                SimpleDiscount::PercentageOff(Percentage::from(0.5)),
+                PromotionBudget::unlimited(),
+            )
+            .with_budget_pool(missing),
+        )];
+
+        let result = GreedySolver::solve_with_budget_pools(
+            &promotions,
+            &item_group,
+            &mut BudgetPools::default(),
+        );
+
+        assert!(matches!(
+            result,
+            Err(SolverError::UnknownBudgetPool { pool_key, .. }) if pool_key == missing
+        ));
+    }
+}
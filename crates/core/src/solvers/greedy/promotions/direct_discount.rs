@@ -0,0 +1,154 @@
+//! Direct Discount Promotions Greedy
+
+use crate::{
+    items::groups::ItemGroup,
+    promotions::types::DirectDiscountPromotion,
+    solvers::{
+        SolverError,
+        greedy::promotions::{Allowance, GreedyPromotion, GreedyRedemption},
+    },
+};
+
+impl GreedyPromotion for DirectDiscountPromotion<'_> {
+    fn allowance(&self) -> Allowance {
+        Allowance::from_budget(self.budget(), self.redemption_limit())
+    }
+
+    fn best_redemption(
+        &self,
+        item_group: &ItemGroup<'_>,
+        free: &[u32],
+        _redeemed: &[GreedyRedemption],
+        allowance: Allowance,
+    ) -> Result<Option<GreedyRedemption>, SolverError> {
+        // Each unit is its own redemption, so take as many units of the single
+        // most rewarding line as the allowance lets through.
+        let mut best: Option<(i64, usize, u32, i64)> = None;
+
+        for (item_idx, item) in item_group.iter().enumerate() {
+            let available = free.get(item_idx).copied().unwrap_or(0);
+
+            if available == 0
+                || !self
+                    .qualification()
+                    .matches_in_context(item.tags(), item_group.context())
+            {
+                continue;
+            }
+
+            let discounted_minor = self.calculate_discounted_price(item)?.to_minor_units();
+            let unit_saving = item.price().to_minor_units() - discounted_minor;
+
+            if unit_saving <= 0 {
+                continue;
+            }
+
+            let mut units = available;
+
+            if let Some(left) = allowance.redemptions {
+                units = units.min(left);
+            }
+
+            if let Some(left) = allowance.discount_minor {
+                let affordable = u32::try_from((left / unit_saving).max(0)).unwrap_or(u32::MAX);
+
+                units = units.min(affordable);
+            }
+
+            let saving = unit_saving * i64::from(units);
+
+            if units > 0 && best.is_none_or(|(best_saving, ..)| saving > best_saving) {
+                best = Some((saving, item_idx, units, discounted_minor));
+            }
+        }
+
+        Ok(best.map(|(_saving, item_idx, units, discounted_minor)| {
+            let mut redemption = GreedyRedemption::new(units);
+
+            redemption.claim(item_idx, units, discounted_minor);
+
+            redemption
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+
+    use decimal_percentage::Percentage;
+    use rusty_money::{Money, iso::GBP};
+    use testresult::TestResult;
+
+    use crate::{
+        discounts::SimpleDiscount,
+        items::Item,
+        products::ProductKey,
+        promotions::{PromotionKey, budget::PromotionBudget, qualification::Qualification},
+        solvers::ilp::promotions::test_support::item_group_from_items,
+    };
+
+    use super::*;
+
+    #[test]
+    fn takes_the_most_rewarding_line_within_the_allowance() -> TestResult {
+        let item_group = item_group_from_items([
+            Item::new(ProductKey::default(), Money::from_minor(100, GBP))
+                .with_quantity(NonZeroU32::new(4).expect("non-zero quantity")),
+            Item::new(ProductKey::default(), Money::from_minor(300, GBP)),
+        ]);
+
+        let promotion = DirectDiscountPromotion::new(
+            PromotionKey::default(),
+            Qualification::match_all(),
+            SimpleDiscount::PercentageOff(Percentage::from(0.5)),
+            PromotionBudget::unlimited(),
+        );
+
+        let unlimited =
+            promotion.best_redemption(&item_group, &[4, 1], &[], promotion.allowance())?;
+
+        assert_eq!(
+            unlimited.map(|redemption| (redemption.units_of(0), redemption.redemptions)),
+            Some((4, 4)),
+            "four units of the cheaper line save more than one expensive unit"
+        );
+
+        let capped = Allowance {
+            redemptions: None,
+            discount_minor: Some(120),
+        };
+
+        let redemption = promotion.best_redemption(&item_group, &[4, 1], &[], capped)?;
+
+        assert_eq!(
+            redemption.map(|redemption| (redemption.units_of(0), redemption.redemptions)),
+            Some((2, 2)),
+            "the expensive unit alone saves more than the allowance"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn ignores_discounts_that_raise_the_price() -> TestResult {
+        let item_group = item_group_from_items([Item::new(
+            ProductKey::default(),
+            Money::from_minor(100, GBP),
+        )]);
+
+        let promotion = DirectDiscountPromotion::new(
+            PromotionKey::default(),
+            Qualification::match_all(),
+            SimpleDiscount::AmountOverride(Money::from_minor(150, GBP)),
+            PromotionBudget::unlimited(),
+        );
+
+        let redemption =
+            promotion.best_redemption(&item_group, &[1], &[], promotion.allowance())?;
+
+        assert!(redemption.is_none(), "no saving, no redemption");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,378 @@
+//! Mix-and-Match Promotions Greedy
+
+use crate::{
+    discounts::rounding::RoundingPolicy,
+    items::groups::ItemGroup,
+    promotions::types::{MixAndMatchDiscount, MixAndMatchPromotion},
+    solvers::{
+        SolverError,
+        greedy::promotions::{
+            Allowance, FreeUnit, GreedyPromotion, GreedyRedemption, allocate_total,
+            free_units_by_price, promotion_rounding, redemption_from_units,
+        },
+    },
+};
+
+impl MixAndMatchPromotion<'_> {
+    /// Final unit prices of one bundle whose units are sorted by price, most expensive first.
+    pub(crate) fn bundle_prices(
+        &self,
+        bundle: &[FreeUnit],
+        rounding: RoundingPolicy,
+    ) -> Result<Vec<i64>, SolverError> {
+        let original_total: i64 = bundle.iter().map(|&(_, price)| price).sum();
+
+        let prices = match self.discount() {
+            MixAndMatchDiscount::AmountOffTotal(amount) => allocate_total(
+                bundle,
+                original_total
+                    .saturating_sub(amount.to_minor_units())
+                    .max(0),
+            ),
+            MixAndMatchDiscount::FixedTotal(amount) => {
+                allocate_total(bundle, amount.to_minor_units())
+            }
+            MixAndMatchDiscount::PercentAllItems(pct) => {
+                let mut prices: Vec<i64> = bundle.iter().map(|&(_, price)| price).collect();
+                let discounted = vec![true; prices.len()];
+
+                rounding
+                    .discount_units(pct, &mut prices, &discounted)
+                    .map_err(SolverError::Discount)?;
+
+                prices
+            }
+            MixAndMatchDiscount::AmountOffEachItem(amount) => bundle
+                .iter()
+                .map(|&(_, price)| price.saturating_sub(amount.to_minor_units()).max(0))
+                .collect(),
+            MixAndMatchDiscount::FixedPriceEachItem(amount) => bundle
+                .iter()
+                .map(|_unit| amount.to_minor_units().max(0))
+                .collect(),
+            MixAndMatchDiscount::PercentCheapest(pct) => {
+                let mut prices: Vec<i64> = bundle.iter().map(|&(_, price)| price).collect();
+
+                if let Some(cheapest) = prices.last_mut() {
+                    *cheapest = discounted_minor_percent(pct, *cheapest, rounding)?;
+                }
+
+                prices
+            }
+            MixAndMatchDiscount::FixedCheapest(amount) => {
+                let mut prices: Vec<i64> = bundle.iter().map(|&(_, price)| price).collect();
+
+                if let Some(cheapest) = prices.last_mut() {
+                    *cheapest = amount.to_minor_units().max(0);
+                }
+
+                prices
+            }
+        };
+
+        Ok(prices)
+    }
+
+    /// Whether each extra unit in a slot adds its own saving to the bundle.
+    fn rewards_extra_units(&self) -> bool {
+        matches!(
+            self.discount(),
+            MixAndMatchDiscount::PercentAllItems(_)
+                | MixAndMatchDiscount::AmountOffEachItem(_)
+                | MixAndMatchDiscount::FixedPriceEachItem(_)
+                | MixAndMatchDiscount::FixedTotal(_)
+        )
+    }
+
+    /// Fill every slot from the free units, most constrained slot first.
+    ///
+    /// Each slot takes its most expensive matching units; returns `None` if any
+    /// slot cannot reach its minimum.
+    fn fill_slots(
+        &self,
+        item_group: &ItemGroup<'_>,
+        units: &[FreeUnit],
+        extend: bool,
+    ) -> Result<Option<Vec<FreeUnit>>, SolverError> {
+        let context = item_group.context();
+        let rounding = promotion_rounding(self.rounding(), item_group);
+
+        let mut matching: Vec<Vec<usize>> = Vec::with_capacity(self.slots().len());
+
+        for slot in self.slots() {
+            let mut positions = Vec::new();
+
+            for (position, &(item_idx, _price)) in units.iter().enumerate() {
+                let item = item_group.get_item(item_idx)?;
+
+                if slot
+                    .qualification()
+                    .matches_in_context(item.tags(), context)
+                {
+                    positions.push(position);
+                }
+            }
+
+            matching.push(positions);
+        }
+
+        let mut order: Vec<usize> = (0..self.slots().len()).collect();
+
+        order.sort_by_key(|&slot_idx| matching.get(slot_idx).map_or(0, Vec::len));
+
+        let mut taken = vec![false; units.len()];
+
+        for &slot_idx in &order {
+            let (Some(slot), Some(positions)) =
+                (self.slots().get(slot_idx), matching.get(slot_idx))
+            else {
+                continue;
+            };
+
+            let mut filled = 0;
+
+            for &position in positions {
+                let Some((is_taken, &(_item_idx, price))) =
+                    taken.get_mut(position).zip(units.get(position))
+                else {
+                    continue;
+                };
+
+                if *is_taken {
+                    continue;
+                }
+
+                let wanted = if filled < slot.min() {
+                    true
+                } else {
+                    extend
+                        && slot.max().is_none_or(|max| filled < max)
+                        && self.extra_unit_saving(price, rounding)? > 0
+                };
+
+                if !wanted {
+                    break;
+                }
+
+                *is_taken = true;
+                filled += 1;
+            }
+
+            if filled < slot.min() {
+                return Ok(None);
+            }
+        }
+
+        Ok(Some(
+            units
+                .iter()
+                .zip(taken)
+                .filter_map(|(&unit, is_taken)| is_taken.then_some(unit))
+                .collect(),
+        ))
+    }
+
+    /// Saving added by one more unit at `price` in an additive discount mode.
+    fn extra_unit_saving(&self, price: i64, rounding: RoundingPolicy) -> Result<i64, SolverError> {
+        let saving = match self.discount() {
+            MixAndMatchDiscount::PercentAllItems(pct) => {
+                price - discounted_minor_percent(pct, price, rounding)?
+            }
+            MixAndMatchDiscount::AmountOffEachItem(amount) => {
+                price - price.saturating_sub(amount.to_minor_units()).max(0)
+            }
+            MixAndMatchDiscount::FixedPriceEachItem(amount) => {
+                price - amount.to_minor_units().max(0)
+            }
+            MixAndMatchDiscount::FixedTotal(_) => price,
+            MixAndMatchDiscount::AmountOffTotal(_)
+            | MixAndMatchDiscount::PercentCheapest(_)
+            | MixAndMatchDiscount::FixedCheapest(_) => 0,
+        };
+
+        Ok(saving)
+    }
+}
+
+impl GreedyPromotion for MixAndMatchPromotion<'_> {
+    fn allowance(&self) -> Allowance {
+        Allowance::from_budget(self.budget(), self.redemption_limit())
+    }
+
+    fn best_redemption(
+        &self,
+        item_group: &ItemGroup<'_>,
+        free: &[u32],
+        redeemed: &[GreedyRedemption],
+        allowance: Allowance,
+    ) -> Result<Option<GreedyRedemption>, SolverError> {
+        let fixed_arity = self.has_fixed_arity();
+
+        // Variable-arity promotions form at most one bundle.
+        if self.slots().is_empty() || (!fixed_arity && !redeemed.is_empty()) {
+            return Ok(None);
+        }
+
+        let context = item_group.context();
+
+        let units = free_units_by_price(item_group, free, |item| {
+            self.slots().iter().any(|slot| {
+                slot.qualification()
+                    .matches_in_context(item.tags(), context)
+            })
+        });
+
+        let extend = !fixed_arity && self.rewards_extra_units();
+
+        let Some(mut bundle) = self.fill_slots(item_group, &units, extend)? else {
+            return Ok(None);
+        };
+
+        bundle.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        let rounding = promotion_rounding(self.rounding(), item_group);
+        let prices = self.bundle_prices(&bundle, rounding)?;
+        let redemption = redemption_from_units(&bundle, &prices, 1);
+        let saving = redemption.saving(item_group)?;
+
+        Ok((saving > 0 && allowance.admits(1, saving)).then_some(redemption))
+    }
+}
+
+fn discounted_minor_percent(
+    pct: &decimal_percentage::Percentage,
+    original_minor: i64,
+    rounding: RoundingPolicy,
+) -> Result<i64, SolverError> {
+    let discount_minor = rounding
+        .discount_of(pct, original_minor)
+        .map_err(SolverError::Discount)?;
+
+    Ok(original_minor.saturating_sub(discount_minor))
+}
+
+#[cfg(test)]
+mod tests {
+    use rusty_money::{Money, iso::GBP};
+    use slotmap::SlotMap;
+    use testresult::TestResult;
+
+    use crate::{
+        items::Item,
+        products::ProductKey,
+        promotions::{PromotionKey, PromotionSlotKey, budget::PromotionBudget},
+        solvers::ilp::promotions::test_support::item_group_from_items,
+        tags::string::StringTagCollection,
+        utils::slot,
+    };
+
+    use super::*;
+
+    fn meal_deal_items() -> ItemGroup<'static> {
+        item_group_from_items([
+            Item::with_tags(
+                ProductKey::default(),
+                Money::from_minor(400, GBP),
+                StringTagCollection::from_strs(&["main"]),
+            ),
+            Item::with_tags(
+                ProductKey::default(),
+                Money::from_minor(150, GBP),
+                StringTagCollection::from_strs(&["drink"]),
+            ),
+            Item::with_tags(
+                ProductKey::default(),
+                Money::from_minor(120, GBP),
+                StringTagCollection::from_strs(&["drink"]),
+            ),
+        ])
+    }
+
+    #[test]
+    fn fills_each_slot_with_its_most_expensive_unit() -> TestResult {
+        let item_group = meal_deal_items();
+        let mut slot_keys = SlotMap::<PromotionSlotKey, ()>::with_key();
+
+        let promotion = MixAndMatchPromotion::new(
+            PromotionKey::default(),
+            vec![
+                slot(
+                    &mut slot_keys,
+                    StringTagCollection::from_strs(&["main"]),
+                    1,
+                    Some(1),
+                ),
+                slot(
+                    &mut slot_keys,
+                    StringTagCollection::from_strs(&["drink"]),
+                    1,
+                    Some(1),
+                ),
+            ],
+            MixAndMatchDiscount::FixedTotal(Money::from_minor(450, GBP)),
+            PromotionBudget::unlimited(),
+        );
+
+        let redemption = promotion
+            .best_redemption(&item_group, &[1, 1, 1], &[], promotion.allowance())?
+            .ok_or("expected a bundle")?;
+
+        assert_eq!(redemption.units_of(1), 1, "dearer drink");
+        assert_eq!(redemption.saving(&item_group)?, 100, "550 for 450");
+
+        let after = promotion.best_redemption(
+            &item_group,
+            &[0, 0, 1],
+            &[redemption],
+            promotion.allowance(),
+        )?;
+
+        assert!(after.is_none(), "no main left for a second bundle");
+
+        Ok(())
+    }
+
+    #[test]
+    fn variable_arity_extends_slots_while_each_unit_saves() -> TestResult {
+        let item_group = meal_deal_items();
+        let mut slot_keys = SlotMap::<PromotionSlotKey, ()>::with_key();
+
+        let promotion = MixAndMatchPromotion::new(
+            PromotionKey::default(),
+            vec![
+                slot(
+                    &mut slot_keys,
+                    StringTagCollection::from_strs(&["main"]),
+                    1,
+                    Some(1),
+                ),
+                slot(
+                    &mut slot_keys,
+                    StringTagCollection::from_strs(&["drink"]),
+                    1,
+                    None,
+                ),
+            ],
+            MixAndMatchDiscount::AmountOffEachItem(Money::from_minor(50, GBP)),
+            PromotionBudget::unlimited(),
+        );
+
+        let redemption = promotion
+            .best_redemption(&item_group, &[1, 1, 1], &[], promotion.allowance())?
+            .ok_or("expected a bundle")?;
+
+        assert_eq!(redemption.unit_count(), 3, "both drinks join the bundle");
+        assert_eq!(redemption.saving(&item_group)?, 150, "50 off each unit");
+
+        let second = promotion.best_redemption(
+            &item_group,
+            &[1, 1, 1],
+            &[redemption],
+            promotion.allowance(),
+        )?;
+
+        assert!(second.is_none(), "variable arity forms one bundle");
+
+        Ok(())
+    }
+}
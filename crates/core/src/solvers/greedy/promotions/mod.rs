@@ -0,0 +1,402 @@
+//! Greedy Promotions
+
+use std::fmt::Debug;
+
+use smallvec::SmallVec;
+
+use crate::{
+    discounts::rounding::{DEFAULT_ROUNDING, RoundingPolicies, RoundingPolicy},
+    items::{Item, groups::ItemGroup},
+    promotions::budget::PromotionBudget,
+    solvers::SolverError,
+};
+
+mod direct_discount;
+mod mix_and_match;
+mod positional_discount;
+mod tiered_threshold;
+
+/// Units of one item line claimed by a greedy redemption at a single final unit price.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClaimedUnits {
+    /// Index of the item line in the item group
+    pub item_idx: usize,
+
+    /// Number of units of the line claimed
+    pub quantity: u32,
+
+    /// Final unit price in minor units
+    pub final_minor: i64,
+}
+
+/// A redemption proposed by a promotion's greedy heuristic.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GreedyRedemption {
+    /// Units claimed by the redemption, with the price each is sold at
+    pub units: SmallVec<[ClaimedUnits; 4]>,
+
+    /// Number of redemptions this counts as against budgets
+    pub redemptions: u32,
+}
+
+impl GreedyRedemption {
+    /// Create an empty redemption counting as `redemptions` against budgets.
+    #[must_use]
+    pub fn new(redemptions: u32) -> Self {
+        Self {
+            units: SmallVec::new(),
+            redemptions,
+        }
+    }
+
+    /// Claim `quantity` units of `item_idx` at `final_minor` each.
+    ///
+    /// Units of the same line at the same price share one entry.
+    pub fn claim(&mut self, item_idx: usize, quantity: u32, final_minor: i64) {
+        if quantity == 0 {
+            return;
+        }
+
+        let existing = self
+            .units
+            .iter_mut()
+            .find(|units| units.item_idx == item_idx && units.final_minor == final_minor);
+
+        match existing {
+            Some(units) => units.quantity += quantity,
+            None => self.units.push(ClaimedUnits {
+                item_idx,
+                quantity,
+                final_minor,
+            }),
+        }
+    }
+
+    /// Number of units of `item_idx` claimed by the redemption.
+    #[must_use]
+    pub fn units_of(&self, item_idx: usize) -> u32 {
+        self.units
+            .iter()
+            .filter(|units| units.item_idx == item_idx)
+            .map(|units| units.quantity)
+            .sum()
+    }
+
+    /// Total units claimed by the redemption.
+    #[must_use]
+    pub fn unit_count(&self) -> u32 {
+        self.units.iter().map(|units| units.quantity).sum()
+    }
+
+    /// Amount saved against the full price of the claimed units, in minor units.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SolverError::ItemGroup`] if a claimed line is missing from the item group.
+    pub fn saving(&self, item_group: &ItemGroup<'_>) -> Result<i64, SolverError> {
+        let mut saving = 0_i64;
+
+        for units in &self.units {
+            let full_minor = item_group
+                .get_item(units.item_idx)?
+                .price()
+                .to_minor_units();
+
+            saving += (full_minor - units.final_minor) * i64::from(units.quantity);
+        }
+
+        Ok(saving)
+    }
+}
+
+/// Budget left to a promotion while a greedy solve is in progress.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Allowance {
+    /// Redemptions left, if limited
+    pub redemptions: Option<u32>,
+
+    /// Discount value left in minor units, if limited
+    pub discount_minor: Option<i64>,
+}
+
+impl Allowance {
+    /// Allowance with no limits.
+    #[must_use]
+    pub const fn unlimited() -> Self {
+        Self {
+            redemptions: None,
+            discount_minor: None,
+        }
+    }
+
+    /// Allowance matching a promotion budget, with its redemption limit overridden.
+    ///
+    /// Promotions pass their coupon-capped redemption limit rather than the budget's own.
+    #[must_use]
+    pub fn from_budget(budget: &PromotionBudget<'_>, redemption_limit: Option<u32>) -> Self {
+        Self {
+            redemptions: redemption_limit,
+            discount_minor: budget.monetary_limit.map(|limit| limit.to_minor_units()),
+        }
+    }
+
+    /// Whether a redemption counting `redemptions` and saving `saving` fits.
+    #[must_use]
+    pub fn admits(&self, redemptions: u32, saving: i64) -> bool {
+        self.redemptions.is_none_or(|left| redemptions <= left)
+            && self.discount_minor.is_none_or(|left| saving <= left)
+    }
+
+    /// Whether no further redemption can fit.
+    #[must_use]
+    pub fn is_exhausted(&self) -> bool {
+        self.redemptions == Some(0)
+    }
+
+    /// The tighter of two allowances, limit by limit.
+    #[must_use]
+    pub fn min(self, other: Self) -> Self {
+        Self {
+            redemptions: min_limit(self.redemptions, other.redemptions),
+            discount_minor: min_limit(self.discount_minor, other.discount_minor),
+        }
+    }
+
+    /// What is left after `redemptions` redemptions saving `saving` in total.
+    ///
+    /// Overdrawn limits go negative in discount value, so [`Allowance::admits`]
+    /// rejects anything saving money until usage drops again.
+    #[must_use]
+    pub fn after(self, redemptions: u32, saving: i64) -> Self {
+        Self {
+            redemptions: self
+                .redemptions
+                .map(|left| left.saturating_sub(redemptions)),
+            discount_minor: self.discount_minor.map(|left| left - saving),
+        }
+    }
+}
+
+fn min_limit<L: Ord>(a: Option<L>, b: Option<L>) -> Option<L> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, None) => a,
+        (None, b) => b,
+    }
+}
+
+/// Prices a promotion without an ILP, one redemption at a time.
+///
+/// [`GreedySolver`](crate::solvers::greedy::GreedySolver) repeatedly asks every
+/// promotion for its most valuable redemption from the units still free and
+/// applies the best one across all promotions. Local improvement later gives
+/// redemptions up and asks again, so proposals must depend only on the arguments.
+pub trait GreedyPromotion: Debug + Send + Sync {
+    /// Return the promotion's own budget, before any redemptions.
+    fn allowance(&self) -> Allowance;
+
+    /// Propose the redemption that saves the most from the units still free.
+    ///
+    /// `free` holds the unclaimed units of each item line and `redeemed` this
+    /// promotion's redemptions so far in the solve. Return `None` when no
+    /// redemption within `allowance` saves anything.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SolverError`] if a discounted price cannot be computed.
+    fn best_redemption(
+        &self,
+        item_group: &ItemGroup<'_>,
+        free: &[u32],
+        redeemed: &[GreedyRedemption],
+        allowance: Allowance,
+    ) -> Result<Option<GreedyRedemption>, SolverError>;
+
+    /// Reprice the promotion's redemptions after they have been rearranged.
+    ///
+    /// Promotions whose prices depend on how redemptions relate to one another,
+    /// such as positional bundles formed in price order, restore that here once
+    /// local improvement has given some up. The default keeps them as proposed.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SolverError`] if a discounted price cannot be computed.
+    fn reprice(
+        &self,
+        _item_group: &ItemGroup<'_>,
+        _redeemed: &mut Vec<GreedyRedemption>,
+    ) -> Result<(), SolverError> {
+        Ok(())
+    }
+}
+
+/// A free unit of an item line: `(item_idx, price_minor)`.
+pub type FreeUnit = (usize, i64);
+
+/// Free units of the lines matching `matches`, most expensive first.
+///
+/// Ties keep item group order, matching the order ILP formulations walk items in.
+pub(crate) fn free_units_by_price<'a>(
+    item_group: &ItemGroup<'a>,
+    free: &[u32],
+    matches: impl Fn(&Item<'a>) -> bool,
+) -> Vec<FreeUnit> {
+    let mut units = Vec::new();
+
+    for (item_idx, item) in item_group.iter().enumerate() {
+        let quantity = free.get(item_idx).copied().unwrap_or(0);
+
+        if quantity == 0 || !matches(item) {
+            continue;
+        }
+
+        let price = item.price().to_minor_units();
+
+        units.extend((0..quantity).map(|_unit| (item_idx, price)));
+    }
+
+    units.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    units
+}
+
+/// Rounding for a promotion's percentages in `item_group`: its own policy, else the default.
+pub(crate) fn promotion_rounding(
+    own: Option<&RoundingPolicies>,
+    item_group: &ItemGroup<'_>,
+) -> RoundingPolicy {
+    RoundingPolicies::resolve(own, &DEFAULT_ROUNDING, item_group.currency())
+}
+
+/// Spread `new_total` across `units` in proportion to their prices.
+///
+/// Any rounding remainder lands on the last unit, as in the ILP solver.
+pub(crate) fn allocate_total(units: &[FreeUnit], new_total: i64) -> Vec<i64> {
+    let original_total: i64 = units.iter().map(|&(_, price)| price).sum();
+    let mut remaining = new_total;
+    let mut prices = Vec::with_capacity(units.len());
+
+    for (position, &(_, price)) in units.iter().enumerate() {
+        let final_minor = if position + 1 == units.len() {
+            remaining
+        } else if original_total == 0 {
+            0
+        } else {
+            proportional_alloc(new_total, price, original_total)
+        };
+
+        remaining -= final_minor;
+        prices.push(final_minor);
+    }
+
+    prices
+}
+
+fn proportional_alloc(total: i64, part: i64, denom: i64) -> i64 {
+    let total = i128::from(total);
+    let part = i128::from(part);
+    let denom = i128::from(denom);
+
+    i64::try_from((total * part + denom / 2) / denom).unwrap_or(0)
+}
+
+/// Build a redemption from units and their final prices.
+pub(crate) fn redemption_from_units(
+    units: &[FreeUnit],
+    final_prices: &[i64],
+    redemptions: u32,
+) -> GreedyRedemption {
+    let mut redemption = GreedyRedemption::new(redemptions);
+
+    for (&(item_idx, _price), &final_minor) in units.iter().zip(final_prices) {
+        redemption.claim(item_idx, 1, final_minor);
+    }
+
+    redemption
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+
+    use rusty_money::{Money, iso::GBP};
+    use testresult::TestResult;
+
+    use crate::{items::Item, products::ProductKey, solvers::ilp::promotions::test_support};
+
+    use super::*;
+
+    #[test]
+    fn claim_merges_units_at_the_same_price() {
+        let mut redemption = GreedyRedemption::new(1);
+
+        redemption.claim(0, 1, 50);
+        redemption.claim(0, 2, 50);
+        redemption.claim(0, 1, 75);
+        redemption.claim(1, 0, 10);
+
+        assert_eq!(redemption.units.len(), 2, "one entry per line and price");
+        assert_eq!(redemption.units_of(0), 4, "all units of the line");
+        assert_eq!(redemption.unit_count(), 4, "zero-unit claims are ignored");
+    }
+
+    #[test]
+    fn saving_counts_every_claimed_unit() -> TestResult {
+        let item_group = test_support::item_group_from_items([Item::new(
+            ProductKey::default(),
+            Money::from_minor(100, GBP),
+        )
+        .with_quantity(NonZeroU32::new(3).expect("non-zero quantity"))]);
+
+        let mut redemption = GreedyRedemption::new(3);
+
+        redemption.claim(0, 3, 80);
+
+        assert_eq!(redemption.saving(&item_group)?, 60);
+
+        Ok(())
+    }
+
+    #[test]
+    fn allowance_tracks_the_tighter_limit() {
+        let own = Allowance {
+            redemptions: Some(3),
+            discount_minor: None,
+        };
+        let pool = Allowance {
+            redemptions: Some(5),
+            discount_minor: Some(100),
+        };
+
+        let left = own.min(pool).after(2, 60);
+
+        assert_eq!(left.redemptions, Some(1), "own redemption limit");
+        assert_eq!(left.discount_minor, Some(40), "pool discount limit");
+        assert!(left.admits(1, 40), "fits exactly");
+        assert!(!left.admits(1, 41), "over the discount limit");
+        assert!(!left.admits(2, 0), "over the redemption limit");
+        assert!(left.after(1, 0).is_exhausted(), "no redemptions left");
+    }
+
+    #[test]
+    fn free_units_are_sorted_by_price_then_index() {
+        let item_group = test_support::item_group_from_items([
+            Item::new(ProductKey::default(), Money::from_minor(100, GBP)),
+            Item::new(ProductKey::default(), Money::from_minor(300, GBP))
+                .with_quantity(NonZeroU32::new(2).expect("non-zero quantity")),
+            Item::new(ProductKey::default(), Money::from_minor(100, GBP)),
+        ]);
+
+        let units = free_units_by_price(&item_group, &[1, 2, 0], |_item| true);
+
+        assert_eq!(units, vec![(1, 300), (1, 300), (0, 100)]);
+    }
+
+    #[test]
+    fn allocate_total_puts_the_remainder_on_the_last_unit() {
+        let prices = allocate_total(&[(0, 300), (1, 200), (2, 200)], 500);
+
+        assert_eq!(prices, vec![214, 143, 143]);
+        assert_eq!(prices.iter().sum::<i64>(), 500, "allocation adds up");
+    }
+}
@@ -0,0 +1,228 @@
+//! Positional Discount Promotions Greedy
+
+use crate::{
+    discounts::{SimpleDiscount, rounding::RoundingPolicy},
+    items::groups::ItemGroup,
+    promotions::types::PositionalDiscountPromotion,
+    solvers::{
+        SolverError,
+        greedy::promotions::{
+            Allowance, FreeUnit, GreedyPromotion, GreedyRedemption, free_units_by_price,
+            promotion_rounding, redemption_from_units,
+        },
+    },
+};
+
+impl PositionalDiscountPromotion<'_> {
+    /// Final unit prices of one bundle, in bundle order.
+    pub(crate) fn bundle_prices(
+        &self,
+        bundle: &[FreeUnit],
+        rounding: RoundingPolicy,
+    ) -> Result<Vec<i64>, SolverError> {
+        let mut prices: Vec<i64> = bundle.iter().map(|&(_item_idx, price)| price).collect();
+
+        let discounted: Vec<bool> = (0..bundle.len())
+            .map(|position| {
+                u16::try_from(position).is_ok_and(|position| self.positions().contains(&position))
+            })
+            .collect();
+
+        if let SimpleDiscount::PercentageOff(pct) = self.discount() {
+            rounding
+                .discount_units(pct, &mut prices, &discounted)
+                .map_err(SolverError::Discount)?;
+
+            return Ok(prices);
+        }
+
+        for (price, _discounted) in prices.iter_mut().zip(discounted).filter(|(_, d)| *d) {
+            *price = self.discounted_minor(*price);
+        }
+
+        Ok(prices)
+    }
+
+    fn discounted_minor(&self, price: i64) -> i64 {
+        let discounted = match self.discount() {
+            SimpleDiscount::PercentageOff(_) => price,
+            SimpleDiscount::AmountOverride(amount) => amount.to_minor_units(),
+            SimpleDiscount::AmountOff(amount) => price.saturating_sub(amount.to_minor_units()),
+        };
+
+        discounted.max(0)
+    }
+}
+
+impl GreedyPromotion for PositionalDiscountPromotion<'_> {
+    fn allowance(&self) -> Allowance {
+        Allowance::from_budget(self.budget(), self.redemption_limit())
+    }
+
+    fn best_redemption(
+        &self,
+        item_group: &ItemGroup<'_>,
+        free: &[u32],
+        _redeemed: &[GreedyRedemption],
+        allowance: Allowance,
+    ) -> Result<Option<GreedyRedemption>, SolverError> {
+        let size = usize::from(self.size());
+
+        if size == 0 {
+            return Ok(None);
+        }
+
+        let units = free_units_by_price(item_group, free, |item| {
+            self.qualification()
+                .matches_in_context(item.tags(), item_group.context())
+        });
+
+        let rounding = promotion_rounding(self.rounding(), item_group);
+
+        // The most expensive units give the most at every position, so slide
+        // down the price order only when the allowance cannot cover the top.
+        for bundle in units.windows(size) {
+            let prices = self.bundle_prices(bundle, rounding)?;
+            let redemption = redemption_from_units(bundle, &prices, 1);
+            let saving = redemption.saving(item_group)?;
+
+            if saving <= 0 {
+                return Ok(None);
+            }
+
+            if allowance.admits(1, saving) {
+                return Ok(Some(redemption));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Re-form the bundles in price order, as the ILP walks eligible units.
+    fn reprice(
+        &self,
+        item_group: &ItemGroup<'_>,
+        redeemed: &mut Vec<GreedyRedemption>,
+    ) -> Result<(), SolverError> {
+        let size = usize::from(self.size());
+
+        if size == 0 {
+            return Ok(());
+        }
+
+        let mut claimed = vec![0_u32; item_group.len()];
+
+        for redemption in redeemed.iter() {
+            for units in &redemption.units {
+                if let Some(count) = claimed.get_mut(units.item_idx) {
+                    *count += units.quantity;
+                }
+            }
+        }
+
+        let units = free_units_by_price(item_group, &claimed, |_item| true);
+        let rounding = promotion_rounding(self.rounding(), item_group);
+        let mut bundles = Vec::with_capacity(redeemed.len());
+
+        for bundle in units.chunks_exact(size) {
+            let prices = self.bundle_prices(bundle, rounding)?;
+
+            bundles.push(redemption_from_units(bundle, &prices, 1));
+        }
+
+        *redeemed = bundles;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+
+    use decimal_percentage::Percentage;
+    use rusty_money::{Money, iso::GBP};
+    use smallvec::SmallVec;
+    use testresult::TestResult;
+
+    use crate::{
+        items::Item,
+        products::ProductKey,
+        promotions::{PromotionKey, budget::PromotionBudget, qualification::Qualification},
+        solvers::ilp::promotions::test_support::item_group_from_items,
+    };
+
+    use super::*;
+
+    fn three_for_two() -> PositionalDiscountPromotion<'static> {
+        PositionalDiscountPromotion::new(
+            PromotionKey::default(),
+            Qualification::match_all(),
+            3,
+            SmallVec::from_slice(&[2]),
+            SimpleDiscount::PercentageOff(Percentage::from(1.0)),
+            PromotionBudget::unlimited(),
+        )
+    }
+
+    #[test]
+    fn bundles_the_most_expensive_units() -> TestResult {
+        let item_group = item_group_from_items([
+            Item::new(ProductKey::default(), Money::from_minor(100, GBP)),
+            Item::new(ProductKey::default(), Money::from_minor(300, GBP)),
+            Item::new(ProductKey::default(), Money::from_minor(200, GBP))
+                .with_quantity(NonZeroU32::new(2).expect("non-zero quantity")),
+        ]);
+
+        let promotion = three_for_two();
+
+        let redemption = promotion
+            .best_redemption(&item_group, &[1, 1, 2], &[], promotion.allowance())?
+            .ok_or("expected a bundle")?;
+
+        assert_eq!(redemption.units_of(1), 1, "most expensive unit");
+        assert_eq!(redemption.units_of(2), 2, "both mid-priced units");
+        assert_eq!(
+            redemption.saving(&item_group)?,
+            200,
+            "cheapest of the three free"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn reprice_reforms_bundles_in_price_order() -> TestResult {
+        let item_group = item_group_from_items([
+            Item::new(ProductKey::default(), Money::from_minor(600, GBP)),
+            Item::new(ProductKey::default(), Money::from_minor(500, GBP)),
+            Item::new(ProductKey::default(), Money::from_minor(400, GBP)),
+            Item::new(ProductKey::default(), Money::from_minor(300, GBP)),
+            Item::new(ProductKey::default(), Money::from_minor(200, GBP)),
+            Item::new(ProductKey::default(), Money::from_minor(100, GBP)),
+        ]);
+
+        let promotion = three_for_two();
+
+        // Interleaved bundles: {600, 400, 200} and {500, 300, 100}
+        let mut redeemed = vec![
+            redemption_from_units(&[(0, 600), (2, 400), (4, 200)], &[600, 400, 0], 1),
+            redemption_from_units(&[(1, 500), (3, 300), (5, 100)], &[500, 300, 0], 1),
+        ];
+
+        promotion.reprice(&item_group, &mut redeemed)?;
+
+        let savings = redeemed
+            .iter()
+            .map(|redemption| redemption.saving(&item_group))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        assert_eq!(
+            savings,
+            vec![400, 100],
+            "{{600, 500, 400}} and {{300, 200, 100}}"
+        );
+
+        Ok(())
+    }
+}
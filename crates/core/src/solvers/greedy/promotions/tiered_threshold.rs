@@ -0,0 +1,436 @@
+//! Tiered Threshold Promotions Greedy
+
+use rusty_money::Money;
+
+use crate::{
+    discounts::rounding::RoundingPolicy,
+    items::groups::ItemGroup,
+    promotions::types::{ThresholdDiscount, ThresholdTier, TieredThresholdPromotion},
+    solvers::{
+        SolverError,
+        greedy::promotions::{
+            Allowance, FreeUnit, GreedyPromotion, GreedyRedemption, allocate_total,
+            free_units_by_price, promotion_rounding,
+        },
+    },
+};
+
+/// A free unit considered by a tier, with the roles it can play.
+#[derive(Debug, Clone, Copy)]
+struct TierUnit {
+    unit: FreeUnit,
+    contributes: bool,
+    discountable: bool,
+}
+
+/// Units claimed for one tier, with running totals checked against its thresholds.
+#[derive(Debug, Default)]
+struct TierClaim {
+    claimed: Vec<bool>,
+    contribution_spend: i64,
+    contribution_count: u32,
+    discountable_spend: i64,
+    discountable_count: u32,
+}
+
+impl TierClaim {
+    fn new(units: usize) -> Self {
+        Self {
+            claimed: vec![false; units],
+            ..Self::default()
+        }
+    }
+
+    fn is_claimed(&self, position: usize) -> bool {
+        self.claimed.get(position).copied().unwrap_or(true)
+    }
+
+    /// Whether claiming `unit` keeps the tier within its upper caps.
+    fn fits(&self, tier: &ThresholdTier<'_>, unit: &TierUnit) -> bool {
+        let Some(upper) = tier.upper_threshold() else {
+            return true;
+        };
+
+        let price = unit.unit.1;
+        let spend_cap = upper.monetary_threshold().map(Money::to_minor_units);
+        let count_cap = upper.item_count_threshold();
+
+        let within = |spend: i64, count: u32| {
+            spend_cap.is_none_or(|cap| spend + price <= cap)
+                && count_cap.is_none_or(|cap| count < cap)
+        };
+
+        (!unit.contributes || within(self.contribution_spend, self.contribution_count))
+            && (!unit.discountable || within(self.discountable_spend, self.discountable_count))
+    }
+
+    fn claim(&mut self, position: usize, unit: &TierUnit) {
+        if let Some(claimed) = self.claimed.get_mut(position) {
+            *claimed = true;
+        }
+
+        if unit.contributes {
+            self.contribution_spend += unit.unit.1;
+            self.contribution_count += 1;
+        }
+
+        if unit.discountable {
+            self.discountable_spend += unit.unit.1;
+            self.discountable_count += 1;
+        }
+    }
+
+    /// Whether the contribution reaches the tier's lower thresholds.
+    fn reaches(&self, tier: &ThresholdTier<'_>) -> bool {
+        let lower = tier.lower_threshold();
+
+        lower
+            .monetary_threshold()
+            .is_none_or(|threshold| self.contribution_spend >= threshold.to_minor_units())
+            && lower
+                .item_count_threshold()
+                .is_none_or(|threshold| self.contribution_count >= threshold)
+    }
+}
+
+/// Claim the discountable units worth having for the tier's discount.
+fn claim_discount_units(
+    tier: &ThresholdTier<'_>,
+    rounding: RoundingPolicy,
+    item_group: &ItemGroup<'_>,
+    units: &[TierUnit],
+    claim: &mut TierClaim,
+) -> Result<(), SolverError> {
+    for (position, unit) in units.iter().enumerate() {
+        if !unit.discountable || !claim.fits(tier, unit) {
+            continue;
+        }
+
+        let wanted = match tier.discount() {
+            ThresholdDiscount::PercentEachItem(_)
+            | ThresholdDiscount::AmountOffEachItem(_)
+            | ThresholdDiscount::FixedPriceEachItem(_) => {
+                let item = item_group.get_item(unit.unit.0)?;
+                let discounted =
+                    TieredThresholdPromotion::calculate_rounded_price(tier, item, rounding)?;
+
+                discounted.to_minor_units() < unit.unit.1
+            }
+            ThresholdDiscount::FixedTotal(_) => true,
+            ThresholdDiscount::AmountOffTotal(amount) => {
+                claim.discountable_spend < amount.to_minor_units()
+            }
+            ThresholdDiscount::PercentCheapest(_) | ThresholdDiscount::FixedCheapest(_) => {
+                claim.discountable_count == 0
+            }
+        };
+
+        if wanted {
+            claim.claim(position, unit);
+        }
+    }
+
+    Ok(())
+}
+
+/// Claim contributing units until the lower thresholds are reached.
+///
+/// Units that would also take the discount are used last, since they can
+/// change how it is priced.
+fn claim_contribution_units(tier: &ThresholdTier<'_>, units: &[TierUnit], claim: &mut TierClaim) {
+    for discountable in [false, true] {
+        for (position, unit) in units.iter().enumerate() {
+            if claim.reaches(tier) {
+                return;
+            }
+
+            if unit.contributes
+                && unit.discountable == discountable
+                && !claim.is_claimed(position)
+                && claim.fits(tier, unit)
+            {
+                claim.claim(position, unit);
+            }
+        }
+    }
+}
+
+/// Price the claimed units the way the ILP prices the active tier.
+fn price_claim(
+    tier: &ThresholdTier<'_>,
+    rounding: RoundingPolicy,
+    item_group: &ItemGroup<'_>,
+    units: &[TierUnit],
+    claim: &TierClaim,
+) -> Result<GreedyRedemption, SolverError> {
+    let claimed = units
+        .iter()
+        .zip(&claim.claimed)
+        .filter_map(|(unit, &claimed)| claimed.then_some(*unit));
+
+    let (discounted, full_price): (Vec<TierUnit>, Vec<TierUnit>) =
+        claimed.partition(|unit| unit.discountable);
+
+    let discounted_units: Vec<FreeUnit> = discounted.iter().map(|unit| unit.unit).collect();
+
+    let prices: Vec<i64> = match tier.discount() {
+        ThresholdDiscount::PercentEachItem(_)
+        | ThresholdDiscount::AmountOffEachItem(_)
+        | ThresholdDiscount::FixedPriceEachItem(_) => discounted_units
+            .iter()
+            .map(|&(item_idx, _price)| {
+                let item = item_group.get_item(item_idx)?;
+
+                Ok(
+                    TieredThresholdPromotion::calculate_rounded_price(tier, item, rounding)?
+                        .to_minor_units(),
+                )
+            })
+            .collect::<Result<_, SolverError>>()?,
+        ThresholdDiscount::AmountOffTotal(amount) => allocate_total(
+            &discounted_units,
+            claim
+                .discountable_spend
+                .saturating_sub(amount.to_minor_units())
+                .max(0),
+        ),
+        ThresholdDiscount::FixedTotal(amount) => {
+            allocate_total(&discounted_units, amount.to_minor_units().max(0))
+        }
+        ThresholdDiscount::PercentCheapest(pct) => {
+            let mut prices: Vec<i64> = discounted_units.iter().map(|unit| unit.1).collect();
+
+            if let Some(cheapest) = prices.last_mut() {
+                let saving = rounding
+                    .discount_of(pct, *cheapest)
+                    .map_err(SolverError::Discount)?;
+
+                *cheapest = cheapest.saturating_sub(saving).max(0);
+            }
+
+            prices
+        }
+        ThresholdDiscount::FixedCheapest(amount) => {
+            let mut prices: Vec<i64> = discounted_units.iter().map(|unit| unit.1).collect();
+
+            if let Some(cheapest) = prices.last_mut() {
+                *cheapest = amount.to_minor_units().max(0);
+            }
+
+            prices
+        }
+    };
+
+    let mut redemption = GreedyRedemption::new(1);
+
+    for (&(item_idx, _price), final_minor) in discounted_units.iter().zip(prices) {
+        redemption.claim(item_idx, 1, final_minor);
+    }
+
+    for unit in full_price {
+        redemption.claim(unit.unit.0, 1, unit.unit.1);
+    }
+
+    Ok(redemption)
+}
+
+/// The tier's most valuable claim from the free units, if it can be reached.
+fn tier_redemption(
+    tier: &ThresholdTier<'_>,
+    rounding: RoundingPolicy,
+    item_group: &ItemGroup<'_>,
+    free: &[u32],
+) -> Result<Option<GreedyRedemption>, SolverError> {
+    let context = item_group.context();
+
+    let units: Vec<TierUnit> = free_units_by_price(item_group, free, |item| {
+        tier.contribution_qualification()
+            .matches_in_context(item.tags(), context)
+            || tier
+                .discount_qualification()
+                .matches_in_context(item.tags(), context)
+    })
+    .into_iter()
+    .map(|unit| {
+        let item = item_group.get_item(unit.0)?;
+
+        Ok(TierUnit {
+            unit,
+            contributes: tier
+                .contribution_qualification()
+                .matches_in_context(item.tags(), context),
+            discountable: tier
+                .discount_qualification()
+                .matches_in_context(item.tags(), context),
+        })
+    })
+    .collect::<Result<_, SolverError>>()?;
+
+    let mut claim = TierClaim::new(units.len());
+
+    claim_discount_units(tier, rounding, item_group, &units, &mut claim)?;
+    claim_contribution_units(tier, &units, &mut claim);
+
+    let amount_off_covered = match tier.discount() {
+        ThresholdDiscount::AmountOffTotal(amount) => {
+            claim.discountable_spend >= amount.to_minor_units()
+        }
+        _ => true,
+    };
+
+    if !claim.reaches(tier) || claim.discountable_count == 0 || !amount_off_covered {
+        return Ok(None);
+    }
+
+    price_claim(tier, rounding, item_group, &units, &claim).map(Some)
+}
+
+impl GreedyPromotion for TieredThresholdPromotion<'_> {
+    fn allowance(&self) -> Allowance {
+        Allowance::from_budget(self.budget(), self.redemption_limit())
+    }
+
+    fn best_redemption(
+        &self,
+        item_group: &ItemGroup<'_>,
+        free: &[u32],
+        redeemed: &[GreedyRedemption],
+        allowance: Allowance,
+    ) -> Result<Option<GreedyRedemption>, SolverError> {
+        // At most one tier is active per solve.
+        if !redeemed.is_empty() {
+            return Ok(None);
+        }
+
+        let rounding = promotion_rounding(self.rounding(), item_group);
+
+        let mut best: Option<(i64, GreedyRedemption)> = None;
+
+        for tier in self.tiers() {
+            let Some(redemption) = tier_redemption(tier, rounding, item_group, free)? else {
+                continue;
+            };
+
+            let saving = redemption.saving(item_group)?;
+
+            if saving > 0
+                && allowance.admits(1, saving)
+                && best
+                    .as_ref()
+                    .is_none_or(|(best_saving, _)| saving > *best_saving)
+            {
+                best = Some((saving, redemption));
+            }
+        }
+
+        Ok(best.map(|(_saving, redemption)| redemption))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use decimal_percentage::Percentage;
+    use rusty_money::{Money, iso::GBP};
+    use testresult::TestResult;
+
+    use crate::{
+        items::Item,
+        products::ProductKey,
+        promotions::{
+            PromotionKey, budget::PromotionBudget, qualification::Qualification,
+            types::TierThreshold,
+        },
+        solvers::ilp::promotions::test_support::item_group_from_items,
+        tags::string::StringTagCollection,
+    };
+
+    use super::*;
+
+    fn spend_and_save_items() -> ItemGroup<'static> {
+        item_group_from_items([
+            Item::with_tags(
+                ProductKey::default(),
+                Money::from_minor(600, GBP),
+                StringTagCollection::from_strs(&["wine"]),
+            ),
+            Item::with_tags(
+                ProductKey::default(),
+                Money::from_minor(300, GBP),
+                StringTagCollection::from_strs(&["cheese"]),
+            ),
+            Item::with_tags(
+                ProductKey::default(),
+                Money::from_minor(200, GBP),
+                StringTagCollection::from_strs(&["cheese"]),
+            ),
+        ])
+    }
+
+    #[test]
+    fn contribution_units_reach_the_threshold_at_full_price() -> TestResult {
+        let item_group = spend_and_save_items();
+
+        let promotion = TieredThresholdPromotion::new(
+            PromotionKey::default(),
+            vec![ThresholdTier::new(
+                TierThreshold::with_monetary_threshold(Money::from_minor(1000, GBP)),
+                None,
+                Qualification::match_all(),
+                Qualification::match_any(StringTagCollection::from_strs(&["cheese"])),
+                ThresholdDiscount::PercentEachItem(Percentage::from(0.5)),
+            )],
+            PromotionBudget::unlimited(),
+        );
+
+        let redemption = promotion
+            .best_redemption(&item_group, &[1, 1, 1], &[], promotion.allowance())?
+            .ok_or("expected the tier to be reached")?;
+
+        assert_eq!(redemption.unit_count(), 3, "wine contributes to the spend");
+        assert_eq!(
+            redemption.saving(&item_group)?,
+            250,
+            "half off both cheeses"
+        );
+
+        let unreachable =
+            promotion.best_redemption(&item_group, &[0, 1, 1], &[], promotion.allowance())?;
+
+        assert!(
+            unreachable.is_none(),
+            "threshold not reached without the wine"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn cheapest_mode_discounts_a_single_unit() -> TestResult {
+        let item_group = spend_and_save_items();
+
+        let promotion = TieredThresholdPromotion::new(
+            PromotionKey::default(),
+            vec![ThresholdTier::new(
+                TierThreshold::with_item_count_threshold(2),
+                None,
+                Qualification::match_all(),
+                Qualification::match_any(StringTagCollection::from_strs(&["cheese"])),
+                ThresholdDiscount::PercentCheapest(Percentage::from(1.0)),
+            )],
+            PromotionBudget::unlimited(),
+        );
+
+        let redemption = promotion
+            .best_redemption(&item_group, &[1, 1, 1], &[], promotion.allowance())?
+            .ok_or("expected the tier to be reached")?;
+
+        assert_eq!(redemption.unit_count(), 2, "dearer cheese plus the wine");
+        assert_eq!(
+            redemption.saving(&item_group)?,
+            300,
+            "dearer cheese is free"
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,340 @@
+//! MILP Solver Backends
+//!
+//! The ILP formulation is backend-agnostic; this module selects which `good_lp`
+//! engine solves it. Each backend is behind its own cargo feature, and the engine
+//! is chosen per solve at runtime from those that were compiled in.
+
+use std::{fmt, time::Duration};
+
+use good_lp::{
+    Constraint, Solution, SolutionStatus, SolverModel, Variable, variable::UnsolvedProblem,
+};
+
+use rustc_hash::FxHashMap;
+
+use crate::solvers::SolverError;
+
+#[cfg(feature = "solver-highs")]
+use good_lp::solvers::highs::{HighsSolution, highs};
+#[cfg(feature = "solver-microlp")]
+use good_lp::solvers::microlp::{MicroLpSolution, microlp};
+
+/// MILP engine used to solve promotion formulations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SolverBackend {
+    /// Pure Rust solver bundled with `good_lp` (`solver-microlp` feature).
+    #[cfg(feature = "solver-microlp")]
+    Microlp,
+
+    /// HiGHS, built from vendored source (`solver-highs` feature).
+    ///
+    /// Considerably faster than microlp on large baskets.
+    #[cfg(feature = "solver-highs")]
+    Highs,
+}
+
+impl SolverBackend {
+    /// Every backend compiled into this build, default first.
+    pub const AVAILABLE: &'static [SolverBackend] = &[
+        #[cfg(feature = "solver-microlp")]
+        SolverBackend::Microlp,
+        #[cfg(feature = "solver-highs")]
+        SolverBackend::Highs,
+    ];
+
+    /// Human readable name of the backend.
+    pub const fn name(self) -> &'static str {
+        match self {
+            #[cfg(feature = "solver-microlp")]
+            Self::Microlp => "microlp",
+            #[cfg(feature = "solver-highs")]
+            Self::Highs => "highs",
+        }
+    }
+
+    /// Find a compiled-in backend by name (case-insensitive).
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::AVAILABLE
+            .iter()
+            .copied()
+            .find(|backend| backend.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Solve `problem` subject to `constraints` with this backend.
+    ///
+    /// With a `time_limit`, HiGHS stops at the limit and returns its best feasible
+    /// solution so far, reported with [`SolutionStatus::TimeLimit`]. microlp cannot
+    /// be interrupted, so it solves on a worker thread that is abandoned once the
+    /// limit passes (it finishes in the background and its result is discarded).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SolverError::TimeLimitReached`] if no feasible solution was found
+    /// within `time_limit`, or the backend's resolution error.
+    pub(crate) fn solve(
+        self,
+        problem: UnsolvedProblem,
+        constraints: impl IntoIterator<Item = Constraint>,
+        time_limit: Option<Duration>,
+    ) -> Result<BackendSolution, SolverError> {
+        if time_limit.is_some_and(|limit| limit.is_zero()) {
+            return Err(SolverError::TimeLimitReached);
+        }
+
+        match self {
+            #[cfg(feature = "solver-microlp")]
+            Self::Microlp => match time_limit {
+                Some(limit) => microlp_within(problem, constraints.into_iter().collect(), limit),
+                None => Ok(problem
+                    .using(microlp)
+                    .with_all(constraints)
+                    .solve()
+                    .map(BackendSolution::Microlp)?),
+            },
+            #[cfg(feature = "solver-highs")]
+            Self::Highs => {
+                // Solve to a zero relative gap so totals match the other backends.
+                let mut model = problem.using(highs).set_option("mip_rel_gap", 0.0);
+
+                if let Some(limit) = time_limit {
+                    model = model.set_time_limit(limit.as_secs_f64());
+                }
+
+                match model.with_all(constraints).solve() {
+                    Ok(solution) => Ok(BackendSolution::Highs(solution)),
+                    Err(good_lp::ResolutionError::Other("NoSolutionFound"))
+                        if time_limit.is_some() =>
+                    {
+                        Err(SolverError::TimeLimitReached)
+                    }
+                    Err(err) => Err(err.into()),
+                }
+            }
+        }
+    }
+}
+
+/// Most abandoned microlp solves that may still be running at once.
+///
+/// microlp cannot be interrupted, so a solve that outlives its time limit keeps
+/// its worker thread until it finishes. While this many are still running, further
+/// time-limited microlp solves are not started and report the time limit at once.
+#[cfg(all(feature = "solver-microlp", not(target_arch = "wasm32")))]
+pub const MAX_ABANDONED_SOLVES: usize = 4;
+
+/// Abandoned microlp solves whose worker threads are still running.
+#[cfg(all(feature = "solver-microlp", not(target_arch = "wasm32")))]
+static ABANDONED_SOLVES: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+/// Solve with microlp on a worker thread, giving up after `limit`.
+///
+/// At most [`MAX_ABANDONED_SOLVES`] workers are left behind by solves that gave
+/// up; each stops counting against the cap once its solve finishes.
+#[cfg(all(feature = "solver-microlp", not(target_arch = "wasm32")))]
+fn microlp_within(
+    problem: UnsolvedProblem,
+    constraints: Vec<Constraint>,
+    limit: Duration,
+) -> Result<BackendSolution, SolverError> {
+    use std::{
+        sync::{
+            Arc,
+            atomic::{AtomicU8, Ordering},
+            mpsc::{self, RecvTimeoutError},
+        },
+        thread,
+    };
+
+    const RUNNING: u8 = 0;
+    const FINISHED: u8 = 1;
+    const ABANDONED: u8 = 2;
+
+    if ABANDONED_SOLVES.load(Ordering::Acquire) >= MAX_ABANDONED_SOLVES {
+        return Err(SolverError::TimeLimitReached);
+    }
+
+    let (sender, receiver) = mpsc::channel();
+    let state = Arc::new(AtomicU8::new(RUNNING));
+    let worker_state = Arc::clone(&state);
+
+    thread::Builder::new()
+        .name("lattice-microlp".to_string())
+        .spawn(move || {
+            let solution = problem.using(microlp).with_all(constraints).solve();
+
+            // The receiver is gone if the time limit has already passed.
+            sender.send(solution).ok();
+
+            if worker_state.swap(FINISHED, Ordering::AcqRel) == ABANDONED {
+                ABANDONED_SOLVES.fetch_sub(1, Ordering::AcqRel);
+            }
+        })
+        .map_err(SolverError::WorkerThread)?;
+
+    match receiver.recv_timeout(limit) {
+        Ok(solution) => Ok(BackendSolution::Microlp(solution?)),
+        Err(RecvTimeoutError::Timeout) => {
+            // Count the worker before marking it, so it can never be released first.
+            ABANDONED_SOLVES.fetch_add(1, Ordering::AcqRel);
+
+            if state.swap(ABANDONED, Ordering::AcqRel) == FINISHED {
+                ABANDONED_SOLVES.fetch_sub(1, Ordering::AcqRel);
+            }
+
+            Err(SolverError::TimeLimitReached)
+        }
+        Err(RecvTimeoutError::Disconnected) => Err(SolverError::InvariantViolation {
+            message: "solver worker thread exited without a solution",
+        }),
+    }
+}
+
+/// Without threads the solve cannot be abandoned, so the limit is not enforced.
+#[cfg(all(feature = "solver-microlp", target_arch = "wasm32"))]
+fn microlp_within(
+    problem: UnsolvedProblem,
+    constraints: Vec<Constraint>,
+    _limit: Duration,
+) -> Result<BackendSolution, SolverError> {
+    Ok(problem
+        .using(microlp)
+        .with_all(constraints)
+        .solve()
+        .map(BackendSolution::Microlp)?)
+}
+
+/// microlp is the default whenever it is compiled in.
+#[cfg(feature = "solver-microlp")]
+const DEFAULT_BACKEND: SolverBackend = SolverBackend::Microlp;
+
+/// HiGHS when it is the only backend compiled in.
+#[cfg(all(feature = "solver-highs", not(feature = "solver-microlp")))]
+const DEFAULT_BACKEND: SolverBackend = SolverBackend::Highs;
+
+impl Default for SolverBackend {
+    fn default() -> Self {
+        DEFAULT_BACKEND
+    }
+}
+
+impl fmt::Display for SolverBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Solution produced by one of the compiled-in backends.
+pub(crate) enum BackendSolution {
+    /// Solution found by microlp
+    #[cfg(feature = "solver-microlp")]
+    Microlp(MicroLpSolution),
+
+    /// Solution found by HiGHS
+    #[cfg(feature = "solver-highs")]
+    Highs(HighsSolution),
+}
+
+impl Solution for BackendSolution {
+    fn status(&self) -> SolutionStatus {
+        match self {
+            #[cfg(feature = "solver-microlp")]
+            Self::Microlp(solution) => solution.status(),
+            #[cfg(feature = "solver-highs")]
+            Self::Highs(solution) => solution.status(),
+        }
+    }
+
+    fn value(&self, variable: Variable) -> f64 {
+        match self {
+            #[cfg(feature = "solver-microlp")]
+            Self::Microlp(solution) => solution.value(variable),
+            #[cfg(feature = "solver-highs")]
+            Self::Highs(solution) => solution.value(variable),
+        }
+    }
+}
+
+/// A solution's values copied out of the backend, so it can outlive the solve.
+#[derive(Debug, Clone)]
+pub(crate) struct StoredSolution {
+    status: SolutionStatus,
+    values: FxHashMap<Variable, f64>,
+}
+
+impl StoredSolution {
+    /// Copy the values `solution` gives `variables`.
+    pub(crate) fn new<S: Solution>(
+        solution: &S,
+        variables: impl IntoIterator<Item = Variable>,
+    ) -> Self {
+        Self {
+            status: solution.status(),
+            values: variables
+                .into_iter()
+                .map(|var| (var, solution.value(var)))
+                .collect(),
+        }
+    }
+}
+
+impl Solution for StoredSolution {
+    fn status(&self) -> SolutionStatus {
+        self.status
+    }
+
+    /// Variables the solution was not copied for are zero.
+    fn value(&self, variable: Variable) -> f64 {
+        self.values.get(&variable).copied().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use good_lp::{ProblemVariables, variable};
+
+    use super::*;
+
+    #[test]
+    fn available_backends_start_with_the_default() {
+        assert_eq!(
+            SolverBackend::AVAILABLE.first(),
+            Some(&SolverBackend::default()),
+            "default backend is listed first"
+        );
+    }
+
+    #[test]
+    fn backends_are_found_by_name() {
+        for &backend in SolverBackend::AVAILABLE {
+            assert_eq!(
+                SolverBackend::from_name(&backend.name().to_uppercase()),
+                Some(backend),
+                "{backend} found by name"
+            );
+        }
+
+        assert_eq!(SolverBackend::from_name("simplex"), None, "unknown name");
+    }
+
+    #[test]
+    fn every_backend_solves_a_small_integer_program() -> Result<(), SolverError> {
+        for &backend in SolverBackend::AVAILABLE {
+            let mut pb = ProblemVariables::new();
+            let x = pb.add(variable().integer().min(0).max(10));
+            let y = pb.add(variable().integer().min(0).max(10));
+
+            let solution = backend.solve(
+                pb.minimise(3 * x + 2 * y),
+                [(x + y).geq(4), (x - y).geq(1)],
+                None,
+            )?;
+
+            assert!(
+                (solution.eval(3 * x + 2 * y) - 11.0).abs() < 1e-6,
+                "{backend} optimum"
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,183 @@
+//! Shared Budget Pool Constraints
+
+use good_lp::{Expression, Solution};
+use rustc_hash::FxHashMap;
+use rusty_money::Money;
+use smallvec::SmallVec;
+
+use crate::{
+    items::groups::ItemGroup,
+    promotions::budget::{BudgetPoolKey, BudgetPools},
+    solvers::{
+        SolverError,
+        ilp::{
+            ILPObserver, i64_to_f64_exact, objective_value_to_integral_minor_units,
+            promotions::PromotionInstances, state::ILPState,
+        },
+    },
+};
+
+/// A pool's limited usage with its label and its limit as a solver coefficient.
+type LimitRow<'e> = (BudgetPoolKey, &'static str, &'e Expression, f64);
+
+/// Combined usage of each shared budget pool across the promotions drawing on it.
+///
+/// Only limits a pool actually sets are tracked, so a pool with just a monetary
+/// limit never asks its promotions to count redemptions.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct BudgetPoolUsage {
+    /// Pool key -> sum of redemption counts
+    redemptions: FxHashMap<BudgetPoolKey, Expression>,
+
+    /// Pool key -> sum of discount values in minor units
+    discounts: FxHashMap<BudgetPoolKey, Expression>,
+}
+
+impl BudgetPoolUsage {
+    /// Add the usage of every pooled promotion in `instances`.
+    ///
+    /// May be called once per layer to share pools across a multi-layer model.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SolverError::UnknownBudgetPool`] if a promotion draws on a pool missing
+    /// from `pools`, or [`SolverError::BudgetPoolUnsupported`] if it cannot express its usage.
+    pub(crate) fn add_instances(
+        &mut self,
+        instances: &PromotionInstances<'_>,
+        item_group: &ItemGroup<'_>,
+        pools: &BudgetPools<'_>,
+    ) -> Result<(), SolverError> {
+        for instance in instances.iter() {
+            for &pool_key in instance.budget_pools() {
+                let pool = pools.get(pool_key).ok_or(SolverError::UnknownBudgetPool {
+                    promotion_key: instance.promotion_key(),
+                    pool_key,
+                })?;
+
+                if pool.redemption_limit.is_some() {
+                    *self.redemptions.entry(pool_key).or_default() += instance
+                        .redemption_count_expr()
+                        .ok_or(SolverError::BudgetPoolUnsupported(instance.promotion_key()))?;
+                }
+
+                if pool.monetary_limit.is_some() {
+                    *self.discounts.entry(pool_key).or_default() +=
+                        instance.discount_value_expr(item_group)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Constrain each pool's combined usage to its limits.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SolverError::MinorUnitsNotRepresentable`] if a limit cannot be represented
+    /// exactly as a solver coefficient.
+    pub(crate) fn add_constraints(
+        &self,
+        pools: &BudgetPools<'_>,
+        state: &mut ILPState,
+        observer: &mut dyn ILPObserver,
+    ) -> Result<(), SolverError> {
+        for (pool_key, label, expr, limit) in self.limit_rows(pools)? {
+            observer.on_budget_pool_constraint(pool_key, label, expr, "<=", limit);
+
+            state.add_leq_constraint(expr.clone(), limit);
+        }
+
+        Ok(())
+    }
+
+    /// The right-hand sides of the rows [`add_constraints()`](Self::add_constraints)
+    /// adds for `pools`, in the same order.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SolverError::MinorUnitsNotRepresentable`] if a limit cannot be represented
+    /// exactly as a solver coefficient.
+    pub(crate) fn limits(
+        &self,
+        pools: &BudgetPools<'_>,
+    ) -> Result<SmallVec<[f64; 4]>, SolverError> {
+        Ok(self
+            .limit_rows(pools)?
+            .into_iter()
+            .map(|(_pool_key, _label, _expr, limit)| limit)
+            .collect())
+    }
+
+    /// Each limited usage with its label and limit.
+    fn limit_rows(
+        &self,
+        pools: &BudgetPools<'_>,
+    ) -> Result<SmallVec<[LimitRow<'_>; 4]>, SolverError> {
+        let mut rows = SmallVec::new();
+
+        // Iterate the pools rather than the maps so rows are emitted in a stable order.
+        for (pool_key, pool) in pools {
+            if let (Some(limit), Some(expr)) =
+                (pool.redemption_limit, self.redemptions.get(&pool_key))
+            {
+                let limit_f64 = i64_to_f64_exact(i64::from(limit))
+                    .ok_or(SolverError::MinorUnitsNotRepresentable(i64::from(limit)))?;
+
+                rows.push((pool_key, "redemption count budget", expr, limit_f64));
+            }
+
+            if let (Some(limit), Some(expr)) = (pool.monetary_limit, self.discounts.get(&pool_key))
+            {
+                let limit_minor = limit.to_minor_units();
+                let limit_f64 = i64_to_f64_exact(limit_minor)
+                    .ok_or(SolverError::MinorUnitsNotRepresentable(limit_minor))?;
+
+                rows.push((pool_key, "monetary value budget", expr, limit_f64));
+            }
+        }
+
+        Ok(rows)
+    }
+
+    /// Deduct the usage chosen by `solution` from each pool's remaining limits.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SolverError::InvariantViolation`] if the solved usage is not integral.
+    pub(crate) fn debit<S: Solution>(
+        &self,
+        pools: &mut BudgetPools<'_>,
+        solution: &S,
+    ) -> Result<(), SolverError> {
+        for (pool_key, pool) in pools.iter_mut() {
+            if let (Some(limit), Some(expr)) =
+                (pool.redemption_limit, self.redemptions.get(&pool_key))
+            {
+                let used = objective_value_to_integral_minor_units(
+                    solution.eval(expr),
+                    "budget pool redemption count is non-integral",
+                )?;
+
+                let remaining = i64::from(limit).saturating_sub(used).max(0);
+
+                pool.redemption_limit = Some(u32::try_from(remaining).unwrap_or(limit));
+            }
+
+            if let (Some(limit), Some(expr)) = (pool.monetary_limit, self.discounts.get(&pool_key))
+            {
+                let used = objective_value_to_integral_minor_units(
+                    solution.eval(expr),
+                    "budget pool discount value is non-integral",
+                )?;
+
+                let remaining = limit.to_minor_units().saturating_sub(used).max(0);
+
+                pool.monetary_limit = Some(Money::from_minor(remaining, limit.currency()));
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,500 @@
+//! Solve Cache
+//!
+//! Re-pricing a basket after a small change solves mostly the same models again.
+//! Solves are deterministic, so a model whose promotions and items are unchanged
+//! can take its previous solution instead of being rebuilt and solved, and the
+//! qualification results of an unchanged item can be reused.
+//!
+//! A model whose solution cannot be reused, because it draws on a shared budget
+//! pool, is re-priced with a stability preference, or keeps items off excluded
+//! promotions, still has the same formulation as before. It is set up from the
+//! cached formulation, and its previous first pass is the starting point: taken
+//! as is when the pools' balances are unchanged, since the backend would find it
+//! again, and otherwise given to the backend to start its search from.
+
+use std::hash::{Hash, Hasher};
+
+use rustc_hash::{FxHashMap, FxHasher};
+use smallvec::SmallVec;
+
+use crate::{
+    context::EvaluationContext,
+    discounts::rounding::RoundingPolicies,
+    items::{Item, groups::ItemGroup},
+    products::ProductKey,
+    promotions::PromotionKey,
+    solvers::{
+        SolverResult,
+        ilp::{
+            DetachedFormulation, ILPPromotion, ItemSignature, ObjectiveMode, SolverBackend,
+            TieBreakPolicy, backend::StoredSolution, options::SolveRun,
+        },
+    },
+};
+
+/// Run settings that change what a model's solution is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SolveSettings {
+    backend: SolverBackend,
+    tie_break: TieBreakPolicy,
+    objective: ObjectiveMode,
+    rounding: RoundingPolicies,
+}
+
+impl SolveSettings {
+    fn matches(&self, run: &SolveRun<'_>) -> bool {
+        self.backend == run.backend
+            && self.tie_break == run.tie_break
+            && self.objective == run.objective
+            && self.rounding == *run.rounding
+    }
+}
+
+/// A solved model: its promotions, its items in order, the settings it was
+/// solved with, and its solution.
+#[derive(Debug, Clone)]
+struct CachedSolution<'b> {
+    promotions: SmallVec<[PromotionKey; 5]>,
+    items: SmallVec<[Item<'b>; 10]>,
+    settings: SolveSettings,
+    result: SolverResult<'b>,
+
+    /// Last evaluation that used or stored this solution
+    generation: u64,
+}
+
+/// A formulated model: its promotions, its items in order, the settings it was
+/// formulated under, and the last solution of its first pass.
+#[derive(Debug, Clone)]
+struct CachedFormulation<'b> {
+    promotions: SmallVec<[PromotionKey; 5]>,
+    items: SmallVec<[Item<'b>; 10]>,
+    rounding: RoundingPolicies,
+    excluded: SmallVec<[(usize, PromotionKey); 4]>,
+    formulation: DetachedFormulation,
+    previous: Option<PreviousSolution>,
+
+    /// Last evaluation that used or stored this formulation
+    generation: u64,
+}
+
+impl<'b> CachedFormulation<'b> {
+    fn matches(
+        &self,
+        promotions: &[&dyn ILPPromotion],
+        item_group: &ItemGroup<'b>,
+        run: &SolveRun<'_>,
+    ) -> bool {
+        self.rounding == *run.rounding
+            && self.excluded.as_slice() == run.excluded
+            && self
+                .promotions
+                .iter()
+                .copied()
+                .eq(promotions.iter().map(|p| p.key()))
+            && self.items.iter().eq(item_group.iter())
+    }
+}
+
+/// The proven-optimal first-pass solution of a model, with the backend,
+/// objective and budget pool balances it was found under.
+#[derive(Debug, Clone)]
+pub(crate) struct PreviousSolution {
+    backend: SolverBackend,
+    objective: ObjectiveMode,
+    pool_limits: SmallVec<[f64; 4]>,
+    solution: StoredSolution,
+}
+
+impl PreviousSolution {
+    /// A first pass solved under `run` with the pool rows bounded by `pool_limits`.
+    pub(crate) fn new(
+        run: &SolveRun<'_>,
+        pool_limits: SmallVec<[f64; 4]>,
+        solution: StoredSolution,
+    ) -> Self {
+        Self {
+            backend: run.backend,
+            objective: run.objective,
+            pool_limits,
+            solution,
+        }
+    }
+
+    /// Whether the first pass under `run` with the pool rows bounded by
+    /// `pool_limits` is the one this solution was found for.
+    pub(crate) fn is_current(&self, run: &SolveRun<'_>, pool_limits: &[f64]) -> bool {
+        self.backend == run.backend
+            && self.objective == run.objective
+            && self
+                .pool_limits
+                .iter()
+                .map(|limit| limit.to_bits())
+                .eq(pool_limits.iter().map(|limit| limit.to_bits()))
+    }
+
+    /// The solution's variable values.
+    pub(crate) fn solution(&self) -> &StoredSolution {
+        &self.solution
+    }
+}
+
+/// Qualification results of a promotion for an item.
+#[derive(Debug, Clone)]
+struct CachedSignature<'b> {
+    item: Item<'b>,
+    signature: Option<ItemSignature>,
+}
+
+/// How many models a cache answered and how many had to be solved.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Models solved
+    pub solved: usize,
+
+    /// Models answered with a previous solution
+    pub reused: usize,
+
+    /// Solved models set up from an earlier formulation instead of being
+    /// formulated again
+    pub reformulations_avoided: usize,
+
+    /// Solved models whose first pass started from their previous one
+    pub warm_starts: usize,
+}
+
+/// Solutions, formulations and qualification results kept between solves of a
+/// changing basket.
+///
+/// Entries are matched on exact equality of their inputs, including the run's
+/// objective, rounding policies, tie-break policy and backend, so a reused
+/// solution is the one a fresh solve would find. Only proven-optimal solutions of
+/// models that draw on no shared budget pool are kept, since pool balances change
+/// between solves; formulations are kept for every model, with their pool rows
+/// bounded afresh on each solve. All entries assume one evaluation context and one
+/// set of budget pools; call [`clear()`](Self::clear) when the context changes.
+#[derive(Debug, Default)]
+pub(crate) struct SolveCache<'b> {
+    /// Solutions bucketed by a hash of their objective, promotions, products, prices
+    /// and quantities
+    solutions: FxHashMap<u64, SmallVec<[CachedSolution<'b>; 1]>>,
+
+    /// Formulations bucketed by a hash of their promotions, products, prices and
+    /// quantities
+    formulations: FxHashMap<u64, SmallVec<[CachedFormulation<'b>; 1]>>,
+
+    /// Signatures bucketed by promotion and product
+    signatures: FxHashMap<(PromotionKey, ProductKey), SmallVec<[CachedSignature<'b>; 1]>>,
+
+    /// Current evaluation, used to drop solutions that are no longer needed
+    generation: u64,
+
+    stats: CacheStats,
+}
+
+impl<'b> SolveCache<'b> {
+    /// Drop every entry, e.g. after the evaluation context changes.
+    pub fn clear(&mut self) {
+        self.solutions.clear();
+        self.formulations.clear();
+        self.signatures.clear();
+    }
+
+    /// Counts of solved and reused models since the cache was created.
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    /// Start a new evaluation, dropping solutions and formulations the previous two
+    /// did not use.
+    ///
+    /// Keeping one evaluation of history means undoing the latest change (such as
+    /// removing the item just added) is still answered from the cache.
+    pub fn next_generation(&mut self) {
+        self.generation += 1;
+
+        let oldest = self.generation.saturating_sub(2);
+
+        self.solutions.retain(|_hash, bucket| {
+            bucket.retain(|entry| entry.generation >= oldest);
+
+            !bucket.is_empty()
+        });
+
+        self.formulations.retain(|_hash, bucket| {
+            bucket.retain(|entry| entry.generation >= oldest);
+
+            !bucket.is_empty()
+        });
+    }
+
+    /// Which of `promotion`'s qualifications `item` matches, reusing earlier results.
+    pub fn item_signature(
+        &mut self,
+        promotion: &dyn ILPPromotion,
+        item: &Item<'b>,
+        context: &EvaluationContext,
+    ) -> Option<ItemSignature> {
+        let bucket = self
+            .signatures
+            .entry((promotion.key(), item.product()))
+            .or_default();
+
+        if let Some(entry) = bucket.iter().find(|entry| entry.item == *item) {
+            return entry.signature.clone();
+        }
+
+        let signature = promotion.item_signature(item, context);
+
+        bucket.push(CachedSignature {
+            item: item.clone(),
+            signature: signature.clone(),
+        });
+
+        signature
+    }
+
+    /// The stored solution of `promotions` over `item_group` under `run`'s
+    /// settings, if there is one.
+    pub fn solution(
+        &mut self,
+        promotions: &[&dyn ILPPromotion],
+        item_group: &ItemGroup<'b>,
+        run: &SolveRun<'_>,
+    ) -> Option<SolverResult<'b>> {
+        let generation = self.generation;
+        let entry = self
+            .solutions
+            .get_mut(&solution_hash(promotions, item_group, run))?
+            .iter_mut()
+            .find(|entry| {
+                entry.settings.matches(run)
+                    && entry
+                        .promotions
+                        .iter()
+                        .copied()
+                        .eq(promotions.iter().map(|p| p.key()))
+                    && entry.items.iter().eq(item_group.iter())
+            })?;
+
+        entry.generation = generation;
+        self.stats.reused += 1;
+
+        Some(entry.result.clone())
+    }
+
+    /// Store the solution of `promotions` over `item_group` under `run`'s settings.
+    pub fn insert_solution(
+        &mut self,
+        promotions: &[&dyn ILPPromotion],
+        item_group: &ItemGroup<'b>,
+        run: &SolveRun<'_>,
+        result: &SolverResult<'b>,
+    ) {
+        if !result.quality.is_optimal() {
+            return;
+        }
+
+        self.solutions
+            .entry(solution_hash(promotions, item_group, run))
+            .or_default()
+            .push(CachedSolution {
+                promotions: promotions.iter().map(|promotion| promotion.key()).collect(),
+                items: item_group.iter().cloned().collect(),
+                settings: SolveSettings {
+                    backend: run.backend,
+                    tie_break: run.tie_break,
+                    objective: run.objective,
+                    rounding: run.rounding.clone(),
+                },
+                result: result.clone(),
+                generation: self.generation,
+            });
+    }
+
+    /// The formulation of `promotions` over `item_group` under `run`'s rounding
+    /// policies and exclusions, with its last first-pass solution, if it was
+    /// formulated before.
+    pub fn formulation(
+        &mut self,
+        promotions: &[&dyn ILPPromotion],
+        item_group: &ItemGroup<'b>,
+        run: &SolveRun<'_>,
+    ) -> Option<(&DetachedFormulation, Option<&PreviousSolution>)> {
+        let generation = self.generation;
+        let entry = self
+            .formulations
+            .get_mut(&formulation_hash(promotions, item_group))?
+            .iter_mut()
+            .find(|entry| entry.matches(promotions, item_group, run))?;
+
+        entry.generation = generation;
+        self.stats.reformulations_avoided += 1;
+
+        if entry.previous.is_some() {
+            self.stats.warm_starts += 1;
+        }
+
+        Some((&entry.formulation, entry.previous.as_ref()))
+    }
+
+    /// Record a solve of a formulation found with [`formulation()`](Self::formulation),
+    /// keeping `first_pass` as its previous solution if there is one.
+    pub fn update_formulation(
+        &mut self,
+        promotions: &[&dyn ILPPromotion],
+        item_group: &ItemGroup<'b>,
+        run: &SolveRun<'_>,
+        first_pass: Option<PreviousSolution>,
+    ) {
+        self.stats.solved += 1;
+
+        let Some(entry) = self
+            .formulations
+            .get_mut(&formulation_hash(promotions, item_group))
+            .and_then(|bucket| {
+                bucket
+                    .iter_mut()
+                    .find(|entry| entry.matches(promotions, item_group, run))
+            })
+        else {
+            return;
+        };
+
+        if first_pass.is_some() {
+            entry.previous = first_pass;
+        }
+    }
+
+    /// Store the formulation of `promotions` over `item_group` under `run`'s
+    /// settings, with the solution of its first pass if it was proven optimal.
+    pub fn insert_formulation(
+        &mut self,
+        promotions: &[&dyn ILPPromotion],
+        item_group: &ItemGroup<'b>,
+        run: &SolveRun<'_>,
+        formulation: DetachedFormulation,
+        first_pass: Option<PreviousSolution>,
+    ) {
+        self.stats.solved += 1;
+
+        self.formulations
+            .entry(formulation_hash(promotions, item_group))
+            .or_default()
+            .push(CachedFormulation {
+                promotions: promotions.iter().map(|promotion| promotion.key()).collect(),
+                items: item_group.iter().cloned().collect(),
+                rounding: run.rounding.clone(),
+                excluded: run.excluded.iter().copied().collect(),
+                formulation,
+                previous: first_pass,
+                generation: self.generation,
+            });
+    }
+}
+
+/// Hash the parts of a model's inputs that are cheap to hash; tags and the
+/// remaining settings are compared on lookup instead.
+fn solution_hash(
+    promotions: &[&dyn ILPPromotion],
+    item_group: &ItemGroup<'_>,
+    run: &SolveRun<'_>,
+) -> u64 {
+    let mut hasher = FxHasher::default();
+
+    run.objective.hash(&mut hasher);
+
+    hash_model(promotions, item_group, &mut hasher);
+
+    hasher.finish()
+}
+
+/// Hash the promotions and items of a formulation; the settings it was
+/// formulated under are compared on lookup instead.
+fn formulation_hash(promotions: &[&dyn ILPPromotion], item_group: &ItemGroup<'_>) -> u64 {
+    let mut hasher = FxHasher::default();
+
+    hash_model(promotions, item_group, &mut hasher);
+
+    hasher.finish()
+}
+
+/// Hash the keys of a model's promotions and the products, prices and quantities
+/// of its items.
+fn hash_model(promotions: &[&dyn ILPPromotion], item_group: &ItemGroup<'_>, hasher: &mut FxHasher) {
+    for promotion in promotions {
+        promotion.key().hash(hasher);
+    }
+
+    for item in item_group.iter() {
+        item.product().hash(hasher);
+        item.price().to_minor_units().hash(hasher);
+        item.quantity().hash(hasher);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rusty_money::{Money, iso::GBP};
+    use smallvec::smallvec;
+
+    use crate::{
+        discounts::rounding::{RoundingMode, RoundingPolicy},
+        solvers::{SolutionQuality, ilp::SolverOptions},
+    };
+
+    use super::*;
+
+    fn solved<'b>(item_group: &ItemGroup<'b>) -> SolverResult<'b> {
+        SolverResult {
+            affected_items: SmallVec::new(),
+            unaffected_items: (0..item_group.len()).collect(),
+            total: Money::from_minor(100, GBP),
+            promotion_redemptions: SmallVec::new(),
+            quality: SolutionQuality::Optimal,
+        }
+    }
+
+    #[test]
+    fn solutions_are_only_reused_under_the_same_settings() {
+        let item_group = ItemGroup::new(
+            smallvec![Item::new(
+                ProductKey::default(),
+                Money::from_minor(100, GBP)
+            )],
+            GBP,
+        );
+
+        let options = SolverOptions::default();
+        let least_generous = options.clone().with_objective(ObjectiveMode::LeastGenerous);
+        let smaller_discount =
+            options
+                .clone()
+                .with_rounding(RoundingPolicies::new(RoundingPolicy::new(
+                    RoundingMode::SmallerDiscount,
+                )));
+
+        let mut cache = SolveCache::default();
+
+        cache.insert_solution(&[], &item_group, &options.start(), &solved(&item_group));
+
+        assert!(
+            cache
+                .solution(&[], &item_group, &least_generous.start())
+                .is_none()
+        );
+        assert!(
+            cache
+                .solution(&[], &item_group, &smaller_discount.start())
+                .is_none()
+        );
+        assert!(cache.solution(&[], &item_group, &options.start()).is_some());
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                reused: 1,
+                ..CacheStats::default()
+            }
+        );
+    }
+}
@@ -0,0 +1,466 @@
+//! Problem Decomposition
+//!
+//! Items and promotions form a bipartite eligibility graph: a promotion is linked
+//! to every item it could inspect, and promotions drawing on the same budget pool
+//! or excluded from the same basket are linked to each other. Connected components
+//! of that graph never interact, so each one can be solved as its own, much
+//! smaller, model.
+
+use petgraph::unionfind::UnionFind;
+use rustc_hash::FxHashMap;
+use rusty_money::Money;
+use smallvec::SmallVec;
+
+use crate::{
+    items::{Item, groups::ItemGroup},
+    promotions::{
+        PromotionKey, budget::BudgetPoolKey, exclusion::ExclusionRules,
+        redemptions::PromotionRedemption,
+    },
+    solvers::{
+        SolverError, SolverResult,
+        ilp::{ILPPromotion, cache::SolveCache},
+    },
+};
+
+/// A set of items and the promotions that can only interact with each other.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct Component {
+    /// Indexes of the component's items in the original item group, ascending
+    pub items: SmallVec<[usize; 10]>,
+
+    /// Positions of the component's promotions in the original promotion list, ascending
+    pub promotions: SmallVec<[usize; 5]>,
+}
+
+impl Component {
+    /// Build the item group holding just this component's items.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SolverError::ItemGroup`] if an item index is out of range.
+    pub fn item_group<'b>(&self, item_group: &ItemGroup<'b>) -> Result<ItemGroup<'b>, SolverError> {
+        let items = self
+            .items
+            .iter()
+            .map(|&item_idx| item_group.get_item(item_idx).cloned())
+            .collect::<Result<SmallVec<[Item<'b>; 10]>, _>>()?;
+
+        Ok(ItemGroup::new(items, item_group.currency()).with_context(item_group.context().clone()))
+    }
+}
+
+/// Partition the item group into independently solvable components.
+///
+/// A promotion touches an item when [`ILPPromotion::item_signature`] reports any
+/// matching qualification; a promotion without signatures is assumed to touch every
+/// item. Promotions sharing a key or a budget pool, or that cannot both be redeemed
+/// in the basket, are kept together, and promotions touching no item are dropped
+/// since they cannot redeem. Components are ordered by their first item. Signatures
+/// are looked up in `cache` when one is given.
+pub(crate) fn components<'b>(
+    promotions: &[&dyn ILPPromotion],
+    item_group: &ItemGroup<'b>,
+    mut cache: Option<&mut SolveCache<'b>>,
+) -> SmallVec<[Component; 4]> {
+    let item_count = item_group.len();
+    let mut sets = UnionFind::<usize>::new(item_count + promotions.len());
+
+    let mut first_by_key: FxHashMap<PromotionKey, usize> = FxHashMap::default();
+    let mut first_by_pool: FxHashMap<BudgetPoolKey, usize> = FxHashMap::default();
+    let mut touches_items: SmallVec<[bool; 5]> = SmallVec::from_elem(false, promotions.len());
+
+    for (promotion_idx, promotion) in promotions.iter().enumerate() {
+        let node = item_count + promotion_idx;
+
+        for (item_idx, item) in item_group.iter().enumerate() {
+            let signature = match cache.as_deref_mut() {
+                Some(cache) => cache.item_signature(*promotion, item, item_group.context()),
+                None => promotion.item_signature(item, item_group.context()),
+            };
+
+            let touches = signature.is_none_or(|signature| signature.contains(&true));
+
+            if touches {
+                sets.union(node, item_idx);
+
+                if let Some(flag) = touches_items.get_mut(promotion_idx) {
+                    *flag = true;
+                }
+            }
+        }
+
+        let first = *first_by_key.entry(promotion.key()).or_insert(node);
+
+        sets.union(node, first);
+
+        for &pool_key in promotion.budget_pools() {
+            let first = *first_by_pool.entry(pool_key).or_insert(node);
+
+            sets.union(node, first);
+        }
+    }
+
+    // Basket exclusions are decided across the whole model, like shared pools.
+    let exclusion_rules = ExclusionRules::from_promotions(promotions.iter().copied());
+
+    for (first, second) in exclusion_rules.basket_pairs() {
+        if let (Some(&first), Some(&second)) = (first_by_key.get(&first), first_by_key.get(&second))
+        {
+            sets.union(first, second);
+        }
+    }
+
+    let mut components: SmallVec<[Component; 4]> = SmallVec::new();
+    let mut component_by_root: FxHashMap<usize, usize> = FxHashMap::default();
+
+    for item_idx in 0..item_count {
+        let root = sets.find(item_idx);
+        let component_idx = *component_by_root.entry(root).or_insert_with(|| {
+            components.push(Component::default());
+            components.len() - 1
+        });
+
+        if let Some(component) = components.get_mut(component_idx) {
+            component.items.push(item_idx);
+        }
+    }
+
+    for (promotion_idx, touches) in touches_items.into_iter().enumerate() {
+        if !touches {
+            continue;
+        }
+
+        let root = sets.find(item_count + promotion_idx);
+
+        if let Some(component) = component_by_root
+            .get(&root)
+            .and_then(|&component_idx| components.get_mut(component_idx))
+        {
+            component.promotions.push(promotion_idx);
+        }
+    }
+
+    components
+}
+
+/// Combine the results of solving each component into one result for the whole group.
+///
+/// Redemptions are listed promotion by promotion in the original promotion order and
+/// renumbered so bundle ids are unique across components, matching a single solve of
+/// the whole group.
+///
+/// # Errors
+///
+/// Returns [`SolverError::InvariantViolation`] if the results do not line up with the
+/// components, or a [`SolverError`] if totals cannot be added.
+pub(crate) fn merge_results<'b>(
+    promotions: &[&dyn ILPPromotion],
+    item_group: &ItemGroup<'b>,
+    components: &[Component],
+    results: &[SolverResult<'b>],
+) -> Result<SolverResult<'b>, SolverError> {
+    if results.len() != components.len() {
+        return Err(SolverError::InvariantViolation {
+            message: "component result count does not match component count",
+        });
+    }
+
+    let mut total = Money::from_minor(0, item_group.currency());
+    let mut unaffected_items: SmallVec<[usize; 10]> = SmallVec::new();
+    let mut component_by_promotion: FxHashMap<PromotionKey, usize> = FxHashMap::default();
+
+    for (component_idx, (component, result)) in components.iter().zip(results).enumerate() {
+        total = total.add(result.total)?;
+
+        for &local_idx in &result.unaffected_items {
+            unaffected_items.push(global_item_idx(component, local_idx)?);
+        }
+
+        for promotion_idx in &component.promotions {
+            if let Some(promotion) = promotions.get(*promotion_idx) {
+                component_by_promotion.insert(promotion.key(), component_idx);
+            }
+        }
+    }
+
+    unaffected_items.sort_unstable();
+
+    let mut affected_items: SmallVec<[usize; 10]> = SmallVec::new();
+    let mut promotion_redemptions: SmallVec<[PromotionRedemption<'b>; 10]> = SmallVec::new();
+    let mut redemption_ids: FxHashMap<(usize, usize), usize> = FxHashMap::default();
+
+    for promotion in promotions {
+        let key = promotion.key();
+
+        // Promotions sharing a key were solved together; take their redemptions once.
+        let Some(component_idx) = component_by_promotion.remove(&key) else {
+            continue;
+        };
+
+        let (Some(component), Some(result)) =
+            (components.get(component_idx), results.get(component_idx))
+        else {
+            continue;
+        };
+
+        for redemption in result
+            .promotion_redemptions
+            .iter()
+            .filter(|redemption| redemption.promotion_key == key)
+        {
+            let item_idx = global_item_idx(component, redemption.item_idx)?;
+
+            let next_id = redemption_ids.len();
+            let bundle_id = *redemption_ids
+                .entry((component_idx, redemption.redemption_idx))
+                .or_insert(next_id);
+
+            if !affected_items.contains(&item_idx) {
+                affected_items.push(item_idx);
+            }
+
+            promotion_redemptions.push(PromotionRedemption {
+                item_idx,
+                redemption_idx: bundle_id,
+                ..redemption.clone()
+            });
+        }
+    }
+
+    // The merged allocation is only as good as its weakest component.
+    let quality = results
+        .iter()
+        .map(|result| result.quality)
+        .max()
+        .unwrap_or_default();
+
+    Ok(SolverResult {
+        affected_items,
+        unaffected_items,
+        total,
+        promotion_redemptions,
+        quality,
+    })
+}
+
+fn global_item_idx(component: &Component, local_idx: usize) -> Result<usize, SolverError> {
+    component
+        .items
+        .get(local_idx)
+        .copied()
+        .ok_or(SolverError::InvariantViolation {
+            message: "component redemption references an unknown item",
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use decimal_percentage::Percentage;
+    use rusty_money::iso::GBP;
+    use slotmap::SlotMap;
+    use smallvec::smallvec;
+    use testresult::TestResult;
+
+    use crate::{
+        discounts::SimpleDiscount,
+        products::ProductKey,
+        promotions::{
+            Promotion, budget::PromotionBudget, promotion, qualification::Qualification,
+            types::DirectDiscountPromotion,
+        },
+        solvers::SolutionQuality,
+        tags::string::StringTagCollection,
+    };
+
+    use super::*;
+
+    fn items<'a>(tags: &[&str]) -> ItemGroup<'a> {
+        let items = tags
+            .iter()
+            .map(|tag| {
+                Item::with_tags(
+                    ProductKey::default(),
+                    Money::from_minor(100, GBP),
+                    StringTagCollection::from_strs(&[tag]),
+                )
+            })
+            .collect();
+
+        ItemGroup::new(items, GBP)
+    }
+
+    fn half_off(key: PromotionKey, tags: &[&str]) -> DirectDiscountPromotion<'static> {
+        DirectDiscountPromotion::new(
+            key,
+            Qualification::match_any(StringTagCollection::from_strs(tags)),
+            SimpleDiscount::PercentageOff(Percentage::from(0.5)),
+            PromotionBudget::unlimited(),
+        )
+    }
+
+    fn refs<'p>(promotions: &'p [Promotion<'_>]) -> SmallVec<[&'p dyn ILPPromotion; 5]> {
+        promotions.iter().map(AsRef::as_ref).collect()
+    }
+
+    #[test]
+    fn unrelated_promotions_form_separate_components() {
+        let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+        let item_group = items(&["drink", "hair", "drink", "other"]);
+
+        let promotions = [
+            promotion(half_off(keys.insert(()), &["hair"])),
+            promotion(half_off(keys.insert(()), &["drink"])),
+        ];
+
+        let components = components(&refs(&promotions), &item_group, None);
+
+        assert_eq!(
+            components.as_slice(),
+            [
+                Component {
+                    items: smallvec![0, 2],
+                    promotions: smallvec![1],
+                },
+                Component {
+                    items: smallvec![1],
+                    promotions: smallvec![0],
+                },
+                Component {
+                    items: smallvec![3],
+                    promotions: smallvec![],
+                },
+            ],
+            "each promotion keeps its own items"
+        );
+    }
+
+    #[test]
+    fn shared_budget_pool_joins_components() {
+        let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+        let mut pools = SlotMap::<BudgetPoolKey, ()>::with_key();
+        let pool = pools.insert(());
+        let item_group = items(&["drink", "hair"]);
+
+        let promotions = [
+            promotion(half_off(keys.insert(()), &["drink"]).with_budget_pool(pool)),
+            promotion(half_off(keys.insert(()), &["hair"]).with_budget_pool(pool)),
+        ];
+
+        let components = components(&refs(&promotions), &item_group, None);
+
+        assert_eq!(components.len(), 1, "pooled promotions are solved together");
+    }
+
+    #[test]
+    fn promotion_touching_nothing_is_dropped() {
+        let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+        let item_group = items(&["drink"]);
+
+        let promotions = [promotion(half_off(keys.insert(()), &["hair"]))];
+
+        let components = components(&refs(&promotions), &item_group, None);
+
+        assert_eq!(
+            components.as_slice(),
+            [Component {
+                items: smallvec![0],
+                promotions: smallvec![],
+            }],
+            "promotion without eligible items has no component"
+        );
+    }
+
+    #[test]
+    fn merge_maps_items_and_renumbers_bundles() -> TestResult {
+        let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+        let item_group = items(&["hair", "drink", "drink"]);
+
+        let promotions = [
+            promotion(half_off(keys.insert(()), &["drink"])),
+            promotion(half_off(keys.insert(()), &["hair"])),
+        ];
+
+        let promotion_refs = refs(&promotions);
+        let components = components(&promotion_refs, &item_group, None);
+
+        let redemption = |promotion_idx: usize, item_idx, redemption_idx| {
+            let promotion_key = promotion_refs
+                .get(promotion_idx)
+                .map(|promotion| promotion.key())
+                .unwrap_or_default();
+
+            PromotionRedemption {
+                promotion_key,
+                item_idx,
+                redemption_idx,
+                original_price: Money::from_minor(100, GBP),
+                final_price: Money::from_minor(50, GBP),
+                quantity: 1,
+                funding: SmallVec::new(),
+                floor_limited: false,
+            }
+        };
+
+        let results = [
+            SolverResult {
+                affected_items: smallvec![0],
+                unaffected_items: smallvec![],
+                total: Money::from_minor(50, GBP),
+                promotion_redemptions: smallvec![redemption(1, 0, 0)],
+                quality: SolutionQuality::Optimal,
+            },
+            SolverResult {
+                affected_items: smallvec![0, 1],
+                unaffected_items: smallvec![],
+                total: Money::from_minor(100, GBP),
+                promotion_redemptions: smallvec![redemption(0, 0, 0), redemption(0, 1, 1)],
+                quality: SolutionQuality::TimeLimited,
+            },
+        ];
+
+        let merged = merge_results(&promotion_refs, &item_group, &components, &results)?;
+
+        assert_eq!(
+            merged.total,
+            Money::from_minor(150, GBP),
+            "totals are summed"
+        );
+        assert_eq!(
+            merged.affected_items.as_slice(),
+            [1, 2, 0],
+            "affected items follow promotion order"
+        );
+
+        let placements: Vec<_> = merged
+            .promotion_redemptions
+            .iter()
+            .map(|redemption| (redemption.item_idx, redemption.redemption_idx))
+            .collect();
+
+        assert_eq!(
+            placements,
+            [(1, 0), (2, 1), (0, 2)],
+            "bundle ids are unique across components"
+        );
+        assert_eq!(
+            merged.quality,
+            SolutionQuality::TimeLimited,
+            "merged quality is the worst component's"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn merge_rejects_mismatched_results() {
+        let item_group = items(&["drink"]);
+
+        let result = merge_results(&[], &item_group, &[Component::default()], &[]);
+
+        assert!(
+            matches!(result, Err(SolverError::InvariantViolation { .. })),
+            "one result is required per component"
+        );
+    }
+}
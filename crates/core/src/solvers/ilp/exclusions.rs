@@ -0,0 +1,136 @@
+//! Promotion Exclusion Constraints
+
+use std::collections::BTreeMap;
+
+use good_lp::{Expression, IntoAffineExpression, Variable, variable};
+
+use crate::{
+    items::groups::ItemGroup,
+    promotions::{PromotionKey, exclusion::ExclusionRules},
+    solvers::{
+        SolverError,
+        ilp::{ILPObserver, item_quantity_f64, promotions::PromotionInstances, state::ILPState},
+    },
+};
+
+/// Whether any promotion of a basket exclusion pair is redeemed anywhere in the model.
+///
+/// Each promotion in a basket-scoped pair gets a binary usage variable that every
+/// unit it takes switches on, and no two excluded promotions may both be used.
+/// Item-scoped pairs need no tracking within a single item group, since each unit
+/// is already taken by at most one promotion.
+#[derive(Debug, Default)]
+pub(crate) struct ExclusionUsage {
+    /// Promotion key -> binary usage variable
+    used: BTreeMap<PromotionKey, Variable>,
+}
+
+impl ExclusionUsage {
+    /// Link the usage of every basket-excluded promotion in `instances` to the
+    /// units it takes.
+    ///
+    /// May be called once per layer to share usage across a multi-layer model.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SolverError::ItemGroup`] if an item quantity cannot be read.
+    pub(crate) fn add_instances(
+        &mut self,
+        rules: &ExclusionRules,
+        instances: &PromotionInstances<'_>,
+        item_group: &ItemGroup<'_>,
+        state: &mut ILPState,
+        observer: &mut dyn ILPObserver,
+    ) -> Result<(), SolverError> {
+        for instance in instances.iter() {
+            let key = instance.promotion_key();
+
+            if !rules
+                .basket_pairs()
+                .any(|(first, second)| first == key || second == key)
+            {
+                continue;
+            }
+
+            let used = *self.used.entry(key).or_insert_with(|| {
+                let var = state.problem_variables_mut().add(variable().binary());
+
+                observer.on_auxiliary_variable(key, var, "exclusion_used", None, None);
+
+                var
+            });
+
+            for item_idx in 0..item_group.len() {
+                let participation =
+                    instance.add_item_presence_term(Expression::default(), item_idx);
+
+                if !has_terms(&participation) {
+                    continue;
+                }
+
+                // Any unit taken marks the promotion as used.
+                let expr = participation - item_quantity_f64(item_group, item_idx)? * used;
+
+                observer.on_promotion_constraint(key, "basket exclusion usage", &expr, "<=", 0.0);
+
+                state.add_leq_constraint(expr, 0.0);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Allow at most one promotion of each basket exclusion pair to be used.
+    pub(crate) fn add_constraints(
+        &self,
+        rules: &ExclusionRules,
+        state: &mut ILPState,
+        observer: &mut dyn ILPObserver,
+    ) {
+        for (first, second) in rules.basket_pairs() {
+            let (Some(&first_used), Some(&second_used)) =
+                (self.used.get(&first), self.used.get(&second))
+            else {
+                continue;
+            };
+
+            let expr = Expression::from(first_used) + second_used;
+
+            observer.on_exclusion_constraint(first, second, "basket exclusion", &expr, "<=", 1.0);
+
+            state.add_leq_constraint(expr, 1.0);
+        }
+    }
+}
+
+/// Keep each promotion in `excluded` off the item it is paired with.
+///
+/// Used for items that were already redeemed in a promotion the paired one cannot
+/// be combined with.
+pub(crate) fn exclude_items(
+    instances: &PromotionInstances<'_>,
+    excluded: &[(usize, PromotionKey)],
+    state: &mut ILPState,
+    observer: &mut dyn ILPObserver,
+) {
+    for &(item_idx, key) in excluded {
+        for instance in instances
+            .iter()
+            .filter(|instance| instance.promotion_key() == key)
+        {
+            let participation = instance.add_item_presence_term(Expression::default(), item_idx);
+
+            if !has_terms(&participation) {
+                continue;
+            }
+
+            observer.on_promotion_constraint(key, "item exclusion", &participation, "=", 0.0);
+
+            state.add_eq_constraint(participation, 0.0);
+        }
+    }
+}
+
+fn has_terms(expr: &Expression) -> bool {
+    expr.linear_coefficients().next().is_some()
+}
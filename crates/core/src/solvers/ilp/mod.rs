@@ -1,39 +1,69 @@
 //! ILP Solver
 
+use std::{fmt, ops::Range};
+
 use good_lp::{
-    Expression, IntoAffineExpression, ProblemVariables, Solution, SolverModel, Variable,
-    solvers::microlp::microlp as default_solver, variable,
+    Constraint, Expression, IntoAffineExpression, ProblemVariables, ResolutionError, Solution,
+    Variable, VariableDefinition, variable,
 };
 use rusty_money::{Money, iso::Currency};
-use smallvec::{SmallVec, smallvec};
+use smallvec::SmallVec;
 
 use crate::{
-    items::groups::ItemGroup,
-    promotions::{Promotion, redemptions::PromotionRedemption},
+    discounts::rounding::DEFAULT_ROUNDING,
+    items::{Item, groups::ItemGroup},
+    promotions::{
+        Promotion, PromotionKey, budget::BudgetPools, exclusion::ExclusionRules,
+        funding::attribute_funding, redemptions::PromotionRedemption,
+    },
     solvers::{
-        Solver, SolverError, SolverResult,
+        SolutionQuality, Solver, SolverError, SolverResult,
+        greedy::GreedySolver,
         ilp::{
-            promotions::PromotionInstances,
+            backend::StoredSolution,
+            budget_pools::BudgetPoolUsage,
+            cache::{PreviousSolution, SolveCache},
+            exclusions::ExclusionUsage,
+            options::SolveRun,
+            promotions::{DetachedInstance, PromotionInstances},
+            stability::StabilityScope,
             state::{ConstraintRelation, ILPConstraint},
         },
     },
 };
 
+pub mod backend;
+pub(crate) mod budget_pools;
+pub(crate) mod cache;
+pub(crate) mod decomposition;
+pub(crate) mod exclusions;
+pub mod objective;
 pub mod observer;
+pub mod options;
 pub(crate) mod promotions;
 pub mod renderers;
+pub mod stability;
 pub(crate) mod state;
+pub mod tie_break;
 
+pub use backend::SolverBackend;
+pub use cache::CacheStats;
+pub use objective::ObjectiveMode;
 pub use observer::{ILPObserver, NoopObserver};
-pub use promotions::{ILPPromotion, ILPPromotionVars, PromotionVars, i64_to_f64_exact};
+pub use options::SolverOptions;
+pub use promotions::{
+    ILPPromotion, ILPPromotionVars, ItemSignature, PriceOutcomes, PromotionVars, i64_to_f64_exact,
+};
+pub use stability::{Allocation, Stability};
 pub use state::ILPState;
+pub use tie_break::{TieBreakPolicy, TieBreakRule};
 
 /// Binary threshold for determining truthiness
 pub const BINARY_THRESHOLD: f64 = 0.5;
 
 type ItemIndexList = SmallVec<[usize; 10]>;
-type ItemUsageFlags = SmallVec<[bool; 10]>;
-type AppliedPromotionState<'a> = (ItemIndexList, ItemUsageFlags, Money<'a, Currency>);
+type ItemUnitsRemaining = SmallVec<[u32; 10]>;
+type AppliedPromotionState<'a> = (ItemIndexList, ItemUnitsRemaining, Money<'a, Currency>);
 type FullPriceState<'a> = (ItemIndexList, Money<'a, Currency>);
 
 struct BuiltILPFormulation<'a> {
@@ -51,6 +81,50 @@ struct BuiltILPFormulation<'a> {
 
     /// Compiled promotion runtimes bound to the variables above.
     promotion_instances: PromotionInstances<'a>,
+
+    /// Combined usage of the shared budget pools drawn on by the promotions.
+    budget_pool_usage: BudgetPoolUsage,
+}
+
+/// A built formulation with its promotions' variables detached, so the model can
+/// be set up again without formulating the promotions again: for each pass after
+/// the first, and from a [`SolveCache`] for later solves of the same items.
+#[derive(Clone)]
+pub(crate) struct DetachedFormulation {
+    pb: ProblemVariables,
+    cost: Expression,
+    item_presence: SmallVec<[Variable; 10]>,
+
+    /// Recorded constraints, with the budget pool rows bounded by the balances
+    /// the formulation was built with
+    constraints: Vec<ILPConstraint>,
+    instances: SmallVec<[DetachedInstance; 5]>,
+    budget_pool_usage: BudgetPoolUsage,
+
+    /// Positions of the budget pool rows in `constraints`
+    pool_rows: Range<usize>,
+}
+
+impl fmt::Debug for DetachedFormulation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DetachedFormulation")
+            .field("pb", &format!("[{} variables]", self.pb.len()))
+            .field(
+                "constraints",
+                &format!("[{} constraints]", self.constraints.len()),
+            )
+            .field("instances", &self.instances)
+            .field("pool_rows", &self.pool_rows)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A solved model, with the first pass a later solve of it can reuse.
+struct SolvedModel<'b> {
+    result: SolverResult<'b>,
+
+    /// Solution of the first pass, if it was proven optimal
+    first_pass: Option<PreviousSolution>,
 }
 
 /// Solver using Integer Linear Programming (ILP)
@@ -99,14 +173,158 @@ impl ILPSolver {
         let promotion_refs: SmallVec<[&dyn ILPPromotion; 5]> =
             promotions.iter().map(AsRef::as_ref).collect();
 
-        Self::solve_internal(&promotion_refs, item_group, observer)
+        Self::solve_internal(
+            &promotion_refs,
+            item_group,
+            &mut BudgetPools::default(),
+            observer,
+            SolveRun::default(),
+            None,
+        )
+    }
+
+    /// Solve with shared budget pools, deducting the solution's usage from them.
+    ///
+    /// Promotions drawing on a pool are jointly limited by the pool's remaining
+    /// redemptions and discount value. After solving, `pools` holds what is left,
+    /// so it can be threaded through later solves that share the same pools.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SolverError`] if the solver encounters an error, or
+    /// [`SolverError::UnknownBudgetPool`] if a promotion draws on a pool missing
+    /// from `pools`.
+    pub fn solve_with_budget_pools<'b>(
+        promotions: &[Promotion<'_>],
+        item_group: &ItemGroup<'b>,
+        pools: &mut BudgetPools<'_>,
+        observer: &mut dyn ILPObserver,
+    ) -> Result<SolverResult<'b>, SolverError> {
+        let promotion_refs: SmallVec<[&dyn ILPPromotion; 5]> =
+            promotions.iter().map(AsRef::as_ref).collect();
+
+        Self::solve_internal(
+            &promotion_refs,
+            item_group,
+            pools,
+            observer,
+            SolveRun::default(),
+            None,
+        )
+    }
+
+    /// Solve with shared budget pools using a specific MILP backend.
+    ///
+    /// Same as [`solve_with_budget_pools()`](Self::solve_with_budget_pools), but the
+    /// formulation is handed to `backend` instead of the default engine. Every
+    /// backend finds the same optimal total.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SolverError`] if the solver encounters an error, or
+    /// [`SolverError::UnknownBudgetPool`] if a promotion draws on a pool missing
+    /// from `pools`.
+    pub fn solve_with_backend<'b>(
+        promotions: &[Promotion<'_>],
+        item_group: &ItemGroup<'b>,
+        pools: &mut BudgetPools<'_>,
+        observer: &mut dyn ILPObserver,
+        backend: SolverBackend,
+    ) -> Result<SolverResult<'b>, SolverError> {
+        Self::solve_with_options(
+            promotions,
+            item_group,
+            pools,
+            observer,
+            &SolverOptions::default().with_backend(backend),
+        )
+    }
+
+    /// Solve with shared budget pools under the given solver options.
+    ///
+    /// With a time limit the solve never blocks for much longer than the limit:
+    /// the best feasible allocation found in time is returned instead, or every
+    /// item at full price if none was found. Either way the result's
+    /// [`quality`](SolverResult::quality) records that it is not proven optimal,
+    /// and pools are only debited by the allocation actually returned.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SolverError`] if the solver encounters an error, or
+    /// [`SolverError::UnknownBudgetPool`] if a promotion draws on a pool missing
+    /// from `pools`.
+    pub fn solve_with_options<'b>(
+        promotions: &[Promotion<'_>],
+        item_group: &ItemGroup<'b>,
+        pools: &mut BudgetPools<'_>,
+        observer: &mut dyn ILPObserver,
+        options: &SolverOptions,
+    ) -> Result<SolverResult<'b>, SolverError> {
+        Self::solve_run(
+            promotions,
+            item_group,
+            pools,
+            observer,
+            options.start(),
+            None,
+        )
+    }
+
+    /// Re-price with a preference for keeping a previous allocation.
+    ///
+    /// Same as [`solve_with_options()`](Self::solve_with_options), but once the
+    /// cheapest total is known the allocation is chosen to keep as many units as
+    /// possible on the promotions `stability` records for them, at a total at most
+    /// its threshold above the cheapest. Items are identified by their index in
+    /// `item_group`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SolverError`] if the solver encounters an error, or
+    /// [`SolverError::UnknownBudgetPool`] if a promotion draws on a pool missing
+    /// from `pools`.
+    pub fn solve_with_stability<'b>(
+        promotions: &[Promotion<'_>],
+        item_group: &ItemGroup<'b>,
+        pools: &mut BudgetPools<'_>,
+        options: &SolverOptions,
+        stability: &Stability,
+    ) -> Result<SolverResult<'b>, SolverError> {
+        let run = options
+            .start()
+            .with_stability(Some(StabilityScope::new(stability)));
+
+        Self::solve_run(promotions, item_group, pools, &mut NoopObserver, run, None)
+    }
+
+    /// Solve as part of a run whose clock is already ticking.
+    ///
+    /// With a `cache`, models solved before are answered from it.
+    pub(crate) fn solve_run<'b>(
+        promotions: &[Promotion<'_>],
+        item_group: &ItemGroup<'b>,
+        pools: &mut BudgetPools<'_>,
+        observer: &mut dyn ILPObserver,
+        run: SolveRun<'_>,
+        cache: Option<&mut SolveCache<'b>>,
+    ) -> Result<SolverResult<'b>, SolverError> {
+        let promotion_refs: SmallVec<[&dyn ILPPromotion; 5]> =
+            promotions.iter().map(AsRef::as_ref).collect();
+
+        Self::solve_internal(&promotion_refs, item_group, pools, observer, run, cache)
     }
 
     /// Internal solve implementation that supports an observer.
+    ///
+    /// When the observer allows it, the item group is first split into components
+    /// that no promotion or budget pool spans, and each is solved as its own model.
     fn solve_internal<'b>(
         promotions: &[&dyn ILPPromotion],
         item_group: &ItemGroup<'b>,
+        pools: &mut BudgetPools<'_>,
         observer: &mut dyn ILPObserver,
+        run: SolveRun<'_>,
+        mut cache: Option<&mut SolveCache<'b>>,
     ) -> Result<SolverResult<'b>, SolverError> {
         // Return early if the item group is empty
         if item_group.is_empty() {
@@ -115,16 +333,206 @@ impl ILPSolver {
                 unaffected_items: SmallVec::with_capacity(0),
                 total: Money::from_minor(0, item_group.currency()),
                 promotion_redemptions: SmallVec::with_capacity(0),
+                quality: SolutionQuality::Optimal,
             });
         }
 
+        if !observer.allows_decomposition() {
+            return Self::solve_model(promotions, item_group, pools, observer, run, None);
+        }
+
+        let components = decomposition::components(promotions, item_group, cache.as_deref_mut());
+
+        if components.len() <= 1 {
+            return Self::solve_cached(promotions, item_group, pools, observer, run, cache);
+        }
+
+        let mut results = Vec::with_capacity(components.len());
+
+        for component in &components {
+            let component_group = component.item_group(item_group)?;
+
+            // Items no promotion can touch are simply bought at full price.
+            if component.promotions.is_empty() {
+                results.push(full_price_result(&component_group)?);
+
+                continue;
+            }
+
+            let component_promotions: SmallVec<[&dyn ILPPromotion; 5]> = component
+                .promotions
+                .iter()
+                .filter_map(|&promotion_idx| promotions.get(promotion_idx).copied())
+                .collect();
+
+            let basket_idxs = run
+                .stability
+                .map(|stability| stability.subset(&component.items))
+                .unwrap_or_default();
+
+            // Exclusions on the component's items, renumbered within the component.
+            let excluded: SmallVec<[(usize, PromotionKey); 4]> = run
+                .excluded
+                .iter()
+                .filter_map(|&(item_idx, key)| {
+                    component
+                        .items
+                        .binary_search(&item_idx)
+                        .ok()
+                        .map(|local_idx| (local_idx, key))
+                })
+                .collect();
+
+            let component_run = run
+                .with_stability(
+                    run.stability
+                        .map(|stability| stability.with_basket_idxs(&basket_idxs)),
+                )
+                .with_excluded(&excluded);
+
+            results.push(Self::solve_cached(
+                &component_promotions,
+                &component_group,
+                pools,
+                observer,
+                component_run,
+                cache.as_deref_mut(),
+            )?);
+        }
+
+        decomposition::merge_results(promotions, item_group, &components, &results)
+    }
+
+    /// Solve a single model, or take its solution from `cache` if it was solved before.
+    ///
+    /// The solution of a model drawing on shared budget pools is not reused, since
+    /// the pools' balances are part of its input, and neither is that of a model
+    /// re-priced with a stability preference, since the previous allocation is,
+    /// or of a model keeping items off excluded promotions, since the items'
+    /// history is. Such models still reuse their formulation and first pass from
+    /// `cache` (see [`solve_model()`](Self::solve_model)).
+    fn solve_cached<'b>(
+        promotions: &[&dyn ILPPromotion],
+        item_group: &ItemGroup<'b>,
+        pools: &mut BudgetPools<'_>,
+        observer: &mut dyn ILPObserver,
+        run: SolveRun<'_>,
+        cache: Option<&mut SolveCache<'b>>,
+    ) -> Result<SolverResult<'b>, SolverError> {
+        let Some(cache) = cache else {
+            return Self::solve_model(promotions, item_group, pools, observer, run, None);
+        };
+
+        let reusable = run.stability.is_none()
+            && run.excluded.is_empty()
+            && promotions
+                .iter()
+                .all(|promotion| promotion.budget_pools().is_empty());
+
+        if reusable && let Some(result) = cache.solution(promotions, item_group, &run) {
+            return Ok(result);
+        }
+
+        let result = Self::solve_model(promotions, item_group, pools, observer, run, Some(cache))?;
+
+        if reusable {
+            cache.insert_solution(promotions, item_group, &run, &result);
+        }
+
+        Ok(result)
+    }
+
+    /// Build and solve a single model covering the whole item group.
+    ///
+    /// With a `cache`, a model formulated before is set up from its cached
+    /// formulation instead of being formulated again. If its first pass was
+    /// proven optimal under the same objective, backend and budget pool balances,
+    /// that solution is taken as the first pass's, since the backend would find it
+    /// again; otherwise it is given to the backend as a starting point.
+    fn solve_model<'b>(
+        promotions: &[&dyn ILPPromotion],
+        item_group: &ItemGroup<'b>,
+        pools: &mut BudgetPools<'_>,
+        observer: &mut dyn ILPObserver,
+        run: SolveRun<'_>,
+        cache: Option<&mut SolveCache<'b>>,
+    ) -> Result<SolverResult<'b>, SolverError> {
+        let Some(cache) = cache else {
+            let formulation =
+                DetachedFormulation::build(promotions, item_group, pools, observer, run)?;
+
+            return Self::solve_formulation(
+                promotions,
+                item_group,
+                pools,
+                observer,
+                run,
+                &formulation,
+                None,
+            )
+            .map(|solved| solved.result);
+        };
+
+        if let Some((formulation, previous)) = cache.formulation(promotions, item_group, &run) {
+            let solved = Self::solve_formulation(
+                promotions,
+                item_group,
+                pools,
+                observer,
+                run,
+                formulation,
+                previous,
+            )?;
+
+            cache.update_formulation(promotions, item_group, &run, solved.first_pass);
+
+            return Ok(solved.result);
+        }
+
+        let formulation = DetachedFormulation::build(promotions, item_group, pools, observer, run)?;
+        let solved = Self::solve_formulation(
+            promotions,
+            item_group,
+            pools,
+            observer,
+            run,
+            &formulation,
+            None,
+        )?;
+
+        cache.insert_formulation(promotions, item_group, &run, formulation, solved.first_pass);
+
+        Ok(solved.result)
+    }
+
+    /// Solve the model `formulation` describes, starting from `previous` if the
+    /// model was solved before.
+    fn solve_formulation<'b>(
+        promotions: &[&dyn ILPPromotion],
+        item_group: &ItemGroup<'b>,
+        pools: &mut BudgetPools<'_>,
+        observer: &mut dyn ILPObserver,
+        run: SolveRun<'_>,
+        formulation: &DetachedFormulation,
+        previous: Option<&PreviousSolution>,
+    ) -> Result<SolvedModel<'b>, SolverError> {
+        let pool_limits = formulation.pool_limits(pools)?;
+
+        // Under the same limits the model is the one solved before, and the backend
+        // would find the same solution again.
+        let current = previous.filter(|previous| previous.is_current(&run, &pool_limits));
+        let start = previous
+            .filter(|_previous| current.is_none())
+            .map(PreviousSolution::solution);
+
         let BuiltILPFormulation {
             pb,
             cost,
             item_presence,
             constraints,
             promotion_instances,
-        } = build_ilp_formulation(promotions, item_group, observer)?;
+            budget_pool_usage,
+        } = formulation.model(promotions, &pool_limits, start)?;
 
         // Promotions may optionally contribute a secondary tie-break objective.
         // We check whether any non-zero linear terms were emitted so we can skip
@@ -136,14 +544,21 @@ impl ILPSolver {
                 .next()
                 .is_some();
 
-        // Keep a clone of the exact first-pass objective so we can evaluate the
-        // solved optimum value before consuming `cost` in the model builder.
-        let primary_cost = cost.clone();
-        let mut model = pb.minimise(cost).using(default_solver);
+        // The first pass forms the most entitled redemptions under a least-generous
+        // objective, and otherwise optimises the total directly. Keep a clone of its
+        // objective so the solved optimum can be evaluated after `pb` is consumed.
+        let entitled = run.objective.entitled_redemptions(&promotion_instances)?;
+        let primary_objective = match &entitled {
+            Some(entitled) => -entitled.clone(),
+            None => run.objective.signed_cost(cost),
+        };
+        let mut model_constraints = Vec::with_capacity(item_group.len() + constraints.len());
 
         ensure_presence_vars_len(item_presence.len(), item_group.len())?;
 
         // Ensure each item is purchased exactly once (either full price OR via one promotion).
+        // For quantity lines this holds per unit: the counts across all options sum to the
+        // line quantity.
         //
         // This prevents items from being:
         // - Omitted from the checkout entirely
@@ -158,80 +573,499 @@ impl ILPSolver {
             // Notify observer before adding constraint
             observer.on_exclusivity_constraint(item_idx, &constraint_expr);
 
-            model = model.with(constraint_expr.eq(1));
+            model_constraints.push(constraint_expr.eq(item_quantity_f64(item_group, item_idx)?));
         }
 
         // Add all recorded promotion constraints.
-        model = apply_recorded_constraints(model, constraints);
-
-        // Pass 1: optimize the real business objective (total final basket value).
-        let primary_solution = model.solve()?;
+        model_constraints.extend(recorded_constraints(constraints));
+
+        // Pass 1: optimize the real business objective (total final basket value),
+        // or the entitlement if the retailer gives the least discount it can.
+        let primary_solution = if let Some(previous) = current {
+            previous.solution().clone()
+        } else {
+            match run.solve(pb.minimise(primary_objective.clone()), model_constraints) {
+                Ok(solution) => StoredSolution::new(&solution, formulation.variables()),
+                // Nothing feasible was found in time, so fall back to a heuristic.
+                // Every item at full price is always feasible, so infeasibility is
+                // numerical trouble in the backend and is handled the same way.
+                Err(
+                    SolverError::TimeLimitReached
+                    | SolverError::ResolutionError(ResolutionError::Infeasible),
+                ) => {
+                    return fallback_result(promotions, item_group, pools, &run).map(|result| {
+                        SolvedModel {
+                            result,
+                            first_pass: None,
+                        }
+                    });
+                }
+                Err(err) => return Err(err),
+            }
+        };
 
-        if !has_secondary_objective_terms {
-            return build_solver_result(
-                &promotion_instances,
-                &primary_solution,
+        let quality = SolutionQuality::from(primary_solution.status());
+        let first_pass = quality
+            .is_optimal()
+            .then(|| PreviousSolution::new(&run, pool_limits, primary_solution.clone()));
+
+        // A time-limited incumbent leaves no time for the follow-up passes.
+        if quality.is_optimal()
+            && (has_secondary_objective_terms
+                || run.stability.is_some()
+                || !run.tie_break.is_empty()
+                || entitled.is_some())
+        {
+            let primary_optimal_value = primary_solution.eval(&primary_objective);
+
+            // Convert a total optimum back to an integral minor-unit value. The model
+            // is built from integer coefficients/variables, so this should be
+            // integral apart from tiny floating-point noise from the LP backend.
+            let face = match entitled {
+                Some(_) => OptimumFace {
+                    min_entitled: Some(-primary_optimal_value),
+                    ..OptimumFace::default()
+                },
+                None => OptimumFace {
+                    max_cost: Some(objective_value_to_integral_minor_units(
+                        primary_optimal_value,
+                        "primary objective value is non-integral",
+                    )?),
+                    ..OptimumFace::default()
+                },
+            };
+
+            if let Some(result) = Self::solve_follow_up_passes(
+                formulation,
+                promotions,
                 item_group,
-                &item_presence,
-            );
+                pools,
+                run,
+                face,
+                has_secondary_objective_terms,
+            )? {
+                return Ok(SolvedModel { result, first_pass });
+            }
         }
 
-        // Convert the first-pass optimum back to an integral minor-unit value.
-        // The model is built from integer coefficients/variables, so this should
-        // be integral apart from tiny floating-point noise from the LP backend.
-        let primary_optimal_value = objective_value_to_integral_minor_units(
-            primary_solution.eval(&primary_cost),
-            "primary objective value is non-integral",
+        budget_pool_usage.debit(pools, &primary_solution)?;
+
+        let result = build_solver_result(
+            &promotion_instances,
+            &primary_solution,
+            item_group,
+            &item_presence,
+            quality,
         )?;
-        let primary_optimal_f64 = i64_to_f64_exact(primary_optimal_value).ok_or(
-            SolverError::MinorUnitsNotRepresentable(primary_optimal_value),
+
+        Ok(SolvedModel { result, first_pass })
+    }
+
+    /// Run the least-generous, stability, tie-break rule and promotion tie-break
+    /// passes after the first pass.
+    ///
+    /// Each pass is confined to the optimum face of the passes before it, starting
+    /// from `face`. Returns `None` if no pass produced a solution; the first pass's
+    /// solution is then used as is. Pools are only debited by the allocation
+    /// returned.
+    fn solve_follow_up_passes<'b, 's>(
+        formulation: &DetachedFormulation,
+        promotions: &[&dyn ILPPromotion],
+        item_group: &ItemGroup<'b>,
+        pools: &mut BudgetPools<'_>,
+        run: SolveRun<'s>,
+        mut face: OptimumFace<'s>,
+        tie_break: bool,
+    ) -> Result<Option<SolverResult<'b>>, SolverError> {
+        let mut settled = None;
+
+        // Keep the entitlement while giving the least discount.
+        let max_cost = if let Some(max_cost) = face.max_cost {
+            max_cost
+        } else {
+            let mut cost_pools = pools.clone();
+
+            let Some((result, cost)) = Self::solve_on_face(
+                formulation,
+                promotions,
+                item_group,
+                &mut cost_pools,
+                run,
+                &face,
+                |model| Ok(Some(model.cost.clone())),
+            )?
+            else {
+                return Ok(None);
+            };
+
+            settled = Some((result, cost_pools));
+
+            objective_value_to_integral_minor_units(
+                cost,
+                "least-generous objective value is non-integral",
+            )?
+        };
+
+        face.max_cost = Some(max_cost);
+
+        if let Some(stability) = run.stability {
+            let mut stable_pools = pools.clone();
+
+            let stable_face = OptimumFace {
+                max_cost: Some(max_cost.saturating_add(stability.stability.threshold())),
+                ..face.clone()
+            };
+
+            if let Some((result, cost, kept)) = Self::solve_stable(
+                formulation,
+                promotions,
+                item_group,
+                &mut stable_pools,
+                run,
+                stability,
+                &stable_face,
+            )? {
+                face.max_cost = Some(cost);
+                face.min_kept = Some((stability, kept));
+
+                settled = Some((result, stable_pools));
+            }
+        }
+
+        for rule in run.tie_break.rules() {
+            let mut rule_pools = pools.clone();
+
+            if let Some((result, best)) = Self::solve_on_face(
+                formulation,
+                promotions,
+                item_group,
+                &mut rule_pools,
+                run,
+                &face,
+                |model| {
+                    Ok(Some(rule.add_objective_terms(
+                        Expression::default(),
+                        &model.promotion_instances,
+                        item_group,
+                    )))
+                },
+            )? {
+                face.rule_bounds.push((rule, best));
+
+                settled = Some((result, rule_pools));
+            }
+        }
+
+        if tie_break {
+            let mut tie_break_pools = pools.clone();
+
+            // Choose a deterministic/cheaper branch profile among equal-cost solutions.
+            if let Some((result, _secondary)) = Self::solve_on_face(
+                formulation,
+                promotions,
+                item_group,
+                &mut tie_break_pools,
+                run,
+                &face,
+                |model| {
+                    model
+                        .promotion_instances
+                        .add_secondary_objective_terms(Expression::default(), item_group)
+                        .map(Some)
+                },
+            )? {
+                *pools = tie_break_pools;
+
+                return Ok(Some(result));
+            }
+        }
+
+        Ok(settled.map(|(result, settled_pools)| {
+            *pools = settled_pools;
+
+            result
+        }))
+    }
+
+    /// Re-solve within the stability threshold of the primary optimum, keeping as
+    /// many units as possible on the options the previous allocation gave them,
+    /// and then finding the cheapest total that keeps them.
+    ///
+    /// `face` bounds the total by the threshold. Returns the result with its total
+    /// (signed as the objective minimises it) in minor units and the number of
+    /// units kept, or `None` if the time limit passes first or the backend cannot
+    /// reproduce a solution within the threshold.
+    fn solve_stable<'b>(
+        formulation: &DetachedFormulation,
+        promotions: &[&dyn ILPPromotion],
+        item_group: &ItemGroup<'b>,
+        pools: &mut BudgetPools<'_>,
+        run: SolveRun<'_>,
+        stability: StabilityScope<'_>,
+        face: &OptimumFace<'_>,
+    ) -> Result<Option<(SolverResult<'b>, i64, f64)>, SolverError> {
+        let mut model = build_follow_up_model(formulation, promotions, item_group, pools, run)?;
+
+        face.constrain(&mut model, item_group)?;
+
+        let (kept, kept_constraints) = stability.kept_units(
+            &mut model.pb,
+            item_group,
+            &model.item_presence,
+            &model.promotion_instances,
         )?;
 
-        // Pass 2 uses an identical formulation but with:
-        // 1) a fixed equality `primary_cost == optimum_from_pass_1`
-        // 2) a secondary objective that only breaks ties among pass-1 optima.
-        //
-        // We rebuild instead of mutating the solved model in-place to keep the
-        // construction path identical and avoid backend-specific model mutation
-        // assumptions.
-        let mut secondary_observer = NoopObserver;
-        let BuiltILPFormulation {
+        let FollowUpModel {
             pb,
             cost,
             item_presence,
-            constraints,
             promotion_instances,
-        } = build_ilp_formulation(promotions, item_group, &mut secondary_observer)?;
+            budget_pool_usage,
+            mut constraints,
+            ..
+        } = model;
+
+        constraints.extend(kept_constraints);
+
+        // Totals on the face differ by at most the threshold, so weighting each kept
+        // unit above it ranks solutions by units kept first and total second.
+        let kept_weight = stability.stability.threshold().saturating_add(1);
+        let kept_weight = i64_to_f64_exact(kept_weight)
+            .ok_or(SolverError::MinorUnitsNotRepresentable(kept_weight))?;
+
+        let objective = cost.clone() - kept.clone() * kept_weight;
+
+        let solution = match run.solve(pb.minimise(objective), constraints) {
+            Ok(solution) => solution,
+            // The primary solution satisfies every constraint here too, so
+            // infeasibility is numerical trouble in the backend.
+            Err(
+                SolverError::TimeLimitReached
+                | SolverError::ResolutionError(ResolutionError::Infeasible),
+            ) => return Ok(None),
+            Err(err) => return Err(err),
+        };
 
-        let secondary_objective =
-            promotion_instances.add_secondary_objective_terms(Expression::default(), item_group)?;
-        let mut secondary_model = pb.minimise(secondary_objective).using(default_solver);
+        let stable_cost = objective_value_to_integral_minor_units(
+            solution.eval(&cost),
+            "stable objective value is non-integral",
+        )?;
 
-        ensure_presence_vars_len(item_presence.len(), item_group.len())?;
+        let kept_units = solution.eval(&kept);
 
-        for (item_idx, z_i) in item_presence.iter().copied().enumerate() {
-            let constraint_expr =
-                promotion_instances.add_item_presence_term(Expression::from(z_i), item_idx);
+        budget_pool_usage.debit(pools, &solution)?;
+
+        let result = build_solver_result(
+            &promotion_instances,
+            &solution,
+            item_group,
+            &item_presence,
+            SolutionQuality::Optimal,
+        )?;
+
+        Ok(Some((result, stable_cost, kept_units)))
+    }
+
+    /// Re-solve on the optimum face of the earlier passes, minimising the measure
+    /// `measure` builds for the rebuilt model.
+    ///
+    /// The pass sets up the first pass's formulation again, with bounds pinning
+    /// the optimum of the earlier passes, rather than mutating the solved model in
+    /// place, to avoid backend-specific model mutation assumptions.
+    ///
+    /// Returns the result with the best measure, or `None` if the measure is the
+    /// same for every allocation, the time limit passes first, or the backend
+    /// cannot reproduce the earlier optimum; the earlier solution is then used as
+    /// is.
+    fn solve_on_face<'b>(
+        formulation: &DetachedFormulation,
+        promotions: &[&dyn ILPPromotion],
+        item_group: &ItemGroup<'b>,
+        pools: &mut BudgetPools<'_>,
+        run: SolveRun<'_>,
+        face: &OptimumFace<'_>,
+        measure: impl FnOnce(&FollowUpModel<'_>) -> Result<Option<Expression>, SolverError>,
+    ) -> Result<Option<(SolverResult<'b>, f64)>, SolverError> {
+        let mut model = build_follow_up_model(formulation, promotions, item_group, pools, run)?;
+
+        let Some(measure) = measure(&model)? else {
+            return Ok(None);
+        };
 
-            secondary_observer.on_exclusivity_constraint(item_idx, &constraint_expr);
-            secondary_model = secondary_model.with(constraint_expr.eq(1));
+        // Every allocation on the face measures the same, so there is nothing to
+        // choose between.
+        if IntoAffineExpression::linear_coefficients(&measure)
+            .next()
+            .is_none()
+        {
+            return Ok(None);
         }
 
-        secondary_model = apply_recorded_constraints(secondary_model, constraints);
-        // Lexicographic guardrail: force pass 2 to stay on the primary optimum face.
-        secondary_model = secondary_model.with(cost.eq(primary_optimal_f64));
+        face.constrain(&mut model, item_group)?;
+
+        let FollowUpModel {
+            pb,
+            item_presence,
+            promotion_instances,
+            budget_pool_usage,
+            constraints,
+            ..
+        } = model;
+
+        let solution = match run.solve(pb.minimise(measure.clone()), constraints) {
+            Ok(solution) => solution,
+            // The earlier solution satisfies every constraint here too, so
+            // infeasibility is numerical trouble in the backend rather than a real
+            // conflict.
+            Err(
+                SolverError::TimeLimitReached
+                | SolverError::ResolutionError(ResolutionError::Infeasible),
+            ) => return Ok(None),
+            Err(err) => return Err(err),
+        };
+
+        let best = solution.eval(&measure);
 
-        // Pass 2: choose a deterministic/cheaper branch profile among equal-cost
-        // pass-1 solutions.
-        let secondary_solution = secondary_model.solve()?;
+        budget_pool_usage.debit(pools, &solution)?;
 
-        build_solver_result(
+        let result = build_solver_result(
             &promotion_instances,
-            &secondary_solution,
+            &solution,
             item_group,
             &item_presence,
-        )
+            SolutionQuality::Optimal,
+        )?;
+
+        Ok(Some((result, best)))
+    }
+}
+
+/// A model set up again for a pass after the first, with every constraint of the first.
+struct FollowUpModel<'a> {
+    pb: ProblemVariables,
+
+    /// Total in the direction the objective minimises it
+    cost: Expression,
+    item_presence: SmallVec<[Variable; 10]>,
+    promotion_instances: PromotionInstances<'a>,
+    budget_pool_usage: BudgetPoolUsage,
+    constraints: Vec<Constraint>,
+
+    /// Redemptions the customer is entitled to, under a least-generous objective
+    entitled: Option<Expression>,
+}
+
+/// Bounds keeping a later pass on the optimum face of the passes before it.
+#[derive(Debug, Clone, Default)]
+struct OptimumFace<'s> {
+    /// Highest total allowed in minor units, signed as the objective minimises
+    /// it, once known
+    max_cost: Option<i64>,
+
+    /// Fewest entitled redemptions, under a least-generous objective
+    min_entitled: Option<f64>,
+
+    /// Fewest units to keep on their previous option, if a stability pass ran
+    min_kept: Option<(StabilityScope<'s>, f64)>,
+
+    /// Best measure found by each tie-break rule pass so far
+    rule_bounds: SmallVec<[(TieBreakRule, f64); 3]>,
+}
+
+impl OptimumFace<'_> {
+    /// Add the face's bounds to `model`.
+    fn constrain(
+        &self,
+        model: &mut FollowUpModel<'_>,
+        item_group: &ItemGroup<'_>,
+    ) -> Result<(), SolverError> {
+        // Lexicographic guardrail. Costs are whole minor units and the earlier
+        // passes found the bound, so half a unit of slack pins the same face without
+        // an exact float equality, which the solver can reject as infeasible through
+        // rounding. Redemptions and kept units are whole too.
+        if let Some(max_cost) = self.max_cost {
+            let max_cost = i64_to_f64_exact(max_cost)
+                .ok_or(SolverError::MinorUnitsNotRepresentable(max_cost))?;
+
+            model
+                .constraints
+                .push(model.cost.clone().leq(max_cost + 0.5));
+        }
+
+        if let (Some(min_entitled), Some(entitled)) = (self.min_entitled, &model.entitled) {
+            model
+                .constraints
+                .push(entitled.clone().geq(min_entitled - 0.5));
+        }
+
+        if let Some((stability, min_kept)) = self.min_kept {
+            let (kept, kept_constraints) = stability.kept_units(
+                &mut model.pb,
+                item_group,
+                &model.item_presence,
+                &model.promotion_instances,
+            )?;
+
+            model.constraints.extend(kept_constraints);
+            model.constraints.push(kept.geq(min_kept - 0.5));
+        }
+
+        // Rule measures count whole units or redemptions, scaled by whole priorities.
+        for &(rule, best) in &self.rule_bounds {
+            let measure = rule.add_objective_terms(
+                Expression::default(),
+                &model.promotion_instances,
+                item_group,
+            );
+
+            model.constraints.push(measure.leq(best + 0.5));
+        }
+
+        Ok(())
+    }
+}
+
+/// Set the first pass's formulation up again for a follow-up pass.
+fn build_follow_up_model<'a>(
+    formulation: &DetachedFormulation,
+    promotions: &[&'a dyn ILPPromotion],
+    item_group: &ItemGroup<'_>,
+    pools: &BudgetPools<'_>,
+    run: SolveRun<'_>,
+) -> Result<FollowUpModel<'a>, SolverError> {
+    let BuiltILPFormulation {
+        pb,
+        cost,
+        item_presence,
+        constraints,
+        promotion_instances,
+        budget_pool_usage,
+    } = formulation.model(promotions, &formulation.pool_limits(pools)?, None)?;
+
+    let mut model_constraints = Vec::with_capacity(item_group.len() + constraints.len() + 1);
+
+    ensure_presence_vars_len(item_presence.len(), item_group.len())?;
+
+    for (item_idx, z_i) in item_presence.iter().copied().enumerate() {
+        let constraint_expr =
+            promotion_instances.add_item_presence_term(Expression::from(z_i), item_idx);
+
+        model_constraints.push(constraint_expr.eq(item_quantity_f64(item_group, item_idx)?));
     }
+
+    model_constraints.extend(recorded_constraints(constraints));
+
+    let entitled = run.objective.entitled_redemptions(&promotion_instances)?;
+
+    Ok(FollowUpModel {
+        pb,
+        cost: run.objective.signed_cost(cost),
+        item_presence,
+        promotion_instances,
+        budget_pool_usage,
+        constraints: model_constraints,
+        entitled,
+    })
 }
 
 impl Solver for ILPSolver {
@@ -244,56 +1078,223 @@ impl Solver for ILPSolver {
         let promotion_refs: SmallVec<[&dyn ILPPromotion; 5]> =
             promotions.iter().map(AsRef::as_ref).collect();
 
-        Self::solve_internal(&promotion_refs, item_group, &mut observer)
+        Self::solve_internal(
+            &promotion_refs,
+            item_group,
+            &mut BudgetPools::default(),
+            &mut observer,
+            SolveRun::default(),
+            None,
+        )
     }
 }
 
-fn apply_recorded_constraints<S: SolverModel>(mut model: S, constraints: Vec<ILPConstraint>) -> S {
-    for constraint in constraints {
-        model = match constraint.relation {
-            ConstraintRelation::Eq => model.with(constraint.lhs.eq(constraint.rhs)),
-            ConstraintRelation::Leq => model.with(constraint.lhs.leq(constraint.rhs)),
-            ConstraintRelation::Geq => model.with(constraint.lhs.geq(constraint.rhs)),
-        };
+/// Variable definition for the number of units of an item line taken by one option.
+///
+/// Single-unit lines keep a binary variable. Larger lines use an integer count
+/// bounded by the line quantity, so a line of identical units needs a single
+/// variable per option rather than one per unit.
+pub fn item_units_variable(quantity: u32) -> VariableDefinition {
+    if quantity <= 1 {
+        variable().binary()
+    } else {
+        variable().integer().min(0).max(quantity)
+    }
+}
+
+/// Read the number of units selected by a count variable from a solution.
+///
+/// Solvers return floats, so the value is rounded to the nearest whole unit to
+/// tolerate tiny numerical noise.
+pub fn solution_units<S: Solution + ?Sized>(solution: &S, var: Variable) -> u32 {
+    let value = solution.value(var).round();
+
+    if value <= 0.0 {
+        return 0;
+    }
+
+    if value >= f64::from(u32::MAX) {
+        return u32::MAX;
     }
 
-    model
+    #[expect(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        reason = "value is rounded and checked to be within the u32 range"
+    )]
+    let units = value as u32;
+
+    units
 }
 
-fn build_ilp_formulation<'a>(
-    promotions: &[&'a dyn ILPPromotion],
+/// Unit quantity of an item line as an exact solver coefficient.
+pub(crate) fn item_quantity_f64(
     item_group: &ItemGroup<'_>,
-    observer: &mut dyn ILPObserver,
-) -> Result<BuiltILPFormulation<'a>, SolverError> {
-    // Build the optimization problem using ILPState to manage variables and objective.
-    // The goal is to find the best combination of promotions that minimizes
-    // total item group cost.
-    //
-    // We set up three things:
-    //
-    // 1. Presence variables: each item at full price (baseline option)
-    // 2. Promotion variables: each item with each applicable promotion (discount options)
-    // 3. Constraints: ensure each item is purchased exactly once (baseline full price OR one promotion discount applied)
-    let mut state = ILPState::with_presence_variables_and_observer(item_group, observer)?;
-
-    // Set up all possible promotion choices for the solver to consider.
-    // For each promotion, we create decision variables that let the solver choose
-    // whether to apply that promotion to each eligible item.
-    let promotion_instances =
-        PromotionInstances::from_promotions(promotions, item_group, &mut state, observer)?;
-
-    let (pb, cost, item_presence, constraints) = state.into_parts_with_constraints();
-
-    Ok(BuiltILPFormulation {
-        pb,
-        cost,
-        item_presence,
-        constraints,
-        promotion_instances,
-    })
+    item_idx: usize,
+) -> Result<f64, SolverError> {
+    Ok(f64::from(item_group.get_item(item_idx)?.quantity()))
+}
+
+/// Turn recorded promotion constraints into solver constraints.
+pub(crate) fn recorded_constraints(
+    constraints: Vec<ILPConstraint>,
+) -> impl Iterator<Item = Constraint> {
+    constraints
+        .into_iter()
+        .map(|constraint| match constraint.relation {
+            ConstraintRelation::Eq => constraint.lhs.eq(constraint.rhs),
+            ConstraintRelation::Leq => constraint.lhs.leq(constraint.rhs),
+            ConstraintRelation::Geq => constraint.lhs.geq(constraint.rhs),
+        })
 }
 
-fn objective_value_to_integral_minor_units(
+impl DetachedFormulation {
+    /// Formulate `promotions` over `item_group`, with the budget pool rows bounded
+    /// by `pools`' balances.
+    fn build(
+        promotions: &[&dyn ILPPromotion],
+        item_group: &ItemGroup<'_>,
+        pools: &BudgetPools<'_>,
+        observer: &mut dyn ILPObserver,
+        run: SolveRun<'_>,
+    ) -> Result<Self, SolverError> {
+        // Build the optimization problem using ILPState to manage variables and objective.
+        // The goal is to find the best combination of promotions that minimizes
+        // total item group cost.
+        //
+        // We set up three things:
+        //
+        // 1. Presence variables: each item at full price (baseline option)
+        // 2. Promotion variables: each item with each applicable promotion (discount options)
+        // 3. Constraints: ensure each item is purchased exactly once (baseline full price OR one promotion discount applied)
+        let mut state = ILPState::with_presence_variables_and_observer(item_group, observer)?;
+
+        state.set_rounding(run.rounding);
+
+        // Items kept off some promotions are no longer interchangeable with their copies.
+        if !run.excluded.is_empty() {
+            state.disable_symmetry_breaking();
+        }
+
+        // Set up all possible promotion choices for the solver to consider.
+        // For each promotion, we create decision variables that let the solver choose
+        // whether to apply that promotion to each eligible item.
+        let promotion_instances =
+            PromotionInstances::from_promotions(promotions, item_group, &mut state, observer)?;
+
+        // Promotions sharing a budget pool are limited together, on top of their own budgets.
+        let mut budget_pool_usage = BudgetPoolUsage::default();
+
+        budget_pool_usage.add_instances(&promotion_instances, item_group, pools)?;
+
+        let pool_rows_start = state.constraint_count();
+
+        budget_pool_usage.add_constraints(pools, &mut state, observer)?;
+
+        let pool_rows = pool_rows_start..state.constraint_count();
+
+        // Promotions that cannot be combined are kept apart, on the same items or
+        // across the basket.
+        let exclusion_rules = ExclusionRules::from_promotions(promotions.iter().copied());
+        let mut exclusion_usage = ExclusionUsage::default();
+
+        exclusion_usage.add_instances(
+            &exclusion_rules,
+            &promotion_instances,
+            item_group,
+            &mut state,
+            observer,
+        )?;
+        exclusion_usage.add_constraints(&exclusion_rules, &mut state, observer);
+        exclusions::exclude_items(&promotion_instances, run.excluded, &mut state, observer);
+
+        let (pb, cost, item_presence, constraints) = state.into_parts_with_constraints();
+
+        for (var, definition) in pb.iter_variables_with_def() {
+            observer.on_variable_definition(var, definition);
+        }
+
+        Ok(Self {
+            pb,
+            cost,
+            item_presence,
+            constraints,
+            instances: promotion_instances.detach(),
+            budget_pool_usage,
+            pool_rows,
+        })
+    }
+
+    /// The balances of `pools` bounding the budget pool rows, in row order.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SolverError::MinorUnitsNotRepresentable`] if a balance cannot be
+    /// represented exactly as a solver coefficient.
+    pub(crate) fn pool_limits(
+        &self,
+        pools: &BudgetPools<'_>,
+    ) -> Result<SmallVec<[f64; 4]>, SolverError> {
+        self.budget_pool_usage.limits(pools)
+    }
+
+    /// Set the model up for `promotions`, the promotions it was formulated for,
+    /// with its budget pool rows bounded by `pool_limits`.
+    ///
+    /// Each variable starts from its value in `start`, if given, on backends that
+    /// take a starting point.
+    fn model<'a>(
+        &self,
+        promotions: &[&'a dyn ILPPromotion],
+        pool_limits: &[f64],
+        start: Option<&StoredSolution>,
+    ) -> Result<BuiltILPFormulation<'a>, SolverError> {
+        let mut constraints = self.constraints.clone();
+
+        let pool_rows = constraints
+            .get_mut(self.pool_rows.clone())
+            .filter(|rows| rows.len() == pool_limits.len())
+            .ok_or(SolverError::InvariantViolation {
+                message: "budget pool limits do not match the formulation's pool rows",
+            })?;
+
+        for (row, &limit) in pool_rows.iter_mut().zip(pool_limits) {
+            row.rhs = limit;
+        }
+
+        // Variables are added in the same order, so they keep their handles.
+        let pb = match start {
+            Some(start) => {
+                let mut pb = ProblemVariables::new();
+
+                for (var, definition) in self.pb.iter_variables_with_def() {
+                    pb.add(definition.clone().initial(start.value(var)));
+                }
+
+                pb
+            }
+            None => self.pb.clone(),
+        };
+
+        Ok(BuiltILPFormulation {
+            pb,
+            cost: self.cost.clone(),
+            item_presence: self.item_presence.clone(),
+            constraints,
+            promotion_instances: PromotionInstances::attach(promotions, &self.instances)?,
+            budget_pool_usage: self.budget_pool_usage.clone(),
+        })
+    }
+
+    /// The model's variables.
+    fn variables(&self) -> impl Iterator<Item = Variable> + '_ {
+        self.pb
+            .iter_variables_with_def()
+            .map(|(var, _definition)| var)
+    }
+}
+
+pub(crate) fn objective_value_to_integral_minor_units(
     value: f64,
     non_integral_message: &'static str,
 ) -> Result<i64, SolverError> {
@@ -335,10 +1336,11 @@ fn build_solver_result<'a, 'b, S: Solution>(
     solution: &S,
     item_group: &ItemGroup<'b>,
     item_presence: &[Variable],
+    quality: SolutionQuality,
 ) -> Result<SolverResult<'b>, SolverError> {
     // Translate the solver's decisions back into business terms: which items got
     // discounted, by which promotions, and what their final prices are.
-    let mut used_items: ItemUsageFlags = smallvec![false; item_group.len()];
+    let mut remaining_units: ItemUnitsRemaining = item_group.iter().map(Item::quantity).collect();
     let mut total = Money::from_minor(0, item_group.currency());
     let mut promotion_redemptions: SmallVec<[PromotionRedemption<'b>; 10]> = SmallVec::new();
     let mut next_redemption_idx: usize = 0;
@@ -346,29 +1348,95 @@ fn build_solver_result<'a, 'b, S: Solution>(
 
     // Extract which items each promotion selected and their discounted prices
     for instance in promotion_instances.iter() {
-        let apps =
+        let mut apps =
             instance.calculate_item_redemptions(solution, item_group, &mut next_redemption_idx)?;
 
-        let (applied_items, updated_used_items, updated_total) =
-            apply_promotion_redemptions(item_group.len(), used_items, total, &apps)?;
+        attribute_funding(&mut apps, |_key| Some(instance.promotion()))?;
+
+        let (applied_items, updated_remaining_units, updated_total) =
+            apply_promotion_redemptions(item_group.len(), remaining_units, total, &apps)?;
 
-        affected_items.extend(applied_items);
-        used_items = updated_used_items;
+        for item_idx in applied_items {
+            if !affected_items.contains(&item_idx) {
+                affected_items.push(item_idx);
+            }
+        }
+
+        remaining_units = updated_remaining_units;
         total = updated_total;
 
         promotion_redemptions.extend(apps);
     }
 
     let (unaffected_items, total) =
-        collect_full_price_items(item_group, solution, item_presence, used_items, total)?;
+        collect_full_price_items(item_group, solution, item_presence, remaining_units, total)?;
 
-    // At this point every item-group position is accounted for exactly once:
-    // either via a promotion redemption or via its baseline full-price variable.
+    // At this point every unit of every item-group line is accounted for exactly
+    // once: either via a promotion redemption or via its baseline full-price variable.
     Ok(SolverResult {
         affected_items,
         unaffected_items,
         total,
         promotion_redemptions,
+        quality,
+    })
+}
+
+/// Result for an item group bought entirely at full price.
+fn full_price_result<'b>(item_group: &ItemGroup<'b>) -> Result<SolverResult<'b>, SolverError> {
+    let mut total = Money::from_minor(0, item_group.currency());
+
+    for item in item_group.iter() {
+        let unit_price = Money::from_minor(item.price().to_minor_units(), item_group.currency());
+
+        total = total.add(unit_price.mul(item.quantity())?)?;
+    }
+
+    Ok(SolverResult {
+        affected_items: SmallVec::new(),
+        unaffected_items: (0..item_group.len()).collect(),
+        total,
+        promotion_redemptions: SmallVec::new(),
+        quality: SolutionQuality::Optimal,
+    })
+}
+
+/// Result used when the time limit passes before any solution is found, or the
+/// backend wrongly reports the model infeasible.
+///
+/// The greedy heuristic prices the item group whenever it can honour the run:
+/// it knows nothing of exclusions, run-wide rounding policies or least-generous
+/// objectives, and cannot price every promotion or enforce price floors. Failing
+/// that, every item stays at full price.
+fn fallback_result<'b>(
+    promotions: &[&dyn ILPPromotion],
+    item_group: &ItemGroup<'b>,
+    pools: &mut BudgetPools<'_>,
+    run: &SolveRun<'_>,
+) -> Result<SolverResult<'b>, SolverError> {
+    let greedy_honours_run = run.objective == ObjectiveMode::LowestTotal
+        && run.excluded.is_empty()
+        && *run.rounding == DEFAULT_ROUNDING
+        && promotions
+            .iter()
+            .all(|promotion| promotion.exclusions().is_empty());
+
+    if greedy_honours_run {
+        match GreedySolver::solve_promotions(promotions, item_group, pools) {
+            Ok(result) => {
+                return Ok(SolverResult {
+                    quality: SolutionQuality::Heuristic,
+                    ..result
+                });
+            }
+            Err(SolverError::GreedyUnsupported(_) | SolverError::PriceFloorsUnenforced(..)) => {}
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(SolverResult {
+        quality: SolutionQuality::Fallback,
+        ..full_price_result(item_group)?
     })
 }
 
@@ -405,7 +1473,7 @@ fn build_presence_variables_and_objective<O: ILPObserver + ?Sized>(
     // variables later, they'll offer alternative (discounted) costs. The solver will
     // compare full-price vs. discounted options and choose what minimizes the total.
     for (item_idx, item) in item_group.iter().enumerate() {
-        let var = pb.add(variable().binary());
+        let var = pb.add(item_units_variable(item.quantity()));
         let minor_units = item.price().to_minor_units();
 
         // `good_lp` stores coefficients as `f64`. Only integers with absolute value <= 2^53
@@ -426,6 +1494,9 @@ fn build_presence_variables_and_objective<O: ILPObserver + ?Sized>(
 
 /// Collect unaffected items and their total price.
 ///
+/// A line is unaffected when at least one of its units is bought at full price,
+/// so a partially redeemed quantity line is reported as both affected and unaffected.
+///
 /// # Errors
 ///
 /// Returns a [`SolverError`] if any item in the group contains a Money amount in minor units
@@ -434,34 +1505,34 @@ fn collect_full_price_items<'b>(
     item_group: &ItemGroup<'b>,
     solution: &impl Solution,
     z: &[Variable],
-    used_items: ItemUsageFlags,
+    remaining_units: ItemUnitsRemaining,
     total: Money<'b, Currency>,
 ) -> Result<FullPriceState<'b>, SolverError> {
     let mut unaffected_items = SmallVec::new();
-    let mut used_items = used_items;
+    let mut remaining_units = remaining_units;
     let mut total = total;
 
-    // Any item that wasn't claimed by a promotion is treated as an unaffected
-    // full-price item and contributes its full price to the total.
+    // Any unit that wasn't claimed by a promotion is treated as an unaffected
+    // full-price unit and contributes its full price to the total.
     for (item_idx, (var, item)) in z.iter().copied().zip(item_group.iter()).enumerate() {
-        let Some(used) = used_items.get_mut(item_idx) else {
+        let Some(remaining) = remaining_units.get_mut(item_idx) else {
             continue;
         };
 
-        // `var` is a binary decision variable; the solver return floats, so treat values
-        // greater than 0.5 as "selected" (i.e. 1) to tolerate tiny numerical noise.
-        if solution.value(var) > BINARY_THRESHOLD && !*used {
+        let full_price_units = solution_units(solution, var).min(*remaining);
+
+        if full_price_units > 0 {
             // Add the item to the list of unaffected items.
             unaffected_items.push(item_idx);
 
-            // Add the item's full price to the result total.
-            total = total.add(Money::from_minor(
-                item.price().to_minor_units(),
-                item_group.currency(),
-            ))?;
+            // Add the full price of the unclaimed units to the result total.
+            let unit_price =
+                Money::from_minor(item.price().to_minor_units(), item_group.currency());
+
+            total = total.add(unit_price.mul(full_price_units)?)?;
 
-            // Mark the item as used.
-            *used = true;
+            // Mark the units as used.
+            *remaining -= full_price_units;
         }
     }
 
@@ -470,11 +1541,11 @@ fn collect_full_price_items<'b>(
 
 /// Apply promotion redemptions to track affected items and accumulate total.
 ///
-/// This function processes [`PromotionRedemption`] instances, ensuring each item
-/// group position is used at most once (via `used_items`), records affected item indices,
-/// and adds the final prices to `total`.
+/// This function processes [`PromotionRedemption`] instances, ensuring each unit of
+/// an item group line is used at most once (via `remaining_units`), records affected
+/// item indices, and adds the final prices of the claimed units to `total`.
 ///
-/// Note: `used_items` is indexed by item group position (item index). This keeps it
+/// Note: `remaining_units` is indexed by item group position (item index). This keeps it
 /// aligned with other per-variable/per-position arrays used by the ILP formulation.
 ///
 /// # Errors
@@ -482,14 +1553,14 @@ fn collect_full_price_items<'b>(
 /// Returns a [`SolverError`] if adding a final price to `total` fails.
 fn apply_promotion_redemptions<'b>(
     item_count: usize,
-    used_items: ItemUsageFlags,
+    remaining_units: ItemUnitsRemaining,
     total: Money<'b, Currency>,
     redemptions: &[PromotionRedemption<'b>],
 ) -> Result<AppliedPromotionState<'b>, SolverError> {
     // The indexes of items that are being affected by promotions
     let mut affected_items: ItemIndexList = ItemIndexList::new();
 
-    let mut used_items = used_items;
+    let mut remaining_units = remaining_units;
     let mut total = total;
 
     for redemption in redemptions {
@@ -497,26 +1568,28 @@ fn apply_promotion_redemptions<'b>(
             continue;
         }
 
-        // If this position is already claimed, skip it to avoid double-counting.
-        if let Some(used) = used_items.get(redemption.item_idx)
-            && *used
-        {
+        // If the line has too few unclaimed units left, skip it to avoid double-counting.
+        let Some(remaining) = remaining_units.get_mut(redemption.item_idx) else {
             continue;
-        }
+        };
 
-        // Commit to consuming this item position as soon as we apply its discount.
-        if let Some(used) = used_items.get_mut(redemption.item_idx) {
-            *used = true;
+        if redemption.quantity == 0 || *remaining < redemption.quantity {
+            continue;
         }
 
+        // Commit to consuming these units as soon as we apply their discount.
+        *remaining -= redemption.quantity;
+
         // Track that this item was included in a promotion.
-        affected_items.push(redemption.item_idx);
+        if !affected_items.contains(&redemption.item_idx) {
+            affected_items.push(redemption.item_idx);
+        }
 
-        // Add the final price to the running total.
-        total = total.add(redemption.final_price)?;
+        // Add the final price of the claimed units to the running total.
+        total = total.add(redemption.total_final_price()?)?;
     }
 
-    Ok((affected_items, used_items, total))
+    Ok((affected_items, remaining_units, total))
 }
 
 #[cfg(test)]
@@ -668,6 +1741,9 @@ mod tests {
                     redemption_idx,
                     original_price: *item.price(),
                     final_price: Money::from_minor(self.final_minor.max(0), currency),
+                    quantity: 1,
+                    funding: SmallVec::new(),
+                    floor_limited: false,
                 });
             }
 
@@ -739,13 +1815,13 @@ mod tests {
 
     #[test]
     fn apply_redemptions_skips_pre_used_positions() -> TestResult {
-        // `apply_redemptions` uses `used_items` (indexed by item group position)
+        // `apply_redemptions` uses `remaining_units` (indexed by item group position)
         // to prevent an item from being claimed by more than one promotion.
-        let mut used_items: ItemUsageFlags = smallvec![false; 3];
+        let mut remaining_units: ItemUnitsRemaining = smallvec![1; 3];
 
         // Simulate a different promotion already consuming the middle position.
-        if let Some(used) = used_items.get_mut(1) {
-            *used = true;
+        if let Some(remaining) = remaining_units.get_mut(1) {
+            *remaining = 0;
         }
 
         // Start from zero so any applied discount would be visible in the result total.
@@ -758,10 +1834,13 @@ mod tests {
             redemption_idx: 0,
             original_price: Money::from_minor(200, GBP),
             final_price: Money::from_minor(150, GBP),
+            quantity: 1,
+            funding: SmallVec::new(),
+            floor_limited: false,
         }];
 
-        let (affected_items, _used_items, total) =
-            apply_promotion_redemptions(3, used_items, total, &redemptions)?;
+        let (affected_items, _remaining_units, total) =
+            apply_promotion_redemptions(3, remaining_units, total, &redemptions)?;
 
         // Because the only discounted item was already marked "used", nothing should be applied.
         assert!(affected_items.is_empty());
@@ -772,7 +1851,7 @@ mod tests {
 
     #[test]
     fn apply_redemptions_skips_items_not_in_selection() -> TestResult {
-        let used_items: ItemUsageFlags = smallvec![false; 2];
+        let remaining_units: ItemUnitsRemaining = smallvec![1; 2];
         let total = Money::from_minor(0, GBP);
 
         let redemptions = [PromotionRedemption {
@@ -781,10 +1860,13 @@ mod tests {
             redemption_idx: 0,
             original_price: Money::from_minor(200, GBP),
             final_price: Money::from_minor(150, GBP),
+            quantity: 1,
+            funding: SmallVec::new(),
+            floor_limited: false,
         }];
 
-        let (affected_items, _used_items, total) =
-            apply_promotion_redemptions(2, used_items, total, &redemptions)?;
+        let (affected_items, _remaining_units, total) =
+            apply_promotion_redemptions(2, remaining_units, total, &redemptions)?;
 
         assert!(affected_items.is_empty());
         assert_eq!(total.to_minor_units(), 0);
@@ -1022,13 +2104,13 @@ mod tests {
 
         let solution: HashMap<Variable, f64> = z.iter().copied().map(|v| (v, 1.0)).collect();
 
-        let mut used_items: ItemUsageFlags = smallvec![false; 3];
-        used_items[1] = true; // pretend item 1 was claimed by a promotion
+        let mut remaining_units: ItemUnitsRemaining = smallvec![1; 3];
+        remaining_units[1] = 0; // pretend item 1 was claimed by a promotion
 
         let total = Money::from_minor(0, item_group.currency());
 
         let (unaffected_items, total) =
-            collect_full_price_items(&item_group, &solution, &z, used_items, total)?;
+            collect_full_price_items(&item_group, &solution, &z, remaining_units, total)?;
 
         assert_eq!(unaffected_items.as_slice(), &[0, 2]);
         assert_eq!(total.to_minor_units(), 400);
@@ -1049,11 +2131,11 @@ mod tests {
         let solution: HashMap<Variable, f64> = z.iter().copied().map(|v| (v, 1.0)).collect();
 
         // Deliberately shorter than the item group to exercise the guard path.
-        let used_items: ItemUsageFlags = smallvec![false; 1];
+        let remaining_units: ItemUnitsRemaining = smallvec![1; 1];
         let total = Money::from_minor(0, item_group.currency());
 
         let (unaffected_items, total) =
-            collect_full_price_items(&item_group, &solution, &z, used_items, total)?;
+            collect_full_price_items(&item_group, &solution, &z, remaining_units, total)?;
 
         assert_eq!(unaffected_items.as_slice(), &[0]);
         assert_eq!(total.to_minor_units(), 100);
@@ -0,0 +1,88 @@
+//! Objective Modes
+//!
+//! By default the solver finds the lowest basket total, which is the allocation
+//! most generous to the customer. Some markets only require the retailer to apply
+//! every promotion the customer is entitled to, and let the retailer choose the
+//! least costly allocation that does so (for example which item is free in a buy
+//! one get one free).
+//!
+//! A least-generous objective is solved lexicographically: the first pass forms
+//! as many entitled redemptions as possible, and the second keeps that many while
+//! giving as little discount as possible. Any later passes (stability and
+//! tie-breaks) stay on that optimum.
+
+use good_lp::{Expression, IntoAffineExpression};
+use serde::Deserialize;
+
+use crate::solvers::{SolverError, ilp::promotions::PromotionInstances};
+
+/// What the solver optimises.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ObjectiveMode {
+    /// Find the lowest basket total
+    #[default]
+    LowestTotal,
+
+    /// Form as many redemptions as possible, then give the least total discount
+    LeastGenerous,
+
+    /// Form as many redemptions of mandatory promotions as possible, then give the
+    /// least total discount
+    ///
+    /// Promotions that are not mandatory are never needed to meet the
+    /// entitlement, so they only apply where they cost the retailer nothing.
+    LeastGenerousMandatory,
+}
+
+impl ObjectiveMode {
+    /// Whether the mode gives the least discount rather than the lowest total.
+    pub(crate) fn is_least_generous(self) -> bool {
+        !matches!(self, Self::LowestTotal)
+    }
+
+    /// Express the total in the direction the mode minimises.
+    pub(crate) fn signed_cost(self, cost: Expression) -> Expression {
+        if self.is_least_generous() {
+            -cost
+        } else {
+            cost
+        }
+    }
+
+    /// Count the redemptions the customer is entitled to, if the mode has an
+    /// entitlement.
+    ///
+    /// Returns `None` for [`ObjectiveMode::LowestTotal`], and for a mandatory
+    /// entitlement when no mandatory promotion could apply.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SolverError::RedemptionCountUnsupported`] if an entitled promotion
+    /// cannot count its redemptions.
+    pub(crate) fn entitled_redemptions(
+        self,
+        promotion_instances: &PromotionInstances<'_>,
+    ) -> Result<Option<Expression>, SolverError> {
+        let mut entitled = Expression::default();
+
+        for instance in promotion_instances.iter() {
+            let is_entitled = match self {
+                Self::LowestTotal => return Ok(None),
+                Self::LeastGenerous => true,
+                Self::LeastGenerousMandatory => instance.is_mandatory(),
+            };
+
+            if is_entitled {
+                entitled += instance.redemption_count_expr().ok_or(
+                    SolverError::RedemptionCountUnsupported(instance.promotion_key()),
+                )?;
+            }
+        }
+
+        Ok(IntoAffineExpression::linear_coefficients(&entitled)
+            .next()
+            .is_some()
+            .then_some(entitled))
+    }
+}
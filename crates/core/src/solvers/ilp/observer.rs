@@ -2,10 +2,13 @@
 
 use std::any::Any;
 
-use good_lp::{Expression, Variable};
+use good_lp::{Expression, Variable, VariableDefinition};
 use petgraph::graph::NodeIndex;
 
-use crate::{graph::PromotionLayerKey, promotions::PromotionKey};
+use crate::{
+    graph::PromotionLayerKey,
+    promotions::{PromotionKey, budget::BudgetPoolKey},
+};
 
 /// Observer trait for capturing ILP formulation as it's built.
 ///
@@ -30,8 +33,8 @@ pub trait ILPObserver: Send + Sync + Any {
     /// # Parameters
     ///
     /// - `item_idx`: Index of the item in the item group
-    /// - `var`: The binary decision variable
-    /// - `price_minor`: Full price in minor units (e.g., pence, cents)
+    /// - `var`: The decision variable (binary, or a unit count for quantity lines)
+    /// - `price_minor`: Full unit price in minor units (e.g., pence, cents)
     fn on_presence_variable(&mut self, item_idx: usize, var: Variable, price_minor: i64);
 
     /// Called when a promotion variable is created.
@@ -76,6 +79,17 @@ pub trait ILPObserver: Send + Sync + Any {
     ) {
     }
 
+    /// Called for every variable once the model is built, before it is solved.
+    ///
+    /// Reports the bounds and integrality each variable was created with, which the
+    /// other callbacks do not carry. Variables are reported in creation order.
+    ///
+    /// # Parameters
+    ///
+    /// - `var`: The decision variable
+    /// - `definition`: Its bounds and whether it is integer
+    fn on_variable_definition(&mut self, _var: Variable, _definition: &VariableDefinition) {}
+
     /// Called when a term is added to the objective function.
     ///
     /// # Parameters
@@ -86,13 +100,13 @@ pub trait ILPObserver: Send + Sync + Any {
 
     /// Called when an exclusivity constraint is added for an item.
     ///
-    /// Exclusivity constraints ensure each item is purchased exactly once:
+    /// Exclusivity constraints ensure each unit is purchased exactly once:
     /// either at full price OR via one promotion, never both.
     ///
     /// # Parameters
     ///
     /// - `item_idx`: Index of the item in the item group
-    /// - `constraint_expr`: The constraint expression (sum of variables = 1)
+    /// - `constraint_expr`: The constraint expression (sum of variables = line quantity)
     fn on_exclusivity_constraint(&mut self, item_idx: usize, constraint_expr: &Expression);
 
     /// Called when a promotion-specific constraint is added.
@@ -116,6 +130,73 @@ pub trait ILPObserver: Send + Sync + Any {
         rhs: f64,
     );
 
+    /// Called when a shared budget pool constraint is added.
+    ///
+    /// Pool constraints limit the combined usage of every promotion drawing on
+    /// the pool, so they are not tied to a single promotion.
+    ///
+    /// # Parameters
+    ///
+    /// - `pool_key`: Key identifying the budget pool
+    /// - `constraint_type`: Human-readable constraint type (e.g., `"monetary value budget"`)
+    /// - `constraint_expr`: The left-hand side expression
+    /// - `relation`: Relation operator ("=", "<=", ">=")
+    /// - `rhs`: Right-hand side value
+    fn on_budget_pool_constraint(
+        &mut self,
+        _pool_key: BudgetPoolKey,
+        _constraint_type: &str,
+        _constraint_expr: &Expression,
+        _relation: &str,
+        _rhs: f64,
+    ) {
+    }
+
+    /// Called when a constraint keeps two excluded promotions apart.
+    ///
+    /// Exclusion constraints limit a pair of promotions that cannot be combined,
+    /// so they are not tied to a single promotion.
+    ///
+    /// # Parameters
+    ///
+    /// - `first`, `second`: Keys of the excluded promotions
+    /// - `constraint_type`: Human-readable constraint type (e.g., `"basket exclusion"`)
+    /// - `constraint_expr`: The left-hand side expression
+    /// - `relation`: Relation operator ("=", "<=", ">=")
+    /// - `rhs`: Right-hand side value
+    fn on_exclusion_constraint(
+        &mut self,
+        _first: PromotionKey,
+        _second: PromotionKey,
+        _constraint_type: &str,
+        _constraint_expr: &Expression,
+        _relation: &str,
+        _rhs: f64,
+    ) {
+    }
+
+    /// Called when a joint evaluation links an item row of a layer to the
+    /// outcomes of the earlier layer that bring it there.
+    ///
+    /// The row's units equal the units of those outcomes, and the joint objective
+    /// replaces the row's incoming price with its outgoing price, adding
+    /// `-price_minor` times `incoming` to the cost.
+    ///
+    /// # Parameters
+    ///
+    /// - `item_idx`: Index of the row in the layer's item group
+    /// - `constraint_expr`: The constraint expression (row's units minus `incoming` = 0)
+    /// - `incoming`: Sum of the earlier layer's outcome variables leading to the row
+    /// - `price_minor`: Unit price the row enters the layer at, in minor units
+    fn on_linking_constraint(
+        &mut self,
+        _item_idx: usize,
+        _constraint_expr: &Expression,
+        _incoming: &Expression,
+        _price_minor: i64,
+    ) {
+    }
+
     /// Called before solving a layer in graph evaluation.
     ///
     /// Allows multi-layer observers to track which layer is being solved.
@@ -130,6 +211,15 @@ pub trait ILPObserver: Send + Sync + Any {
     ///
     /// Allows multi-layer observers to finalize the current layer's formulation.
     fn on_layer_end(&mut self) {}
+
+    /// Return whether the solver may split an item group into independent
+    /// sub-problems and solve each as its own model.
+    ///
+    /// Observers capturing the formulation expect a single model indexed by the
+    /// whole item group, so decomposition is off by default. [`NoopObserver`] opts in.
+    fn allows_decomposition(&self) -> bool {
+        false
+    }
 }
 
 /// No-op observer for unobserved solves.
@@ -160,6 +250,10 @@ impl ILPObserver for NoopObserver {
         _: f64,
     ) {
     }
+
+    fn allows_decomposition(&self) -> bool {
+        true
+    }
 }
 
 #[cfg(test)]
@@ -205,4 +299,41 @@ mod tests {
         obs.on_layer_begin(PromotionLayerKey::default(), NodeIndex::new(0));
         obs.on_layer_end();
     }
+
+    #[test]
+    fn only_noop_observer_allows_decomposition() {
+        assert!(
+            !MinimalObserver.allows_decomposition(),
+            "capturing observers see a single model by default"
+        );
+        assert!(
+            NoopObserver.allows_decomposition(),
+            "unobserved solves may decompose"
+        );
+    }
+
+    #[test]
+    fn default_variable_definition_callback_is_callable() {
+        let mut observer = MinimalObserver;
+        let mut pb = good_lp::ProblemVariables::new();
+        let _var = pb.add(good_lp::variable().binary());
+
+        for (var, definition) in pb.iter_variables_with_def() {
+            observer.on_variable_definition(var, definition);
+        }
+    }
+
+    #[test]
+    fn default_budget_pool_callback_is_callable() {
+        let mut observer = MinimalObserver;
+        let obs: &mut dyn ILPObserver = &mut observer;
+
+        obs.on_budget_pool_constraint(
+            BudgetPoolKey::default(),
+            "monetary value budget",
+            &Expression::default(),
+            "<=",
+            0.0,
+        );
+    }
 }
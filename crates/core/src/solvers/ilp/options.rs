@@ -0,0 +1,224 @@
+//! ILP Solver Options
+
+use std::time::{Duration, Instant};
+
+use good_lp::{Constraint, variable::UnsolvedProblem};
+
+use crate::{
+    discounts::rounding::{DEFAULT_ROUNDING, RoundingPolicies},
+    promotions::PromotionKey,
+    solvers::{
+        SolverError,
+        ilp::{
+            backend::{BackendSolution, SolverBackend},
+            objective::ObjectiveMode,
+            stability::StabilityScope,
+            tie_break::TieBreakPolicy,
+        },
+    },
+};
+
+/// Settings for a solve or a whole graph evaluation.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SolverOptions {
+    /// MILP backend used to solve the formulation
+    pub backend: SolverBackend,
+
+    /// Wall-clock budget for the whole solve or evaluation
+    ///
+    /// `None` waits for the proven optimum. When the budget runs out the best
+    /// feasible allocation found so far is used, or the greedy heuristic's
+    /// allocation if there is none (full price if greedy cannot stand in), and
+    /// the result is flagged as not optimal.
+    pub time_limit: Option<Duration>,
+
+    /// Rules deciding between allocations that give the same total
+    pub tie_break: TieBreakPolicy,
+
+    /// What the solver optimises
+    pub objective: ObjectiveMode,
+
+    /// How percentage discounts are rounded, for promotions without their own
+    /// policies
+    pub rounding: RoundingPolicies,
+}
+
+impl SolverOptions {
+    /// Set the MILP backend.
+    #[must_use]
+    pub fn with_backend(mut self, backend: SolverBackend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Set the wall-clock budget.
+    #[must_use]
+    pub fn with_time_limit(mut self, time_limit: Duration) -> Self {
+        self.time_limit = Some(time_limit);
+        self
+    }
+
+    /// Set the tie-break policy.
+    #[must_use]
+    pub fn with_tie_break(mut self, tie_break: TieBreakPolicy) -> Self {
+        self.tie_break = tie_break;
+        self
+    }
+
+    /// Set what the solver optimises.
+    #[must_use]
+    pub fn with_objective(mut self, objective: ObjectiveMode) -> Self {
+        self.objective = objective;
+        self
+    }
+
+    /// Set how percentage discounts are rounded.
+    #[must_use]
+    pub fn with_rounding(mut self, rounding: impl Into<RoundingPolicies>) -> Self {
+        self.rounding = rounding.into();
+        self
+    }
+
+    /// Start the clock for a solve or evaluation.
+    pub(crate) fn start(&self) -> SolveRun<'_> {
+        SolveRun {
+            backend: self.backend,
+            // A limit too far in the future to represent is no limit at all.
+            deadline: self
+                .time_limit
+                .and_then(|limit| Instant::now().checked_add(limit)),
+            stability: None,
+            tie_break: self.tie_break,
+            objective: self.objective,
+            rounding: &self.rounding,
+            excluded: &[],
+        }
+    }
+}
+
+/// Backend, deadline and preferences shared by every model solved in one run.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SolveRun<'s> {
+    /// MILP backend used for every model in the run
+    pub backend: SolverBackend,
+
+    /// Instant after which solves give up, if limited
+    pub deadline: Option<Instant>,
+
+    /// Previous allocation to keep, if re-pricing with a stability preference
+    pub stability: Option<StabilityScope<'s>>,
+
+    /// Rules deciding between allocations that give the same total
+    pub tie_break: TieBreakPolicy,
+
+    /// What every model in the run optimises
+    pub objective: ObjectiveMode,
+
+    /// How percentage discounts are rounded, for promotions without their own
+    /// policies
+    pub rounding: &'s RoundingPolicies,
+
+    /// Items paired with a promotion they may not be redeemed in, because they
+    /// were already redeemed in one it cannot be combined with
+    pub excluded: &'s [(usize, PromotionKey)],
+}
+
+impl Default for SolveRun<'_> {
+    fn default() -> Self {
+        Self {
+            backend: SolverBackend::default(),
+            deadline: None,
+            stability: None,
+            tie_break: TieBreakPolicy::default(),
+            objective: ObjectiveMode::default(),
+            rounding: &DEFAULT_ROUNDING,
+            excluded: &[],
+        }
+    }
+}
+
+impl<'s> SolveRun<'s> {
+    /// The same run with a stability preference for its models.
+    pub fn with_stability<'t>(self, stability: Option<StabilityScope<'t>>) -> SolveRun<'t>
+    where
+        's: 't,
+    {
+        SolveRun {
+            backend: self.backend,
+            deadline: self.deadline,
+            stability,
+            tie_break: self.tie_break,
+            objective: self.objective,
+            rounding: self.rounding,
+            excluded: self.excluded,
+        }
+    }
+
+    /// The same run keeping each item in `excluded` off the promotion it is paired with.
+    pub fn with_excluded<'t>(self, excluded: &'t [(usize, PromotionKey)]) -> SolveRun<'t>
+    where
+        's: 't,
+    {
+        SolveRun { excluded, ..self }
+    }
+
+    /// The same run optimising `objective`.
+    pub fn with_objective(self, objective: ObjectiveMode) -> Self {
+        Self { objective, ..self }
+    }
+
+    /// Time left before the deadline, if there is one.
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    /// Solve a model with the run's backend within the remaining time.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SolverError::TimeLimitReached`] if no feasible solution was found
+    /// before the deadline, or the backend's resolution error.
+    pub fn solve(
+        &self,
+        problem: UnsolvedProblem,
+        constraints: impl IntoIterator<Item = Constraint>,
+    ) -> Result<BackendSolution, SolverError> {
+        self.backend.solve(problem, constraints, self.remaining())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unlimited_run_has_no_deadline() {
+        let options = SolverOptions::default();
+        let run = options.start();
+
+        assert_eq!(run.deadline, None, "no deadline without a time limit");
+        assert_eq!(run.remaining(), None, "no remaining time to track");
+    }
+
+    #[test]
+    fn limited_run_counts_down_from_the_time_limit() {
+        let options = SolverOptions::default().with_time_limit(Duration::from_secs(60));
+        let run = options.start();
+
+        let remaining = run.remaining().unwrap_or_default();
+
+        assert!(
+            remaining > Duration::ZERO && remaining <= Duration::from_secs(60),
+            "remaining time is within the limit"
+        );
+    }
+
+    #[test]
+    fn elapsed_run_has_no_time_left() {
+        let options = SolverOptions::default().with_time_limit(Duration::ZERO);
+        let run = options.start();
+
+        assert_eq!(run.remaining(), Some(Duration::ZERO), "deadline has passed");
+    }
+}
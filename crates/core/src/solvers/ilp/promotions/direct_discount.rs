@@ -1,19 +1,35 @@
 //! Direct Discount Promotions ILP
 
-use good_lp::{Expression, Solution, Variable, variable};
+use good_lp::{Expression, Solution, Variable};
 use rustc_hash::FxHashMap;
-use smallvec::SmallVec;
+use smallvec::{SmallVec, smallvec};
 
 use rusty_money::Money;
 
 use crate::{
-    items::groups::ItemGroup,
-    promotions::{PromotionKey, redemptions::PromotionRedemption, types::DirectDiscountPromotion},
+    context::EvaluationContext,
+    discounts::rounding::RoundingPolicies,
+    items::{Item, groups::ItemGroup},
+    promotions::{
+        PromotionKey,
+        budget::{BudgetPoolKey, BudgetPools},
+        coupon::PromotionCoupon,
+        exclusion::PromotionExclusions,
+        explain::{MissReason, gate_reason},
+        funding::PromotionFunding,
+        redemptions::PromotionRedemption,
+        types::DirectDiscountPromotion,
+    },
     solvers::{
         SolverError,
+        exhaustive::ExhaustivePromotion,
+        greedy::GreedyPromotion,
         ilp::{
-            BINARY_THRESHOLD, ILPObserver, i64_to_f64_exact,
-            promotions::{ILPPromotion, ILPPromotionVars, PromotionVars},
+            ILPObserver, i64_to_f64_exact, item_units_variable,
+            promotions::{
+                ILPPromotion, ILPPromotionVars, ItemSignature, PriceOutcomes, PromotionVars,
+            },
+            solution_units,
             state::ILPState,
         },
     },
@@ -22,7 +38,7 @@ use crate::{
 /// Solver variables for a direct discount promotion.
 ///
 /// Tracks the mapping from item group indices to their corresponding
-/// binary decision variables in the ILP model.
+/// unit count decision variables in the ILP model.
 #[derive(Debug)]
 pub struct DirectDiscountPromotionVars {
     /// Promotion key for observer/redemption output.
@@ -51,6 +67,15 @@ impl DirectDiscountPromotionVars {
         self.add_budget_constraints(self.promotion_key, item_group, state, observer)
     }
 
+    /// Number of units of `item_idx` the solution applies this promotion to.
+    fn participating_units(&self, solution: &dyn Solution, item_idx: usize) -> u32 {
+        self.item_participation
+            .iter()
+            .filter(|&&(idx, _var)| idx == item_idx)
+            .map(|&(_idx, var)| solution_units(solution, var))
+            .sum()
+    }
+
     fn discounted_minor_for_item(&self, item_idx: usize) -> Result<i64, SolverError> {
         self.discounted_minor_by_item.get(&item_idx).copied().ok_or(
             SolverError::InvariantViolation {
@@ -59,6 +84,30 @@ impl DirectDiscountPromotionVars {
         )
     }
 
+    /// Total participating units; each unit is one redemption.
+    fn participation_sum(&self) -> Expression {
+        self.item_participation.iter().map(|(_, var)| *var).sum()
+    }
+
+    /// Total discount value: `sum((full_price - discounted_price) * var)`
+    fn discount_expr(&self, item_group: &ItemGroup<'_>) -> Result<Expression, SolverError> {
+        let mut discount_expr = Expression::default();
+
+        for &(item_idx, var) in &self.item_participation {
+            let item = item_group.get_item(item_idx).map_err(SolverError::from)?;
+            let full_minor = item.price().to_minor_units();
+            let discounted_minor = self.discounted_minor_for_item(item_idx)?;
+
+            let discount_amount = full_minor.saturating_sub(discounted_minor);
+            let coeff = i64_to_f64_exact(discount_amount)
+                .ok_or(SolverError::MinorUnitsNotRepresentable(discount_amount))?;
+
+            discount_expr += var * coeff;
+        }
+
+        Ok(discount_expr)
+    }
+
     /// Add budget constraints to the ILP state.
     pub fn add_budget_constraints(
         &self,
@@ -69,8 +118,7 @@ impl DirectDiscountPromotionVars {
     ) -> Result<(), SolverError> {
         // Redemption count limit: sum(participation_vars) <= limit
         if let Some(redemption_limit) = self.redemption_limit {
-            let participation_sum: Expression =
-                self.item_participation.iter().map(|(_, var)| *var).sum();
+            let participation_sum = self.participation_sum();
 
             let limit_f64 = i64_to_f64_exact(i64::from(redemption_limit)).ok_or(
                 SolverError::MinorUnitsNotRepresentable(i64::from(redemption_limit)),
@@ -89,19 +137,7 @@ impl DirectDiscountPromotionVars {
 
         // Monetary limit: sum((full_price - discounted_price) * var) <= limit
         if let Some(limit_minor) = self.monetary_limit_minor {
-            let mut discount_expr = Expression::default();
-
-            for &(item_idx, var) in &self.item_participation {
-                let item = item_group.get_item(item_idx).map_err(SolverError::from)?;
-                let full_minor = item.price().to_minor_units();
-                let discounted_minor = self.discounted_minor_for_item(item_idx)?;
-
-                let discount_amount = full_minor.saturating_sub(discounted_minor);
-                let coeff = i64_to_f64_exact(discount_amount)
-                    .ok_or(SolverError::MinorUnitsNotRepresentable(discount_amount))?;
-
-                discount_expr += var * coeff;
-            }
+            let discount_expr = self.discount_expr(item_group)? - state.floor_excess(promotion_key);
 
             let limit_f64 = i64_to_f64_exact(limit_minor)
                 .ok_or(SolverError::MinorUnitsNotRepresentable(limit_minor))?;
@@ -135,9 +171,34 @@ impl ILPPromotionVars for DirectDiscountPromotionVars {
     }
 
     fn is_item_participating(&self, solution: &dyn Solution, item_idx: usize) -> bool {
-        self.item_participation
-            .iter()
-            .any(|&(idx, var)| idx == item_idx && solution.value(var) > BINARY_THRESHOLD)
+        self.participating_units(solution, item_idx) > 0
+    }
+
+    fn item_price_outcomes(
+        &self,
+        _item_group: &ItemGroup<'_>,
+        item_idx: usize,
+    ) -> Result<Option<PriceOutcomes>, SolverError> {
+        let mut outcomes = PriceOutcomes::new();
+
+        for &(idx, var) in &self.item_participation {
+            if idx == item_idx {
+                outcomes.push((Expression::from(var), self.discounted_minor_for_item(idx)?));
+            }
+        }
+
+        Ok(Some(outcomes))
+    }
+
+    fn redemption_count_expr(&self) -> Option<Expression> {
+        Some(self.participation_sum())
+    }
+
+    fn discount_value_expr(
+        &self,
+        item_group: &ItemGroup<'_>,
+    ) -> Result<Option<Expression>, SolverError> {
+        self.discount_expr(item_group).map(Some)
     }
 
     fn add_constraints(
@@ -182,14 +243,15 @@ impl ILPPromotionVars for DirectDiscountPromotionVars {
 
         for item_idx in 0..item_group.len() {
             let item = item_group.get_item(item_idx)?;
+            let quantity = self.participating_units(solution, item_idx);
 
-            if !self.is_item_participating(solution, item_idx) {
+            if quantity == 0 {
                 continue;
             }
 
             let discounted_minor = self.discounted_minor_for_item(item_idx)?;
 
-            // For DirectDiscountPromotion, each item gets its own unique redemption_idx
+            // For DirectDiscountPromotion, each item line gets its own unique redemption_idx
             let redemption_idx = *next_redemption_idx;
             *next_redemption_idx += 1;
 
@@ -199,6 +261,9 @@ impl ILPPromotionVars for DirectDiscountPromotionVars {
                 redemption_idx,
                 original_price: *item.price(),
                 final_price: Money::from_minor(discounted_minor, currency),
+                quantity,
+                funding: SmallVec::new(),
+                floor_limited: false,
             });
         }
 
@@ -211,8 +276,83 @@ impl ILPPromotion for DirectDiscountPromotion<'_> {
         DirectDiscountPromotion::key(self)
     }
 
+    fn coupon(&self) -> Option<&PromotionCoupon> {
+        DirectDiscountPromotion::coupon(self)
+    }
+
+    fn budget_pools(&self) -> &[BudgetPoolKey] {
+        DirectDiscountPromotion::budget_pools(self)
+    }
+
+    fn priority(&self) -> i32 {
+        DirectDiscountPromotion::priority(self)
+    }
+
+    fn funding(&self) -> &PromotionFunding {
+        DirectDiscountPromotion::funding(self)
+    }
+
+    fn redeems_each_unit(&self) -> bool {
+        true
+    }
+
+    fn is_mandatory(&self) -> bool {
+        DirectDiscountPromotion::is_mandatory(self)
+    }
+
+    fn rounding(&self) -> Option<&RoundingPolicies> {
+        DirectDiscountPromotion::rounding(self)
+    }
+
+    fn exclusions(&self) -> &PromotionExclusions {
+        DirectDiscountPromotion::exclusions(self)
+    }
+
+    fn near_miss<'b>(
+        &self,
+        item_group: &ItemGroup<'b>,
+        pools: &BudgetPools<'_>,
+    ) -> Option<MissReason<'b>> {
+        gate_reason(
+            self.schedule(),
+            self.coupon(),
+            self.budget(),
+            self.redemption_limit(),
+            self.budget_pools(),
+            pools,
+            item_group.context(),
+        )
+        .or_else(|| self.shortfall(item_group).map(MissReason::Shortfall))
+    }
+
+    fn item_signature(
+        &self,
+        item: &Item<'_>,
+        context: &EvaluationContext,
+    ) -> Option<ItemSignature> {
+        Some(smallvec![
+            self.qualification()
+                .matches_in_context(item.tags(), context)
+        ])
+    }
+
+    fn greedy(&self) -> Option<&dyn GreedyPromotion> {
+        Some(self)
+    }
+
+    fn exhaustive(&self) -> Option<&dyn ExhaustivePromotion> {
+        Some(self)
+    }
+
     fn is_applicable(&self, item_group: &ItemGroup<'_>) -> bool {
-        if item_group.is_empty() {
+        let context = item_group.context();
+
+        if item_group.is_empty()
+            || !self.schedule().is_active_in(context)
+            || !self
+                .coupon()
+                .is_none_or(|coupon| coupon.is_presented_in(context))
+        {
             return false;
         }
 
@@ -220,7 +360,7 @@ impl ILPPromotion for DirectDiscountPromotion<'_> {
 
         item_group
             .iter()
-            .any(|item| qualification.matches(item.tags()))
+            .any(|item| qualification.matches_in_context(item.tags(), item_group.context()))
     }
 
     fn add_variables(
@@ -234,17 +374,21 @@ impl ILPPromotion for DirectDiscountPromotion<'_> {
         // Keep the mapping from item group index to solver variable so we can interpret solutions later.
         let mut item_participation = SmallVec::new();
         let mut discounted_minor_by_item = FxHashMap::default();
+        let rounding = state.rounding_for(self.rounding(), item_group.currency());
 
         for (item_idx, item) in item_group.iter().enumerate() {
             // Enforce the promotion's qualification rules up-front so the solver doesn't need
             // extra constraints.
-            if !self.qualification().matches(item.tags()) {
+            if !self
+                .qualification()
+                .matches_in_context(item.tags(), item_group.context())
+            {
                 continue;
             }
 
             // Compute the discounted price in minor units; if the discount can't be computed, return an error.
             let discounted_minor = self
-                .calculate_discounted_price(item)
+                .calculate_rounded_price(item, rounding)
                 .map_err(SolverError::from)?
                 .to_minor_units();
 
@@ -254,15 +398,18 @@ impl ILPPromotion for DirectDiscountPromotion<'_> {
                 return Err(SolverError::MinorUnitsNotRepresentable(discounted_minor));
             };
 
-            // Create a binary decision variable for this item: should this promotion apply to it?
-            let participation_var = state.problem_variables_mut().add(variable().binary());
+            // Create a decision variable for this item: how many of its units should this
+            // promotion apply to? (binary for single-unit lines)
+            let participation_var = state
+                .problem_variables_mut()
+                .add(item_units_variable(item.quantity()));
 
             // Persist the variable so we can later mark items as participating from the solved model.
             item_participation.push((item_idx, participation_var));
             discounted_minor_by_item.insert(item_idx, discounted_minor);
 
-            // Tell the solver "if you set this variable to 1 (apply this promotion to this item),
-            // add the discounted price to the total instead of full price". The solver will weigh
+            // Tell the solver "for each unit this promotion applies to, add the discounted
+            // price to the total instead of full price". The solver will weigh
             // this against other options when minimizing cost.
             state.add_to_objective(participation_var, coeff);
 
@@ -282,7 +429,7 @@ impl ILPPromotion for DirectDiscountPromotion<'_> {
             promotion_key,
             item_participation,
             discounted_minor_by_item,
-            redemption_limit: self.budget().redemption_limit,
+            redemption_limit: self.redemption_limit(),
             monetary_limit_minor: self.budget().monetary_limit.map(|v| v.to_minor_units()),
         }))
     }
@@ -313,6 +460,7 @@ mod tests {
                 },
             },
         },
+        tags::string::StringTagCollection,
     };
 
     use super::*;
@@ -418,6 +566,53 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn item_price_outcomes_report_discounted_price_for_participation() -> TestResult {
+        let items = [
+            Item::with_tags(
+                ProductKey::default(),
+                Money::from_minor(100, GBP),
+                StringTagCollection::from_strs(&["eligible"]),
+            ),
+            Item::new(ProductKey::default(), Money::from_minor(200, GBP)),
+        ];
+
+        let item_group = item_group_from_items(items);
+
+        let promo = DirectDiscountPromotion::new(
+            PromotionKey::default(),
+            Qualification::match_any(StringTagCollection::from_strs(&["eligible"])),
+            SimpleDiscount::AmountOverride(Money::from_minor(50, GBP)),
+            PromotionBudget::unlimited(),
+        );
+
+        let mut state = ILPState::new(ProblemVariables::new(), Expression::default());
+        let mut observer = NoopObserver;
+
+        let vars = promo.add_variables(&item_group, &mut state, &mut observer)?;
+
+        let outcomes = vars
+            .item_price_outcomes(&item_group, 0)?
+            .ok_or("expected outcomes")?;
+
+        assert_eq!(outcomes.len(), 1, "one outcome for the participating item");
+        assert_eq!(outcomes.first().map(|(_, price)| *price), Some(50));
+        assert!(
+            outcomes
+                .iter()
+                .all(|(expr, _)| (SelectAllSolution.eval(expr) - 1.0).abs() < 1e-9),
+            "outcome should be active when the item participates"
+        );
+
+        let excluded = vars
+            .item_price_outcomes(&item_group, 1)?
+            .ok_or("expected outcomes")?;
+
+        assert!(excluded.is_empty(), "ineligible items have no outcomes");
+
+        Ok(())
+    }
+
     #[test]
     fn calculate_item_discounts_skips_unselected_items() -> TestResult {
         let items = [Item::new(
@@ -4,26 +4,37 @@
 use std::any::Any;
 
 use decimal_percentage::Percentage;
-use good_lp::{Expression, Solution, Variable, variable};
+use good_lp::{Expression, IntoAffineExpression, Solution, Variable, variable};
 use num_traits::ToPrimitive;
 use rustc_hash::{FxHashMap, FxHashSet};
-use smallvec::SmallVec;
+use smallvec::{SmallVec, smallvec};
 
 use rusty_money::Money;
 
 use crate::{
-    discounts::percent_of_minor,
-    items::groups::ItemGroup,
+    context::EvaluationContext,
+    discounts::rounding::{RoundingPolicies, RoundingPolicy, RoundingScope},
+    items::{Item, groups::ItemGroup},
     promotions::{
         PromotionKey,
+        budget::{BudgetPoolKey, BudgetPools},
+        coupon::PromotionCoupon,
+        exclusion::PromotionExclusions,
+        explain::{MissReason, gate_reason},
+        funding::PromotionFunding,
         redemptions::PromotionRedemption,
-        types::{MixAndMatchDiscount, MixAndMatchPromotion},
+        types::{MixAndMatchDiscount, MixAndMatchPromotion, MixAndMatchSlot},
     },
     solvers::{
         SolverError,
+        exhaustive::ExhaustivePromotion,
+        greedy::GreedyPromotion,
         ilp::{
-            BINARY_THRESHOLD, ILPObserver, i64_to_f64_exact,
-            promotions::{ILPPromotion, ILPPromotionVars, PromotionVars},
+            BINARY_THRESHOLD, ILPObserver, i64_to_f64_exact, item_units_variable,
+            promotions::{
+                ILPPromotion, ILPPromotionVars, ItemSignature, PriceOutcomes, PromotionVars,
+            },
+            solution_units,
             state::ILPState,
         },
     },
@@ -40,6 +51,50 @@ enum MixAndMatchRuntimeDiscount {
     FixedTotal(i64),
 }
 
+/// A bundle priced on its own, so an amount off its total saves no more than it costs.
+#[derive(Debug)]
+struct AmountOffBundle {
+    /// Whether the bundle is formed.
+    formed: Variable,
+
+    /// Units of each item filling each slot of this bundle. Empty when the bundle
+    /// is built from the promotion's own slot variables (variable arity).
+    slot_vars: Vec<SmallVec<[(usize, Variable); 10]>>,
+
+    /// Full price of the units in the bundle.
+    spend: Expression,
+
+    /// Saving taken by the bundle, bounded by the amount off and by its spend.
+    saving: Variable,
+
+    /// Whether the saving is the full amount off (otherwise the whole spend).
+    ///
+    /// Only tracked when a monetary budget could hold the saving back; otherwise
+    /// the objective already takes the most the bundle can save.
+    capped: Option<Variable>,
+
+    /// Whether every unit the bundle could hold costs at least the amount off, so
+    /// a formed bundle always saves it in full.
+    covers_amount: bool,
+
+    /// Amount off, in minor units.
+    amount_off: f64,
+
+    /// Most the bundle's units could cost above the amount off, in minor units.
+    headroom: f64,
+}
+
+/// Fixed-arity bundles holding a unit that costs at least the amount off, so each
+/// saves the amount in full.
+#[derive(Debug)]
+struct FullAmountOffBundles {
+    /// Number of such bundles (the promotion's bundle count when every bundle is one).
+    count: Variable,
+
+    /// Items whose units cost at least the amount off on their own.
+    covering_items: SmallVec<[usize; 10]>,
+}
+
 /// Solver variables for a mix-and-match promotion.
 #[derive(Debug)]
 pub struct MixAndMatchVars {
@@ -61,8 +116,11 @@ pub struct MixAndMatchVars {
     /// Slot bounds (min, max) copied from the promotion.
     slot_bounds: Vec<(usize, Option<usize>)>,
 
-    /// Total bundle size (sum of slot mins).
-    bundle_size: usize,
+    /// Bundles with capped savings (amount-off-total discounts only).
+    amount_off_bundles: Vec<AmountOffBundle>,
+
+    /// Fixed-arity bundles that save the whole amount off (amount-off-total discounts only).
+    full_amount_off_bundles: Option<FullAmountOffBundles>,
 
     /// Eligible items sorted by price asc, then index asc (for cheapest targeting).
     sorted_items: SmallVec<[(usize, i64); 10]>,
@@ -70,6 +128,9 @@ pub struct MixAndMatchVars {
     /// Runtime discount mode captured during variable creation.
     runtime_discount: MixAndMatchRuntimeDiscount,
 
+    /// Rounding for percentage discounts, resolved during variable creation.
+    rounding: RoundingPolicy,
+
     /// Budget: optional max redemptions.
     redemption_limit: Option<u32>,
 
@@ -78,6 +139,15 @@ pub struct MixAndMatchVars {
 }
 
 impl MixAndMatchVars {
+    /// Whether percentage discounts are rounded once per bundle.
+    fn is_rounded_per_bundle(&self) -> bool {
+        matches!(
+            self.runtime_discount,
+            MixAndMatchRuntimeDiscount::PercentAllItems(_)
+                | MixAndMatchRuntimeDiscount::PercentCheapest(_)
+        ) && self.rounding.scope() == RoundingScope::PerBundle
+    }
+
     fn selected_exprs(&self) -> SmallVec<[Expression; 10]> {
         let mut exprs: SmallVec<[Expression; 10]> = SmallVec::with_capacity(self.target_vars.len());
 
@@ -105,12 +175,37 @@ impl MixAndMatchVars {
         }
 
         self.add_slot_constraints(promotion_key, state, observer);
+        self.add_amount_off_bundle_constraints(promotion_key, state, observer);
 
         if self.needs_target_constraints() {
             self.add_target_constraints(promotion_key, state, observer);
         }
     }
 
+    /// Price outcomes for a cheapest-item discount: targeted items take the
+    /// discounted price, other selected items keep their original price.
+    fn cheapest_price_outcomes(
+        &self,
+        selected_expr: Expression,
+        item_idx: usize,
+        original_minor: i64,
+        target_minor: i64,
+    ) -> PriceOutcomes {
+        let Some(target_var) = self.target_vars.get(item_idx).and_then(|v| *v) else {
+            return smallvec![(selected_expr, original_minor)];
+        };
+
+        smallvec![
+            (selected_expr - target_var, original_minor),
+            (Expression::from(target_var), target_minor),
+        ]
+    }
+
+    /// The variable counting bundles formed, whichever arity is in use.
+    fn bundle_control_var(&self) -> Option<Variable> {
+        self.y_bundle.or(self.bundle_formed)
+    }
+
     fn has_bundle_control_vars(&self) -> bool {
         self.y_bundle.is_some() || self.bundle_formed.is_some()
     }
@@ -215,6 +310,183 @@ impl MixAndMatchVars {
         state.add_leq_constraint(formed_expr, 0.0);
     }
 
+    fn add_amount_off_bundle_constraints(
+        &self,
+        promotion_key: PromotionKey,
+        state: &mut ILPState,
+        observer: &mut dyn ILPObserver,
+    ) {
+        let mut previous_formed = None;
+
+        for bundle in &self.amount_off_bundles {
+            // A bundle saves at most its own spend: s_b <= sum(price_i * x_{b,i})
+            let saving_expr = Expression::from(bundle.saving) - bundle.spend.clone();
+
+            observer.on_promotion_constraint(
+                promotion_key,
+                "amount off saving",
+                &saving_expr,
+                "<=",
+                0.0,
+            );
+
+            state.add_leq_constraint(saving_expr, 0.0);
+
+            // ... nothing unless it is formed: s_b <= amount * y_b
+            let formed_expr = Expression::from(bundle.saving)
+                - Expression::from(bundle.formed) * bundle.amount_off;
+
+            observer.on_promotion_constraint(
+                promotion_key,
+                "amount off saving (formed)",
+                &formed_expr,
+                "<=",
+                0.0,
+            );
+
+            state.add_leq_constraint(formed_expr, 0.0);
+
+            if bundle.covers_amount {
+                // s_b >= amount * y_b
+                let full_expr = Expression::from(bundle.saving)
+                    - Expression::from(bundle.formed) * bundle.amount_off;
+
+                observer.on_promotion_constraint(
+                    promotion_key,
+                    "amount off saving (full)",
+                    &full_expr,
+                    ">=",
+                    0.0,
+                );
+
+                state.add_geq_constraint(full_expr, 0.0);
+            } else if let Some(capped) = bundle.capped {
+                // ... and exactly the lesser of the two, so budgets see the real saving:
+                // s_b >= amount * c_b and s_b >= spend_b - headroom * c_b
+                let capped_expr =
+                    Expression::from(bundle.saving) - Expression::from(capped) * bundle.amount_off;
+
+                observer.on_promotion_constraint(
+                    promotion_key,
+                    "amount off saving (capped)",
+                    &capped_expr,
+                    ">=",
+                    0.0,
+                );
+
+                state.add_geq_constraint(capped_expr, 0.0);
+
+                let spend_expr = Expression::from(bundle.saving) - bundle.spend.clone()
+                    + Expression::from(capped) * bundle.headroom;
+
+                observer.on_promotion_constraint(
+                    promotion_key,
+                    "amount off saving (spend)",
+                    &spend_expr,
+                    ">=",
+                    0.0,
+                );
+
+                state.add_geq_constraint(spend_expr, 0.0);
+            }
+
+            if bundle.slot_vars.is_empty() {
+                continue;
+            }
+
+            // Each formed bundle fills every slot exactly.
+            for (slot, &(min, _max)) in bundle.slot_vars.iter().zip(&self.slot_bounds) {
+                let slot_sum: Expression = slot.iter().map(|&(_, var)| var).sum();
+                let expr = slot_sum - i32_from_usize(min) * bundle.formed;
+
+                observer.on_promotion_constraint(promotion_key, "bundle slot", &expr, "=", 0.0);
+                state.add_eq_constraint(expr, 0.0);
+            }
+
+            // Bundles are formed in order, so equivalent solutions are not revisited.
+            if let Some(previous) = previous_formed {
+                let expr = Expression::from(bundle.formed) - previous;
+
+                observer.on_promotion_constraint(promotion_key, "bundle order", &expr, "<=", 0.0);
+                state.add_leq_constraint(expr, 0.0);
+            }
+
+            previous_formed = Some(bundle.formed);
+        }
+
+        if previous_formed.is_some() {
+            self.add_full_amount_off_bundle_constraints(promotion_key, state, observer);
+        }
+    }
+
+    /// Tie the short bundles and the full bundles to the promotion's own selections.
+    fn add_full_amount_off_bundle_constraints(
+        &self,
+        promotion_key: PromotionKey,
+        state: &mut ILPState,
+        observer: &mut dyn ILPObserver,
+    ) {
+        let (Some(y_bundle), Some(full)) = (self.y_bundle, &self.full_amount_off_bundles) else {
+            return;
+        };
+
+        // Short bundles take cheap units from the promotion's slot selections...
+        for (slot_idx, slot) in self.slot_vars.iter().enumerate() {
+            for &(item_idx, var) in slot {
+                let mut expr = Expression::from(var);
+                let mut shared = false;
+
+                for bundle in &self.amount_off_bundles {
+                    for &(idx, bundle_var) in bundle.slot_vars.get(slot_idx).into_iter().flatten() {
+                        if idx == item_idx {
+                            expr -= bundle_var;
+                            shared = true;
+                        }
+                    }
+                }
+
+                if shared {
+                    observer.on_promotion_constraint(
+                        promotion_key,
+                        "bundle units",
+                        &expr,
+                        ">=",
+                        0.0,
+                    );
+
+                    state.add_geq_constraint(expr, 0.0);
+                }
+            }
+        }
+
+        // ... and every other bundle holds a unit covering the amount.
+        let formed_sum: Expression = self.amount_off_bundles.iter().map(|b| b.formed).sum();
+        let expr = Expression::from(y_bundle) - full.count - formed_sum;
+
+        observer.on_promotion_constraint(promotion_key, "bundle count", &expr, "=", 0.0);
+        state.add_eq_constraint(expr, 0.0);
+
+        let mut covering_expr = Expression::default();
+
+        for &(item_idx, var) in self.slot_vars.iter().flatten() {
+            if full.covering_items.contains(&item_idx) {
+                covering_expr += var;
+            }
+        }
+
+        let covering_expr = covering_expr - full.count;
+
+        observer.on_promotion_constraint(
+            promotion_key,
+            "covering units",
+            &covering_expr,
+            ">=",
+            0.0,
+        );
+
+        state.add_geq_constraint(covering_expr, 0.0);
+    }
+
     fn add_target_constraints(
         &self,
         promotion_key: PromotionKey,
@@ -251,15 +523,15 @@ impl MixAndMatchVars {
                 promotion_key,
                 state,
                 observer,
-                &selected_exprs,
                 target_sum,
                 y_bundle,
             );
         } else if let Some(bundle_formed) = self.bundle_formed {
-            Self::add_variable_arity_target_constraints(
+            self.add_variable_arity_target_constraints(
                 promotion_key,
                 state,
                 observer,
+                &selected_exprs,
                 target_sum,
                 bundle_formed,
             );
@@ -271,40 +543,47 @@ impl MixAndMatchVars {
         promotion_key: PromotionKey,
         state: &mut ILPState,
         observer: &mut dyn ILPObserver,
-        selected_exprs: &[Expression],
         target_sum: Expression,
         y_bundle: Variable,
     ) {
-        let mut prefix_selected = Expression::default();
+        // Each bundle's target is its cheapest unit, so a bundle targeted within the
+        // most expensive units also fills its slots from within them. For every slot
+        // and price-descending prefix: slot min * targets <= slot units selected.
         let mut prefix_targets = Expression::default();
+        let mut prefix_selected = vec![Expression::default(); self.slot_vars.len()];
 
-        let k_total = i32_from_usize(self.bundle_size);
-
-        if k_total > 0 {
-            for &(item_idx, _price) in &self.sorted_items {
-                let selected_expr = selected_exprs.get(item_idx).cloned().unwrap_or_default();
+        for &(item_idx, _price) in self.sorted_items.iter().rev() {
+            if let Some(target_var) = self.target_vars.get(item_idx).and_then(|v| *v) {
+                prefix_targets += target_var;
+            }
 
-                prefix_selected += selected_expr;
+            for ((slot, &(min, _max)), selected) in self
+                .slot_vars
+                .iter()
+                .zip(&self.slot_bounds)
+                .zip(&mut prefix_selected)
+            {
+                for &(idx, var) in slot {
+                    if idx == item_idx {
+                        *selected += var;
+                    }
+                }
 
-                if let Some(target_var) = self.target_vars.get(item_idx).and_then(|v| *v) {
-                    prefix_targets += target_var;
+                if min == 0 {
+                    continue;
                 }
 
-                let expr =
-                    prefix_targets.clone() - (prefix_selected.clone() - (k_total - 1) * y_bundle);
+                let expr = i32_from_usize(min) * prefix_targets.clone() - selected.clone();
 
                 observer.on_promotion_constraint(
                     promotion_key,
                     "cheapest prefix",
                     &expr,
-                    ">=",
+                    "<=",
                     0.0,
                 );
 
-                state.add_geq_constraint(
-                    prefix_targets.clone() - (prefix_selected.clone() - (k_total - 1) * y_bundle),
-                    0.0,
-                );
+                state.add_leq_constraint(expr, 0.0);
             }
         }
 
@@ -322,12 +601,45 @@ impl MixAndMatchVars {
     }
 
     fn add_variable_arity_target_constraints(
+        &self,
         promotion_key: PromotionKey,
         state: &mut ILPState,
         observer: &mut dyn ILPObserver,
+        selected_exprs: &[Expression],
         target_sum: Expression,
         bundle_formed: Variable,
     ) {
+        // The single bundle never holds more units than its slots allow.
+        let capacity = i32_from_usize(
+            self.slot_bounds
+                .iter()
+                .map(|&(min, max)| max.unwrap_or(min))
+                .sum(),
+        );
+
+        // The target must be the cheapest selected unit: any selected item needs
+        // the target at or below it in price order.
+        let mut prefix_targets = Expression::default();
+
+        for &(item_idx, _price) in &self.sorted_items {
+            if let Some(target_var) = self.target_vars.get(item_idx).and_then(|v| *v) {
+                prefix_targets += target_var;
+            }
+
+            let selected_expr = selected_exprs.get(item_idx).cloned().unwrap_or_default();
+            let expr = capacity * prefix_targets.clone() - selected_expr;
+
+            observer.on_promotion_constraint(
+                promotion_key,
+                "cheapest prefix (formed)",
+                &expr,
+                ">=",
+                0.0,
+            );
+
+            state.add_geq_constraint(expr, 0.0);
+        }
+
         let expr = target_sum - bundle_formed;
 
         observer.on_promotion_constraint(promotion_key, "target count (formed)", &expr, "=", 0.0);
@@ -335,6 +647,98 @@ impl MixAndMatchVars {
         state.add_eq_constraint(expr, 0.0);
     }
 
+    /// Total discount given by the promotion, in minor units, as a linear
+    /// expression over its variables.
+    fn monetary_discount_expr(
+        &self,
+        item_group: &ItemGroup<'_>,
+    ) -> Result<Expression, SolverError> {
+        let mut discount_expr = Expression::default();
+
+        match self.runtime_discount {
+            MixAndMatchRuntimeDiscount::PercentCheapest(_)
+            | MixAndMatchRuntimeDiscount::FixedCheapest(_) => {
+                // Cheapest-item modes are exact with target vars: only targets consume budget.
+                for (item_idx, target_var) in self.target_vars.iter().enumerate() {
+                    let Some(target_var) = target_var else {
+                        continue;
+                    };
+
+                    let item = item_group.get_item(item_idx).map_err(SolverError::from)?;
+                    let full_minor = item.price().to_minor_units();
+                    let discounted_minor = calculate_discounted_minor_for_budget(
+                        full_minor,
+                        self.runtime_discount,
+                        self.rounding,
+                    )?;
+
+                    let discount_amount = full_minor.saturating_sub(discounted_minor);
+                    let coeff = i64_to_f64_exact(discount_amount)
+                        .ok_or(SolverError::MinorUnitsNotRepresentable(discount_amount))?;
+
+                    discount_expr += *target_var * coeff;
+                }
+            }
+            MixAndMatchRuntimeDiscount::AmountOffTotal(amount) => {
+                // Each bundle formed takes the amount off its total, down to zero.
+                for bundle in &self.amount_off_bundles {
+                    discount_expr += bundle.saving;
+                }
+
+                if let Some(full) = &self.full_amount_off_bundles {
+                    let amount = amount.max(0);
+                    let coeff = i64_to_f64_exact(amount)
+                        .ok_or(SolverError::MinorUnitsNotRepresentable(amount))?;
+
+                    discount_expr += full.count * coeff;
+                }
+            }
+            MixAndMatchRuntimeDiscount::FixedTotal(bundle_price) => {
+                // Selected units give up their full price and each bundle
+                // formed is charged the fixed total instead.
+                for slot in &self.slot_vars {
+                    for &(item_idx, var) in slot {
+                        let full_minor = item_group.get_item(item_idx)?.price().to_minor_units();
+                        let coeff = i64_to_f64_exact(full_minor)
+                            .ok_or(SolverError::MinorUnitsNotRepresentable(full_minor))?;
+
+                        discount_expr += var * coeff;
+                    }
+                }
+
+                if let Some(bundle_var) = self.bundle_control_var() {
+                    let coeff = i64_to_f64_exact(bundle_price)
+                        .ok_or(SolverError::MinorUnitsNotRepresentable(bundle_price))?;
+
+                    discount_expr -= bundle_var * coeff;
+                }
+            }
+            MixAndMatchRuntimeDiscount::PercentAllItems(_)
+            | MixAndMatchRuntimeDiscount::AmountOffEachItem(_)
+            | MixAndMatchRuntimeDiscount::FixedPriceEachItem(_) => {
+                for slot in &self.slot_vars {
+                    for &(item_idx, var) in slot {
+                        let item = item_group.get_item(item_idx).map_err(SolverError::from)?;
+                        let full_minor = item.price().to_minor_units();
+                        let discounted_minor = calculate_discounted_minor_for_budget(
+                            full_minor,
+                            self.runtime_discount,
+                            self.rounding,
+                        )?;
+
+                        let discount_amount = full_minor.saturating_sub(discounted_minor);
+                        let coeff = i64_to_f64_exact(discount_amount)
+                            .ok_or(SolverError::MinorUnitsNotRepresentable(discount_amount))?;
+
+                        discount_expr += var * coeff;
+                    }
+                }
+            }
+        }
+
+        Ok(discount_expr)
+    }
+
     /// Add budget constraints for mix-and-match promotions
     pub fn add_budget_constraints(
         &self,
@@ -383,55 +787,25 @@ impl MixAndMatchVars {
 
         // Monetary limit: sum(discount_amount * participation_var) <= limit
         if let Some(limit_minor) = self.monetary_limit_minor {
-            let mut discount_expr = Expression::default();
-
-            match self.runtime_discount {
-                MixAndMatchRuntimeDiscount::PercentCheapest(_)
-                | MixAndMatchRuntimeDiscount::FixedCheapest(_) => {
-                    // Cheapest-item modes are exact with target vars: only targets consume budget.
-                    for (item_idx, target_var) in self.target_vars.iter().enumerate() {
-                        let Some(target_var) = target_var else {
-                            continue;
-                        };
-
-                        let item = item_group.get_item(item_idx).map_err(SolverError::from)?;
-                        let full_minor = item.price().to_minor_units();
-                        let discounted_minor = calculate_discounted_minor_for_budget(
-                            full_minor,
-                            self.runtime_discount,
-                        )?;
-
-                        let discount_amount = full_minor.saturating_sub(discounted_minor);
-                        let coeff = i64_to_f64_exact(discount_amount)
-                            .ok_or(SolverError::MinorUnitsNotRepresentable(discount_amount))?;
+            let floor_excess = state.floor_excess(self.promotion_key);
+
+            // Whole minor units on integer variables can take half a unit of slack
+            // without changing the row, which keeps microlp clear of rounding
+            // trouble at the limit. A floor excess may be continuous, so it can't.
+            let slack = if IntoAffineExpression::linear_coefficients(&floor_excess)
+                .next()
+                .is_some()
+            {
+                0.0
+            } else {
+                0.5
+            };
 
-                        discount_expr += *target_var * coeff;
-                    }
-                }
-                _ => {
-                    // Iterate over all slot variables to compute total discount.
-                    // For bundle-total discounts this remains a conservative estimate.
-                    for slot in &self.slot_vars {
-                        for &(item_idx, var) in slot {
-                            let item = item_group.get_item(item_idx).map_err(SolverError::from)?;
-                            let full_minor = item.price().to_minor_units();
-                            let discounted_minor = calculate_discounted_minor_for_budget(
-                                full_minor,
-                                self.runtime_discount,
-                            )?;
-
-                            let discount_amount = full_minor.saturating_sub(discounted_minor);
-                            let coeff = i64_to_f64_exact(discount_amount)
-                                .ok_or(SolverError::MinorUnitsNotRepresentable(discount_amount))?;
-
-                            discount_expr += var * coeff;
-                        }
-                    }
-                }
-            }
+            let discount_expr = self.monetary_discount_expr(item_group)? - floor_excess;
 
             let limit_f64 = i64_to_f64_exact(limit_minor)
-                .ok_or(SolverError::MinorUnitsNotRepresentable(limit_minor))?;
+                .ok_or(SolverError::MinorUnitsNotRepresentable(limit_minor))?
+                + slack;
 
             observer.on_promotion_constraint(
                 self.promotion_key,
@@ -483,6 +857,14 @@ impl ILPPromotionVars for MixAndMatchVars {
         })
     }
 
+    fn add_item_discount_term(&self, expr: Expression, item_idx: usize) -> Expression {
+        // Cheapest-item modes only discount the targeted units.
+        match self.target_vars.get(item_idx).and_then(|v| *v) {
+            Some(var) => expr + var,
+            None => self.add_item_participation_term(expr, item_idx),
+        }
+    }
+
     fn is_item_priced_by_promotion(&self, solution: &dyn Solution, item_idx: usize) -> bool {
         if let Some(var) = self.target_vars.get(item_idx).and_then(|v| *v) {
             return solution.value(var) > BINARY_THRESHOLD;
@@ -491,6 +873,84 @@ impl ILPPromotionVars for MixAndMatchVars {
         self.is_item_participating(solution, item_idx)
     }
 
+    fn item_price_outcomes(
+        &self,
+        item_group: &ItemGroup<'_>,
+        item_idx: usize,
+    ) -> Result<Option<PriceOutcomes>, SolverError> {
+        let mut selected_expr = Expression::default();
+        let mut is_slotted = false;
+
+        for slot in &self.slot_vars {
+            for &(idx, var) in slot {
+                if idx == item_idx {
+                    selected_expr += var;
+                    is_slotted = true;
+                }
+            }
+        }
+
+        if !is_slotted {
+            return Ok(Some(PriceOutcomes::new()));
+        }
+
+        // Rounding per bundle makes a unit's price depend on the rest of its bundle.
+        if self.is_rounded_per_bundle() {
+            return Ok(None);
+        }
+
+        let original_minor = item_group.get_item(item_idx)?.price().to_minor_units();
+
+        let final_minor = match self.runtime_discount {
+            MixAndMatchRuntimeDiscount::PercentAllItems(pct) => {
+                discounted_minor_percent(&pct, original_minor, self.rounding)?
+            }
+            MixAndMatchRuntimeDiscount::AmountOffEachItem(amount_off) => {
+                original_minor.saturating_sub(amount_off).max(0)
+            }
+            MixAndMatchRuntimeDiscount::FixedPriceEachItem(fixed_minor) => fixed_minor.max(0),
+            MixAndMatchRuntimeDiscount::PercentCheapest(pct) => {
+                let target_minor = discounted_minor_percent(&pct, original_minor, self.rounding)?;
+
+                return Ok(Some(self.cheapest_price_outcomes(
+                    selected_expr,
+                    item_idx,
+                    original_minor,
+                    target_minor,
+                )));
+            }
+            MixAndMatchRuntimeDiscount::FixedCheapest(fixed_minor) => {
+                return Ok(Some(self.cheapest_price_outcomes(
+                    selected_expr,
+                    item_idx,
+                    original_minor,
+                    fixed_minor.max(0),
+                )));
+            }
+            // Bundle totals are allocated across the whole bundle after solving,
+            // so an individual item's final price is not a linear outcome.
+            MixAndMatchRuntimeDiscount::AmountOffTotal(_)
+            | MixAndMatchRuntimeDiscount::FixedTotal(_) => return Ok(None),
+        };
+
+        Ok(Some(smallvec![(selected_expr, final_minor)]))
+    }
+
+    fn redemption_count_expr(&self) -> Option<Expression> {
+        // Without a bundle counter no bundle can form, so nothing is redeemed.
+        Some(
+            self.bundle_control_var()
+                .map_or_else(Expression::default, Expression::from),
+        )
+    }
+
+    fn discount_value_expr(
+        &self,
+        item_group: &ItemGroup<'_>,
+    ) -> Result<Option<Expression>, SolverError> {
+        self.monetary_discount_expr(item_group).map(Some)
+    }
+
     fn add_constraints(
         &self,
         promotion_key: PromotionKey,
@@ -523,29 +983,43 @@ impl ILPPromotionVars for MixAndMatchVars {
             return Ok(SmallVec::new());
         }
 
-        let discounts = calculate_discounts_for_vars(solution, self, item_group)?;
+        let unit_prices = bundle_unit_prices(solution, self, item_group, &bundles)?;
         let currency = item_group.currency();
 
-        let mut redemptions = SmallVec::new();
+        let mut redemptions: SmallVec<[PromotionRedemption<'b>; 10]> = SmallVec::new();
 
-        for bundle in bundles {
+        for (bundle, prices) in bundles.iter().zip(unit_prices) {
             let redemption_idx = *next_redemption_idx;
             *next_redemption_idx += 1;
 
-            for item_idx in bundle {
-                let item = item_group.get_item(item_idx)?;
-                let original_minor = item.price().to_minor_units();
+            let bundle_start = redemptions.len();
 
-                let final_minor = discounts
-                    .get(&item_idx)
-                    .map_or(original_minor, |(_, final_minor)| *final_minor);
+            for (&item_idx, final_minor) in bundle.iter().zip(prices) {
+                let final_price = Money::from_minor(final_minor, currency);
+
+                // Units of the same line at the same price share one redemption entry.
+                let existing = redemptions.get_mut(bundle_start..).and_then(|entries| {
+                    entries.iter_mut().find(|redemption| {
+                        redemption.item_idx == item_idx && redemption.final_price == final_price
+                    })
+                });
+
+                if let Some(redemption) = existing {
+                    redemption.quantity += 1;
+                    continue;
+                }
+
+                let item = item_group.get_item(item_idx)?;
 
                 redemptions.push(PromotionRedemption {
                     promotion_key,
                     item_idx,
                     redemption_idx,
                     original_price: *item.price(),
-                    final_price: Money::from_minor(final_minor, currency),
+                    final_price,
+                    quantity: 1,
+                    funding: SmallVec::new(),
+                    floor_limited: false,
                 });
             }
         }
@@ -554,8 +1028,14 @@ impl ILPPromotionVars for MixAndMatchVars {
     }
 }
 
-fn discounted_minor_percent(pct: &Percentage, original_minor: i64) -> Result<i64, SolverError> {
-    let discount_minor = percent_of_minor(pct, original_minor).map_err(SolverError::Discount)?;
+fn discounted_minor_percent(
+    pct: &Percentage,
+    original_minor: i64,
+    rounding: RoundingPolicy,
+) -> Result<i64, SolverError> {
+    let discount_minor = rounding
+        .discount_of(pct, original_minor)
+        .map_err(SolverError::Discount)?;
 
     Ok(original_minor.saturating_sub(discount_minor))
 }
@@ -593,22 +1073,27 @@ fn runtime_discount_from_config(discount: &MixAndMatchDiscount<'_>) -> MixAndMat
 fn calculate_discounted_minor_for_budget(
     full_minor: i64,
     discount: MixAndMatchRuntimeDiscount,
+    rounding: RoundingPolicy,
 ) -> Result<i64, SolverError> {
     let discounted_minor = match discount {
         MixAndMatchRuntimeDiscount::PercentAllItems(pct)
         | MixAndMatchRuntimeDiscount::PercentCheapest(pct) => {
-            let discount_amount =
-                percent_of_minor(&pct, full_minor).map_err(SolverError::Discount)?;
+            let discount_amount = rounding
+                .discount_of(&pct, full_minor)
+                .map_err(SolverError::Discount)?;
 
             full_minor.saturating_sub(discount_amount)
         }
         MixAndMatchRuntimeDiscount::AmountOffEachItem(amount_off) => {
             full_minor.saturating_sub(amount_off)
         }
-        // Conservative approximation for budgeting bundle-total discounts:
-        // assume each selected item could be fully discounted.
+        // Bundle totals are budgeted per bundle, not per item.
         MixAndMatchRuntimeDiscount::AmountOffTotal(_)
-        | MixAndMatchRuntimeDiscount::FixedTotal(_) => 0,
+        | MixAndMatchRuntimeDiscount::FixedTotal(_) => {
+            return Err(SolverError::InvariantViolation {
+                message: "bundle-total discounts have no per-item budget price",
+            });
+        }
         MixAndMatchRuntimeDiscount::FixedPriceEachItem(fixed_minor)
         | MixAndMatchRuntimeDiscount::FixedCheapest(fixed_minor) => fixed_minor,
     };
@@ -631,6 +1116,10 @@ fn proportional_alloc(total: i64, part: i64, denom: i64) -> i64 {
     i64::try_from(value).unwrap_or(0)
 }
 
+/// Group the selected units into bundles.
+///
+/// Each bundle lists one item index per unit, so a quantity line contributing
+/// several units to a bundle appears several times.
 fn build_bundles(solution: &dyn Solution, vars: &MixAndMatchVars) -> Vec<Vec<usize>> {
     let bundles_applied = vars.bundle_count(solution);
 
@@ -645,7 +1134,7 @@ fn build_bundles(solution: &dyn Solution, vars: &MixAndMatchVars) -> Vec<Vec<usi
         let mut items = Vec::new();
 
         for &(item_idx, var) in slot_vars {
-            if solution.value(var) > BINARY_THRESHOLD {
+            for _unit in 0..solution_units(solution, var) {
                 items.push(item_idx);
             }
         }
@@ -655,6 +1144,38 @@ fn build_bundles(solution: &dyn Solution, vars: &MixAndMatchVars) -> Vec<Vec<usi
 
     let mut bundles = Vec::new();
 
+    // Amount-off bundles are priced as the solver formed them.
+    if let (Some(_), Some(full)) = (vars.y_bundle, &vars.full_amount_off_bundles) {
+        for bundle in &vars.amount_off_bundles {
+            let mut bundle_items = Vec::new();
+
+            for (slot, items) in bundle.slot_vars.iter().zip(slot_items.iter_mut()) {
+                for &(item_idx, var) in slot {
+                    for _unit in 0..solution_units(solution, var) {
+                        bundle_items.push(item_idx);
+
+                        if let Some(pos) = items.iter().position(|&idx| idx == item_idx) {
+                            items.remove(pos);
+                        }
+                    }
+                }
+            }
+
+            if !bundle_items.is_empty() {
+                bundles.push(bundle_items);
+            }
+        }
+
+        bundles.extend(build_full_amount_off_bundles(
+            solution_units(solution, full.count) as usize,
+            full,
+            &vars.slot_bounds,
+            slot_items,
+        ));
+
+        return bundles;
+    }
+
     if vars.y_bundle.is_some() {
         for redemption_idxx in 0..bundles_applied {
             let mut bundle_items = Vec::new();
@@ -681,19 +1202,204 @@ fn build_bundles(solution: &dyn Solution, vars: &MixAndMatchVars) -> Vec<Vec<usi
     } else {
         let mut bundle_items = Vec::new();
 
-        for items in slot_items {
-            bundle_items.extend(items);
-        }
+        for items in slot_items {
+            bundle_items.extend(items);
+        }
+
+        if !bundle_items.is_empty() {
+            bundles.push(bundle_items);
+        }
+    }
+
+    bundles
+}
+
+/// Share the units left over by the short bundles between `count` full bundles.
+///
+/// Covering units go first, one to each bundle still without one, so every bundle
+/// saves the whole amount off.
+fn build_full_amount_off_bundles(
+    count: usize,
+    full: &FullAmountOffBundles,
+    slot_bounds: &[(usize, Option<usize>)],
+    slot_items: Vec<Vec<usize>>,
+) -> Vec<Vec<usize>> {
+    // (units, covered, units taken from the current slot)
+    let mut bundles = vec![(Vec::new(), false, 0_usize); count];
+
+    for (mut items, &(min, _max)) in slot_items.into_iter().zip(slot_bounds) {
+        items.sort_by_key(|idx| !full.covering_items.contains(idx));
+
+        for bundle in &mut bundles {
+            bundle.2 = 0;
+        }
+
+        for item_idx in items {
+            let covering = full.covering_items.contains(&item_idx);
+            let mut open = bundles.iter_mut().filter(|bundle| bundle.2 < min);
+
+            let target = if covering {
+                open.min_by_key(|bundle| bundle.1)
+            } else {
+                open.next()
+            };
+
+            if let Some(bundle) = target {
+                bundle.0.push(item_idx);
+                bundle.1 |= covering;
+                bundle.2 += 1;
+            }
+        }
+    }
+
+    bundles
+        .into_iter()
+        .map(|(units, _covered, _taken)| units)
+        .filter(|units| !units.is_empty())
+        .collect()
+}
+
+/// Final price of every unit in each bundle, in the same order as the bundle units.
+///
+/// Bundle totals are allocated proportionally across the bundle's units (with any
+/// rounding remainder on the last unit). Cheapest-item discounts go to as many units
+/// of each line as its target count selects.
+fn bundle_unit_prices(
+    solution: &dyn Solution,
+    vars: &MixAndMatchVars,
+    item_group: &ItemGroup<'_>,
+    bundles: &[Vec<usize>],
+) -> Result<Vec<Vec<i64>>, SolverError> {
+    let mut remaining_targets: SmallVec<[u32; 10]> = vars
+        .target_vars
+        .iter()
+        .map(|var| var.map_or(0, |var| solution_units(solution, var)))
+        .collect();
+
+    let mut prices = Vec::with_capacity(bundles.len());
+
+    for bundle_items in bundles {
+        let mut original_total = 0_i64;
+
+        for &item_idx in bundle_items {
+            original_total += item_group.get_item(item_idx)?.price().to_minor_units();
+        }
+
+        let bundle_total = match vars.runtime_discount {
+            MixAndMatchRuntimeDiscount::AmountOffTotal(amount_off) => {
+                Some(original_total.saturating_sub(amount_off).max(0))
+            }
+            MixAndMatchRuntimeDiscount::FixedTotal(bundle_price) => Some(bundle_price),
+            _ => None,
+        };
+
+        let percent_prices = match vars.runtime_discount {
+            MixAndMatchRuntimeDiscount::PercentAllItems(pct) => Some(percent_unit_prices(
+                vars,
+                &pct,
+                item_group,
+                bundle_items,
+                None,
+            )?),
+            MixAndMatchRuntimeDiscount::PercentCheapest(pct) => Some(percent_unit_prices(
+                vars,
+                &pct,
+                item_group,
+                bundle_items,
+                Some(&mut remaining_targets),
+            )?),
+            _ => None,
+        };
+
+        let mut unit_prices = Vec::with_capacity(bundle_items.len());
+        let mut remaining = bundle_total.unwrap_or_default();
+
+        for (i, &item_idx) in bundle_items.iter().enumerate() {
+            let original_minor = item_group.get_item(item_idx)?.price().to_minor_units();
+
+            let final_minor = match (bundle_total, vars.runtime_discount) {
+                (Some(_), _) if i == bundle_items.len() - 1 => remaining,
+                (Some(_), _) if original_total == 0 => 0,
+                (Some(total), _) => proportional_alloc(total, original_minor, original_total),
+                (
+                    None,
+                    MixAndMatchRuntimeDiscount::PercentAllItems(_)
+                    | MixAndMatchRuntimeDiscount::PercentCheapest(_),
+                ) => percent_prices
+                    .as_ref()
+                    .and_then(|prices| prices.get(i).copied())
+                    .unwrap_or(original_minor),
+                (None, MixAndMatchRuntimeDiscount::AmountOffEachItem(amount_off)) => {
+                    original_minor.saturating_sub(amount_off).max(0)
+                }
+                (None, MixAndMatchRuntimeDiscount::FixedPriceEachItem(fixed_minor)) => {
+                    fixed_minor.max(0)
+                }
+                (None, MixAndMatchRuntimeDiscount::FixedCheapest(fixed_minor)) => {
+                    if take_target_unit(&mut remaining_targets, item_idx) {
+                        fixed_minor.max(0)
+                    } else {
+                        original_minor
+                    }
+                }
+                (
+                    None,
+                    MixAndMatchRuntimeDiscount::AmountOffTotal(_)
+                    | MixAndMatchRuntimeDiscount::FixedTotal(_),
+                ) => original_minor,
+            };
+
+            remaining -= final_minor;
+            unit_prices.push(final_minor);
+        }
+
+        prices.push(unit_prices);
+    }
+
+    Ok(prices)
+}
+
+/// Final price of every unit in a bundle under a percentage discount.
+///
+/// With `remaining_targets` only the targeted cheapest units are discounted.
+/// Discounted units are rounded together when the promotion rounds per bundle.
+fn percent_unit_prices(
+    vars: &MixAndMatchVars,
+    pct: &Percentage,
+    item_group: &ItemGroup<'_>,
+    bundle_items: &[usize],
+    mut remaining_targets: Option<&mut SmallVec<[u32; 10]>>,
+) -> Result<SmallVec<[i64; 10]>, SolverError> {
+    let mut prices: SmallVec<[i64; 10]> = SmallVec::with_capacity(bundle_items.len());
+    let mut discounted: SmallVec<[bool; 10]> = SmallVec::with_capacity(bundle_items.len());
+
+    for &item_idx in bundle_items {
+        prices.push(item_group.get_item(item_idx)?.price().to_minor_units());
+        discounted.push(
+            remaining_targets
+                .as_deref_mut()
+                .is_none_or(|targets| take_target_unit(targets, item_idx)),
+        );
+    }
+
+    vars.rounding
+        .discount_units(pct, &mut prices, &discounted)
+        .map_err(SolverError::Discount)?;
 
-        if !bundle_items.is_empty() {
-            bundles.push(bundle_items);
+    Ok(prices)
+}
+
+/// Consume one targeted unit of `item_idx`, returning whether one was left.
+fn take_target_unit(remaining_targets: &mut [u32], item_idx: usize) -> bool {
+    match remaining_targets.get_mut(item_idx) {
+        Some(remaining) if *remaining > 0 => {
+            *remaining -= 1;
+            true
         }
+        _ => false,
     }
-
-    bundles
 }
 
-#[expect(clippy::too_many_lines, reason = "Complex discount calculation logic")]
 fn calculate_discounts_for_vars(
     solution: &dyn Solution,
     vars: &MixAndMatchVars,
@@ -701,6 +1407,28 @@ fn calculate_discounts_for_vars(
 ) -> Result<FxHashMap<usize, (i64, i64)>, SolverError> {
     let mut discounts = FxHashMap::default();
 
+    // Bundle totals, and percentages rounded per bundle, are only known bundle by bundle.
+    if vars.is_rounded_per_bundle()
+        || matches!(
+            vars.runtime_discount,
+            MixAndMatchRuntimeDiscount::AmountOffTotal(_)
+                | MixAndMatchRuntimeDiscount::FixedTotal(_)
+        )
+    {
+        let bundles = build_bundles(solution, vars);
+        let unit_prices = bundle_unit_prices(solution, vars, item_group, &bundles)?;
+
+        for (bundle_items, prices) in bundles.iter().zip(unit_prices) {
+            for (&item_idx, final_minor) in bundle_items.iter().zip(prices) {
+                let original_minor = item_group.get_item(item_idx)?.price().to_minor_units();
+
+                discounts.insert(item_idx, (original_minor, final_minor));
+            }
+        }
+
+        return Ok(discounts);
+    }
+
     match vars.runtime_discount {
         MixAndMatchRuntimeDiscount::PercentAllItems(pct) => {
             for (item_idx, item) in item_group.iter().enumerate() {
@@ -709,7 +1437,8 @@ fn calculate_discounts_for_vars(
                 }
 
                 let original_minor = item.price().to_minor_units();
-                let discounted_minor = discounted_minor_percent(&pct, original_minor)?;
+                let discounted_minor =
+                    discounted_minor_percent(&pct, original_minor, vars.rounding)?;
 
                 discounts.insert(item_idx, (original_minor, discounted_minor));
             }
@@ -748,7 +1477,8 @@ fn calculate_discounts_for_vars(
                 discounts.insert(item_idx, (original_minor, original_minor));
 
                 if vars.is_item_priced_by_promotion(solution, item_idx) {
-                    let discounted_minor = discounted_minor_percent(&pct, original_minor)?;
+                    let discounted_minor =
+                        discounted_minor_percent(&pct, original_minor, vars.rounding)?;
 
                     discounts.insert(item_idx, (original_minor, discounted_minor));
                 }
@@ -773,82 +1503,337 @@ fn calculate_discounts_for_vars(
                 discounts.insert(item_idx, (original_minor, final_minor));
             }
         }
-        MixAndMatchRuntimeDiscount::AmountOffTotal(amount_off) => {
-            let bundles = build_bundles(solution, vars);
+        // Priced bundle by bundle above.
+        MixAndMatchRuntimeDiscount::AmountOffTotal(_)
+        | MixAndMatchRuntimeDiscount::FixedTotal(_) => {}
+    }
 
-            for bundle_items in bundles {
-                if bundle_items.is_empty() {
-                    continue;
-                }
+    Ok(discounts)
+}
+
+/// Least a bundle filling every slot's minimum from these selections could cost.
+///
+/// Slots may share units, so this is a lower bound rather than the cheapest bundle.
+fn min_bundle_spend(
+    item_group: &ItemGroup<'_>,
+    slot_vars: &[SmallVec<[(usize, Variable); 10]>],
+    slot_bounds: &[(usize, Option<usize>)],
+) -> Result<i64, SolverError> {
+    let mut spend = 0_i64;
 
-                let mut original_total = 0_i64;
+    for (slot, &(min, _max)) in slot_vars.iter().zip(slot_bounds) {
+        let mut units = Vec::with_capacity(slot.len());
 
-                for &item_idx in &bundle_items {
-                    let item = item_group.get_item(item_idx)?;
+        for &(item_idx, _var) in slot {
+            let item = item_group.get_item(item_idx)?;
 
-                    original_total += item.price().to_minor_units();
-                }
+            units.push((item.price().to_minor_units(), item.quantity() as usize));
+        }
 
-                let bundle_total = original_total.saturating_sub(amount_off).max(0);
-                let mut remaining = bundle_total;
+        units.sort_unstable();
 
-                for (i, &item_idx) in bundle_items.iter().enumerate() {
-                    let item = item_group.get_item(item_idx)?;
-                    let original_minor = item.price().to_minor_units();
+        let mut needed = min;
 
-                    let final_minor = if i == bundle_items.len() - 1 {
-                        remaining
-                    } else if original_total == 0 {
-                        0
-                    } else {
-                        proportional_alloc(bundle_total, original_minor, original_total)
-                    };
+        for (price, quantity) in units {
+            let taken = needed.min(quantity);
+
+            spend = spend.saturating_add(price.saturating_mul(i64::try_from(taken).unwrap_or(0)));
+            needed -= taken;
+        }
+
+        // A slot that cannot be filled forms no bundle at all.
+        if needed > 0 {
+            return Ok(i64::MAX);
+        }
+    }
+
+    Ok(spend)
+}
+
+impl MixAndMatchPromotion<'_> {
+    /// Bundles for an amount-off-total discount, each saving the amount or its own
+    /// total if that is less.
+    ///
+    /// A variable-arity promotion forms at most one bundle, from its slot variables.
+    /// A fixed-arity bundle holding a unit that costs at least the amount always saves
+    /// all of it, so those bundles are only counted; just the bundles made up entirely
+    /// of cheaper units get their own slot variables.
+    #[expect(clippy::too_many_arguments, reason = "mirrors add_variables' state")]
+    fn add_amount_off_bundles(
+        &self,
+        item_group: &ItemGroup<'_>,
+        slot_vars: &[SmallVec<[(usize, Variable); 10]>],
+        slot_bounds: &[(usize, Option<usize>)],
+        y_bundle: Option<Variable>,
+        bundle_formed: Option<Variable>,
+        max_bundles: usize,
+        state: &mut ILPState,
+        observer: &mut dyn ILPObserver,
+    ) -> Result<(Vec<AmountOffBundle>, Option<FullAmountOffBundles>), SolverError> {
+        let MixAndMatchDiscount::AmountOffTotal(amount) = self.discount() else {
+            return Ok((Vec::new(), None));
+        };
+
+        let promotion_key = self.key();
+        let amount_off = amount.to_minor_units().max(0);
+        let max_saving = i64_to_f64_exact(amount_off)
+            .ok_or(SolverError::MinorUnitsNotRepresentable(amount_off))?;
+
+        // The saving only needs pinning down when a monetary budget could hold it back.
+        let budget_limited =
+            self.budget().monetary_limit.is_some() || !self.budget_pools().is_empty();
+
+        let covers_amount = min_bundle_spend(item_group, slot_vars, slot_bounds)? >= amount_off;
+
+        if let Some(formed) = bundle_formed {
+            let bundle = self.add_amount_off_bundle(
+                item_group,
+                formed,
+                Vec::new(),
+                slot_vars,
+                (max_saving, covers_amount, budget_limited),
+                0,
+                state,
+                observer,
+            )?;
+
+            return Ok((vec![bundle], None));
+        }
 
-                    remaining -= final_minor;
+        let Some(y_bundle) = y_bundle else {
+            return Ok((Vec::new(), None));
+        };
+
+        // Only units cheaper than the amount can make up a bundle that saves less.
+        let mut cheap_slot_vars = Vec::with_capacity(slot_vars.len());
+        let mut covering_items: SmallVec<[usize; 10]> = SmallVec::new();
+        let mut short_bundles = max_bundles;
+
+        for (slot, &(min, _max)) in slot_vars.iter().zip(slot_bounds) {
+            let mut cheap: SmallVec<[(usize, Variable); 10]> = SmallVec::new();
+            let mut cheap_units = 0_usize;
+
+            for &(item_idx, var) in slot {
+                let item = item_group.get_item(item_idx)?;
 
-                    discounts.insert(item_idx, (original_minor, final_minor));
+                if item.price().to_minor_units() < amount_off {
+                    cheap.push((item_idx, var));
+                    cheap_units += item.quantity() as usize;
+                } else if !covering_items.contains(&item_idx) {
+                    covering_items.push(item_idx);
                 }
             }
+
+            if min > 0 {
+                short_bundles = short_bundles.min(cheap_units / min);
+            }
+
+            cheap_slot_vars.push(cheap);
         }
-        MixAndMatchRuntimeDiscount::FixedTotal(bundle_price) => {
-            let bundles = build_bundles(solution, vars);
 
-            for bundle_items in bundles {
-                if bundle_items.is_empty() {
-                    continue;
-                }
+        if covers_amount {
+            short_bundles = 0;
+        }
 
-                let mut original_total = 0_i64;
+        let coeff = -max_saving;
 
-                for &item_idx in &bundle_items {
-                    let item = item_group.get_item(item_idx)?;
+        if short_bundles == 0 {
+            // Every bundle saves the amount in full.
+            state.add_to_objective(y_bundle, coeff);
+            observer.on_objective_term(y_bundle, coeff);
 
-                    original_total += item.price().to_minor_units();
-                }
+            return Ok((
+                Vec::new(),
+                Some(FullAmountOffBundles {
+                    count: y_bundle,
+                    covering_items,
+                }),
+            ));
+        }
 
-                let mut remaining = bundle_price;
+        let full_count = state
+            .problem_variables_mut()
+            .add(variable().integer().min(0).max(i32_from_usize(max_bundles)));
 
-                for (i, &item_idx) in bundle_items.iter().enumerate() {
-                    let item = item_group.get_item(item_idx)?;
-                    let original_minor = item.price().to_minor_units();
+        observer.on_auxiliary_variable(promotion_key, full_count, "full bundles", None, None);
 
-                    let final_minor = if i == bundle_items.len() - 1 {
-                        remaining
-                    } else if original_total == 0 {
-                        0
-                    } else {
-                        proportional_alloc(bundle_price, original_minor, original_total)
-                    };
+        state.add_to_objective(full_count, coeff);
+        observer.on_objective_term(full_count, coeff);
 
-                    remaining -= final_minor;
+        let bundles = self.add_short_amount_off_bundles(
+            item_group,
+            &cheap_slot_vars,
+            slot_bounds,
+            short_bundles,
+            (max_saving, budget_limited),
+            state,
+            observer,
+        )?;
+
+        Ok((
+            bundles,
+            Some(FullAmountOffBundles {
+                count: full_count,
+                covering_items,
+            }),
+        ))
+    }
+
+    /// Bundles made up entirely of units cheaper than the amount off, each with its
+    /// own slot variables so its saving can fall short of the amount.
+    #[expect(clippy::too_many_arguments, reason = "mirrors add_variables' state")]
+    fn add_short_amount_off_bundles(
+        &self,
+        item_group: &ItemGroup<'_>,
+        cheap_slot_vars: &[SmallVec<[(usize, Variable); 10]>],
+        slot_bounds: &[(usize, Option<usize>)],
+        short_bundles: usize,
+        (max_saving, budget_limited): (f64, bool),
+        state: &mut ILPState,
+        observer: &mut dyn ILPObserver,
+    ) -> Result<Vec<AmountOffBundle>, SolverError> {
+        let promotion_key = self.key();
+        let mut bundles = Vec::with_capacity(short_bundles);
+
+        for bundle_idx in 0..short_bundles {
+            let formed = state.problem_variables_mut().add(variable().binary());
+
+            observer.on_auxiliary_variable(
+                promotion_key,
+                formed,
+                "bundle formed",
+                Some(bundle_idx),
+                None,
+            );
+
+            let mut bundle_slot_vars = Vec::with_capacity(cheap_slot_vars.len());
+
+            for (slot, &(min, _max)) in cheap_slot_vars.iter().zip(slot_bounds) {
+                let slot_units = u32::try_from(min).unwrap_or(u32::MAX);
+                let mut vars = SmallVec::new();
 
-                    discounts.insert(item_idx, (original_minor, final_minor));
+                for &(item_idx, _var) in slot {
+                    let quantity = item_group.get_item(item_idx)?.quantity();
+                    let var = state
+                        .problem_variables_mut()
+                        .add(item_units_variable(quantity.min(slot_units)));
+
+                    observer.on_auxiliary_variable(
+                        promotion_key,
+                        var,
+                        "bundle slot units",
+                        Some(bundle_idx),
+                        None,
+                    );
+
+                    vars.push((item_idx, var));
                 }
+
+                bundle_slot_vars.push(vars);
             }
+
+            let bundle = self.add_amount_off_bundle(
+                item_group,
+                formed,
+                bundle_slot_vars,
+                &[],
+                (max_saving, false, budget_limited),
+                bundle_idx,
+                state,
+                observer,
+            )?;
+
+            bundles.push(bundle);
         }
+
+        Ok(bundles)
     }
 
-    Ok(discounts)
+    /// One bundle's saving variable, bounded once constraints are added.
+    ///
+    /// The bundle's spend is taken from its own slot variables, or from
+    /// `promotion_slot_vars` when it has none.
+    #[expect(clippy::too_many_arguments, reason = "mirrors add_variables' state")]
+    fn add_amount_off_bundle(
+        &self,
+        item_group: &ItemGroup<'_>,
+        formed: Variable,
+        slot_vars: Vec<SmallVec<[(usize, Variable); 10]>>,
+        promotion_slot_vars: &[SmallVec<[(usize, Variable); 10]>],
+        (amount_off, covers_amount, budget_limited): (f64, bool, bool),
+        bundle_idx: usize,
+        state: &mut ILPState,
+        observer: &mut dyn ILPObserver,
+    ) -> Result<AmountOffBundle, SolverError> {
+        let promotion_key = self.key();
+        let mut spend = Expression::default();
+        let mut max_spend = 0_i64;
+
+        let spend_vars = if slot_vars.is_empty() {
+            promotion_slot_vars
+        } else {
+            slot_vars.as_slice()
+        };
+
+        for &(item_idx, var) in spend_vars.iter().flatten() {
+            let item = item_group.get_item(item_idx)?;
+            let price = item.price().to_minor_units();
+            let coeff =
+                i64_to_f64_exact(price).ok_or(SolverError::MinorUnitsNotRepresentable(price))?;
+
+            spend += var * coeff;
+            max_spend =
+                max_spend.saturating_add(price.max(0).saturating_mul(i64::from(item.quantity())));
+        }
+
+        // Past the amount off, the spend only needs to be relaxed by what is left over.
+        let amount_off_minor = amount_off.to_i64().unwrap_or(0);
+        let headroom = max_spend.saturating_sub(amount_off_minor).max(0);
+        let headroom =
+            i64_to_f64_exact(headroom).ok_or(SolverError::MinorUnitsNotRepresentable(headroom))?;
+
+        let saving = state
+            .problem_variables_mut()
+            .add(variable().integer().min(0.0).max(amount_off));
+
+        observer.on_auxiliary_variable(
+            promotion_key,
+            saving,
+            "amount off saving",
+            Some(bundle_idx),
+            None,
+        );
+
+        state.add_to_objective(saving, -1.0);
+        observer.on_objective_term(saving, -1.0);
+
+        let capped = if budget_limited && !covers_amount {
+            let capped = state.problem_variables_mut().add(variable().binary());
+
+            observer.on_auxiliary_variable(
+                promotion_key,
+                capped,
+                "amount off capped",
+                Some(bundle_idx),
+                None,
+            );
+
+            Some(capped)
+        } else {
+            None
+        };
+
+        Ok(AmountOffBundle {
+            formed,
+            slot_vars,
+            spend,
+            saving,
+            capped,
+            covers_amount,
+            amount_off,
+            headroom,
+        })
+    }
 }
 
 impl ILPPromotion for MixAndMatchPromotion<'_> {
@@ -856,18 +1841,98 @@ impl ILPPromotion for MixAndMatchPromotion<'_> {
         MixAndMatchPromotion::key(self)
     }
 
+    fn coupon(&self) -> Option<&PromotionCoupon> {
+        MixAndMatchPromotion::coupon(self)
+    }
+
+    fn budget_pools(&self) -> &[BudgetPoolKey] {
+        MixAndMatchPromotion::budget_pools(self)
+    }
+
+    fn priority(&self) -> i32 {
+        MixAndMatchPromotion::priority(self)
+    }
+
+    fn funding(&self) -> &PromotionFunding {
+        MixAndMatchPromotion::funding(self)
+    }
+
+    fn is_mandatory(&self) -> bool {
+        MixAndMatchPromotion::is_mandatory(self)
+    }
+
+    fn rounding(&self) -> Option<&RoundingPolicies> {
+        MixAndMatchPromotion::rounding(self)
+    }
+
+    fn exclusions(&self) -> &PromotionExclusions {
+        MixAndMatchPromotion::exclusions(self)
+    }
+
+    fn near_miss<'b>(
+        &self,
+        item_group: &ItemGroup<'b>,
+        pools: &BudgetPools<'_>,
+    ) -> Option<MissReason<'b>> {
+        gate_reason(
+            self.schedule(),
+            self.coupon(),
+            self.budget(),
+            self.redemption_limit(),
+            self.budget_pools(),
+            pools,
+            item_group.context(),
+        )
+        .or_else(|| self.shortfall(item_group).map(MissReason::Shortfall))
+    }
+
+    fn item_signature(
+        &self,
+        item: &Item<'_>,
+        context: &EvaluationContext,
+    ) -> Option<ItemSignature> {
+        Some(
+            self.slots()
+                .iter()
+                .map(|slot| {
+                    slot.qualification()
+                        .matches_in_context(item.tags(), context)
+                })
+                .collect(),
+        )
+    }
+
+    fn greedy(&self) -> Option<&dyn GreedyPromotion> {
+        Some(self)
+    }
+
+    fn exhaustive(&self) -> Option<&dyn ExhaustivePromotion> {
+        Some(self)
+    }
+
     fn is_applicable(&self, item_group: &ItemGroup<'_>) -> bool {
-        if item_group.is_empty() {
+        let context = item_group.context();
+
+        if item_group.is_empty()
+            || !self.schedule().is_active_in(context)
+            || !self
+                .coupon()
+                .is_none_or(|coupon| coupon.is_presented_in(context))
+        {
             return false;
         }
 
         for slot in self.slots() {
-            let matching_items = item_group
+            let matching_units: usize = item_group
                 .iter()
-                .filter(|item| slot.qualification().matches(item.tags()))
-                .count();
-
-            if matching_items < slot.min() {
+                .filter(|item| {
+                    slot.qualification()
+                        .matches_in_context(item.tags(), item_group.context())
+                })
+                .map(|item| item.quantity() as usize)
+                .sum();
+
+            if matching_units < slot.min() {
                 return false;
             }
         }
@@ -887,7 +1952,8 @@ impl ILPPromotion for MixAndMatchPromotion<'_> {
     ) -> Result<PromotionVars, SolverError> {
         let promotion_key = self.key();
         let runtime_discount = runtime_discount_from_config(self.discount());
-        let redemption_limit = self.budget().redemption_limit;
+        let rounding = state.rounding_for(self.rounding(), item_group.currency());
+        let redemption_limit = self.redemption_limit();
 
         let monetary_limit_minor = self
             .budget()
@@ -902,35 +1968,63 @@ impl ILPPromotion for MixAndMatchPromotion<'_> {
                 bundle_formed: None,
                 target_vars: Vec::new(),
                 slot_bounds: Vec::new(),
-                bundle_size: 0,
+                amount_off_bundles: Vec::new(),
+                full_amount_off_bundles: None,
                 sorted_items: SmallVec::new(),
                 runtime_discount,
+                rounding,
                 redemption_limit,
                 monetary_limit_minor,
             }));
         }
 
-        // Collect eligible items per slot.
+        // Collect eligible items (and their total unit counts) per slot.
         let mut eligible_per_slot: Vec<SmallVec<[(usize, i64); 10]>> =
             Vec::with_capacity(self.slots().len());
+        let mut eligible_units_per_slot: SmallVec<[usize; 5]> = SmallVec::new();
         let mut slot_bounds = Vec::with_capacity(self.slots().len());
         let mut feasible = true;
 
         for slot in self.slots() {
             let mut eligible = SmallVec::new();
+            let mut eligible_units = 0_usize;
 
             for (item_idx, item) in item_group.iter().enumerate() {
-                if slot.qualification().matches(item.tags()) {
+                if slot
+                    .qualification()
+                    .matches_in_context(item.tags(), item_group.context())
+                {
                     eligible.push((item_idx, item.price().to_minor_units()));
+                    eligible_units += item.quantity() as usize;
                 }
             }
 
-            if eligible.len() < slot.min() {
+            if eligible_units < slot.min() {
                 feasible = false;
             }
 
-            slot_bounds.push((slot.min(), slot.max()));
+            // An open-ended slot still holds no more than its eligible units; bounding
+            // it keeps its selections tied to the bundle being formed.
+            slot_bounds.push((slot.min(), Some(slot.max().unwrap_or(eligible_units))));
             eligible_per_slot.push(eligible);
+            eligible_units_per_slot.push(eligible_units);
+        }
+
+        // Slots may compete for the same units, so together they need enough units
+        // to fill every minimum at once.
+        let bundle_units: usize = item_group
+            .iter()
+            .filter(|item| {
+                self.slots().iter().any(|slot| {
+                    slot.qualification()
+                        .matches_in_context(item.tags(), item_group.context())
+                })
+            })
+            .map(|item| item.quantity() as usize)
+            .sum();
+
+        if bundle_units < self.slots().iter().map(MixAndMatchSlot::min).sum() {
+            feasible = false;
         }
 
         if !feasible {
@@ -941,9 +2035,11 @@ impl ILPPromotion for MixAndMatchPromotion<'_> {
                 bundle_formed: None,
                 target_vars: Vec::new(),
                 slot_bounds: Vec::new(),
-                bundle_size: 0,
+                amount_off_bundles: Vec::new(),
+                full_amount_off_bundles: None,
                 sorted_items: SmallVec::new(),
                 runtime_discount,
+                rounding,
                 redemption_limit,
                 monetary_limit_minor,
             }));
@@ -952,10 +2048,10 @@ impl ILPPromotion for MixAndMatchPromotion<'_> {
         // Determine whether we can use a bundle counter.
         let can_use_bundle_counter = self.has_fixed_arity();
 
-        let max_bundles = eligible_per_slot
+        let max_bundles = eligible_units_per_slot
             .iter()
             .zip(self.slots())
-            .map(|(slot_items, slot)| slot_items.len() / slot.min())
+            .map(|(slot_units, slot)| slot_units / slot.min())
             .min()
             .unwrap_or(0);
 
@@ -977,8 +2073,6 @@ impl ILPPromotion for MixAndMatchPromotion<'_> {
             (None, Some(var))
         };
 
-        let bundle_size = self.bundle_size();
-
         // Build per-slot variables and collect all eligible items for target vars.
         let mut slot_vars: Vec<SmallVec<[(usize, Variable); 10]>> =
             Vec::with_capacity(eligible_per_slot.len());
@@ -989,7 +2083,10 @@ impl ILPPromotion for MixAndMatchPromotion<'_> {
             let mut vars = SmallVec::new();
 
             for &(item_idx, price_minor) in slot_items {
-                let var = state.problem_variables_mut().add(variable().binary());
+                let quantity = item_group.get_item(item_idx)?.quantity();
+                let var = state
+                    .problem_variables_mut()
+                    .add(item_units_variable(quantity));
 
                 vars.push((item_idx, var));
 
@@ -999,7 +2096,7 @@ impl ILPPromotion for MixAndMatchPromotion<'_> {
 
                 let coeff_minor = match self.discount() {
                     MixAndMatchDiscount::PercentAllItems(pct) => {
-                        discounted_minor_percent(pct, price_minor)?
+                        discounted_minor_percent(pct, price_minor, rounding)?
                     }
                     MixAndMatchDiscount::AmountOffEachItem(amount) => {
                         price_minor.saturating_sub(amount.to_minor_units()).max(0)
@@ -1048,7 +2145,10 @@ impl ILPPromotion for MixAndMatchPromotion<'_> {
             sorted_items.extend(all_bundle_items.iter().copied());
 
             for &(item_idx, price_minor) in &all_bundle_items {
-                let var = state.problem_variables_mut().add(variable().binary());
+                let quantity = item_group.get_item(item_idx)?.quantity();
+                let var = state
+                    .problem_variables_mut()
+                    .add(item_units_variable(quantity));
 
                 if let Some(slot) = target_vars.get_mut(item_idx) {
                     *slot = Some(var);
@@ -1056,7 +2156,7 @@ impl ILPPromotion for MixAndMatchPromotion<'_> {
 
                 let (discount_amount, discounted_minor) = match self.discount() {
                     MixAndMatchDiscount::PercentCheapest(pct) => {
-                        let discounted = discounted_minor_percent(pct, price_minor)?;
+                        let discounted = discounted_minor_percent(pct, price_minor, rounding)?;
                         let discount_amount = price_minor.saturating_sub(discounted);
 
                         (discount_amount, discounted)
@@ -1104,20 +2204,16 @@ impl ILPPromotion for MixAndMatchPromotion<'_> {
             }
         }
 
-        // Amount off total objective term (negative per bundle formed)
-        if let MixAndMatchDiscount::AmountOffTotal(amount) = self.discount() {
-            let amount_off = amount.to_minor_units();
-            let coeff = i64_to_f64_exact(amount_off)
-                .ok_or(SolverError::MinorUnitsNotRepresentable(amount_off))?;
-
-            if let Some(y_bundle) = y_bundle {
-                state.add_to_objective(y_bundle, -coeff);
-                observer.on_objective_term(y_bundle, -coeff);
-            } else if let Some(bundle_formed) = bundle_formed {
-                state.add_to_objective(bundle_formed, -coeff);
-                observer.on_objective_term(bundle_formed, -coeff);
-            }
-        }
+        let (amount_off_bundles, full_amount_off_bundles) = self.add_amount_off_bundles(
+            item_group,
+            &slot_vars,
+            &slot_bounds,
+            y_bundle,
+            bundle_formed,
+            max_bundles,
+            state,
+            observer,
+        )?;
 
         Ok(Box::new(MixAndMatchVars {
             promotion_key,
@@ -1126,9 +2222,11 @@ impl ILPPromotion for MixAndMatchPromotion<'_> {
             bundle_formed,
             target_vars,
             slot_bounds,
-            bundle_size,
+            amount_off_bundles,
+            full_amount_off_bundles,
             sorted_items,
             runtime_discount,
+            rounding,
             redemption_limit,
             monetary_limit_minor,
         }))
@@ -1471,6 +2569,119 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn item_price_outcomes_match_cheapest_discounts() -> TestResult {
+        let items: SmallVec<[Item<'_>; 10]> = SmallVec::from_vec(vec![
+            Item::with_tags(
+                ProductKey::default(),
+                Money::from_minor(400, GBP),
+                StringTagCollection::from_strs(&["main"]),
+            ),
+            Item::with_tags(
+                ProductKey::default(),
+                Money::from_minor(200, GBP),
+                StringTagCollection::from_strs(&["drink"]),
+            ),
+        ]);
+
+        let item_group = ItemGroup::new(items, GBP);
+        let mut slot_keys = SlotMap::<PromotionSlotKey, ()>::with_key();
+
+        let slots = vec![
+            slot(
+                &mut slot_keys,
+                StringTagCollection::from_strs(&["main"]),
+                1,
+                Some(1),
+            ),
+            slot(
+                &mut slot_keys,
+                StringTagCollection::from_strs(&["drink"]),
+                1,
+                Some(1),
+            ),
+        ];
+
+        let promo = MixAndMatchPromotion::new(
+            PromotionKey::default(),
+            slots,
+            MixAndMatchDiscount::PercentCheapest(Percentage::from(0.50)),
+            PromotionBudget::unlimited(),
+        );
+
+        let mut state = ILPState::with_presence_variables(&item_group)?;
+        let mut observer = NoopObserver;
+        let vars = promo.add_variables(&item_group, &mut state, &mut observer)?;
+
+        let typed_vars = ((vars.as_ref() as &dyn Any).downcast_ref::<MixAndMatchVars>())
+            .expect("Expected mix-and-match vars");
+
+        let mut values = Vec::new();
+
+        for slot in &typed_vars.slot_vars {
+            for &(_idx, var) in slot {
+                values.push((var, 1.0));
+            }
+        }
+
+        if let Some(target_var) = typed_vars.target_vars.get(1).and_then(|v| *v) {
+            values.push((target_var, 1.0));
+        }
+
+        let solution = MapSolution::with(&values);
+
+        for item_idx in 0..item_group.len() {
+            let outcomes = vars
+                .item_price_outcomes(&item_group, item_idx)?
+                .ok_or("expected outcomes")?;
+
+            let active_prices: Vec<i64> = outcomes
+                .iter()
+                .filter(|(expr, _)| solution.eval(expr) > 0.5)
+                .map(|(_, price)| *price)
+                .collect();
+
+            let expected = typed_vars
+                .calculate_item_discounts(&solution, &item_group)?
+                .get(&item_idx)
+                .map(|(_, final_minor)| *final_minor);
+
+            assert_eq!(active_prices.len(), 1, "exactly one outcome is active");
+            assert_eq!(active_prices.first().copied(), expected);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn item_price_outcomes_unavailable_for_bundle_totals() -> TestResult {
+        let item_group = item_group_from_prices(&[400, 200]);
+        let mut slot_keys = SlotMap::<PromotionSlotKey, ()>::with_key();
+
+        let promo = MixAndMatchPromotion::new(
+            PromotionKey::default(),
+            vec![slot(
+                &mut slot_keys,
+                StringTagCollection::from_strs(&[]),
+                2,
+                Some(2),
+            )],
+            MixAndMatchDiscount::FixedTotal(Money::from_minor(300, GBP)),
+            PromotionBudget::unlimited(),
+        );
+
+        let mut state = ILPState::with_presence_variables(&item_group)?;
+        let mut observer = NoopObserver;
+        let vars = promo.add_variables(&item_group, &mut state, &mut observer)?;
+
+        assert!(
+            vars.item_price_outcomes(&item_group, 0)?.is_none(),
+            "bundle totals have no per-item price outcomes"
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn calculate_item_discounts_fixed_cheapest() -> TestResult {
         let items: SmallVec<[Item<'_>; 10]> = SmallVec::from_vec(vec![
@@ -1977,9 +3188,11 @@ mod tests {
             bundle_formed: None,
             target_vars: Vec::new(),
             slot_bounds: Vec::new(),
-            bundle_size: 0,
+            amount_off_bundles: Vec::new(),
+            full_amount_off_bundles: None,
             sorted_items: SmallVec::new(),
             runtime_discount: MixAndMatchRuntimeDiscount::PercentAllItems(Percentage::from(0.0)),
+            rounding: RoundingPolicy::default(),
             redemption_limit: None,
             monetary_limit_minor: None,
         };
@@ -2001,9 +3214,11 @@ mod tests {
             bundle_formed: None,
             target_vars: vec![Some(target_var)],
             slot_bounds: vec![(1, Some(1))],
-            bundle_size: 1,
+            amount_off_bundles: Vec::new(),
+            full_amount_off_bundles: None,
             sorted_items: smallvec![(0, 100)],
             runtime_discount: MixAndMatchRuntimeDiscount::PercentCheapest(Percentage::from(0.5)),
+            rounding: RoundingPolicy::default(),
             redemption_limit: None,
             monetary_limit_minor: None,
         };
@@ -2094,7 +3309,7 @@ mod tests {
         );
         assert_eq!(
             observed_lhs_values_for_type(&observer, "cheapest prefix", &solution),
-            vec![1.0, 0.0, 0.0]
+            vec![0.0, -1.0, 0.0]
         );
         assert_eq!(
             observed_lhs_values_for_type(&observer, "target count", &solution),
@@ -2110,11 +3325,11 @@ mod tests {
 
         assert_eq!(
             state_lhs_values_for_relation(&constraints, ConstraintRelation::Geq, &solution),
-            vec![0.0, 0.0, 0.0, 1.0]
+            vec![0.0]
         );
         assert_eq!(
             state_lhs_values_for_relation(&constraints, ConstraintRelation::Leq, &solution),
-            vec![-1.0, 0.0, 0.0, 0.0]
+            vec![-1.0, -1.0, 0.0, 0.0, 0.0, 0.0, 0.0]
         );
         assert_eq!(
             state_lhs_values_for_relation(&constraints, ConstraintRelation::Eq, &solution),
@@ -2225,9 +3440,11 @@ mod tests {
             bundle_formed: Some(bundle_formed_zero),
             target_vars: Vec::new(),
             slot_bounds: Vec::new(),
-            bundle_size: 0,
+            amount_off_bundles: Vec::new(),
+            full_amount_off_bundles: None,
             sorted_items: SmallVec::new(),
             runtime_discount: MixAndMatchRuntimeDiscount::PercentAllItems(Percentage::from(0.25)),
+            rounding: RoundingPolicy::default(),
             redemption_limit: Some(0),
             monetary_limit_minor: None,
         };
@@ -2260,9 +3477,11 @@ mod tests {
             bundle_formed: Some(bundle_formed_one),
             target_vars: Vec::new(),
             slot_bounds: Vec::new(),
-            bundle_size: 0,
+            amount_off_bundles: Vec::new(),
+            full_amount_off_bundles: None,
             sorted_items: SmallVec::new(),
             runtime_discount: MixAndMatchRuntimeDiscount::PercentAllItems(Percentage::from(0.25)),
+            rounding: RoundingPolicy::default(),
             redemption_limit: Some(1),
             monetary_limit_minor: None,
         };
@@ -2488,6 +3707,7 @@ mod tests {
         let percent = calculate_discounted_minor_for_budget(
             200,
             MixAndMatchRuntimeDiscount::PercentAllItems(Percentage::from(0.25)),
+            RoundingPolicy::default(),
         )?;
 
         assert_eq!(percent, 150);
@@ -2495,6 +3715,7 @@ mod tests {
         let amount_off_each = calculate_discounted_minor_for_budget(
             200,
             MixAndMatchRuntimeDiscount::AmountOffEachItem(50),
+            RoundingPolicy::default(),
         )?;
 
         assert_eq!(amount_off_each, 150);
@@ -2502,28 +3723,36 @@ mod tests {
         let fixed_price_each = calculate_discounted_minor_for_budget(
             200,
             MixAndMatchRuntimeDiscount::FixedPriceEachItem(90),
+            RoundingPolicy::default(),
         )?;
 
         assert_eq!(fixed_price_each, 90);
 
-        let amount_off_total = calculate_discounted_minor_for_budget(
-            200,
-            MixAndMatchRuntimeDiscount::AmountOffTotal(120),
-        )?;
-
-        // Conservative for bundle-total discounts in budget constraints.
-        assert_eq!(amount_off_total, 0);
-
-        let fixed_total = calculate_discounted_minor_for_budget(
-            200,
-            MixAndMatchRuntimeDiscount::FixedTotal(120),
-        )?;
+        // Bundle-total discounts are budgeted per bundle instead.
+        assert!(
+            calculate_discounted_minor_for_budget(
+                200,
+                MixAndMatchRuntimeDiscount::AmountOffTotal(120),
+                RoundingPolicy::default(),
+            )
+            .is_err(),
+            "amount off total has no per-item budget price"
+        );
 
-        assert_eq!(fixed_total, 0);
+        assert!(
+            calculate_discounted_minor_for_budget(
+                200,
+                MixAndMatchRuntimeDiscount::FixedTotal(120),
+                RoundingPolicy::default(),
+            )
+            .is_err(),
+            "fixed total has no per-item budget price"
+        );
 
         let fixed_cheapest = calculate_discounted_minor_for_budget(
             200,
             MixAndMatchRuntimeDiscount::FixedCheapest(50),
+            RoundingPolicy::default(),
         )?;
 
         assert_eq!(fixed_cheapest, 50);
@@ -2531,6 +3760,7 @@ mod tests {
         let fixed_cheapest_negative = calculate_discounted_minor_for_budget(
             200,
             MixAndMatchRuntimeDiscount::FixedCheapest(-50),
+            RoundingPolicy::default(),
         )?;
 
         assert_eq!(fixed_cheapest_negative, 0);
@@ -2731,9 +3961,11 @@ mod tests {
             bundle_formed: None,
             target_vars: vec![None, None],
             slot_bounds: vec![(1, Some(1))],
-            bundle_size: 1,
+            amount_off_bundles: Vec::new(),
+            full_amount_off_bundles: None,
             sorted_items: SmallVec::new(),
             runtime_discount: MixAndMatchRuntimeDiscount::PercentAllItems(Percentage::from(0.0)),
+            rounding: RoundingPolicy::default(),
             redemption_limit: None,
             monetary_limit_minor: None,
         };
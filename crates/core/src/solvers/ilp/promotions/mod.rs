@@ -2,16 +2,29 @@
 
 use std::{any::Any, fmt::Debug, sync::Arc};
 
-use good_lp::{Expression, Solution};
+use good_lp::{Expression, IntoAffineExpression, Solution, variable};
 use num_traits::ToPrimitive;
 use rustc_hash::FxHashMap;
+use rusty_money::Money;
 use smallvec::SmallVec;
 
 use crate::{
-    items::groups::ItemGroup,
-    promotions::{PromotionKey, redemptions::PromotionRedemption},
+    context::EvaluationContext,
+    discounts::rounding::RoundingPolicies,
+    items::{Item, groups::ItemGroup},
+    promotions::{
+        PromotionKey,
+        budget::{BudgetPoolKey, BudgetPools},
+        coupon::PromotionCoupon,
+        exclusion::{NO_EXCLUSIONS, PromotionExclusions},
+        explain::{MissReason, Shortfall},
+        funding::{PromotionFunding, RETAILER_FUNDED},
+        redemptions::PromotionRedemption,
+    },
     solvers::{
         SolverError,
+        exhaustive::ExhaustivePromotion,
+        greedy::GreedyPromotion,
         ilp::{ILPObserver, state::ILPState},
     },
 };
@@ -53,6 +66,58 @@ impl<'a> PromotionInstances<'a> {
         Ok(Self { instances })
     }
 
+    /// Pair `promotions` with instances detached from an earlier build over the
+    /// same items.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SolverError::InvariantViolation`] if the promotions are not the ones
+    /// the instances were built for.
+    pub(crate) fn attach(
+        promotions: &[&'a dyn ILPPromotion],
+        detached: &[DetachedInstance],
+    ) -> Result<Self, SolverError> {
+        if promotions.len() != detached.len() {
+            return Err(SolverError::InvariantViolation {
+                message: "detached instances do not match the promotions",
+            });
+        }
+
+        promotions
+            .iter()
+            .zip(detached)
+            .map(|(&promotion, detached)| {
+                if promotion.key() != detached.promotion_key {
+                    return Err(SolverError::InvariantViolation {
+                        message: "detached instances do not match the promotions",
+                    });
+                }
+
+                Ok(PromotionInstance {
+                    promotion,
+                    vars: detached.vars.clone(),
+                    floor_excess: detached.floor_excess.clone(),
+                    pools_floor_excess: detached.pools_floor_excess,
+                })
+            })
+            .collect::<Result<_, _>>()
+            .map(|instances| Self { instances })
+    }
+
+    /// Detach the instances' variables from their promotions, so the model can be
+    /// rebuilt later with [`attach()`](Self::attach).
+    pub(crate) fn detach(&self) -> SmallVec<[DetachedInstance; 5]> {
+        self.instances
+            .iter()
+            .map(|instance| DetachedInstance {
+                promotion_key: instance.promotion_key(),
+                vars: instance.vars.clone(),
+                floor_excess: instance.floor_excess.clone(),
+                pools_floor_excess: instance.pools_floor_excess,
+            })
+            .collect()
+    }
+
     /// Iterate over instances
     pub(crate) fn iter(&self) -> impl Iterator<Item = &PromotionInstance<'a>> {
         self.instances.iter()
@@ -96,14 +161,31 @@ impl<'a> PromotionInstances<'a> {
     }
 }
 
+/// A promotion instance's variables without the promotion they were built for
+#[derive(Debug, Clone)]
+pub(crate) struct DetachedInstance {
+    promotion_key: PromotionKey,
+    vars: Option<Arc<dyn ILPPromotionVars>>,
+    floor_excess: Expression,
+    pools_floor_excess: bool,
+}
+
 /// A promotion instance that pairs a promotion with its solver variables
 #[derive(Debug)]
 pub(crate) struct PromotionInstance<'a> {
     /// The promotion being solved
     promotion: &'a dyn ILPPromotion,
 
-    /// The solver variables for this promotion instance
-    vars: Option<PromotionVars>,
+    /// The solver variables for this promotion instance, shared with any
+    /// detached copies of the model
+    vars: Option<Arc<dyn ILPPromotionVars>>,
+
+    /// Discount the items' price floors take back from the promotion, in minor units
+    floor_excess: Expression,
+
+    /// Whether the promotion's discount is split across its units after solving,
+    /// so floors move it between units instead of only capping each one
+    pools_floor_excess: bool,
 }
 
 impl<'a> PromotionInstance<'a> {
@@ -124,16 +206,71 @@ impl<'a> PromotionInstance<'a> {
         state: &mut ILPState,
         observer: &mut dyn ILPObserver,
     ) -> Result<Self, SolverError> {
-        let vars = if promotion.is_applicable(item_group) {
+        let mut instance = Self {
+            promotion,
+            vars: None,
+            floor_excess: Expression::default(),
+            pools_floor_excess: false,
+        };
+
+        if promotion.is_applicable(item_group) {
             let vars = promotion.add_variables(item_group, state, observer)?;
+
+            // Floors are modelled before the promotion's own constraints, so its
+            // monetary budget is charged the capped discount.
+            instance.add_floor_excess(&*vars, item_group, state, observer)?;
             vars.add_constraints(promotion.key(), item_group, state, observer)?;
 
-            Some(vars)
+            instance.vars = Some(Arc::from(vars));
+        }
+
+        Ok(instance)
+    }
+
+    /// Model how much of the promotion's discount the items' price floors take back.
+    ///
+    /// Where the promotion describes each item's price outcomes, every outcome below
+    /// an item's floor gives back exactly the shortfall. Bundle-total discounts are
+    /// split across their units after solving, so instead their whole discount is
+    /// bounded by the room the discounted units have above their floors, and any
+    /// excess is given back. The excess is added to the objective, so outcomes are
+    /// compared at the prices the customer will actually pay, and subtracted from
+    /// what budgets are charged.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SolverError::PriceFloorUnsupported`] if the promotion can neither
+    /// describe its outcomes nor express its discount value, or any error raised
+    /// while building the expressions.
+    fn add_floor_excess(
+        &mut self,
+        vars: &dyn ILPPromotionVars,
+        item_group: &ItemGroup<'_>,
+        state: &mut ILPState,
+        observer: &mut dyn ILPObserver,
+    ) -> Result<(), SolverError> {
+        if !item_group.iter().any(|item| item.price_floor().is_some()) {
+            return Ok(());
+        }
+
+        let promotion_key = self.promotion.key();
+        let excess = if let Some(excess) = per_item_floor_excess(vars, item_group)? {
+            excess
         } else {
-            None
+            self.pools_floor_excess = true;
+
+            pooled_floor_excess(vars, promotion_key, item_group, state, observer)?
         };
 
-        Ok(Self { promotion, vars })
+        for (var, coeff) in excess.clone().linear_coefficients() {
+            state.add_to_objective(var, coeff);
+            observer.on_objective_term(var, coeff);
+        }
+
+        state.set_floor_excess(promotion_key, excess.clone());
+        self.floor_excess = excess;
+
+        Ok(())
     }
 
     /// Contribute this promotion's presence term for `item_idx`.
@@ -164,6 +301,89 @@ impl<'a> PromotionInstance<'a> {
         }
     }
 
+    /// Price outcomes for `item_idx` under this promotion instance.
+    ///
+    /// Inapplicable promotions have no variables, so they contribute no outcomes.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SolverError`] if the promotion runtime fails to describe its outcomes.
+    pub(crate) fn item_price_outcomes(
+        &self,
+        item_group: &ItemGroup<'_>,
+        item_idx: usize,
+    ) -> Result<Option<PriceOutcomes>, SolverError> {
+        match &self.vars {
+            Some(vars) => vars.item_price_outcomes(item_group, item_idx),
+            None => Ok(Some(PriceOutcomes::new())),
+        }
+    }
+
+    /// Return the key of the promotion backing this instance.
+    pub(crate) fn promotion_key(&self) -> PromotionKey {
+        self.promotion.key()
+    }
+
+    /// Return the shared budget pools the promotion draws on.
+    pub(crate) fn budget_pools(&self) -> &[BudgetPoolKey] {
+        self.promotion.budget_pools()
+    }
+
+    /// Return the priority of the promotion backing this instance.
+    pub(crate) fn priority(&self) -> i32 {
+        self.promotion.priority()
+    }
+
+    /// Return the promotion backing this instance.
+    pub(crate) fn promotion(&self) -> &'a dyn ILPPromotion {
+        self.promotion
+    }
+
+    /// Return who funds the promotion backing this instance.
+    pub(crate) fn funding(&self) -> &PromotionFunding {
+        self.promotion.funding()
+    }
+
+    /// Return whether the customer is always entitled to the promotion backing this
+    /// instance.
+    pub(crate) fn is_mandatory(&self) -> bool {
+        self.promotion.is_mandatory()
+    }
+
+    /// Expression counting this instance's redemptions.
+    ///
+    /// Returns `None` if the promotion runtime cannot count its redemptions.
+    /// Inapplicable promotions have no variables, so they redeem nothing.
+    pub(crate) fn redemption_count_expr(&self) -> Option<Expression> {
+        match &self.vars {
+            Some(vars) => vars.redemption_count_expr(),
+            None => Some(Expression::default()),
+        }
+    }
+
+    /// Expression for this instance's total discount value (in minor units) against a
+    /// shared budget pool.
+    ///
+    /// The discount is capped at the items' price floors. Inapplicable promotions
+    /// have no variables, so they give no discount.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SolverError::BudgetPoolUnsupported`] if the promotion runtime cannot
+    /// express its discount value, or any error raised while building the expression.
+    pub(crate) fn discount_value_expr(
+        &self,
+        item_group: &ItemGroup<'_>,
+    ) -> Result<Expression, SolverError> {
+        match &self.vars {
+            Some(vars) => vars
+                .discount_value_expr(item_group)?
+                .map(|discount| discount - self.floor_excess.clone())
+                .ok_or(SolverError::BudgetPoolUnsupported(self.promotion.key())),
+            None => Ok(Expression::default()),
+        }
+    }
+
     /// Post-solve interpretation for this promotion instance.
     ///
     /// Reads the solved variable values to determine which items this promotion selected and
@@ -189,6 +409,8 @@ impl<'a> PromotionInstance<'a> {
     ///
     /// Reads the solved variable values to determine which items this promotion selected and
     /// returns [`PromotionRedemption`] instances with redemption indexes and price details.
+    /// Prices below an item's floor are raised to it; a bundle-total discount gives
+    /// what that takes back to the other units it discounts, as the model allowed for.
     ///
     /// # Errors
     ///
@@ -200,18 +422,220 @@ impl<'a> PromotionInstance<'a> {
         item_group: &ItemGroup<'b>,
         next_redemption_idx: &mut usize,
     ) -> Result<SmallVec<[PromotionRedemption<'b>; 10]>, SolverError> {
-        self.vars.as_ref().map_or_else(
-            || Ok(SmallVec::new()),
-            |vars| {
-                vars.calculate_item_redemptions(
-                    self.promotion.key(),
-                    solution,
-                    item_group,
-                    next_redemption_idx,
-                )
-            },
-        )
+        let Some(vars) = &self.vars else {
+            return Ok(SmallVec::new());
+        };
+
+        let mut redemptions = vars.calculate_item_redemptions(
+            self.promotion.key(),
+            solution,
+            item_group,
+            next_redemption_idx,
+        )?;
+
+        let mut floors = SmallVec::<[i64; 10]>::with_capacity(redemptions.len());
+        let mut excess_by_bundle = FxHashMap::<usize, i64>::default();
+
+        for redemption in &mut redemptions {
+            let floor_minor = price_floor_minor(item_group.get_item(redemption.item_idx)?);
+            let final_minor = redemption.final_price.to_minor_units();
+
+            if let Some(floor_minor) = floor_minor
+                && final_minor < floor_minor
+            {
+                redemption.final_price =
+                    Money::from_minor(floor_minor, redemption.final_price.currency());
+                redemption.floor_limited = true;
+
+                *excess_by_bundle
+                    .entry(redemption.redemption_idx)
+                    .or_default() +=
+                    (floor_minor - final_minor).saturating_mul(i64::from(redemption.quantity));
+            }
+
+            floors.push(floor_minor.unwrap_or(0));
+        }
+
+        if self.pools_floor_excess && !excess_by_bundle.is_empty() {
+            let receives = |redemption: &PromotionRedemption<'_>| {
+                !redemption.floor_limited
+                    && vars.is_item_priced_by_promotion(solution, redemption.item_idx)
+            };
+
+            // Excess stays within its own bundle where it can, then spills over.
+            let mut spilled = 0;
+            let mut bundles: SmallVec<[(usize, i64); 4]> = excess_by_bundle.into_iter().collect();
+
+            bundles.sort_unstable();
+
+            for (bundle_idx, excess) in bundles {
+                spilled += spread_floor_excess(&mut redemptions, &mut floors, excess, |r| {
+                    r.redemption_idx == bundle_idx && receives(r)
+                });
+            }
+
+            spread_floor_excess(&mut redemptions, &mut floors, spilled, receives);
+        }
+
+        Ok(redemptions)
+    }
+}
+
+/// Floor excess of a promotion that describes every floored item's price outcomes.
+///
+/// Returns `None` if any floored item's outcomes are unavailable.
+fn per_item_floor_excess(
+    vars: &dyn ILPPromotionVars,
+    item_group: &ItemGroup<'_>,
+) -> Result<Option<Expression>, SolverError> {
+    let mut excess = Expression::default();
+
+    for (item_idx, item) in item_group.iter().enumerate() {
+        let Some(floor_minor) = price_floor_minor(item) else {
+            continue;
+        };
+
+        let Some(outcomes) = vars.item_price_outcomes(item_group, item_idx)? else {
+            return Ok(None);
+        };
+
+        for (expr, final_minor) in outcomes {
+            let shortfall = floor_minor - final_minor;
+
+            if shortfall > 0 {
+                let coeff = i64_to_f64_exact(shortfall)
+                    .ok_or(SolverError::MinorUnitsNotRepresentable(shortfall))?;
+
+                excess += expr * coeff;
+            }
+        }
+    }
+
+    Ok(Some(excess))
+}
+
+/// Floor excess of a promotion whose discount is only known in total.
+///
+/// The excess is `max(0, discount - headroom)`, where the headroom is what the
+/// discounted units can lose before reaching their floors. A binary picks which
+/// side of the `max` applies, with every unit's full price as the big-M bound.
+fn pooled_floor_excess(
+    vars: &dyn ILPPromotionVars,
+    promotion_key: PromotionKey,
+    item_group: &ItemGroup<'_>,
+    state: &mut ILPState,
+    observer: &mut dyn ILPObserver,
+) -> Result<Expression, SolverError> {
+    let discount = vars
+        .discount_value_expr(item_group)?
+        .ok_or(SolverError::PriceFloorUnsupported(promotion_key))?;
+
+    let mut headroom = Expression::default();
+    let mut bound_minor = 0_i64;
+
+    for (item_idx, item) in item_group.iter().enumerate() {
+        let price_minor = item.price().to_minor_units();
+        let room_minor = price_minor - price_floor_minor(item).unwrap_or(0);
+        let coeff = i64_to_f64_exact(room_minor)
+            .ok_or(SolverError::MinorUnitsNotRepresentable(room_minor))?;
+
+        headroom += vars.add_item_discount_term(Expression::default(), item_idx) * coeff;
+        bound_minor =
+            bound_minor.saturating_add(price_minor.saturating_mul(i64::from(item.quantity())));
+    }
+
+    let bound = i64_to_f64_exact(bound_minor)
+        .ok_or(SolverError::MinorUnitsNotRepresentable(bound_minor))?;
+
+    let excess = state
+        .problem_variables_mut()
+        .add(variable().min(0.0).max(bound));
+    let over = state.problem_variables_mut().add(variable().binary());
+
+    observer.on_auxiliary_variable(promotion_key, excess, "floor excess", None, None);
+    observer.on_auxiliary_variable(promotion_key, over, "floor exceeded", None, None);
+
+    // excess >= discount - headroom
+    let expr = Expression::from(excess) - discount.clone() + headroom.clone();
+
+    observer.on_promotion_constraint(promotion_key, "floor excess (lower)", &expr, ">=", 0.0);
+    state.add_geq_constraint(expr, 0.0);
+
+    // excess <= bound * over, so it is zero while the headroom covers the discount
+    let expr = Expression::from(excess) - over * bound;
+
+    observer.on_promotion_constraint(promotion_key, "floor excess (unused)", &expr, "<=", 0.0);
+    state.add_leq_constraint(expr, 0.0);
+
+    // excess <= discount - headroom + bound * (1 - over)
+    let expr = Expression::from(excess) - discount + headroom + over * bound;
+
+    observer.on_promotion_constraint(promotion_key, "floor excess (upper)", &expr, "<=", bound);
+    state.add_leq_constraint(expr, bound);
+
+    Ok(Expression::from(excess))
+}
+
+/// Lower the final prices of the redemptions `receives` accepts by up to `excess`
+/// minor units in total, never below their floors, and return what is left.
+///
+/// A quantity line that can only take part of a unit's worth is split, so the
+/// remainder lands on a single unit.
+fn spread_floor_excess(
+    redemptions: &mut SmallVec<[PromotionRedemption<'_>; 10]>,
+    floors: &mut SmallVec<[i64; 10]>,
+    excess: i64,
+    receives: impl Fn(&PromotionRedemption<'_>) -> bool,
+) -> i64 {
+    let mut left = excess;
+    let mut idx = 0;
+
+    while left > 0 && idx < redemptions.len() {
+        let (Some(redemption), Some(&floor_minor)) = (redemptions.get_mut(idx), floors.get(idx))
+        else {
+            break;
+        };
+
+        idx += 1;
+
+        if !receives(redemption) {
+            continue;
+        }
+
+        let currency = redemption.final_price.currency();
+        let final_minor = redemption.final_price.to_minor_units();
+        let quantity = i64::from(redemption.quantity);
+        let per_unit = (final_minor - floor_minor).min(left / quantity).max(0);
+
+        redemption.final_price = Money::from_minor(final_minor - per_unit, currency);
+        left -= per_unit * quantity;
+
+        // Less than a unit's worth is left over: one unit of the line takes it.
+        let remainder = left.min(final_minor - per_unit - floor_minor);
+
+        if remainder > 0 && redemption.quantity > 1 {
+            let mut unit = redemption.clone();
+
+            unit.quantity = 1;
+            unit.final_price = Money::from_minor(final_minor - per_unit - remainder, currency);
+            redemption.quantity -= 1;
+            left -= remainder;
+
+            redemptions.push(unit);
+            floors.push(floor_minor);
+        }
     }
+
+    left
+}
+
+/// Lowest unit price promotions may take `item` to, in minor units, if it has a floor.
+///
+/// A floor above the item's current price only stops promotions discounting it
+/// further; it never raises the price.
+pub(crate) fn price_floor_minor(item: &Item<'_>) -> Option<i64> {
+    item.price_floor()
+        .map(|floor| floor.to_minor_units().min(item.price().to_minor_units()))
 }
 
 /// Interface for promotion-specific runtime variable bundles.
@@ -221,8 +645,20 @@ impl<'a> PromotionInstance<'a> {
 /// to interpret a solved model into discounts/redemptions.
 pub trait ILPPromotionVars: Debug + Send + Sync + Any {
     /// Contribute the participation variable(s) for `item_idx` into `expr`.
+    ///
+    /// The term counts how many units of the item line the promotion claims, so it
+    /// must never exceed the line quantity (a binary for single-unit lines).
     fn add_item_participation_term(&self, expr: Expression, item_idx: usize) -> Expression;
 
+    /// Contribute the number of units of `item_idx` the promotion may discount into `expr`.
+    ///
+    /// Price floors bound a bundle-total discount by the room these units have above
+    /// their floors, so units that only help a bundle qualify must be left out. The
+    /// default implementation counts every participating unit.
+    fn add_item_discount_term(&self, expr: Expression, item_idx: usize) -> Expression {
+        self.add_item_participation_term(expr, item_idx)
+    }
+
     /// Returns true if `item_idx` participates in this promotion.
     fn is_item_participating(&self, solution: &dyn Solution, item_idx: usize) -> bool;
 
@@ -249,6 +685,59 @@ pub trait ILPPromotionVars: Debug + Send + Sync + Any {
         Ok(expr)
     }
 
+    /// Describe the final price of `item_idx` as linear outcomes of this promotion's variables.
+    ///
+    /// Each entry pairs an expression with the final unit price (in minor units) that
+    /// the units counted by the expression leave this promotion at. The expressions must
+    /// sum to this promotion's participation term for the item, so for a single-unit
+    /// line at most one of them is 1 in any solution.
+    ///
+    /// Joint multi-layer formulations use these outcomes to carry discounted prices
+    /// into later layers. Returns `None` when the final price cannot be expressed per
+    /// item, such as bundle-total discounts whose allocation depends on the whole
+    /// bundle; the default implementation always returns `None`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SolverError`] if the item is missing or a discounted price cannot
+    /// be computed.
+    fn item_price_outcomes(
+        &self,
+        _item_group: &ItemGroup<'_>,
+        _item_idx: usize,
+    ) -> Result<Option<PriceOutcomes>, SolverError> {
+        Ok(None)
+    }
+
+    /// Expression counting how many times the promotion is redeemed.
+    ///
+    /// Shared budget pools sum this across every promotion drawing on a pool and
+    /// constrain the total to the pool's redemption limit, so it must count
+    /// redemptions the same way the promotion's own redemption budget does. The
+    /// default implementation returns `None`, which makes the promotion unusable
+    /// with pools that limit redemptions.
+    fn redemption_count_expr(&self) -> Option<Expression> {
+        None
+    }
+
+    /// Expression for the total discount value given by the promotion, in minor units.
+    ///
+    /// Shared budget pools sum this across every promotion drawing on a pool and
+    /// constrain the total to the pool's monetary limit. The default implementation
+    /// returns `None`, which makes the promotion unusable with pools that limit
+    /// discount value.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SolverError`] if the item is missing or a discount coefficient
+    /// cannot be represented.
+    fn discount_value_expr(
+        &self,
+        _item_group: &ItemGroup<'_>,
+    ) -> Result<Option<Expression>, SolverError> {
+        Ok(None)
+    }
+
     /// Emit vars-owned constraints into the ILP state.
     ///
     /// # Errors
@@ -294,6 +783,12 @@ pub trait ILPPromotionVars: Debug + Send + Sync + Any {
 /// Promotion variable bundle produced by an ILP promotion implementation.
 pub type PromotionVars = Box<dyn ILPPromotionVars>;
 
+/// Per-item price outcomes: `(indicator expression, final price in minor units)` pairs.
+pub type PriceOutcomes = SmallVec<[(Expression, i64); 2]>;
+
+/// Which of a promotion's qualifications an item matches, in a stable order.
+pub type ItemSignature = SmallVec<[bool; 4]>;
+
 /// Makes a [`crate::promotions::Promotion`] usable by the ILP solver.
 ///
 /// Implementations are responsible for compiling a promotion into:
@@ -317,6 +812,130 @@ pub trait ILPPromotion: Debug + Send + Sync {
     /// Return the promotion key.
     fn key(&self) -> PromotionKey;
 
+    /// Return the coupon that must be presented for the promotion to apply, if any.
+    ///
+    /// Promotion graphs use this to validate single-use codes and to report which
+    /// presented codes were used. Implementations that gate on a coupon must also
+    /// return `false` from [`ILPPromotion::is_applicable`] when none of its codes is
+    /// presented in the item group's context.
+    fn coupon(&self) -> Option<&PromotionCoupon> {
+        None
+    }
+
+    /// Return the shared budget pools this promotion draws on.
+    ///
+    /// Solvers constrain the combined usage of every promotion in a pool to the
+    /// pool's limits, using [`ILPPromotionVars::redemption_count_expr`] and
+    /// [`ILPPromotionVars::discount_value_expr`]. The default implementation draws
+    /// on no pools.
+    fn budget_pools(&self) -> &[BudgetPoolKey] {
+        &[]
+    }
+
+    /// Return the priority used to prefer this promotion when totals tie.
+    ///
+    /// Only consulted by tie-break policies that prefer higher priorities; higher
+    /// numbers win. The default implementation returns 0.
+    fn priority(&self) -> i32 {
+        0
+    }
+
+    /// Return who funds this promotion's discounts.
+    ///
+    /// Consulted when attributing each redemption's discount to the parties paying
+    /// for it, and by tie-break policies that prefer supplier-funded promotions.
+    /// The default implementation is retailer funded.
+    fn funding(&self) -> &PromotionFunding {
+        &RETAILER_FUNDED
+    }
+
+    /// Return whether each unit this promotion discounts is a redemption of its own.
+    ///
+    /// Redemptions of such promotions can span several units of one item line, so
+    /// funding fixed per redemption is paid once per unit. The default
+    /// implementation returns `false`.
+    fn redeems_each_unit(&self) -> bool {
+        false
+    }
+
+    /// Return whether the customer is always entitled to this promotion.
+    ///
+    /// Only consulted by the least-generous objective for mandatory promotions,
+    /// which applies as many redemptions of mandatory promotions as possible. The
+    /// default implementation returns `false`.
+    fn is_mandatory(&self) -> bool {
+        false
+    }
+
+    /// Return this promotion's own rounding policies for percentage discounts.
+    ///
+    /// `None`, the default, rounds with the policies of the graph or solver
+    /// evaluating the promotion.
+    fn rounding(&self) -> Option<&RoundingPolicies> {
+        None
+    }
+
+    /// Return this promotion's exclusion groups and "cannot be combined with" rules.
+    ///
+    /// The default implementation joins no groups and excludes nothing.
+    fn exclusions(&self) -> &PromotionExclusions {
+        &NO_EXCLUSIONS
+    }
+
+    /// Explain why this promotion cannot redeem against the given item group.
+    ///
+    /// `pools` holds the remaining balances of the shared budget pools when the
+    /// promotion's layer was solved. Return `None` when the promotion could
+    /// apply, in which case a promotion graph that did not redeem it reports it
+    /// as outcompeted. The default implementation never reports a reason.
+    fn near_miss<'b>(
+        &self,
+        _item_group: &ItemGroup<'b>,
+        _pools: &BudgetPools<'_>,
+    ) -> Option<MissReason<'b>> {
+        None
+    }
+
+    /// Describe the smallest change that would unlock a better reward from this
+    /// promotion when it already applies, such as reaching a higher tier.
+    fn upsell<'b>(&self, _item_group: &ItemGroup<'b>) -> Option<Shortfall<'b>> {
+        None
+    }
+
+    /// Return which of this promotion's qualifications the item matches.
+    ///
+    /// Items with the same price, quantity and signature under every promotion
+    /// are interchangeable to the solver, and an item matching nothing cannot
+    /// change any promotion's outcome. Basket estimates rely on this to avoid
+    /// solving once per candidate. Return `None` (the default) when the
+    /// promotion inspects items in other ways; estimates then fall back to
+    /// comparing tags.
+    fn item_signature(
+        &self,
+        _item: &Item<'_>,
+        _context: &EvaluationContext,
+    ) -> Option<ItemSignature> {
+        None
+    }
+
+    /// Return the greedy heuristic pricing this promotion, if it has one.
+    ///
+    /// [`GreedySolver`](crate::solvers::greedy::GreedySolver) uses it to price
+    /// baskets without building an ILP. The default implementation has none, so
+    /// greedy solves reject the promotion whenever it applies.
+    fn greedy(&self) -> Option<&dyn GreedyPromotion> {
+        None
+    }
+
+    /// Return the enumeration pricing this promotion, if it has one.
+    ///
+    /// [`ExhaustiveSolver`](crate::solvers::exhaustive::ExhaustiveSolver) uses it to
+    /// find the optimum of small baskets by brute force. The default implementation
+    /// has none, so exhaustive solves reject the promotion whenever it applies.
+    fn exhaustive(&self) -> Option<&dyn ExhaustivePromotion> {
+        None
+    }
+
     /// Return whether this promotion _might_ apply to the given item group.
     ///
     /// This is used as a fast pre-check to avoid allocating variables/constraints for
@@ -354,6 +973,66 @@ impl ILPPromotion for Arc<dyn ILPPromotion + '_> {
         self.as_ref().key()
     }
 
+    fn coupon(&self) -> Option<&PromotionCoupon> {
+        self.as_ref().coupon()
+    }
+
+    fn budget_pools(&self) -> &[BudgetPoolKey] {
+        self.as_ref().budget_pools()
+    }
+
+    fn priority(&self) -> i32 {
+        self.as_ref().priority()
+    }
+
+    fn funding(&self) -> &PromotionFunding {
+        self.as_ref().funding()
+    }
+
+    fn redeems_each_unit(&self) -> bool {
+        self.as_ref().redeems_each_unit()
+    }
+
+    fn is_mandatory(&self) -> bool {
+        self.as_ref().is_mandatory()
+    }
+
+    fn rounding(&self) -> Option<&RoundingPolicies> {
+        self.as_ref().rounding()
+    }
+
+    fn exclusions(&self) -> &PromotionExclusions {
+        self.as_ref().exclusions()
+    }
+
+    fn near_miss<'b>(
+        &self,
+        item_group: &ItemGroup<'b>,
+        pools: &BudgetPools<'_>,
+    ) -> Option<MissReason<'b>> {
+        self.as_ref().near_miss(item_group, pools)
+    }
+
+    fn upsell<'b>(&self, item_group: &ItemGroup<'b>) -> Option<Shortfall<'b>> {
+        self.as_ref().upsell(item_group)
+    }
+
+    fn item_signature(
+        &self,
+        item: &Item<'_>,
+        context: &EvaluationContext,
+    ) -> Option<ItemSignature> {
+        self.as_ref().item_signature(item, context)
+    }
+
+    fn greedy(&self) -> Option<&dyn GreedyPromotion> {
+        self.as_ref().greedy()
+    }
+
+    fn exhaustive(&self) -> Option<&dyn ExhaustivePromotion> {
+        self.as_ref().exhaustive()
+    }
+
     fn is_applicable(&self, item_group: &ItemGroup<'_>) -> bool {
         self.as_ref().is_applicable(item_group)
     }
@@ -818,4 +1497,34 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn spread_floor_excess_splits_a_quantity_line_for_the_remainder() {
+        let redemption = PromotionRedemption {
+            promotion_key: PromotionKey::default(),
+            item_idx: 0,
+            redemption_idx: 0,
+            original_price: Money::from_minor(20, GBP),
+            final_price: Money::from_minor(10, GBP),
+            quantity: 3,
+            funding: SmallVec::new(),
+            floor_limited: false,
+        };
+
+        let mut redemptions: SmallVec<[PromotionRedemption<'_>; 10]> =
+            SmallVec::from_iter([redemption]);
+        let mut floors: SmallVec<[i64; 10]> = SmallVec::from_iter([0]);
+
+        let left = spread_floor_excess(&mut redemptions, &mut floors, 7, |_| true);
+
+        let lines: Vec<(u32, i64)> = redemptions
+            .iter()
+            .map(|r| (r.quantity, r.final_price.to_minor_units()))
+            .collect();
+
+        // Two units take 2p each and the third takes the remaining 3p.
+        assert_eq!(left, 0);
+        assert_eq!(lines, [(2, 8), (1, 7)]);
+        assert_eq!(floors.as_slice(), &[0, 0]);
+    }
 }
@@ -6,26 +6,46 @@ use std::any::Any;
 use decimal_percentage::Percentage;
 use good_lp::{Expression, Solution, Variable, variable};
 use rustc_hash::FxHashMap;
-use smallvec::SmallVec;
+use smallvec::{SmallVec, smallvec};
 
 use rusty_money::Money;
 
 use crate::{
-    discounts::{SimpleDiscount, percent_of_minor},
-    items::groups::ItemGroup,
+    context::EvaluationContext,
+    discounts::{
+        SimpleDiscount,
+        rounding::{RoundingPolicies, RoundingPolicy, RoundingScope},
+    },
+    items::{Item, groups::ItemGroup},
     promotions::{
-        PromotionKey, redemptions::PromotionRedemption, types::PositionalDiscountPromotion,
+        PromotionKey,
+        budget::{BudgetPoolKey, BudgetPools},
+        coupon::PromotionCoupon,
+        exclusion::PromotionExclusions,
+        explain::{MissReason, gate_reason},
+        funding::PromotionFunding,
+        redemptions::PromotionRedemption,
+        types::PositionalDiscountPromotion,
     },
     solvers::{
         SolverError,
+        exhaustive::ExhaustivePromotion,
+        greedy::GreedyPromotion,
         ilp::{
-            BINARY_THRESHOLD, ILPObserver, i64_to_f64_exact,
-            promotions::{ILPPromotion, ILPPromotionVars, PromotionVars},
+            ILPObserver, i64_to_f64_exact, item_units_variable,
+            promotions::{
+                ILPPromotion, ILPPromotionVars, ItemSignature, PriceOutcomes, PromotionVars,
+            },
+            solution_units,
             state::ILPState,
         },
     },
 };
 
+/// Multi-unit DFA transitions at one position: `(stride, vars)` pairs where `vars[r]`
+/// takes `stride` units when in state r.
+type PositionJumpVars = SmallVec<[(usize, SmallVec<[Variable; 8]>); 4]>;
+
 #[derive(Debug, Clone, Copy)]
 enum PositionalRuntimeDiscount {
     PercentageOff(Percentage),
@@ -36,7 +56,7 @@ enum PositionalRuntimeDiscount {
 /// Solver variables for a positional discount promotion.
 ///
 /// Tracks the mapping from item group indices to their corresponding
-/// binary decision variables in the ILP model.
+/// unit count decision variables in the ILP model.
 #[derive(Debug)]
 pub struct PositionalDiscountVars {
     /// Promotion key for observer metadata.
@@ -57,6 +77,9 @@ pub struct PositionalDiscountVars {
     /// Runtime discount mode captured during variable creation.
     runtime_discount: PositionalRuntimeDiscount,
 
+    /// Rounding for percentage discounts, resolved during variable creation.
+    rounding: RoundingPolicy,
+
     /// Bundle size copied from promotion config.
     bundle_size: usize,
 
@@ -81,6 +104,39 @@ struct PositionalDFAConstraintData {
 
     /// DFA transition variables: `take_vars[pos][r]` = take item at pos when in state r
     take_vars: SmallVec<[SmallVec<[Variable; 8]>; 12]>,
+
+    /// Multi-unit DFA transitions for quantity lines: `jump_vars[pos]` holds
+    /// `(stride, vars)` pairs where `vars[r]` = take `stride` units at pos when in state r
+    jump_vars: SmallVec<[PositionJumpVars; 12]>,
+
+    /// Whole bundles taken at pos without changing state (quantity lines only)
+    cycle_vars: SmallVec<[Option<Variable>; 12]>,
+}
+
+impl PositionalDFAConstraintData {
+    /// Multi-unit transitions available at `pos`.
+    fn jumps_at(&self, pos: usize) -> &[(usize, SmallVec<[Variable; 8]>)] {
+        self.jump_vars
+            .get(pos)
+            .map_or(&[], |jumps| jumps.as_slice())
+    }
+
+    /// Whole-bundle cycle count variable at `pos`, if the line can fill a bundle on its own.
+    fn cycle_at(&self, pos: usize) -> Option<Variable> {
+        self.cycle_vars.get(pos).copied().flatten()
+    }
+
+    /// Number of discounted bundle positions covered by taking `stride` units from state `r`.
+    fn discounted_positions_in_stride(&self, r: usize, stride: usize) -> usize {
+        let size = usize::from(self.size);
+
+        (r..r + stride)
+            .filter(|offset| {
+                u16::try_from(offset % size)
+                    .is_ok_and(|position| self.positions.contains(&position))
+            })
+            .count()
+    }
 }
 
 impl PositionalDiscountVars {
@@ -97,9 +153,7 @@ impl PositionalDiscountVars {
 
     /// Check if an item is discounted based on the solution.
     pub fn is_item_discounted(&self, solution: &dyn Solution, item_idx: usize) -> bool {
-        self.item_discounts
-            .iter()
-            .any(|&(idx, var)| idx == item_idx && solution.value(var) > BINARY_THRESHOLD)
+        units_for_item(&self.item_discounts, solution, item_idx) > 0
     }
 
     /// Add DFA constraints to the model.
@@ -119,6 +173,10 @@ impl PositionalDiscountVars {
     /// These state transitions are enforced with linear constraints so that
     /// only valid sequences of takes and skips are possible.
     ///
+    /// Quantity lines take several units at a single position: a "jump" of
+    /// `stride` units advances the state by `stride`, and whole bundles taken
+    /// from the line are counted separately since they leave the state as-is.
+    ///
     /// The model then links these transitions back to the rest of the pricing
     /// logic: whether an item participates in the promotion, and whether it
     /// receives a discount, is determined by the position where it falls within
@@ -266,7 +324,20 @@ impl PositionalDiscountVars {
                     continue;
                 };
 
-                let transition_expr = curr_state - take_curr + take_prev;
+                let mut transition_expr = curr_state - take_curr + take_prev;
+
+                for (stride, jumps) in dfa_data.jumps_at(pos) {
+                    let r_from = (r + bundle_size - stride % bundle_size) % bundle_size;
+
+                    if let Some(&jump_out) = jumps.get(r) {
+                        transition_expr -= jump_out;
+                    }
+
+                    if let Some(&jump_in) = jumps.get(r_from) {
+                        transition_expr += jump_in;
+                    }
+                }
+
                 let expr = Expression::from(next_state) - transition_expr.clone();
 
                 observer.on_promotion_constraint(
@@ -291,12 +362,24 @@ impl PositionalDiscountVars {
         observer: &mut dyn ILPObserver,
     ) {
         for eligible_idx in 0..num_eligible {
-            let take_sum: Expression = dfa_data
+            let mut take_sum: Expression = dfa_data
                 .take_vars
                 .get(eligible_idx)
                 .map(|takes| takes.iter().copied().sum())
                 .unwrap_or_default();
 
+            for (stride, jumps) in dfa_data.jumps_at(eligible_idx) {
+                let stride_i32 = i32::try_from(*stride).unwrap_or(i32::MAX);
+
+                for &jump_var in jumps {
+                    take_sum += stride_i32 * jump_var;
+                }
+            }
+
+            if let Some(cycle_var) = dfa_data.cycle_at(eligible_idx) {
+                take_sum += i32::from(dfa_data.size) * cycle_var;
+            }
+
             if let Some(&(_idx, participation_var)) = self.item_participation.get(eligible_idx) {
                 let expr = Expression::from(participation_var);
                 let observed_expr = Expression::from(participation_var) - take_sum.clone();
@@ -340,6 +423,22 @@ impl PositionalDiscountVars {
                 }
             }
 
+            for (stride, jumps) in dfa_data.jumps_at(eligible_idx) {
+                for (r, &jump_var) in jumps.iter().enumerate() {
+                    let covered = dfa_data.discounted_positions_in_stride(r, *stride);
+
+                    if covered > 0 {
+                        discount_sum += i32::try_from(covered).unwrap_or(i32::MAX) * jump_var;
+                    }
+                }
+            }
+
+            if let Some(cycle_var) = dfa_data.cycle_at(eligible_idx) {
+                let covered = i32::try_from(dfa_data.positions.len()).unwrap_or(i32::MAX);
+
+                discount_sum += covered * cycle_var;
+            }
+
             if let Some(&(_idx, discount_var)) = self.item_discounts.get(eligible_idx) {
                 let expr = Expression::from(discount_var);
                 let observed_expr = Expression::from(discount_var) - discount_sum.clone();
@@ -371,7 +470,16 @@ impl PositionalDiscountVars {
                     dfa_data.take_vars.get(pos).and_then(|t| t.get(r).copied()),
                     dfa_data.state_vars.get(pos).and_then(|s| s.get(r).copied()),
                 ) {
-                    let expr = Expression::from(take_var) - state_var;
+                    // At most one transition (a single take or one jump) leaves each state.
+                    let mut moves = Expression::from(take_var);
+
+                    for (_stride, jumps) in dfa_data.jumps_at(pos) {
+                        if let Some(&jump_var) = jumps.get(r) {
+                            moves += jump_var;
+                        }
+                    }
+
+                    let expr = moves.clone() - state_var;
 
                     observer.on_promotion_constraint(
                         promotion_key,
@@ -381,12 +489,82 @@ impl PositionalDiscountVars {
                         0.0,
                     );
 
-                    state.add_leq_constraint(Expression::from(take_var) - state_var, 0.0);
+                    state.add_leq_constraint(moves - state_var, 0.0);
                 }
             }
         }
     }
 
+    /// Whether percentage discounts are rounded once per bundle.
+    fn is_rounded_per_bundle(&self) -> bool {
+        matches!(
+            self.runtime_discount,
+            PositionalRuntimeDiscount::PercentageOff(_)
+        ) && self.rounding.scope() == RoundingScope::PerBundle
+    }
+
+    /// Final prices of one bundle's `units`, discounting the units flagged in
+    /// `discounted`.
+    fn bundle_final_prices(
+        &self,
+        units: &[(usize, i64)],
+        discounted: &[bool],
+    ) -> Result<SmallVec<[i64; 10]>, SolverError> {
+        let mut prices: SmallVec<[i64; 10]> =
+            units.iter().map(|&(_item_idx, price)| price).collect();
+
+        let PositionalRuntimeDiscount::PercentageOff(pct) = self.runtime_discount else {
+            for (price, _discounted) in prices
+                .iter_mut()
+                .zip(discounted)
+                .filter(|(_price, discounted)| **discounted)
+            {
+                *price = calculate_discounted_minor_for_runtime(
+                    *price,
+                    self.runtime_discount,
+                    self.rounding,
+                )?;
+            }
+
+            return Ok(prices);
+        };
+
+        self.rounding
+            .discount_units(&pct, &mut prices, discounted)
+            .map_err(SolverError::Discount)?;
+
+        Ok(prices)
+    }
+
+    /// Total participating units, including undiscounted bundle positions.
+    fn participation_sum(&self) -> Expression {
+        self.item_participation.iter().map(|(_, var)| *var).sum()
+    }
+
+    /// Total discount value: `sum(discount_amount * discount_var)`
+    fn discount_expr(&self, item_group: &ItemGroup<'_>) -> Result<Expression, SolverError> {
+        let mut discount_expr = Expression::default();
+
+        for &(item_idx, discount_var) in &self.item_discounts {
+            let item = item_group.get_item(item_idx).map_err(SolverError::from)?;
+
+            let full_minor = item.price().to_minor_units();
+            let discounted_minor = calculate_discounted_minor_for_runtime(
+                full_minor,
+                self.runtime_discount,
+                self.rounding,
+            )?;
+
+            let discount_amount = full_minor.saturating_sub(discounted_minor);
+            let coeff = i64_to_f64_exact(discount_amount)
+                .ok_or(SolverError::MinorUnitsNotRepresentable(discount_amount))?;
+
+            discount_expr += discount_var * coeff;
+        }
+
+        Ok(discount_expr)
+    }
+
     /// Add budget constraints for positional promotions
     pub fn add_budget_constraints(
         &self,
@@ -400,8 +578,7 @@ impl PositionalDiscountVars {
         // Redemption limit: For positional, this limits bundles
         // Constraint: sum(participation_vars) <= redemption_limit * bundle_size
         if let Some(redemption_limit) = self.redemption_limit {
-            let participation_sum: Expression =
-                self.item_participation.iter().map(|(_, var)| *var).sum();
+            let participation_sum = self.participation_sum();
 
             let bundle_size_u32 =
                 u32::try_from(bundle_size).map_err(|_e| SolverError::InvariantViolation {
@@ -430,21 +607,7 @@ impl PositionalDiscountVars {
 
         // Monetary limit: sum(discount_amount * discount_var) <= limit
         if let Some(limit_minor) = self.monetary_limit_minor {
-            let mut discount_expr = Expression::default();
-
-            for &(item_idx, discount_var) in &self.item_discounts {
-                let item = item_group.get_item(item_idx).map_err(SolverError::from)?;
-
-                let full_minor = item.price().to_minor_units();
-                let discounted_minor =
-                    calculate_discounted_minor_for_runtime(full_minor, self.runtime_discount)?;
-
-                let discount_amount = full_minor.saturating_sub(discounted_minor);
-                let coeff = i64_to_f64_exact(discount_amount)
-                    .ok_or(SolverError::MinorUnitsNotRepresentable(discount_amount))?;
-
-                discount_expr += discount_var * coeff;
-            }
+            let discount_expr = self.discount_expr(item_group)? - state.floor_excess(promotion_key);
 
             let limit_f64 = i64_to_f64_exact(limit_minor)
                 .ok_or(SolverError::MinorUnitsNotRepresentable(limit_minor))?;
@@ -477,16 +640,80 @@ impl ILPPromotionVars for PositionalDiscountVars {
         updated_expr
     }
 
+    fn add_item_discount_term(&self, expr: Expression, item_idx: usize) -> Expression {
+        let mut updated_expr = expr;
+
+        for &(idx, var) in &self.item_discounts {
+            if idx == item_idx {
+                updated_expr += var;
+            }
+        }
+
+        updated_expr
+    }
+
     fn is_item_participating(&self, solution: &dyn Solution, item_idx: usize) -> bool {
-        self.item_participation
-            .iter()
-            .any(|&(idx, var)| idx == item_idx && solution.value(var) > BINARY_THRESHOLD)
+        units_for_item(&self.item_participation, solution, item_idx) > 0
     }
 
     fn is_item_priced_by_promotion(&self, solution: &dyn Solution, item_idx: usize) -> bool {
         self.is_item_discounted(solution, item_idx)
     }
 
+    fn item_price_outcomes(
+        &self,
+        item_group: &ItemGroup<'_>,
+        item_idx: usize,
+    ) -> Result<Option<PriceOutcomes>, SolverError> {
+        // Rounding per bundle makes a unit's price depend on the rest of its bundle.
+        if self.is_rounded_per_bundle() {
+            return Ok(None);
+        }
+
+        let mut outcomes = PriceOutcomes::new();
+
+        for (&(idx, participation_var), &(_, discount_var)) in
+            self.item_participation.iter().zip(&self.item_discounts)
+        {
+            if idx != item_idx {
+                continue;
+            }
+
+            let original_minor = item_group.get_item(idx)?.price().to_minor_units();
+            let discounted_minor = calculate_discounted_minor_for_runtime(
+                original_minor,
+                self.runtime_discount,
+                self.rounding,
+            )?;
+
+            // Bundle members outside the discounted positions keep their price.
+            outcomes.push((
+                Expression::from(participation_var) - discount_var,
+                original_minor,
+            ));
+            outcomes.push((Expression::from(discount_var), discounted_minor));
+        }
+
+        Ok(Some(outcomes))
+    }
+
+    fn redemption_count_expr(&self) -> Option<Expression> {
+        // Participation only forms whole bundles, so each bundle of `bundle_size`
+        // units is one redemption.
+        let bundle_size = u32::try_from(self.bundle_size)
+            .ok()
+            .filter(|&size| size > 0)?;
+
+        Some(self.participation_sum() * (1.0 / f64::from(bundle_size)))
+    }
+
+    fn discount_value_expr(
+        &self,
+        item_group: &ItemGroup<'_>,
+    ) -> Result<Option<Expression>, SolverError> {
+        self.discount_expr(item_group).map(Some)
+    }
+
     fn add_constraints(
         &self,
         promotion_key: PromotionKey,
@@ -512,7 +739,11 @@ impl ILPPromotionVars for PositionalDiscountVars {
             let original_minor = item.price().to_minor_units();
 
             let final_minor = if self.is_item_priced_by_promotion(solution, item_idx) {
-                calculate_discounted_minor_for_runtime(original_minor, self.runtime_discount)?
+                calculate_discounted_minor_for_runtime(
+                    original_minor,
+                    self.runtime_discount,
+                    self.rounding,
+                )?
             } else {
                 original_minor
             };
@@ -534,31 +765,79 @@ impl ILPPromotionVars for PositionalDiscountVars {
         let currency = item_group.currency();
         let bundle_size = self.bundle_size;
 
-        let mut participating_items: SmallVec<[(usize, i64); 10]> = SmallVec::new();
+        // Expand participating lines into units, in the same order the DFA walks them.
+        let mut participating_units: SmallVec<[(usize, i64); 10]> = SmallVec::new();
+        let mut remaining_discounts: FxHashMap<usize, u32> = FxHashMap::default();
 
         for (item_idx, item) in item_group.iter().enumerate() {
-            if self.is_item_participating(solution, item_idx) {
-                participating_items.push((item_idx, item.price().to_minor_units()));
+            let units = units_for_item(&self.item_participation, solution, item_idx);
+
+            for _unit in 0..units {
+                participating_units.push((item_idx, item.price().to_minor_units()));
+            }
+
+            if units > 0 {
+                remaining_discounts.insert(
+                    item_idx,
+                    units_for_item(&self.item_discounts, solution, item_idx),
+                );
             }
         }
 
-        participating_items.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        participating_units.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        let positions: SmallVec<[u16; 5]> = self
+            .dfa_data
+            .as_ref()
+            .map(|dfa_data| dfa_data.positions.clone())
+            .unwrap_or_default();
 
-        for chunk in participating_items.chunks(bundle_size) {
+        for chunk in participating_units.chunks(bundle_size) {
             let redemption_idx = *next_redemption_idx;
             *next_redemption_idx += 1;
 
-            for &(item_idx, price_minor) in chunk {
-                let item = item_group.get_item(item_idx)?;
+            let bundle_start = redemptions.len();
 
-                let final_price = if self.is_item_priced_by_promotion(solution, item_idx) {
-                    Money::from_minor(
-                        calculate_discounted_minor_for_runtime(price_minor, self.runtime_discount)?,
-                        currency,
-                    )
-                } else {
-                    Money::from_minor(price_minor, currency)
-                };
+            let discounted: SmallVec<[bool; 10]> = chunk
+                .iter()
+                .enumerate()
+                .map(|(position, &(item_idx, _price_minor))| {
+                    let at_discounted_position =
+                        u16::try_from(position).is_ok_and(|position| positions.contains(&position));
+
+                    at_discounted_position
+                        && remaining_discounts
+                            .get_mut(&item_idx)
+                            .is_some_and(|remaining| {
+                                let available = *remaining > 0;
+
+                                *remaining = remaining.saturating_sub(1);
+
+                                available
+                            })
+                })
+                .collect();
+
+            let final_prices = self.bundle_final_prices(chunk, &discounted)?;
+
+            for (&(item_idx, _price_minor), final_minor) in chunk.iter().zip(final_prices) {
+                let final_price = Money::from_minor(final_minor, currency);
+
+                // Units of the same line at the same price share one redemption entry.
+                let existing = redemptions.get_mut(bundle_start..).and_then(
+                    |entries: &mut [PromotionRedemption<'b>]| {
+                        entries.iter_mut().find(|redemption| {
+                            redemption.item_idx == item_idx && redemption.final_price == final_price
+                        })
+                    },
+                );
+
+                if let Some(redemption) = existing {
+                    redemption.quantity += 1;
+                    continue;
+                }
+
+                let item = item_group.get_item(item_idx)?;
 
                 redemptions.push(PromotionRedemption {
                     promotion_key,
@@ -566,6 +845,9 @@ impl ILPPromotionVars for PositionalDiscountVars {
                     redemption_idx,
                     original_price: *item.price(),
                     final_price,
+                    quantity: 1,
+                    funding: SmallVec::new(),
+                    floor_limited: false,
                 });
             }
         }
@@ -574,6 +856,14 @@ impl ILPPromotionVars for PositionalDiscountVars {
     }
 }
 
+/// Total units selected by the variables belonging to `item_idx`.
+fn units_for_item(vars: &[(usize, Variable)], solution: &dyn Solution, item_idx: usize) -> u32 {
+    vars.iter()
+        .filter(|&&(idx, _var)| idx == item_idx)
+        .map(|&(_idx, var)| solution_units(solution, var))
+        .sum()
+}
+
 fn positional_runtime_discount_from_config(
     discount: &SimpleDiscount<'_>,
 ) -> PositionalRuntimeDiscount {
@@ -591,11 +881,13 @@ fn positional_runtime_discount_from_config(
 fn calculate_discounted_minor_for_runtime(
     original_minor: i64,
     discount: PositionalRuntimeDiscount,
+    rounding: RoundingPolicy,
 ) -> Result<i64, SolverError> {
     let discount_minor = match discount {
         PositionalRuntimeDiscount::PercentageOff(pct) => {
-            let discount_minor =
-                percent_of_minor(&pct, original_minor).map_err(SolverError::Discount)?;
+            let discount_minor = rounding
+                .discount_of(&pct, original_minor)
+                .map_err(SolverError::Discount)?;
 
             original_minor.saturating_sub(discount_minor)
         }
@@ -613,8 +905,79 @@ impl ILPPromotion for PositionalDiscountPromotion<'_> {
         PositionalDiscountPromotion::key(self)
     }
 
+    fn coupon(&self) -> Option<&PromotionCoupon> {
+        PositionalDiscountPromotion::coupon(self)
+    }
+
+    fn budget_pools(&self) -> &[BudgetPoolKey] {
+        PositionalDiscountPromotion::budget_pools(self)
+    }
+
+    fn priority(&self) -> i32 {
+        PositionalDiscountPromotion::priority(self)
+    }
+
+    fn funding(&self) -> &PromotionFunding {
+        PositionalDiscountPromotion::funding(self)
+    }
+
+    fn is_mandatory(&self) -> bool {
+        PositionalDiscountPromotion::is_mandatory(self)
+    }
+
+    fn rounding(&self) -> Option<&RoundingPolicies> {
+        PositionalDiscountPromotion::rounding(self)
+    }
+
+    fn exclusions(&self) -> &PromotionExclusions {
+        PositionalDiscountPromotion::exclusions(self)
+    }
+
+    fn near_miss<'b>(
+        &self,
+        item_group: &ItemGroup<'b>,
+        pools: &BudgetPools<'_>,
+    ) -> Option<MissReason<'b>> {
+        gate_reason(
+            self.schedule(),
+            self.coupon(),
+            self.budget(),
+            self.redemption_limit(),
+            self.budget_pools(),
+            pools,
+            item_group.context(),
+        )
+        .or_else(|| self.shortfall(item_group).map(MissReason::Shortfall))
+    }
+
+    fn item_signature(
+        &self,
+        item: &Item<'_>,
+        context: &EvaluationContext,
+    ) -> Option<ItemSignature> {
+        Some(smallvec![
+            self.qualification()
+                .matches_in_context(item.tags(), context)
+        ])
+    }
+
+    fn greedy(&self) -> Option<&dyn GreedyPromotion> {
+        Some(self)
+    }
+
+    fn exhaustive(&self) -> Option<&dyn ExhaustivePromotion> {
+        Some(self)
+    }
+
     fn is_applicable(&self, item_group: &ItemGroup<'_>) -> bool {
-        if item_group.is_empty() {
+        let context = item_group.context();
+
+        if item_group.is_empty()
+            || !self.schedule().is_active_in(context)
+            || !self
+                .coupon()
+                .is_none_or(|coupon| coupon.is_presented_in(context))
+        {
             return false;
         }
 
@@ -622,7 +985,7 @@ impl ILPPromotion for PositionalDiscountPromotion<'_> {
 
         item_group
             .iter()
-            .any(|item| qualification.matches(item.tags()))
+            .any(|item| qualification.matches_in_context(item.tags(), item_group.context()))
     }
 
     #[expect(
@@ -637,8 +1000,9 @@ impl ILPPromotion for PositionalDiscountPromotion<'_> {
     ) -> Result<PromotionVars, SolverError> {
         let promotion_key = self.key();
         let runtime_discount = positional_runtime_discount_from_config(self.discount());
+        let rounding = state.rounding_for(self.rounding(), item_group.currency());
         let bundle_size = self.size() as usize;
-        let redemption_limit = self.budget().redemption_limit;
+        let redemption_limit = self.redemption_limit();
         let monetary_limit_minor = self
             .budget()
             .monetary_limit
@@ -648,7 +1012,10 @@ impl ILPPromotion for PositionalDiscountPromotion<'_> {
         let mut eligible: SmallVec<[(usize, i64); 10]> = SmallVec::new();
 
         for (item_idx, item) in item_group.iter().enumerate() {
-            if !self.qualification().matches(item.tags()) {
+            if !self
+                .qualification()
+                .matches_in_context(item.tags(), item_group.context())
+            {
                 continue;
             }
 
@@ -660,9 +1027,15 @@ impl ILPPromotion for PositionalDiscountPromotion<'_> {
         });
 
         let num_eligible = eligible.len();
-        // Early return if there are insufficient items that are eligible for even
+        let mut eligible_units = 0_usize;
+
+        for &(item_idx, _price_minor) in &eligible {
+            eligible_units += item_group.get_item(item_idx)?.quantity() as usize;
+        }
+
+        // Early return if there are insufficient units that are eligible for even
         // a single bundle
-        if num_eligible < bundle_size {
+        if eligible_units < bundle_size {
             return Ok(Box::new(PositionalDiscountVars {
                 promotion_key,
                 eligible_items: SmallVec::new(),
@@ -670,6 +1043,7 @@ impl ILPPromotion for PositionalDiscountPromotion<'_> {
                 item_discounts: SmallVec::new(),
                 dfa_data: None,
                 runtime_discount,
+                rounding,
                 bundle_size,
                 redemption_limit,
                 monetary_limit_minor,
@@ -685,8 +1059,10 @@ impl ILPPromotion for PositionalDiscountPromotion<'_> {
 
             let original_minor = item.price().to_minor_units();
 
-            // Create participation variable
-            let participation_var = state.problem_variables_mut().add(variable().binary());
+            // Create participation variable (counts units for quantity lines)
+            let participation_var = state
+                .problem_variables_mut()
+                .add(item_units_variable(item.quantity()));
             item_participation.push((item_idx, participation_var));
 
             // Add objective contribution for participation (full price)
@@ -708,10 +1084,12 @@ impl ILPPromotion for PositionalDiscountPromotion<'_> {
 
             // Calculate discounted price
             let discounted_minor =
-                calculate_discounted_minor_for_runtime(original_minor, runtime_discount)?;
+                calculate_discounted_minor_for_runtime(original_minor, runtime_discount, rounding)?;
 
-            // Create discount variable
-            let discount_var = state.problem_variables_mut().add(variable().binary());
+            // Create discount variable (counts units for quantity lines)
+            let discount_var = state
+                .problem_variables_mut()
+                .add(item_units_variable(item.quantity()));
             item_discounts.push((item_idx, discount_var));
 
             // Subtract discount contribution from the objective
@@ -744,8 +1122,10 @@ impl ILPPromotion for PositionalDiscountPromotion<'_> {
             SmallVec::<[SmallVec<[Variable; 8]>; 12]>::with_capacity(num_eligible + 1);
 
         let mut take_vars = SmallVec::<[SmallVec<[Variable; 8]>; 12]>::with_capacity(num_eligible);
+        let mut jump_vars = SmallVec::<[PositionJumpVars; 12]>::with_capacity(num_eligible);
+        let mut cycle_vars = SmallVec::<[Option<Variable>; 12]>::with_capacity(num_eligible);
 
-        for pos in 0..num_eligible {
+        for (pos, &(item_idx, _price_minor)) in eligible.iter().enumerate() {
             let mut states_at_pos = SmallVec::<[Variable; 8]>::with_capacity(bundle_size);
             let mut takes_at_pos = SmallVec::<[Variable; 8]>::with_capacity(bundle_size);
 
@@ -774,6 +1154,59 @@ impl ILPPromotion for PositionalDiscountPromotion<'_> {
 
             state_vars.push(states_at_pos);
             take_vars.push(takes_at_pos);
+
+            // Quantity lines can take several units at once: partial strides move
+            // the state forward, whole bundles are counted by the cycle variable.
+            let quantity = item_group.get_item(item_idx)?.quantity() as usize;
+            let mut jumps_at_pos = SmallVec::<[(usize, SmallVec<[Variable; 8]>); 4]>::new();
+
+            for stride in 2..=quantity.min(bundle_size.saturating_sub(1)) {
+                let jumps: SmallVec<[Variable; 8]> = (0..bundle_size)
+                    .map(|r| {
+                        let jump_var = state.problem_variables_mut().add(variable().binary());
+
+                        observer.on_auxiliary_variable(
+                            promotion_key,
+                            jump_var,
+                            "DFA jump",
+                            Some(pos),
+                            Some(r),
+                        );
+
+                        jump_var
+                    })
+                    .collect();
+
+                jumps_at_pos.push((stride, jumps));
+            }
+
+            let max_cycles = if quantity > 1 {
+                quantity / bundle_size
+            } else {
+                0
+            };
+
+            let cycle_var = (max_cycles > 0).then(|| {
+                let cycle_var = state.problem_variables_mut().add(
+                    variable()
+                        .integer()
+                        .min(0)
+                        .max(u32::try_from(max_cycles).unwrap_or(u32::MAX)),
+                );
+
+                observer.on_auxiliary_variable(
+                    promotion_key,
+                    cycle_var,
+                    "DFA bundle cycles",
+                    Some(pos),
+                    None,
+                );
+
+                cycle_var
+            });
+
+            jump_vars.push(jumps_at_pos);
+            cycle_vars.push(cycle_var);
         }
 
         // Final state position (after all items processed)
@@ -804,10 +1237,13 @@ impl ILPPromotion for PositionalDiscountPromotion<'_> {
                     state_vars
                 },
                 take_vars,
+                jump_vars,
+                cycle_vars,
                 size: self.size(),
                 positions: self.positions().iter().copied().collect(),
             }),
             runtime_discount,
+            rounding,
             bundle_size,
             redemption_limit,
             monetary_limit_minor,
@@ -850,6 +1286,7 @@ mod tests {
             positional_runtime_discount_from_config(&SimpleDiscount::PercentageOff(
                 Percentage::from(0.25),
             )),
+            RoundingPolicy::default(),
         )?;
 
         assert_eq!(pct, 75);
@@ -859,6 +1296,7 @@ mod tests {
             positional_runtime_discount_from_config(&SimpleDiscount::AmountOverride(
                 Money::from_minor(60, GBP),
             )),
+            RoundingPolicy::default(),
         )?;
 
         assert_eq!(override_price, 60);
@@ -868,6 +1306,7 @@ mod tests {
             positional_runtime_discount_from_config(&SimpleDiscount::AmountOff(Money::from_minor(
                 30, GBP,
             ))),
+            RoundingPolicy::default(),
         )?;
 
         assert_eq!(amount_off, 70);
@@ -877,6 +1316,7 @@ mod tests {
             positional_runtime_discount_from_config(&SimpleDiscount::AmountOff(Money::from_minor(
                 200, GBP,
             ))),
+            RoundingPolicy::default(),
         )?;
 
         assert_eq!(clamped, 0);
@@ -1042,9 +1482,12 @@ mod tests {
                 size: 1,
                 positions: SmallVec::from_vec(vec![0]),
                 state_vars: SmallVec::from_vec(vec![SmallVec::from_vec(vec![state_var])]),
+                jump_vars: SmallVec::new(),
+                cycle_vars: SmallVec::new(),
                 take_vars: SmallVec::from_vec(vec![SmallVec::from_vec(vec![take_var])]),
             }),
             runtime_discount: PositionalRuntimeDiscount::PercentageOff(Percentage::from(0.5)),
+            rounding: RoundingPolicy::default(),
             bundle_size: 1,
             redemption_limit: None,
             monetary_limit_minor: None,
@@ -1094,9 +1537,12 @@ mod tests {
                     SmallVec::new(),
                     SmallVec::from_vec(vec![next_state]),
                 ]),
+                jump_vars: SmallVec::new(),
+                cycle_vars: SmallVec::new(),
                 take_vars: SmallVec::from_vec(vec![SmallVec::from_vec(vec![take_var])]),
             }),
             runtime_discount: PositionalRuntimeDiscount::PercentageOff(Percentage::from(0.5)),
+            rounding: RoundingPolicy::default(),
             bundle_size: 1,
             redemption_limit: None,
             monetary_limit_minor: None,
@@ -1149,9 +1595,12 @@ mod tests {
                     SmallVec::from_vec(vec![state_now]),
                     SmallVec::from_vec(vec![state_next]),
                 ]),
+                jump_vars: SmallVec::new(),
+                cycle_vars: SmallVec::new(),
                 take_vars: SmallVec::from_vec(vec![SmallVec::new()]),
             }),
             runtime_discount: PositionalRuntimeDiscount::PercentageOff(Percentage::from(0.5)),
+            rounding: RoundingPolicy::default(),
             bundle_size: 1,
             redemption_limit: None,
             monetary_limit_minor: None,
@@ -1208,9 +1657,12 @@ mod tests {
                     SmallVec::from_vec(vec![state_now]),
                     SmallVec::from_vec(vec![state_next]),
                 ]),
+                jump_vars: SmallVec::new(),
+                cycle_vars: SmallVec::new(),
                 take_vars: SmallVec::from_vec(vec![SmallVec::from_vec(vec![take_curr])]),
             }),
             runtime_discount: PositionalRuntimeDiscount::PercentageOff(Percentage::from(0.5)),
+            rounding: RoundingPolicy::default(),
             bundle_size: 2,
             redemption_limit: None,
             monetary_limit_minor: None,
@@ -1445,12 +1897,15 @@ mod tests {
                     SmallVec::from_vec(vec![s10, s11]),
                     SmallVec::from_vec(vec![s20, s21]),
                 ]),
+                jump_vars: SmallVec::new(),
+                cycle_vars: SmallVec::new(),
                 take_vars: SmallVec::from_vec(vec![
                     SmallVec::from_vec(vec![t00, t01]),
                     SmallVec::from_vec(vec![t10, t11]),
                 ]),
             }),
             runtime_discount: PositionalRuntimeDiscount::PercentageOff(Percentage::from(0.5)),
+            rounding: RoundingPolicy::default(),
             bundle_size: 2,
             redemption_limit: None,
             monetary_limit_minor: None,
@@ -1536,6 +1991,59 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn item_price_outcomes_split_participation_into_full_and_discounted_prices() -> TestResult {
+        let item_group = item_group_from_prices(&[100, 200, 300]);
+
+        let promo = PositionalDiscountPromotion::new(
+            PromotionKey::default(),
+            Qualification::match_all(),
+            2,
+            SmallVec::from_vec(vec![1]),
+            SimpleDiscount::PercentageOff(Percentage::from(0.5)),
+            PromotionBudget::unlimited(),
+        );
+
+        let mut state = ILPState::with_presence_variables(&item_group)?;
+        let mut observer = NoopObserver;
+
+        let vars = promo.add_variables(&item_group, &mut state, &mut observer)?;
+
+        let outcomes = vars
+            .item_price_outcomes(&item_group, 1)?
+            .ok_or("expected outcomes")?;
+
+        let prices: Vec<i64> = outcomes.iter().map(|(_, price)| *price).collect();
+
+        assert_eq!(prices, vec![200, 100], "full-price member, then discounted");
+
+        let typed_vars = ((vars.as_ref() as &dyn Any).downcast_ref::<PositionalDiscountVars>())
+            .expect("Expected positional discount vars");
+
+        let mut values = Vec::new();
+
+        for &(idx, var) in &typed_vars.item_participation {
+            if idx == 1 {
+                values.push((var, 1.0));
+            }
+        }
+
+        let solution = MapSolution::with(&values);
+
+        let active: Vec<f64> = outcomes
+            .iter()
+            .map(|(expr, _)| solution.eval(expr))
+            .collect();
+
+        assert_eq!(
+            active,
+            vec![1.0, 0.0],
+            "undiscounted participation keeps the full price"
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn add_variables_reports_negative_discount_objective_term() -> TestResult {
         let item_group = item_group_from_prices(&[100]);
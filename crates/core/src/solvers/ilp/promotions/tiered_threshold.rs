@@ -7,24 +7,42 @@ use rusty_money::Money;
 use smallvec::SmallVec;
 
 use crate::{
-    discounts::percent_of_minor,
-    items::groups::ItemGroup,
+    context::EvaluationContext,
+    discounts::rounding::{RoundingPolicies, RoundingPolicy, RoundingScope},
+    items::{Item, groups::ItemGroup},
     products::ProductKey,
     promotions::{
         PromotionKey,
+        budget::{BudgetPoolKey, BudgetPools},
+        coupon::PromotionCoupon,
+        exclusion::PromotionExclusions,
+        explain::{MissReason, Shortfall, gate_reason},
+        funding::PromotionFunding,
         redemptions::PromotionRedemption,
         types::{ThresholdDiscount, TierThreshold, TieredThresholdPromotion},
     },
     solvers::{
         SolverError,
+        exhaustive::ExhaustivePromotion,
+        greedy::GreedyPromotion,
         ilp::{
-            BINARY_THRESHOLD, ILPObserver, i64_to_f64_exact,
-            promotions::{ILPPromotion, ILPPromotionVars, PromotionVars},
+            BINARY_THRESHOLD, ILPObserver, i64_to_f64_exact, item_units_variable,
+            promotions::{
+                ILPPromotion, ILPPromotionVars, ItemSignature, PriceOutcomes, PromotionVars,
+            },
+            solution_units,
             state::ILPState,
         },
     },
 };
 
+/// Units of an item line claimed by the active tier at one price:
+/// `(item_idx, original_minor, final_minor, quantity)`.
+type TierLinePrices = SmallVec<[(usize, i64, i64, u32); 10]>;
+
+/// Symmetry class key: `(product, price_minor, quantity, contributes, discountable)`.
+type SymmetryClassKey = (ProductKey, i64, u32, bool, bool);
+
 /// Per-qualifying-tier data captured during variable creation.
 #[derive(Debug)]
 struct QualifyingTier {
@@ -64,6 +82,11 @@ struct QualifyingTier {
     /// Bundle-level fixed amount off total discount.
     amount_off_total_minor: Option<i64>,
 
+    /// Saving taken by an amount-off-total tier, and whether it is the full amount
+    /// (otherwise the whole discounted spend). The latter is only tracked when a
+    /// monetary budget could hold the saving back.
+    amount_off_saving_vars: Option<(Variable, Option<Variable>)>,
+
     /// Bundle-level fixed total discount.
     fixed_total_minor: Option<i64>,
 
@@ -75,6 +98,12 @@ struct QualifyingTier {
 
     /// Cheapest item free discount.
     cheapest_free: bool,
+
+    /// Percentage taken off each discounted item, when that is the tier's discount.
+    percent_each_item: Option<Percentage>,
+
+    /// Rounding applied to percentage discounts.
+    rounding: RoundingPolicy,
 }
 
 impl QualifyingTier {
@@ -85,6 +114,11 @@ impl QualifyingTier {
     fn has_per_item_discount(&self) -> bool {
         self.has_per_item_discount
     }
+
+    /// Whether the percentage off each item is rounded over the claimed units together.
+    fn is_rounded_per_bundle(&self) -> bool {
+        self.percent_each_item.is_some() && self.rounding.scope() == RoundingScope::PerBundle
+    }
 }
 
 /// Solver variables for a tiered threshold promotion.
@@ -132,6 +166,27 @@ impl ILPPromotionVars for TieredThresholdPromotionVars {
         updated_expr
     }
 
+    fn add_item_discount_term(&self, expr: Expression, item_idx: usize) -> Expression {
+        let mut updated_expr = expr;
+
+        for qt in &self.qualifying_tiers {
+            // Cheapest-item variants only discount the targeted units.
+            let vars = if qt.has_per_item_discount() || qt.has_bundle_total_discount() {
+                &qt.discount_vars
+            } else {
+                &qt.target_vars
+            };
+
+            for &(idx, var) in vars {
+                if idx == item_idx {
+                    updated_expr += var;
+                }
+            }
+        }
+
+        updated_expr
+    }
+
     fn is_item_participating(&self, solution: &dyn Solution, item_idx: usize) -> bool {
         self.all_item_vars()
             .any(|&(idx, var)| idx == item_idx && solution.value(var) > BINARY_THRESHOLD)
@@ -193,6 +248,80 @@ impl ILPPromotionVars for TieredThresholdPromotionVars {
         Ok(updated_expr)
     }
 
+    fn item_price_outcomes(
+        &self,
+        item_group: &ItemGroup<'_>,
+        item_idx: usize,
+    ) -> Result<Option<PriceOutcomes>, SolverError> {
+        let mut outcomes = PriceOutcomes::new();
+
+        for qt in &self.qualifying_tiers {
+            let Some(&(_, item_var)) = qt.item_vars.iter().find(|(idx, _)| *idx == item_idx) else {
+                continue;
+            };
+
+            let full_minor = item_group.get_item(item_idx)?.price().to_minor_units();
+            let discountable = qt.discount_vars.iter().any(|(idx, _)| *idx == item_idx);
+
+            if !discountable {
+                // Contribution-only items participate at full price.
+                outcomes.push((Expression::from(item_var), full_minor));
+                continue;
+            }
+
+            if qt.has_bundle_total_discount() || qt.is_rounded_per_bundle() {
+                // Bundle totals (and bundle-rounded percentages) are allocated
+                // across all claimed items after solving.
+                return Ok(None);
+            }
+
+            if qt.has_per_item_discount() {
+                let discounted = qt.discounted_minor_by_item.get(&item_idx).copied().ok_or(
+                    SolverError::InvariantViolation {
+                        message: "missing discounted value for participating item",
+                    },
+                )?;
+
+                outcomes.push((Expression::from(item_var), discounted));
+                continue;
+            }
+
+            let Some(&(_, target_var)) = qt.target_vars.iter().find(|(idx, _)| *idx == item_idx)
+            else {
+                outcomes.push((Expression::from(item_var), full_minor));
+                continue;
+            };
+
+            let target_minor = if qt.cheapest_free {
+                0
+            } else if let Some(pct) = qt.percent_cheapest {
+                let savings = qt.rounding.discount_of(&pct, full_minor).unwrap_or(0);
+
+                (full_minor - savings).max(0)
+            } else if let Some(fixed) = qt.fixed_cheapest_minor {
+                fixed.max(0)
+            } else {
+                full_minor
+            };
+
+            outcomes.push((Expression::from(item_var) - target_var, full_minor));
+            outcomes.push((Expression::from(target_var), target_minor));
+        }
+
+        Ok(Some(outcomes))
+    }
+
+    fn redemption_count_expr(&self) -> Option<Expression> {
+        Some(self.tier_sum())
+    }
+
+    fn discount_value_expr(
+        &self,
+        item_group: &ItemGroup<'_>,
+    ) -> Result<Option<Expression>, SolverError> {
+        self.discount_expr(item_group).map(Some)
+    }
+
     fn add_constraints(
         &self,
         _promotion_key: PromotionKey,
@@ -231,9 +360,13 @@ impl ILPPromotionVars for TieredThresholdPromotionVars {
         item_group: &ItemGroup<'b>,
         next_redemption_idx: &mut usize,
     ) -> Result<SmallVec<[PromotionRedemption<'b>; 10]>, SolverError> {
-        let discounts = self.calculate_item_discounts(solution, item_group)?;
+        let Some(active_tier) = self.active_tier(solution) else {
+            return Ok(SmallVec::new());
+        };
+
+        let mut line_prices = calculate_line_prices_for_tier(active_tier, solution, item_group)?;
 
-        if discounts.is_empty() {
+        if line_prices.is_empty() {
             return Ok(SmallVec::new());
         }
 
@@ -244,22 +377,18 @@ impl ILPPromotionVars for TieredThresholdPromotionVars {
 
         let mut redemptions = SmallVec::new();
 
-        let mut sorted_discounts: SmallVec<[(usize, (i64, i64)); 10]> = discounts
-            .iter()
-            .map(|(&item_idx, &(original_minor, final_minor))| {
-                (item_idx, (original_minor, final_minor))
-            })
-            .collect();
-
-        sorted_discounts.sort_by_key(|(item_idx, _)| *item_idx);
+        line_prices.sort_by_key(|&(item_idx, _, _, _)| item_idx);
 
-        for (item_idx, (original_minor, final_minor)) in sorted_discounts {
+        for (item_idx, original_minor, final_minor, quantity) in line_prices {
             redemptions.push(PromotionRedemption {
                 promotion_key,
                 item_idx,
                 redemption_idx,
                 original_price: Money::from_minor(original_minor, currency),
                 final_price: Money::from_minor(final_minor, currency),
+                quantity,
+                funding: SmallVec::new(),
+                floor_limited: false,
             });
         }
 
@@ -297,14 +426,15 @@ impl TieredThresholdPromotionVars {
         state: &mut ILPState,
         observer: &mut dyn ILPObserver,
     ) -> Result<(), SolverError> {
-        self.add_tier_item_link_constraints(qt, state, observer);
+        self.add_tier_item_link_constraints(qt, item_group, state, observer)?;
         self.add_lower_threshold_constraints(qt, item_group, state, observer)?;
         self.add_upper_threshold_constraints(qt, item_group, state, observer)?;
         self.add_upper_cap_symmetry_break_constraints(qt, item_group, state, observer)?;
         self.add_tier_activation_constraint(qt, state, observer);
+        self.add_amount_off_saving_constraints(qt, item_group, state, observer)?;
 
         if !qt.target_vars.is_empty() {
-            add_cheapest_constraints(qt, self.promotion_key, state, observer);
+            add_cheapest_constraints(qt, self.promotion_key, item_group, state, observer)?;
         }
 
         Ok(())
@@ -321,6 +451,10 @@ impl TieredThresholdPromotionVars {
             return Ok(());
         }
 
+        if !state.allows_symmetry_breaking() {
+            return Ok(());
+        }
+
         // If multiple identical copies of a product exist, selecting different
         // permutations can create equivalent branches. Enforce a prefix order
         // within each equivalent class to break this symmetry.
@@ -328,12 +462,13 @@ impl TieredThresholdPromotionVars {
         // Class key fields:
         // - ProductKey: same underlying SKU
         // - price_minor: same price point in this basket
+        // - quantity: same number of units on the line
         // - contributes: same role in threshold qualification
         // - discountable: same role in discount targeting
         //
-        // If two variables match on all four, swapping them does not change any
+        // If two variables match on all five, swapping them does not change any
         // business outcome, only solver branch shape.
-        let mut classes: FxHashMap<(ProductKey, i64, bool, bool), SmallVec<[Variable; 10]>> =
+        let mut classes: FxHashMap<SymmetryClassKey, SmallVec<[Variable; 10]>> =
             FxHashMap::default();
 
         for &(item_idx, item_var) in &qt.item_vars {
@@ -344,7 +479,13 @@ impl TieredThresholdPromotionVars {
             let discountable = qt.discount_vars.iter().any(|(idx, _)| *idx == item_idx);
 
             classes
-                .entry((item.product(), price_minor, contributes, discountable))
+                .entry((
+                    item.product(),
+                    price_minor,
+                    item.quantity(),
+                    contributes,
+                    discountable,
+                ))
                 .or_default()
                 .push(item_var);
         }
@@ -380,12 +521,19 @@ impl TieredThresholdPromotionVars {
     fn add_tier_item_link_constraints(
         &self,
         qt: &QualifyingTier,
+        item_group: &ItemGroup<'_>,
         state: &mut ILPState,
         observer: &mut dyn ILPObserver,
-    ) {
-        // Link items to their tier: d_{t,i} <= tier_t
-        for &(_item_idx, item_var) in &qt.item_vars {
-            let link_expr = Expression::from(item_var) - Expression::from(qt.tier_var);
+    ) -> Result<(), SolverError> {
+        // Link items to their tier: d_{t,i} <= q_i * tier_t
+        for &(item_idx, item_var) in &qt.item_vars {
+            let quantity = item_group.get_item(item_idx)?.quantity();
+
+            let link_expr = if quantity <= 1 {
+                Expression::from(item_var) - Expression::from(qt.tier_var)
+            } else {
+                Expression::from(item_var) - Expression::from(qt.tier_var) * f64::from(quantity)
+            };
 
             observer.on_promotion_constraint(
                 self.promotion_key,
@@ -397,6 +545,8 @@ impl TieredThresholdPromotionVars {
 
             state.add_leq_constraint(link_expr, 0.0);
         }
+
+        Ok(())
     }
 
     fn add_lower_threshold_constraints(
@@ -657,6 +807,195 @@ impl TieredThresholdPromotionVars {
         state.add_leq_constraint(expr, 0.0);
     }
 
+    fn add_amount_off_saving_constraints(
+        &self,
+        qt: &QualifyingTier,
+        item_group: &ItemGroup<'_>,
+        state: &mut ILPState,
+        observer: &mut dyn ILPObserver,
+    ) -> Result<(), SolverError> {
+        let (Some(amount_off), Some((saving_var, capped_var))) =
+            (qt.amount_off_total_minor, qt.amount_off_saving_vars)
+        else {
+            return Ok(());
+        };
+
+        // Only the active tier saves: s_t <= amount * tier_t
+        let amount_off = amount_off.max(0);
+        let coeff = i64_to_f64_exact(amount_off)
+            .ok_or(SolverError::MinorUnitsNotRepresentable(amount_off))?;
+        let active_expr = Expression::from(saving_var) - Expression::from(qt.tier_var) * coeff;
+
+        observer.on_promotion_constraint(
+            self.promotion_key,
+            "amount off saving (active tier)",
+            &active_expr,
+            "<=",
+            0.0,
+        );
+
+        state.add_leq_constraint(active_expr, 0.0);
+
+        // And never more than the discounted items cost: s_t <= sum(price_i * d_{t,i})
+        let spend = weighted_price_sum_expr(item_group, &qt.discount_vars)?;
+        let spend_expr = Expression::from(saving_var) - spend.clone();
+
+        observer.on_promotion_constraint(
+            self.promotion_key,
+            "amount off saving (discounted spend)",
+            &spend_expr,
+            "<=",
+            0.0,
+        );
+
+        state.add_leq_constraint(spend_expr, 0.0);
+
+        let Some(capped_var) = capped_var else {
+            return self.add_amount_off_full_saving_constraint(
+                qt, item_group, saving_var, amount_off, state, observer,
+            );
+        };
+
+        // But exactly the lesser of the two, so budgets see the real saving:
+        // s_t >= amount * c_t and s_t >= spend_t - headroom * c_t
+        let capped_expr = Expression::from(saving_var) - Expression::from(capped_var) * coeff;
+
+        observer.on_promotion_constraint(
+            self.promotion_key,
+            "amount off saving (capped)",
+            &capped_expr,
+            ">=",
+            0.0,
+        );
+
+        state.add_geq_constraint(capped_expr, 0.0);
+
+        let mut max_spend = 0_i64;
+
+        for &(item_idx, _var) in &qt.discount_vars {
+            let item = item_group.get_item(item_idx)?;
+
+            max_spend = max_spend.saturating_add(
+                item.price()
+                    .to_minor_units()
+                    .max(0)
+                    .saturating_mul(i64::from(item.quantity())),
+            );
+        }
+
+        // Past the amount off, the spend only needs to be relaxed by what is left over.
+        let headroom = max_spend.saturating_sub(amount_off).max(0);
+        let headroom_coeff =
+            i64_to_f64_exact(headroom).ok_or(SolverError::MinorUnitsNotRepresentable(headroom))?;
+        let uncapped_expr =
+            Expression::from(saving_var) - spend + Expression::from(capped_var) * headroom_coeff;
+
+        observer.on_promotion_constraint(
+            self.promotion_key,
+            "amount off saving (spend)",
+            &uncapped_expr,
+            ">=",
+            0.0,
+        );
+
+        state.add_geq_constraint(uncapped_expr, 0.0);
+
+        Ok(())
+    }
+
+    fn add_amount_off_full_saving_constraint(
+        &self,
+        qt: &QualifyingTier,
+        item_group: &ItemGroup<'_>,
+        saving_var: Variable,
+        amount_off: i64,
+        state: &mut ILPState,
+        observer: &mut dyn ILPObserver,
+    ) -> Result<(), SolverError> {
+        // Otherwise the objective already takes the most it can save.
+        for &(item_idx, _var) in &qt.discount_vars {
+            if item_group.get_item(item_idx)?.price().to_minor_units() < amount_off {
+                return Ok(());
+            }
+        }
+
+        // Every discountable unit covers the amount, so the active tier saves all of it:
+        // s_t >= amount * tier_t
+        let coeff = i64_to_f64_exact(amount_off)
+            .ok_or(SolverError::MinorUnitsNotRepresentable(amount_off))?;
+        let full_expr = Expression::from(saving_var) - Expression::from(qt.tier_var) * coeff;
+
+        observer.on_promotion_constraint(
+            self.promotion_key,
+            "amount off saving (full)",
+            &full_expr,
+            ">=",
+            0.0,
+        );
+
+        state.add_geq_constraint(full_expr, 0.0);
+
+        Ok(())
+    }
+
+    /// Number of active tiers; each active tier is one redemption.
+    fn tier_sum(&self) -> Expression {
+        self.qualifying_tiers.iter().map(|qt| qt.tier_var).sum()
+    }
+
+    /// Total discount value: `sum((full_price - discounted_price) * var)`
+    fn discount_expr(&self, item_group: &ItemGroup<'_>) -> Result<Expression, SolverError> {
+        let mut discount_expr = Expression::default();
+
+        for qt in &self.qualifying_tiers {
+            if qt.percent_cheapest.is_some()
+                || qt.fixed_cheapest_minor.is_some()
+                || qt.cheapest_free
+            {
+                // Cheapest-item modes are exact with target vars: only targets consume budget.
+                for &(item_idx, target_var) in &qt.target_vars {
+                    let item = item_group.get_item(item_idx).map_err(SolverError::from)?;
+                    let full_minor = item.price().to_minor_units();
+                    let discounted_minor =
+                        estimate_target_discounted_minor_for_budget(qt, full_minor)?;
+
+                    let discount_amount = full_minor.saturating_sub(discounted_minor);
+                    let coeff = i64_to_f64_exact(discount_amount)
+                        .ok_or(SolverError::MinorUnitsNotRepresentable(discount_amount))?;
+
+                    discount_expr += target_var * coeff;
+                }
+            } else if let Some((saving_var, _capped_var)) = qt.amount_off_saving_vars {
+                // The active tier takes the amount off its discounted items' total.
+                discount_expr += saving_var;
+            } else if let Some(fixed_total) = qt.fixed_total_minor {
+                // Discounted items give up their full price and the active
+                // tier charges the fixed total instead.
+                discount_expr += weighted_price_sum_expr(item_group, &qt.discount_vars)?;
+
+                let coeff = i64_to_f64_exact(fixed_total)
+                    .ok_or(SolverError::MinorUnitsNotRepresentable(fixed_total))?;
+
+                discount_expr -= qt.tier_var * coeff;
+            } else {
+                for &(item_idx, var) in &qt.item_vars {
+                    let item = item_group.get_item(item_idx).map_err(SolverError::from)?;
+                    let full_minor = item.price().to_minor_units();
+                    let discounted_minor =
+                        estimate_discounted_minor_for_budget(qt, item_idx, var, full_minor)?;
+
+                    let discount_amount = full_minor.saturating_sub(discounted_minor);
+                    let coeff = i64_to_f64_exact(discount_amount)
+                        .ok_or(SolverError::MinorUnitsNotRepresentable(discount_amount))?;
+
+                    discount_expr += var * coeff;
+                }
+            }
+        }
+
+        Ok(discount_expr)
+    }
+
     /// Add budget constraints to the ILP state.
     fn add_budget_constraints(
         &self,
@@ -667,7 +1006,7 @@ impl TieredThresholdPromotionVars {
     ) -> Result<(), SolverError> {
         // Redemption count limit: sum(active tiers) <= limit
         if let Some(redemption_limit) = self.redemption_limit {
-            let tier_sum: Expression = self.qualifying_tiers.iter().map(|qt| qt.tier_var).sum();
+            let tier_sum = self.tier_sum();
 
             let limit_f64 = i64_to_f64_exact(i64::from(redemption_limit)).ok_or(
                 SolverError::MinorUnitsNotRepresentable(i64::from(redemption_limit)),
@@ -686,41 +1025,7 @@ impl TieredThresholdPromotionVars {
 
         // Monetary limit: sum((full_price - discounted_price) * var) <= limit
         if let Some(limit_minor) = self.monetary_limit_minor {
-            let mut discount_expr = Expression::default();
-
-            for qt in &self.qualifying_tiers {
-                if qt.percent_cheapest.is_some()
-                    || qt.fixed_cheapest_minor.is_some()
-                    || qt.cheapest_free
-                {
-                    // Cheapest-item modes are exact with target vars: only targets consume budget.
-                    for &(item_idx, target_var) in &qt.target_vars {
-                        let item = item_group.get_item(item_idx).map_err(SolverError::from)?;
-                        let full_minor = item.price().to_minor_units();
-                        let discounted_minor =
-                            estimate_target_discounted_minor_for_budget(qt, full_minor)?;
-
-                        let discount_amount = full_minor.saturating_sub(discounted_minor);
-                        let coeff = i64_to_f64_exact(discount_amount)
-                            .ok_or(SolverError::MinorUnitsNotRepresentable(discount_amount))?;
-
-                        discount_expr += target_var * coeff;
-                    }
-                } else {
-                    for &(item_idx, var) in &qt.item_vars {
-                        let item = item_group.get_item(item_idx).map_err(SolverError::from)?;
-                        let full_minor = item.price().to_minor_units();
-                        let discounted_minor =
-                            estimate_discounted_minor_for_budget(qt, item_idx, var, full_minor)?;
-
-                        let discount_amount = full_minor.saturating_sub(discounted_minor);
-                        let coeff = i64_to_f64_exact(discount_amount)
-                            .ok_or(SolverError::MinorUnitsNotRepresentable(discount_amount))?;
-
-                        discount_expr += var * coeff;
-                    }
-                }
-            }
+            let discount_expr = self.discount_expr(item_group)? - state.floor_excess(promotion_key);
 
             let limit_f64 = i64_to_f64_exact(limit_minor)
                 .ok_or(SolverError::MinorUnitsNotRepresentable(limit_minor))?;
@@ -880,7 +1185,7 @@ fn estimate_discounted_minor_for_budget(
         return Ok(fixed.max(0));
     }
 
-    // Conservative for other bundle-level modes: assume item could be free.
+    // Bundle totals are budgeted per tier rather than per item.
     Ok(0)
 }
 
@@ -893,7 +1198,10 @@ fn estimate_target_discounted_minor_for_budget(
     }
 
     if let Some(pct) = tier.percent_cheapest {
-        let discount_amount = percent_of_minor(&pct, full_minor).map_err(SolverError::Discount)?;
+        let discount_amount = tier
+            .rounding
+            .discount_of(&pct, full_minor)
+            .map_err(SolverError::Discount)?;
 
         return Ok(full_minor.saturating_sub(discount_amount));
     }
@@ -908,12 +1216,17 @@ fn estimate_target_discounted_minor_for_budget(
 }
 
 /// Add cheapest-item constraints for a qualifying tier.
+///
+/// # Errors
+///
+/// Returns [`SolverError`] if a targeted item is missing from the item group.
 fn add_cheapest_constraints(
     qt: &QualifyingTier,
     promotion_key: PromotionKey,
+    item_group: &ItemGroup<'_>,
     state: &mut ILPState,
     observer: &mut dyn ILPObserver,
-) {
+) -> Result<(), SolverError> {
     // target_i <= d_{t,i} (can only target a claimed item)
     for &(item_idx, target_var) in &qt.target_vars {
         if let Some(&(_, item_var)) = qt.discount_vars.iter().find(|(idx, _)| *idx == item_idx) {
@@ -938,93 +1251,138 @@ fn add_cheapest_constraints(
     observer.on_promotion_constraint(promotion_key, "target count", &expr, "<=", 0.0);
     state.add_leq_constraint(expr, 0.0);
 
-    // Cheapest ordering: target_k + d_{k-1} <= 1
-    // (target_vars are sorted by price ascending)
-    for k in 1..qt.target_vars.len() {
-        let Some(&(prev_idx, _)) = qt.target_vars.get(k - 1) else {
-            continue;
-        };
-
-        let Some(&(_, curr_target)) = qt.target_vars.get(k) else {
-            continue;
-        };
+    // Cheapest ordering: Q_k * target_k + sum(d_j for j < k) <= Q_k, where Q_k is
+    // the unit count of every cheaper line (target_vars are sorted by price
+    // ascending, so no cheaper unit may be claimed when a dearer one is targeted)
+    let mut cheaper_claimed = Expression::default();
+    let mut cheaper_units = 0_u32;
 
-        let Some(&(_, prev_d)) = qt.discount_vars.iter().find(|(idx, _)| *idx == prev_idx) else {
-            continue;
-        };
+    for &(item_idx, target_var) in &qt.target_vars {
+        if cheaper_units > 0 {
+            let units = f64::from(cheaper_units);
+            let expr = Expression::from(target_var) * units + cheaper_claimed.clone();
 
-        let expr = Expression::from(curr_target) + Expression::from(prev_d);
+            observer.on_promotion_constraint(
+                promotion_key,
+                "cheapest ordering",
+                &expr,
+                "<=",
+                units,
+            );
 
-        observer.on_promotion_constraint(promotion_key, "cheapest ordering", &expr, "<=", 1.0);
+            state.add_leq_constraint(expr, units);
+        }
 
-        state.add_leq_constraint(expr, 1.0);
+        if let Some(&(_, item_var)) = qt.discount_vars.iter().find(|(idx, _)| *idx == item_idx) {
+            cheaper_claimed += item_var;
+            cheaper_units = cheaper_units.saturating_add(item_group.get_item(item_idx)?.quantity());
+        }
     }
+
+    Ok(())
 }
 
 /// Compute final per-item prices for the active tier.
+///
+/// Quantity lines priced at more than one unit price report the discounted price.
 fn calculate_discounts_for_tier(
     qt: &QualifyingTier,
     solution: &dyn Solution,
     item_group: &ItemGroup<'_>,
 ) -> Result<FxHashMap<usize, (i64, i64)>, SolverError> {
-    let mut discounts = if qt.has_per_item_discount() {
-        calculate_per_item_discounts(qt, solution, item_group)?
-    } else if let Some(amount) = qt.amount_off_total_minor {
-        calculate_total_discounts(&qt.discount_vars, solution, item_group, &|total| {
-            total.saturating_sub(amount)
-        })?
-    } else if let Some(fixed) = qt.fixed_total_minor {
-        calculate_total_discounts(&qt.discount_vars, solution, item_group, &|_total| {
-            fixed.max(0)
-        })?
-    } else if qt.cheapest_free {
-        calculate_cheapest_discounts(
-            &qt.discount_vars,
-            &qt.target_vars,
-            solution,
-            item_group,
-            &|_price| 0,
-        )?
-    } else if let Some(pct) = qt.percent_cheapest {
-        calculate_cheapest_discounts(
-            &qt.discount_vars,
-            &qt.target_vars,
-            solution,
-            item_group,
-            &|price| {
-                let savings = percent_of_minor(&pct, price).unwrap_or(0);
+    let mut discounts = FxHashMap::default();
 
-                (price - savings).max(0)
-            },
-        )?
-    } else if let Some(fixed) = qt.fixed_cheapest_minor {
-        calculate_cheapest_discounts(
-            &qt.discount_vars,
-            &qt.target_vars,
-            solution,
-            item_group,
-            &|_price| fixed.max(0),
-        )?
-    } else {
-        return Err(SolverError::InvariantViolation {
-            message: "qualifying tier has no discount mode configured",
-        });
-    };
+    for (item_idx, original_minor, final_minor, _quantity) in
+        calculate_line_prices_for_tier(qt, solution, item_group)?
+    {
+        let is_discounted = final_minor != original_minor;
+
+        discounts
+            .entry(item_idx)
+            .and_modify(|entry: &mut (i64, i64)| {
+                if is_discounted {
+                    *entry = (original_minor, final_minor);
+                }
+            })
+            .or_insert((original_minor, final_minor));
+    }
+
+    Ok(discounts)
+}
+
+/// Compute the final unit prices (and unit counts) claimed by the active tier.
+fn calculate_line_prices_for_tier(
+    qt: &QualifyingTier,
+    solution: &dyn Solution,
+    item_group: &ItemGroup<'_>,
+) -> Result<TierLinePrices, SolverError> {
+    let mut line_prices =
+        if let Some(pct) = qt.percent_each_item.filter(|_| qt.is_rounded_per_bundle()) {
+            calculate_bundle_rounded_discounts(qt, pct, solution, item_group)?
+        } else if qt.has_per_item_discount() {
+            calculate_per_item_discounts(qt, solution, item_group)?
+        } else if let Some(amount) = qt.amount_off_total_minor {
+            calculate_total_discounts(&qt.discount_vars, solution, item_group, &|total| {
+                total.saturating_sub(amount).max(0)
+            })?
+        } else if let Some(fixed) = qt.fixed_total_minor {
+            calculate_total_discounts(&qt.discount_vars, solution, item_group, &|_total| {
+                fixed.max(0)
+            })?
+        } else if qt.cheapest_free {
+            calculate_cheapest_discounts(
+                &qt.discount_vars,
+                &qt.target_vars,
+                solution,
+                item_group,
+                &|_price| 0,
+            )?
+        } else if let Some(pct) = qt.percent_cheapest {
+            calculate_cheapest_discounts(
+                &qt.discount_vars,
+                &qt.target_vars,
+                solution,
+                item_group,
+                &|price| {
+                    let savings = qt.rounding.discount_of(&pct, price).unwrap_or(0);
+
+                    (price - savings).max(0)
+                },
+            )?
+        } else if let Some(fixed) = qt.fixed_cheapest_minor {
+            calculate_cheapest_discounts(
+                &qt.discount_vars,
+                &qt.target_vars,
+                solution,
+                item_group,
+                &|_price| fixed.max(0),
+            )?
+        } else {
+            return Err(SolverError::InvariantViolation {
+                message: "qualifying tier has no discount mode configured",
+            });
+        };
 
     // Participation is exclusive across promotions even for non-discounted
     // contribution items; include them with full prices.
     for &(item_idx, item_var) in &qt.item_vars {
-        if solution.value(item_var) <= BINARY_THRESHOLD || discounts.contains_key(&item_idx) {
+        let units = solution_units(solution, item_var);
+
+        if units == 0
+            || line_prices
+                .iter()
+                .any(|&(priced_idx, _, _, _)| priced_idx == item_idx)
+        {
             continue;
         }
 
         let item = item_group.get_item(item_idx).map_err(SolverError::from)?;
         let full_minor = item.price().to_minor_units();
 
-        discounts.insert(item_idx, (full_minor, full_minor));
+        line_prices.push((item_idx, full_minor, full_minor, units));
     }
 
-    Ok(discounts)
+    Ok(line_prices)
 }
 
 /// Per-item discount: use pre-computed discounted prices.
@@ -1032,11 +1390,13 @@ fn calculate_per_item_discounts(
     qt: &QualifyingTier,
     solution: &dyn Solution,
     item_group: &ItemGroup<'_>,
-) -> Result<FxHashMap<usize, (i64, i64)>, SolverError> {
-    let mut discounts = FxHashMap::default();
+) -> Result<TierLinePrices, SolverError> {
+    let mut line_prices = TierLinePrices::new();
 
     for &(item_idx, item_var) in &qt.discount_vars {
-        if solution.value(item_var) <= BINARY_THRESHOLD {
+        let units = solution_units(solution, item_var);
+
+        if units == 0 {
             continue;
         }
 
@@ -1048,68 +1408,140 @@ fn calculate_per_item_discounts(
             },
         )?;
 
-        discounts.insert(item_idx, (item.price().to_minor_units(), discounted));
+        line_prices.push((item_idx, item.price().to_minor_units(), discounted, units));
     }
 
-    Ok(discounts)
+    Ok(line_prices)
+}
+
+/// Percentage off each item, rounded once over every claimed discount unit.
+///
+/// Consecutive units of a line that end on the same price share an entry.
+fn calculate_bundle_rounded_discounts(
+    qt: &QualifyingTier,
+    pct: Percentage,
+    solution: &dyn Solution,
+    item_group: &ItemGroup<'_>,
+) -> Result<TierLinePrices, SolverError> {
+    let mut units_by_item: SmallVec<[(usize, i64, u32); 10]> = SmallVec::new();
+    let mut unit_prices: SmallVec<[i64; 10]> = SmallVec::new();
+
+    for &(item_idx, item_var) in &qt.discount_vars {
+        let units = solution_units(solution, item_var);
+
+        if units == 0 {
+            continue;
+        }
+
+        let full_minor = item_group
+            .get_item(item_idx)
+            .map_err(SolverError::from)?
+            .price()
+            .to_minor_units();
+
+        units_by_item.push((item_idx, full_minor, units));
+        unit_prices.extend((0..units).map(|_| full_minor));
+    }
+
+    let discounted = SmallVec::<[bool; 10]>::from_elem(true, unit_prices.len());
+
+    qt.rounding
+        .discount_units(&pct, &mut unit_prices, &discounted)
+        .map_err(SolverError::Discount)?;
+
+    let mut line_prices = TierLinePrices::new();
+    let mut finals = unit_prices.into_iter();
+
+    for (item_idx, full_minor, units) in units_by_item {
+        for final_minor in finals.by_ref().take(units as usize) {
+            match line_prices.last_mut() {
+                Some((last_idx, _, last_final, count))
+                    if *last_idx == item_idx && *last_final == final_minor =>
+                {
+                    *count += 1;
+                }
+                _ => line_prices.push((item_idx, full_minor, final_minor, 1)),
+            }
+        }
+    }
+
+    Ok(line_prices)
 }
 
-/// Bundle-total discount: distribute the new total proportionally.
+/// Bundle-total discount: distribute the new total proportionally across claimed units.
+///
+/// Any rounding remainder lands on the last claimed unit, so a quantity line may
+/// be split into two prices.
 fn calculate_total_discounts(
     discount_vars: &SmallVec<[(usize, Variable); 10]>,
     solution: &dyn Solution,
     item_group: &ItemGroup<'_>,
     new_total: &dyn Fn(i64) -> i64,
-) -> Result<FxHashMap<usize, (i64, i64)>, SolverError> {
-    let mut discounts = FxHashMap::default();
+) -> Result<TierLinePrices, SolverError> {
+    let mut line_prices = TierLinePrices::new();
 
-    let mut claimed: SmallVec<[(usize, i64); 10]> = SmallVec::new();
+    let mut claimed: SmallVec<[(usize, i64, u32); 10]> = SmallVec::new();
 
     for &(item_idx, item_var) in discount_vars {
-        if solution.value(item_var) <= BINARY_THRESHOLD {
+        let units = solution_units(solution, item_var);
+
+        if units == 0 {
             continue;
         }
 
         let item = item_group.get_item(item_idx).map_err(SolverError::from)?;
 
-        claimed.push((item_idx, item.price().to_minor_units()));
+        claimed.push((item_idx, item.price().to_minor_units(), units));
     }
 
     if claimed.is_empty() {
-        return Ok(discounts);
+        return Ok(line_prices);
     }
 
-    let original_total: i64 = claimed.iter().map(|(_, p)| p).sum();
+    let original_total: i64 = claimed
+        .iter()
+        .map(|&(_, price, units)| price * i64::from(units))
+        .sum();
     let target_total = new_total(original_total);
 
     let mut remaining = target_total;
 
-    for (i, &(item_idx, full_minor)) in claimed.iter().enumerate() {
-        let final_minor = if i == claimed.len() - 1 {
-            remaining
-        } else if original_total == 0 {
+    for (i, &(item_idx, full_minor, units)) in claimed.iter().enumerate() {
+        let unit_minor = if original_total == 0 {
             0
         } else {
             proportional_alloc(target_total, full_minor, original_total)
         };
 
-        remaining -= final_minor;
+        // All but the very last claimed unit take the proportional price.
+        let leading_units = if i == claimed.len() - 1 {
+            units - 1
+        } else {
+            units
+        };
 
-        discounts.insert(item_idx, (full_minor, final_minor));
+        if leading_units > 0 {
+            line_prices.push((item_idx, full_minor, unit_minor, leading_units));
+            remaining -= unit_minor * i64::from(leading_units);
+        }
+
+        if leading_units < units {
+            line_prices.push((item_idx, full_minor, remaining, 1));
+        }
     }
 
-    Ok(discounts)
+    Ok(line_prices)
 }
 
-/// Cheapest-item discount: target item gets the discount, others at full price.
+/// Cheapest-item discount: one targeted unit gets the discount, others stay at full price.
 fn calculate_cheapest_discounts(
     discount_vars: &SmallVec<[(usize, Variable); 10]>,
     target_vars: &SmallVec<[(usize, Variable); 10]>,
     solution: &dyn Solution,
     item_group: &ItemGroup<'_>,
     target_price: &dyn Fn(i64) -> i64,
-) -> Result<FxHashMap<usize, (i64, i64)>, SolverError> {
-    let mut discounts = FxHashMap::default();
+) -> Result<TierLinePrices, SolverError> {
+    let mut line_prices = TierLinePrices::new();
 
     let target_idx = target_vars
         .iter()
@@ -1117,23 +1549,27 @@ fn calculate_cheapest_discounts(
         .map(|(idx, _)| *idx);
 
     for &(item_idx, item_var) in discount_vars {
-        if solution.value(item_var) <= BINARY_THRESHOLD {
+        let units = solution_units(solution, item_var);
+
+        if units == 0 {
             continue;
         }
 
         let item = item_group.get_item(item_idx).map_err(SolverError::from)?;
         let full = item.price().to_minor_units();
 
-        let final_minor = if Some(item_idx) == target_idx {
-            target_price(full)
-        } else {
-            full
-        };
+        if Some(item_idx) == target_idx {
+            if units > 1 {
+                line_prices.push((item_idx, full, full, units - 1));
+            }
 
-        discounts.insert(item_idx, (full, final_minor));
+            line_prices.push((item_idx, full, target_price(full), 1));
+        } else {
+            line_prices.push((item_idx, full, full, units));
+        }
     }
 
-    Ok(discounts)
+    Ok(line_prices)
 }
 
 /// Proportionally allocate a total across items by their share of the denominator.
@@ -1152,11 +1588,13 @@ fn proportional_alloc(total: i64, part: i64, denom: i64) -> i64 {
 }
 
 /// Create target variables for cheapest-item discount types.
+#[expect(clippy::too_many_arguments, reason = "mirrors add_variables' state")]
 fn build_target_vars(
     eligible: &SmallVec<[(usize, i64); 10]>,
     percent_cheapest: Option<Percentage>,
     fixed_cheapest_minor: Option<i64>,
     cheapest_free: bool,
+    rounding: RoundingPolicy,
     promotion_key: PromotionKey,
     state: &mut ILPState,
     observer: &mut dyn ILPObserver,
@@ -1175,7 +1613,9 @@ fn build_target_vars(
         let savings = if cheapest_free {
             price
         } else if let Some(pct) = percent_cheapest {
-            percent_of_minor(&pct, price).map_err(SolverError::Discount)?
+            rounding
+                .discount_of(&pct, price)
+                .map_err(SolverError::Discount)?
         } else if let Some(fixed) = fixed_cheapest_minor {
             price.saturating_sub(fixed.max(0))
         } else {
@@ -1203,16 +1643,109 @@ impl ILPPromotion for TieredThresholdPromotion<'_> {
         TieredThresholdPromotion::key(self)
     }
 
+    fn coupon(&self) -> Option<&PromotionCoupon> {
+        TieredThresholdPromotion::coupon(self)
+    }
+
+    fn budget_pools(&self) -> &[BudgetPoolKey] {
+        TieredThresholdPromotion::budget_pools(self)
+    }
+
+    fn priority(&self) -> i32 {
+        TieredThresholdPromotion::priority(self)
+    }
+
+    fn funding(&self) -> &PromotionFunding {
+        TieredThresholdPromotion::funding(self)
+    }
+
+    fn is_mandatory(&self) -> bool {
+        TieredThresholdPromotion::is_mandatory(self)
+    }
+
+    fn rounding(&self) -> Option<&RoundingPolicies> {
+        TieredThresholdPromotion::rounding(self)
+    }
+
+    fn exclusions(&self) -> &PromotionExclusions {
+        TieredThresholdPromotion::exclusions(self)
+    }
+
+    fn near_miss<'b>(
+        &self,
+        item_group: &ItemGroup<'b>,
+        pools: &BudgetPools<'_>,
+    ) -> Option<MissReason<'b>> {
+        gate_reason(
+            self.schedule(),
+            self.coupon(),
+            self.budget(),
+            self.redemption_limit(),
+            self.budget_pools(),
+            pools,
+            item_group.context(),
+        )
+        .or_else(|| self.shortfall(item_group).map(MissReason::Shortfall))
+    }
+
+    fn item_signature(
+        &self,
+        item: &Item<'_>,
+        context: &EvaluationContext,
+    ) -> Option<ItemSignature> {
+        Some(
+            self.tiers()
+                .iter()
+                .flat_map(|tier| {
+                    [
+                        tier.contribution_qualification()
+                            .matches_in_context(item.tags(), context),
+                        tier.discount_qualification()
+                            .matches_in_context(item.tags(), context),
+                    ]
+                })
+                .collect(),
+        )
+    }
+
+    fn upsell<'b>(&self, item_group: &ItemGroup<'b>) -> Option<Shortfall<'b>> {
+        if self
+            .near_miss(item_group, &BudgetPools::default())
+            .is_some()
+        {
+            return None;
+        }
+
+        self.next_tier_shortfall(item_group)
+    }
+
+    fn greedy(&self) -> Option<&dyn GreedyPromotion> {
+        Some(self)
+    }
+
+    fn exhaustive(&self) -> Option<&dyn ExhaustivePromotion> {
+        Some(self)
+    }
+
     fn is_applicable(&self, item_group: &ItemGroup<'_>) -> bool {
-        if item_group.is_empty() || self.tiers().is_empty() {
+        let context = item_group.context();
+
+        if item_group.is_empty()
+            || self.tiers().is_empty()
+            || !self.schedule().is_active_in(context)
+            || !self
+                .coupon()
+                .is_none_or(|coupon| coupon.is_presented_in(context))
+        {
             return false;
         }
 
         // At least one tier must have items matching its discount qualification.
         self.tiers().iter().any(|tier| {
-            item_group
-                .iter()
-                .any(|item| tier.discount_qualification().matches(item.tags()))
+            item_group.iter().any(|item| {
+                tier.discount_qualification()
+                    .matches_in_context(item.tags(), item_group.context())
+            })
         })
     }
 
@@ -1229,6 +1762,12 @@ impl ILPPromotion for TieredThresholdPromotion<'_> {
         let promotion_key = self.key();
         let mut qualifying_tiers = SmallVec::new();
 
+        // Amount-off savings only need pinning down when a monetary budget could hold them back.
+        let budget_limited =
+            self.budget().monetary_limit.is_some() || !self.budget_pools().is_empty();
+
+        let rounding = state.rounding_for(self.rounding(), item_group.currency());
+
         for (tier_idx, tier) in self.tiers().iter().enumerate() {
             let lower_monetary_threshold_minor = tier
                 .lower_threshold()
@@ -1251,16 +1790,22 @@ impl ILPPromotion for TieredThresholdPromotion<'_> {
 
             let contribution_total: i64 = item_group
                 .iter()
-                .filter(|item| contribution_qualification.matches(item.tags()))
-                .map(|item| item.price().to_minor_units())
+                .filter(|item| {
+                    contribution_qualification.matches_in_context(item.tags(), item_group.context())
+                })
+                .map(|item| {
+                    item.price()
+                        .to_minor_units()
+                        .saturating_mul(i64::from(item.quantity()))
+                })
                 .sum();
 
-            let contribution_count = item_group
+            let contribution_count_u32 = item_group
                 .iter()
-                .filter(|item| contribution_qualification.matches(item.tags()))
-                .count();
-
-            let contribution_count_u32 = u32::try_from(contribution_count).unwrap_or(u32::MAX);
+                .filter(|item| {
+                    contribution_qualification.matches_in_context(item.tags(), item_group.context())
+                })
+                .fold(0_u32, |count, item| count.saturating_add(item.quantity()));
 
             // Skip tiers that can never meet their thresholds even if they claim all
             // available contribution items.
@@ -1290,6 +1835,11 @@ impl ILPPromotion for TieredThresholdPromotion<'_> {
                 continue;
             }
 
+            let percent_each_item = match tier.discount() {
+                ThresholdDiscount::PercentEachItem(pct) => Some(*pct),
+                _ => None,
+            };
+
             // Create tier auxiliary variable
             let tier_var = state.problem_variables_mut().add(variable().binary());
 
@@ -1309,9 +1859,7 @@ impl ILPPromotion for TieredThresholdPromotion<'_> {
                     (true, None, None, None, None, false, 0_i64)
                 }
                 ThresholdDiscount::AmountOffTotal(a) => {
-                    let m = a.to_minor_units();
-
-                    (false, Some(m), None, None, None, false, -m)
+                    (false, Some(a.to_minor_units()), None, None, None, false, 0)
                 }
                 ThresholdDiscount::FixedTotal(a) => {
                     let m = a.to_minor_units();
@@ -1345,6 +1893,61 @@ impl ILPPromotion for TieredThresholdPromotion<'_> {
                 observer.on_objective_term(tier_var, coeff);
             }
 
+            // The amount off cannot take the discounted items below zero, so the
+            // saving is its own variable, bounded by the amount and by their spend.
+            let amount_off_saving_vars = match amount_off_total_minor {
+                Some(amount_off) => {
+                    let amount_off = amount_off.max(0);
+                    let upper = i64_to_f64_exact(amount_off)
+                        .ok_or(SolverError::MinorUnitsNotRepresentable(amount_off))?;
+
+                    let saving_var = state
+                        .problem_variables_mut()
+                        .add(variable().min(0.0).max(upper));
+
+                    observer.on_auxiliary_variable(
+                        promotion_key,
+                        saving_var,
+                        "amount off saving",
+                        Some(tier_idx),
+                        None,
+                    );
+
+                    state.add_to_objective(saving_var, -1.0);
+
+                    observer.on_objective_term(saving_var, -1.0);
+
+                    // When every discountable unit covers the amount, an active tier
+                    // always saves it in full and the saving is pinned without one.
+                    let covers_amount = item_group
+                        .iter()
+                        .filter(|item| {
+                            discount_qualification
+                                .matches_in_context(item.tags(), item_group.context())
+                        })
+                        .all(|item| item.price().to_minor_units() >= amount_off);
+
+                    let capped_var = if budget_limited && !covers_amount {
+                        let capped_var = state.problem_variables_mut().add(variable().binary());
+
+                        observer.on_auxiliary_variable(
+                            promotion_key,
+                            capped_var,
+                            "amount off capped",
+                            Some(tier_idx),
+                            None,
+                        );
+
+                        Some(capped_var)
+                    } else {
+                        None
+                    };
+
+                    Some((saving_var, capped_var))
+                }
+                None => None,
+            };
+
             // Create participation variables. Items that contribute to the
             // threshold and/or receive discount are participating and therefore
             // exclusive against other promotions in this layer.
@@ -1361,8 +1964,10 @@ impl ILPPromotion for TieredThresholdPromotion<'_> {
             for (item_idx, item) in item_group.iter().enumerate() {
                 let price = item.price().to_minor_units();
 
-                let contributes = contribution_qualification.matches(item.tags());
-                let discountable = discount_qualification.matches(item.tags());
+                let contributes = contribution_qualification
+                    .matches_in_context(item.tags(), item_group.context());
+                let discountable =
+                    discount_qualification.matches_in_context(item.tags(), item_group.context());
 
                 if !contributes && !discountable {
                     continue;
@@ -1380,7 +1985,9 @@ impl ILPPromotion for TieredThresholdPromotion<'_> {
             for (item_idx, price, contributes, discountable) in eligible_items {
                 let item = item_group.get_item(item_idx).map_err(SolverError::from)?;
 
-                let item_var = state.problem_variables_mut().add(variable().binary());
+                let item_var = state
+                    .problem_variables_mut()
+                    .add(item_units_variable(item.quantity()));
                 item_vars.push((item_idx, item_var));
 
                 if contributes {
@@ -1395,7 +2002,7 @@ impl ILPPromotion for TieredThresholdPromotion<'_> {
                 let coeff_minor = if has_per_item_discount {
                     if discountable {
                         let discounted =
-                            TieredThresholdPromotion::calculate_discounted_price(tier, item)
+                            TieredThresholdPromotion::calculate_rounded_price(tier, item, rounding)
                                 .map_err(SolverError::from)?
                                 .to_minor_units();
 
@@ -1447,6 +2054,7 @@ impl ILPPromotion for TieredThresholdPromotion<'_> {
                     percent_cheapest,
                     fixed_cheapest_minor,
                     cheapest_free,
+                    rounding,
                     promotion_key,
                     state,
                     observer,
@@ -1468,17 +2076,20 @@ impl ILPPromotion for TieredThresholdPromotion<'_> {
                 target_vars,
                 has_per_item_discount,
                 amount_off_total_minor,
+                amount_off_saving_vars,
                 fixed_total_minor,
                 percent_cheapest,
                 fixed_cheapest_minor,
                 cheapest_free,
+                percent_each_item,
+                rounding,
             });
         }
 
         Ok(Box::new(TieredThresholdPromotionVars {
             promotion_key,
             qualifying_tiers,
-            redemption_limit: self.budget().redemption_limit,
+            redemption_limit: self.redemption_limit(),
             monetary_limit_minor: self.budget().monetary_limit.map(|v| v.to_minor_units()),
         }))
     }
@@ -1486,6 +2097,8 @@ impl ILPPromotion for TieredThresholdPromotion<'_> {
 
 #[cfg(test)]
 mod tests {
+    use std::num::NonZeroU32;
+
     use std::any::Any;
 
     use decimal_percentage::Percentage;
@@ -1494,7 +2107,7 @@ mod tests {
         Money,
         iso::{self, GBP},
     };
-    use smallvec::SmallVec;
+    use smallvec::{SmallVec, smallvec};
     use testresult::TestResult;
 
     use crate::{
@@ -2037,6 +2650,91 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn item_price_outcomes_keep_contribution_items_at_full_price() -> TestResult {
+        let items = [
+            Item::with_tags(
+                ProductKey::default(),
+                Money::from_minor(3000, GBP),
+                StringTagCollection::from_strs(&["wine"]),
+            ),
+            Item::with_tags(
+                ProductKey::default(),
+                Money::from_minor(500, GBP),
+                StringTagCollection::from_strs(&["cheese"]),
+            ),
+        ];
+
+        let item_group = item_group_from_items(items);
+
+        let promo = TieredThresholdPromotion::new(
+            PromotionKey::default(),
+            vec![make_tier_with_tags(
+                2000,
+                &["wine"],
+                &["cheese"],
+                ThresholdDiscount::PercentEachItem(Percentage::from(0.10)),
+            )],
+            PromotionBudget::unlimited(),
+        );
+
+        let mut state = ILPState::new(ProblemVariables::new(), Expression::default());
+        let mut observer = NoopObserver;
+
+        let vars = promo.add_variables(&item_group, &mut state, &mut observer)?;
+
+        let wine = vars
+            .item_price_outcomes(&item_group, 0)?
+            .ok_or("expected outcomes")?;
+        let cheese = vars
+            .item_price_outcomes(&item_group, 1)?
+            .ok_or("expected outcomes")?;
+
+        assert_eq!(
+            wine.iter().map(|(_, price)| *price).collect::<Vec<_>>(),
+            vec![3000],
+            "contribution-only item keeps its price"
+        );
+        assert_eq!(
+            cheese.iter().map(|(_, price)| *price).collect::<Vec<_>>(),
+            vec![450],
+            "discount item takes the per-item discounted price"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn item_price_outcomes_unavailable_for_bundle_total_discount_items() -> TestResult {
+        let items = [Item::with_tags(
+            ProductKey::default(),
+            Money::from_minor(3000, GBP),
+            StringTagCollection::from_strs(&["wine"]),
+        )];
+
+        let item_group = item_group_from_items(items);
+
+        let promo = TieredThresholdPromotion::new(
+            PromotionKey::default(),
+            vec![make_tier_with_tags(
+                2000,
+                &["wine"],
+                &["wine"],
+                ThresholdDiscount::AmountOffTotal(Money::from_minor(500, GBP)),
+            )],
+            PromotionBudget::unlimited(),
+        );
+
+        let mut state = ILPState::new(ProblemVariables::new(), Expression::default());
+        let mut observer = NoopObserver;
+
+        let vars = promo.add_variables(&item_group, &mut state, &mut observer)?;
+
+        assert!(vars.item_price_outcomes(&item_group, 0)?.is_none());
+
+        Ok(())
+    }
+
     #[test]
     fn calculate_item_discounts_with_select_all() -> TestResult {
         let items = [Item::with_tags(
@@ -2478,6 +3176,59 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn upper_cap_symmetry_break_constraints_are_skipped_when_disabled() -> TestResult {
+        let same_product = ProductKey::default();
+        let items = [
+            Item::with_tags(
+                same_product,
+                Money::from_minor(5000, GBP),
+                StringTagCollection::from_strs(&["wine"]),
+            ),
+            Item::with_tags(
+                same_product,
+                Money::from_minor(5000, GBP),
+                StringTagCollection::from_strs(&["wine"]),
+            ),
+        ];
+
+        let item_group = item_group_from_items(items);
+
+        let tier = ThresholdTier::new(
+            TierThreshold::with_monetary_threshold(Money::from_minor(1000, GBP)),
+            Some(TierThreshold::with_monetary_threshold(Money::from_minor(
+                8000, GBP,
+            ))),
+            Qualification::match_any(StringTagCollection::from_strs(&["wine"])),
+            Qualification::match_any(StringTagCollection::from_strs(&["wine"])),
+            ThresholdDiscount::PercentEachItem(Percentage::from(0.10)),
+        );
+
+        let promo = TieredThresholdPromotion::new(
+            PromotionKey::default(),
+            vec![tier],
+            PromotionBudget::unlimited(),
+        );
+
+        let mut state = ILPState::new(ProblemVariables::new(), Expression::default());
+        state.disable_symmetry_breaking();
+
+        let mut observer = RecordingObserver::default();
+
+        let vars = promo.add_variables(&item_group, &mut state, &mut observer)?;
+        vars.add_constraints(promo.key(), &item_group, &mut state, &mut observer)?;
+
+        let symmetry_break_count = observer
+            .promotion_constraints
+            .iter()
+            .filter(|record| record.constraint_type == "Upper cap symmetry break")
+            .count();
+
+        assert_eq!(symmetry_break_count, 0);
+
+        Ok(())
+    }
+
     #[test]
     fn is_applicable_empty_items_even_when_discount_tags_are_empty() {
         let item_group: ItemGroup<'_> = ItemGroup::new(SmallVec::new(), GBP);
@@ -2547,10 +3298,13 @@ mod tests {
             target_vars: SmallVec::new(),
             has_per_item_discount: false,
             amount_off_total_minor: Some(50),
+            amount_off_saving_vars: None,
             fixed_total_minor: None,
             percent_cheapest: None,
             fixed_cheapest_minor: None,
             cheapest_free: false,
+            percent_each_item: None,
+            rounding: RoundingPolicy::default(),
         };
 
         assert!(bundle_tier.has_bundle_total_discount());
@@ -2589,12 +3343,11 @@ mod tests {
             t.saturating_sub(60)
         })?;
 
-        assert_eq!(discounts.get(&0), Some(&(200, 160)));
-        assert_eq!(discounts.get(&1), Some(&(100, 80)));
+        assert_eq!(discounts.as_slice(), &[(0, 200, 160, 1), (1, 100, 80, 1)]);
 
         let final_total: i64 = discounts
-            .values()
-            .map(|(_, final_minor)| *final_minor)
+            .iter()
+            .map(|&(_, _, final_minor, units)| final_minor * i64::from(units))
             .sum();
 
         assert_eq!(final_total, 240);
@@ -2618,13 +3371,14 @@ mod tests {
         let discounts =
             calculate_total_discounts(&discount_vars, &solution, &item_group, &|_| 100)?;
 
-        assert_eq!(discounts.get(&0), Some(&(100, 33)));
-        assert_eq!(discounts.get(&1), Some(&(100, 33)));
-        assert_eq!(discounts.get(&2), Some(&(100, 34)));
+        assert_eq!(
+            discounts.as_slice(),
+            &[(0, 100, 33, 1), (1, 100, 33, 1), (2, 100, 34, 1)]
+        );
 
         let final_total: i64 = discounts
-            .values()
-            .map(|(_, final_minor)| *final_minor)
+            .iter()
+            .map(|&(_, _, final_minor, units)| final_minor * i64::from(units))
             .sum();
 
         assert_eq!(final_total, 100);
@@ -2655,8 +3409,32 @@ mod tests {
             &|price| price.saturating_sub(40),
         )?;
 
-        assert_eq!(discounts.get(&0), Some(&(250, 250)));
-        assert_eq!(discounts.get(&1), Some(&(150, 110)));
+        assert_eq!(discounts.as_slice(), &[(0, 250, 250, 1), (1, 150, 110, 1)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn calculate_total_discounts_splits_remainder_from_quantity_line() -> TestResult {
+        let item_group = ItemGroup::new(
+            smallvec![
+                Item::new(ProductKey::default(), Money::from_minor(100, iso::GBP))
+                    .with_quantity(NonZeroU32::new(3).expect("non-zero quantity"))
+            ],
+            iso::GBP,
+        );
+
+        let mut state = ILPState::new(ProblemVariables::new(), Expression::default());
+
+        let v0 = state.problem_variables_mut().add(item_units_variable(3));
+
+        let discount_vars = SmallVec::from_vec(vec![(0, v0)]);
+        let solution = MapSolution::with(&[(v0, 3.0)]);
+
+        let discounts =
+            calculate_total_discounts(&discount_vars, &solution, &item_group, &|_| 100)?;
+
+        assert_eq!(discounts.as_slice(), &[(0, 100, 33, 2), (0, 100, 34, 1)]);
 
         Ok(())
     }
@@ -2682,10 +3460,13 @@ mod tests {
             target_vars: SmallVec::new(),
             has_per_item_discount: true,
             amount_off_total_minor: None,
+            amount_off_saving_vars: None,
             fixed_total_minor: None,
             percent_cheapest: None,
             fixed_cheapest_minor: None,
             cheapest_free: false,
+            percent_each_item: None,
+            rounding: RoundingPolicy::default(),
         };
 
         assert_eq!(
@@ -2728,6 +3509,7 @@ mod tests {
             Some(Percentage::from(0.50)),
             None,
             false,
+            RoundingPolicy::default(),
             PromotionKey::default(),
             &mut state,
             &mut observer,
@@ -2760,6 +3542,7 @@ mod tests {
             Some(Percentage::from(0.50)),
             None,
             false,
+            RoundingPolicy::default(),
             PromotionKey::default(),
             &mut state,
             &mut observer,
@@ -2791,6 +3574,7 @@ mod tests {
             Some(Percentage::from(0.50)),
             None,
             false,
+            RoundingPolicy::default(),
             PromotionKey::default(),
             &mut state,
             &mut observer,
@@ -2806,7 +3590,7 @@ mod tests {
     }
 
     #[test]
-    fn add_variables_amount_off_total_uses_negative_tier_objective_term() -> TestResult {
+    fn add_variables_amount_off_total_saves_through_a_capped_saving_variable() -> TestResult {
         let items = [Item::with_tags(
             ProductKey::default(),
             Money::from_minor(1000, GBP),
@@ -2831,8 +3615,15 @@ mod tests {
 
         let _vars = promo.add_variables(&item_group, &mut state, &mut observer)?;
 
+        // The saving is worth one unit per minor unit, never a flat 100 for the tier.
         assert!(
             observer
+                .objective_terms
+                .iter()
+                .any(|(_, coeff)| (*coeff - -1.0).abs() < f64::EPSILON)
+        );
+        assert!(
+            !observer
                 .objective_terms
                 .iter()
                 .any(|(_, coeff)| (*coeff - -100.0).abs() < f64::EPSILON)
@@ -2906,10 +3697,13 @@ mod tests {
             target_vars: SmallVec::from_vec(vec![(0, t0), (1, t1)]),
             has_per_item_discount: false,
             amount_off_total_minor: None,
+            amount_off_saving_vars: None,
             fixed_total_minor: None,
             percent_cheapest: Some(Percentage::from(0.25)),
             fixed_cheapest_minor: None,
             cheapest_free: false,
+            percent_each_item: None,
+            rounding: RoundingPolicy::default(),
         };
 
         let solution = MapSolution::with(&[(d0, 1.0), (d1, 1.0), (t0, 1.0), (t1, 0.0)]);
@@ -3007,7 +3801,9 @@ mod tests {
     }
 
     #[test]
-    fn add_cheapest_constraints_emit_expected_relations() {
+    fn add_cheapest_constraints_emit_expected_relations() -> TestResult {
+        let item_group = item_group_from_prices(&[100, 200]);
+
         let mut state = ILPState::new(ProblemVariables::new(), Expression::default());
 
         let tier_var = state.problem_variables_mut().add(variable().binary());
@@ -3029,15 +3825,24 @@ mod tests {
             target_vars: SmallVec::from_vec(vec![(0, t0), (1, t1)]),
             has_per_item_discount: false,
             amount_off_total_minor: None,
+            amount_off_saving_vars: None,
             fixed_total_minor: None,
             percent_cheapest: Some(Percentage::from(0.25)),
             fixed_cheapest_minor: None,
             cheapest_free: false,
+            percent_each_item: None,
+            rounding: RoundingPolicy::default(),
         };
 
         let mut observer = RecordingObserver::default();
 
-        add_cheapest_constraints(&qt, PromotionKey::default(), &mut state, &mut observer);
+        add_cheapest_constraints(
+            &qt,
+            PromotionKey::default(),
+            &item_group,
+            &mut state,
+            &mut observer,
+        )?;
 
         assert_eq!(observer.promotion_constraints.len(), 4);
 
@@ -3061,6 +3866,8 @@ mod tests {
         let (_pb, _cost, _presence, constraints) = state.into_parts_with_constraints();
 
         assert_state_constraints_hold(&constraints, &satisfied);
+
+        Ok(())
     }
 
     #[test]
@@ -0,0 +1,1313 @@
+//! ILP LP/MPS Renderer
+//!
+//! This module provides a renderer that captures ILP formulations and writes
+//! them as CPLEX LP and free MPS files, the formats most external MILP solvers
+//! (HiGHS, CBC, SCIP, Gurobi, CPLEX) read. Greedy graph evaluations write one
+//! pair of files per solved layer; joint evaluations write their single model.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use lattice::solvers::ilp::{ILPSolver, renderers::lp::LpRenderer};
+//! use std::path::PathBuf;
+//! # use lattice::{fixtures::Fixture, items::groups::ItemGroup};
+//! # fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! # let fixture = Fixture::from_set("example_direct_discounts")?;
+//! # let basket = fixture.basket(Some(10))?;
+//! # let item_group = ItemGroup::from(&basket);
+//! # let promotions = fixture.promotions();
+//!
+//! let mut renderer = LpRenderer::new(PathBuf::from("formulation"));
+//!
+//! let _result = ILPSolver::solve_with_observer(promotions, &item_group, &mut renderer)?;
+//!
+//! // Writes formulation.lp and formulation.mps
+//! let _paths = renderer.write()?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::borrow::Cow;
+use std::fmt::{self, Write as _};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use good_lp::{Expression, IntoAffineExpression, Variable, VariableDefinition};
+use petgraph::graph::NodeIndex;
+use rustc_hash::{FxHashMap, FxHashSet};
+use slotmap::SlotMap;
+
+use crate::{
+    graph::PromotionLayerKey,
+    items::groups::ItemGroup,
+    products::{Product, ProductKey},
+    promotions::{PromotionKey, PromotionMeta, budget::BudgetPoolKey},
+    solvers::ilp::{ILPObserver, i64_to_f64_exact},
+};
+
+/// Errors that can occur during LP/MPS rendering.
+#[derive(Debug, thiserror::Error)]
+pub enum LpRenderError {
+    /// Failed to write to an output file.
+    #[error("Failed to write to output file: {0}")]
+    IoError(#[from] std::io::Error),
+
+    /// Failed to format the output.
+    #[error("Failed to format output: {0}")]
+    FormatError(#[from] fmt::Error),
+}
+
+/// Bounds and integrality of a variable.
+#[derive(Debug, Clone, Copy)]
+struct VarBounds {
+    min: f64,
+    max: f64,
+    integer: bool,
+}
+
+impl VarBounds {
+    /// `good_lp`'s default: a continuous, unbounded variable.
+    const FREE: Self = Self {
+        min: f64::NEG_INFINITY,
+        max: f64::INFINITY,
+        integer: false,
+    };
+
+    fn is_binary(self) -> bool {
+        self.integer && self.min.abs() < f64::EPSILON && (self.max - 1.0).abs() < f64::EPSILON
+    }
+}
+
+/// Relation of a constraint row to its right-hand side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RowRelation {
+    Eq,
+    Leq,
+    Geq,
+}
+
+impl RowRelation {
+    fn from_symbol(relation: &str) -> Self {
+        match relation {
+            "<=" => Self::Leq,
+            ">=" => Self::Geq,
+            _ => Self::Eq,
+        }
+    }
+
+    fn lp_symbol(self) -> &'static str {
+        match self {
+            Self::Eq => "=",
+            Self::Leq => "<=",
+            Self::Geq => ">=",
+        }
+    }
+
+    fn mps_type(self) -> &'static str {
+        match self {
+            Self::Eq => "E",
+            Self::Leq => "L",
+            Self::Geq => "G",
+        }
+    }
+}
+
+/// A named constraint row, with any constant moved to the right-hand side.
+#[derive(Debug, Clone)]
+struct Row {
+    name: String,
+    terms: Vec<(Variable, f64)>,
+    relation: RowRelation,
+    rhs: f64,
+
+    /// Variable whose upper bound is added to `rhs` when rendering.
+    rhs_from_bound: Option<Variable>,
+}
+
+/// One model captured from a solve (or one layer of a graph evaluation).
+#[derive(Debug, Clone, Default)]
+struct CapturedModel {
+    /// Variables in the order they were first reported.
+    vars: Vec<Variable>,
+
+    /// Variable -> column name
+    names: FxHashMap<Variable, String>,
+
+    /// Variable -> (creation order, bounds), from `on_variable_definition`.
+    definitions: FxHashMap<Variable, (usize, VarBounds)>,
+
+    /// Variable -> objective coefficient (minor units)
+    objective: FxHashMap<Variable, f64>,
+
+    rows: Vec<Row>,
+
+    /// Item index -> presence variable
+    presence: FxHashMap<usize, Variable>,
+
+    /// Column and row names already in use.
+    taken_names: FxHashSet<String>,
+}
+
+impl CapturedModel {
+    fn is_empty(&self) -> bool {
+        self.vars.is_empty() && self.rows.is_empty()
+    }
+
+    /// Claim `base`, or `base_2`, `base_3`, ... if it is already used.
+    fn unique_name(&mut self, base: &str) -> String {
+        let base = sanitize_name(base);
+        let mut name = base.clone();
+        let mut suffix = 2_usize;
+
+        while self.taken_names.contains(&name) {
+            name = format!("{base}_{suffix}");
+            suffix += 1;
+        }
+
+        self.taken_names.insert(name.clone());
+
+        name
+    }
+
+    /// Register a variable, naming it `base` if it has no name yet.
+    fn name_var(&mut self, var: Variable, base: &str) {
+        if self.names.contains_key(&var) {
+            return;
+        }
+
+        let name = self.unique_name(base);
+
+        self.names.insert(var, name);
+        self.vars.push(var);
+    }
+
+    /// Name any variables in `expr` that no callback has reported.
+    fn see_expression(&mut self, expr: &Expression) {
+        let mut unseen: Vec<Variable> = expr
+            .linear_coefficients()
+            .map(|(var, _coeff)| var)
+            .filter(|var| !self.names.contains_key(var))
+            .collect();
+
+        // Expressions don't keep term order; fall back to creation order.
+        unseen.sort_by_key(|var| variable_index(*var));
+
+        for var in unseen {
+            let base = format!("v{}", self.vars.len() + 1);
+
+            self.name_var(var, &base);
+        }
+    }
+
+    fn add_row(&mut self, base: &str, expr: &Expression, relation: &str, rhs: f64) {
+        self.see_expression(expr);
+
+        let name = self.unique_name(base);
+        let terms = expr
+            .linear_coefficients()
+            .filter(|(_var, coeff)| coeff.abs() >= f64::EPSILON)
+            .collect();
+
+        self.rows.push(Row {
+            name,
+            terms,
+            relation: RowRelation::from_symbol(relation),
+            rhs: rhs - expr.constant(),
+            rhs_from_bound: None,
+        });
+    }
+
+    fn define(&mut self, var: Variable, definition: &VariableDefinition) {
+        if !self.names.contains_key(&var) {
+            let base = format!("v{}", self.vars.len() + 1);
+
+            self.name_var(var, &base);
+        }
+
+        let order = self.definitions.len();
+
+        self.definitions.insert(
+            var,
+            (
+                order,
+                VarBounds {
+                    min: definition.get_min(),
+                    max: definition.get_max(),
+                    integer: definition.is_integer(),
+                },
+            ),
+        );
+    }
+
+    /// Add `other`'s columns, objective and rows, renaming any that clash.
+    fn merge(&mut self, other: &Self) {
+        for &var in &other.vars {
+            self.name_var(var, other.name(var));
+        }
+
+        let offset = self.definitions.len();
+
+        for (&var, &(order, bounds)) in &other.definitions {
+            self.definitions.insert(var, (offset + order, bounds));
+        }
+
+        for (&var, &coeff) in &other.objective {
+            *self.objective.entry(var).or_insert(0.0) += coeff;
+        }
+
+        for row in &other.rows {
+            let name = self.unique_name(&row.name);
+
+            self.rows.push(Row {
+                name,
+                ..row.clone()
+            });
+        }
+    }
+
+    /// Variables in creation order where known, then in the order first seen.
+    fn ordered_vars(&self) -> Vec<Variable> {
+        let mut vars = self.vars.clone();
+
+        vars.sort_by_key(|var| {
+            self.definitions
+                .get(var)
+                .map_or(usize::MAX, |(order, _bounds)| *order)
+        });
+
+        vars
+    }
+
+    fn bounds(&self, var: Variable) -> VarBounds {
+        self.definitions
+            .get(&var)
+            .map_or(VarBounds::FREE, |(_order, bounds)| *bounds)
+    }
+
+    fn rhs(&self, row: &Row) -> f64 {
+        let bound = row
+            .rhs_from_bound
+            .map(|var| self.bounds(var).max)
+            .filter(|max| max.is_finite())
+            .unwrap_or(1.0);
+
+        if row.rhs_from_bound.is_some() {
+            row.rhs + bound
+        } else {
+            row.rhs
+        }
+    }
+
+    fn name(&self, var: Variable) -> &str {
+        self.names.get(&var).map_or("v", String::as_str)
+    }
+
+    fn render_lp(&self, title: &str) -> Result<String, fmt::Error> {
+        let vars = self.ordered_vars();
+        let mut output = String::new();
+
+        writeln!(output, "\\ {title}")?;
+        output.push_str("Minimize\n");
+
+        let objective: Vec<(Variable, f64)> = vars
+            .iter()
+            .filter_map(|var| {
+                self.objective
+                    .get(var)
+                    .filter(|coeff| coeff.abs() >= f64::EPSILON)
+                    .map(|coeff| (*var, *coeff))
+            })
+            .collect();
+
+        output.push_str(" obj:");
+        self.write_lp_terms(&mut output, &objective, &vars)?;
+        output.push('\n');
+
+        output.push_str("Subject To\n");
+
+        let position: FxHashMap<Variable, usize> = vars
+            .iter()
+            .enumerate()
+            .map(|(idx, var)| (*var, idx))
+            .collect();
+
+        for row in &self.rows {
+            write!(output, " {}:", row.name)?;
+
+            // Expressions don't keep term order, so write them in column order.
+            let mut terms = row.terms.clone();
+
+            terms.sort_by_key(|(var, _coeff)| position.get(var).copied());
+
+            self.write_lp_terms(&mut output, &terms, &vars)?;
+
+            writeln!(
+                output,
+                " {} {}",
+                row.relation.lp_symbol(),
+                render_number(self.rhs(row))
+            )?;
+        }
+
+        let mut bound_lines = Vec::new();
+        let mut generals = Vec::new();
+        let mut binaries = Vec::new();
+
+        for &var in &vars {
+            let bounds = self.bounds(var);
+            let name = self.name(var);
+
+            if bounds.is_binary() {
+                binaries.push(name);
+
+                continue;
+            }
+
+            if bounds.integer {
+                generals.push(name);
+            }
+
+            bound_lines.push(lp_bound(name, bounds));
+        }
+
+        if !bound_lines.is_empty() {
+            output.push_str("Bounds\n");
+
+            for line in bound_lines {
+                writeln!(output, " {line}")?;
+            }
+        }
+
+        write_lp_section(&mut output, "Generals", &generals);
+        write_lp_section(&mut output, "Binaries", &binaries);
+
+        output.push_str("End\n");
+
+        Ok(output)
+    }
+
+    /// Write `terms` as `+ 3 x - 2 y`, wrapping long rows. An empty row is written
+    /// as a zero multiple of the first variable, since LP rows need a term.
+    fn write_lp_terms(
+        &self,
+        output: &mut String,
+        terms: &[(Variable, f64)],
+        vars: &[Variable],
+    ) -> fmt::Result {
+        if terms.is_empty() {
+            if let Some(&var) = vars.first() {
+                write!(output, " 0 {}", self.name(var))?;
+            }
+
+            return Ok(());
+        }
+
+        for (idx, &(var, coeff)) in terms.iter().enumerate() {
+            if idx > 0 && idx % LP_TERMS_PER_LINE == 0 {
+                output.push_str("\n  ");
+            }
+
+            let sign = if coeff < 0.0 { '-' } else { '+' };
+
+            write!(
+                output,
+                " {sign} {} {}",
+                render_number(coeff.abs()),
+                self.name(var)
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn render_mps(&self, title: &str) -> Result<String, fmt::Error> {
+        let vars = self.ordered_vars();
+        let mut columns: FxHashMap<Variable, Vec<(&str, f64)>> = FxHashMap::default();
+
+        for var in &vars {
+            if let Some(coeff) = self
+                .objective
+                .get(var)
+                .filter(|coeff| coeff.abs() >= f64::EPSILON)
+            {
+                columns.entry(*var).or_default().push(("obj", *coeff));
+            }
+        }
+
+        for row in &self.rows {
+            for &(var, coeff) in &row.terms {
+                columns
+                    .entry(var)
+                    .or_default()
+                    .push((row.name.as_str(), coeff));
+            }
+        }
+
+        let mut output = String::new();
+
+        writeln!(output, "* {title}")?;
+        output.push_str("NAME lattice\nROWS\n N obj\n");
+
+        for row in &self.rows {
+            writeln!(output, " {} {}", row.relation.mps_type(), row.name)?;
+        }
+
+        output.push_str("COLUMNS\n");
+
+        let mut in_integer_block = false;
+
+        for &var in &vars {
+            let integer = self.bounds(var).integer;
+
+            if integer != in_integer_block {
+                let marker = if integer { "INTORG" } else { "INTEND" };
+                writeln!(output, " MARKER 'MARKER' '{marker}'")?;
+
+                in_integer_block = integer;
+            }
+
+            let name = self.name(var);
+
+            match columns.get(&var) {
+                Some(entries) => {
+                    for (row, coeff) in entries {
+                        writeln!(output, " {name} {row} {}", render_number(*coeff))?;
+                    }
+                }
+                // Columns must be listed to take bounds, even without coefficients.
+                None => {
+                    writeln!(output, " {name} obj 0")?;
+                }
+            }
+        }
+
+        if in_integer_block {
+            output.push_str(" MARKER 'MARKER' 'INTEND'\n");
+        }
+
+        output.push_str("RHS\n");
+
+        for row in &self.rows {
+            let rhs = self.rhs(row);
+
+            if rhs.abs() >= f64::EPSILON {
+                writeln!(output, " RHS {} {}", row.name, render_number(rhs))?;
+            }
+        }
+
+        output.push_str("BOUNDS\n");
+
+        for &var in &vars {
+            let bounds = self.bounds(var);
+            let name = self.name(var);
+
+            if bounds.is_binary() {
+                writeln!(output, " BV BND {name}")?;
+
+                continue;
+            }
+
+            match (bounds.min.is_finite(), bounds.max.is_finite()) {
+                (false, false) => {
+                    writeln!(output, " FR BND {name}")?;
+                }
+                (true, true) if (bounds.max - bounds.min).abs() < f64::EPSILON => {
+                    writeln!(output, " FX BND {name} {}", render_number(bounds.min))?;
+                }
+                (min_finite, max_finite) => {
+                    // Both sides are always written: readers differ on the defaults
+                    // for integer columns.
+                    if min_finite {
+                        writeln!(output, " LO BND {name} {}", render_number(bounds.min))?;
+                    } else {
+                        writeln!(output, " MI BND {name}")?;
+                    }
+
+                    if max_finite {
+                        writeln!(output, " UP BND {name} {}", render_number(bounds.max))?;
+                    } else {
+                        writeln!(output, " PL BND {name}")?;
+                    }
+                }
+            }
+        }
+
+        output.push_str("ENDATA\n");
+
+        Ok(output)
+    }
+}
+
+/// Terms (or names) written per line before wrapping, keeping lines within the
+/// LP format's 560 characters.
+const LP_TERMS_PER_LINE: usize = 4;
+
+/// Longest name written, well within the LP format's 255 characters.
+const MAX_NAME_LEN: usize = 120;
+
+/// Restrict a name to letters, digits and underscores, starting with a letter,
+/// so it is valid in both formats.
+fn sanitize_name(name: &str) -> String {
+    let mut sanitized = String::with_capacity(name.len());
+
+    for ch in name.chars() {
+        if ch.is_ascii_alphanumeric() {
+            sanitized.push(ch);
+        } else if !sanitized.is_empty() && !sanitized.ends_with('_') {
+            sanitized.push('_');
+        }
+    }
+
+    while sanitized.ends_with('_') {
+        sanitized.pop();
+    }
+
+    if !sanitized.starts_with(|ch: char| ch.is_ascii_alphabetic()) {
+        sanitized.insert_str(0, "v_");
+    }
+
+    sanitized.truncate(MAX_NAME_LEN);
+
+    sanitized
+}
+
+/// Creation index of a variable, read from its `Debug` output since `good_lp`
+/// doesn't expose it.
+fn variable_index(var: Variable) -> Option<usize> {
+    let debug = format!("{var:?}");
+    let digits: String = debug.chars().filter(char::is_ascii_digit).collect();
+
+    digits.parse().ok()
+}
+
+/// An LP bounds line for `name`, without indentation.
+fn lp_bound(name: &str, bounds: VarBounds) -> String {
+    match (bounds.min.is_finite(), bounds.max.is_finite()) {
+        (false, false) => format!("{name} free"),
+        (true, false) => format!("{name} >= {}", render_number(bounds.min)),
+        (false, true) => format!("-inf <= {name} <= {}", render_number(bounds.max)),
+        (true, true) if (bounds.max - bounds.min).abs() < f64::EPSILON => {
+            format!("{name} = {}", render_number(bounds.min))
+        }
+        (true, true) => format!(
+            "{} <= {name} <= {}",
+            render_number(bounds.min),
+            render_number(bounds.max)
+        ),
+    }
+}
+
+fn render_number(value: f64) -> String {
+    // Avoid writing negative zero.
+    if value.abs() < f64::EPSILON {
+        return String::from("0");
+    }
+
+    format!("{value}")
+}
+
+fn write_lp_section(output: &mut String, heading: &str, names: &[&str]) {
+    if names.is_empty() {
+        return;
+    }
+
+    output.push_str(heading);
+    output.push('\n');
+
+    for chunk in names.chunks(LP_TERMS_PER_LINE) {
+        output.push(' ');
+        output.push_str(&chunk.join(" "));
+        output.push('\n');
+    }
+}
+
+/// A captured layer: (solve order, layer key, graph node, model).
+type CapturedLayer = (usize, PromotionLayerKey, NodeIndex, CapturedModel);
+
+/// LP/MPS renderer that implements `ILPObserver`.
+///
+/// Captures the model of a solve, or of every layer of a graph evaluation, and
+/// writes each as an `.lp` and an `.mps` file. Column and row names are derived
+/// from product and promotion names when metadata is attached.
+///
+/// In [`EvaluationMode::Joint`](crate::graph::EvaluationMode::Joint) all layers
+/// share one model, so they are written together as a single model, with the
+/// rows linking each layer to the one before it and any shared budget pools.
+#[derive(Debug, Clone)]
+pub struct LpRenderer {
+    /// Output path without extension; files are written next to it.
+    output_path: PathBuf,
+
+    /// Item index -> product name
+    item_names: Vec<Option<String>>,
+
+    /// Promotion key -> promotion name
+    promotion_names: FxHashMap<PromotionKey, String>,
+
+    /// Completed layers
+    layers: Vec<CapturedLayer>,
+
+    /// Layer currently being captured, if any
+    current_layer: Option<(usize, PromotionLayerKey, NodeIndex)>,
+
+    /// Model being captured
+    current: CapturedModel,
+}
+
+impl LpRenderer {
+    /// Create a new LP/MPS renderer writing to `output_path` with `.lp` and `.mps`
+    /// extensions.
+    #[must_use]
+    pub fn new(output_path: PathBuf) -> Self {
+        Self {
+            output_path,
+            item_names: Vec::new(),
+            promotion_names: FxHashMap::default(),
+            layers: Vec::new(),
+            current_layer: None,
+            current: CapturedModel::default(),
+        }
+    }
+
+    /// Create a renderer and attach product/promotion metadata for naming.
+    #[must_use]
+    pub fn new_with_metadata<'a>(
+        output_path: PathBuf,
+        item_group: &ItemGroup<'a>,
+        product_meta: &SlotMap<ProductKey, Product<'a>>,
+        promotion_meta: &SlotMap<PromotionKey, PromotionMeta>,
+    ) -> Self {
+        let item_names = item_group
+            .iter()
+            .map(|item| {
+                product_meta
+                    .get(item.product())
+                    .map(|product| product.name.clone())
+            })
+            .collect();
+
+        let promotion_names = promotion_meta
+            .iter()
+            .map(|(key, meta)| (key, meta.name.clone()))
+            .collect();
+
+        Self {
+            item_names,
+            promotion_names,
+            ..Self::new(output_path)
+        }
+    }
+
+    /// Get the output path (without extension).
+    #[must_use]
+    pub fn output_path(&self) -> &Path {
+        &self.output_path
+    }
+
+    /// Render every captured model in CPLEX LP format, in solve order.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`LpRenderError::FormatError`] if the output cannot be formatted.
+    pub fn render_lp(&self) -> Result<Vec<String>, LpRenderError> {
+        self.models()
+            .into_iter()
+            .map(|(title, _suffix, model)| Ok(model.render_lp(&title)?))
+            .collect()
+    }
+
+    /// Render every captured model in free MPS format, in solve order.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`LpRenderError::FormatError`] if the output cannot be formatted.
+    pub fn render_mps(&self) -> Result<Vec<String>, LpRenderError> {
+        self.models()
+            .into_iter()
+            .map(|(title, _suffix, model)| Ok(model.render_mps(&title)?))
+            .collect()
+    }
+
+    /// Write an `.lp` and an `.mps` file for every captured model.
+    ///
+    /// A single solve or a joint graph evaluation writes `<output>.lp` and
+    /// `<output>.mps`; a greedy graph evaluation writes `<output>-layer-<n>.lp`
+    /// and `<output>-layer-<n>.mps` for each layer in solve order. Returns the
+    /// paths written.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`LpRenderError::IoError`] if a file cannot be created or written, or
+    /// [`LpRenderError::FormatError`] if the output cannot be formatted.
+    pub fn write(&self) -> Result<Vec<PathBuf>, LpRenderError> {
+        let mut paths = Vec::new();
+
+        for (title, suffix, model) in self.models() {
+            for (extension, content) in [
+                ("lp", model.render_lp(&title)?),
+                ("mps", model.render_mps(&title)?),
+            ] {
+                let path = self.file_path(&suffix, extension);
+
+                fs::write(&path, content)?;
+
+                paths.push(path);
+            }
+        }
+
+        Ok(paths)
+    }
+
+    /// Captured models with their titles and file name suffixes.
+    fn models(&self) -> Vec<(String, String, Cow<'_, CapturedModel>)> {
+        if self.layers.is_empty() {
+            if self.current.is_empty() {
+                return Vec::new();
+            }
+
+            return vec![(
+                String::from("Lattice ILP formulation"),
+                String::new(),
+                Cow::Borrowed(&self.current),
+            )];
+        }
+
+        // A joint evaluation reports its bounds and the rows shared by its layers
+        // after the last layer ends, while greedy layers are each solved whole.
+        if !self.current.is_empty() {
+            let mut combined = CapturedModel::default();
+
+            for (_order, _key, _node, model) in &self.layers {
+                combined.merge(model);
+            }
+
+            combined.merge(&self.current);
+
+            return vec![(
+                format!(
+                    "Lattice ILP formulation, joint evaluation of {} layers",
+                    self.layers.len()
+                ),
+                String::new(),
+                Cow::Owned(combined),
+            )];
+        }
+
+        self.layers
+            .iter()
+            .filter(|(_order, _key, _node, model)| !model.is_empty())
+            .map(|(order, _key, node_idx, model)| {
+                let layer = order + 1;
+
+                (
+                    format!(
+                        "Lattice ILP formulation, layer {layer} (graph node {})",
+                        node_idx.index()
+                    ),
+                    format!("-layer-{layer}"),
+                    Cow::Borrowed(model),
+                )
+            })
+            .collect()
+    }
+
+    fn file_path(&self, suffix: &str, extension: &str) -> PathBuf {
+        let stem = self.output_path.file_name().map_or_else(
+            || String::from("formulation"),
+            |name| name.to_string_lossy().into_owned(),
+        );
+
+        self.output_path
+            .with_file_name(format!("{stem}{suffix}.{extension}"))
+    }
+
+    fn item_label(&self, item_idx: usize) -> String {
+        let display_idx = item_idx + 1;
+
+        match self.item_names.get(item_idx).and_then(|name| name.as_ref()) {
+            Some(name) => format!("i{display_idx}_{name}"),
+            None => format!("i{display_idx}"),
+        }
+    }
+
+    fn promotion_label(&self, promotion_key: PromotionKey) -> String {
+        self.promotion_names
+            .get(&promotion_key)
+            .cloned()
+            .unwrap_or_else(|| format!("{promotion_key:?}"))
+    }
+}
+
+impl ILPObserver for LpRenderer {
+    fn on_presence_variable(&mut self, item_idx: usize, var: Variable, _price_minor: i64) {
+        let base = format!("full_{}", self.item_label(item_idx));
+
+        self.current.name_var(var, &base);
+        self.current.presence.insert(item_idx, var);
+    }
+
+    fn on_promotion_variable(
+        &mut self,
+        promotion_key: PromotionKey,
+        item_idx: usize,
+        var: Variable,
+        _discounted_price_minor: i64,
+        metadata: Option<&str>,
+    ) {
+        let mut base = format!(
+            "{}_{}",
+            self.promotion_label(promotion_key),
+            self.item_label(item_idx)
+        );
+
+        if let Some(metadata) = metadata {
+            base.push('_');
+            base.push_str(metadata);
+        }
+
+        self.current.name_var(var, &base);
+    }
+
+    fn on_auxiliary_variable(
+        &mut self,
+        promotion_key: PromotionKey,
+        var: Variable,
+        role: &str,
+        position: Option<usize>,
+        state: Option<usize>,
+    ) {
+        let mut base = format!("{}_{role}", self.promotion_label(promotion_key));
+
+        if let Some(position) = position {
+            base.push('_');
+            base.push_str(&position.to_string());
+        }
+
+        if let Some(state) = state {
+            base.push_str("_s");
+            base.push_str(&state.to_string());
+        }
+
+        self.current.name_var(var, &base);
+    }
+
+    fn on_variable_definition(&mut self, var: Variable, definition: &VariableDefinition) {
+        self.current.define(var, definition);
+    }
+
+    fn on_objective_term(&mut self, var: Variable, coefficient: f64) {
+        if !self.current.names.contains_key(&var) {
+            let base = format!("v{}", self.current.vars.len() + 1);
+
+            self.current.name_var(var, &base);
+        }
+
+        *self.current.objective.entry(var).or_insert(0.0) += coefficient;
+    }
+
+    fn on_exclusivity_constraint(&mut self, item_idx: usize, constraint_expr: &Expression) {
+        let base = format!("excl_{}", self.item_label(item_idx));
+
+        self.current.add_row(&base, constraint_expr, "=", 0.0);
+
+        // Exclusivity rows equal the line quantity, which is the upper bound of the
+        // item's presence variable; it is resolved once bounds are reported.
+        let presence = self.current.presence.get(&item_idx).copied();
+
+        if let Some(row) = self.current.rows.last_mut() {
+            row.rhs_from_bound = presence;
+        }
+    }
+
+    fn on_promotion_constraint(
+        &mut self,
+        promotion_key: PromotionKey,
+        constraint_type: &str,
+        constraint_expr: &Expression,
+        relation: &str,
+        rhs: f64,
+    ) {
+        let base = format!("{}_{constraint_type}", self.promotion_label(promotion_key));
+
+        self.current.add_row(&base, constraint_expr, relation, rhs);
+    }
+
+    fn on_budget_pool_constraint(
+        &mut self,
+        pool_key: BudgetPoolKey,
+        constraint_type: &str,
+        constraint_expr: &Expression,
+        relation: &str,
+        rhs: f64,
+    ) {
+        let base = format!("pool_{pool_key:?}_{constraint_type}");
+
+        self.current.add_row(&base, constraint_expr, relation, rhs);
+    }
+
+    fn on_exclusion_constraint(
+        &mut self,
+        first: PromotionKey,
+        second: PromotionKey,
+        constraint_type: &str,
+        constraint_expr: &Expression,
+        relation: &str,
+        rhs: f64,
+    ) {
+        let base = format!("exclusion_{first:?}_{second:?}_{constraint_type}");
+
+        self.current.add_row(&base, constraint_expr, relation, rhs);
+    }
+
+    fn on_linking_constraint(
+        &mut self,
+        item_idx: usize,
+        constraint_expr: &Expression,
+        incoming: &Expression,
+        price_minor: i64,
+    ) {
+        let base = format!("link_{}", self.item_label(item_idx));
+
+        self.current.see_expression(incoming);
+        self.current.add_row(&base, constraint_expr, "=", 0.0);
+
+        let Some(price) = i64_to_f64_exact(price_minor) else {
+            return;
+        };
+
+        for (var, coeff) in incoming.linear_coefficients() {
+            *self.current.objective.entry(var).or_insert(0.0) -= price * coeff;
+        }
+    }
+
+    fn on_layer_begin(&mut self, layer_key: PromotionLayerKey, node_idx: NodeIndex) {
+        self.current_layer = Some((self.layers.len(), layer_key, node_idx));
+        self.current = CapturedModel::default();
+    }
+
+    fn on_layer_end(&mut self) {
+        if let Some((order, layer_key, node_idx)) = self.current_layer.take() {
+            let model = std::mem::take(&mut self.current);
+
+            self.layers.push((order, layer_key, node_idx, model));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+
+    use decimal_percentage::Percentage;
+    use good_lp::{ProblemVariables, variable};
+    use rusty_money::{Money, iso::GBP};
+    use smallvec::{SmallVec, smallvec};
+    use tempfile::tempdir;
+    use testresult::TestResult;
+
+    use crate::{
+        discounts::SimpleDiscount,
+        graph::{EvaluationMode, OutputMode, PromotionGraph, PromotionGraphBuilder},
+        items::Item,
+        pricing::PriceFloor,
+        promotions::{
+            budget::PromotionBudget, promotion, qualification::Qualification,
+            types::DirectDiscountPromotion,
+        },
+        solvers::ilp::ILPSolver,
+        tags::string::StringTagCollection,
+    };
+
+    use super::*;
+
+    fn fruit_basket<'a>(
+        products: &mut SlotMap<ProductKey, Product<'a>>,
+    ) -> (ItemGroup<'a>, ProductKey) {
+        let apple = products.insert(Product {
+            name: "Apple (Gala)".to_string(),
+            tags: StringTagCollection::from_strs(&["fruit"]),
+            price: Money::from_minor(100, GBP),
+            floor: PriceFloor::default(),
+        });
+
+        let items: SmallVec<[Item<'_>; 10]> = smallvec![
+            Item::with_tags(
+                apple,
+                Money::from_minor(100, GBP),
+                StringTagCollection::from_strs(&["fruit"]),
+            )
+            .with_quantity(NonZeroU32::new(3).expect("non-zero quantity")),
+        ];
+
+        (ItemGroup::new(items, GBP), apple)
+    }
+
+    #[test]
+    fn sanitize_name_keeps_names_valid_in_both_formats() {
+        assert_eq!(sanitize_name("Apple (Gala)"), "Apple_Gala");
+        assert_eq!(sanitize_name("25% off"), "v_25_off");
+        assert_eq!(sanitize_name("--"), "v_");
+        assert_eq!(sanitize_name(&"x".repeat(300)).len(), MAX_NAME_LEN);
+    }
+
+    #[test]
+    fn render_number_avoids_negative_zero() {
+        assert_eq!(render_number(-0.0), "0");
+        assert_eq!(render_number(75.0), "75");
+        assert_eq!(render_number(-2.5), "-2.5");
+    }
+
+    #[test]
+    fn duplicate_names_are_suffixed() {
+        let mut renderer = LpRenderer::new(PathBuf::from("test"));
+        let mut pb = ProblemVariables::new();
+
+        let a = pb.add(variable().binary());
+        let b = pb.add(variable().binary());
+
+        renderer.on_auxiliary_variable(PromotionKey::default(), a, "flag", None, None);
+        renderer.on_auxiliary_variable(PromotionKey::default(), b, "flag", None, None);
+
+        assert_eq!(renderer.current.name(a), "PromotionKey_null_flag");
+        assert_eq!(renderer.current.name(b), "PromotionKey_null_flag_2");
+    }
+
+    #[test]
+    fn renders_rows_bounds_and_integrality() -> TestResult {
+        let mut renderer = LpRenderer::new(PathBuf::from("test"));
+        let mut pb = ProblemVariables::new();
+
+        let pick = pb.add(variable().binary());
+        let count = pb.add(variable().integer().min(0).max(4));
+        let saving = pb.add(variable().min(0).max(250));
+
+        renderer.on_presence_variable(0, pick, 100);
+        renderer.on_auxiliary_variable(PromotionKey::default(), count, "count", None, None);
+        renderer.on_auxiliary_variable(PromotionKey::default(), saving, "saving", None, None);
+        renderer.on_objective_term(pick, 100.0);
+        renderer.on_objective_term(saving, -1.0);
+        renderer.on_promotion_constraint(
+            PromotionKey::default(),
+            "cap",
+            &(Expression::from(saving) - count * 50 + 10),
+            "<=",
+            0.0,
+        );
+
+        for (var, definition) in pb.iter_variables_with_def() {
+            renderer.on_variable_definition(var, definition);
+        }
+
+        let lp = renderer.render_lp()?;
+
+        assert_eq!(
+            lp,
+            vec![
+                "\\ Lattice ILP formulation\n\
+                 Minimize\n \
+                 obj: + 100 full_i1 - 1 PromotionKey_null_saving\n\
+                 Subject To\n \
+                 PromotionKey_null_cap: - 50 PromotionKey_null_count + 1 PromotionKey_null_saving <= -10\n\
+                 Bounds\n \
+                 0 <= PromotionKey_null_count <= 4\n \
+                 0 <= PromotionKey_null_saving <= 250\n\
+                 Generals\n \
+                 PromotionKey_null_count\n\
+                 Binaries\n \
+                 full_i1\n\
+                 End\n"
+                    .to_string()
+            ]
+        );
+
+        let mps = renderer.render_mps()?;
+        let mps = mps.first().map_or("", String::as_str);
+
+        assert!(mps.contains(" L PromotionKey_null_cap\n"));
+        assert!(mps.contains(" MARKER 'MARKER' 'INTORG'\n full_i1 obj 100\n"));
+        assert!(mps.contains(
+            " PromotionKey_null_count PromotionKey_null_cap -50\n MARKER 'MARKER' 'INTEND'\n"
+        ));
+        assert!(mps.contains(" RHS PromotionKey_null_cap -10\n"));
+        assert!(mps.contains(" BV BND full_i1\n"));
+        assert!(
+            mps.contains(" LO BND PromotionKey_null_count 0\n UP BND PromotionKey_null_count 4\n")
+        );
+        assert!(mps.ends_with("ENDATA\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn captures_a_solve_with_metadata_names() -> TestResult {
+        let mut products = SlotMap::with_key();
+        let (item_group, _apple) = fruit_basket(&mut products);
+
+        let mut promotion_meta = SlotMap::with_key();
+        let promotion_key = promotion_meta.insert(PromotionMeta {
+            name: "25% off fruit".to_string(),
+            ..Default::default()
+        });
+
+        let promotions = [promotion(DirectDiscountPromotion::new(
+            promotion_key,
+            Qualification::match_any(StringTagCollection::from_strs(&["fruit"])),
+            SimpleDiscount::PercentageOff(Percentage::from(0.25)),
+            PromotionBudget::unlimited(),
+        ))];
+
+        let mut renderer = LpRenderer::new_with_metadata(
+            PathBuf::from("test"),
+            &item_group,
+            &products,
+            &promotion_meta,
+        );
+
+        ILPSolver::solve_with_observer(&promotions, &item_group, &mut renderer)?;
+
+        let lp = renderer.render_lp()?;
+        let lp = lp.first().map_or("", String::as_str);
+
+        // The line quantity becomes the exclusivity right-hand side and the bound of
+        // each (integer) unit count.
+        assert!(lp.contains(" obj: + 100 full_i1_Apple_Gala + 75 v_25_off_fruit_i1_Apple_Gala\n"));
+        assert!(lp.contains(
+            " excl_i1_Apple_Gala: + 1 full_i1_Apple_Gala + 1 v_25_off_fruit_i1_Apple_Gala = 3\n"
+        ));
+        assert!(lp.contains(" 0 <= full_i1_Apple_Gala <= 3\n"));
+        assert!(lp.contains("Generals\n full_i1_Apple_Gala v_25_off_fruit_i1_Apple_Gala\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn write_creates_lp_and_mps_files() -> TestResult {
+        let dir = tempdir()?;
+        let mut renderer = LpRenderer::new(dir.path().join("formulation"));
+        let mut pb = ProblemVariables::new();
+
+        let var = pb.add(variable().binary());
+
+        renderer.on_presence_variable(0, var, 100);
+        renderer.on_objective_term(var, 100.0);
+
+        let paths = renderer.write()?;
+
+        assert_eq!(
+            paths,
+            vec![
+                dir.path().join("formulation.lp"),
+                dir.path().join("formulation.mps")
+            ]
+        );
+        assert!(fs::read_to_string(dir.path().join("formulation.mps"))?.starts_with("* "));
+
+        Ok(())
+    }
+
+    #[test]
+    fn layers_are_written_to_separate_files() -> TestResult {
+        let dir = tempdir()?;
+        let mut renderer = LpRenderer::new(dir.path().join("basket"));
+        let mut pb = ProblemVariables::new();
+        let mut layers = SlotMap::<PromotionLayerKey, ()>::with_key();
+
+        let first = pb.add(variable().binary());
+        let second = pb.add(variable().binary());
+
+        renderer.on_layer_begin(layers.insert(()), NodeIndex::new(0));
+        renderer.on_presence_variable(0, first, 100);
+        renderer.on_layer_end();
+
+        // Layers with nothing to solve are skipped.
+        renderer.on_layer_begin(layers.insert(()), NodeIndex::new(1));
+        renderer.on_layer_end();
+
+        renderer.on_layer_begin(layers.insert(()), NodeIndex::new(2));
+        renderer.on_presence_variable(0, second, 80);
+        renderer.on_layer_end();
+
+        let paths = renderer.write()?;
+
+        assert_eq!(
+            paths,
+            vec![
+                dir.path().join("basket-layer-1.lp"),
+                dir.path().join("basket-layer-1.mps"),
+                dir.path().join("basket-layer-3.lp"),
+                dir.path().join("basket-layer-3.mps"),
+            ]
+        );
+        assert!(
+            fs::read_to_string(dir.path().join("basket-layer-3.lp"))?
+                .starts_with("\\ Lattice ILP formulation, layer 3 (graph node 2)\n")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn joint_layers_are_written_as_one_model() -> TestResult {
+        let dir = tempdir()?;
+        let mut products = SlotMap::with_key();
+        let (item_group, _apple) = fruit_basket(&mut products);
+
+        let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+        let fruit_off = |key, pct| {
+            promotion(DirectDiscountPromotion::new(
+                key,
+                Qualification::match_any(StringTagCollection::from_strs(&["fruit"])),
+                SimpleDiscount::PercentageOff(Percentage::from(pct)),
+                PromotionBudget::unlimited(),
+            ))
+        };
+
+        let mut builder = PromotionGraphBuilder::new();
+        let first = builder.add_layer(
+            "First",
+            [fruit_off(keys.insert(()), 0.1)],
+            OutputMode::PassThrough,
+        )?;
+        let second = builder.add_layer(
+            "Second",
+            [fruit_off(keys.insert(()), 0.25)],
+            OutputMode::PassThrough,
+        )?;
+
+        builder.set_root(first);
+        builder.connect_pass_through(first, second)?;
+
+        let graph =
+            PromotionGraph::from_builder(builder)?.with_evaluation_mode(EvaluationMode::Joint);
+
+        let mut renderer = LpRenderer::new(dir.path().join("basket"));
+
+        graph.evaluate_with_observer(&item_group, Some(&mut renderer))?;
+
+        let paths = renderer.write()?;
+
+        assert_eq!(
+            paths,
+            vec![dir.path().join("basket.lp"), dir.path().join("basket.mps")]
+        );
+
+        let lp = fs::read_to_string(dir.path().join("basket.lp"))?;
+
+        assert!(lp.starts_with("\\ Lattice ILP formulation, joint evaluation of 2 layers\n"));
+
+        // The second layer's rows are tied to the first layer's outcomes, whose
+        // costs are replaced by the prices they leave the second layer at.
+        assert!(lp.contains(" link_i1: - 1 full_i1 + 1 full_i1_2 + 1 PromotionKey_2v1_i1 = 0\n"));
+        assert!(lp.contains(
+            " link_i2: - 1 PromotionKey_1v1_i1 + 1 full_i2 + 1 PromotionKey_2v1_i2 = 0\n"
+        ));
+        assert!(lp.contains(
+            " obj: + 100 full_i1_2 + 90 full_i2 + 75 PromotionKey_2v1_i1 + 67 PromotionKey_2v1_i2\n"
+        ));
+
+        // The first layer's columns take the bounds reported after the last layer.
+        assert!(lp.contains(" 0 <= PromotionKey_1v1_i1 <= 3\n"));
+
+        Ok(())
+    }
+}
@@ -1,3 +1,4 @@
 //! ILP Renderers
 
+pub mod lp;
 pub mod typst;
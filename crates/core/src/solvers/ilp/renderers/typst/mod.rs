@@ -1423,6 +1423,7 @@ mod tests {
     use crate::{
         discounts::SimpleDiscount,
         items::{Item, groups::ItemGroup},
+        pricing::PriceFloor,
         products::{Product, ProductKey},
         promotions::{
             PromotionKey, PromotionMeta, budget::PromotionBudget, promotion,
@@ -1546,6 +1547,7 @@ mod tests {
             name: "Alpha".to_string(),
             tags: StringTagCollection::from_strs(&["tag"]),
             price: Money::from_minor(100, GBP),
+            floor: PriceFloor::default(),
         });
         let item_b = ProductKey::default();
 
@@ -1707,11 +1709,13 @@ mod tests {
             name: "Alpha".to_string(),
             tags: StringTagCollection::from_strs(&[]),
             price: Money::from_minor(100, GBP),
+            floor: PriceFloor::default(),
         });
         let item_b = products.insert(Product {
             name: "Beta".to_string(),
             tags: StringTagCollection::from_strs(&[]),
             price: Money::from_minor(200, GBP),
+            floor: PriceFloor::default(),
         });
 
         let items = SmallVec::from_vec(vec![
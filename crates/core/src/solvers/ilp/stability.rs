@@ -0,0 +1,405 @@
+//! Allocation Stability
+//!
+//! Re-pricing after a change can move a promotion onto different items for the
+//! same (or a marginally better) total, which looks to a customer watching the
+//! till display as if their discount jumped around. A stability preference keeps
+//! each unit on the promotion it had before unless moving saves more than a
+//! threshold.
+//!
+//! It is applied as an extra lexicographic pass: once the cheapest total is
+//! known, the model is solved again with the total allowed to rise by at most the
+//! threshold, maximising the number of units whose promotion is unchanged and
+//! then minimising the total among allocations keeping that many.
+
+use good_lp::{Constraint, Expression, ProblemVariables, Variable, variable};
+use rustc_hash::FxHashMap;
+use rusty_money::{Money, iso::Currency};
+use smallvec::SmallVec;
+
+use crate::{
+    items::groups::ItemGroup,
+    promotions::{PromotionKey, redemptions::PromotionRedemption},
+    solvers::{SolverError, ilp::promotions::PromotionInstances},
+};
+
+/// Which promotions took each item's units in an allocation already shown.
+///
+/// Items are identified by their index in the basket (or item group). Units of a
+/// shown line not taken by any promotion were at full price; lines added since
+/// have no previous allocation to keep.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Allocation {
+    lines: FxHashMap<usize, AllocatedLine>,
+}
+
+/// A shown item line and the units each promotion took from it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct AllocatedLine {
+    quantity: u32,
+    promotions: SmallVec<[(PromotionKey, u32); 2]>,
+}
+
+impl Allocation {
+    /// Collect the allocation `redemptions` made of the lines in `item_group`.
+    ///
+    /// Redemptions of items outside `item_group` are ignored.
+    pub fn new<'r, 'b: 'r>(
+        item_group: &ItemGroup<'_>,
+        redemptions: impl IntoIterator<Item = &'r PromotionRedemption<'b>>,
+    ) -> Self {
+        let mut lines: FxHashMap<usize, AllocatedLine> = item_group
+            .iter()
+            .enumerate()
+            .map(|(item_idx, item)| {
+                (
+                    item_idx,
+                    AllocatedLine {
+                        quantity: item.quantity(),
+                        promotions: SmallVec::new(),
+                    },
+                )
+            })
+            .collect();
+
+        for redemption in redemptions {
+            let Some(line) = lines.get_mut(&redemption.item_idx) else {
+                continue;
+            };
+
+            match line
+                .promotions
+                .iter_mut()
+                .find(|(promotion_key, _units)| *promotion_key == redemption.promotion_key)
+            {
+                Some((_promotion_key, units)) => {
+                    *units = units.saturating_add(redemption.quantity);
+                }
+                None => line
+                    .promotions
+                    .push((redemption.promotion_key, redemption.quantity)),
+            }
+        }
+
+        Self { lines }
+    }
+
+    /// Units of `item_idx` that `promotion` took.
+    #[must_use]
+    pub fn units(&self, item_idx: usize, promotion: PromotionKey) -> u32 {
+        self.lines
+            .get(&item_idx)
+            .and_then(|line| {
+                line.promotions
+                    .iter()
+                    .find(|(promotion_key, _units)| *promotion_key == promotion)
+            })
+            .map_or(0, |(_promotion_key, units)| *units)
+    }
+
+    /// Units the shown line `item_idx` had, or `None` if it was not shown.
+    #[must_use]
+    pub fn quantity(&self, item_idx: usize) -> Option<u32> {
+        self.lines.get(&item_idx).map(|line| line.quantity)
+    }
+
+    /// Whether no line was shown.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// Forget `item_idx`, moving later items down one index as a basket does.
+    pub fn remove_item(&mut self, item_idx: usize) {
+        self.lines = self
+            .lines
+            .drain()
+            .filter(|(idx, _line)| *idx != item_idx)
+            .map(|(idx, line)| (if idx > item_idx { idx - 1 } else { idx }, line))
+            .collect();
+    }
+}
+
+/// A preference for keeping a previous allocation when re-pricing.
+///
+/// The re-priced total may be up to `threshold` more than the cheapest total if
+/// that keeps more units on the promotions they had in `previous`. With a zero
+/// threshold the cheapest total is always found, and the previous allocation
+/// only decides between equally cheap ones.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stability {
+    previous: Allocation,
+    threshold: i64,
+}
+
+impl Stability {
+    /// Prefer `previous` unless changing it saves more than `threshold`.
+    #[must_use]
+    pub fn new(previous: Allocation, threshold: &Money<'_, Currency>) -> Self {
+        Self {
+            previous,
+            threshold: threshold.to_minor_units().max(0),
+        }
+    }
+
+    /// Replace the allocation to keep.
+    #[must_use]
+    pub fn with_previous(mut self, previous: Allocation) -> Self {
+        self.previous = previous;
+        self
+    }
+
+    /// The allocation to keep.
+    #[must_use]
+    pub fn previous(&self) -> &Allocation {
+        &self.previous
+    }
+
+    /// The allocation to keep, for updating as the basket changes.
+    pub(crate) fn previous_mut(&mut self) -> &mut Allocation {
+        &mut self.previous
+    }
+
+    /// Largest saving, in minor units, given up to keep the previous allocation.
+    #[must_use]
+    pub fn threshold(&self) -> i64 {
+        self.threshold
+    }
+}
+
+/// A stability preference applied to one model.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct StabilityScope<'s> {
+    pub stability: &'s Stability,
+
+    /// Basket index of each of the model's items, when they are not the same
+    pub basket_idxs: Option<&'s [usize]>,
+}
+
+impl<'s> StabilityScope<'s> {
+    /// Apply `stability` to a model over the whole basket.
+    pub fn new(stability: &'s Stability) -> Self {
+        Self {
+            stability,
+            basket_idxs: None,
+        }
+    }
+
+    /// The same preference for a model whose items have the given basket indexes.
+    pub fn with_basket_idxs<'t>(&self, basket_idxs: &'t [usize]) -> StabilityScope<'t>
+    where
+        's: 't,
+    {
+        StabilityScope {
+            stability: self.stability,
+            basket_idxs: Some(basket_idxs),
+        }
+    }
+
+    /// Basket index of the model's item `item_idx`.
+    pub fn basket_idx(&self, item_idx: usize) -> usize {
+        self.basket_idxs
+            .map_or(Some(item_idx), |basket_idxs| {
+                basket_idxs.get(item_idx).copied()
+            })
+            .unwrap_or(usize::MAX)
+    }
+
+    /// Basket indexes of a subset of the model's items.
+    pub fn subset(&self, item_idxs: &[usize]) -> SmallVec<[usize; 10]> {
+        item_idxs
+            .iter()
+            .map(|&item_idx| self.basket_idx(item_idx))
+            .collect()
+    }
+
+    /// Count the units that stay on the option they had in the previous allocation.
+    ///
+    /// For each shown item and option (full price, or a promotion in the model) a
+    /// kept variable is bounded by the units the option had before and the units it
+    /// has now, so maximising their sum counts the units left where they were. Units
+    /// the model's promotions did not take before were at full price as far as this
+    /// model is concerned.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SolverError`] if an item index is out of range.
+    pub fn kept_units(
+        &self,
+        pb: &mut ProblemVariables,
+        item_group: &ItemGroup<'_>,
+        item_presence: &[Variable],
+        promotion_instances: &PromotionInstances<'_>,
+    ) -> Result<(Expression, Vec<Constraint>), SolverError> {
+        let mut kept = Expression::default();
+        let mut constraints = Vec::new();
+
+        for (item_idx, z_i) in item_presence.iter().copied().enumerate() {
+            let quantity = item_group.get_item(item_idx)?.quantity();
+            let basket_idx = self.basket_idx(item_idx);
+
+            let Some(previous_quantity) = self.stability.previous.quantity(basket_idx) else {
+                continue;
+            };
+
+            let mut promoted = 0_u32;
+
+            for instance in promotion_instances.iter() {
+                let units = self
+                    .stability
+                    .previous
+                    .units(basket_idx, instance.promotion_key())
+                    .min(quantity);
+
+                if units == 0 {
+                    continue;
+                }
+
+                promoted = promoted.saturating_add(units);
+
+                let kept_var = pb.add(variable().min(0).max(units));
+
+                kept += kept_var;
+                constraints.push(
+                    Expression::from(kept_var)
+                        .leq(instance.add_item_presence_term(Expression::default(), item_idx)),
+                );
+            }
+
+            let full_price_units = previous_quantity.min(quantity).saturating_sub(promoted);
+
+            if full_price_units > 0 {
+                let kept_var = pb.add(variable().min(0).max(full_price_units));
+
+                kept += kept_var;
+                constraints.push(Expression::from(kept_var).leq(z_i));
+            }
+        }
+
+        Ok((kept, constraints))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+
+    use rusty_money::iso::GBP;
+    use slotmap::SlotMap;
+
+    use crate::{items::Item, products::ProductKey};
+
+    use super::*;
+
+    fn redemption(
+        promotion_key: PromotionKey,
+        item_idx: usize,
+        quantity: u32,
+    ) -> PromotionRedemption<'static> {
+        PromotionRedemption {
+            promotion_key,
+            item_idx,
+            redemption_idx: 0,
+            original_price: Money::from_minor(100, GBP),
+            final_price: Money::from_minor(50, GBP),
+            quantity,
+            funding: SmallVec::new(),
+            floor_limited: false,
+        }
+    }
+
+    fn item_group(quantities: &[u32]) -> ItemGroup<'static> {
+        ItemGroup::new(
+            quantities
+                .iter()
+                .map(|&quantity| {
+                    Item::new(ProductKey::default(), Money::from_minor(100, GBP))
+                        .with_quantity(NonZeroU32::new(quantity).expect("non-zero quantity"))
+                })
+                .collect(),
+            GBP,
+        )
+    }
+
+    #[test]
+    fn allocation_sums_units_per_item_and_promotion() {
+        let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+        let (a, b) = (keys.insert(()), keys.insert(()));
+
+        let allocation = Allocation::new(
+            &item_group(&[4, 1, 1]),
+            &[
+                redemption(a, 0, 1),
+                redemption(a, 0, 2),
+                redemption(b, 0, 1),
+                redemption(b, 2, 1),
+            ],
+        );
+
+        assert_eq!(allocation.units(0, a), 3);
+        assert_eq!(allocation.units(0, b), 1);
+        assert_eq!(allocation.units(1, a), 0);
+        assert_eq!(allocation.units(2, b), 1);
+    }
+
+    #[test]
+    fn allocation_records_every_shown_line() {
+        let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+        let a = keys.insert(());
+
+        let allocation = Allocation::new(
+            &item_group(&[1, 3]),
+            &[redemption(a, 0, 1), redemption(a, 5, 1)],
+        );
+
+        assert_eq!(allocation.quantity(0), Some(1));
+        assert_eq!(
+            allocation.quantity(1),
+            Some(3),
+            "full price lines are shown"
+        );
+        assert_eq!(allocation.quantity(2), None, "later lines were not shown");
+        assert_eq!(allocation.units(5, a), 0, "unknown lines are ignored");
+    }
+
+    #[test]
+    fn removing_an_item_shifts_later_items_down() {
+        let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+        let a = keys.insert(());
+
+        let mut allocation = Allocation::new(
+            &item_group(&[1, 2, 3]),
+            &[
+                redemption(a, 0, 1),
+                redemption(a, 1, 2),
+                redemption(a, 2, 3),
+            ],
+        );
+
+        allocation.remove_item(1);
+
+        assert_eq!(allocation.units(0, a), 1);
+        assert_eq!(allocation.units(1, a), 3);
+        assert_eq!(allocation.quantity(2), None);
+    }
+
+    #[test]
+    fn negative_thresholds_are_treated_as_zero() {
+        let stability = Stability::new(Allocation::default(), &Money::from_minor(-100, GBP));
+
+        assert_eq!(stability.threshold(), 0);
+    }
+
+    #[test]
+    fn scopes_map_model_items_to_basket_items() {
+        let stability = Stability::new(Allocation::default(), &Money::from_minor(0, GBP));
+        let scope = StabilityScope::new(&stability);
+
+        assert_eq!(scope.basket_idx(3), 3);
+
+        let basket_idxs = [4, 7, 9];
+        let component = scope.with_basket_idxs(&basket_idxs);
+
+        assert_eq!(component.basket_idx(1), 7);
+        assert_eq!(component.subset(&[0, 2]).as_slice(), &[4, 9]);
+    }
+}
@@ -3,12 +3,16 @@
 use std::fmt;
 
 use good_lp::{Expression, ProblemVariables, Variable};
+use rustc_hash::FxHashMap;
+use rusty_money::iso::Currency;
 use smallvec::SmallVec;
 
 #[cfg(test)]
 use crate::solvers::ilp::observer::NoopObserver;
 use crate::{
+    discounts::rounding::{RoundingPolicies, RoundingPolicy},
     items::groups::ItemGroup,
+    promotions::PromotionKey,
     solvers::{
         SolverError,
         ilp::{build_presence_variables_and_objective, observer::ILPObserver},
@@ -47,6 +51,9 @@ pub struct ILPState {
     cost: Expression,
     item_presence: SmallVec<[Variable; 10]>,
     constraints: Vec<ILPConstraint>,
+    symmetry_breaking: bool,
+    rounding: RoundingPolicies,
+    floor_excess: FxHashMap<PromotionKey, Expression>,
 }
 
 impl fmt::Debug for ILPState {
@@ -62,6 +69,12 @@ impl fmt::Debug for ILPState {
                 "constraints",
                 &format!("[{} constraints]", self.constraints.len()),
             )
+            .field("symmetry_breaking", &self.symmetry_breaking)
+            .field("rounding", &self.rounding)
+            .field(
+                "floor_excess",
+                &format!("[{} promotions]", self.floor_excess.len()),
+            )
             .finish()
     }
 }
@@ -75,6 +88,26 @@ impl ILPState {
             cost,
             item_presence: SmallVec::new(),
             constraints: Vec::new(),
+            symmetry_breaking: true,
+            rounding: RoundingPolicies::default(),
+            floor_excess: FxHashMap::default(),
+        }
+    }
+
+    /// Create an empty ILP state with no presence variables.
+    ///
+    /// Used by formulations that span several item groups (for example a joint
+    /// multi-layer graph solve), where each group's presence variables are added
+    /// separately with [`ILPState::add_presence_variables`].
+    pub(crate) fn empty() -> Self {
+        Self {
+            pb: ProblemVariables::new(),
+            cost: Expression::default(),
+            item_presence: SmallVec::new(),
+            constraints: Vec::new(),
+            symmetry_breaking: true,
+            rounding: RoundingPolicies::default(),
+            floor_excess: FxHashMap::default(),
         }
     }
 
@@ -119,9 +152,94 @@ impl ILPState {
             cost,
             item_presence,
             constraints: Vec::new(),
+            symmetry_breaking: true,
+            rounding: RoundingPolicies::default(),
+            floor_excess: FxHashMap::default(),
         })
     }
 
+    /// Add full-price presence variables for another item group to this state.
+    ///
+    /// The presence costs are added to the objective, and the new variables are
+    /// returned rather than tracked in the state's own presence list.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SolverError`] if any item's price cannot be represented exactly as
+    /// a solver coefficient.
+    pub(crate) fn add_presence_variables<O: ILPObserver + ?Sized>(
+        &mut self,
+        item_group: &ItemGroup<'_>,
+        observer: &mut O,
+    ) -> Result<SmallVec<[Variable; 10]>, SolverError> {
+        let (presence, cost) =
+            build_presence_variables_and_objective(item_group, &mut self.pb, observer)?;
+
+        self.cost += cost;
+
+        Ok(presence)
+    }
+
+    /// Take the objective accumulated so far, leaving an empty objective behind.
+    pub(crate) fn take_objective(&mut self) -> Expression {
+        std::mem::take(&mut self.cost)
+    }
+
+    /// Disallow constraints that assume identical items are interchangeable.
+    ///
+    /// Joint multi-layer formulations include several mutually exclusive copies
+    /// of the same item, so promotions must not force "identical" copies to be
+    /// selected in a fixed order.
+    pub(crate) fn disable_symmetry_breaking(&mut self) {
+        self.symmetry_breaking = false;
+    }
+
+    /// Whether promotions may add symmetry-breaking constraints between identical items.
+    ///
+    /// Symmetry breaking assumes every item in the group is available to the
+    /// solver. When this returns `false`, only constraints that hold for any
+    /// subset of available items should be emitted.
+    pub fn allows_symmetry_breaking(&self) -> bool {
+        self.symmetry_breaking
+    }
+
+    /// Round percentage discounts with `rounding` unless a promotion has its own
+    /// policies.
+    pub(crate) fn set_rounding(&mut self, rounding: &RoundingPolicies) {
+        self.rounding.clone_from(rounding);
+    }
+
+    /// The rounding policy for a promotion's percentage discounts in `currency`,
+    /// preferring the promotion's `own` policies to the state's.
+    pub fn rounding_for(
+        &self,
+        own: Option<&RoundingPolicies>,
+        currency: &Currency,
+    ) -> RoundingPolicy {
+        RoundingPolicies::resolve(own, &self.rounding, currency)
+    }
+
+    /// Record the discount item price floors take back from `promotion_key`.
+    pub(crate) fn set_floor_excess(&mut self, promotion_key: PromotionKey, excess: Expression) {
+        self.floor_excess.insert(promotion_key, excess);
+    }
+
+    /// Discount item price floors take back from `promotion_key`, in minor units.
+    ///
+    /// Monetary budgets subtract this from the promotion's discount, so they are
+    /// only charged what its items can actually save.
+    pub fn floor_excess(&self, promotion_key: PromotionKey) -> Expression {
+        self.floor_excess
+            .get(&promotion_key)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Number of constraints recorded so far.
+    pub(crate) fn constraint_count(&self) -> usize {
+        self.constraints.len()
+    }
+
     /// Extract the problem variables, cost expression, item presence variables,
     /// and all recorded constraints.
     pub(crate) fn into_parts_with_constraints(
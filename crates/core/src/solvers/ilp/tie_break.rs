@@ -0,0 +1,186 @@
+//! Tie-Break Policies
+//!
+//! Several allocations can give the same basket total. A tie-break policy says
+//! which of them to prefer, as an ordered list of rules. Each rule is applied as a
+//! lexicographic pass after the cheapest total is known: the model is solved again
+//! on the optimum face of the passes before it, optimising the rule's measure, and
+//! the best value found is then pinned for the rules after it. Promotions' own
+//! tie-break terms decide whatever ties remain.
+
+use good_lp::Expression;
+
+use crate::{
+    items::groups::ItemGroup,
+    solvers::ilp::promotions::{PromotionInstance, PromotionInstances},
+};
+
+/// A preference between allocations that give the same total.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TieBreakRule {
+    /// Redeem as many units as possible through supplier-funded promotions
+    PreferSupplierFunded,
+
+    /// Redeem units through promotions with higher priority numbers
+    ///
+    /// Each unit a promotion takes scores the promotion's priority, and the
+    /// allocation with the highest score wins.
+    PreferHigherPriority,
+
+    /// Form as few redemptions as possible
+    ///
+    /// Promotions that cannot count their redemptions are left out of the count.
+    PreferFewerRedemptions,
+}
+
+/// Number of distinct rules, and so the most a policy can hold.
+const RULE_COUNT: usize = 3;
+
+impl TieBreakRule {
+    /// Add the rule's measure for `promotion_instances`, to be minimised, to `expr`.
+    pub(crate) fn add_objective_terms(
+        self,
+        expr: Expression,
+        promotion_instances: &PromotionInstances<'_>,
+        item_group: &ItemGroup<'_>,
+    ) -> Expression {
+        let mut updated_expr = expr;
+
+        for instance in promotion_instances.iter() {
+            match self {
+                Self::PreferSupplierFunded => {
+                    if instance.funding().is_supplier_funded() {
+                        updated_expr -= units_expr(instance, item_group);
+                    }
+                }
+                Self::PreferHigherPriority => {
+                    let priority = instance.priority();
+
+                    if priority != 0 {
+                        updated_expr -= units_expr(instance, item_group) * f64::from(priority);
+                    }
+                }
+                Self::PreferFewerRedemptions => {
+                    if let Some(count) = instance.redemption_count_expr() {
+                        updated_expr += count;
+                    }
+                }
+            }
+        }
+
+        updated_expr
+    }
+}
+
+/// Units of every item in `item_group` that `instance` takes.
+fn units_expr(instance: &PromotionInstance<'_>, item_group: &ItemGroup<'_>) -> Expression {
+    (0..item_group.len()).fold(Expression::default(), |expr, item_idx| {
+        instance.add_item_presence_term(expr, item_idx)
+    })
+}
+
+/// Ordered tie-break rules, applied in turn when allocations give the same total.
+///
+/// The default policy has no rules, leaving ties to the promotions' own tie-break
+/// terms.
+///
+/// ```
+/// use lattice::solvers::ilp::{TieBreakPolicy, TieBreakRule};
+///
+/// let policy = TieBreakPolicy::default()
+///     .then(TieBreakRule::PreferSupplierFunded)
+///     .then(TieBreakRule::PreferFewerRedemptions);
+///
+/// assert_eq!(
+///     policy.rules().collect::<Vec<_>>(),
+///     [TieBreakRule::PreferSupplierFunded, TieBreakRule::PreferFewerRedemptions]
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct TieBreakPolicy {
+    rules: [Option<TieBreakRule>; RULE_COUNT],
+}
+
+impl TieBreakPolicy {
+    /// Apply `rule` after the rules already in the policy.
+    ///
+    /// A rule already in the policy keeps its place, since applying it again
+    /// cannot break any more ties.
+    #[must_use]
+    pub fn then(mut self, rule: TieBreakRule) -> Self {
+        if !self.rules.contains(&Some(rule))
+            && let Some(slot) = self.rules.iter_mut().find(|slot| slot.is_none())
+        {
+            *slot = Some(rule);
+        }
+
+        self
+    }
+
+    /// Return the rules in the order they are applied
+    pub fn rules(&self) -> impl Iterator<Item = TieBreakRule> + '_ {
+        self.rules.iter().flatten().copied()
+    }
+
+    /// Check whether the policy has no rules
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.rules.iter().all(Option::is_none)
+    }
+}
+
+impl FromIterator<TieBreakRule> for TieBreakPolicy {
+    fn from_iter<I: IntoIterator<Item = TieBreakRule>>(rules: I) -> Self {
+        rules.into_iter().fold(Self::default(), Self::then)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn policies_apply_rules_in_the_order_given() {
+        let policy = TieBreakPolicy::default()
+            .then(TieBreakRule::PreferFewerRedemptions)
+            .then(TieBreakRule::PreferHigherPriority);
+
+        assert_eq!(
+            policy.rules().collect::<Vec<_>>(),
+            [
+                TieBreakRule::PreferFewerRedemptions,
+                TieBreakRule::PreferHigherPriority
+            ]
+        );
+    }
+
+    #[test]
+    fn repeated_rules_keep_their_first_place() {
+        let policy: TieBreakPolicy = [
+            TieBreakRule::PreferHigherPriority,
+            TieBreakRule::PreferSupplierFunded,
+            TieBreakRule::PreferHigherPriority,
+            TieBreakRule::PreferFewerRedemptions,
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(
+            policy.rules().collect::<Vec<_>>(),
+            [
+                TieBreakRule::PreferHigherPriority,
+                TieBreakRule::PreferSupplierFunded,
+                TieBreakRule::PreferFewerRedemptions
+            ]
+        );
+    }
+
+    #[test]
+    fn default_policy_is_empty() {
+        assert!(TieBreakPolicy::default().is_empty());
+        assert!(
+            !TieBreakPolicy::default()
+                .then(TieBreakRule::PreferSupplierFunded)
+                .is_empty()
+        );
+    }
+}
@@ -1,6 +1,6 @@
 //! Solvers for Promotions
 
-use good_lp::ResolutionError;
+use good_lp::{ResolutionError, SolutionStatus};
 use rusty_money::{Money, MoneyError, iso::Currency};
 use smallvec::SmallVec;
 use thiserror::Error;
@@ -8,9 +8,13 @@ use thiserror::Error;
 use crate::{
     discounts::DiscountError,
     items::groups::{ItemGroup, ItemGroupError},
-    promotions::{Promotion, redemptions::PromotionRedemption},
+    promotions::{
+        Promotion, PromotionKey, budget::BudgetPoolKey, redemptions::PromotionRedemption,
+    },
 };
 
+pub mod exhaustive;
+pub mod greedy;
 pub mod ilp;
 
 /// Solver Errors
@@ -38,6 +42,63 @@ pub enum SolverError {
     #[error(transparent)]
     ResolutionError(#[from] ResolutionError),
 
+    /// A promotion draws on a budget pool that was not provided to the solver.
+    #[error("promotion {promotion_key:?} draws on unknown budget pool {pool_key:?}")]
+    UnknownBudgetPool {
+        /// Key of the promotion referencing the pool
+        promotion_key: PromotionKey,
+
+        /// Key of the missing pool
+        pool_key: BudgetPoolKey,
+    },
+
+    /// A promotion draws on a budget pool but cannot express its pool usage.
+    #[error("promotion {0:?} cannot draw on shared budget pools")]
+    BudgetPoolUnsupported(PromotionKey),
+
+    /// An item has a price floor, but the promotion can neither describe its
+    /// per-item prices nor express its discount value, so it cannot be capped.
+    #[error("promotion {0:?} cannot be capped at item price floors")]
+    PriceFloorUnsupported(PromotionKey),
+
+    /// An item has a price floor, which only the ILP solver enforces.
+    #[error("item {0} has a price floor, which the {1} solver cannot enforce")]
+    PriceFloorsUnenforced(usize, &'static str),
+
+    /// A least-generous objective entitles the customer to a promotion's
+    /// redemptions, but the promotion cannot count them.
+    #[error("promotion {0:?} cannot count its redemptions for a least-generous objective")]
+    RedemptionCountUnsupported(PromotionKey),
+
+    /// A promotion has no greedy heuristic, so the greedy solver cannot price it.
+    #[error("promotion {0:?} cannot be priced by the greedy solver")]
+    GreedyUnsupported(PromotionKey),
+
+    /// A promotion cannot be enumerated, so the exhaustive solver cannot price it.
+    #[error("promotion {0:?} cannot be priced by the exhaustive solver")]
+    ExhaustiveUnsupported(PromotionKey),
+
+    /// The basket is too large to enumerate every allocation of its units.
+    #[error("basket has {units} units, more than the exhaustive solver's limit of {limit}")]
+    TooManyUnits {
+        /// Units in the basket, counting each unit of a quantity line
+        units: usize,
+
+        /// Most units the solver accepts
+        limit: usize,
+    },
+
+    /// The time limit passed before the backend found any feasible solution.
+    ///
+    /// The ILP solver handles this itself by falling back to full-price pricing,
+    /// so it is only seen by code driving a backend directly.
+    #[error("time limit reached before a feasible solution was found")]
+    TimeLimitReached,
+
+    /// A worker thread for a time-limited solve could not be started.
+    #[error("failed to start solver worker thread: {0}")]
+    WorkerThread(#[source] std::io::Error),
+
     /// Internal solver invariant was violated (this is a bug).
     #[error("solver invariant violated: {message}")]
     InvariantViolation {
@@ -52,7 +113,9 @@ pub struct SolverResult<'a> {
     /// Indexes of item group entries that were affected by promotions
     pub affected_items: SmallVec<[usize; 10]>,
 
-    /// Indexes of item group entries that were not affected by promotions
+    /// Indexes of item group entries with units that were not affected by promotions
+    ///
+    /// A quantity line whose units were only partially redeemed appears in both lists.
     pub unaffected_items: SmallVec<[usize; 10]>,
 
     /// Total cost of the items after applying promotions
@@ -60,6 +123,54 @@ pub struct SolverResult<'a> {
 
     /// Details of each promotion redemptions (item, bundle, original/final price)
     pub promotion_redemptions: SmallVec<[PromotionRedemption<'a>; 10]>,
+
+    /// Whether the allocation is proven optimal, cut short by a time limit, or heuristic
+    pub quality: SolutionQuality,
+}
+
+/// How an allocation relates to the optimum when a solve has a time limit.
+///
+/// Ordered from best to worst, so combining the results of several solves is a
+/// matter of taking the maximum.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SolutionQuality {
+    /// The allocation is proven optimal.
+    #[default]
+    Optimal,
+
+    /// The time limit passed; this is the best feasible allocation found so far.
+    TimeLimited,
+
+    /// The allocation was found by a heuristic, with no bound on how far it is
+    /// from the optimum.
+    ///
+    /// The ILP solver falls back to the greedy heuristic when the time limit
+    /// passes before any allocation was found, or when the backend wrongly
+    /// reports the model infeasible.
+    Heuristic,
+
+    /// No allocation was found (the time limit passed, or the backend wrongly
+    /// reported the model infeasible) and the greedy heuristic could not stand
+    /// in, so the items are priced at full price.
+    Fallback,
+}
+
+impl SolutionQuality {
+    /// Whether the allocation is proven optimal.
+    pub fn is_optimal(self) -> bool {
+        self == Self::Optimal
+    }
+}
+
+impl From<SolutionStatus> for SolutionQuality {
+    fn from(status: SolutionStatus) -> Self {
+        match status {
+            // Objectives are integral, so a solve stopped within the absolute gap
+            // tolerance has already found the optimum.
+            SolutionStatus::Optimal | SolutionStatus::GapLimit => Self::Optimal,
+            SolutionStatus::TimeLimit => Self::TimeLimited,
+        }
+    }
 }
 
 /// Trait for solving promotion problems on a set of items
@@ -5,6 +5,7 @@ use slotmap::SlotMap;
 
 use crate::{
     promotions::{PromotionSlotKey, qualification::Qualification, types::MixAndMatchSlot},
+    solvers::ilp::SolverBackend,
     tags::string::StringTagCollection,
 };
 
@@ -22,6 +23,33 @@ pub struct ExampleBasketArgs {
     /// Output file path
     #[clap(short, long)]
     pub out: Option<String>,
+
+    /// File name (without extension) for LP and MPS exports of the solved models
+    #[clap(short, long, conflicts_with = "out")]
+    pub export: Option<String>,
+
+    /// MILP backend used to solve the promotions
+    #[clap(short, long, default_value_t, value_parser = parse_solver_backend)]
+    pub backend: SolverBackend,
+
+    /// Time limit for the whole evaluation, in milliseconds
+    #[clap(short, long)]
+    pub time_limit: Option<u64>,
+}
+
+/// Parse a compiled-in solver backend by name.
+fn parse_solver_backend(name: &str) -> Result<SolverBackend, String> {
+    SolverBackend::from_name(name).ok_or_else(|| {
+        let available: Vec<&str> = SolverBackend::AVAILABLE
+            .iter()
+            .map(|backend| backend.name())
+            .collect();
+
+        format!(
+            "unknown solver backend `{name}` (available: {})",
+            available.join(", ")
+        )
+    })
 }
 
 /// Create a new promotion slot with the given tags, minimum and maximum values.
@@ -0,0 +1,179 @@
+//! Integration tests for allocation stability
+//!
+//! Re-pricing with a stability preference keeps the previous allocation unless
+//! changing it saves more than the threshold.
+
+use rusty_money::{Money, iso::GBP};
+use testresult::TestResult;
+
+use lattice::{
+    fixtures::Fixture,
+    graph::LayeredSolverResult,
+    items::groups::ItemGroup,
+    promotions::{PromotionKey, budget::BudgetPools},
+    solvers::ilp::{Allocation, ILPSolver, NoopObserver, SolverOptions, Stability},
+};
+
+/// Promotion taking each basket line, in basket order.
+fn line_promotions(result: &LayeredSolverResult<'_>, lines: usize) -> Vec<Option<PromotionKey>> {
+    (0..lines)
+        .map(|item_idx| {
+            result
+                .item_redemptions
+                .get(&item_idx)
+                .and_then(|redemptions| redemptions.first())
+                .map(|redemption| redemption.promotion_key)
+        })
+        .collect()
+}
+
+/// Price the "complex" basket one item at a time with the given stability threshold.
+fn scan_complex_basket(threshold: i64) -> TestResult<Vec<(i64, Vec<Option<PromotionKey>>)>> {
+    let fixture = Fixture::from_set("complex")?;
+    let graph = fixture.graph()?;
+    let mut session = graph
+        .session(fixture.basket(Some(0))?)
+        .with_stability(&Money::from_minor(threshold, GBP));
+
+    let mut totals = Vec::new();
+
+    for item in fixture.items() {
+        session.add_item(item.clone())?;
+
+        let result = session.evaluate()?;
+
+        totals.push((
+            result.total.to_minor_units(),
+            line_promotions(&result, session.basket().len()),
+        ));
+    }
+
+    Ok(totals)
+}
+
+#[test]
+fn stability_keeps_a_bundle_when_reshuffling_saves_less_than_the_threshold() -> TestResult {
+    let fixture = Fixture::from_set("complex")?;
+    let three_for_two = fixture.promotion("haircare-3-for-2")?.key();
+
+    let stable = scan_complex_basket(100)?;
+    let (total, promotions) = stable.last().ok_or("no evaluations")?;
+
+    // Moving the hair mask into the bundle saves 55p, under the £1 threshold, so
+    // the body wash keeps its place in the bundle.
+    assert_eq!(*total, 1445);
+    assert_eq!(promotions.get(3), Some(&Some(three_for_two)));
+    assert_ne!(promotions.get(4), Some(&Some(three_for_two)));
+
+    // Forming the bundle in the first place saves £1.27, so it still happens.
+    assert_eq!(stable.get(3).map(|(total, _)| *total), Some(935));
+
+    Ok(())
+}
+
+#[test]
+fn stability_gives_way_when_reshuffling_saves_more_than_the_threshold() -> TestResult {
+    let fixture = Fixture::from_set("complex")?;
+    let graph = fixture.graph()?;
+
+    let stable = scan_complex_basket(50)?;
+
+    let cold = graph.evaluate(&fixture.item_group()?)?;
+
+    assert_eq!(
+        stable.last().map(|(total, _)| *total),
+        Some(cold.total.to_minor_units())
+    );
+    assert_eq!(cold.total.to_minor_units(), 1390);
+
+    Ok(())
+}
+
+#[test]
+fn zero_threshold_never_changes_the_total() -> TestResult {
+    for set in ["complex", "layered", "comprehensive", "mix-and-match"] {
+        let fixture = Fixture::from_set(set)?;
+        let graph = fixture.graph()?;
+        let mut session = graph
+            .session(fixture.basket(Some(0))?)
+            .with_context(fixture.context().clone())
+            .with_stability(&Money::from_minor(0, GBP));
+
+        for (n, item) in fixture.items().iter().enumerate() {
+            session.add_item(item.clone())?;
+
+            let basket = fixture.basket(Some(n + 1))?;
+            let cold = graph.evaluate_with_context(&ItemGroup::from(&basket), fixture.context())?;
+
+            assert_eq!(
+                session.evaluate()?.total,
+                cold.total,
+                "{set}: {} items",
+                n + 1
+            );
+        }
+
+        session.remove_item(0)?;
+
+        let item_group = ItemGroup::new(session.basket().iter().cloned().collect(), GBP);
+        let cold = graph.evaluate_with_context(&item_group, fixture.context())?;
+
+        assert_eq!(
+            session.evaluate()?.total,
+            cold.total,
+            "{set}: removed first item"
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn flat_solves_keep_a_previous_allocation_within_the_threshold() -> TestResult {
+    let fixture = Fixture::from_set("complex")?;
+    let three_for_two = fixture.promotion("haircare-3-for-2")?.key();
+
+    // The allocation shown for the first four items: shampoo, conditioner and body
+    // wash in the 3-for-2, travel shower gel on 15% off.
+    let four_item_basket = fixture.basket(Some(4))?;
+    let four_items = ItemGroup::from(&four_item_basket);
+    let shown = ILPSolver::solve_with_options(
+        fixture.promotions(),
+        &four_items,
+        &mut BudgetPools::default(),
+        &mut NoopObserver,
+        &SolverOptions::default(),
+    )?;
+
+    let previous = Allocation::new(&four_items, &shown.promotion_redemptions);
+
+    assert_eq!(previous.units(3, three_for_two), 1);
+
+    let five_items = fixture.item_group()?;
+
+    let stable = ILPSolver::solve_with_stability(
+        fixture.promotions(),
+        &five_items,
+        &mut BudgetPools::default(),
+        &SolverOptions::default(),
+        &Stability::new(previous.clone(), &Money::from_minor(100, GBP)),
+    )?;
+
+    assert_eq!(stable.total.to_minor_units(), 1445);
+    assert_eq!(
+        Allocation::new(&five_items, &stable.promotion_redemptions).units(3, three_for_two),
+        1
+    );
+
+    let reshuffled = ILPSolver::solve_with_stability(
+        fixture.promotions(),
+        &five_items,
+        &mut BudgetPools::default(),
+        &SolverOptions::default(),
+        &Stability::new(previous, &Money::from_minor(50, GBP)),
+    )?;
+
+    assert_eq!(reshuffled.total.to_minor_units(), 1390);
+
+    Ok(())
+}
@@ -0,0 +1,194 @@
+//! Integration tests for marginal "add to basket" estimates
+//!
+//! Estimating many candidates at once must give the same totals as solving
+//! the basket again with each candidate added.
+
+use decimal_percentage::Percentage;
+use rusty_money::{Money, iso::GBP};
+use slotmap::SlotMap;
+use testresult::TestResult;
+
+use lattice::{
+    discounts::SimpleDiscount,
+    fixtures::Fixture,
+    graph::{EvaluationMode, PromotionGraph},
+    items::{Item, groups::ItemGroup},
+    products::ProductKey,
+    promotions::{
+        PromotionKey,
+        budget::PromotionBudget,
+        promotion,
+        qualification::Qualification,
+        types::{
+            DirectDiscountPromotion, ThresholdDiscount, ThresholdTier, TierThreshold,
+            TieredThresholdPromotion,
+        },
+    },
+    tags::string::StringTagCollection,
+};
+
+fn item<'a>(price: i64, tag: &str) -> Item<'a> {
+    Item::with_tags(
+        ProductKey::default(),
+        Money::from_minor(price, GBP),
+        StringTagCollection::from_strs(&[tag]),
+    )
+}
+
+fn assert_matches_full_solves<'a>(
+    graph: &PromotionGraph<'_>,
+    item_group: &ItemGroup<'a>,
+    candidates: &[Item<'a>],
+) -> TestResult {
+    let base = graph.evaluate(item_group)?.total;
+    let estimates = graph.estimate_additions(item_group, candidates)?;
+
+    assert_eq!(
+        estimates.len(),
+        candidates.len(),
+        "one estimate per candidate"
+    );
+
+    for (candidate, estimate) in candidates.iter().zip(&estimates) {
+        let items = item_group.iter().chain([candidate]).cloned().collect();
+        let with_candidate =
+            ItemGroup::new(items, item_group.currency()).with_context(item_group.context().clone());
+
+        let expected = graph.evaluate(&with_candidate)?.total;
+
+        assert_eq!(estimate.total, expected, "total for {candidate:?}");
+        assert_eq!(
+            estimate.marginal,
+            expected.sub(base)?,
+            "marginal for {candidate:?}"
+        );
+        assert_eq!(
+            estimate.savings,
+            candidate.line_price()?.sub(estimate.marginal)?,
+            "savings for {candidate:?}"
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn estimates_match_full_solves_for_fixture_catalogues() -> TestResult {
+    for set in ["layered", "complex", "comprehensive", "coupons"] {
+        let fixture = Fixture::from_set(set)?;
+        let item_group = fixture.item_group()?;
+
+        // Every fixture item doubles as a catalogue candidate.
+        let candidates = fixture.items();
+
+        for mode in [EvaluationMode::Greedy, EvaluationMode::Joint] {
+            let graph = fixture.graph()?.clone().with_evaluation_mode(mode);
+
+            assert_matches_full_solves(&graph, &item_group, candidates)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn unqualified_candidate_costs_its_price() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+
+    let graph = PromotionGraph::single_layer([promotion(DirectDiscountPromotion::new(
+        keys.insert(()),
+        Qualification::match_any(StringTagCollection::from_strs(&["snack"])),
+        SimpleDiscount::PercentageOff(Percentage::from(0.50)),
+        PromotionBudget::unlimited(),
+    ))])?;
+
+    let item_group = ItemGroup::new([item(300, "snack")].into_iter().collect(), GBP);
+    let candidates = [item(1500, "wine"), item(200, "snack")];
+
+    let estimates = graph.estimate_additions(&item_group, &candidates)?;
+
+    let marginals: Vec<i64> = estimates
+        .iter()
+        .map(|estimate| estimate.marginal.to_minor_units())
+        .collect();
+
+    let savings: Vec<i64> = estimates
+        .iter()
+        .map(|estimate| estimate.savings.to_minor_units())
+        .collect();
+
+    assert_eq!(marginals, [1500, 100]);
+    assert_eq!(savings, [0, 100]);
+
+    Ok(())
+}
+
+#[test]
+fn candidate_crossing_a_threshold_discounts_the_whole_basket() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+
+    // 20% off all wine once £20 of wine is in the basket.
+    let graph = PromotionGraph::single_layer([promotion(TieredThresholdPromotion::new(
+        keys.insert(()),
+        vec![ThresholdTier::new(
+            TierThreshold::with_monetary_threshold(Money::from_minor(2000, GBP)),
+            None,
+            Qualification::match_any(StringTagCollection::from_strs(&["wine"])),
+            Qualification::match_any(StringTagCollection::from_strs(&["wine"])),
+            ThresholdDiscount::PercentEachItem(Percentage::from(0.20)),
+        )],
+        PromotionBudget::unlimited(),
+    ))])?;
+
+    let item_group = ItemGroup::new([item(1500, "wine")].into_iter().collect(), GBP);
+    let candidates = [item(500, "wine"), item(500, "wine"), item(500, "cheese")];
+
+    for mode in [EvaluationMode::Greedy, EvaluationMode::Joint] {
+        let graph = graph.clone().with_evaluation_mode(mode);
+        let estimates = graph.estimate_additions(&item_group, &candidates)?;
+
+        let marginals: Vec<i64> = estimates
+            .iter()
+            .map(|estimate| estimate.marginal.to_minor_units())
+            .collect();
+
+        // £15 -> £16 for both wines: a £5 bottle costs £1 more.
+        assert_eq!(marginals, [100, 100, 500], "{mode:?}");
+
+        assert_matches_full_solves(&graph, &item_group, &candidates)?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn candidates_with_different_floors_are_solved_apart() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+
+    let graph = PromotionGraph::single_layer([promotion(DirectDiscountPromotion::new(
+        keys.insert(()),
+        Qualification::match_any(StringTagCollection::from_strs(&["snack"])),
+        SimpleDiscount::PercentageOff(Percentage::from(0.50)),
+        PromotionBudget::unlimited(),
+    ))])?;
+
+    let item_group = ItemGroup::new([item(300, "snack")].into_iter().collect(), GBP);
+    let candidates = [
+        item(200, "snack"),
+        item(200, "snack").with_price_floor(Money::from_minor(150, GBP)),
+    ];
+
+    let estimates = graph.estimate_additions(&item_group, &candidates)?;
+
+    let marginals: Vec<i64> = estimates
+        .iter()
+        .map(|estimate| estimate.marginal.to_minor_units())
+        .collect();
+
+    // The floored snack only comes down to £1.50.
+    assert_eq!(marginals, [100, 150]);
+
+    assert_matches_full_solves(&graph, &item_group, &candidates)?;
+
+    Ok(())
+}
@@ -0,0 +1,155 @@
+//! Integration tests for incremental basket sessions
+//!
+//! A session reuses earlier solutions as the basket changes; after every change
+//! its result must match evaluating the basket from scratch.
+
+use std::num::NonZeroU32;
+
+use testresult::TestResult;
+
+use lattice::{
+    fixtures::Fixture,
+    graph::{BasketSession, EvaluationMode, LayeredSolverResult, PromotionGraph},
+    items::groups::ItemGroup,
+    promotions::PromotionKey,
+};
+
+/// Per basket line: (promotion, bundle, original price, final price, quantity)
+type LineRedemptions = Vec<(usize, Vec<(PromotionKey, usize, i64, i64, u32)>)>;
+
+fn redemptions(result: &LayeredSolverResult<'_>) -> LineRedemptions {
+    let mut lines: LineRedemptions = result
+        .item_redemptions
+        .iter()
+        .map(|(item_idx, redemptions)| {
+            let redemptions = redemptions
+                .iter()
+                .map(|redemption| {
+                    (
+                        redemption.promotion_key,
+                        redemption.redemption_idx,
+                        redemption.original_price.to_minor_units(),
+                        redemption.final_price.to_minor_units(),
+                        redemption.quantity,
+                    )
+                })
+                .collect();
+
+            (*item_idx, redemptions)
+        })
+        .collect();
+
+    lines.sort_by_key(|(item_idx, _redemptions)| *item_idx);
+
+    lines
+}
+
+fn assert_matches_cold_evaluation(
+    graph: &PromotionGraph<'_>,
+    session: &mut BasketSession<'_, '_, '_>,
+    step: &str,
+) -> TestResult {
+    let basket = session.basket();
+    let item_group = ItemGroup::new(basket.iter().cloned().collect(), basket.currency())
+        .with_context(session.context().clone());
+
+    let cold = graph.evaluate(&item_group)?;
+    let warm = session.evaluate()?;
+
+    assert_eq!(warm.total, cold.total, "total after {step}");
+    assert_eq!(
+        redemptions(&warm),
+        redemptions(&cold),
+        "redemptions after {step}"
+    );
+    assert_eq!(
+        warm.full_price_items, cold.full_price_items,
+        "full price items after {step}"
+    );
+    assert_eq!(
+        warm.coupon_codes, cold.coupon_codes,
+        "coupon codes after {step}"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn sessions_match_cold_evaluations_as_the_basket_changes() -> TestResult {
+    let sets = [
+        "layered",
+        "complex",
+        "comprehensive",
+        "coupons",
+        "budget-pools",
+        "mix-and-match",
+        "tiered-threshold",
+    ];
+
+    for set in sets {
+        let fixture = Fixture::from_set(set)?;
+        let items = fixture.items();
+
+        // Joint mode runs on the sets other joint tests cover; microlp wrongly
+        // reports some larger tiered-threshold baskets infeasible when solved jointly.
+        let modes: &[EvaluationMode] = if set == "tiered-threshold" {
+            &[EvaluationMode::Greedy]
+        } else {
+            &[EvaluationMode::Greedy, EvaluationMode::Joint]
+        };
+
+        for &mode in modes {
+            let graph = fixture.graph()?.clone().with_evaluation_mode(mode);
+            let mut session = graph
+                .session(fixture.basket(Some(0))?)
+                .with_context(fixture.context().clone());
+
+            for (idx, item) in items.iter().enumerate() {
+                session.add_item(item.clone())?;
+
+                assert_matches_cold_evaluation(&graph, &mut session, &format!("{set}: add {idx}"))?;
+            }
+
+            if session.basket().len() < 2 {
+                continue;
+            }
+
+            session.set_quantity(0, NonZeroU32::new(3).expect("non-zero quantity"))?;
+            assert_matches_cold_evaluation(&graph, &mut session, &format!("{set}: quantity"))?;
+
+            let removed = session.remove_item(1)?;
+            assert_matches_cold_evaluation(&graph, &mut session, &format!("{set}: remove"))?;
+
+            session.add_item(removed)?;
+            assert_matches_cold_evaluation(&graph, &mut session, &format!("{set}: re-add"))?;
+
+            session.set_quantity(0, NonZeroU32::MIN)?;
+            assert_matches_cold_evaluation(&graph, &mut session, &format!("{set}: restore"))?;
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn sessions_reuse_solutions_of_unchanged_sub_baskets() -> TestResult {
+    let fixture = Fixture::from_set("layered")?;
+    let graph = fixture.graph()?;
+    let mut session = graph
+        .session(fixture.basket(Some(0))?)
+        .with_context(fixture.context().clone());
+
+    for item in fixture.items() {
+        session.add_item(item.clone())?;
+        session.evaluate()?;
+    }
+
+    let stats = session.stats();
+
+    assert!(
+        stats.reused > 0,
+        "adding items one by one should reuse some solutions: {stats:?}"
+    );
+
+    Ok(())
+}
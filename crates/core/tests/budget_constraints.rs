@@ -691,3 +691,260 @@ fn budget_both_limits_enforced() -> TestResult {
 
     Ok(())
 }
+
+fn main_and_drink<'a>() -> ItemGroup<'a> {
+    let items = [(400, "main"), (200, "drink")].map(|(price, tag)| {
+        Item::with_tags(
+            ProductKey::default(),
+            Money::from_minor(price, GBP),
+            StringTagCollection::from_strs(&[tag]),
+        )
+    });
+
+    ItemGroup::new(items.into_iter().collect(), GBP)
+}
+
+fn meal_deal(
+    discount: MixAndMatchDiscount<'static>,
+    limit_minor: i64,
+) -> MixAndMatchPromotion<'static> {
+    let mut slot_keys = SlotMap::<PromotionSlotKey, ()>::with_key();
+
+    MixAndMatchPromotion::new(
+        PromotionKey::default(),
+        vec![
+            slot(
+                &mut slot_keys,
+                StringTagCollection::from_strs(&["main"]),
+                1,
+                Some(1),
+            ),
+            slot(
+                &mut slot_keys,
+                StringTagCollection::from_strs(&["drink"]),
+                1,
+                Some(1),
+            ),
+        ],
+        discount,
+        PromotionBudget::with_monetary_limit(Money::from_minor(limit_minor, GBP)),
+    )
+}
+
+fn spend_tier(
+    discount: ThresholdDiscount<'static>,
+    limit_minor: i64,
+) -> TieredThresholdPromotion<'static> {
+    let any_item = || {
+        lattice::promotions::qualification::Qualification::match_any(
+            StringTagCollection::from_strs(&["main", "drink"]),
+        )
+    };
+
+    TieredThresholdPromotion::new(
+        PromotionKey::default(),
+        vec![ThresholdTier::new(
+            TierThreshold::with_monetary_threshold(Money::from_minor(500, GBP)),
+            None,
+            any_item(),
+            any_item(),
+            discount,
+        )],
+        PromotionBudget::with_monetary_limit(Money::from_minor(limit_minor, GBP)),
+    )
+}
+
+#[test]
+fn mix_and_match_amount_off_total_budget_is_exact() -> TestResult {
+    let item_group = main_and_drink();
+
+    // £1 off the bundle fits a £1 budget exactly.
+    let deal = promotion(meal_deal(
+        MixAndMatchDiscount::AmountOffTotal(Money::from_minor(100, GBP)),
+        100,
+    ));
+
+    let result = ILPSolver::solve(&[deal], &item_group)?;
+
+    assert_eq!(result.total.to_minor_units(), 500);
+    assert_eq!(result.promotion_redemptions.len(), 2);
+
+    // One penny less and the bundle cannot form.
+    let deal = promotion(meal_deal(
+        MixAndMatchDiscount::AmountOffTotal(Money::from_minor(100, GBP)),
+        99,
+    ));
+
+    let result = ILPSolver::solve(&[deal], &item_group)?;
+
+    assert_eq!(result.total.to_minor_units(), 600);
+
+    Ok(())
+}
+
+#[test]
+fn mix_and_match_fixed_total_budget_is_exact() -> TestResult {
+    let item_group = main_and_drink();
+
+    // Main and drink for £5 saves exactly £1.
+    let deal = promotion(meal_deal(
+        MixAndMatchDiscount::FixedTotal(Money::from_minor(500, GBP)),
+        100,
+    ));
+
+    let result = ILPSolver::solve(&[deal], &item_group)?;
+
+    assert_eq!(result.total.to_minor_units(), 500);
+    assert_eq!(result.promotion_redemptions.len(), 2);
+
+    let deal = promotion(meal_deal(
+        MixAndMatchDiscount::FixedTotal(Money::from_minor(500, GBP)),
+        99,
+    ));
+
+    let result = ILPSolver::solve(&[deal], &item_group)?;
+
+    assert_eq!(result.total.to_minor_units(), 600);
+
+    Ok(())
+}
+
+#[test]
+fn mix_and_match_bundle_total_budget_counts_every_bundle() -> TestResult {
+    let items =
+        [(400, "main"), (200, "drink"), (400, "main"), (200, "drink")].map(|(price, tag)| {
+            Item::with_tags(
+                ProductKey::default(),
+                Money::from_minor(price, GBP),
+                StringTagCollection::from_strs(&[tag]),
+            )
+        });
+
+    let item_group = ItemGroup::new(items.into_iter().collect(), GBP);
+
+    // A £1.50 wallet balance covers one £1 bundle discount, not two.
+    let deal = promotion(meal_deal(
+        MixAndMatchDiscount::FixedTotal(Money::from_minor(500, GBP)),
+        150,
+    ));
+
+    let result = ILPSolver::solve(&[deal], &item_group)?;
+
+    assert_eq!(result.total.to_minor_units(), 1100);
+
+    Ok(())
+}
+
+#[test]
+fn tiered_threshold_amount_off_total_budget_is_exact() -> TestResult {
+    let item_group = main_and_drink();
+
+    let tier = promotion(spend_tier(
+        ThresholdDiscount::AmountOffTotal(Money::from_minor(100, GBP)),
+        100,
+    ));
+
+    let result = ILPSolver::solve(&[tier], &item_group)?;
+
+    assert_eq!(result.total.to_minor_units(), 500);
+
+    let tier = promotion(spend_tier(
+        ThresholdDiscount::AmountOffTotal(Money::from_minor(100, GBP)),
+        99,
+    ));
+
+    let result = ILPSolver::solve(&[tier], &item_group)?;
+
+    assert_eq!(result.total.to_minor_units(), 600);
+
+    Ok(())
+}
+
+#[test]
+fn tiered_threshold_fixed_total_budget_is_exact() -> TestResult {
+    let item_group = main_and_drink();
+
+    let tier = promotion(spend_tier(
+        ThresholdDiscount::FixedTotal(Money::from_minor(500, GBP)),
+        100,
+    ));
+
+    let result = ILPSolver::solve(&[tier], &item_group)?;
+
+    assert_eq!(result.total.to_minor_units(), 500);
+
+    let tier = promotion(spend_tier(
+        ThresholdDiscount::FixedTotal(Money::from_minor(500, GBP)),
+        99,
+    ));
+
+    let result = ILPSolver::solve(&[tier], &item_group)?;
+
+    assert_eq!(result.total.to_minor_units(), 600);
+
+    Ok(())
+}
+
+#[test]
+fn budgets_too_small_for_any_discount_leave_the_basket_at_full_price() -> TestResult {
+    let items = [1000, 1000].map(|price| {
+        Item::with_tags(
+            ProductKey::default(),
+            Money::from_minor(price, GBP),
+            StringTagCollection::from_strs(&["a"]),
+        )
+    });
+
+    let item_group = ItemGroup::new(items.into_iter().collect(), GBP);
+
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let mut slot_keys = SlotMap::<PromotionSlotKey, ()>::with_key();
+
+    let any_a = || {
+        lattice::promotions::qualification::Qualification::match_any(
+            StringTagCollection::from_strs(&["a"]),
+        )
+    };
+
+    // 30% off each item would cost £6 against a 1p budget.
+    let tier = promotion(TieredThresholdPromotion::new(
+        keys.insert(()),
+        vec![ThresholdTier::new(
+            TierThreshold::with_monetary_threshold(Money::from_minor(100, GBP)),
+            None,
+            any_a(),
+            any_a(),
+            ThresholdDiscount::PercentEachItem(Percentage::from(0.30)),
+        )],
+        PromotionBudget::with_monetary_limit(Money::from_minor(1, GBP)),
+    ));
+
+    // Two for £9 would save £11 against a £3.66 budget.
+    let bundle = promotion(MixAndMatchPromotion::new(
+        keys.insert(()),
+        vec![
+            slot(
+                &mut slot_keys,
+                StringTagCollection::from_strs(&["a"]),
+                1,
+                Some(1),
+            ),
+            slot(
+                &mut slot_keys,
+                StringTagCollection::from_strs(&["a"]),
+                1,
+                Some(1),
+            ),
+        ],
+        MixAndMatchDiscount::FixedTotal(Money::from_minor(900, GBP)),
+        PromotionBudget::with_monetary_limit(Money::from_minor(366, GBP)),
+    ));
+
+    let result = ILPSolver::solve(&[tier, bundle], &item_group)?;
+
+    assert_eq!(result.total.to_minor_units(), 2000);
+    assert!(result.promotion_redemptions.is_empty());
+    assert!(result.quality.is_optimal());
+
+    Ok(())
+}
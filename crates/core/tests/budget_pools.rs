@@ -0,0 +1,215 @@
+//! Integration tests for shared budget pools
+//!
+//! Promotions drawing on the same pool are limited together, across layers and
+//! evaluation modes, while each keeps its own budget as well.
+
+use decimal_percentage::Percentage;
+use rusty_money::{Money, iso::GBP};
+use slotmap::SlotMap;
+use smallvec::SmallVec;
+use testresult::TestResult;
+
+use lattice::{
+    discounts::SimpleDiscount,
+    fixtures::Fixture,
+    graph::{EvaluationMode, GraphError, OutputMode, PromotionGraph, PromotionGraphBuilder},
+    items::{Item, groups::ItemGroup},
+    products::ProductKey,
+    promotions::{
+        Promotion, PromotionKey, PromotionSlotKey,
+        budget::{BudgetPoolKey, BudgetPools, PromotionBudget},
+        promotion,
+        qualification::Qualification,
+        types::{DirectDiscountPromotion, MixAndMatchDiscount, MixAndMatchPromotion},
+    },
+    solvers::ilp::{ILPSolver, NoopObserver},
+    tags::string::StringTagCollection,
+    utils::slot,
+};
+
+fn items<'a>(items: &[(i64, &str)]) -> ItemGroup<'a> {
+    let items: SmallVec<[Item<'a>; 10]> = items
+        .iter()
+        .map(|&(price, tag)| {
+            Item::with_tags(
+                ProductKey::default(),
+                Money::from_minor(price, GBP),
+                StringTagCollection::from_strs(&[tag]),
+            )
+        })
+        .collect();
+
+    ItemGroup::new(items, GBP)
+}
+
+fn half_off(key: PromotionKey, tag: &str, pool: BudgetPoolKey) -> Promotion<'static> {
+    promotion(
+        DirectDiscountPromotion::new(
+            key,
+            Qualification::match_any(StringTagCollection::from_strs(&[tag])),
+            SimpleDiscount::PercentageOff(Percentage::from(0.50)),
+            PromotionBudget::unlimited(),
+        )
+        .with_budget_pool(pool),
+    )
+}
+
+fn redemption_pool(limit: u32) -> (BudgetPools<'static>, BudgetPoolKey) {
+    let mut pools = BudgetPools::default();
+
+    let pool = pools.insert(PromotionBudget {
+        redemption_limit: Some(limit),
+        monetary_limit: None,
+    });
+
+    (pools, pool)
+}
+
+#[test]
+fn pool_is_shared_across_layers() -> TestResult {
+    let fixture = Fixture::from_set("budget-pools")?;
+    let item_group = fixture.item_group()?;
+
+    for mode in [EvaluationMode::Greedy, EvaluationMode::Joint] {
+        let graph = fixture.graph()?.clone().with_evaluation_mode(mode);
+
+        // £1 off each of 3 coffees and 2 cakes would save £5; the £2.50 pool allows two.
+        let result = graph.evaluate(&item_group)?;
+
+        assert_eq!(result.total.to_minor_units(), 1200, "{mode:?} total");
+
+        // The pool is a per-evaluation balance, so a second evaluation sees it in full.
+        let again = graph.evaluate(&item_group)?;
+
+        assert_eq!(again.total.to_minor_units(), 1200, "{mode:?} repeat total");
+    }
+
+    let pool = fixture.budget_pool_key("launch-fund")?;
+
+    assert_eq!(
+        fixture
+            .budget_pools()
+            .get(pool)
+            .and_then(|budget| budget.monetary_limit),
+        Some(Money::from_minor(250, GBP)),
+        "fixture pool is unchanged by evaluation"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn redemption_pool_limits_promotions_together() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let (mut pools, pool) = redemption_pool(3);
+
+    let promotions = [
+        half_off(keys.insert(()), "fruit", pool),
+        half_off(keys.insert(()), "veg", pool),
+    ];
+
+    let item_group = items(&[(100, "fruit"), (100, "fruit"), (100, "veg"), (100, "veg")]);
+
+    let result = ILPSolver::solve_with_budget_pools(
+        &promotions,
+        &item_group,
+        &mut pools,
+        &mut NoopObserver,
+    )?;
+
+    assert_eq!(result.total.to_minor_units(), 250, "three items half price");
+    assert_eq!(
+        result.promotion_redemptions.len(),
+        3,
+        "pool caps redemptions"
+    );
+
+    assert_eq!(
+        pools.get(pool).and_then(|budget| budget.redemption_limit),
+        Some(0),
+        "pool is debited by the solve"
+    );
+
+    let exhausted = ILPSolver::solve_with_budget_pools(
+        &promotions,
+        &item_group,
+        &mut pools,
+        &mut NoopObserver,
+    )?;
+
+    assert_eq!(
+        exhausted.total.to_minor_units(),
+        400,
+        "exhausted pool blocks discounts"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn bundle_counts_as_one_pool_redemption() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let mut slot_keys = SlotMap::<PromotionSlotKey, ()>::with_key();
+    let (mut pools, pool) = redemption_pool(1);
+
+    let meal_deal = promotion(
+        MixAndMatchPromotion::new(
+            keys.insert(()),
+            vec![
+                slot(
+                    &mut slot_keys,
+                    StringTagCollection::from_strs(&["main"]),
+                    1,
+                    Some(1),
+                ),
+                slot(
+                    &mut slot_keys,
+                    StringTagCollection::from_strs(&["drink"]),
+                    1,
+                    Some(1),
+                ),
+            ],
+            MixAndMatchDiscount::FixedTotal(Money::from_minor(500, GBP)),
+            PromotionBudget::unlimited(),
+        )
+        .with_budget_pool(pool),
+    );
+
+    let promotions = [meal_deal, half_off(keys.insert(()), "snack", pool)];
+    let item_group = items(&[(400, "main"), (200, "drink"), (300, "snack")]);
+
+    let result = ILPSolver::solve_with_budget_pools(
+        &promotions,
+        &item_group,
+        &mut pools,
+        &mut NoopObserver,
+    )?;
+
+    // The meal deal saves 100 and the snack 150; only one redemption is left.
+    assert_eq!(result.total.to_minor_units(), 750, "snack discount wins");
+
+    Ok(())
+}
+
+#[test]
+fn graph_rejects_unknown_budget_pool() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let (_pools, pool) = redemption_pool(1);
+
+    let mut builder = PromotionGraphBuilder::new();
+
+    let root = builder.add_layer(
+        "Root",
+        [half_off(keys.insert(()), "fruit", pool)],
+        OutputMode::PassThrough,
+    )?;
+
+    builder.set_root(root);
+
+    assert!(matches!(
+        PromotionGraph::from_builder(builder),
+        Err(GraphError::UnknownBudgetPool { pool_key, .. }) if pool_key == pool
+    ));
+
+    Ok(())
+}
@@ -0,0 +1,107 @@
+//! Helpers shared by the integration tests
+
+use decimal_percentage::Percentage;
+use rusty_money::{Money, iso::GBP};
+use slotmap::SlotMap;
+use smallvec::SmallVec;
+
+use lattice::{
+    discounts::SimpleDiscount,
+    items::{Item, groups::ItemGroup},
+    products::ProductKey,
+    promotions::{
+        Promotion, PromotionKey, PromotionSlotKey,
+        budget::PromotionBudget,
+        promotion,
+        qualification::Qualification,
+        types::{
+            DirectDiscountPromotion, MixAndMatchDiscount, MixAndMatchPromotion,
+            PositionalDiscountPromotion,
+        },
+    },
+    tags::string::StringTagCollection,
+    utils::slot,
+};
+
+/// Every fixture set with a graph, items and promotions.
+pub const FIXTURE_SETS: &[&str] = &[
+    "budget-application",
+    "budget-monetary",
+    "budget-pools",
+    "complex",
+    "comprehensive",
+    "conformance/meal-deals",
+    "context",
+    "coupons",
+    "demo",
+    "direct",
+    "exclusions",
+    "layered",
+    "mix-and-match",
+    "positional",
+    "qualification",
+    "tiered-threshold",
+];
+
+/// The basket total with no promotions applied, in minor units.
+pub fn full_price(item_group: &ItemGroup<'_>) -> i64 {
+    item_group
+        .iter()
+        .map(|item| item.price().to_minor_units() * i64::from(item.quantity()))
+        .sum()
+}
+
+/// One department of overlapping promotions that no decomposition can split.
+pub fn crowded_basket(items: usize) -> (Vec<Promotion<'static>>, ItemGroup<'static>) {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let mut slot_keys = SlotMap::<PromotionSlotKey, ()>::with_key();
+
+    let tags = ["main", "side", "drink"];
+
+    let promotions = vec![
+        promotion(DirectDiscountPromotion::new(
+            keys.insert(()),
+            Qualification::match_any(StringTagCollection::from_strs(&["main"])),
+            SimpleDiscount::PercentageOff(Percentage::from(0.15)),
+            PromotionBudget::unlimited(),
+        )),
+        promotion(PositionalDiscountPromotion::new(
+            keys.insert(()),
+            Qualification::match_any(StringTagCollection::from_strs(&tags)),
+            3,
+            SmallVec::from_slice(&[2]),
+            SimpleDiscount::PercentageOff(Percentage::from(1.0)),
+            PromotionBudget::unlimited(),
+        )),
+        promotion(MixAndMatchPromotion::new(
+            keys.insert(()),
+            tags.iter()
+                .map(|tag| {
+                    slot(
+                        &mut slot_keys,
+                        StringTagCollection::from_strs(&[tag]),
+                        1,
+                        Some(1),
+                    )
+                })
+                .collect(),
+            MixAndMatchDiscount::FixedTotal(Money::from_minor(500, GBP)),
+            PromotionBudget::unlimited(),
+        )),
+    ];
+
+    let items: SmallVec<[Item<'static>; 10]> = (0..items)
+        .zip(tags.iter().cycle())
+        .map(|(idx, tag)| {
+            let price = 150 + 37 * i64::try_from(idx % 11).unwrap_or_default();
+
+            Item::with_tags(
+                ProductKey::default(),
+                Money::from_minor(price, GBP),
+                StringTagCollection::from_strs(&[tag]),
+            )
+        })
+        .collect();
+
+    (promotions, ItemGroup::new(items, GBP))
+}
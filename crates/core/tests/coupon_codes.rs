@@ -0,0 +1,236 @@
+//! Integration tests for coupon code gated promotions
+//!
+//! Promotions that require a code only apply when the basket presents it, and
+//! the graph result reports what happened to every presented code.
+
+use decimal_percentage::Percentage;
+use rusty_money::{Money, iso::GBP};
+use slotmap::SlotMap;
+use smallvec::SmallVec;
+use testresult::TestResult;
+
+use lattice::{
+    context::EvaluationContext,
+    discounts::SimpleDiscount,
+    fixtures::Fixture,
+    graph::{EvaluationMode, GraphError, OutputMode, PromotionGraph, PromotionGraphBuilder},
+    items::{Item, groups::ItemGroup},
+    products::ProductKey,
+    promotions::{
+        Promotion, PromotionKey,
+        budget::PromotionBudget,
+        coupon::PromotionCoupon,
+        promotion,
+        qualification::Qualification,
+        types::{DirectDiscountPromotion, PositionalDiscountPromotion},
+    },
+    tags::string::StringTagCollection,
+};
+
+fn basket<'a>(codes: &[&str]) -> ItemGroup<'a> {
+    let items = [(1000, "shirt"), (1000, "shirt"), (1000, "shirt")].map(|(price, tag)| {
+        Item::with_tags(
+            ProductKey::default(),
+            Money::from_minor(price, GBP),
+            StringTagCollection::from_strs(&[tag]),
+        )
+    });
+
+    ItemGroup::new(SmallVec::from_iter(items), GBP)
+        .with_context(EvaluationContext::new().with_codes(codes.iter().copied()))
+}
+
+fn pound_off_shirts(key: PromotionKey, coupon: PromotionCoupon) -> Promotion<'static> {
+    promotion(
+        DirectDiscountPromotion::new(
+            key,
+            Qualification::match_any(StringTagCollection::from_strs(&["shirt"])),
+            SimpleDiscount::AmountOff(Money::from_minor(100, GBP)),
+            PromotionBudget::unlimited(),
+        )
+        .with_coupon(coupon),
+    )
+}
+
+#[test]
+fn coupon_promotion_needs_its_code() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+
+    for mode in [EvaluationMode::Greedy, EvaluationMode::Joint] {
+        let graph = PromotionGraph::single_layer([pound_off_shirts(
+            keys.insert(()),
+            PromotionCoupon::multi_use(["SHIRTS"]),
+        )])?
+        .with_evaluation_mode(mode);
+
+        let without = graph.evaluate(&basket(&[]))?;
+        let with = graph.evaluate(&basket(&["shirts"]))?;
+
+        assert_eq!(
+            without.total.to_minor_units(),
+            3000,
+            "{mode:?} without code"
+        );
+        assert_eq!(with.total.to_minor_units(), 2700, "{mode:?} with code");
+        assert_eq!(with.coupon_codes.used.as_slice(), ["shirts"], "{mode:?}");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn single_use_code_unlocks_one_redemption() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+
+    for mode in [EvaluationMode::Greedy, EvaluationMode::Joint] {
+        let graph = PromotionGraph::single_layer([pound_off_shirts(
+            keys.insert(()),
+            PromotionCoupon::single_use(["ONCE"]),
+        )])?
+        .with_evaluation_mode(mode);
+
+        let result = graph.evaluate(&basket(&["ONCE"]))?;
+
+        assert_eq!(result.total.to_minor_units(), 2900, "{mode:?} total");
+        assert_eq!(result.item_redemptions.len(), 1, "{mode:?} redemptions");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn single_use_code_caps_bundle_redemptions() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+
+    // Buy one get one free on shirts, once per code.
+    let bogof = promotion(
+        PositionalDiscountPromotion::new(
+            keys.insert(()),
+            Qualification::match_any(StringTagCollection::from_strs(&["shirt"])),
+            2,
+            SmallVec::from_slice(&[1]),
+            SimpleDiscount::PercentageOff(Percentage::from(1.0)),
+            PromotionBudget::unlimited(),
+        )
+        .with_coupon(PromotionCoupon::single_use(["BOGOF"])),
+    );
+
+    let items = (0..4).map(|_| {
+        Item::with_tags(
+            ProductKey::default(),
+            Money::from_minor(1000, GBP),
+            StringTagCollection::from_strs(&["shirt"]),
+        )
+    });
+
+    let item_group = ItemGroup::new(items.collect(), GBP)
+        .with_context(EvaluationContext::new().with_code("BOGOF"));
+
+    let result = PromotionGraph::single_layer([bogof])?.evaluate(&item_group)?;
+
+    assert_eq!(result.total.to_minor_units(), 3000);
+
+    Ok(())
+}
+
+#[test]
+fn report_classifies_used_no_benefit_and_unknown_codes() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+
+    let shoes = promotion(
+        DirectDiscountPromotion::new(
+            keys.insert(()),
+            Qualification::match_any(StringTagCollection::from_strs(&["shoes"])),
+            SimpleDiscount::PercentageOff(Percentage::from(0.30)),
+            PromotionBudget::unlimited(),
+        )
+        .with_coupon(PromotionCoupon::multi_use(["STAFF"])),
+    );
+
+    let graph = PromotionGraph::single_layer([
+        pound_off_shirts(keys.insert(()), PromotionCoupon::single_use(["SAVE1"])),
+        shoes,
+    ])?;
+
+    let result = graph.evaluate(&basket(&["BOGUS", "staff", "save1"]))?;
+
+    assert_eq!(result.coupon_codes.used.as_slice(), ["save1"]);
+    assert_eq!(result.coupon_codes.no_benefit.as_slice(), ["staff"]);
+    assert_eq!(result.coupon_codes.unknown.as_slice(), ["BOGUS"]);
+
+    // Without codes the report is empty.
+    assert!(graph.evaluate(&basket(&[]))?.coupon_codes.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn coupon_promotion_applies_in_a_later_layer() -> TestResult {
+    let fixture = Fixture::from_set("coupons")?;
+
+    for mode in [EvaluationMode::Greedy, EvaluationMode::Joint] {
+        let graph = fixture.graph()?.clone().with_evaluation_mode(mode);
+        let item_group = fixture.item_group()?;
+
+        let result = graph.evaluate(&item_group)?;
+
+        // Shirts 2000 -> 1800, socks 400 -> 360; SAVE5 then takes 500 off one shirt.
+        assert_eq!(result.total.to_minor_units(), 3460, "{mode:?} total");
+        assert_eq!(result.coupon_codes.used.as_slice(), ["save5"], "{mode:?}");
+        assert_eq!(
+            result.coupon_codes.no_benefit.as_slice(),
+            ["STAFF"],
+            "{mode:?}"
+        );
+        assert_eq!(
+            result.coupon_codes.unknown.as_slice(),
+            ["BOGUS"],
+            "{mode:?}"
+        );
+
+        let without_codes =
+            graph.evaluate_with_context(&item_group, &EvaluationContext::default())?;
+
+        assert_eq!(
+            without_codes.total.to_minor_units(),
+            3960,
+            "{mode:?} total without codes"
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn single_use_code_cannot_unlock_two_promotions() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let mut builder = PromotionGraphBuilder::new();
+
+    let first = builder.add_layer(
+        "First",
+        [pound_off_shirts(
+            keys.insert(()),
+            PromotionCoupon::single_use(["ONCE"]),
+        )],
+        OutputMode::PassThrough,
+    )?;
+
+    let second = builder.add_layer(
+        "Second",
+        [pound_off_shirts(
+            keys.insert(()),
+            PromotionCoupon::single_use(["once"]),
+        )],
+        OutputMode::PassThrough,
+    )?;
+
+    builder.set_root(first);
+    builder.connect_pass_through(first, second)?;
+
+    assert!(matches!(
+        PromotionGraph::from_builder(builder),
+        Err(GraphError::SharedSingleUseCode { .. })
+    ));
+
+    Ok(())
+}
@@ -20,6 +20,7 @@ use lattice::{
     },
     solvers::{
         Solver, SolverError,
+        greedy::GreedySolver,
         ilp::{BINARY_THRESHOLD, ILPSolver, observer::ILPObserver},
     },
     tags::string::StringTagCollection,
@@ -119,6 +120,9 @@ impl ILPPromotionVars for ExternalCustomPromotionVars {
                 redemption_idx,
                 original_price: *item.price(),
                 final_price: Money::from_minor(self.final_minor.max(0), currency),
+                quantity: 1,
+                funding: SmallVec::new(),
+                floor_limited: false,
             });
         }
 
@@ -237,3 +241,22 @@ fn solve_handles_builtin_and_external_promotions() -> TestResult {
 
     Ok(())
 }
+
+#[test]
+fn greedy_solve_rejects_external_promotions_without_a_heuristic() {
+    let items = [Item::new(
+        ProductKey::default(),
+        Money::from_minor(100, GBP),
+    )];
+    let item_group = ItemGroup::new(items.into_iter().collect(), GBP);
+
+    let key = PromotionKey::default();
+    let promotions = [promotion(ExternalCustomPromotion {
+        key,
+        final_minor: 1,
+    })];
+
+    let result = GreedySolver::solve(&promotions, &item_group);
+
+    assert!(matches!(result, Err(SolverError::GreedyUnsupported(k)) if k == key));
+}
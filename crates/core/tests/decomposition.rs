@@ -0,0 +1,236 @@
+//! Integration tests for solving independent sub-baskets separately
+//!
+//! Unobserved solves split the basket into components no promotion spans; the
+//! merged result must price the basket exactly as a single model would.
+
+use decimal_percentage::Percentage;
+use good_lp::{Expression, Variable};
+use rusty_money::{Money, iso::GBP};
+use slotmap::SlotMap;
+use smallvec::SmallVec;
+use testresult::TestResult;
+
+use lattice::{
+    discounts::SimpleDiscount,
+    items::{Item, groups::ItemGroup},
+    products::ProductKey,
+    promotions::{
+        Promotion, PromotionKey, PromotionSlotKey,
+        budget::{BudgetPools, PromotionBudget},
+        promotion,
+        qualification::Qualification,
+        types::{
+            DirectDiscountPromotion, MixAndMatchDiscount, MixAndMatchPromotion,
+            PositionalDiscountPromotion, ThresholdDiscount, ThresholdTier, TierThreshold,
+            TieredThresholdPromotion,
+        },
+    },
+    solvers::{
+        Solver, SolverResult,
+        ilp::{ILPObserver, ILPSolver, NoopObserver},
+    },
+    tags::string::StringTagCollection,
+    utils::slot,
+};
+
+/// Observer that keeps the whole basket in a single model.
+struct WholeModel;
+
+impl ILPObserver for WholeModel {
+    fn on_presence_variable(&mut self, _: usize, _: Variable, _: i64) {}
+
+    fn on_promotion_variable(
+        &mut self,
+        _: PromotionKey,
+        _: usize,
+        _: Variable,
+        _: i64,
+        _: Option<&str>,
+    ) {
+    }
+
+    fn on_exclusivity_constraint(&mut self, _: usize, _: &Expression) {}
+
+    fn on_promotion_constraint(
+        &mut self,
+        _: PromotionKey,
+        _: &str,
+        _: &Expression,
+        _: &str,
+        _: f64,
+    ) {
+    }
+}
+
+fn item<'a>(price: i64, tag: &str) -> Item<'a> {
+    Item::with_tags(
+        ProductKey::default(),
+        Money::from_minor(price, GBP),
+        StringTagCollection::from_strs(&[tag]),
+    )
+}
+
+fn mixed_promotions() -> Vec<Promotion<'static>> {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let mut slot_keys = SlotMap::<PromotionSlotKey, ()>::with_key();
+
+    vec![
+        promotion(DirectDiscountPromotion::new(
+            keys.insert(()),
+            Qualification::match_any(StringTagCollection::from_strs(&["drink"])),
+            SimpleDiscount::PercentageOff(Percentage::from(0.20)),
+            PromotionBudget::unlimited(),
+        )),
+        promotion(PositionalDiscountPromotion::new(
+            keys.insert(()),
+            Qualification::match_any(StringTagCollection::from_strs(&["hair"])),
+            3,
+            SmallVec::from_slice(&[2]),
+            SimpleDiscount::PercentageOff(Percentage::from(1.0)),
+            PromotionBudget::unlimited(),
+        )),
+        promotion(MixAndMatchPromotion::new(
+            keys.insert(()),
+            vec![
+                slot(
+                    &mut slot_keys,
+                    StringTagCollection::from_strs(&["main"]),
+                    1,
+                    Some(1),
+                ),
+                slot(
+                    &mut slot_keys,
+                    StringTagCollection::from_strs(&["drink"]),
+                    1,
+                    Some(1),
+                ),
+            ],
+            MixAndMatchDiscount::FixedTotal(Money::from_minor(350, GBP)),
+            PromotionBudget::unlimited(),
+        )),
+        promotion(TieredThresholdPromotion::new(
+            keys.insert(()),
+            vec![ThresholdTier::new(
+                TierThreshold::with_monetary_threshold(Money::from_minor(1500, GBP)),
+                None,
+                Qualification::match_any(StringTagCollection::from_strs(&["garden"])),
+                Qualification::match_any(StringTagCollection::from_strs(&["garden"])),
+                ThresholdDiscount::PercentEachItem(Percentage::from(0.25)),
+            )],
+            PromotionBudget::unlimited(),
+        )),
+    ]
+}
+
+fn mixed_items<'a>() -> ItemGroup<'a> {
+    let items = [
+        item(300, "main"),
+        item(600, "hair"),
+        item(150, "drink"),
+        item(800, "garden"),
+        item(450, "hair"),
+        item(120, "drink"),
+        item(999, "stationery"),
+        item(700, "garden"),
+        item(500, "hair"),
+        item(275, "main"),
+    ];
+
+    ItemGroup::new(SmallVec::from_iter(items), GBP)
+}
+
+fn final_prices(item_group: &ItemGroup<'_>, result: &SolverResult<'_>) -> Vec<i64> {
+    let mut prices: Vec<i64> = item_group
+        .iter()
+        .map(|item| item.price().to_minor_units())
+        .collect();
+
+    for redemption in &result.promotion_redemptions {
+        if let Some(price) = prices.get_mut(redemption.item_idx) {
+            *price = redemption.final_price.to_minor_units();
+        }
+    }
+
+    prices
+}
+
+#[test]
+fn decomposed_solve_matches_single_model() -> TestResult {
+    let promotions = mixed_promotions();
+    let item_group = mixed_items();
+
+    let decomposed = ILPSolver::solve(&promotions, &item_group)?;
+    let single = ILPSolver::solve_with_observer(&promotions, &item_group, &mut WholeModel)?;
+
+    assert_eq!(decomposed.total, single.total, "same optimal total");
+    assert_eq!(
+        final_prices(&item_group, &decomposed),
+        final_prices(&item_group, &single),
+        "same per-item prices"
+    );
+    assert_eq!(
+        decomposed.unaffected_items, single.unaffected_items,
+        "same full-price items"
+    );
+
+    let mut bundle_ids: Vec<usize> = decomposed
+        .promotion_redemptions
+        .iter()
+        .map(|redemption| redemption.redemption_idx)
+        .collect();
+
+    bundle_ids.dedup();
+
+    assert_eq!(
+        bundle_ids,
+        (0..bundle_ids.len()).collect::<Vec<_>>(),
+        "bundle ids are numbered consecutively across components"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn shared_pool_keeps_promotions_in_one_component() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let mut pools = BudgetPools::default();
+
+    let pool = pools.insert(PromotionBudget {
+        redemption_limit: Some(1),
+        monetary_limit: None,
+    });
+
+    let half_off = |key, tag: &str| {
+        promotion(
+            DirectDiscountPromotion::new(
+                key,
+                Qualification::match_any(StringTagCollection::from_strs(&[tag])),
+                SimpleDiscount::PercentageOff(Percentage::from(0.50)),
+                PromotionBudget::unlimited(),
+            )
+            .with_budget_pool(pool),
+        )
+    };
+
+    let promotions = [
+        half_off(keys.insert(()), "drink"),
+        half_off(keys.insert(()), "hair"),
+    ];
+
+    let item_group = ItemGroup::new(
+        SmallVec::from_iter([item(100, "drink"), item(400, "hair")]),
+        GBP,
+    );
+
+    let result = ILPSolver::solve_with_budget_pools(
+        &promotions,
+        &item_group,
+        &mut pools,
+        &mut NoopObserver,
+    )?;
+
+    // The pool links both promotions, so only the larger discount is taken.
+    assert_eq!(result.total.to_minor_units(), 300, "one pooled redemption");
+
+    Ok(())
+}
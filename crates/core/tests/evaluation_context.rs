@@ -0,0 +1,222 @@
+//! Integration tests for basket-level evaluation context
+//!
+//! Context rules accept or reject every item in the basket based on who is
+//! buying, where and when, and must behave the same in every evaluation mode.
+
+use decimal_percentage::Percentage;
+use jiff::Timestamp;
+use rusty_money::{Money, iso::GBP};
+use slotmap::SlotMap;
+use smallvec::{SmallVec, smallvec};
+use testresult::TestResult;
+
+use lattice::{
+    context::EvaluationContext,
+    discounts::SimpleDiscount,
+    fixtures::Fixture,
+    graph::{EvaluationMode, OutputMode, PromotionGraph, PromotionGraphBuilder},
+    items::{Item, groups::ItemGroup},
+    products::ProductKey,
+    promotions::{
+        Promotion, PromotionKey,
+        budget::PromotionBudget,
+        promotion,
+        qualification::{BoolOp, Qualification, QualificationRule},
+        types::DirectDiscountPromotion,
+    },
+    solvers::{Solver, ilp::ILPSolver},
+    tags::{collection::TagCollection, string::StringTagCollection},
+};
+
+fn item<'a>(price_minor: i64, tags: &[&str]) -> Item<'a> {
+    Item::with_tags(
+        ProductKey::default(),
+        Money::from_minor(price_minor, GBP),
+        StringTagCollection::from_strs(tags),
+    )
+}
+
+fn basket<'a>() -> ItemGroup<'a> {
+    ItemGroup::new(
+        SmallVec::from_vec(vec![item(800, &["coffee"]), item(150, &["bakery"])]),
+        GBP,
+    )
+}
+
+fn half_price<'a>(
+    key: PromotionKey,
+    rules: SmallVec<[QualificationRule<StringTagCollection>; 2]>,
+) -> Promotion<'a> {
+    promotion(DirectDiscountPromotion::new(
+        key,
+        Qualification::new(BoolOp::And, rules),
+        SimpleDiscount::PercentageOff(Percentage::from(0.50)),
+        PromotionBudget::unlimited(),
+    ))
+}
+
+fn loyalty_coffee<'a>(key: PromotionKey) -> Promotion<'a> {
+    half_price(
+        key,
+        smallvec![
+            QualificationRule::HasAny {
+                tags: StringTagCollection::from_strs(&["coffee"]),
+            },
+            QualificationRule::CustomerSegment {
+                segments: StringTagCollection::from_strs(&["loyalty"]),
+            },
+        ],
+    )
+}
+
+fn loyalty_context() -> EvaluationContext {
+    EvaluationContext::new().with_customer_segments(StringTagCollection::from_strs(&["loyalty"]))
+}
+
+#[test]
+fn customer_segment_rule_requires_matching_context() -> TestResult {
+    for mode in [EvaluationMode::Greedy, EvaluationMode::Joint] {
+        let graph = PromotionGraph::single_layer([loyalty_coffee(PromotionKey::default())])?
+            .with_evaluation_mode(mode);
+
+        let anonymous = graph.evaluate(&basket())?;
+        let loyal = graph.evaluate_with_context(&basket(), &loyalty_context())?;
+        let staff = graph.evaluate_with_context(
+            &basket(),
+            &EvaluationContext::new()
+                .with_customer_segments(StringTagCollection::from_strs(&["staff"])),
+        )?;
+
+        assert_eq!(anonymous.total.to_minor_units(), 950);
+        assert_eq!(loyal.total.to_minor_units(), 550);
+        assert_eq!(staff.total.to_minor_units(), 950);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn solver_reads_context_from_item_group() -> TestResult {
+    let promotions = [half_price(
+        PromotionKey::default(),
+        smallvec![QualificationRule::Channel {
+            channels: smallvec!["online".to_string()],
+        }],
+    )];
+
+    let in_store = ILPSolver::solve(
+        &promotions,
+        &basket().with_context(EvaluationContext::new().with_channel("in-store")),
+    )?;
+    let online = ILPSolver::solve(
+        &promotions,
+        &basket().with_context(EvaluationContext::new().with_channel("online")),
+    )?;
+
+    assert_eq!(in_store.total.to_minor_units(), 950);
+    assert_eq!(online.total.to_minor_units(), 475);
+
+    Ok(())
+}
+
+#[test]
+fn time_window_includes_start_and_excludes_end() -> TestResult {
+    let starts_at: Timestamp = "2026-03-01T00:00:00Z".parse()?;
+    let ends_at: Timestamp = "2026-03-08T00:00:00Z".parse()?;
+
+    let graph = PromotionGraph::single_layer([half_price(
+        PromotionKey::default(),
+        smallvec![QualificationRule::TimeWindow {
+            starts_at: Some(starts_at),
+            ends_at: Some(ends_at),
+        }],
+    )])?;
+
+    let total_at = |timestamp: Timestamp| -> TestResult<i64> {
+        let context = EvaluationContext::new().with_timestamp(timestamp);
+
+        Ok(graph
+            .evaluate_with_context(&basket(), &context)?
+            .total
+            .to_minor_units())
+    };
+
+    assert_eq!(total_at("2026-02-28T23:59:59Z".parse()?)?, 950);
+    assert_eq!(total_at(starts_at)?, 475);
+    assert_eq!(total_at("2026-03-07T23:59:59Z".parse()?)?, 475);
+    assert_eq!(total_at(ends_at)?, 950);
+
+    // Without a timestamp the window cannot be satisfied.
+    assert_eq!(graph.evaluate(&basket())?.total.to_minor_units(), 950);
+
+    Ok(())
+}
+
+#[test]
+fn context_reaches_every_layer() -> TestResult {
+    let build_graph = |mode: EvaluationMode| -> TestResult<PromotionGraph<'static>> {
+        let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+
+        let mut builder = PromotionGraphBuilder::new();
+
+        let root = builder.add_layer(
+            "Bakery",
+            [promotion(DirectDiscountPromotion::new(
+                keys.insert(()),
+                Qualification::match_any(StringTagCollection::from_strs(&["bakery"])),
+                SimpleDiscount::PercentageOff(Percentage::from(0.10)),
+                PromotionBudget::unlimited(),
+            ))],
+            OutputMode::Split,
+        )?;
+
+        let loyalty = builder.add_layer(
+            "Loyalty",
+            [loyalty_coffee(keys.insert(()))],
+            OutputMode::PassThrough,
+        )?;
+
+        builder.set_root(root);
+        builder.connect_split_non_participating_only(root, loyalty)?;
+
+        Ok(PromotionGraph::from_builder(builder)?.with_evaluation_mode(mode))
+    };
+
+    for mode in [EvaluationMode::Greedy, EvaluationMode::Joint] {
+        let graph = build_graph(mode)?;
+
+        let anonymous = graph.evaluate(&basket())?;
+        let loyal = graph.evaluate_with_context(&basket(), &loyalty_context())?;
+
+        // Croissant 150 -> 135 in the root layer; coffee 800 -> 400 only for loyalty.
+        assert_eq!(anonymous.total.to_minor_units(), 935);
+        assert_eq!(loyal.total.to_minor_units(), 535);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn fixture_context_is_attached_to_item_group() -> TestResult {
+    let fixture = Fixture::from_set("context")?;
+
+    assert_eq!(fixture.context().channel(), Some("online"));
+    assert_eq!(fixture.context().store_id(), Some("42"));
+    assert_eq!(fixture.context().attribute("tier"), Some("gold"));
+    assert!(fixture.context().customer_segments().contains("loyalty"));
+
+    let item_group = fixture.item_group()?;
+    let result = fixture.graph()?.evaluate(&item_group)?;
+
+    // Coffee 800 -> 640, oat milk 200 -> 180, croissant 150 -> 75; staff discount never applies.
+    assert_eq!(result.total.to_minor_units(), 895);
+
+    // The same basket without its context gets no discounts at all.
+    let anonymous = fixture
+        .graph()?
+        .evaluate_with_context(&item_group, &EvaluationContext::default())?;
+
+    assert_eq!(anonymous.total.to_minor_units(), 1150);
+
+    Ok(())
+}
@@ -0,0 +1,230 @@
+//! Integration tests for promotion exclusions
+//!
+//! "Cannot be combined with" rules keep promotions apart on the same item or
+//! across the basket, within a single solve and across the layers of a graph in
+//! both evaluation modes.
+
+use decimal_percentage::Percentage;
+use rusty_money::{Money, iso::GBP};
+use slotmap::SlotMap;
+use smallvec::SmallVec;
+use testresult::TestResult;
+
+use lattice::{
+    discounts::SimpleDiscount,
+    fixtures::Fixture,
+    graph::{EvaluationMode, OutputMode, PromotionGraph, PromotionGraphBuilder},
+    items::{Item, groups::ItemGroup},
+    products::ProductKey,
+    promotions::{
+        PromotionKey,
+        budget::PromotionBudget,
+        exclusion::{Exclusion, ExclusionGroupKey, ExclusionScope, PromotionExclusions},
+        promotion,
+        qualification::Qualification,
+        types::DirectDiscountPromotion,
+    },
+    solvers::{
+        Solver,
+        ilp::{ILPPromotion, ILPSolver},
+    },
+    tags::string::StringTagCollection,
+};
+
+fn items<'a>(tags: &[&str]) -> ItemGroup<'a> {
+    let items: SmallVec<[Item<'a>; 10]> = tags
+        .iter()
+        .map(|&tag| {
+            Item::with_tags(
+                ProductKey::default(),
+                Money::from_minor(100, GBP),
+                StringTagCollection::from_strs(&[tag]),
+            )
+        })
+        .collect();
+
+    ItemGroup::new(items, GBP)
+}
+
+fn percent_off(key: PromotionKey, tag: &str, percent: f64) -> DirectDiscountPromotion<'static> {
+    DirectDiscountPromotion::new(
+        key,
+        Qualification::match_any(StringTagCollection::from_strs(&[tag])),
+        SimpleDiscount::PercentageOff(Percentage::from(percent)),
+        PromotionBudget::unlimited(),
+    )
+}
+
+fn not_with(key: PromotionKey, scope: ExclusionScope) -> PromotionExclusions {
+    PromotionExclusions::default().with_rule(Exclusion::promotion(key, scope))
+}
+
+fn redeemed_keys(result: &lattice::graph::LayeredSolverResult<'_>) -> Vec<PromotionKey> {
+    let mut keys: Vec<PromotionKey> = result
+        .item_redemptions
+        .values()
+        .flatten()
+        .map(|redemption| redemption.promotion_key)
+        .collect();
+
+    keys.sort_unstable();
+    keys.dedup();
+
+    keys
+}
+
+#[test]
+fn basket_exclusion_keeps_the_better_promotion() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let (socks, shirts) = (keys.insert(()), keys.insert(()));
+
+    // The promotions never share an item, yet only one may apply to the basket.
+    let promotions = [
+        promotion(percent_off(socks, "sock", 0.1)),
+        promotion(
+            percent_off(shirts, "shirt", 0.3)
+                .with_exclusions(not_with(socks, ExclusionScope::Basket)),
+        ),
+    ];
+
+    let result = ILPSolver::solve(&promotions, &items(&["sock", "shirt"]))?;
+
+    assert_eq!(result.total.to_minor_units(), 170);
+    assert!(
+        result
+            .promotion_redemptions
+            .iter()
+            .all(|redemption| redemption.promotion_key == shirts)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn item_exclusion_allows_promotions_on_different_items() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let (socks, shirts) = (keys.insert(()), keys.insert(()));
+
+    let promotions = [
+        promotion(percent_off(socks, "sock", 0.1)),
+        promotion(
+            percent_off(shirts, "shirt", 0.3)
+                .with_exclusions(not_with(socks, ExclusionScope::Item)),
+        ),
+    ];
+
+    let result = ILPSolver::solve(&promotions, &items(&["sock", "shirt"]))?;
+
+    assert_eq!(result.total.to_minor_units(), 160);
+
+    Ok(())
+}
+
+#[test]
+fn group_members_exclude_each_other_within_a_layer() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let (first, second, outsider) = (keys.insert(()), keys.insert(()), keys.insert(()));
+
+    let mut groups = SlotMap::<ExclusionGroupKey, ()>::with_key();
+    let vouchers = groups.insert(());
+
+    let voucher = PromotionExclusions::default()
+        .in_group(vouchers)
+        .with_rule(Exclusion::group(vouchers, ExclusionScope::Basket));
+
+    let promotions = [
+        promotion(percent_off(first, "sock", 0.2).with_exclusions(voucher.clone())),
+        promotion(percent_off(second, "shirt", 0.4).with_exclusions(voucher)),
+        promotion(percent_off(outsider, "hat", 0.1)),
+    ];
+
+    let result = ILPSolver::solve(&promotions, &items(&["sock", "shirt", "hat"]))?;
+
+    // The larger voucher wins; the promotion outside the group still applies.
+    assert_eq!(result.total.to_minor_units(), 100 + 60 + 90);
+
+    Ok(())
+}
+
+#[test]
+fn item_exclusion_applies_across_layers() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let (first, second) = (keys.insert(()), keys.insert(()));
+
+    let mut builder = PromotionGraphBuilder::new();
+
+    let everyday = builder.add_layer(
+        "Everyday",
+        [promotion(percent_off(first, "sock", 0.2))],
+        OutputMode::PassThrough,
+    )?;
+
+    let vouchers = builder.add_layer(
+        "Vouchers",
+        [promotion(percent_off(second, "sock", 0.5).with_exclusions(
+            PromotionExclusions::default().with_rule(Exclusion::any_other(ExclusionScope::Item)),
+        ))],
+        OutputMode::PassThrough,
+    )?;
+
+    builder.set_root(everyday);
+    builder.connect_pass_through(everyday, vouchers)?;
+
+    let graph = PromotionGraph::from_builder(builder)?;
+
+    // Greedy takes the first layer's 20% and then may not add the voucher.
+    let greedy = graph.evaluate(&items(&["sock"]))?;
+
+    assert_eq!(greedy.total.to_minor_units(), 80);
+    assert_eq!(redeemed_keys(&greedy), [first]);
+
+    // Joint passes on the first layer to redeem the voucher alone.
+    let joint = graph
+        .with_evaluation_mode(EvaluationMode::Joint)
+        .evaluate(&items(&["sock"]))?;
+
+    assert_eq!(joint.total.to_minor_units(), 50);
+    assert_eq!(redeemed_keys(&joint), [second]);
+
+    Ok(())
+}
+
+#[test]
+fn fixture_exclusions_apply_in_both_modes() -> TestResult {
+    let fixture = Fixture::from_set("exclusions")?;
+    let item_group = fixture.item_group()?;
+
+    let coffee_club = fixture.promotion("coffee-club")?.key();
+    let welcome = fixture.promotion("welcome-voucher")?.key();
+    let staff = fixture.promotion("staff-discount")?.key();
+
+    // Greedy takes the coffee club first, which rules out the welcome voucher
+    // and leaves staff discount for the cake alone: 2 × 2.00 + 3.60.
+    let greedy = fixture.graph()?.evaluate(&item_group)?;
+
+    assert_eq!(greedy.total.to_minor_units(), 760);
+
+    let mut expected = vec![coffee_club, staff];
+
+    expected.sort_unstable();
+
+    assert_eq!(redeemed_keys(&greedy), expected);
+
+    // Joint gives up the coffee club for the voucher on the cake and staff
+    // discount on the coffees: 2 × 2.70 + 1.00.
+    let joint = fixture
+        .graph()?
+        .clone()
+        .with_evaluation_mode(EvaluationMode::Joint)
+        .evaluate(&item_group)?;
+
+    assert_eq!(joint.total.to_minor_units(), 640);
+
+    let mut expected = vec![welcome, staff];
+
+    expected.sort_unstable();
+
+    assert_eq!(redeemed_keys(&joint), expected);
+
+    Ok(())
+}
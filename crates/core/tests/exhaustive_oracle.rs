@@ -0,0 +1,495 @@
+//! Property tests checking the ILP solver against the exhaustive oracle
+//!
+//! Random small baskets and promotions of every built-in type, with budgets and
+//! shared pools, must price to the same optimum under both solvers.
+
+use std::num::NonZeroU32;
+
+use decimal_percentage::Percentage;
+use proptest::{collection::vec, option, prelude::*, sample::select, test_runner::RngSeed};
+use rusty_money::{Money, iso::GBP};
+use slotmap::SlotMap;
+use smallvec::SmallVec;
+
+use lattice::{
+    discounts::SimpleDiscount,
+    items::{Item, groups::ItemGroup},
+    products::ProductKey,
+    promotions::{
+        Promotion, PromotionKey, PromotionSlotKey,
+        budget::{BudgetPoolKey, BudgetPools, PromotionBudget},
+        promotion,
+        qualification::Qualification,
+        types::{
+            DirectDiscountPromotion, MixAndMatchDiscount, MixAndMatchPromotion,
+            PositionalDiscountPromotion, ThresholdDiscount, ThresholdTier, TierThreshold,
+            TieredThresholdPromotion,
+        },
+    },
+    solvers::{
+        SolverError,
+        exhaustive::ExhaustiveSolver,
+        ilp::{ILPSolver, NoopObserver},
+    },
+    tags::string::StringTagCollection,
+    utils::slot,
+};
+
+const TAGS: [&str; 3] = ["a", "b", "c"];
+
+#[derive(Debug, Clone)]
+struct ItemSpec {
+    price: i64,
+    tags: Vec<&'static str>,
+    quantity: NonZeroU32,
+}
+
+#[derive(Debug, Clone)]
+enum SimpleSpec {
+    PercentageOff(f64),
+    AmountOff(i64),
+    AmountOverride(i64),
+}
+
+#[derive(Debug, Clone)]
+enum MixSpec {
+    PercentAllItems(f64),
+    AmountOffEachItem(i64),
+    FixedPriceEachItem(i64),
+    AmountOffTotal(i64),
+    FixedTotal(i64),
+    PercentCheapest(f64),
+    FixedCheapest(i64),
+}
+
+#[derive(Debug, Clone)]
+enum ThresholdSpec {
+    PercentEachItem(f64),
+    AmountOffEachItem(i64),
+    FixedPriceEachItem(i64),
+    AmountOffTotal(i64),
+    FixedTotal(i64),
+    PercentCheapest(f64),
+    FixedCheapest(i64),
+}
+
+#[derive(Debug, Clone)]
+struct SlotSpec {
+    tag: &'static str,
+    min: usize,
+    fixed: bool,
+}
+
+#[derive(Debug, Clone)]
+struct TierSpec {
+    lower_spend: Option<i64>,
+    lower_count: Option<u32>,
+    upper_count: Option<u32>,
+    contribution_tag: Option<&'static str>,
+    discount_tag: Option<&'static str>,
+    discount: ThresholdSpec,
+}
+
+#[derive(Debug, Clone)]
+enum PromotionSpec {
+    Direct {
+        tag: Option<&'static str>,
+        discount: SimpleSpec,
+    },
+    Positional {
+        tag: Option<&'static str>,
+        size: u16,
+        positions: Vec<u16>,
+        discount: SimpleSpec,
+    },
+    MixAndMatch {
+        slots: Vec<SlotSpec>,
+        discount: MixSpec,
+    },
+    Tiered {
+        tiers: Vec<TierSpec>,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct BudgetSpec {
+    redemptions: Option<u32>,
+    monetary: Option<i64>,
+}
+
+#[derive(Debug, Clone)]
+struct Scenario {
+    items: Vec<ItemSpec>,
+    promotions: Vec<(PromotionSpec, BudgetSpec, bool)>,
+    pool: Option<BudgetSpec>,
+}
+
+fn percent() -> impl Strategy<Value = f64> {
+    select(vec![0.1, 0.25, 0.5, 1.0])
+}
+
+fn amount() -> impl Strategy<Value = i64> {
+    (1_i64..=12).prop_map(|units| units * 25)
+}
+
+fn tag() -> impl Strategy<Value = Option<&'static str>> {
+    option::of(select(TAGS.to_vec()))
+}
+
+fn item() -> impl Strategy<Value = ItemSpec> {
+    (
+        (1_i64..=16).prop_map(|units| units * 50),
+        proptest::sample::subsequence(TAGS.to_vec(), 1..=2),
+        0_u32..=1,
+    )
+        .prop_map(|(price, tags, extra)| ItemSpec {
+            price,
+            tags,
+            quantity: NonZeroU32::MIN.saturating_add(extra),
+        })
+}
+
+fn simple_discount() -> impl Strategy<Value = SimpleSpec> {
+    prop_oneof![
+        percent().prop_map(SimpleSpec::PercentageOff),
+        amount().prop_map(SimpleSpec::AmountOff),
+        amount().prop_map(SimpleSpec::AmountOverride),
+    ]
+}
+
+fn mix_discount() -> impl Strategy<Value = MixSpec> {
+    prop_oneof![
+        percent().prop_map(MixSpec::PercentAllItems),
+        amount().prop_map(MixSpec::AmountOffEachItem),
+        amount().prop_map(MixSpec::FixedPriceEachItem),
+        amount().prop_map(MixSpec::AmountOffTotal),
+        amount().prop_map(MixSpec::FixedTotal),
+        percent().prop_map(MixSpec::PercentCheapest),
+        amount().prop_map(MixSpec::FixedCheapest),
+    ]
+}
+
+fn threshold_discount() -> impl Strategy<Value = ThresholdSpec> {
+    prop_oneof![
+        percent().prop_map(ThresholdSpec::PercentEachItem),
+        amount().prop_map(ThresholdSpec::AmountOffEachItem),
+        amount().prop_map(ThresholdSpec::FixedPriceEachItem),
+        amount().prop_map(ThresholdSpec::AmountOffTotal),
+        amount().prop_map(ThresholdSpec::FixedTotal),
+        percent().prop_map(ThresholdSpec::PercentCheapest),
+        amount().prop_map(ThresholdSpec::FixedCheapest),
+    ]
+}
+
+fn tier() -> impl Strategy<Value = TierSpec> {
+    (
+        option::of((1_i64..=12).prop_map(|units| units * 100)),
+        option::of(1_u32..=3),
+        option::of(1_u32..=4),
+        tag(),
+        tag(),
+        threshold_discount(),
+    )
+        .prop_map(
+            |(lower_spend, lower_count, upper_count, contribution_tag, discount_tag, discount)| {
+                TierSpec {
+                    lower_spend,
+                    lower_count,
+                    upper_count,
+                    contribution_tag,
+                    discount_tag,
+                    discount,
+                }
+            },
+        )
+}
+
+fn promotion_spec() -> impl Strategy<Value = PromotionSpec> {
+    prop_oneof![
+        (tag(), simple_discount())
+            .prop_map(|(tag, discount)| PromotionSpec::Direct { tag, discount }),
+        (tag(), 2_u16..=3, simple_discount())
+            .prop_flat_map(|(tag, size, discount)| {
+                (
+                    Just(tag),
+                    Just(size),
+                    proptest::sample::subsequence((0..size).collect::<Vec<_>>(), 1..=2),
+                    Just(discount),
+                )
+            })
+            .prop_map(
+                |(tag, size, positions, discount)| PromotionSpec::Positional {
+                    tag,
+                    size,
+                    positions,
+                    discount,
+                }
+            ),
+        (
+            vec(
+                (select(TAGS.to_vec()), 1_usize..=2, any::<bool>())
+                    .prop_map(|(tag, min, fixed)| SlotSpec { tag, min, fixed }),
+                1..=2,
+            ),
+            mix_discount(),
+        )
+            .prop_map(|(slots, discount)| PromotionSpec::MixAndMatch { slots, discount }),
+        vec(tier(), 1..=2).prop_map(|tiers| PromotionSpec::Tiered { tiers }),
+    ]
+}
+
+fn budget() -> impl Strategy<Value = BudgetSpec> {
+    prop_oneof![
+        3 => Just(BudgetSpec {
+            redemptions: None,
+            monetary: None,
+        }),
+        1 => (option::of(1_u32..=3), option::of((1_i64..=20).prop_map(|units| units * 25)))
+            .prop_map(|(redemptions, monetary)| BudgetSpec {
+                redemptions,
+                monetary,
+            }),
+    ]
+}
+
+fn scenario() -> impl Strategy<Value = Scenario> {
+    (
+        vec(item(), 1..=5).prop_filter("small enough to enumerate", |items| {
+            items.iter().map(|item| item.quantity.get()).sum::<u32>() <= 7
+        }),
+        vec((promotion_spec(), budget(), any::<bool>()), 1..=3),
+        option::of(budget()),
+    )
+        .prop_map(|(items, promotions, pool)| Scenario {
+            items,
+            promotions,
+            pool,
+        })
+}
+
+fn qualification(tag: Option<&str>) -> Qualification {
+    tag.map_or_else(Qualification::match_all, |tag| {
+        Qualification::match_any(StringTagCollection::from_strs(&[tag]))
+    })
+}
+
+fn money(minor: i64) -> Money<'static, rusty_money::iso::Currency> {
+    Money::from_minor(minor, GBP)
+}
+
+fn simple(spec: &SimpleSpec) -> SimpleDiscount<'static> {
+    match *spec {
+        SimpleSpec::PercentageOff(pct) => SimpleDiscount::PercentageOff(Percentage::from(pct)),
+        SimpleSpec::AmountOff(minor) => SimpleDiscount::AmountOff(money(minor)),
+        SimpleSpec::AmountOverride(minor) => SimpleDiscount::AmountOverride(money(minor)),
+    }
+}
+
+fn mix(spec: &MixSpec) -> MixAndMatchDiscount<'static> {
+    match *spec {
+        MixSpec::PercentAllItems(pct) => {
+            MixAndMatchDiscount::PercentAllItems(Percentage::from(pct))
+        }
+        MixSpec::AmountOffEachItem(minor) => MixAndMatchDiscount::AmountOffEachItem(money(minor)),
+        MixSpec::FixedPriceEachItem(minor) => MixAndMatchDiscount::FixedPriceEachItem(money(minor)),
+        MixSpec::AmountOffTotal(minor) => MixAndMatchDiscount::AmountOffTotal(money(minor)),
+        MixSpec::FixedTotal(minor) => MixAndMatchDiscount::FixedTotal(money(minor)),
+        MixSpec::PercentCheapest(pct) => {
+            MixAndMatchDiscount::PercentCheapest(Percentage::from(pct))
+        }
+        MixSpec::FixedCheapest(minor) => MixAndMatchDiscount::FixedCheapest(money(minor)),
+    }
+}
+
+fn threshold(spec: &ThresholdSpec) -> ThresholdDiscount<'static> {
+    match *spec {
+        ThresholdSpec::PercentEachItem(pct) => {
+            ThresholdDiscount::PercentEachItem(Percentage::from(pct))
+        }
+        ThresholdSpec::AmountOffEachItem(minor) => {
+            ThresholdDiscount::AmountOffEachItem(money(minor))
+        }
+        ThresholdSpec::FixedPriceEachItem(minor) => {
+            ThresholdDiscount::FixedPriceEachItem(money(minor))
+        }
+        ThresholdSpec::AmountOffTotal(minor) => ThresholdDiscount::AmountOffTotal(money(minor)),
+        ThresholdSpec::FixedTotal(minor) => ThresholdDiscount::FixedTotal(money(minor)),
+        ThresholdSpec::PercentCheapest(pct) => {
+            ThresholdDiscount::PercentCheapest(Percentage::from(pct))
+        }
+        ThresholdSpec::FixedCheapest(minor) => ThresholdDiscount::FixedCheapest(money(minor)),
+    }
+}
+
+fn promotion_budget(spec: &BudgetSpec) -> PromotionBudget<'static> {
+    PromotionBudget {
+        redemption_limit: spec.redemptions,
+        monetary_limit: spec.monetary.map(money),
+    }
+}
+
+fn build_promotion(
+    key: PromotionKey,
+    spec: &PromotionSpec,
+    budget: PromotionBudget<'static>,
+    pool: Option<BudgetPoolKey>,
+    slot_keys: &mut SlotMap<PromotionSlotKey, ()>,
+) -> Promotion<'static> {
+    match spec {
+        PromotionSpec::Direct { tag, discount } => {
+            let built =
+                DirectDiscountPromotion::new(key, qualification(*tag), simple(discount), budget);
+
+            match pool {
+                Some(pool) => promotion(built.with_budget_pool(pool)),
+                None => promotion(built),
+            }
+        }
+        PromotionSpec::Positional {
+            tag,
+            size,
+            positions,
+            discount,
+        } => {
+            let built = PositionalDiscountPromotion::new(
+                key,
+                qualification(*tag),
+                *size,
+                SmallVec::from_slice(positions),
+                simple(discount),
+                budget,
+            );
+
+            match pool {
+                Some(pool) => promotion(built.with_budget_pool(pool)),
+                None => promotion(built),
+            }
+        }
+        PromotionSpec::MixAndMatch { slots, discount } => {
+            let slots = slots
+                .iter()
+                .map(|spec| {
+                    slot(
+                        slot_keys,
+                        StringTagCollection::from_strs(&[spec.tag]),
+                        spec.min,
+                        spec.fixed.then_some(spec.min),
+                    )
+                })
+                .collect();
+
+            let built = MixAndMatchPromotion::new(key, slots, mix(discount), budget);
+
+            match pool {
+                Some(pool) => promotion(built.with_budget_pool(pool)),
+                None => promotion(built),
+            }
+        }
+        PromotionSpec::Tiered { tiers } => {
+            let tiers = tiers
+                .iter()
+                .map(|spec| {
+                    ThresholdTier::new(
+                        TierThreshold::new(spec.lower_spend.map(money), spec.lower_count),
+                        spec.upper_count
+                            .map(TierThreshold::with_item_count_threshold),
+                        qualification(spec.contribution_tag),
+                        qualification(spec.discount_tag),
+                        threshold(&spec.discount),
+                    )
+                })
+                .collect();
+
+            let built = TieredThresholdPromotion::new(key, tiers, budget);
+
+            match pool {
+                Some(pool) => promotion(built.with_budget_pool(pool)),
+                None => promotion(built),
+            }
+        }
+    }
+}
+
+fn build(
+    scenario: &Scenario,
+) -> (
+    ItemGroup<'static>,
+    Vec<Promotion<'static>>,
+    BudgetPools<'static>,
+) {
+    // Lines of one product share their tags, so each generated line is its own product.
+    let mut products = SlotMap::<ProductKey, ()>::with_key();
+
+    let items: SmallVec<[Item<'static>; 10]> = scenario
+        .items
+        .iter()
+        .map(|spec| {
+            Item::with_tags(
+                products.insert(()),
+                money(spec.price),
+                StringTagCollection::from_strs(&spec.tags),
+            )
+            .with_quantity(spec.quantity)
+        })
+        .collect();
+
+    let mut pools = BudgetPools::default();
+    let pool = scenario
+        .pool
+        .as_ref()
+        .map(|spec| pools.insert(promotion_budget(spec)));
+
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let mut slot_keys = SlotMap::<PromotionSlotKey, ()>::with_key();
+
+    let promotions = scenario
+        .promotions
+        .iter()
+        .map(|(spec, budget, pooled)| {
+            build_promotion(
+                keys.insert(()),
+                spec,
+                promotion_budget(budget),
+                pool.filter(|_| *pooled),
+                &mut slot_keys,
+            )
+        })
+        .collect();
+
+    (ItemGroup::new(items, GBP), promotions, pools)
+}
+
+fn totals(scenario: &Scenario) -> Result<(i64, i64), SolverError> {
+    let (item_group, promotions, pools) = build(scenario);
+
+    let optimal = ILPSolver::solve_with_budget_pools(
+        &promotions,
+        &item_group,
+        &mut pools.clone(),
+        &mut NoopObserver,
+    )?;
+
+    let oracle =
+        ExhaustiveSolver::solve_with_budget_pools(&promotions, &item_group, &mut pools.clone())?;
+
+    Ok((
+        optimal.total.to_minor_units(),
+        oracle.total.to_minor_units(),
+    ))
+}
+
+proptest! {
+    // A fixed seed keeps the generated cases the same from run to run.
+    #![proptest_config(ProptestConfig {
+        cases: 512,
+        rng_seed: RngSeed::Fixed(0x1a77_1ce0),
+        ..ProptestConfig::default()
+    })]
+
+    #[test]
+    fn ilp_total_matches_the_exhaustive_optimum(scenario in scenario()) {
+        let (optimal, oracle) = totals(&scenario).map_err(|err| TestCaseError::fail(err.to_string()))?;
+
+        prop_assert_eq!(optimal, oracle, "ILP total against the brute-force optimum");
+    }
+}
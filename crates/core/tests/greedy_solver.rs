@@ -0,0 +1,112 @@
+//! Integration tests for the greedy heuristic solver
+//!
+//! The greedy solver prices every unit exactly once, never beats the ILP
+//! optimum, and reaches it on the fixture sets.
+
+mod common;
+
+use rustc_hash::FxHashMap;
+use testresult::TestResult;
+
+use lattice::{
+    fixtures::Fixture,
+    items::groups::ItemGroup,
+    solvers::{
+        SolutionQuality, Solver, SolverResult,
+        greedy::GreedySolver,
+        ilp::{ILPSolver, NoopObserver},
+    },
+};
+
+use common::{FIXTURE_SETS, crowded_basket, full_price};
+
+/// Assert every unit is priced once and the total adds up.
+fn assert_consistent(set: &str, item_group: &ItemGroup<'_>, result: &SolverResult<'_>) {
+    let mut claimed: FxHashMap<usize, u32> = FxHashMap::default();
+    let mut total = 0;
+
+    for redemption in &result.promotion_redemptions {
+        *claimed.entry(redemption.item_idx).or_default() += redemption.quantity;
+        total += redemption.final_price.to_minor_units() * i64::from(redemption.quantity);
+    }
+
+    for (item_idx, item) in item_group.iter().enumerate() {
+        let claimed = claimed.get(&item_idx).copied().unwrap_or(0);
+
+        assert!(
+            claimed <= item.quantity(),
+            "{set}: line {item_idx} claimed {claimed} of {} units",
+            item.quantity()
+        );
+
+        let free = item.quantity() - claimed;
+
+        assert_eq!(
+            result.unaffected_items.contains(&item_idx),
+            free > 0,
+            "{set}: line {item_idx} unaffected"
+        );
+
+        total += item.price().to_minor_units() * i64::from(free);
+    }
+
+    assert_eq!(result.total.to_minor_units(), total, "{set}: total adds up");
+}
+
+#[test]
+fn greedy_matches_the_optimum_on_fixture_sets() -> TestResult {
+    // The stand-alone greedy solver does not apply exclusion rules.
+    for set in FIXTURE_SETS.iter().filter(|&&set| set != "exclusions") {
+        let fixture = Fixture::from_set(set)?;
+        let item_group = fixture.item_group()?;
+
+        let optimal = ILPSolver::solve_with_budget_pools(
+            fixture.promotions(),
+            &item_group,
+            &mut fixture.budget_pools().clone(),
+            &mut NoopObserver,
+        )?;
+
+        let greedy = GreedySolver::solve_with_budget_pools(
+            fixture.promotions(),
+            &item_group,
+            &mut fixture.budget_pools().clone(),
+        )?;
+
+        assert_eq!(greedy.quality, SolutionQuality::Heuristic, "{set}: quality");
+        assert_eq!(greedy.total, optimal.total, "{set}: total");
+
+        assert_consistent(set, &item_group, &greedy);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn greedy_never_beats_the_optimum_and_scales_to_large_baskets() -> TestResult {
+    let (promotions, item_group) = crowded_basket(60);
+
+    let optimal = ILPSolver::solve(&promotions, &item_group)?;
+    let greedy = GreedySolver::solve(&promotions, &item_group)?;
+
+    assert!(
+        greedy.total.to_minor_units() >= optimal.total.to_minor_units(),
+        "greedy {} beat the optimum {}",
+        greedy.total,
+        optimal.total
+    );
+    assert!(
+        greedy.total.to_minor_units() < full_price(&item_group),
+        "greedy found savings"
+    );
+
+    assert_consistent("crowded", &item_group, &greedy);
+
+    // Far beyond what the ILP solves in reasonable time
+    let (promotions, item_group) = crowded_basket(1000);
+    let greedy = GreedySolver::solve(&promotions, &item_group)?;
+
+    assert_consistent("crowded-large", &item_group, &greedy);
+
+    Ok(())
+}
@@ -0,0 +1,278 @@
+//! Integration tests for joint (whole-graph) evaluation of layered promotions.
+
+use decimal_percentage::Percentage;
+use rustc_hash::FxHashSet;
+use rusty_money::{Money, iso::GBP};
+use slotmap::SlotMap;
+use testresult::TestResult;
+
+use lattice::{
+    discounts::SimpleDiscount,
+    graph::{EvaluationMode, GraphError, OutputMode, PromotionGraph, PromotionGraphBuilder},
+    items::{Item, groups::ItemGroup},
+    products::ProductKey,
+    promotions::{
+        Promotion, PromotionKey, PromotionSlotKey,
+        budget::PromotionBudget,
+        promotion,
+        qualification::Qualification,
+        types::{
+            DirectDiscountPromotion, MixAndMatchDiscount, MixAndMatchPromotion, ThresholdDiscount,
+            ThresholdTier, TierThreshold, TieredThresholdPromotion,
+        },
+    },
+    tags::string::StringTagCollection,
+    utils::slot,
+};
+
+fn percent_off(key: PromotionKey, tags: &[&str], pct: f64) -> Promotion<'static> {
+    promotion(DirectDiscountPromotion::new(
+        key,
+        Qualification::match_any(StringTagCollection::from_strs(tags)),
+        SimpleDiscount::PercentageOff(Percentage::from(pct)),
+        PromotionBudget::unlimited(),
+    ))
+}
+
+fn meal_deal(key: PromotionKey) -> Promotion<'static> {
+    let mut slot_keys = SlotMap::<PromotionSlotKey, ()>::with_key();
+
+    promotion(MixAndMatchPromotion::new(
+        key,
+        vec![
+            slot(
+                &mut slot_keys,
+                StringTagCollection::from_strs(&["main"]),
+                1,
+                Some(1),
+            ),
+            slot(
+                &mut slot_keys,
+                StringTagCollection::from_strs(&["drink"]),
+                1,
+                Some(1),
+            ),
+        ],
+        MixAndMatchDiscount::FixedTotal(Money::from_minor(500, GBP)),
+        PromotionBudget::unlimited(),
+    ))
+}
+
+fn meal_items() -> ItemGroup<'static> {
+    ItemGroup::new(
+        [
+            Item::with_tags(
+                ProductKey::default(),
+                Money::from_minor(400, GBP),
+                StringTagCollection::from_strs(&["main"]),
+            ),
+            Item::with_tags(
+                ProductKey::default(),
+                Money::from_minor(200, GBP),
+                StringTagCollection::from_strs(&["drink"]),
+            ),
+        ]
+        .into_iter()
+        .collect(),
+        GBP,
+    )
+}
+
+/// Layer 1: 5% off food (1000 -> 950).
+/// Layer 2: "Spend £10, get 50% off".
+///
+/// Greedy takes the 5% and then misses the threshold: 950.
+/// Joint skips the 5% so the threshold is met: 500.
+#[test]
+fn joint_mode_forgoes_upstream_discount_to_meet_downstream_threshold() -> TestResult {
+    let item_group = ItemGroup::new(
+        [Item::with_tags(
+            ProductKey::default(),
+            Money::from_minor(1000, GBP),
+            StringTagCollection::from_strs(&["food"]),
+        )]
+        .into_iter()
+        .collect(),
+        GBP,
+    );
+
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let food_key = keys.insert(());
+    let threshold_key = keys.insert(());
+
+    let threshold = promotion(TieredThresholdPromotion::new(
+        threshold_key,
+        vec![ThresholdTier::new(
+            TierThreshold::with_monetary_threshold(Money::from_minor(1000, GBP)),
+            None,
+            Qualification::match_any(StringTagCollection::from_strs(&["food"])),
+            Qualification::match_any(StringTagCollection::from_strs(&["food"])),
+            ThresholdDiscount::PercentEachItem(Percentage::from(0.50)),
+        )],
+        PromotionBudget::unlimited(),
+    ));
+
+    let mut builder = PromotionGraphBuilder::new();
+    let root = builder.add_layer(
+        "Food Deals",
+        [percent_off(food_key, &["food"], 0.05)],
+        OutputMode::PassThrough,
+    )?;
+    let spend = builder.add_layer("Spend & Save", [threshold], OutputMode::PassThrough)?;
+
+    builder.set_root(root);
+    builder.connect_pass_through(root, spend)?;
+
+    let graph = PromotionGraph::from_builder(builder)?;
+
+    let greedy = graph.evaluate(&item_group)?;
+
+    assert_eq!(greedy.total.to_minor_units(), 950);
+
+    let graph = graph.with_evaluation_mode(EvaluationMode::Joint);
+    let joint = graph.evaluate(&item_group)?;
+
+    assert_eq!(joint.total.to_minor_units(), 500);
+
+    let redemptions = joint
+        .item_redemptions
+        .get(&0)
+        .ok_or("item should be redeemed")?;
+
+    assert_eq!(redemptions.len(), 1, "food deal should be skipped");
+    assert_eq!(
+        redemptions.first().map(|r| r.promotion_key),
+        Some(threshold_key)
+    );
+    assert_eq!(
+        redemptions
+            .first()
+            .map(|r| r.original_price.to_minor_units()),
+        Some(1000),
+        "threshold layer should see the undiscounted price"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn joint_mode_redemption_indices_are_globally_unique() -> TestResult {
+    let item_group = ItemGroup::new(
+        [
+            Item::with_tags(
+                ProductKey::default(),
+                Money::from_minor(1000, GBP),
+                StringTagCollection::from_strs(&["food"]),
+            ),
+            Item::with_tags(
+                ProductKey::default(),
+                Money::from_minor(500, GBP),
+                StringTagCollection::from_strs(&["drink"]),
+            ),
+        ]
+        .into_iter()
+        .collect(),
+        GBP,
+    );
+
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let k1 = keys.insert(());
+    let k2 = keys.insert(());
+
+    let mut builder = PromotionGraphBuilder::new();
+    let root = builder.add_layer(
+        "Everything",
+        [percent_off(k1, &[], 0.10)],
+        OutputMode::PassThrough,
+    )?;
+    let second = builder.add_layer(
+        "Everything Again",
+        [percent_off(k2, &[], 0.10)],
+        OutputMode::PassThrough,
+    )?;
+
+    builder.set_root(root);
+    builder.connect_pass_through(root, second)?;
+
+    let graph = PromotionGraph::from_builder(builder)?.with_evaluation_mode(EvaluationMode::Joint);
+    let result = graph.evaluate(&item_group)?;
+
+    // 1000 -> 900 -> 810, 500 -> 450 -> 405
+    assert_eq!(result.total.to_minor_units(), 1215);
+
+    let mut seen = FxHashSet::default();
+
+    for redemption in result.item_redemptions.values().flatten() {
+        assert!(
+            seen.insert((redemption.promotion_key, redemption.redemption_idx)),
+            "redemption index {} repeated for the same promotion",
+            redemption.redemption_idx
+        );
+    }
+
+    assert_eq!(seen.len(), 4);
+
+    Ok(())
+}
+
+#[test]
+fn joint_mode_supports_bundle_totals_in_leaf_layers() -> TestResult {
+    let item_group = meal_items();
+
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let k1 = keys.insert(());
+    let k2 = keys.insert(());
+
+    let mut builder = PromotionGraphBuilder::new();
+    let root = builder.add_layer(
+        "Drinks",
+        [percent_off(k1, &["drink"], 0.50)],
+        OutputMode::PassThrough,
+    )?;
+    let meals = builder.add_layer("Meal Deals", [meal_deal(k2)], OutputMode::PassThrough)?;
+
+    builder.set_root(root);
+    builder.connect_pass_through(root, meals)?;
+
+    let graph = PromotionGraph::from_builder(builder)?.with_evaluation_mode(EvaluationMode::Joint);
+    let result = graph.evaluate(&item_group)?;
+
+    // Either route lands on 500: the half price drink (400 + 100) or the meal deal.
+    assert_eq!(result.total.to_minor_units(), 500);
+
+    Ok(())
+}
+
+#[test]
+fn joint_mode_rejects_bundle_totals_in_non_leaf_layers() -> TestResult {
+    let item_group = meal_items();
+
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let k1 = keys.insert(());
+    let k2 = keys.insert(());
+
+    let mut builder = PromotionGraphBuilder::new();
+    let root = builder.add_layer("Meal Deals", [meal_deal(k1)], OutputMode::PassThrough)?;
+    let leaf = builder.add_layer(
+        "Drinks",
+        [percent_off(k2, &["drink"], 0.50)],
+        OutputMode::PassThrough,
+    )?;
+
+    builder.set_root(root);
+    builder.connect_pass_through(root, leaf)?;
+
+    let graph = PromotionGraph::from_builder(builder)?.with_evaluation_mode(EvaluationMode::Joint);
+    let result = graph.evaluate(&item_group);
+
+    assert!(
+        matches!(
+            result,
+            Err(GraphError::JointPriceOutcomesUnavailable { promotion_key, .. })
+                if promotion_key == k1
+        ),
+        "expected JointPriceOutcomesUnavailable, got {result:?}"
+    );
+
+    Ok(())
+}
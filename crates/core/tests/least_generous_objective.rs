@@ -0,0 +1,228 @@
+//! Integration tests for least-generous objective modes
+//!
+//! A least-generous graph still forms every redemption the customer is entitled
+//! to, but picks the allocation that gives away the least discount.
+
+use decimal_percentage::Percentage;
+use rusty_money::{Money, iso::GBP};
+use slotmap::SlotMap;
+use smallvec::SmallVec;
+use testresult::TestResult;
+
+use lattice::{
+    discounts::SimpleDiscount,
+    graph::{EvaluationMode, GraphError, OutputMode, PromotionGraph, PromotionGraphBuilder},
+    items::{Item, groups::ItemGroup},
+    products::ProductKey,
+    promotions::{
+        PromotionKey,
+        budget::PromotionBudget,
+        promotion,
+        qualification::Qualification,
+        types::{DirectDiscountPromotion, PositionalDiscountPromotion},
+    },
+    solvers::ilp::ObjectiveMode,
+    tags::string::StringTagCollection,
+};
+
+const MODES: [EvaluationMode; 2] = [EvaluationMode::Greedy, EvaluationMode::Joint];
+
+fn shirts<'a>(prices: &[i64]) -> ItemGroup<'a> {
+    let items = prices.iter().map(|&price| {
+        Item::with_tags(
+            ProductKey::default(),
+            Money::from_minor(price, GBP),
+            StringTagCollection::from_strs(&["shirt"]),
+        )
+    });
+
+    ItemGroup::new(items.collect(), GBP)
+}
+
+/// Percentage off every shirt.
+fn percent_off_shirts(key: PromotionKey, pct: f64) -> DirectDiscountPromotion<'static> {
+    DirectDiscountPromotion::new(
+        key,
+        Qualification::match_any(StringTagCollection::from_strs(&["shirt"])),
+        SimpleDiscount::PercentageOff(Percentage::from(pct)),
+        PromotionBudget::unlimited(),
+    )
+}
+
+/// Buy one shirt, get the cheaper one free.
+fn bogof_shirts(key: PromotionKey) -> PositionalDiscountPromotion<'static> {
+    PositionalDiscountPromotion::new(
+        key,
+        Qualification::match_any(StringTagCollection::from_strs(&["shirt"])),
+        2,
+        SmallVec::from_slice(&[1]),
+        SimpleDiscount::PercentageOff(Percentage::from(1.0)),
+        PromotionBudget::unlimited(),
+    )
+}
+
+#[test]
+fn least_generous_pairs_pick_the_cheapest_free_items() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let key = keys.insert(());
+
+    for mode in MODES {
+        let graph = PromotionGraph::single_layer([promotion(bogof_shirts(key))])?
+            .with_evaluation_mode(mode);
+
+        let generous = graph.evaluate(&shirts(&[1000, 800, 600]))?;
+        let least = graph
+            .with_objective(ObjectiveMode::LeastGenerous)
+            .evaluate(&shirts(&[1000, 800, 600]))?;
+
+        // Only one pair fits: pairing the £8 shirt frees £8, while pairing the
+        // £6 shirt still forms the pair but only frees £6.
+        assert_eq!(generous.total.to_minor_units(), 1600, "{mode:?}");
+        assert_eq!(least.total.to_minor_units(), 1800, "{mode:?}");
+        assert_eq!(
+            least.item_redemptions.values().flatten().count(),
+            2,
+            "{mode:?}"
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn least_generous_applies_the_smallest_discount() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let (small, large) = (keys.insert(()), keys.insert(()));
+
+    for mode in MODES {
+        let graph = PromotionGraph::single_layer([
+            promotion(percent_off_shirts(small, 0.10)),
+            promotion(percent_off_shirts(large, 0.50)),
+        ])?
+        .with_evaluation_mode(mode)
+        .with_objective(ObjectiveMode::LeastGenerous);
+
+        let result = graph.evaluate(&shirts(&[1000, 500]))?;
+
+        // Every shirt is still discounted, but only by 10%.
+        assert_eq!(result.total.to_minor_units(), 1350, "{mode:?}");
+        assert!(
+            result
+                .item_redemptions
+                .values()
+                .flatten()
+                .all(|redemption| redemption.promotion_key == small),
+            "{mode:?}"
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn mandatory_mode_skips_optional_promotions() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let (mandatory, optional) = (keys.insert(()), keys.insert(()));
+
+    for mode in MODES {
+        let graph = PromotionGraph::single_layer([
+            promotion(percent_off_shirts(mandatory, 0.20).with_mandatory(true)),
+            promotion(percent_off_shirts(optional, 0.50)),
+        ])?
+        .with_evaluation_mode(mode);
+
+        let least = graph
+            .clone()
+            .with_objective(ObjectiveMode::LeastGenerous)
+            .evaluate(&shirts(&[1000]))?;
+        let mandatory_only = graph
+            .clone()
+            .with_objective(ObjectiveMode::LeastGenerousMandatory)
+            .evaluate(&shirts(&[1000]))?;
+
+        assert_eq!(least.total.to_minor_units(), 800, "{mode:?}");
+        assert_eq!(mandatory_only.total.to_minor_units(), 800, "{mode:?}");
+
+        let optional_only =
+            PromotionGraph::single_layer([promotion(percent_off_shirts(optional, 0.50))])?
+                .with_evaluation_mode(mode)
+                .with_objective(ObjectiveMode::LeastGenerousMandatory)
+                .evaluate(&shirts(&[1000]))?;
+
+        // Nothing is mandatory, so the customer pays full price.
+        assert_eq!(optional_only.total.to_minor_units(), 1000, "{mode:?}");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn layers_can_override_the_graph_objective() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let (small, large, bogof) = (keys.insert(()), keys.insert(()), keys.insert(()));
+
+    let mut builder = PromotionGraphBuilder::new();
+    let root = builder.add_layer(
+        "Percentages",
+        [
+            promotion(percent_off_shirts(small, 0.10)),
+            promotion(percent_off_shirts(large, 0.50)),
+        ],
+        OutputMode::PassThrough,
+    )?;
+    let pairs = builder.add_layer(
+        "Pairs",
+        [promotion(bogof_shirts(bogof))],
+        OutputMode::PassThrough,
+    )?;
+
+    builder.set_root(root);
+    builder.connect_pass_through(root, pairs)?;
+    builder.set_layer_objective(root, ObjectiveMode::LeastGenerous)?;
+
+    let graph = PromotionGraph::from_builder(builder)?;
+    let result = graph.evaluate(&shirts(&[1000, 800, 600, 400]))?;
+
+    // 10% off leaves 900, 720, 540 and 360, then the generous pairing frees 720 + 360.
+    assert_eq!(result.total.to_minor_units(), 1440);
+
+    Ok(())
+}
+
+#[test]
+fn joint_mode_rejects_mixed_objectives() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let (first, second) = (keys.insert(()), keys.insert(()));
+
+    let mut builder = PromotionGraphBuilder::new();
+    let root = builder.add_layer(
+        "First",
+        [promotion(percent_off_shirts(first, 0.10))],
+        OutputMode::PassThrough,
+    )?;
+    let next = builder.add_layer(
+        "Second",
+        [promotion(percent_off_shirts(second, 0.10))],
+        OutputMode::PassThrough,
+    )?;
+
+    builder.set_root(root);
+    builder.connect_pass_through(root, next)?;
+    builder.set_layer_objective(next, ObjectiveMode::LeastGenerous)?;
+
+    let graph = PromotionGraph::from_builder(builder)?.with_evaluation_mode(EvaluationMode::Joint);
+
+    assert!(matches!(
+        graph.evaluate(&shirts(&[1000])),
+        Err(GraphError::JointObjectiveMismatch { .. })
+    ));
+
+    // Setting the graph's objective to match every layer resolves it.
+    let result = graph
+        .with_objective(ObjectiveMode::LeastGenerous)
+        .evaluate(&shirts(&[1000]))?;
+
+    assert_eq!(result.total.to_minor_units(), 810);
+
+    Ok(())
+}
@@ -0,0 +1,401 @@
+//! Integration tests for price floors
+//!
+//! Promotions discount an item down to its floor and no further, within a layer
+//! and across stacked layers, in both graph evaluation modes.
+
+use decimal_percentage::Percentage;
+use rusty_money::{Money, iso::GBP};
+use slotmap::SlotMap;
+use testresult::TestResult;
+
+use lattice::{
+    discounts::SimpleDiscount,
+    graph::{EvaluationMode, OutputMode, PromotionGraph, PromotionGraphBuilder},
+    items::{Item, groups::ItemGroup},
+    pricing::PriceFloor,
+    products::ProductKey,
+    promotions::{
+        PromotionKey, PromotionSlotKey,
+        budget::{BudgetPools, PromotionBudget},
+        promotion,
+        qualification::Qualification,
+        redemptions::PromotionRedemption,
+        types::{
+            DirectDiscountPromotion, MixAndMatchDiscount, MixAndMatchPromotion, MixAndMatchSlot,
+        },
+    },
+    solvers::{
+        Solver,
+        ilp::{ILPSolver, NoopObserver},
+    },
+    tags::string::StringTagCollection,
+};
+
+const MODES: [EvaluationMode; 2] = [EvaluationMode::Greedy, EvaluationMode::Joint];
+
+fn item(tag: &str, price: i64) -> Item<'static> {
+    Item::with_tags(
+        ProductKey::default(),
+        Money::from_minor(price, GBP),
+        StringTagCollection::from_strs(&[tag]),
+    )
+}
+
+fn floored(item: Item<'static>, floor: PriceFloor<'static>) -> TestResult<Item<'static>> {
+    let minimum = floor.minimum_for(item.price())?;
+
+    Ok(match minimum {
+        Some(minimum) => item.with_price_floor(minimum),
+        None => item,
+    })
+}
+
+fn final_prices<'r>(
+    redemptions: impl IntoIterator<Item = &'r PromotionRedemption<'static>>,
+) -> Vec<i64> {
+    let mut prices: Vec<i64> = redemptions
+        .into_iter()
+        .map(|redemption| redemption.final_price.to_minor_units())
+        .collect();
+
+    prices.sort_unstable_by(|a, b| b.cmp(a));
+    prices
+}
+
+fn percent_off(key: PromotionKey, percent: f64) -> DirectDiscountPromotion<'static> {
+    DirectDiscountPromotion::new(
+        key,
+        Qualification::match_any(StringTagCollection::from_strs(&["sock"])),
+        SimpleDiscount::PercentageOff(Percentage::from(percent)),
+        PromotionBudget::unlimited(),
+    )
+}
+
+#[test]
+fn discount_stops_at_the_cost_price() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let key = keys.insert(());
+
+    let sock = floored(
+        item("sock", 100),
+        PriceFloor::default().with_cost_price(Money::from_minor(70, GBP)),
+    )?;
+
+    let item_group = ItemGroup::new([sock].into_iter().collect(), GBP);
+
+    let result = ILPSolver::solve(&[promotion(percent_off(key, 0.5))], &item_group)?;
+
+    let [redemption] = result.promotion_redemptions.as_slice() else {
+        panic!(
+            "expected one redemption, got {:?}",
+            result.promotion_redemptions
+        );
+    };
+
+    assert_eq!(result.total.to_minor_units(), 70);
+    assert_eq!(redemption.final_price.to_minor_units(), 70);
+    assert!(redemption.floor_limited);
+
+    Ok(())
+}
+
+#[test]
+fn discounts_above_the_floor_are_not_limited() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let key = keys.insert(());
+
+    let sock = floored(
+        item("sock", 100),
+        PriceFloor::default().with_minimum_price(Money::from_minor(50, GBP)),
+    )?;
+
+    let item_group = ItemGroup::new([sock].into_iter().collect(), GBP);
+
+    for mode in MODES {
+        let graph = PromotionGraph::single_layer([promotion(percent_off(key, 0.2))])?
+            .with_evaluation_mode(mode);
+
+        let result = graph.evaluate(&item_group)?;
+
+        assert_eq!(result.total.to_minor_units(), 80, "{mode:?}");
+        assert!(result.floor_limited_promotions().is_empty(), "{mode:?}");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn stacked_layers_share_one_largest_discount() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let (first, second) = (keys.insert(()), keys.insert(()));
+
+    let mut builder = PromotionGraphBuilder::new();
+    let root = builder.add_layer(
+        "Everyday",
+        [promotion(percent_off(first, 0.2))],
+        OutputMode::PassThrough,
+    )?;
+    let member = builder.add_layer(
+        "Members",
+        [promotion(percent_off(second, 0.2))],
+        OutputMode::PassThrough,
+    )?;
+
+    builder.set_root(root);
+    builder.connect_pass_through(root, member)?;
+
+    let graph = PromotionGraph::from_builder(builder)?;
+
+    // 20% and then 20% would take 36% off; at most 30% may go.
+    let sock = floored(
+        item("sock", 100),
+        PriceFloor::default().with_max_discount(Percentage::from(0.3)),
+    )?;
+
+    let item_group = ItemGroup::new([sock].into_iter().collect(), GBP);
+
+    for mode in MODES {
+        let result = graph
+            .clone()
+            .with_evaluation_mode(mode)
+            .evaluate(&item_group)?;
+
+        let finals: Vec<(i64, bool)> = result
+            .item_redemptions
+            .get(&0)
+            .into_iter()
+            .flatten()
+            .map(|redemption| {
+                (
+                    redemption.final_price.to_minor_units(),
+                    redemption.floor_limited,
+                )
+            })
+            .collect();
+
+        assert_eq!(result.total.to_minor_units(), 70, "{mode:?}");
+        assert_eq!(finals, [(80, false), (70, true)], "{mode:?}");
+        assert_eq!(
+            result.floor_limited_promotions().as_slice(),
+            &[second],
+            "{mode:?}"
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn solver_spends_a_limited_promotion_where_the_floor_allows_it() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let key = keys.insert(());
+
+    // Half off one sock only; the first sock can barely be discounted.
+    let once = DirectDiscountPromotion::new(
+        key,
+        Qualification::match_any(StringTagCollection::from_strs(&["sock"])),
+        SimpleDiscount::PercentageOff(Percentage::from(0.5)),
+        PromotionBudget {
+            redemption_limit: Some(1),
+            monetary_limit: None,
+        },
+    );
+
+    let item_group = ItemGroup::new(
+        [
+            floored(
+                item("sock", 100),
+                PriceFloor::default().with_cost_price(Money::from_minor(95, GBP)),
+            )?,
+            item("sock", 100),
+        ]
+        .into_iter()
+        .collect(),
+        GBP,
+    );
+
+    for mode in MODES {
+        let graph =
+            PromotionGraph::single_layer([promotion(once.clone())])?.with_evaluation_mode(mode);
+
+        let result = graph.evaluate(&item_group)?;
+
+        assert_eq!(result.total.to_minor_units(), 150, "{mode:?}");
+        assert_eq!(result.full_price_items.as_slice(), &[0], "{mode:?}");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn bundle_totals_are_moved_off_floored_items() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let key = keys.insert(());
+    let slot = |tag: &str| {
+        MixAndMatchSlot::new(
+            PromotionSlotKey::default(),
+            Qualification::match_any(StringTagCollection::from_strs(&[tag])),
+            1,
+            Some(1),
+        )
+    };
+
+    // A £3 meal deal would leave the £3 sandwich at £1.80, below its £2.50 cost.
+    let meal_deal = MixAndMatchPromotion::new(
+        key,
+        vec![slot("sandwich"), slot("drink")],
+        MixAndMatchDiscount::FixedTotal(Money::from_minor(300, GBP)),
+        PromotionBudget::unlimited(),
+    );
+
+    let item_group = ItemGroup::new(
+        [
+            floored(
+                item("sandwich", 300),
+                PriceFloor::default().with_cost_price(Money::from_minor(250, GBP)),
+            )?,
+            item("drink", 200),
+        ]
+        .into_iter()
+        .collect(),
+        GBP,
+    );
+
+    for mode in MODES {
+        let graph = PromotionGraph::single_layer([promotion(meal_deal.clone())])?
+            .with_evaluation_mode(mode);
+
+        let result = graph.evaluate(&item_group)?;
+
+        // The drink takes what the sandwich cannot, so the deal still costs £3.
+        assert_eq!(result.total.to_minor_units(), 300, "{mode:?}");
+        assert_eq!(
+            final_prices(result.item_redemptions.values().flatten()),
+            [250, 50],
+            "{mode:?}"
+        );
+        assert_eq!(
+            result.floor_limited_promotions().as_slice(),
+            &[key],
+            "{mode:?}"
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn bundle_savings_are_bounded_by_the_room_above_floors() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let key = keys.insert(());
+    let slot = |tag: &str| {
+        MixAndMatchSlot::new(
+            PromotionSlotKey::default(),
+            Qualification::match_any(StringTagCollection::from_strs(&[tag])),
+            1,
+            Some(1),
+        )
+    };
+
+    // The £3 meal deal would save £2, but the floors leave only £1.
+    let meal_deal = MixAndMatchPromotion::new(
+        key,
+        vec![slot("sandwich"), slot("drink")],
+        MixAndMatchDiscount::FixedTotal(Money::from_minor(300, GBP)),
+        PromotionBudget::unlimited(),
+    );
+
+    let item_group = ItemGroup::new(
+        [
+            item("sandwich", 300).with_price_floor(Money::from_minor(250, GBP)),
+            item("drink", 200).with_price_floor(Money::from_minor(150, GBP)),
+        ]
+        .into_iter()
+        .collect(),
+        GBP,
+    );
+
+    let result = ILPSolver::solve(&[promotion(meal_deal)], &item_group)?;
+
+    assert_eq!(result.total.to_minor_units(), 400);
+    assert_eq!(final_prices(&result.promotion_redemptions), [250, 150]);
+
+    Ok(())
+}
+
+#[test]
+fn monetary_budgets_are_charged_the_capped_discount() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+
+    // Half off would save 50p, but the floor leaves 20p, which the budget covers.
+    let deal = DirectDiscountPromotion::new(
+        keys.insert(()),
+        Qualification::match_any(StringTagCollection::from_strs(&["sock"])),
+        SimpleDiscount::PercentageOff(Percentage::from(0.5)),
+        PromotionBudget::with_monetary_limit(Money::from_minor(20, GBP)),
+    );
+
+    let sock = item("sock", 100).with_price_floor(Money::from_minor(80, GBP));
+    let item_group = ItemGroup::new([sock].into_iter().collect(), GBP);
+
+    let result = ILPSolver::solve(&[promotion(deal)], &item_group)?;
+
+    assert_eq!(result.total.to_minor_units(), 80);
+
+    Ok(())
+}
+
+#[test]
+fn budget_pools_are_charged_the_capped_discount() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let mut pools = BudgetPools::default();
+    let pool = pools.insert(PromotionBudget::with_monetary_limit(Money::from_minor(
+        50, GBP,
+    )));
+
+    let deal = percent_off(keys.insert(()), 0.5).with_budget_pool(pool);
+
+    // Both socks fit the pool once the first is capped at its floor.
+    let item_group = ItemGroup::new(
+        [
+            item("sock", 100).with_price_floor(Money::from_minor(80, GBP)),
+            item("sock", 60),
+        ]
+        .into_iter()
+        .collect(),
+        GBP,
+    );
+
+    let result = ILPSolver::solve_with_budget_pools(
+        &[promotion(deal)],
+        &item_group,
+        &mut pools,
+        &mut NoopObserver,
+    )?;
+
+    assert_eq!(result.total.to_minor_units(), 80 + 30);
+    assert_eq!(
+        pools
+            .get(pool)
+            .and_then(|budget| budget.monetary_limit)
+            .map(|limit| limit.to_minor_units()),
+        Some(0)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn floor_above_the_price_only_stops_further_discount() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let key = keys.insert(());
+
+    let sock = item("sock", 100).with_price_floor(Money::from_minor(120, GBP));
+    let item_group = ItemGroup::new([sock].into_iter().collect(), GBP);
+
+    let result = ILPSolver::solve(&[promotion(percent_off(key, 0.5))], &item_group)?;
+
+    assert_eq!(result.total.to_minor_units(), 100);
+
+    Ok(())
+}
@@ -0,0 +1,303 @@
+//! Integration tests for near-miss and upsell explanations
+//!
+//! After evaluating a basket, every promotion in the graph is explained: either
+//! how often it redeemed (and how to unlock more), or why it did not.
+
+use decimal_percentage::Percentage;
+use rusty_money::{Money, iso::GBP};
+use slotmap::SlotMap;
+use smallvec::SmallVec;
+use testresult::TestResult;
+
+use lattice::{
+    discounts::SimpleDiscount,
+    graph::{
+        EvaluationMode, OutputMode, PromotionExplanation, PromotionGraph, PromotionGraphBuilder,
+        PromotionOutcome,
+    },
+    items::{Item, groups::ItemGroup},
+    products::ProductKey,
+    promotions::{
+        PromotionKey,
+        budget::{BudgetPools, PromotionBudget},
+        coupon::PromotionCoupon,
+        explain::{MissReason, Shortfall},
+        promotion,
+        qualification::Qualification,
+        types::{
+            DirectDiscountPromotion, PositionalDiscountPromotion, ThresholdDiscount, ThresholdTier,
+            TierThreshold, TieredThresholdPromotion,
+        },
+    },
+    tags::string::StringTagCollection,
+};
+
+fn basket<'a>() -> ItemGroup<'a> {
+    let items = [(1500, "wine"), (400, "cheese"), (300, "snack")].map(|(price, tag)| {
+        Item::with_tags(
+            ProductKey::default(),
+            Money::from_minor(price, GBP),
+            StringTagCollection::from_strs(&[tag]),
+        )
+    });
+
+    ItemGroup::new(items.into_iter().collect(), GBP)
+}
+
+fn direct(key: PromotionKey, tag: &str, pct: f64) -> DirectDiscountPromotion<'static> {
+    DirectDiscountPromotion::new(
+        key,
+        Qualification::match_any(StringTagCollection::from_strs(&[tag])),
+        SimpleDiscount::PercentageOff(Percentage::from(pct)),
+        PromotionBudget::unlimited(),
+    )
+}
+
+fn wine_tier(threshold: i64, pct: f64) -> ThresholdTier<'static> {
+    ThresholdTier::new(
+        TierThreshold::with_monetary_threshold(Money::from_minor(threshold, GBP)),
+        None,
+        Qualification::match_any(StringTagCollection::from_strs(&["wine"])),
+        Qualification::match_any(StringTagCollection::from_strs(&["wine"])),
+        ThresholdDiscount::PercentEachItem(Percentage::from(pct)),
+    )
+}
+
+fn outcome<'a>(
+    explanations: &'a [PromotionExplanation<'a>],
+    key: PromotionKey,
+) -> Option<&'a PromotionOutcome<'a>> {
+    explanations
+        .iter()
+        .find(|explanation| explanation.promotion_key == key)
+        .map(|explanation| &explanation.outcome)
+}
+
+#[test]
+fn every_promotion_is_explained() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+
+    let snack_sale = keys.insert(());
+    let snack_small = keys.insert(());
+    let fish = keys.insert(());
+    let snack_bogof = keys.insert(());
+    let members = keys.insert(());
+    let exhausted = keys.insert(());
+    let spend_and_save = keys.insert(());
+
+    let promotions = [
+        promotion(direct(snack_sale, "snack", 0.50)),
+        promotion(direct(snack_small, "snack", 0.10)),
+        promotion(direct(fish, "fish", 0.50)),
+        promotion(PositionalDiscountPromotion::new(
+            snack_bogof,
+            Qualification::match_any(StringTagCollection::from_strs(&["snack"])),
+            2,
+            SmallVec::from_slice(&[1]),
+            SimpleDiscount::PercentageOff(Percentage::from(1.0)),
+            PromotionBudget::unlimited(),
+        )),
+        promotion(
+            direct(members, "cheese", 0.25).with_coupon(PromotionCoupon::multi_use(["MEMBER"])),
+        ),
+        promotion(DirectDiscountPromotion::new(
+            exhausted,
+            Qualification::match_any(StringTagCollection::from_strs(&["cheese"])),
+            SimpleDiscount::PercentageOff(Percentage::from(0.10)),
+            PromotionBudget::with_redemption_limit(0),
+        )),
+        promotion(TieredThresholdPromotion::new(
+            spend_and_save,
+            vec![wine_tier(2000, 0.10), wine_tier(5000, 0.20)],
+            PromotionBudget::unlimited(),
+        )),
+    ];
+
+    for mode in [EvaluationMode::Greedy, EvaluationMode::Joint] {
+        let graph = PromotionGraph::single_layer(promotions.clone())?.with_evaluation_mode(mode);
+        let item_group = basket();
+
+        let result = graph.evaluate(&item_group)?;
+        let explanations = graph.explain(&item_group, &result);
+
+        assert_eq!(explanations.len(), 7, "{mode:?}");
+
+        assert_eq!(
+            outcome(&explanations, snack_sale),
+            Some(&PromotionOutcome::Redeemed {
+                redemptions: 1,
+                upsell: None,
+            }),
+            "{mode:?} snack sale"
+        );
+
+        assert_eq!(
+            outcome(&explanations, snack_small),
+            Some(&PromotionOutcome::Missed(MissReason::Outcompeted {
+                by: SmallVec::from_slice(&[snack_sale]),
+            })),
+            "{mode:?} smaller snack discount"
+        );
+
+        assert_eq!(
+            outcome(&explanations, fish),
+            Some(&PromotionOutcome::Missed(MissReason::Shortfall(
+                Shortfall::NoQualifyingItems
+            ))),
+            "{mode:?} fish"
+        );
+
+        assert_eq!(
+            outcome(&explanations, snack_bogof),
+            Some(&PromotionOutcome::Missed(MissReason::Shortfall(
+                Shortfall::Items {
+                    required: 2,
+                    available: 1,
+                }
+            ))),
+            "{mode:?} bogof"
+        );
+
+        assert_eq!(
+            outcome(&explanations, members),
+            Some(&PromotionOutcome::Missed(MissReason::CouponNotPresented)),
+            "{mode:?} members"
+        );
+
+        assert_eq!(
+            outcome(&explanations, exhausted),
+            Some(&PromotionOutcome::Missed(MissReason::BudgetExhausted)),
+            "{mode:?} exhausted"
+        );
+
+        // "Add £5 more wine to unlock 10% off"
+        assert_eq!(
+            outcome(&explanations, spend_and_save),
+            Some(&PromotionOutcome::Missed(MissReason::Shortfall(
+                Shortfall::Threshold {
+                    tier: 0,
+                    spend: Some(Money::from_minor(500, GBP)),
+                    items: None,
+                }
+            ))),
+            "{mode:?} spend and save"
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn redeemed_tier_reports_upsell_to_next_tier() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let key = keys.insert(());
+
+    let graph = PromotionGraph::single_layer([promotion(TieredThresholdPromotion::new(
+        key,
+        vec![wine_tier(1000, 0.10), wine_tier(2000, 0.20)],
+        PromotionBudget::unlimited(),
+    ))])?;
+
+    let item_group = basket();
+    let result = graph.evaluate(&item_group)?;
+    let explanations = graph.explain(&item_group, &result);
+
+    // "Add £5 more wine to move from 10% to 20% off"
+    assert_eq!(
+        outcome(&explanations, key),
+        Some(&PromotionOutcome::Redeemed {
+            redemptions: 1,
+            upsell: Some(Shortfall::Threshold {
+                tier: 1,
+                spend: Some(Money::from_minor(500, GBP)),
+                items: None,
+            }),
+        })
+    );
+
+    Ok(())
+}
+
+#[test]
+fn outcompeted_lists_only_promotions_on_the_same_items() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let (snack_sale, snack_small, wine_sale) = (keys.insert(()), keys.insert(()), keys.insert(()));
+
+    for mode in [EvaluationMode::Greedy, EvaluationMode::Joint] {
+        let graph = PromotionGraph::single_layer([
+            promotion(direct(snack_sale, "snack", 0.50)),
+            promotion(direct(snack_small, "snack", 0.10)),
+            promotion(direct(wine_sale, "wine", 0.10)),
+        ])?
+        .with_evaluation_mode(mode);
+
+        let item_group = basket();
+        let result = graph.evaluate(&item_group)?;
+        let explanations = graph.explain(&item_group, &result);
+
+        // The wine offer redeemed too, but never on a snack.
+        assert_eq!(
+            outcome(&explanations, snack_small),
+            Some(&PromotionOutcome::Missed(MissReason::Outcompeted {
+                by: SmallVec::from_slice(&[snack_sale]),
+            })),
+            "{mode:?}"
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn shared_pools_used_by_earlier_layers_are_reported_exhausted() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let (cheese_sale, wine_sale) = (keys.insert(()), keys.insert(()));
+
+    // The cheese offer saves £2 first; the wine offer would need £1.50 more.
+    for (limit, mode) in [
+        (200, EvaluationMode::Greedy),
+        (250, EvaluationMode::Greedy),
+        (200, EvaluationMode::Joint),
+        (250, EvaluationMode::Joint),
+    ] {
+        let mut pools = BudgetPools::default();
+        let pool = pools.insert(PromotionBudget::with_monetary_limit(Money::from_minor(
+            limit, GBP,
+        )));
+
+        let mut builder = PromotionGraphBuilder::new();
+
+        let first = builder.add_layer(
+            "Cheese",
+            [promotion(
+                direct(cheese_sale, "cheese", 0.50).with_budget_pool(pool),
+            )],
+            OutputMode::PassThrough,
+        )?;
+
+        let second = builder.add_layer(
+            "Wine",
+            [promotion(
+                direct(wine_sale, "wine", 0.10).with_budget_pool(pool),
+            )],
+            OutputMode::PassThrough,
+        )?;
+
+        builder.set_root(first);
+        builder.connect_pass_through(first, second)?;
+        builder.set_budget_pools(pools);
+
+        let graph = PromotionGraph::from_builder(builder)?.with_evaluation_mode(mode);
+        let item_group = basket();
+        let result = graph.evaluate(&item_group)?;
+        let explanations = graph.explain(&item_group, &result);
+
+        assert_eq!(
+            outcome(&explanations, wine_sale),
+            Some(&PromotionOutcome::Missed(MissReason::PoolExhausted(pool))),
+            "{mode:?} with {limit}p in the pool"
+        );
+    }
+
+    Ok(())
+}
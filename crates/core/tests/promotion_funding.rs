@@ -0,0 +1,187 @@
+//! Integration tests for promotion funding attribution
+//!
+//! Every redemption reports who pays for its savings, in flat solves and in both
+//! graph evaluation modes.
+
+use std::num::NonZeroU32;
+
+use decimal_percentage::Percentage;
+use rustc_hash::FxHashMap;
+use rusty_money::{Money, iso::GBP};
+use slotmap::SlotMap;
+use smallvec::SmallVec;
+use testresult::TestResult;
+
+use lattice::{
+    discounts::SimpleDiscount,
+    graph::{EvaluationMode, PromotionGraph},
+    items::{Item, groups::ItemGroup},
+    products::ProductKey,
+    promotions::{
+        PromotionKey,
+        budget::PromotionBudget,
+        funding::{FundingParty, PromotionFunding, SupplierShare},
+        promotion,
+        qualification::Qualification,
+        redemptions::PromotionRedemption,
+        types::{DirectDiscountPromotion, PositionalDiscountPromotion},
+    },
+    solvers::{Solver, ilp::ILPSolver},
+    tags::string::StringTagCollection,
+};
+
+const MODES: [EvaluationMode; 2] = [EvaluationMode::Greedy, EvaluationMode::Joint];
+
+fn shirts<'a>(prices: &[i64]) -> ItemGroup<'a> {
+    let items = prices.iter().map(|&price| {
+        Item::with_tags(
+            ProductKey::default(),
+            Money::from_minor(price, GBP),
+            StringTagCollection::from_strs(&["shirt"]),
+        )
+    });
+
+    ItemGroup::new(items.collect(), GBP)
+}
+
+/// Second of every two shirts half price.
+fn half_price_pairs(key: PromotionKey) -> PositionalDiscountPromotion<'static> {
+    PositionalDiscountPromotion::new(
+        key,
+        Qualification::match_any(StringTagCollection::from_strs(&["shirt"])),
+        2,
+        SmallVec::from_slice(&[1]),
+        SimpleDiscount::PercentageOff(Percentage::from(0.5)),
+        PromotionBudget::unlimited(),
+    )
+}
+
+/// Amount each party funds across `redemptions`, in minor units.
+fn funded<'r, 'a: 'r>(
+    redemptions: impl IntoIterator<Item = &'r PromotionRedemption<'a>>,
+) -> FxHashMap<FundingParty, i64> {
+    let mut totals = FxHashMap::default();
+
+    for funded in redemptions
+        .into_iter()
+        .flat_map(|redemption| &redemption.funding)
+    {
+        *totals.entry(funded.party.clone()).or_default() += funded.amount.to_minor_units();
+    }
+
+    totals
+}
+
+fn acme() -> FundingParty {
+    FundingParty::Supplier("Acme".to_string())
+}
+
+#[test]
+fn each_redemption_line_is_fully_funded() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let key = keys.insert(());
+
+    let funding = PromotionFunding::split([SupplierShare::amount_per_redemption(
+        "Acme",
+        Money::from_minor(100, GBP),
+    )])?;
+
+    let result = ILPSolver::solve(
+        &[promotion(half_price_pairs(key).with_funding(funding))],
+        &shirts(&[1000, 1000, 400, 400]),
+    )?;
+
+    for redemption in &result.promotion_redemptions {
+        let line_funding: i64 = redemption
+            .funding
+            .iter()
+            .map(|funded| funded.amount.to_minor_units())
+            .sum();
+
+        assert_eq!(line_funding, redemption.total_savings()?.to_minor_units());
+    }
+
+    // Two pairs save £5 and £2; Acme pays £1 of each.
+    assert_eq!(
+        funded(&result.promotion_redemptions),
+        FxHashMap::from_iter([(acme(), 200), (FundingParty::Retailer, 500)])
+    );
+
+    Ok(())
+}
+
+#[test]
+fn graphs_attribute_funding_in_both_modes() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let (pairs, everything) = (keys.insert(()), keys.insert(()));
+
+    for mode in MODES {
+        let graph = PromotionGraph::single_layer([
+            promotion(
+                half_price_pairs(pairs).with_funding(PromotionFunding::split([
+                    SupplierShare::percentage("Acme", Percentage::from(0.25)),
+                ])?),
+            ),
+            promotion(
+                DirectDiscountPromotion::new(
+                    everything,
+                    Qualification::match_any(StringTagCollection::from_strs(&["hat"])),
+                    SimpleDiscount::PercentageOff(Percentage::from(0.1)),
+                    PromotionBudget::unlimited(),
+                )
+                .with_funding(PromotionFunding::supplier("Hatters")),
+            ),
+        ])?
+        .with_evaluation_mode(mode);
+
+        let result = graph.evaluate(&shirts(&[1000, 1000]))?;
+
+        // The pair saves £5, a quarter of it from Acme.
+        assert_eq!(result.total.to_minor_units(), 1500, "{mode:?}");
+        assert_eq!(
+            funded(result.item_redemptions.values().flatten()),
+            FxHashMap::from_iter([(acme(), 125), (FundingParty::Retailer, 375)]),
+            "{mode:?}"
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn fixed_funding_is_paid_for_every_unit_of_a_quantity_line() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+
+    let funding = PromotionFunding::split([SupplierShare::amount_per_redemption(
+        "Acme",
+        Money::from_minor(100, GBP),
+    )])?;
+
+    let deal = DirectDiscountPromotion::new(
+        keys.insert(()),
+        Qualification::match_any(StringTagCollection::from_strs(&["shirt"])),
+        SimpleDiscount::PercentageOff(Percentage::from(0.2)),
+        PromotionBudget::unlimited(),
+    )
+    .with_funding(funding);
+
+    let line = Item::with_tags(
+        ProductKey::default(),
+        Money::from_minor(1000, GBP),
+        StringTagCollection::from_strs(&["shirt"]),
+    )
+    .with_quantity(NonZeroU32::new(3).ok_or("non-zero quantity")?);
+
+    let result = ILPSolver::solve(
+        &[promotion(deal)],
+        &ItemGroup::new(SmallVec::from_iter([line]), GBP),
+    )?;
+
+    // Three units each save £2 and each is a redemption, so Acme pays £1 three times.
+    assert_eq!(
+        funded(&result.promotion_redemptions),
+        FxHashMap::from_iter([(acme(), 300), (FundingParty::Retailer, 300)])
+    );
+
+    Ok(())
+}
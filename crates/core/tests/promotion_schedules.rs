@@ -0,0 +1,220 @@
+//! Integration tests for promotion schedules
+//!
+//! A single long-lived graph must price each basket according to the promotions
+//! live at the evaluation instant.
+
+use decimal_percentage::Percentage;
+use jiff::{
+    Timestamp,
+    civil::{Weekday, time},
+    tz::TimeZone,
+};
+use rusty_money::{Money, iso::GBP};
+use slotmap::SlotMap;
+use smallvec::SmallVec;
+use testresult::TestResult;
+
+use lattice::{
+    context::EvaluationContext,
+    discounts::SimpleDiscount,
+    graph::{EvaluationMode, PromotionGraph},
+    items::{Item, groups::ItemGroup},
+    products::ProductKey,
+    promotions::{
+        PromotionKey,
+        budget::PromotionBudget,
+        promotion,
+        qualification::{BoolOp, Qualification, QualificationRule},
+        schedule::{PromotionSchedule, RecurringWindow},
+        types::{DirectDiscountPromotion, PositionalDiscountPromotion},
+    },
+    solvers::{Solver, ilp::ILPSolver},
+    tags::string::StringTagCollection,
+};
+
+fn basket<'a>() -> ItemGroup<'a> {
+    let items = [(500, "beer"), (500, "beer"), (300, "crisps")].map(|(price, tag)| {
+        Item::with_tags(
+            ProductKey::default(),
+            Money::from_minor(price, GBP),
+            StringTagCollection::from_strs(&[tag]),
+        )
+    });
+
+    ItemGroup::new(SmallVec::from_iter(items), GBP)
+}
+
+/// Half-price beer 16:00–18:00 London time on weekdays, and a crisps
+/// promotion that only runs during March 2026.
+fn build_graph(mode: EvaluationMode) -> TestResult<PromotionGraph<'static>> {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+
+    let happy_hour = PromotionSchedule::always()
+        .with_time_zone(TimeZone::get("Europe/London")?)
+        .with_window(RecurringWindow::new(
+            [
+                Weekday::Monday,
+                Weekday::Tuesday,
+                Weekday::Wednesday,
+                Weekday::Thursday,
+                Weekday::Friday,
+            ],
+            time(16, 0, 0, 0),
+            time(18, 0, 0, 0),
+        ));
+
+    let march = PromotionSchedule::between(
+        Some("2026-03-01T00:00:00Z".parse()?),
+        Some("2026-04-01T00:00:00Z".parse()?),
+    );
+
+    let promotions = [
+        promotion(
+            DirectDiscountPromotion::new(
+                keys.insert(()),
+                Qualification::match_any(StringTagCollection::from_strs(&["beer"])),
+                SimpleDiscount::PercentageOff(Percentage::from(0.50)),
+                PromotionBudget::unlimited(),
+            )
+            .with_schedule(happy_hour),
+        ),
+        promotion(
+            DirectDiscountPromotion::new(
+                keys.insert(()),
+                Qualification::match_any(StringTagCollection::from_strs(&["crisps"])),
+                SimpleDiscount::AmountOverride(Money::from_minor(100, GBP)),
+                PromotionBudget::unlimited(),
+            )
+            .with_schedule(march),
+        ),
+    ];
+
+    Ok(PromotionGraph::single_layer(promotions)?.with_evaluation_mode(mode))
+}
+
+#[test]
+fn long_lived_graph_prices_by_evaluation_instant() -> TestResult {
+    let cases: [(&str, i64); 5] = [
+        // Wednesday 4 March, 16:30 GMT: happy hour and March crisps
+        ("2026-03-04T16:30:00Z", 600),
+        // Wednesday 4 March, 12:00 GMT: March crisps only
+        ("2026-03-04T12:00:00Z", 1100),
+        // Saturday 7 March, 16:30 GMT: no happy hour at weekends
+        ("2026-03-07T16:30:00Z", 1100),
+        // Wednesday 8 April, 16:30 BST (15:30 UTC): happy hour only
+        ("2026-04-08T15:30:00Z", 800),
+        // Wednesday 8 April, 17:30 UTC is 18:30 BST: nothing
+        ("2026-04-08T17:30:00Z", 1300),
+    ];
+
+    for mode in [EvaluationMode::Greedy, EvaluationMode::Joint] {
+        let graph = build_graph(mode)?;
+
+        for (instant, expected) in cases {
+            let result = graph.evaluate_at(&basket(), instant.parse()?)?;
+
+            assert_eq!(
+                result.total.to_minor_units(),
+                expected,
+                "{mode:?} total at {instant}"
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn scheduled_promotions_are_inactive_without_a_timestamp() -> TestResult {
+    let graph = build_graph(EvaluationMode::Greedy)?;
+
+    assert_eq!(graph.evaluate(&basket())?.total.to_minor_units(), 1300);
+
+    Ok(())
+}
+
+#[test]
+fn evaluate_at_keeps_the_rest_of_the_context() -> TestResult {
+    let staff_only = promotion(
+        DirectDiscountPromotion::new(
+            PromotionKey::default(),
+            Qualification::new(
+                BoolOp::And,
+                SmallVec::from_vec(vec![QualificationRule::CustomerSegment {
+                    segments: StringTagCollection::from_strs(&["staff"]),
+                }]),
+            ),
+            SimpleDiscount::PercentageOff(Percentage::from(0.10)),
+            PromotionBudget::unlimited(),
+        )
+        .with_schedule(PromotionSchedule::between(
+            None,
+            Some("2026-01-01T00:00:00Z".parse()?),
+        )),
+    );
+
+    let graph = PromotionGraph::single_layer([staff_only])?;
+    let staff_basket = basket().with_context(
+        EvaluationContext::new().with_customer_segments(StringTagCollection::from_strs(&["staff"])),
+    );
+
+    let before: Timestamp = "2025-12-31T23:59:59Z".parse()?;
+    let after: Timestamp = "2026-01-01T00:00:00Z".parse()?;
+
+    assert_eq!(
+        graph
+            .evaluate_at(&staff_basket, before)?
+            .total
+            .to_minor_units(),
+        1170
+    );
+    assert_eq!(
+        graph
+            .evaluate_at(&staff_basket, after)?
+            .total
+            .to_minor_units(),
+        1300
+    );
+    assert_eq!(
+        graph.evaluate_at(&basket(), before)?.total.to_minor_units(),
+        1300
+    );
+
+    Ok(())
+}
+
+#[test]
+fn solver_skips_positional_promotion_outside_its_schedule() -> TestResult {
+    let bogof =
+        [promotion(
+            PositionalDiscountPromotion::new(
+                PromotionKey::default(),
+                Qualification::match_any(StringTagCollection::from_strs(&["beer"])),
+                2,
+                SmallVec::from_vec(vec![1]),
+                SimpleDiscount::PercentageOff(Percentage::from(1.0)),
+                PromotionBudget::unlimited(),
+            )
+            .with_schedule(PromotionSchedule::always().with_window(
+                RecurringWindow::new([Weekday::Friday], time(22, 0, 0, 0), time(2, 0, 0, 0)),
+            )),
+        )];
+
+    let at = |instant: &str| -> TestResult<i64> {
+        let item_group =
+            basket().with_context(EvaluationContext::new().with_timestamp(instant.parse()?));
+
+        Ok(ILPSolver::solve(&bogof, &item_group)?
+            .total
+            .to_minor_units())
+    };
+
+    // Friday 23:00 and Saturday 01:00 UTC are inside the late-night window.
+    assert_eq!(at("2026-03-06T23:00:00Z")?, 800);
+    assert_eq!(at("2026-03-07T01:00:00Z")?, 800);
+
+    // Saturday 23:00 UTC is not.
+    assert_eq!(at("2026-03-07T23:00:00Z")?, 1300);
+
+    Ok(())
+}
@@ -0,0 +1,453 @@
+//! Integration tests for quantity line items
+//!
+//! A line carrying `quantity` units must price exactly like the same basket with
+//! one item per unit, while keeping the ILP size independent of the quantity.
+
+use std::num::{NonZeroU32, TryFromIntError};
+
+use decimal_percentage::Percentage;
+use good_lp::{Expression, Variable};
+use rusty_money::{Money, iso::GBP};
+use slotmap::SlotMap;
+use smallvec::SmallVec;
+use testresult::TestResult;
+
+use lattice::{
+    discounts::SimpleDiscount,
+    graph::{EvaluationMode, OutputMode, PromotionGraph, PromotionGraphBuilder},
+    items::{Item, groups::ItemGroup},
+    products::ProductKey,
+    promotions::{
+        Promotion, PromotionKey,
+        budget::PromotionBudget,
+        promotion,
+        qualification::Qualification,
+        types::{
+            DirectDiscountPromotion, MixAndMatchDiscount, MixAndMatchPromotion,
+            PositionalDiscountPromotion, ThresholdDiscount, ThresholdTier, TierThreshold,
+            TieredThresholdPromotion,
+        },
+    },
+    solvers::{
+        Solver,
+        ilp::{ILPSolver, observer::ILPObserver},
+    },
+    tags::string::StringTagCollection,
+    utils::slot,
+};
+
+#[derive(Debug, Default)]
+struct VariableCounter {
+    presence: usize,
+    promotion: usize,
+    auxiliary: usize,
+}
+
+impl ILPObserver for VariableCounter {
+    fn on_presence_variable(&mut self, _item_idx: usize, _var: Variable, _price_minor: i64) {
+        self.presence += 1;
+    }
+
+    fn on_promotion_variable(
+        &mut self,
+        _promotion_key: PromotionKey,
+        _item_idx: usize,
+        _var: Variable,
+        _discounted_price_minor: i64,
+        _metadata: Option<&str>,
+    ) {
+        self.promotion += 1;
+    }
+
+    fn on_auxiliary_variable(
+        &mut self,
+        _promotion_key: PromotionKey,
+        _var: Variable,
+        _role: &str,
+        _position: Option<usize>,
+        _state: Option<usize>,
+    ) {
+        self.auxiliary += 1;
+    }
+
+    fn on_exclusivity_constraint(&mut self, _item_idx: usize, _constraint_expr: &Expression) {}
+
+    fn on_promotion_constraint(
+        &mut self,
+        _promotion_key: PromotionKey,
+        _constraint_type: &str,
+        _constraint_expr: &Expression,
+        _relation: &str,
+        _rhs: f64,
+    ) {
+    }
+}
+
+fn line<'a>(price_minor: i64, tags: &[&str], quantity: u32) -> Result<Item<'a>, TryFromIntError> {
+    Ok(Item::with_tags(
+        ProductKey::default(),
+        Money::from_minor(price_minor, GBP),
+        StringTagCollection::from_strs(tags),
+    )
+    .with_quantity(NonZeroU32::try_from(quantity)?))
+}
+
+/// Expand quantity lines into one single-unit item per unit.
+fn expand<'a>(lines: &[Item<'a>]) -> SmallVec<[Item<'a>; 10]> {
+    lines
+        .iter()
+        .flat_map(|item| {
+            (0..item.quantity()).map(move |_| item.clone().with_quantity(NonZeroU32::MIN))
+        })
+        .collect()
+}
+
+/// Solve both the quantity basket and its expanded form, asserting equal totals.
+fn assert_matches_expanded(promotions: &[Promotion<'_>], lines: &[Item<'_>]) -> TestResult<i64> {
+    let quantity_group = ItemGroup::new(lines.iter().cloned().collect(), GBP);
+    let expanded_group = ItemGroup::new(expand(lines), GBP);
+
+    let quantity_result = ILPSolver::solve(promotions, &quantity_group)?;
+    let expanded_result = ILPSolver::solve(promotions, &expanded_group)?;
+
+    assert_eq!(
+        quantity_result.total, expanded_result.total,
+        "quantity lines should price like the expanded basket"
+    );
+
+    let redeemed_total: i64 = quantity_result
+        .promotion_redemptions
+        .iter()
+        .map(|redemption| redemption.total_final_price().map(|m| m.to_minor_units()))
+        .sum::<Result<i64, _>>()?;
+
+    let full_price_total: i64 = lines
+        .iter()
+        .enumerate()
+        .map(|(idx, item)| {
+            let redeemed_units: u32 = quantity_result
+                .promotion_redemptions
+                .iter()
+                .filter(|redemption| redemption.item_idx == idx)
+                .map(|redemption| redemption.quantity)
+                .sum();
+
+            item.price().to_minor_units() * i64::from(item.quantity() - redeemed_units)
+        })
+        .sum();
+
+    assert_eq!(
+        redeemed_total + full_price_total,
+        quantity_result.total.to_minor_units(),
+        "redemptions should account for every unit exactly once"
+    );
+
+    Ok(quantity_result.total.to_minor_units())
+}
+
+#[test]
+fn direct_discount_quantity_line_matches_expanded_basket() -> TestResult {
+    let lines = [line(120, &["fruit"], 6)?, line(300, &["bakery"], 2)?];
+
+    let promotions = [promotion(DirectDiscountPromotion::new(
+        PromotionKey::default(),
+        Qualification::match_any(StringTagCollection::from_strs(&["fruit"])),
+        SimpleDiscount::PercentageOff(Percentage::from(0.25)),
+        PromotionBudget {
+            redemption_limit: Some(4),
+            monetary_limit: None,
+        },
+    ))];
+
+    // 4 fruit at 90, 2 fruit at 120, 2 bakery at 300
+    assert_eq!(assert_matches_expanded(&promotions, &lines)?, 1200);
+
+    Ok(())
+}
+
+#[test]
+fn mix_and_match_quantity_lines_match_expanded_basket() -> TestResult {
+    let lines = [
+        line(300, &["main"], 3)?,
+        line(100, &["drink"], 2)?,
+        line(150, &["drink"], 1)?,
+    ];
+
+    let mut slot_keys = SlotMap::with_key();
+
+    let promotions = [promotion(MixAndMatchPromotion::new(
+        PromotionKey::default(),
+        vec![
+            slot(
+                &mut slot_keys,
+                StringTagCollection::from_strs(&["main"]),
+                1,
+                Some(1),
+            ),
+            slot(
+                &mut slot_keys,
+                StringTagCollection::from_strs(&["drink"]),
+                1,
+                Some(1),
+            ),
+        ],
+        MixAndMatchDiscount::FixedTotal(Money::from_minor(350, GBP)),
+        PromotionBudget::unlimited(),
+    ))];
+
+    // Three bundles at 350 each
+    assert_eq!(assert_matches_expanded(&promotions, &lines)?, 1050);
+
+    Ok(())
+}
+
+#[test]
+fn mix_and_match_cheapest_quantity_lines_match_expanded_basket() -> TestResult {
+    let lines = [line(200, &["snack"], 5)?, line(150, &["snack"], 2)?];
+
+    let mut slot_keys = SlotMap::with_key();
+
+    let promotions = [promotion(MixAndMatchPromotion::new(
+        PromotionKey::default(),
+        vec![slot(
+            &mut slot_keys,
+            StringTagCollection::from_strs(&["snack"]),
+            3,
+            Some(3),
+        )],
+        MixAndMatchDiscount::PercentCheapest(Percentage::from(1.0)),
+        PromotionBudget::unlimited(),
+    ))];
+
+    assert_matches_expanded(&promotions, &lines)?;
+
+    Ok(())
+}
+
+#[test]
+fn positional_quantity_lines_match_expanded_basket() -> TestResult {
+    let lines = [line(100, &["snack"], 5)?, line(80, &["snack"], 2)?];
+
+    let promotions = [promotion(PositionalDiscountPromotion::new(
+        PromotionKey::default(),
+        Qualification::match_any(StringTagCollection::from_strs(&["snack"])),
+        3,
+        SmallVec::from_vec(vec![2]),
+        SimpleDiscount::PercentageOff(Percentage::from(1.0)),
+        PromotionBudget::unlimited(),
+    ))];
+
+    // 7 snacks make 2 bundles of 3-for-2, each freeing its cheapest-position unit
+    assert_matches_expanded(&promotions, &lines)?;
+
+    Ok(())
+}
+
+#[test]
+fn tiered_threshold_quantity_lines_match_expanded_basket() -> TestResult {
+    let lines = [line(1000, &["wine"], 3)?, line(500, &["cheese"], 2)?];
+
+    let promotions = [promotion(TieredThresholdPromotion::new(
+        PromotionKey::default(),
+        vec![ThresholdTier::new(
+            TierThreshold::with_monetary_threshold(Money::from_minor(3000, GBP)),
+            None,
+            Qualification::match_any(StringTagCollection::from_strs(&["wine"])),
+            Qualification::match_any(StringTagCollection::from_strs(&["cheese"])),
+            ThresholdDiscount::PercentEachItem(Percentage::from(0.10)),
+        )],
+        PromotionBudget::unlimited(),
+    ))];
+
+    // Wine 3000 + cheese 2 × 450
+    assert_eq!(assert_matches_expanded(&promotions, &lines)?, 3900);
+
+    Ok(())
+}
+
+#[test]
+fn tiered_threshold_total_and_cheapest_quantity_lines_match_expanded_basket() -> TestResult {
+    let lines = [line(333, &["wine"], 4)?, line(250, &["cheese"], 3)?];
+
+    for discount in [
+        ThresholdDiscount::AmountOffTotal(Money::from_minor(100, GBP)),
+        ThresholdDiscount::PercentCheapest(Percentage::from(0.50)),
+    ] {
+        let promotions = [promotion(TieredThresholdPromotion::new(
+            PromotionKey::default(),
+            vec![ThresholdTier::new(
+                TierThreshold::with_monetary_threshold(Money::from_minor(1000, GBP)),
+                None,
+                Qualification::match_any(StringTagCollection::from_strs(&["wine"])),
+                Qualification::match_any(StringTagCollection::from_strs(&["wine", "cheese"])),
+                discount,
+            )],
+            PromotionBudget::unlimited(),
+        ))];
+
+        assert_matches_expanded(&promotions, &lines)?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn large_quantity_line_keeps_model_size_independent_of_quantity() -> TestResult {
+    let item_group = ItemGroup::new(
+        SmallVec::from_vec(vec![
+            line(50, &["can"], 500)?.with_unit("can"),
+            line(200, &["crisps"], 1)?,
+        ]),
+        GBP,
+    );
+
+    let promotions = [
+        promotion(DirectDiscountPromotion::new(
+            PromotionKey::default(),
+            Qualification::match_any(StringTagCollection::from_strs(&["can"])),
+            SimpleDiscount::AmountOff(Money::from_minor(10, GBP)),
+            PromotionBudget::unlimited(),
+        )),
+        promotion(PositionalDiscountPromotion::new(
+            PromotionKey::default(),
+            Qualification::match_any(StringTagCollection::from_strs(&["can"])),
+            4,
+            SmallVec::from_vec(vec![3]),
+            SimpleDiscount::PercentageOff(Percentage::from(1.0)),
+            PromotionBudget::unlimited(),
+        )),
+    ];
+
+    let mut counter = VariableCounter::default();
+
+    let result = ILPSolver::solve_with_observer(&promotions, &item_group, &mut counter)?;
+
+    // One presence variable per line, not per unit.
+    assert_eq!(counter.presence, 2);
+    assert!(counter.promotion + counter.auxiliary < 40);
+
+    // 4-for-3 on every can: 375 paid cans at 50, plus the crisps.
+    assert_eq!(result.total.to_minor_units(), 375 * 50 + 200);
+
+    let can_units: u32 = result
+        .promotion_redemptions
+        .iter()
+        .filter(|redemption| redemption.item_idx == 0)
+        .map(|redemption| redemption.quantity)
+        .sum();
+
+    assert_eq!(can_units, 500);
+
+    Ok(())
+}
+
+#[test]
+fn graph_evaluation_splits_partially_redeemed_quantity_line() -> TestResult {
+    let item_group = ItemGroup::new(
+        SmallVec::from_vec(vec![line(100, &["fruit"], 5)?, line(300, &["bakery"], 1)?]),
+        GBP,
+    );
+
+    let make_promotions = || {
+        [promotion(DirectDiscountPromotion::new(
+            PromotionKey::default(),
+            Qualification::match_any(StringTagCollection::from_strs(&["fruit"])),
+            SimpleDiscount::PercentageOff(Percentage::from(0.50)),
+            PromotionBudget {
+                redemption_limit: Some(2),
+                monetary_limit: None,
+            },
+        ))]
+    };
+
+    for mode in [EvaluationMode::Greedy, EvaluationMode::Joint] {
+        let graph = PromotionGraph::single_layer(make_promotions())?.with_evaluation_mode(mode);
+
+        let result = graph.evaluate(&item_group)?;
+
+        // 2 fruit at 50, 3 fruit at 100, bakery at 300
+        assert_eq!(result.total.to_minor_units(), 700);
+
+        let fruit = result
+            .item_redemptions
+            .get(&0)
+            .map(SmallVec::as_slice)
+            .unwrap_or_default();
+
+        assert_eq!(fruit.len(), 1);
+        assert_eq!(fruit.iter().map(|r| r.quantity).sum::<u32>(), 2);
+
+        // The fruit line still has full-price units.
+        assert!(result.full_price_items.contains(&0));
+        assert!(result.full_price_items.contains(&1));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn layered_graph_quantity_lines_match_expanded_basket() -> TestResult {
+    let lines = [line(100, &["fruit"], 7)?, line(60, &["fruit"], 2)?];
+
+    let build_graph = |mode: EvaluationMode| -> TestResult<PromotionGraph<'static>> {
+        let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+
+        let mut builder = PromotionGraphBuilder::new();
+
+        let root = builder.add_layer(
+            "Half Price",
+            [promotion(DirectDiscountPromotion::new(
+                keys.insert(()),
+                Qualification::match_any(StringTagCollection::from_strs(&["fruit"])),
+                SimpleDiscount::PercentageOff(Percentage::from(0.50)),
+                PromotionBudget {
+                    redemption_limit: Some(2),
+                    monetary_limit: None,
+                },
+            ))],
+            OutputMode::Split,
+        )?;
+
+        let three_for_two = builder.add_layer(
+            "3 for 2",
+            [promotion(PositionalDiscountPromotion::new(
+                keys.insert(()),
+                Qualification::match_any(StringTagCollection::from_strs(&["fruit"])),
+                3,
+                SmallVec::from_vec(vec![2]),
+                SimpleDiscount::PercentageOff(Percentage::from(1.0)),
+                PromotionBudget::unlimited(),
+            ))],
+            OutputMode::PassThrough,
+        )?;
+
+        builder.set_root(root);
+        builder.connect_split_non_participating_only(root, three_for_two)?;
+
+        Ok(PromotionGraph::from_builder(builder)?.with_evaluation_mode(mode))
+    };
+
+    let quantity_group = ItemGroup::new(lines.iter().cloned().collect(), GBP);
+    let expanded_group = ItemGroup::new(expand(&lines), GBP);
+
+    for mode in [EvaluationMode::Greedy, EvaluationMode::Joint] {
+        let graph = build_graph(mode)?;
+
+        let quantity_result = graph.evaluate(&quantity_group)?;
+        let expanded_result = graph.evaluate(&expanded_group)?;
+
+        assert_eq!(quantity_result.total, expanded_result.total);
+
+        let redeemed_units: u32 = quantity_result
+            .item_redemptions
+            .values()
+            .flatten()
+            .map(|redemption| redemption.quantity)
+            .sum();
+
+        // Every unit is either redeemed once or left at full price.
+        assert!(redeemed_units <= 9);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,389 @@
+//! Integration tests for returns and refunds
+//!
+//! Returned lines are refunded what was paid for them, less any discount the
+//! kept lines lose, and the budget the sale used is given back.
+
+use std::num::NonZeroU32;
+
+use decimal_percentage::Percentage;
+use rusty_money::{Money, iso::GBP};
+use slotmap::SlotMap;
+use smallvec::SmallVec;
+use testresult::TestResult;
+
+use lattice::{
+    basket::Basket,
+    discounts::SimpleDiscount,
+    fixtures::Fixture,
+    graph::{GraphError, PromotionGraph, ReturnPricing, ReturnedItem},
+    items::{
+        Item,
+        groups::{ItemGroup, ItemGroupError},
+    },
+    products::ProductKey,
+    promotions::{
+        PromotionKey,
+        budget::PromotionBudget,
+        promotion,
+        qualification::Qualification,
+        types::{
+            DirectDiscountPromotion, PositionalDiscountPromotion, ThresholdDiscount, ThresholdTier,
+            TierThreshold, TieredThresholdPromotion,
+        },
+    },
+    receipt::Receipt,
+    tags::string::StringTagCollection,
+};
+
+fn socks<'a>(prices: &[i64]) -> Vec<Item<'a>> {
+    prices
+        .iter()
+        .map(|&price| {
+            Item::with_tags(
+                ProductKey::default(),
+                Money::from_minor(price, GBP),
+                StringTagCollection::from_strs(&["sock"]),
+            )
+        })
+        .collect()
+}
+
+fn sock() -> Qualification {
+    Qualification::match_any(StringTagCollection::from_strs(&["sock"]))
+}
+
+/// The cheaper of every two socks free.
+fn buy_one_get_one_free(key: PromotionKey) -> PositionalDiscountPromotion<'static> {
+    PositionalDiscountPromotion::new(
+        key,
+        sock(),
+        2,
+        SmallVec::from_slice(&[1]),
+        SimpleDiscount::PercentageOff(Percentage::from(1.0)),
+        PromotionBudget::unlimited(),
+    )
+}
+
+#[test]
+fn returning_the_paid_half_of_a_bundle_claws_back_the_free_item() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let key = keys.insert(());
+
+    let item_group = ItemGroup::new(socks(&[300, 200]).into(), GBP);
+    let graph = PromotionGraph::single_layer([promotion(buy_one_get_one_free(key))])?;
+    let original = graph.evaluate(&item_group)?;
+
+    assert_eq!(original.total.to_minor_units(), 300);
+
+    let result = graph.price_return(&item_group, &original, [0], ReturnPricing::Current)?;
+
+    // The kept sock is no longer free, so its 200p comes out of the 300p refund.
+    let [refund] = result.refunds.as_slice() else {
+        panic!("expected one refund, got {:?}", result.refunds);
+    };
+
+    assert_eq!(refund.item_idx, 0);
+    assert_eq!(refund.paid.to_minor_units(), 300);
+    assert_eq!(refund.clawback.to_minor_units(), 200);
+    assert_eq!(refund.refund.to_minor_units(), 100);
+    assert_eq!(result.total_refund.to_minor_units(), 100);
+
+    assert_eq!(result.kept.total.to_minor_units(), 200);
+    assert_eq!(result.kept.full_price_items.as_slice(), &[1]);
+
+    let [release] = result.budget_releases.as_slice() else {
+        panic!("expected one release, got {:?}", result.budget_releases);
+    };
+
+    assert_eq!(release.promotion_key, key);
+    assert_eq!(release.redemptions, 1);
+    assert_eq!(release.savings.to_minor_units(), 200);
+
+    Ok(())
+}
+
+#[test]
+fn returning_the_free_item_refunds_nothing() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let key = keys.insert(());
+
+    let item_group = ItemGroup::new(socks(&[300, 200]).into(), GBP);
+    let graph = PromotionGraph::single_layer([promotion(buy_one_get_one_free(key))])?;
+    let original = graph.evaluate(&item_group)?;
+
+    let result = graph.price_return(&item_group, &original, [1], ReturnPricing::Current)?;
+
+    assert_eq!(result.total_refund.to_minor_units(), 0);
+    assert_eq!(result.kept.total.to_minor_units(), 300);
+
+    Ok(())
+}
+
+#[test]
+fn falling_below_a_threshold_claws_back_the_lost_discount() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let key = keys.insert(());
+
+    // 10% off every sock when spending £10.
+    let tiered = TieredThresholdPromotion::new(
+        key,
+        vec![ThresholdTier::new(
+            TierThreshold::with_monetary_threshold(Money::from_minor(1000, GBP)),
+            None,
+            sock(),
+            sock(),
+            ThresholdDiscount::PercentEachItem(Percentage::from(0.1)),
+        )],
+        PromotionBudget::unlimited(),
+    );
+
+    let item_group = ItemGroup::new(socks(&[600, 600]).into(), GBP);
+    let graph = PromotionGraph::single_layer([promotion(tiered)])?;
+    let original = graph.evaluate(&item_group)?;
+
+    assert_eq!(original.total.to_minor_units(), 1080);
+
+    let result = graph.price_return(&item_group, &original, [1], ReturnPricing::Current)?;
+
+    assert_eq!(result.kept.total.to_minor_units(), 600);
+    assert_eq!(result.total_refund.to_minor_units(), 480);
+
+    let [release] = result.budget_releases.as_slice() else {
+        panic!("expected one release, got {:?}", result.budget_releases);
+    };
+
+    assert_eq!(release.savings.to_minor_units(), 120);
+
+    Ok(())
+}
+
+#[test]
+fn frozen_pricing_ignores_promotions_the_sale_did_not_use() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let (bundle, percent) = (keys.insert(()), keys.insert(()));
+
+    // Three for two beats 10% off, until a sock goes back.
+    let three_for_two = PositionalDiscountPromotion::new(
+        bundle,
+        sock(),
+        3,
+        SmallVec::from_slice(&[2]),
+        SimpleDiscount::PercentageOff(Percentage::from(1.0)),
+        PromotionBudget::unlimited(),
+    );
+
+    let ten_percent = socks_off(percent, 0.1);
+
+    let item_group = ItemGroup::new(socks(&[100, 100, 50]).into(), GBP);
+    let graph = PromotionGraph::single_layer([promotion(three_for_two), promotion(ten_percent)])?;
+    let original = graph.evaluate(&item_group)?;
+
+    assert_eq!(original.total.to_minor_units(), 200);
+
+    let current = graph.price_return(&item_group, &original, [0], ReturnPricing::Current)?;
+    let frozen = graph.price_return(&item_group, &original, [0], ReturnPricing::Frozen(&graph))?;
+
+    assert_eq!(current.kept.total.to_minor_units(), 135);
+    assert_eq!(current.total_refund.to_minor_units(), 65);
+
+    assert_eq!(frozen.kept.total.to_minor_units(), 150);
+    assert_eq!(frozen.total_refund.to_minor_units(), 50);
+
+    Ok(())
+}
+
+/// Percentage off every sock.
+fn socks_off(key: PromotionKey, pct: f64) -> DirectDiscountPromotion<'static> {
+    DirectDiscountPromotion::new(
+        key,
+        sock(),
+        SimpleDiscount::PercentageOff(Percentage::from(pct)),
+        PromotionBudget::unlimited(),
+    )
+}
+
+#[test]
+fn frozen_pricing_uses_the_promotions_as_they_were_at_the_sale() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let key = keys.insert(());
+
+    let item_group = ItemGroup::new(socks(&[100, 200]).into(), GBP);
+    let at_sale = PromotionGraph::single_layer([promotion(socks_off(key, 0.1))])?;
+    let original = at_sale.evaluate(&item_group)?;
+
+    // The offer has since gone up to 20% off.
+    let today = PromotionGraph::single_layer([promotion(socks_off(key, 0.2))])?;
+
+    let current = today.price_return(&item_group, &original, [0], ReturnPricing::Current)?;
+    let frozen =
+        today.price_return(&item_group, &original, [0], ReturnPricing::Frozen(&at_sale))?;
+
+    assert_eq!(current.kept.total.to_minor_units(), 160);
+    assert_eq!(frozen.kept.total.to_minor_units(), 180);
+    assert_eq!(frozen.total_refund.to_minor_units(), 90);
+
+    let [release] = frozen.budget_releases.as_slice() else {
+        panic!("expected one release, got {:?}", frozen.budget_releases);
+    };
+
+    assert_eq!(release.savings.to_minor_units(), 10);
+
+    Ok(())
+}
+
+#[test]
+fn freezing_to_a_graph_without_the_sale_promotions_is_an_error() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let (sold, other) = (keys.insert(()), keys.insert(()));
+
+    let item_group = ItemGroup::new(socks(&[100, 200]).into(), GBP);
+    let graph = PromotionGraph::single_layer([promotion(socks_off(sold, 0.1))])?;
+    let original = graph.evaluate(&item_group)?;
+
+    let unrelated = PromotionGraph::single_layer([promotion(socks_off(other, 0.1))])?;
+    let result = graph.price_return(
+        &item_group,
+        &original,
+        [0],
+        ReturnPricing::Frozen(&unrelated),
+    );
+
+    assert!(
+        matches!(result, Err(GraphError::FrozenPromotionMissing(key)) if key == sold),
+        "expected FrozenPromotionMissing, got {result:?}"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn returning_part_of_a_quantity_line_settles_with_the_units_kept() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let key = keys.insert(());
+
+    let three = NonZeroU32::new(3).ok_or("non-zero quantity")?;
+    let item_group = ItemGroup::new(
+        socks(&[100])
+            .into_iter()
+            .map(|item| item.with_quantity(three))
+            .collect(),
+        GBP,
+    );
+
+    let graph = PromotionGraph::single_layer([promotion(buy_one_get_one_free(key))])?;
+    let original = graph.evaluate(&item_group)?;
+
+    // Three socks for the price of two.
+    assert_eq!(original.total.to_minor_units(), 200);
+
+    for (returned, kept_total) in [(1, 100), (2, 100)] {
+        let units = NonZeroU32::new(returned).ok_or("non-zero quantity")?;
+        let result = graph.price_return(
+            &item_group,
+            &original,
+            [ReturnedItem::units(0, units)],
+            ReturnPricing::Current,
+        )?;
+
+        let [refund] = result.refunds.as_slice() else {
+            panic!("expected one refund, got {:?}", result.refunds);
+        };
+
+        // Whatever the kept socks no longer cost (or now cost) is settled here.
+        assert_eq!(refund.quantity, returned);
+        assert_eq!(result.kept.total.to_minor_units(), kept_total);
+        assert_eq!(
+            result.total_refund.to_minor_units(),
+            200 - kept_total,
+            "{returned} returned"
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn returns_release_shared_pool_budget() -> TestResult {
+    let fixture = Fixture::from_set("budget-pools")?;
+    let item_group = fixture.item_group()?;
+    let graph = fixture.graph()?;
+
+    let coffee = fixture.promotion("coffee-launch")?.key();
+    let cake = fixture.promotion("cake-launch")?.key();
+    let pool = fixture.budget_pool_key("launch-fund")?;
+
+    let original = graph.evaluate(&item_group)?;
+
+    // Three coffees and a cake go back; the cake that stays takes up £1 of the pool.
+    let result =
+        graph.price_return(&item_group, &original, [0, 1, 2, 3], ReturnPricing::Current)?;
+
+    let releases: Vec<_> = result
+        .budget_releases
+        .iter()
+        .map(|release| {
+            (
+                release.promotion_key,
+                release.redemptions,
+                release.savings.to_minor_units(),
+            )
+        })
+        .collect();
+
+    assert_eq!(releases, [(coffee, 2, 200), (cake, -1, -100)]);
+
+    let [pool_release] = result.pool_releases.as_slice() else {
+        panic!("expected one pool release, got {:?}", result.pool_releases);
+    };
+
+    assert_eq!(pool_release.pool_key, pool);
+    assert_eq!(pool_release.redemptions, 1);
+    assert_eq!(pool_release.savings.to_minor_units(), 100);
+
+    Ok(())
+}
+
+#[test]
+fn receipts_price_returns_like_results() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let key = keys.insert(());
+
+    let basket = Basket::with_items(socks(&[300, 200, 250]), GBP)?;
+    let item_group = ItemGroup::from(&basket);
+    let graph = PromotionGraph::single_layer([promotion(buy_one_get_one_free(key))])?;
+
+    let original = graph.evaluate(&item_group)?;
+    let receipt = Receipt::from_layered_result(&basket, original.clone())?;
+
+    let from_result = graph.price_return(&item_group, &original, [0], ReturnPricing::Current)?;
+    let from_receipt =
+        graph.price_receipt_return(&item_group, &receipt, [0], ReturnPricing::Current)?;
+
+    assert_eq!(from_receipt.refunds, from_result.refunds);
+    assert_eq!(from_receipt.total_refund, from_result.total_refund);
+    assert_eq!(from_receipt.kept.total, from_result.kept.total);
+
+    Ok(())
+}
+
+#[test]
+fn returning_an_unknown_item_is_an_error() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let key = keys.insert(());
+
+    let item_group = ItemGroup::new(socks(&[300, 200]).into(), GBP);
+    let graph = PromotionGraph::single_layer([promotion(buy_one_get_one_free(key))])?;
+    let original = graph.evaluate(&item_group)?;
+
+    let result = graph.price_return(&item_group, &original, [5], ReturnPricing::Current);
+
+    assert!(
+        matches!(
+            result,
+            Err(GraphError::ItemGroup(ItemGroupError::ItemNotFound(5)))
+        ),
+        "expected ItemNotFound, got {result:?}"
+    );
+
+    Ok(())
+}
@@ -0,0 +1,296 @@
+//! Integration tests for rounding policies
+//!
+//! Percentage discounts round the way the promotion (or else the graph) asks, for
+//! every promotion type and in both graph evaluation modes.
+
+use decimal_percentage::Percentage;
+use rusty_money::{
+    Money,
+    iso::{CHF, Currency, GBP},
+};
+use slotmap::SlotMap;
+use smallvec::SmallVec;
+use testresult::TestResult;
+
+use lattice::{
+    basket::Basket,
+    discounts::{
+        SimpleDiscount,
+        rounding::{RoundingMode, RoundingPolicies, RoundingPolicy, RoundingScope},
+    },
+    graph::{EvaluationMode, GraphError, OutputMode, PromotionGraph, PromotionGraphBuilder},
+    items::{Item, groups::ItemGroup},
+    products::ProductKey,
+    promotions::{
+        PromotionKey, PromotionSlotKey,
+        budget::PromotionBudget,
+        promotion,
+        qualification::Qualification,
+        types::{
+            DirectDiscountPromotion, MixAndMatchDiscount, MixAndMatchPromotion, MixAndMatchSlot,
+            PositionalDiscountPromotion, ThresholdDiscount, ThresholdTier, TierThreshold,
+            TieredThresholdPromotion,
+        },
+    },
+    receipt::Receipt,
+    solvers::{Solver, ilp::ILPSolver},
+    tags::string::StringTagCollection,
+};
+
+const MODES: [EvaluationMode; 2] = [EvaluationMode::Greedy, EvaluationMode::Joint];
+
+fn items<'a>(tag: &str, prices: &[i64], currency: &'static Currency) -> Vec<Item<'a>> {
+    prices
+        .iter()
+        .map(|&price| {
+            Item::with_tags(
+                ProductKey::default(),
+                Money::from_minor(price, currency),
+                StringTagCollection::from_strs(&[tag]),
+            )
+        })
+        .collect()
+}
+
+fn socks(prices: &[i64]) -> ItemGroup<'static> {
+    ItemGroup::new(items("sock", prices, GBP).into(), GBP)
+}
+
+fn percent_off(key: PromotionKey, tag: &str, percent: f64) -> DirectDiscountPromotion<'static> {
+    DirectDiscountPromotion::new(
+        key,
+        Qualification::match_any(StringTagCollection::from_strs(&[tag])),
+        SimpleDiscount::PercentageOff(Percentage::from(percent)),
+        PromotionBudget::unlimited(),
+    )
+}
+
+/// Half price on every sock in bundles of three.
+fn three_half_price(key: PromotionKey) -> PositionalDiscountPromotion<'static> {
+    PositionalDiscountPromotion::new(
+        key,
+        Qualification::match_any(StringTagCollection::from_strs(&["sock"])),
+        3,
+        SmallVec::from_slice(&[0, 1, 2]),
+        SimpleDiscount::PercentageOff(Percentage::from(0.5)),
+        PromotionBudget::unlimited(),
+    )
+}
+
+fn per_bundle() -> RoundingPolicy {
+    RoundingPolicy::default().with_scope(RoundingScope::PerBundle)
+}
+
+#[test]
+fn modes_round_a_half_minor_unit_discount_their_own_way() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let key = keys.insert(());
+
+    // Half of 25p is 12.5p off.
+    let cases = [
+        (RoundingMode::HalfAwayFromZero, 12),
+        (RoundingMode::HalfEven, 13),
+        (RoundingMode::SmallerDiscount, 13),
+        (RoundingMode::LargerDiscount, 12),
+    ];
+
+    for (mode, total) in cases {
+        let promo = percent_off(key, "sock", 0.5).with_rounding(RoundingPolicy::new(mode));
+        let result = ILPSolver::solve(&[promotion(promo)], &socks(&[25]))?;
+
+        assert_eq!(result.total.to_minor_units(), total, "{mode:?}");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn cash_rounding_applies_only_to_its_currency() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let key = keys.insert(());
+
+    let rounding =
+        RoundingPolicies::default().with_currency(CHF, RoundingPolicy::default().with_increment(5));
+
+    for mode in MODES {
+        let graph = PromotionGraph::single_layer([promotion(percent_off(key, "sock", 0.15))])?
+            .with_evaluation_mode(mode)
+            .with_rounding(rounding.clone());
+
+        // 15% of 19.90 is 2.985: 3.00 off to the nearest 5 rappen, 2.99 off in pence.
+        let chf = graph.evaluate(&ItemGroup::new(items("sock", &[1990], CHF).into(), CHF))?;
+        let gbp = graph.evaluate(&socks(&[1990]))?;
+
+        assert_eq!(chf.total.to_minor_units(), 1690, "{mode:?}");
+        assert_eq!(gbp.total.to_minor_units(), 1691, "{mode:?}");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn promotion_policy_overrides_the_graph_default() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let (own, inherited) = (keys.insert(()), keys.insert(()));
+
+    let item_group = ItemGroup::new(
+        [items("sock", &[25], GBP), items("hat", &[25], GBP)]
+            .concat()
+            .into(),
+        GBP,
+    );
+
+    for mode in MODES {
+        let graph = PromotionGraph::single_layer([
+            promotion(
+                percent_off(own, "sock", 0.5)
+                    .with_rounding(RoundingPolicy::new(RoundingMode::LargerDiscount)),
+            ),
+            promotion(percent_off(inherited, "hat", 0.5)),
+        ])?
+        .with_evaluation_mode(mode)
+        .with_rounding(RoundingPolicy::new(RoundingMode::SmallerDiscount));
+
+        let result = graph.evaluate(&item_group)?;
+
+        // The sock saves 13p by its own policy, the hat 12p by the graph's.
+        assert_eq!(result.total.to_minor_units(), 12 + 13, "{mode:?}");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn per_bundle_rounding_rounds_each_bundle_once() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let key = keys.insert(());
+
+    // Half of three 25p socks: 13p off each, or 38p off the bundle.
+    let per_item = ILPSolver::solve(&[promotion(three_half_price(key))], &socks(&[25, 25, 25]))?;
+    let bundled = ILPSolver::solve(
+        &[promotion(three_half_price(key).with_rounding(per_bundle()))],
+        &socks(&[25, 25, 25]),
+    )?;
+
+    assert_eq!(per_item.total.to_minor_units(), 36);
+    assert_eq!(bundled.total.to_minor_units(), 37);
+
+    let mut finals: Vec<i64> = bundled
+        .promotion_redemptions
+        .iter()
+        .map(|redemption| redemption.final_price.to_minor_units())
+        .collect();
+
+    finals.sort_unstable();
+
+    assert_eq!(finals, [12, 12, 13]);
+
+    Ok(())
+}
+
+#[test]
+fn per_bundle_rounding_covers_mix_and_match_and_tiered_threshold() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let key = keys.insert(());
+    let sock = || Qualification::match_any(StringTagCollection::from_strs(&["sock"]));
+
+    let mix_and_match = MixAndMatchPromotion::new(
+        key,
+        vec![MixAndMatchSlot::new(
+            PromotionSlotKey::default(),
+            sock(),
+            3,
+            Some(3),
+        )],
+        MixAndMatchDiscount::PercentAllItems(Percentage::from(0.5)),
+        PromotionBudget::unlimited(),
+    )
+    .with_rounding(per_bundle());
+
+    let tiered = TieredThresholdPromotion::new(
+        key,
+        vec![ThresholdTier::new(
+            TierThreshold::with_monetary_threshold(Money::from_minor(75, GBP)),
+            None,
+            sock(),
+            sock(),
+            ThresholdDiscount::PercentEachItem(Percentage::from(0.5)),
+        )],
+        PromotionBudget::unlimited(),
+    )
+    .with_rounding(per_bundle());
+
+    let mix_and_match = ILPSolver::solve(&[promotion(mix_and_match)], &socks(&[25, 25, 25]))?;
+    let tiered = ILPSolver::solve(&[promotion(tiered)], &socks(&[25, 25, 25]))?;
+
+    assert_eq!(mix_and_match.total.to_minor_units(), 37);
+    assert_eq!(tiered.total.to_minor_units(), 37);
+
+    Ok(())
+}
+
+#[test]
+fn receipts_total_the_rounded_prices() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let key = keys.insert(());
+
+    let basket = Basket::with_items(items("sock", &[25, 25, 25], GBP), GBP)?;
+    let item_group = ItemGroup::from(&basket);
+
+    let graph = PromotionGraph::single_layer([promotion(
+        three_half_price(key).with_rounding(per_bundle().with_increment(5)),
+    )])?;
+
+    // 37.5p off the bundle rounds to 40p.
+    let receipt = Receipt::from_layered_result(&basket, graph.evaluate(&item_group)?)?;
+
+    let line_total: i64 = receipt
+        .promotion_redemptions()
+        .values()
+        .flatten()
+        .map(|redemption| redemption.final_price.to_minor_units())
+        .sum();
+
+    assert_eq!(receipt.total().to_minor_units(), 35);
+    assert_eq!(line_total, 35);
+    assert_eq!(receipt.savings()?.to_minor_units(), 40);
+
+    Ok(())
+}
+
+#[test]
+fn joint_mode_rejects_per_bundle_rounding_before_later_layers() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let (bundles, later) = (keys.insert(()), keys.insert(()));
+
+    let mut builder = PromotionGraphBuilder::new();
+    let root = builder.add_layer(
+        "Bundles",
+        [promotion(
+            three_half_price(bundles).with_rounding(per_bundle()),
+        )],
+        OutputMode::PassThrough,
+    )?;
+    let leaf = builder.add_layer(
+        "Everything",
+        [promotion(percent_off(later, "sock", 0.1))],
+        OutputMode::PassThrough,
+    )?;
+
+    builder.set_root(root);
+    builder.connect_pass_through(root, leaf)?;
+
+    let graph = PromotionGraph::from_builder(builder)?.with_evaluation_mode(EvaluationMode::Joint);
+    let result = graph.evaluate(&socks(&[25, 25, 25]));
+
+    assert!(
+        matches!(
+            result,
+            Err(GraphError::JointPriceOutcomesUnavailable { promotion_key, .. })
+                if promotion_key == bundles
+        ),
+        "expected JointPriceOutcomesUnavailable, got {result:?}"
+    );
+
+    Ok(())
+}
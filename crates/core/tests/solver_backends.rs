@@ -0,0 +1,94 @@
+//! Integration tests for the pluggable MILP backends
+//!
+//! Every compiled-in backend must price each fixture set identically, in both
+//! graph evaluation modes. Build with `--all-features` to compare them all.
+
+#[expect(dead_code, reason = "only the fixture set list is used here")]
+mod common;
+
+use lattice::{
+    fixtures::Fixture,
+    graph::{EvaluationMode, GraphError},
+    solvers::ilp::{ILPSolver, NoopObserver, SolverBackend},
+};
+use testresult::TestResult;
+
+use common::FIXTURE_SETS;
+
+#[test]
+fn every_backend_prices_fixture_sets_identically() -> TestResult {
+    for set in FIXTURE_SETS {
+        let fixture = Fixture::from_set(set)?;
+        let item_group = fixture.item_group()?;
+
+        for mode in [EvaluationMode::Greedy, EvaluationMode::Joint] {
+            let graph = fixture.graph()?.clone().with_evaluation_mode(mode);
+            let expected = match graph.evaluate(&item_group) {
+                // Some fixture graphs carry bundle-total discounts into later layers.
+                Err(GraphError::JointPriceOutcomesUnavailable { .. }) => continue,
+                result => result?.total,
+            };
+
+            for &backend in SolverBackend::AVAILABLE {
+                let result = graph
+                    .clone()
+                    .with_solver_backend(backend)
+                    .evaluate(&item_group)?;
+
+                assert_eq!(
+                    result.total, expected,
+                    "{set} {mode:?} total with {backend}"
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn flat_solve_matches_across_backends() -> TestResult {
+    let fixture = Fixture::from_set("comprehensive")?;
+    let item_group = fixture.item_group()?;
+
+    let mut pools = fixture.budget_pools().clone();
+    let expected = ILPSolver::solve_with_budget_pools(
+        fixture.promotions(),
+        &item_group,
+        &mut pools,
+        &mut NoopObserver,
+    )?;
+
+    for &backend in SolverBackend::AVAILABLE {
+        let mut pools = fixture.budget_pools().clone();
+        let result = ILPSolver::solve_with_backend(
+            fixture.promotions(),
+            &item_group,
+            &mut pools,
+            &mut NoopObserver,
+            backend,
+        )?;
+
+        assert_eq!(result.total, expected.total, "total with {backend}");
+        assert_eq!(
+            result.promotion_redemptions.len(),
+            expected.promotion_redemptions.len(),
+            "redemption count with {backend}"
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn graph_uses_default_backend() -> TestResult {
+    let fixture = Fixture::from_set("direct")?;
+
+    assert_eq!(
+        fixture.graph()?.solver_backend(),
+        SolverBackend::default(),
+        "graphs solve with the default backend unless told otherwise"
+    );
+
+    Ok(())
+}
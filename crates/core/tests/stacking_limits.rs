@@ -0,0 +1,208 @@
+//! Integration tests for stacking limits
+//!
+//! Graph-wide and per-layer caps on the total discount and the number of stacked
+//! redemptions an item may collect, in both graph evaluation modes.
+
+use decimal_percentage::Percentage;
+use petgraph::graph::NodeIndex;
+use rusty_money::{Money, iso::GBP};
+use slotmap::SlotMap;
+use testresult::TestResult;
+
+use lattice::{
+    discounts::SimpleDiscount,
+    graph::{
+        EvaluationMode, LayeredSolverResult, OutputMode, PromotionGraph, PromotionGraphBuilder,
+        StackingLimit,
+    },
+    items::{Item, groups::ItemGroup},
+    products::ProductKey,
+    promotions::{
+        PromotionKey, budget::PromotionBudget, promotion, qualification::Qualification,
+        types::DirectDiscountPromotion,
+    },
+    tags::string::StringTagCollection,
+};
+
+const MODES: [EvaluationMode; 2] = [EvaluationMode::Greedy, EvaluationMode::Joint];
+
+fn socks() -> ItemGroup<'static> {
+    let sock = Item::with_tags(
+        ProductKey::default(),
+        Money::from_minor(100, GBP),
+        StringTagCollection::from_strs(&["sock"]),
+    );
+
+    ItemGroup::new([sock].into_iter().collect(), GBP)
+}
+
+fn percent_off(key: PromotionKey, percent: f64) -> DirectDiscountPromotion<'static> {
+    DirectDiscountPromotion::new(
+        key,
+        Qualification::match_any(StringTagCollection::from_strs(&["sock"])),
+        SimpleDiscount::PercentageOff(Percentage::from(percent)),
+        PromotionBudget::unlimited(),
+    )
+}
+
+/// Chain one pass-through layer per promotion, returning the builder and layers.
+fn chain(
+    promotions: &[DirectDiscountPromotion<'static>],
+) -> TestResult<(PromotionGraphBuilder<'static>, Vec<NodeIndex>)> {
+    let mut builder = PromotionGraphBuilder::new();
+    let mut layers = Vec::new();
+
+    for (idx, discount) in promotions.iter().enumerate() {
+        let layer = builder.add_layer(
+            format!("Layer {idx}"),
+            [promotion(discount.clone())],
+            OutputMode::PassThrough,
+        )?;
+
+        match layers.last() {
+            Some(&previous) => builder.connect_pass_through(previous, layer)?,
+            None => builder.set_root(layer),
+        }
+
+        layers.push(layer);
+    }
+
+    Ok((builder, layers))
+}
+
+fn redeemed(result: &LayeredSolverResult<'_>) -> Vec<(PromotionKey, i64, bool)> {
+    result
+        .item_redemptions
+        .get(&0)
+        .into_iter()
+        .flatten()
+        .map(|redemption| {
+            (
+                redemption.promotion_key,
+                redemption.final_price.to_minor_units(),
+                redemption.floor_limited,
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn total_discount_is_capped_across_layers() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let (first, second) = (keys.insert(()), keys.insert(()));
+
+    let (builder, _layers) = chain(&[percent_off(first, 0.4), percent_off(second, 0.4)])?;
+
+    // 40% and then 40% would take 64% off; at most half may go.
+    let graph = PromotionGraph::from_builder(builder)?
+        .with_stacking_limit(StackingLimit::default().with_max_discount(Percentage::from(0.5)));
+
+    for mode in MODES {
+        let result = graph
+            .clone()
+            .with_evaluation_mode(mode)
+            .evaluate(&socks())?;
+
+        assert_eq!(result.total.to_minor_units(), 50, "{mode:?}");
+        assert_eq!(
+            redeemed(&result),
+            [(first, 60, false), (second, 50, true)],
+            "{mode:?}"
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn items_at_the_redemption_cap_pass_later_layers() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let (first, second) = (keys.insert(()), keys.insert(()));
+
+    let (builder, _layers) = chain(&[percent_off(first, 0.1), percent_off(second, 0.3)])?;
+
+    let graph = PromotionGraph::from_builder(builder)?
+        .with_stacking_limit(StackingLimit::default().with_max_redemptions(1));
+
+    // Greedy takes the first layer's 10% and has no headroom left for the 30%.
+    let greedy = graph.evaluate(&socks())?;
+
+    assert_eq!(greedy.total.to_minor_units(), 90);
+    assert_eq!(redeemed(&greedy), [(first, 90, false)]);
+
+    // Joint passes on the first layer to keep its one redemption for the second.
+    let joint = graph
+        .with_evaluation_mode(EvaluationMode::Joint)
+        .evaluate(&socks())?;
+
+    assert_eq!(joint.total.to_minor_units(), 70);
+    assert_eq!(redeemed(&joint), [(second, 70, false)]);
+
+    Ok(())
+}
+
+#[test]
+fn layer_limits_only_cap_their_own_layer() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let (first, second, third) = (keys.insert(()), keys.insert(()), keys.insert(()));
+
+    let (mut builder, layers) = chain(&[
+        percent_off(first, 0.2),
+        percent_off(second, 0.2),
+        percent_off(third, 0.2),
+    ])?;
+
+    // No more than a quarter off by the end of the second layer; the third may
+    // go further.
+    let capped = layers.get(1).copied().ok_or("missing second layer")?;
+
+    builder.set_layer_stacking_limit(
+        capped,
+        StackingLimit::default().with_max_discount(Percentage::from(0.25)),
+    )?;
+
+    let graph = PromotionGraph::from_builder(builder)?;
+
+    for mode in MODES {
+        let result = graph
+            .clone()
+            .with_evaluation_mode(mode)
+            .evaluate(&socks())?;
+
+        assert_eq!(result.total.to_minor_units(), 60, "{mode:?}");
+        assert_eq!(
+            redeemed(&result),
+            [(first, 80, false), (second, 75, true), (third, 60, false)],
+            "{mode:?}"
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn graph_and_layer_limits_combine_to_the_tightest() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let (first, second) = (keys.insert(()), keys.insert(()));
+
+    let (mut builder, layers) = chain(&[percent_off(first, 0.2), percent_off(second, 0.2)])?;
+
+    let last = layers.last().copied().ok_or("missing last layer")?;
+
+    builder.set_layer_stacking_limit(last, StackingLimit::default().with_max_redemptions(1))?;
+
+    let graph = PromotionGraph::from_builder(builder)?
+        .with_stacking_limit(StackingLimit::default().with_max_redemptions(2));
+
+    for mode in MODES {
+        let result = graph
+            .clone()
+            .with_evaluation_mode(mode)
+            .evaluate(&socks())?;
+
+        assert_eq!(result.total.to_minor_units(), 80, "{mode:?}");
+        assert_eq!(redeemed(&result).len(), 1, "{mode:?}");
+    }
+
+    Ok(())
+}
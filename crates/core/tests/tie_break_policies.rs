@@ -0,0 +1,227 @@
+//! Integration tests for tie-break policies
+//!
+//! When several allocations give the same total, the graph's tie-break policy
+//! decides which promotions win, in both evaluation modes.
+
+use rusty_money::{Money, iso::GBP};
+use slotmap::SlotMap;
+use smallvec::SmallVec;
+use testresult::TestResult;
+
+use lattice::{
+    discounts::SimpleDiscount,
+    graph::{EvaluationMode, LayeredSolverResult, PromotionGraph},
+    items::{Item, groups::ItemGroup},
+    products::ProductKey,
+    promotions::{
+        PromotionKey,
+        budget::PromotionBudget,
+        funding::PromotionFunding,
+        promotion,
+        qualification::Qualification,
+        types::{DirectDiscountPromotion, PositionalDiscountPromotion},
+    },
+    solvers::ilp::{TieBreakPolicy, TieBreakRule},
+    tags::string::StringTagCollection,
+};
+
+const MODES: [EvaluationMode; 2] = [EvaluationMode::Greedy, EvaluationMode::Joint];
+
+fn shirts<'a>(count: usize) -> ItemGroup<'a> {
+    let items = (0..count).map(|_| {
+        Item::with_tags(
+            ProductKey::default(),
+            Money::from_minor(1000, GBP),
+            StringTagCollection::from_strs(&["shirt"]),
+        )
+    });
+
+    ItemGroup::new(items.collect(), GBP)
+}
+
+/// £1 off every shirt.
+fn pound_off_shirts(key: PromotionKey) -> DirectDiscountPromotion<'static> {
+    DirectDiscountPromotion::new(
+        key,
+        Qualification::match_any(StringTagCollection::from_strs(&["shirt"])),
+        SimpleDiscount::AmountOff(Money::from_minor(100, GBP)),
+        PromotionBudget::unlimited(),
+    )
+}
+
+/// £2 off the second of every two shirts.
+fn two_pounds_off_pairs(key: PromotionKey) -> PositionalDiscountPromotion<'static> {
+    PositionalDiscountPromotion::new(
+        key,
+        Qualification::match_any(StringTagCollection::from_strs(&["shirt"])),
+        2,
+        SmallVec::from_slice(&[1]),
+        SimpleDiscount::AmountOff(Money::from_minor(200, GBP)),
+        PromotionBudget::unlimited(),
+    )
+}
+
+/// Promotions redeemed anywhere in the result.
+fn redeemed(result: &LayeredSolverResult<'_>) -> Vec<PromotionKey> {
+    let mut keys: Vec<_> = result
+        .item_redemptions
+        .values()
+        .flatten()
+        .map(|redemption| redemption.promotion_key)
+        .collect();
+
+    keys.sort_unstable();
+    keys.dedup();
+
+    keys
+}
+
+#[test]
+fn supplier_funded_promotions_win_ties() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let policy = TieBreakPolicy::default().then(TieBreakRule::PreferSupplierFunded);
+
+    for supplier_first in [true, false] {
+        let (first, second) = (keys.insert(()), keys.insert(()));
+        let (funded, unfunded) = if supplier_first {
+            (first, second)
+        } else {
+            (second, first)
+        };
+
+        for mode in MODES {
+            let graph = PromotionGraph::single_layer([
+                promotion(
+                    pound_off_shirts(funded).with_funding(PromotionFunding::supplier("Acme")),
+                ),
+                promotion(pound_off_shirts(unfunded)),
+            ])?
+            .with_evaluation_mode(mode)
+            .with_tie_break(policy);
+
+            let result = graph.evaluate(&shirts(2))?;
+
+            assert_eq!(result.total.to_minor_units(), 1800, "{mode:?}");
+            assert_eq!(redeemed(&result), [funded], "{mode:?}");
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn higher_priority_promotions_win_ties() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let policy = TieBreakPolicy::default().then(TieBreakRule::PreferHigherPriority);
+
+    for (low, high) in [(-3, 2), (0, 7)] {
+        let (first, second) = (keys.insert(()), keys.insert(()));
+
+        for mode in MODES {
+            let graph = PromotionGraph::single_layer([
+                promotion(pound_off_shirts(first).with_priority(low)),
+                promotion(pound_off_shirts(second).with_priority(high)),
+            ])?
+            .with_evaluation_mode(mode)
+            .with_tie_break(policy);
+
+            let result = graph.evaluate(&shirts(3))?;
+
+            assert_eq!(result.total.to_minor_units(), 2700, "{mode:?}");
+            assert_eq!(redeemed(&result), [second], "{mode:?} {low} vs {high}");
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn fewer_redemptions_win_ties() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let (each, pairs) = (keys.insert(()), keys.insert(()));
+    let policy = TieBreakPolicy::default().then(TieBreakRule::PreferFewerRedemptions);
+
+    for mode in MODES {
+        // £1 off each of two shirts and £2 off the pair both give £18.
+        let graph = PromotionGraph::single_layer([
+            promotion(pound_off_shirts(each)),
+            promotion(two_pounds_off_pairs(pairs)),
+        ])?
+        .with_evaluation_mode(mode)
+        .with_tie_break(policy);
+
+        let result = graph.evaluate(&shirts(2))?;
+
+        assert_eq!(result.total.to_minor_units(), 1800, "{mode:?}");
+        assert_eq!(redeemed(&result), [pairs], "{mode:?}");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn earlier_rules_take_precedence() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let (each, pairs) = (keys.insert(()), keys.insert(()));
+
+    let fewer_first = TieBreakPolicy::default()
+        .then(TieBreakRule::PreferFewerRedemptions)
+        .then(TieBreakRule::PreferHigherPriority);
+    let priority_first = TieBreakPolicy::default()
+        .then(TieBreakRule::PreferHigherPriority)
+        .then(TieBreakRule::PreferFewerRedemptions);
+
+    for (policy, winner) in [(fewer_first, pairs), (priority_first, each)] {
+        for mode in MODES {
+            let graph = PromotionGraph::single_layer([
+                promotion(pound_off_shirts(each).with_priority(5)),
+                promotion(two_pounds_off_pairs(pairs)),
+            ])?
+            .with_evaluation_mode(mode)
+            .with_tie_break(policy);
+
+            let result = graph.evaluate(&shirts(2))?;
+
+            assert_eq!(result.total.to_minor_units(), 1800, "{mode:?}");
+            assert_eq!(redeemed(&result), [winner], "{mode:?} {policy:?}");
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn tie_breaks_never_change_the_total() -> TestResult {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+    let (each, pairs) = (keys.insert(()), keys.insert(()));
+
+    // Pairs are cheaper here, so preferring the high priority £1 off must not win.
+    let policy = TieBreakPolicy::default().then(TieBreakRule::PreferHigherPriority);
+
+    for mode in MODES {
+        let graph = PromotionGraph::single_layer([
+            promotion(pound_off_shirts(each).with_priority(10)),
+            promotion(
+                PositionalDiscountPromotion::new(
+                    pairs,
+                    Qualification::match_any(StringTagCollection::from_strs(&["shirt"])),
+                    2,
+                    SmallVec::from_slice(&[1]),
+                    SimpleDiscount::AmountOff(Money::from_minor(500, GBP)),
+                    PromotionBudget::unlimited(),
+                )
+                .with_funding(PromotionFunding::supplier("Acme")),
+            ),
+        ])?
+        .with_evaluation_mode(mode);
+
+        let cold = graph.evaluate(&shirts(4))?;
+        let result = graph.with_tie_break(policy).evaluate(&shirts(4))?;
+
+        assert_eq!(cold.total.to_minor_units(), 3000, "{mode:?}");
+        assert_eq!(result.total, cold.total, "{mode:?}");
+        assert_eq!(redeemed(&result), [pairs], "{mode:?}");
+    }
+
+    Ok(())
+}
@@ -7,6 +7,7 @@ use testresult::TestResult;
 
 use lattice::{
     basket::Basket,
+    discounts::SimpleDiscount,
     fixtures::Fixture,
     items::{Item, groups::ItemGroup},
     products::ProductKey,
@@ -14,7 +15,10 @@ use lattice::{
         PromotionKey,
         budget::PromotionBudget,
         promotion,
-        types::{ThresholdDiscount, ThresholdTier, TierThreshold, TieredThresholdPromotion},
+        types::{
+            DirectDiscountPromotion, ThresholdDiscount, ThresholdTier, TierThreshold,
+            TieredThresholdPromotion,
+        },
     },
     solvers::{Solver, ilp::ILPSolver},
     tags::{collection::TagCollection, string::StringTagCollection},
@@ -640,3 +644,62 @@ fn contribution_items_are_exclusive_across_promotions() -> TestResult {
 
     Ok(())
 }
+
+#[test]
+fn amount_off_total_saves_no_more_than_the_discounted_items_cost() -> TestResult {
+    let items = [
+        Item::with_tags(
+            ProductKey::default(),
+            Money::from_minor(2000, GBP),
+            StringTagCollection::from_strs(&["wine"]),
+        ),
+        Item::with_tags(
+            ProductKey::default(),
+            Money::from_minor(300, GBP),
+            StringTagCollection::from_strs(&["cheese"]),
+        ),
+    ];
+
+    let basket = Basket::with_items(items, GBP)?;
+    let item_group = ItemGroup::from(&basket);
+
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+
+    let wine_cheese = promotion(TieredThresholdPromotion::new(
+        keys.insert(()),
+        vec![ThresholdTier::new(
+            TierThreshold::with_monetary_threshold(Money::from_minor(2000, GBP)),
+            None,
+            lattice::promotions::qualification::Qualification::match_any(
+                StringTagCollection::from_strs(&["wine"]),
+            ),
+            lattice::promotions::qualification::Qualification::match_any(
+                StringTagCollection::from_strs(&["cheese"]),
+            ),
+            ThresholdDiscount::AmountOffTotal(Money::from_minor(500, GBP)),
+        )],
+        PromotionBudget::unlimited(),
+    ));
+
+    let wine_sale = promotion(DirectDiscountPromotion::new(
+        keys.insert(()),
+        lattice::promotions::qualification::Qualification::match_any(
+            StringTagCollection::from_strs(&["wine"]),
+        ),
+        SimpleDiscount::PercentageOff(Percentage::from(0.20)),
+        PromotionBudget::unlimited(),
+    ));
+
+    let result = ILPSolver::solve(&[wine_cheese, wine_sale], &item_group)?;
+
+    // £5 off £3 of cheese only saves £3, less than the £4 wine sale.
+    assert_eq!(result.total.to_minor_units(), 1900);
+    assert!(
+        result
+            .promotion_redemptions
+            .iter()
+            .all(|redemption| !redemption.final_price.is_negative())
+    );
+
+    Ok(())
+}
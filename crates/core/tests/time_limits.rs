@@ -0,0 +1,234 @@
+//! Integration tests for solver time limits
+//!
+//! A solve or graph evaluation with a time limit never blocks far beyond it;
+//! when it runs out of time it prices the basket with the best allocation found,
+//! the greedy heuristic or, failing that, at full price, and flags the result as
+//! not optimal.
+
+#[expect(dead_code, reason = "only the crowded basket helpers are used here")]
+mod common;
+
+use std::time::{Duration, Instant};
+
+use decimal_percentage::Percentage;
+use slotmap::SlotMap;
+use testresult::TestResult;
+
+use lattice::{
+    discounts::SimpleDiscount,
+    fixtures::Fixture,
+    graph::{EvaluationMode, GraphError},
+    promotions::{
+        Promotion, PromotionKey,
+        budget::{BudgetPoolKey, BudgetPools, PromotionBudget},
+        promotion,
+        qualification::Qualification,
+        types::DirectDiscountPromotion,
+    },
+    solvers::{
+        SolutionQuality, Solver,
+        greedy::GreedySolver,
+        ilp::{ILPSolver, NoopObserver, ObjectiveMode, SolverOptions},
+    },
+};
+
+use common::{crowded_basket, full_price};
+
+fn pooled_half_price(pools: &mut BudgetPools<'static>) -> (Promotion<'static>, BudgetPoolKey) {
+    let mut keys = SlotMap::<PromotionKey, ()>::with_key();
+
+    let pool = pools.insert(PromotionBudget {
+        redemption_limit: Some(5),
+        monetary_limit: None,
+    });
+
+    let half_price = promotion(
+        DirectDiscountPromotion::new(
+            keys.insert(()),
+            Qualification::match_all(),
+            SimpleDiscount::PercentageOff(Percentage::from(0.50)),
+            PromotionBudget::unlimited(),
+        )
+        .with_budget_pool(pool),
+    );
+
+    (half_price, pool)
+}
+
+#[test]
+fn elapsed_time_limit_falls_back_to_the_greedy_heuristic() -> TestResult {
+    let mut pools = BudgetPools::default();
+    let (half_price, pool) = pooled_half_price(&mut pools);
+    let promotions = [half_price];
+
+    let (_, item_group) = crowded_basket(6);
+
+    let greedy =
+        GreedySolver::solve_with_budget_pools(&promotions, &item_group, &mut pools.clone())?;
+
+    let result = ILPSolver::solve_with_options(
+        &promotions,
+        &item_group,
+        &mut pools,
+        &mut NoopObserver,
+        &SolverOptions::default().with_time_limit(Duration::ZERO),
+    )?;
+
+    assert_eq!(result.quality, SolutionQuality::Heuristic, "greedy result");
+    assert_eq!(result.total, greedy.total, "priced by the greedy solver");
+    assert!(
+        result.total.to_minor_units() < full_price(&item_group),
+        "promotions applied"
+    );
+    assert_eq!(
+        pools.get(pool).and_then(|budget| budget.redemption_limit),
+        Some(0),
+        "pool is debited by the greedy redemptions"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn elapsed_time_limit_without_a_heuristic_falls_back_to_full_price() -> TestResult {
+    let mut pools = BudgetPools::default();
+    let (half_price, pool) = pooled_half_price(&mut pools);
+
+    let (_, item_group) = crowded_basket(6);
+
+    // The greedy heuristic cannot give the least discount, so nothing applies.
+    let result = ILPSolver::solve_with_options(
+        &[half_price],
+        &item_group,
+        &mut pools,
+        &mut NoopObserver,
+        &SolverOptions::default()
+            .with_time_limit(Duration::ZERO)
+            .with_objective(ObjectiveMode::LeastGenerous),
+    )?;
+
+    assert_eq!(result.quality, SolutionQuality::Fallback, "fallback result");
+    assert_eq!(
+        result.total.to_minor_units(),
+        full_price(&item_group),
+        "every item at full price"
+    );
+    assert!(
+        result.promotion_redemptions.is_empty(),
+        "no promotions applied"
+    );
+    assert_eq!(
+        pools.get(pool).and_then(|budget| budget.redemption_limit),
+        Some(5),
+        "pool is not debited by the fallback"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn generous_time_limit_matches_unlimited_solve() -> TestResult {
+    let (promotions, item_group) = crowded_basket(12);
+
+    let unlimited = ILPSolver::solve_with_options(
+        &promotions,
+        &item_group,
+        &mut BudgetPools::default(),
+        &mut NoopObserver,
+        &SolverOptions::default(),
+    )?;
+
+    let limited = ILPSolver::solve_with_options(
+        &promotions,
+        &item_group,
+        &mut BudgetPools::default(),
+        &mut NoopObserver,
+        &SolverOptions::default().with_time_limit(Duration::from_secs(60)),
+    )?;
+
+    assert_eq!(limited.quality, SolutionQuality::Optimal, "solved in time");
+    assert_eq!(limited.total, unlimited.total, "same optimal total");
+
+    Ok(())
+}
+
+#[test]
+fn stalled_solve_returns_near_the_time_limit() -> TestResult {
+    let (promotions, item_group) = crowded_basket(400);
+    let limit = Duration::from_millis(20);
+
+    let start = Instant::now();
+
+    let result = ILPSolver::solve_with_options(
+        &promotions,
+        &item_group,
+        &mut BudgetPools::default(),
+        &mut NoopObserver,
+        &SolverOptions::default().with_time_limit(limit),
+    )?;
+
+    let elapsed = start.elapsed();
+
+    // Building the formulation is not interruptible, so allow generous slack.
+    assert!(
+        elapsed < Duration::from_secs(2),
+        "solve gave up near the limit (took {elapsed:?})"
+    );
+    assert_eq!(result.quality, SolutionQuality::Heuristic, "greedy result");
+    assert_eq!(
+        result.total,
+        GreedySolver::solve(&promotions, &item_group)?.total,
+        "priced by the greedy solver"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn graph_time_limit_falls_back_to_greedy_layers_in_both_modes() -> TestResult {
+    let fixture = Fixture::from_set("layered")?;
+    let item_group = fixture.item_group()?;
+
+    for mode in [EvaluationMode::Greedy, EvaluationMode::Joint] {
+        let graph = fixture.graph()?.clone().with_evaluation_mode(mode);
+
+        let result = match graph
+            .clone()
+            .with_time_limit(Duration::ZERO)
+            .evaluate(&item_group)
+        {
+            // Some fixture graphs carry bundle-total discounts into later layers.
+            Err(GraphError::JointPriceOutcomesUnavailable { .. }) => continue,
+            result => result?,
+        };
+
+        assert_eq!(
+            result.quality,
+            SolutionQuality::Heuristic,
+            "{mode:?} quality"
+        );
+        assert!(
+            result.total.to_minor_units() < full_price(&item_group),
+            "{mode:?} promotions applied"
+        );
+
+        let unlimited = graph.evaluate(&item_group)?;
+        let limited = graph
+            .with_time_limit(Duration::from_secs(60))
+            .evaluate(&item_group)?;
+
+        assert_eq!(
+            unlimited.quality,
+            SolutionQuality::Optimal,
+            "{mode:?} unlimited quality"
+        );
+        assert_eq!(
+            limited.quality,
+            SolutionQuality::Optimal,
+            "{mode:?} limited quality"
+        );
+        assert_eq!(limited.total, unlimited.total, "{mode:?} same total");
+    }
+
+    Ok(())
+}
@@ -123,54 +123,82 @@ pub(crate) struct PromotionSavings {
     redemptions: usize,
 }
 
+fn build_item(solver_data: &BasketSolverData, fixture_key: &str) -> Result<Item<'static>, String> {
+    let product_key = solver_data
+        .product_key_by_fixture_key
+        .get(fixture_key)
+        .copied()
+        .ok_or_else(|| format!("Product key not found in fixture: {fixture_key}"))?;
+
+    let product = solver_data
+        .product_meta_map
+        .get(product_key)
+        .ok_or_else(|| format!("Product metadata missing for fixture key: {fixture_key}"))?;
+
+    let item = Item::with_tags(
+        product_key,
+        Money::from_minor(product.price.to_minor_units(), product.price.currency()),
+        product.tags.clone(),
+    );
+
+    let floor = product
+        .floor
+        .minimum_for(&product.price)
+        .map_err(|error| format!("Invalid price floor for fixture key {fixture_key}: {error}"))?;
+
+    Ok(match floor {
+        Some(floor) => item.with_price_floor(floor),
+        None => item,
+    })
+}
+
 fn build_basket(
     solver_data: &BasketSolverData,
     cart_fixture_keys: &[String],
 ) -> Result<Basket<'static>, String> {
-    let mut basket_items: Vec<Item<'static>> = Vec::new();
-
-    for fixture_key in cart_fixture_keys {
-        let product_key = solver_data
-            .product_key_by_fixture_key
-            .get(fixture_key)
-            .copied()
-            .ok_or_else(|| format!("Product key not found in fixture: {fixture_key}"))?;
-
-        let product = solver_data
-            .product_meta_map
-            .get(product_key)
-            .ok_or_else(|| format!("Product metadata missing for fixture key: {fixture_key}"))?;
-
-        basket_items.push(Item::with_tags(
-            product_key,
-            Money::from_minor(product.price.to_minor_units(), product.price.currency()),
-            product.tags.clone(),
-        ));
-    }
+    let basket_items = cart_fixture_keys
+        .iter()
+        .map(|fixture_key| build_item(solver_data, fixture_key))
+        .collect::<Result<Vec<_>, _>>()?;
 
     Basket::with_items(basket_items, solver_data.currency)
         .map_err(|error| format!("Failed to build basket: {error}"))
 }
 
-/// Solve only the basket total (minor units) for marginal-price estimation.
+/// Estimate the marginal price and savings (minor units) of adding each
+/// candidate product to the cart.
 ///
 /// # Errors
 ///
 /// Returns an error if basket construction or graph solving fails.
 #[cfg(target_arch = "wasm32")]
-pub fn solve_total_minor(
+pub fn estimate_additions_minor(
     solver_data: &BasketSolverData,
     cart_fixture_keys: &[String],
-) -> Result<i64, String> {
+    candidate_fixture_keys: &[String],
+) -> Result<Vec<(i64, i64)>, String> {
     let basket = build_basket(solver_data, cart_fixture_keys)?;
     let item_group = ItemGroup::from(&basket);
 
-    let solved = solver_data
-        .graph
-        .evaluate(&item_group)
-        .map_err(|error| format!("Failed to solve promotion graph: {error}"))?;
+    let candidates = candidate_fixture_keys
+        .iter()
+        .map(|fixture_key| build_item(solver_data, fixture_key))
+        .collect::<Result<Vec<_>, _>>()?;
 
-    Ok(solved.total.to_minor_units())
+    let estimates = solver_data
+        .graph
+        .estimate_additions(&item_group, &candidates)
+        .map_err(|error| format!("Failed to estimate basket additions: {error}"))?;
+
+    Ok(estimates
+        .iter()
+        .map(|estimate| {
+            (
+                estimate.marginal.to_minor_units(),
+                estimate.savings.to_minor_units(),
+            )
+        })
+        .collect())
 }
 
 fn solve_basket(
@@ -774,8 +802,8 @@ mod tests {
     use slotmap::{SecondaryMap, SlotMap};
 
     use lattice::{
-        basket::Basket, graph::PromotionGraph, items::groups::ItemGroup, products::Product,
-        receipt::Receipt, tags::string::StringTagCollection,
+        basket::Basket, graph::PromotionGraph, items::groups::ItemGroup, pricing::PriceFloor,
+        products::Product, receipt::Receipt, tags::string::StringTagCollection,
     };
     use testresult::TestResult;
 
@@ -1163,12 +1191,14 @@ mod tests {
             name: "Test Product 1".to_string(),
             price: Money::from_minor(100, iso::GBP),
             tags: StringTagCollection::from_strs(&[]),
+            floor: PriceFloor::default(),
         };
 
         let product2 = Product {
             name: "Test Product 2".to_string(),
             price: Money::from_minor(200, iso::GBP),
             tags: StringTagCollection::from_strs(&[]),
+            floor: PriceFloor::default(),
         };
 
         let key1 = product_meta_map.insert(product1);
@@ -45,18 +45,11 @@ struct EstimateSignals {
     generation: RwSignal<u64>,
 }
 
-#[cfg(target_arch = "wasm32")]
-#[derive(Debug)]
-struct WorkerProductMeta {
-    fixture_key: String,
-    price_minor: i64,
-}
-
 #[cfg(target_arch = "wasm32")]
 #[derive(Debug)]
 struct WorkerData {
     solver_data: basket::BasketSolverData,
-    products: Vec<WorkerProductMeta>,
+    product_keys: Vec<String>,
 }
 
 #[cfg(target_arch = "wasm32")]
@@ -194,13 +187,10 @@ fn load_worker_data() -> Result<WorkerData, String> {
     let loaded_products = crate::products::load_products(PRODUCTS_FIXTURE_YAML)?;
     let loaded_promotions = crate::promotions::load_promotions(PROMOTIONS_FIXTURE_YAML)?;
 
-    let worker_products = loaded_products
+    let product_keys = loaded_products
         .products
         .iter()
-        .map(|product| WorkerProductMeta {
-            fixture_key: product.fixture_key.clone(),
-            price_minor: product.price_minor,
-        })
+        .map(|product| product.fixture_key.clone())
         .collect();
 
     Ok(WorkerData {
@@ -212,7 +202,7 @@ fn load_worker_data() -> Result<WorkerData, String> {
             promotion_meta_map: loaded_promotions.promotion_meta_map,
             currency: loaded_products.currency,
         },
-        products: worker_products,
+        product_keys,
     })
 }
 
@@ -235,31 +225,22 @@ async fn estimate_cart_worker(cart_keys: String) -> String {
 
     let cart_snapshot = decode_cart_keys(&cart_keys);
 
-    let Ok(base_total_minor) = basket::solve_total_minor(&worker_data.solver_data, &cart_snapshot)
-    else {
-        return format!("{WORKER_ERROR_PREFIX}Failed to solve base basket");
+    let Ok(estimates) = basket::estimate_additions_minor(
+        &worker_data.solver_data,
+        &cart_snapshot,
+        &worker_data.product_keys,
+    ) else {
+        return format!("{WORKER_ERROR_PREFIX}Failed to estimate basket additions");
     };
 
-    let mut lines: Vec<String> = Vec::with_capacity(worker_data.products.len());
-
-    for product in &worker_data.products {
-        let mut projected_cart = cart_snapshot.clone();
-        projected_cart.push(product.fixture_key.clone());
-
-        let Ok(projected_total_minor) =
-            basket::solve_total_minor(&worker_data.solver_data, &projected_cart)
-        else {
-            continue;
-        };
-
-        let marginal_minor = projected_total_minor - base_total_minor;
-        let savings_minor = product.price_minor - marginal_minor;
-
-        lines.push(format!(
-            "{}\t{}\t{}",
-            product.fixture_key, marginal_minor, savings_minor
-        ));
-    }
+    let lines: Vec<String> = worker_data
+        .product_keys
+        .iter()
+        .zip(estimates)
+        .map(|(fixture_key, (marginal_minor, savings_minor))| {
+            format!("{fixture_key}\t{marginal_minor}\t{savings_minor}")
+        })
+        .collect();
 
     lines.join("\n")
 }
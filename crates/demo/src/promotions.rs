@@ -1,15 +1,19 @@
 use std::collections::BTreeMap;
 
 use petgraph::graph::NodeIndex;
+use rustc_hash::FxHashMap;
 use slotmap::{SecondaryMap, SlotMap};
 
 use lattice::{
     fixtures::{
         graph::{GraphFixture, GraphNodeFixture},
-        promotions::PromotionsFixture,
+        promotions::{
+            ExclusionGroupNames, PromotionReferences, PromotionsFixture, register_budget_pools,
+            register_exclusion_groups,
+        },
     },
     graph::{OutputMode, PromotionGraph, PromotionGraphBuilder},
-    promotions::{Promotion, PromotionKey, PromotionMeta},
+    promotions::{Promotion, PromotionKey, PromotionMeta, budget::BudgetPools},
 };
 
 /// Render model for a promotion pill.
@@ -55,12 +59,42 @@ pub fn load_promotions(yaml: &str) -> Result<LoadedPromotions, String> {
     let mut promotion_meta_map: SlotMap<PromotionKey, PromotionMeta> = SlotMap::with_key();
     let mut promotion_names: SecondaryMap<PromotionKey, String> = SecondaryMap::new();
     let mut promotions_by_fixture_key: BTreeMap<String, Promotion<'static>> = BTreeMap::new();
+    let mut budget_pools = BudgetPools::default();
+
+    let budget_pool_keys =
+        register_budget_pools(promotions_fixture.budget_pools, &mut budget_pools)
+            .map_err(|error| format!("Failed to parse budget pools: {error}"))?;
+
+    let mut exclusion_groups = SlotMap::with_key();
+    let mut exclusion_group_keys = ExclusionGroupNames::default();
+
+    register_exclusion_groups(
+        promotions_fixture.promotions.values(),
+        &mut exclusion_groups,
+        &mut exclusion_group_keys,
+    );
+
+    // Promotions may exclude each other by fixture key, so every key is assigned first.
+    let mut promotion_keys: FxHashMap<String, PromotionKey> = FxHashMap::default();
+    let mut keyed_fixtures = Vec::with_capacity(promotions_fixture.promotions.len());
 
     for (fixture_key, promotion_fixture) in promotions_fixture.promotions {
         let promotion_key = promotion_meta_map.insert(PromotionMeta::default());
 
+        promotion_keys.insert(fixture_key.clone(), promotion_key);
+        keyed_fixtures.push((fixture_key, promotion_key, promotion_fixture));
+    }
+
+    for (fixture_key, promotion_key, promotion_fixture) in keyed_fixtures {
         let (promotion_meta, promotion) = promotion_fixture
-            .try_into_promotion(promotion_key)
+            .try_into_promotion_with_references(
+                promotion_key,
+                PromotionReferences {
+                    budget_pools: &budget_pool_keys,
+                    promotions: &promotion_keys,
+                    exclusion_groups: &exclusion_group_keys,
+                },
+            )
             .map_err(|error| format!("Failed to parse promotion '{fixture_key}': {error}"))?;
 
         promotion_names.insert(promotion_key, promotion_meta.name.clone());
@@ -75,7 +109,7 @@ pub fn load_promotions(yaml: &str) -> Result<LoadedPromotions, String> {
     }
 
     Ok(LoadedPromotions {
-        graph: build_graph(&graph_fixture, &promotions_by_fixture_key)?,
+        graph: build_graph(&graph_fixture, &promotions_by_fixture_key, budget_pools)?,
         promotion_names,
         promotion_meta_map,
     })
@@ -104,9 +138,12 @@ pub fn redemption_pill_style(redemption_idx: usize) -> String {
 fn build_graph(
     graph_fixture: &GraphFixture,
     promotions_by_fixture_key: &BTreeMap<String, Promotion<'static>>,
+    budget_pools: BudgetPools<'static>,
 ) -> Result<PromotionGraph<'static>, String> {
     let mut builder = PromotionGraphBuilder::new();
 
+    builder.set_budget_pools(budget_pools);
+
     let node_indices = add_graph_nodes(&mut builder, graph_fixture, promotions_by_fixture_key)?;
 
     let root = node_indices
@@ -0,0 +1,186 @@
+//! Evaluation Context
+
+use std::collections::{HashMap, HashSet};
+
+use ext_php_rs::{
+    class::RegisteredClass,
+    convert::{FromZval, IntoZval},
+    flags::DataType,
+    prelude::*,
+    types::Zval,
+};
+use jiff::Timestamp;
+
+use lattice::{
+    context::EvaluationContext as CoreEvaluationContext, tags::string::StringTagCollection,
+};
+
+#[derive(Debug, Clone)]
+#[php_class]
+#[php(name = "Lattice\\EvaluationContext")]
+pub struct EvaluationContext {
+    #[php(prop)]
+    customer_segments: HashSet<String>,
+
+    #[php(prop)]
+    channel: Option<String>,
+
+    #[php(prop)]
+    store_id: Option<String>,
+
+    #[php(prop)]
+    timestamp: Option<String>,
+
+    #[php(prop)]
+    attributes: HashMap<String, String>,
+}
+
+#[php_impl]
+impl EvaluationContext {
+    pub fn __construct(
+        customer_segments: Option<HashSet<String>>,
+        channel: Option<String>,
+        store_id: Option<String>,
+        timestamp: Option<String>,
+        attributes: Option<HashMap<String, String>>,
+    ) -> PhpResult<Self> {
+        if let Some(timestamp) = &timestamp {
+            parse_timestamp(timestamp)?;
+        }
+
+        Ok(Self {
+            customer_segments: customer_segments.unwrap_or_default(),
+            channel,
+            store_id,
+            timestamp,
+            attributes: attributes.unwrap_or_default(),
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct EvaluationContextRef(Zval);
+
+impl<'a> FromZval<'a> for EvaluationContextRef {
+    const TYPE: DataType =
+        DataType::Object(Some(<EvaluationContext as RegisteredClass>::CLASS_NAME));
+
+    fn from_zval(zval: &'a Zval) -> Option<Self> {
+        let obj = zval.object()?;
+
+        if obj.is_instance::<EvaluationContext>() {
+            Some(Self(zval.shallow_clone()))
+        } else {
+            None
+        }
+    }
+}
+
+impl Clone for EvaluationContextRef {
+    fn clone(&self) -> Self {
+        Self(self.0.shallow_clone())
+    }
+}
+
+impl IntoZval for EvaluationContextRef {
+    const TYPE: DataType =
+        DataType::Object(Some(<EvaluationContext as RegisteredClass>::CLASS_NAME));
+    const NULLABLE: bool = false;
+
+    fn set_zval(self, zv: &mut Zval, persistent: bool) -> ext_php_rs::error::Result<()> {
+        self.0.set_zval(zv, persistent)
+    }
+}
+
+impl TryFrom<&EvaluationContextRef> for EvaluationContext {
+    type Error = PhpException;
+
+    fn try_from(value: &EvaluationContextRef) -> Result<Self, Self::Error> {
+        let Some(obj) = value.0.object() else {
+            return Err(PhpException::default(
+                "EvaluationContext object is invalid.".to_string(),
+            ));
+        };
+
+        let customer_segments = obj
+            .get_property::<HashSet<String>>("customerSegments")
+            .map_err(|_| {
+                PhpException::default("EvaluationContext customerSegments are invalid.".to_string())
+            })?;
+
+        let channel = obj.get_property::<Option<String>>("channel").map_err(|_| {
+            PhpException::default("EvaluationContext channel is invalid.".to_string())
+        })?;
+
+        let store_id = obj.get_property::<Option<String>>("storeId").map_err(|_| {
+            PhpException::default("EvaluationContext storeId is invalid.".to_string())
+        })?;
+
+        let timestamp = obj
+            .get_property::<Option<String>>("timestamp")
+            .map_err(|_| {
+                PhpException::default("EvaluationContext timestamp is invalid.".to_string())
+            })?;
+
+        let attributes = obj
+            .get_property::<HashMap<String, String>>("attributes")
+            .map_err(|_| {
+                PhpException::default("EvaluationContext attributes are invalid.".to_string())
+            })?;
+
+        Ok(EvaluationContext {
+            customer_segments,
+            channel,
+            store_id,
+            timestamp,
+            attributes,
+        })
+    }
+}
+
+impl TryFrom<&EvaluationContextRef> for CoreEvaluationContext {
+    type Error = PhpException;
+
+    fn try_from(value: &EvaluationContextRef) -> Result<Self, Self::Error> {
+        let context: EvaluationContext = value.try_into()?;
+
+        context.try_into()
+    }
+}
+
+impl TryFrom<EvaluationContext> for CoreEvaluationContext {
+    type Error = PhpException;
+
+    fn try_from(context: EvaluationContext) -> Result<Self, Self::Error> {
+        let mut core = CoreEvaluationContext::new().with_customer_segments(
+            StringTagCollection::new(context.customer_segments.into_iter().collect()),
+        );
+
+        if let Some(channel) = context.channel {
+            core = core.with_channel(channel);
+        }
+
+        if let Some(store_id) = context.store_id {
+            core = core.with_store_id(store_id);
+        }
+
+        if let Some(timestamp) = context.timestamp {
+            core = core.with_timestamp(parse_timestamp(&timestamp)?);
+        }
+
+        for (key, value) in context.attributes {
+            core = core.with_attribute(key, value);
+        }
+
+        Ok(core)
+    }
+}
+
+/// Parse an RFC 3339 timestamp (e.g. `2026-03-01T12:00:00Z`).
+pub(crate) fn parse_timestamp(value: &str) -> Result<Timestamp, PhpException> {
+    value.parse().map_err(|_| {
+        PhpException::default(format!(
+            "Invalid timestamp \"{value}\"; expected an RFC 3339 string."
+        ))
+    })
+}
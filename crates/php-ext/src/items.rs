@@ -31,6 +31,9 @@ pub struct Item {
 
     #[php(prop)]
     tags: HashSet<String>,
+
+    #[php(prop)]
+    quantity: u32,
 }
 
 #[php_impl]
@@ -41,24 +44,41 @@ impl Item {
         price: MoneyRef,
         product: ProductRef,
         tags: Option<HashSet<String>>,
-    ) -> Self {
-        Self {
+        quantity: Option<u32>,
+    ) -> PhpResult<Self> {
+        Ok(Self {
             reference,
             name,
             price,
             product,
             tags: tags.unwrap_or_default(),
-        }
+            quantity: line_quantity(quantity)?,
+        })
     }
 
-    pub fn from_product(reference: ReferenceValue, product: ProductRef) -> Self {
-        Self {
+    pub fn from_product(
+        reference: ReferenceValue,
+        product: ProductRef,
+        quantity: Option<u32>,
+    ) -> PhpResult<Self> {
+        Ok(Self {
             reference,
             name: product.name(),
             price: product.price(),
             tags: product.tags(),
             product,
-        }
+            quantity: line_quantity(quantity)?,
+        })
+    }
+}
+
+/// Default a missing quantity to one unit and reject a quantity of zero.
+fn line_quantity(quantity: Option<u32>) -> PhpResult<u32> {
+    match quantity.unwrap_or(1) {
+        0 => Err(PhpException::default(
+            "Item quantity must be at least 1.".to_string(),
+        )),
+        quantity => Ok(quantity),
     }
 }
 
@@ -70,6 +90,10 @@ impl Item {
     pub(crate) fn tags(&self) -> &HashSet<String> {
         &self.tags
     }
+
+    pub(crate) fn quantity(&self) -> u32 {
+        self.quantity
+    }
 }
 
 #[derive(Debug)]
@@ -143,12 +167,17 @@ impl TryFrom<&ItemRef> for Item {
             .get_property::<HashSet<String>>("tags")
             .map_err(|_| PhpException::default("Item tags are invalid.".to_string()))?;
 
+        let quantity = obj
+            .get_property::<u32>("quantity")
+            .map_err(|_| PhpException::default("Item quantity is invalid.".to_string()))?;
+
         Ok(Self {
             reference,
             name,
             price,
             product,
             tags,
+            quantity,
         })
     }
 }
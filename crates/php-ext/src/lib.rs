@@ -3,6 +3,7 @@
 use ext_php_rs::prelude::*;
 
 use crate::{
+    context::EvaluationContext,
     discounts::{
         DiscountKind, InvalidDiscountException, SimpleDiscount,
         percentages::{InvalidPercentageException, Percentage, PercentageOutOfRangeException},
@@ -11,7 +12,7 @@ use crate::{
     money::Money,
     products::Product,
     promotions::{
-        budgets::Budget,
+        budgets::{Budget, BudgetPool},
         interface::PhpInterfacePromotion,
         types::{
             direct_discount::DirectDiscountPromotion,
@@ -34,6 +35,7 @@ use crate::{
     },
 };
 
+pub mod context;
 pub mod discounts;
 pub mod items;
 pub mod money;
@@ -50,6 +52,7 @@ pub fn get_module(module: ModuleBuilder) -> ModuleBuilder {
         .class::<Money>()
         .class::<Product>()
         .class::<Item>()
+        .class::<EvaluationContext>()
         .enumeration::<BoolOp>()
         .enumeration::<RuleKind>()
         .class::<Qualification>()
@@ -60,6 +63,7 @@ pub fn get_module(module: ModuleBuilder) -> ModuleBuilder {
         .class::<Percentage>()
         .enumeration::<DiscountKind>()
         .class::<SimpleDiscount>()
+        .class::<BudgetPool>()
         .class::<Budget>()
         .interface::<PhpInterfacePromotion>()
         .class::<DirectDiscountPromotion>()
@@ -1,5 +1,7 @@
 //! Budgets
 
+use std::collections::HashMap;
+
 use ext_php_rs::{
     class::RegisteredClass,
     convert::{FromZval, IntoZval},
@@ -9,7 +11,7 @@ use ext_php_rs::{
     types::Zval,
 };
 
-use lattice::promotions::budget::PromotionBudget;
+use lattice::promotions::budget::{BudgetPoolKey, BudgetPools, PromotionBudget};
 
 use crate::money::{Money, MoneyRef};
 
@@ -22,6 +24,9 @@ pub struct Budget {
 
     #[php(prop)]
     pub monetary_limit: Option<MoneyRef>,
+
+    #[php(prop)]
+    pub pools: Vec<BudgetPoolRef>,
 }
 
 #[php_impl]
@@ -30,6 +35,7 @@ impl Budget {
         Self {
             redemption_limit: None,
             monetary_limit: None,
+            pools: Vec::new(),
         }
     }
 
@@ -37,6 +43,7 @@ impl Budget {
         Self {
             redemption_limit: Some(limit),
             monetary_limit: None,
+            pools: Vec::new(),
         }
     }
 
@@ -44,6 +51,7 @@ impl Budget {
         Self {
             redemption_limit: None,
             monetary_limit: Some(limit),
+            pools: Vec::new(),
         }
     }
 
@@ -51,10 +59,176 @@ impl Budget {
         Self {
             redemption_limit: Some(redemption),
             monetary_limit: Some(monetary),
+            pools: Vec::new(),
+        }
+    }
+
+    pub fn with_pool(&self, pool: BudgetPoolRef) -> Self {
+        let mut budget = self.clone();
+
+        budget.pools.push(pool);
+
+        budget
+    }
+}
+
+#[derive(Debug, Clone)]
+#[php_class]
+#[php(name = "Lattice\\Promotion\\BudgetPool")]
+pub struct BudgetPool {
+    #[php(prop)]
+    pub name: String,
+
+    #[php(prop)]
+    pub redemption_limit: Option<i64>,
+
+    #[php(prop)]
+    pub monetary_limit: Option<MoneyRef>,
+}
+
+#[php_impl]
+impl BudgetPool {
+    pub fn __construct(
+        name: String,
+        redemption_limit: Option<i64>,
+        monetary_limit: Option<MoneyRef>,
+    ) -> Self {
+        Self {
+            name,
+            redemption_limit,
+            monetary_limit,
         }
     }
 }
 
+#[derive(Debug)]
+pub struct BudgetPoolRef(Zval);
+
+impl<'a> FromZval<'a> for BudgetPoolRef {
+    const TYPE: DataType = DataType::Object(Some(<BudgetPool as RegisteredClass>::CLASS_NAME));
+
+    fn from_zval(zval: &'a Zval) -> Option<Self> {
+        let obj = zval.object()?;
+
+        if obj.is_instance::<BudgetPool>() {
+            Some(Self(zval.shallow_clone()))
+        } else {
+            None
+        }
+    }
+}
+
+impl Clone for BudgetPoolRef {
+    fn clone(&self) -> Self {
+        Self(self.0.shallow_clone())
+    }
+}
+
+impl IntoZval for BudgetPoolRef {
+    const TYPE: DataType = DataType::Object(Some(<BudgetPool as RegisteredClass>::CLASS_NAME));
+    const NULLABLE: bool = false;
+
+    fn set_zval(self, zv: &mut Zval, persistent: bool) -> ext_php_rs::error::Result<()> {
+        self.0.set_zval(zv, persistent)
+    }
+}
+
+impl TryFrom<&BudgetPoolRef> for BudgetPool {
+    type Error = PhpException;
+
+    fn try_from(value: &BudgetPoolRef) -> Result<Self, Self::Error> {
+        let Some(obj) = value.0.object() else {
+            return Err(PhpException::default(
+                "BudgetPool object is invalid.".to_string(),
+            ));
+        };
+
+        let name = obj
+            .get_property::<String>("name")
+            .map_err(|_| PhpException::default("BudgetPool name is invalid.".to_string()))?;
+
+        let redemption_limit =
+            obj.get_property::<Option<i64>>("redemptionLimit")
+                .map_err(|_| {
+                    PhpException::default("BudgetPool redemption_limit is invalid.".to_string())
+                })?;
+
+        let monetary_limit = obj
+            .get_property::<Option<MoneyRef>>("monetaryLimit")
+            .map_err(|_| {
+                PhpException::default("BudgetPool monetary_limit is invalid.".to_string())
+            })?;
+
+        Ok(BudgetPool {
+            name,
+            redemption_limit,
+            monetary_limit,
+        })
+    }
+}
+
+/// Shared budget pools collected from the promotions of a stack, keyed by name.
+#[derive(Debug, Default)]
+pub(crate) struct BudgetPoolRegistry {
+    pools: BudgetPools<'static>,
+    keys: HashMap<String, BudgetPoolKey>,
+}
+
+impl BudgetPoolRegistry {
+    /// Convert a promotion budget, registering the pools it draws on.
+    ///
+    /// Pools are matched by name, so every promotion naming a pool must give it the same limits.
+    pub(crate) fn resolve(
+        &mut self,
+        budget: &BudgetRef,
+    ) -> Result<(PromotionBudget<'static>, Vec<BudgetPoolKey>), PhpException> {
+        let budget: Budget = budget.try_into()?;
+
+        let pool_keys = budget
+            .pools
+            .iter()
+            .map(|pool_ref| self.register(pool_ref.try_into()?))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok((budget.try_into()?, pool_keys))
+    }
+
+    fn register(&mut self, pool: BudgetPool) -> Result<BudgetPoolKey, PhpException> {
+        let limits: PromotionBudget<'static> = Budget {
+            redemption_limit: pool.redemption_limit,
+            monetary_limit: pool.monetary_limit,
+            pools: Vec::new(),
+        }
+        .try_into()?;
+
+        if let Some(&key) = self.keys.get(&pool.name) {
+            let matches = self.pools.get(key).is_some_and(|existing| {
+                existing.redemption_limit == limits.redemption_limit
+                    && existing.monetary_limit == limits.monetary_limit
+            });
+
+            if !matches {
+                return Err(PhpException::default(format!(
+                    "Budget pool '{}' is defined with conflicting limits.",
+                    pool.name
+                )));
+            }
+
+            return Ok(key);
+        }
+
+        let key = self.pools.insert(limits);
+
+        self.keys.insert(pool.name, key);
+
+        Ok(key)
+    }
+
+    pub(crate) fn into_pools(self) -> BudgetPools<'static> {
+        self.pools
+    }
+}
+
 #[derive(Debug)]
 pub struct BudgetRef(Zval);
 
@@ -119,9 +293,14 @@ impl TryFrom<&BudgetRef> for Budget {
             .get_property::<Option<MoneyRef>>("monetaryLimit")
             .map_err(|_| PhpException::default("Budget monetary_limit is invalid.".to_string()))?;
 
+        let pools = obj
+            .get_property::<Vec<BudgetPoolRef>>("pools")
+            .map_err(|_| PhpException::default("Budget pools are invalid.".to_string()))?;
+
         Ok(Budget {
             redemption_limit,
             monetary_limit,
+            pools,
         })
     }
 }
@@ -199,6 +378,7 @@ impl From<PromotionBudget<'static>> for Budget {
         Self {
             redemption_limit: budget.redemption_limit.map(i64::from),
             monetary_limit,
+            pools: Vec::new(),
         }
     }
 }
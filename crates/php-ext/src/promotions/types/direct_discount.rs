@@ -16,7 +16,10 @@ use lattice::{
 
 use crate::{
     discounts::SimpleDiscountRef,
-    promotions::{budgets::BudgetRef, interface::PhpInterfacePromotion},
+    promotions::{
+        budgets::{BudgetPoolRegistry, BudgetRef},
+        interface::PhpInterfacePromotion,
+    },
     qualification::QualificationRef,
     reference_value::ReferenceValue,
 };
@@ -60,13 +63,20 @@ impl DirectDiscountPromotion {
     pub(crate) fn try_to_core_with_key(
         &self,
         key: PromotionKey,
+        budget_pools: &mut BudgetPoolRegistry,
     ) -> Result<CoreDirectDiscountPromotion<'static, StringTagCollection>, PhpException> {
-        Ok(CoreDirectDiscountPromotion::new(
+        let (budget, pool_keys) = budget_pools.resolve(&self.budget)?;
+
+        let promotion = CoreDirectDiscountPromotion::new(
             key,
             (&self.qualification).try_into()?,
             (&self.discount).try_into()?,
-            (&self.budget).try_into()?,
-        ))
+            budget,
+        );
+
+        Ok(pool_keys
+            .into_iter()
+            .fold(promotion, CoreDirectDiscountPromotion::with_budget_pool))
     }
 }
 
@@ -20,7 +20,10 @@ use lattice::{
 use crate::{
     discounts::{InvalidDiscountException, percentages::PercentageRef, require_money},
     money::MoneyRef,
-    promotions::{budgets::BudgetRef, interface::PhpInterfacePromotion},
+    promotions::{
+        budgets::{BudgetPoolRegistry, BudgetRef},
+        interface::PhpInterfacePromotion,
+    },
     qualification::QualificationRef,
     reference_value::ReferenceValue,
 };
@@ -571,6 +574,7 @@ impl MixAndMatchDiscountPromotion {
     pub(crate) fn try_to_core_with_key(
         &self,
         key: PromotionKey,
+        budget_pools: &mut BudgetPoolRegistry,
     ) -> Result<CoreMixAndMatchPromotion<'static>, PhpException> {
         let mut slot_keys = SlotMap::<PromotionSlotKey, ()>::with_key();
 
@@ -584,11 +588,13 @@ impl MixAndMatchDiscountPromotion {
             })
             .collect::<Result<Vec<_>, _>>()?;
 
-        Ok(CoreMixAndMatchPromotion::new(
-            key,
-            slots,
-            (&self.discount).try_into()?,
-            (&self.budget).try_into()?,
-        ))
+        let (budget, pool_keys) = budget_pools.resolve(&self.budget)?;
+
+        let promotion =
+            CoreMixAndMatchPromotion::new(key, slots, (&self.discount).try_into()?, budget);
+
+        Ok(pool_keys
+            .into_iter()
+            .fold(promotion, CoreMixAndMatchPromotion::with_budget_pool))
     }
 }
@@ -15,7 +15,10 @@ use lattice::{
 
 use crate::{
     discounts::SimpleDiscountRef,
-    promotions::{budgets::BudgetRef, interface::PhpInterfacePromotion},
+    promotions::{
+        budgets::{BudgetPoolRegistry, BudgetRef},
+        interface::PhpInterfacePromotion,
+    },
     qualification::QualificationRef,
     reference_value::ReferenceValue,
 };
@@ -69,15 +72,22 @@ impl PositionalDiscountPromotion {
     pub(crate) fn try_to_core_with_key(
         &self,
         key: PromotionKey,
+        budget_pools: &mut BudgetPoolRegistry,
     ) -> Result<CorePositionalDiscountPromotion<'static, StringTagCollection>, PhpException> {
-        Ok(CorePositionalDiscountPromotion::new(
+        let (budget, pool_keys) = budget_pools.resolve(&self.budget)?;
+
+        let promotion = CorePositionalDiscountPromotion::new(
             key,
             (&self.qualification).try_into()?,
             self.size,
             self.positions.clone().into(),
             (&self.discount).try_into()?,
-            (&self.budget).try_into()?,
-        ))
+            budget,
+        );
+
+        Ok(pool_keys
+            .into_iter()
+            .fold(promotion, CorePositionalDiscountPromotion::with_budget_pool))
     }
 }
 
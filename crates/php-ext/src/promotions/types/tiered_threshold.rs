@@ -20,7 +20,10 @@ use lattice::{
 use crate::{
     discounts::{InvalidDiscountException, percentages::PercentageRef, require_money},
     money::MoneyRef,
-    promotions::{budgets::BudgetRef, interface::PhpInterfacePromotion},
+    promotions::{
+        budgets::{BudgetPoolRegistry, BudgetRef},
+        interface::PhpInterfacePromotion,
+    },
     qualification::QualificationRef,
     reference_value::ReferenceValue,
 };
@@ -749,6 +752,7 @@ impl TieredThresholdPromotion {
     pub(crate) fn try_to_core_with_key(
         &self,
         key: PromotionKey,
+        budget_pools: &mut BudgetPoolRegistry,
     ) -> Result<CoreTieredThresholdPromotion<'static>, PhpException> {
         let tiers = self
             .tiers
@@ -760,10 +764,12 @@ impl TieredThresholdPromotion {
             })
             .collect::<Result<Vec<_>, _>>()?;
 
-        Ok(CoreTieredThresholdPromotion::new(
-            key,
-            tiers,
-            (&self.budget).try_into()?,
-        ))
+        let (budget, pool_keys) = budget_pools.resolve(&self.budget)?;
+
+        let promotion = CoreTieredThresholdPromotion::new(key, tiers, budget);
+
+        Ok(pool_keys
+            .into_iter()
+            .fold(promotion, CoreTieredThresholdPromotion::with_budget_pool))
     }
 }
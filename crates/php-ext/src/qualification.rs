@@ -12,6 +12,7 @@ use ext_php_rs::{
 use smallvec::SmallVec;
 
 use lattice::{
+    context::EvaluationContext as CoreEvaluationContext,
     promotions::qualification::{
         BoolOp as CoreBoolOp, Qualification as CoreQualification,
         QualificationRule as CoreQualificationRule,
@@ -19,6 +20,8 @@ use lattice::{
     tags::string::StringTagCollection,
 };
 
+use crate::context::{EvaluationContextRef, parse_timestamp};
+
 #[derive(Debug, Clone, Copy)]
 #[php_enum]
 #[php(name = "Lattice\\Qualification\\BoolOp")]
@@ -54,6 +57,21 @@ pub enum RuleKind {
 
     #[php(value = "group")]
     Group,
+
+    #[php(value = "customer_segment")]
+    CustomerSegment,
+
+    #[php(value = "channel")]
+    Channel,
+
+    #[php(value = "store")]
+    Store,
+
+    #[php(value = "time_window")]
+    TimeWindow,
+
+    #[php(value = "attribute")]
+    Attribute,
 }
 
 #[derive(Debug, Clone)]
@@ -68,6 +86,15 @@ pub struct Rule {
 
     #[php(prop)]
     group: Option<QualificationRef>,
+
+    #[php(prop)]
+    key: Option<String>,
+
+    #[php(prop)]
+    starts_at: Option<String>,
+
+    #[php(prop)]
+    ends_at: Option<String>,
 }
 
 #[php_impl]
@@ -77,6 +104,9 @@ impl Rule {
             kind: RuleKind::HasAll,
             tags: tags.unwrap_or_default(),
             group: None,
+            key: None,
+            starts_at: None,
+            ends_at: None,
         }
     }
 
@@ -85,6 +115,9 @@ impl Rule {
             kind: RuleKind::HasAny,
             tags: tags.unwrap_or_default(),
             group: None,
+            key: None,
+            starts_at: None,
+            ends_at: None,
         }
     }
 
@@ -93,6 +126,9 @@ impl Rule {
             kind: RuleKind::HasNone,
             tags: tags.unwrap_or_default(),
             group: None,
+            key: None,
+            starts_at: None,
+            ends_at: None,
         }
     }
 
@@ -101,18 +137,80 @@ impl Rule {
             kind: RuleKind::Group,
             tags: HashSet::default(),
             group: Some(qualification),
+            key: None,
+            starts_at: None,
+            ends_at: None,
         }
     }
 
-    pub fn matches(&self, item_tags: Option<HashSet<String>>) -> PhpResult<bool> {
+    pub fn customer_segment(segments: Option<HashSet<String>>) -> Self {
+        Self::context_rule(RuleKind::CustomerSegment, segments.unwrap_or_default())
+    }
+
+    pub fn channel(channels: Option<HashSet<String>>) -> Self {
+        Self::context_rule(RuleKind::Channel, channels.unwrap_or_default())
+    }
+
+    pub fn store(store_ids: Option<HashSet<String>>) -> Self {
+        Self::context_rule(RuleKind::Store, store_ids.unwrap_or_default())
+    }
+
+    pub fn time_window(starts_at: Option<String>, ends_at: Option<String>) -> PhpResult<Self> {
+        let starts = starts_at.as_deref().map(parse_timestamp).transpose()?;
+        let ends = ends_at.as_deref().map(parse_timestamp).transpose()?;
+
+        if let (Some(starts), Some(ends)) = (starts, ends)
+            && starts >= ends
+        {
+            return Err(PhpException::default(
+                "Time window start must be before its end.".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            kind: RuleKind::TimeWindow,
+            tags: HashSet::default(),
+            group: None,
+            key: None,
+            starts_at,
+            ends_at,
+        })
+    }
+
+    pub fn attribute(key: String, values: Option<HashSet<String>>) -> Self {
+        Self {
+            key: Some(key),
+            ..Self::context_rule(RuleKind::Attribute, values.unwrap_or_default())
+        }
+    }
+
+    pub fn matches(
+        &self,
+        item_tags: Option<HashSet<String>>,
+        context: Option<EvaluationContextRef>,
+    ) -> PhpResult<bool> {
         let core_rule: CoreQualificationRule<StringTagCollection> = self.clone().try_into()?;
 
         let item_tags = tags_to_collection(item_tags.unwrap_or_default());
+        let context = core_context(context.as_ref())?;
 
         let qualification =
             CoreQualification::new(CoreBoolOp::And, SmallVec::from_vec(vec![core_rule]));
 
-        Ok(qualification.matches(&item_tags))
+        Ok(qualification.matches_in_context(&item_tags, &context))
+    }
+}
+
+impl Rule {
+    fn context_rule(kind: RuleKind, values: HashSet<String>) -> Self {
+        Self {
+            kind,
+            tags: values,
+            group: None,
+            key: None,
+            starts_at: None,
+            ends_at: None,
+        }
     }
 }
 
@@ -156,12 +254,17 @@ impl Qualification {
         }
     }
 
-    pub fn matches(&self, item_tags: Option<HashSet<String>>) -> PhpResult<bool> {
+    pub fn matches(
+        &self,
+        item_tags: Option<HashSet<String>>,
+        context: Option<EvaluationContextRef>,
+    ) -> PhpResult<bool> {
         let qualification: CoreQualification<StringTagCollection> = self.clone().try_into()?;
 
         let item_tags = tags_to_collection(item_tags.unwrap_or_default());
+        let context = core_context(context.as_ref())?;
 
-        Ok(qualification.matches(&item_tags))
+        Ok(qualification.matches_in_context(&item_tags, &context))
     }
 }
 
@@ -320,7 +423,26 @@ impl TryFrom<&QualificationRuleRef> for Rule {
             .get_property::<Option<QualificationRef>>("group")
             .map_err(|_| PhpException::default("Rule group is invalid.".to_string()))?;
 
-        Ok(Rule { kind, tags, group })
+        let key = obj
+            .get_property::<Option<String>>("key")
+            .map_err(|_| PhpException::default("Rule key is invalid.".to_string()))?;
+
+        let starts_at = obj
+            .get_property::<Option<String>>("startsAt")
+            .map_err(|_| PhpException::default("Rule startsAt is invalid.".to_string()))?;
+
+        let ends_at = obj
+            .get_property::<Option<String>>("endsAt")
+            .map_err(|_| PhpException::default("Rule endsAt is invalid.".to_string()))?;
+
+        Ok(Rule {
+            kind,
+            tags,
+            group,
+            key,
+            starts_at,
+            ends_at,
+        })
     }
 }
 
@@ -389,10 +511,41 @@ impl TryFrom<Rule> for CoreQualificationRule<StringTagCollection> {
 
                 Ok(CoreQualificationRule::Group(Box::new(group.try_into()?)))
             }
+            RuleKind::CustomerSegment => Ok(CoreQualificationRule::CustomerSegment {
+                segments: tags_to_collection(rule.tags),
+            }),
+            RuleKind::Channel => Ok(CoreQualificationRule::Channel {
+                channels: rule.tags.into_iter().collect(),
+            }),
+            RuleKind::Store => Ok(CoreQualificationRule::Store {
+                store_ids: rule.tags.into_iter().collect(),
+            }),
+            RuleKind::TimeWindow => Ok(CoreQualificationRule::TimeWindow {
+                starts_at: rule.starts_at.as_deref().map(parse_timestamp).transpose()?,
+                ends_at: rule.ends_at.as_deref().map(parse_timestamp).transpose()?,
+            }),
+            RuleKind::Attribute => {
+                let Some(key) = rule.key else {
+                    return Err(PhpException::default(
+                        "Attribute rule requires a key.".to_string(),
+                    ));
+                };
+
+                Ok(CoreQualificationRule::Attribute {
+                    key,
+                    values: rule.tags.into_iter().collect(),
+                })
+            }
         }
     }
 }
 
+fn core_context(
+    context: Option<&EvaluationContextRef>,
+) -> Result<CoreEvaluationContext, PhpException> {
+    context.map_or_else(|| Ok(CoreEvaluationContext::default()), TryInto::try_into)
+}
+
 fn tags_to_collection(tags: HashSet<String>) -> StringTagCollection {
     StringTagCollection::new(tags.into_iter().collect())
 }
@@ -28,6 +28,9 @@ pub struct PromotionRedemption {
 
     #[php(prop)]
     final_price: MoneyRef,
+
+    #[php(prop)]
+    quantity: u32,
 }
 
 #[php_impl]
@@ -38,6 +41,7 @@ impl PromotionRedemption {
         redemption_idx: usize,
         original_price: MoneyRef,
         final_price: MoneyRef,
+        quantity: Option<u32>,
     ) -> Self {
         Self {
             promotion,
@@ -45,6 +49,7 @@ impl PromotionRedemption {
             redemption_idx,
             original_price,
             final_price,
+            quantity: quantity.unwrap_or(1),
         }
     }
 }
@@ -127,12 +132,17 @@ impl TryFrom<&PromotionRedemptionRef> for PromotionRedemption {
             PhpException::default("PromotionRedemption final_price is invalid.".to_string())
         })?;
 
+        let quantity = obj.get_property::<u32>("quantity").map_err(|_| {
+            PhpException::default("PromotionRedemption quantity is invalid.".to_string())
+        })?;
+
         Ok(Self {
             promotion,
             item,
             redemption_idx,
             original_price,
             final_price,
+            quantity,
         })
     }
 }
@@ -1,6 +1,6 @@
 //! Promotion Stack/Graph and Layers/Nodes
 
-use std::collections::HashMap;
+use std::{collections::HashMap, num::NonZeroU32};
 
 use ext_php_rs::{
     class::RegisteredClass,
@@ -16,6 +16,7 @@ use slotmap::SlotMap;
 use smallvec::SmallVec;
 
 use lattice::{
+    context::EvaluationContext as CoreEvaluationContext,
     graph::{GraphError, PromotionGraph, PromotionGraphBuilder},
     items::{Item as CoreItem, groups::ItemGroup},
     products::ProductKey,
@@ -24,9 +25,11 @@ use lattice::{
 };
 
 use crate::{
+    context::EvaluationContextRef,
     items::{Item, ItemRef},
     money::{Money, MoneyRef},
     promotions::{
+        budgets::BudgetPoolRegistry,
         interface::{PhpInterfacePromotion, PromotionRef},
         types::{
             direct_discount::{DirectDiscountPromotion, DirectDiscountPromotionRef},
@@ -146,8 +149,12 @@ impl Stack {
         Ok(true)
     }
 
-    pub fn process(&self, items: Vec<ItemRef>) -> PhpResult<Receipt> {
-        self.process_items(items)
+    pub fn process(
+        &self,
+        items: Vec<ItemRef>,
+        context: Option<EvaluationContextRef>,
+    ) -> PhpResult<Receipt> {
+        self.process_items(items, context.as_ref())
     }
 }
 
@@ -172,6 +179,7 @@ impl Stack {
 
         let mut promotion_keys = SlotMap::<PromotionKey, ()>::with_key();
         let mut promotions = HashMap::new();
+        let mut budget_pools = BudgetPoolRegistry::default();
 
         let mut layer_nodes = Vec::with_capacity(self.layers.len());
         let mut layer_outputs = Vec::with_capacity(self.layers.len());
@@ -194,7 +202,9 @@ impl Stack {
                 {
                     let promo: DirectDiscountPromotion = (&direct_discount_ref).try_into()?;
 
-                    core_promotions.push(promotion(promo.try_to_core_with_key(promotion_key)?));
+                    core_promotions.push(promotion(
+                        promo.try_to_core_with_key(promotion_key, &mut budget_pools)?,
+                    ));
 
                     continue;
                 }
@@ -205,7 +215,9 @@ impl Stack {
                     let promo: PositionalDiscountPromotion =
                         (&positional_discount_ref).try_into()?;
 
-                    core_promotions.push(promotion(promo.try_to_core_with_key(promotion_key)?));
+                    core_promotions.push(promotion(
+                        promo.try_to_core_with_key(promotion_key, &mut budget_pools)?,
+                    ));
 
                     continue;
                 }
@@ -215,7 +227,9 @@ impl Stack {
                 {
                     let promo: MixAndMatchDiscountPromotion = (&mix_and_match_ref).try_into()?;
 
-                    core_promotions.push(promotion(promo.try_to_core_with_key(promotion_key)?));
+                    core_promotions.push(promotion(
+                        promo.try_to_core_with_key(promotion_key, &mut budget_pools)?,
+                    ));
 
                     continue;
                 }
@@ -225,7 +239,9 @@ impl Stack {
                 {
                     let promo: TieredThresholdPromotion = (&tiered_threshold_ref).try_into()?;
 
-                    core_promotions.push(promotion(promo.try_to_core_with_key(promotion_key)?));
+                    core_promotions.push(promotion(
+                        promo.try_to_core_with_key(promotion_key, &mut budget_pools)?,
+                    ));
 
                     continue;
                 }
@@ -292,18 +308,29 @@ impl Stack {
             }
         }
 
+        builder.set_budget_pools(budget_pools.into_pools());
+
         let graph = PromotionGraph::from_builder(builder).map_err(graph_error_to_php_exception)?;
 
         Ok(BuiltGraph { graph, promotions })
     }
 
-    fn process_items(&self, items: Vec<ItemRef>) -> Result<Receipt, PhpException> {
+    fn process_items(
+        &self,
+        items: Vec<ItemRef>,
+        context: Option<&EvaluationContextRef>,
+    ) -> Result<Receipt, PhpException> {
         let built_graph = self.try_build_graph()?;
         let (item_group, php_items, subtotal) = build_item_group_and_subtotal(&items)?;
 
+        let context: CoreEvaluationContext = match context {
+            Some(context) => context.try_into()?,
+            None => CoreEvaluationContext::default(),
+        };
+
         let result = built_graph
             .graph
-            .evaluate(&item_group)
+            .evaluate_with_context(&item_group, &context)
             .map_err(graph_error_to_php_exception)?;
 
         let mut full_price_items = Vec::with_capacity(result.full_price_items.len());
@@ -357,6 +384,7 @@ impl Stack {
                     app.redemption_idx,
                     original_price,
                     final_price,
+                    Some(app.quantity),
                 );
 
                 promotion_redemptions.push(PromotionRedemptionRef::from_redemption(redemption));
@@ -415,21 +443,32 @@ fn build_item_group_and_subtotal(
             Some(_) => {}
         }
 
-        subtotal_minor = subtotal_minor
-            .checked_add(price.to_minor_units())
+        subtotal_minor = price
+            .to_minor_units()
+            .checked_mul(i64::from(item.quantity()))
+            .and_then(|line_minor| subtotal_minor.checked_add(line_minor))
             .ok_or_else(|| {
                 PhpException::from_class::<InvalidStackException>(
                     "Basket subtotal overflowed i64 minor units.".to_string(),
                 )
             })?;
 
+        let quantity = NonZeroU32::new(item.quantity()).ok_or_else(|| {
+            PhpException::from_class::<InvalidStackException>(
+                "Item quantity must be at least 1.".to_string(),
+            )
+        })?;
+
         let tags: SmallVec<[String; 5]> = item.tags().iter().cloned().collect();
 
-        core_items.push(CoreItem::with_tags(
-            product_keys.insert(()),
-            price,
-            StringTagCollection::new(tags),
-        ));
+        core_items.push(
+            CoreItem::with_tags(
+                product_keys.insert(()),
+                price,
+                StringTagCollection::new(tags),
+            )
+            .with_quantity(quantity),
+        );
 
         php_items.push(item_ref.clone());
     }
@@ -55,6 +55,8 @@ if (!class_exists(Item::class)) {
         /** @var string[] */
         public array $tags;
 
+        public int $quantity;
+
         /**
          * @param  string[]|null  $tags
          */
@@ -64,15 +66,47 @@ if (!class_exists(Item::class)) {
             Money $price,
             Product $product,
             ?array $tags = [],
+            ?int $quantity = 1,
         ) {}
 
         public static function fromProduct(
             mixed $reference,
             Product $product,
+            ?int $quantity = 1,
         ): self {}
     }
 }
 
+if (!class_exists(EvaluationContext::class)) {
+    class EvaluationContext
+    {
+        /** @var string[] */
+        public array $customerSegments;
+
+        public ?string $channel;
+
+        public ?string $storeId;
+
+        /** RFC 3339 timestamp, e.g. "2026-03-01T12:00:00Z" */
+        public ?string $timestamp;
+
+        /** @var array<string, string> */
+        public array $attributes;
+
+        /**
+         * @param  string[]|null  $customer_segments
+         * @param  array<string, string>|null  $attributes
+         */
+        public function __construct(
+            ?array $customer_segments = [],
+            ?string $channel = null,
+            ?string $store_id = null,
+            ?string $timestamp = null,
+            ?array $attributes = [],
+        ) {}
+    }
+}
+
 if (!class_exists(Qualification::class)) {
     class Qualification
     {
@@ -99,7 +133,10 @@ if (!class_exists(Qualification::class)) {
         /**
          * @param  string[]|null  $item_tags
          */
-        public function matches(?array $item_tags = []): bool {}
+        public function matches(
+            ?array $item_tags = [],
+            ?\Lattice\EvaluationContext $context = null,
+        ): bool {}
     }
 }
 
@@ -116,12 +153,15 @@ if (!class_exists(PromotionRedemption::class)) {
 
         public Money $finalPrice;
 
+        public int $quantity;
+
         public function __construct(
             Promotion\PromotionInterface $promotion,
             Item $item,
             int $redemption_idx,
             Money $original_price,
             Money $final_price,
+            ?int $quantity = 1,
         ) {}
     }
 }
@@ -169,6 +209,11 @@ if (!enum_exists(RuleKind::class)) {
         case HasAny = "has_any";
         case HasNone = "has_none";
         case Group = "group";
+        case CustomerSegment = "customer_segment";
+        case Channel = "channel";
+        case Store = "store";
+        case TimeWindow = "time_window";
+        case Attribute = "attribute";
     }
 }
 
@@ -182,6 +227,12 @@ if (!class_exists(Rule::class)) {
 
         public ?\Lattice\Qualification $group;
 
+        public ?string $key;
+
+        public ?string $startsAt;
+
+        public ?string $endsAt;
+
         public function __construct() {}
 
         /**
@@ -203,10 +254,41 @@ if (!class_exists(Rule::class)) {
             \Lattice\Qualification $qualification,
         ): self {}
 
+        /**
+         * @param  string[]|null  $segments
+         */
+        public static function customerSegment(?array $segments = []): self {}
+
+        /**
+         * @param  string[]|null  $channels
+         */
+        public static function channel(?array $channels = []): self {}
+
+        /**
+         * @param  string[]|null  $store_ids
+         */
+        public static function store(?array $store_ids = []): self {}
+
+        /**
+         * Bounds are RFC 3339 timestamps; the start is inclusive and the end exclusive.
+         */
+        public static function timeWindow(
+            ?string $starts_at = null,
+            ?string $ends_at = null,
+        ): self {}
+
+        /**
+         * @param  string[]|null  $values  accepted values; empty accepts any value
+         */
+        public static function attribute(string $key, ?array $values = []): self {}
+
         /**
          * @param  string[]|null  $item_tags
          */
-        public function matches(?array $item_tags = []): bool {}
+        public function matches(
+            ?array $item_tags = [],
+            ?\Lattice\EvaluationContext $context = null,
+        ): bool {}
     }
 }
 
@@ -346,7 +428,10 @@ if (!class_exists(Stack::class)) {
         /**
          * @param  \Lattice\Item[]  $items
          */
-        public function process(array $items): \Lattice\Receipt {}
+        public function process(
+            array $items,
+            ?\Lattice\EvaluationContext $context = null,
+        ): \Lattice\Receipt {}
     }
 }
 
@@ -360,6 +445,23 @@ if (!interface_exists(PromotionInterface::class)) {
     interface PromotionInterface {}
 }
 
+if (!class_exists(BudgetPool::class)) {
+    class BudgetPool
+    {
+        public string $name;
+
+        public ?int $redemptionLimit;
+
+        public ?Money $monetaryLimit;
+
+        public function __construct(
+            string $name,
+            ?int $redemptionLimit = null,
+            ?Money $monetaryLimit = null,
+        ) {}
+    }
+}
+
 if (!class_exists(Budget::class)) {
     class Budget
     {
@@ -367,6 +469,9 @@ if (!class_exists(Budget::class)) {
 
         public ?Money $monetaryLimit;
 
+        /** @var BudgetPool[] */
+        public array $pools;
+
         public function __construct() {}
 
         public static function unlimited(): self {}
@@ -379,6 +484,8 @@ if (!class_exists(Budget::class)) {
             int $redemption,
             Money $monetary,
         ): self {}
+
+        public function withPool(BudgetPool $pool): self {}
     }
 }
 
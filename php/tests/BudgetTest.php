@@ -4,6 +4,7 @@ declare(strict_types=1);
 
 use Lattice\Money;
 use Lattice\Promotion\Budget;
+use Lattice\Promotion\BudgetPool;
 
 it("can be instantiated with an unlimited budget", function (): void {
     $budget = Budget::unlimited();
@@ -35,3 +36,18 @@ it(
         expect($budget->monetaryLimit)->toEqual(new Money(250_000, "GBP"));
     },
 );
+
+it("draws on shared budget pools", function (): void {
+    $pool = new BudgetPool(
+        name: "launch-fund",
+        monetaryLimit: new Money(2_50, "GBP"),
+    );
+
+    $budget = Budget::withRedemptionLimit(5)->withPool($pool);
+
+    expect($budget->redemptionLimit)->toBe(5);
+    expect($budget->pools)->toHaveCount(1);
+    expect($budget->pools[0]->name)->toBe("launch-fund");
+    expect($budget->pools[0]->redemptionLimit)->toBeNull();
+    expect($budget->pools[0]->monetaryLimit)->toEqual(new Money(2_50, "GBP"));
+});
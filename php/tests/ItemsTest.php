@@ -56,3 +56,19 @@ it("removes duplicate item tags", function (): void {
 
     expect($item->tags)->toBe(["test-tag"]);
 });
+
+it("defaults to a single unit and accepts a quantity", function (): void {
+    $product = new Product(1, "Cola", new Money(50, "GBP"));
+
+    $single = Item::fromProduct(1, $product);
+    $case = Item::fromProduct(2, $product, 24);
+
+    expect($single->quantity)->toBe(1);
+    expect($case->quantity)->toBe(24);
+});
+
+it("rejects a quantity of zero", function (): void {
+    $product = new Product(1, "Cola", new Money(50, "GBP"));
+
+    Item::fromProduct(1, $product, 0);
+})->throws(Exception::class, "Item quantity must be at least 1.");
@@ -2,6 +2,7 @@
 
 declare(strict_types=1);
 
+use Lattice\EvaluationContext;
 use Lattice\Qualification;
 use Lattice\Qualification\BoolOp;
 use Lattice\Qualification\Rule;
@@ -58,3 +59,46 @@ it("supports nested boolean groups", function (): void {
     expect($qualification->matches(["peak", "member"]))->toBeFalse();
     expect($qualification->matches(["peak", "snack", "excluded"]))->toBeFalse();
 });
+
+it("matches context rules against an evaluation context", function (): void {
+    $qualification = new Qualification(BoolOp::AndOp, [
+        Rule::hasAny(["coffee"]),
+        Rule::customerSegment(["loyalty"]),
+        Rule::channel(["online"]),
+        Rule::store(["42"]),
+        Rule::timeWindow("2026-03-01T00:00:00Z", "2026-03-08T00:00:00Z"),
+        Rule::attribute("tier", ["gold", "platinum"]),
+    ]);
+
+    $context = new EvaluationContext(
+        customer_segments: ["loyalty"],
+        channel: "online",
+        store_id: "42",
+        timestamp: "2026-03-04T12:00:00Z",
+        attributes: ["tier" => "gold"],
+    );
+
+    expect($qualification->matches(["coffee"], $context))->toBeTrue();
+    expect($qualification->matches(["tea"], $context))->toBeFalse();
+    expect($qualification->matches(["coffee"]))->toBeFalse();
+
+    $late = new EvaluationContext(
+        customer_segments: ["loyalty"],
+        channel: "online",
+        store_id: "42",
+        timestamp: "2026-03-08T00:00:00Z",
+        attributes: ["tier" => "gold"],
+    );
+
+    expect($qualification->matches(["coffee"], $late))->toBeFalse();
+});
+
+it("rejects invalid time windows", function (): void {
+    expect(fn () => Rule::timeWindow("not a timestamp"))->toThrow(Exception::class);
+    expect(
+        fn () => Rule::timeWindow("2026-03-08T00:00:00Z", "2026-03-01T00:00:00Z"),
+    )->toThrow(Exception::class);
+    expect(
+        fn () => new EvaluationContext(timestamp: "yesterday"),
+    )->toThrow(Exception::class);
+});
@@ -4,13 +4,17 @@ declare(strict_types=1);
 
 use Lattice\Discount\Simple;
 use Lattice\Discount\Percentage;
+use Lattice\EvaluationContext;
 use Lattice\Item;
 use Lattice\Money;
 use Lattice\Product;
 use Lattice\PromotionRedemption;
 use Lattice\Promotion\Budget;
+use Lattice\Promotion\BudgetPool;
 use Lattice\Promotion\Direct;
 use Lattice\Qualification;
+use Lattice\Qualification\BoolOp;
+use Lattice\Qualification\Rule;
 use Lattice\Receipt;
 use Lattice\Stack\InvalidStackException;
 use Lattice\Stack\Layer;
@@ -130,6 +134,51 @@ it("builds and processes a single-layer stack", function (): void {
     expect($receipt->total)->toEqual(new Money(2_70, "GBP"));
 });
 
+it("applies context rules from the evaluation context passed to process", function (): void {
+    $item = Item::fromProduct(
+        reference: "item",
+        product: new Product(
+            reference: "product",
+            name: "Coffee Beans",
+            price: new Money(8_00, "GBP"),
+            tags: ["coffee"],
+        ),
+    );
+
+    $loyaltyCoffee = new Direct(
+        reference: "loyalty-coffee",
+        qualification: new Qualification(BoolOp::AndOp, [
+            Rule::hasAny(["coffee"]),
+            Rule::customerSegment(["loyalty"]),
+        ]),
+        discount: Simple::percentageOff(Percentage::fromDecimal(0.2)),
+        budget: Budget::unlimited(),
+    );
+
+    $stack = new StackBuilder();
+
+    $layer = $stack->addLayer(
+        new Layer(
+            reference: "layer-one",
+            output: LayerOutput::passThrough(),
+            promotions: [$loyaltyCoffee],
+        ),
+    );
+
+    $stack->setRoot($layer);
+
+    $built = $stack->build();
+
+    $anonymous = $built->process(items: [$item]);
+    $loyal = $built->process(
+        items: [$item],
+        context: new EvaluationContext(customer_segments: ["loyalty"]),
+    );
+
+    expect($anonymous->total)->toEqual(new Money(8_00, "GBP"));
+    expect($loyal->total)->toEqual(new Money(6_40, "GBP"));
+});
+
 it(
     "builds and processes a two-layer stack and applies only the best layer-two discount",
     function (): void {
@@ -327,3 +376,87 @@ it(
         expect($secondRedemption->finalPrice)->toEqual(new Money(9_50, "GBP"));
     },
 );
+
+it("shares a budget pool between promotions in different layers", function (): void {
+    $coffee = new Product(
+        reference: "coffee",
+        name: "Flat White",
+        price: new Money(3_00, "GBP"),
+        tags: ["drink"],
+    );
+
+    $cake = new Product(
+        reference: "cake",
+        name: "Carrot Cake",
+        price: new Money(2_50, "GBP"),
+        tags: ["food"],
+    );
+
+    $budget = Budget::unlimited()->withPool(
+        new BudgetPool(name: "launch-fund", monetaryLimit: new Money(2_50, "GBP")),
+    );
+
+    $stack = new Stack([
+        new Layer(
+            reference: "drinks",
+            output: LayerOutput::passThrough(),
+            promotions: [
+                new Direct(
+                    reference: "coffee-launch",
+                    qualification: Qualification::matchAny(["drink"]),
+                    discount: Simple::amountOff(new Money(1_00, "GBP")),
+                    budget: $budget,
+                ),
+            ],
+        ),
+        new Layer(
+            reference: "treats",
+            output: LayerOutput::passThrough(),
+            promotions: [
+                new Direct(
+                    reference: "cake-launch",
+                    qualification: Qualification::matchAny(["food"]),
+                    discount: Simple::amountOff(new Money(1_00, "GBP")),
+                    budget: $budget,
+                ),
+            ],
+        ),
+    ]);
+
+    $items = [
+        Item::fromProduct(reference: "coffee-1", product: $coffee),
+        Item::fromProduct(reference: "coffee-2", product: $coffee),
+        Item::fromProduct(reference: "coffee-3", product: $coffee),
+        Item::fromProduct(reference: "cake-1", product: $cake),
+        Item::fromProduct(reference: "cake-2", product: $cake),
+    ];
+
+    $receipt = $stack->process(items: $items);
+
+    expect($receipt->subtotal)->toEqual(new Money(14_00, "GBP"));
+    expect($receipt->total)->toEqual(new Money(12_00, "GBP"));
+});
+
+it("rejects a budget pool defined with conflicting limits", function (): void {
+    $promotion = fn (string $reference, int $limit): Direct => new Direct(
+        reference: $reference,
+        qualification: Qualification::matchAny(["food"]),
+        discount: Simple::amountOff(new Money(25, "GBP")),
+        budget: Budget::unlimited()->withPool(
+            new BudgetPool(name: "shared", redemptionLimit: $limit),
+        ),
+    );
+
+    $stack = new Stack([
+        new Layer(
+            reference: "layer-1",
+            output: LayerOutput::passThrough(),
+            promotions: [$promotion("first", 1), $promotion("second", 2)],
+        ),
+    ]);
+
+    expect(fn () => $stack->validateGraph())->toThrow(
+        Exception::class,
+        "conflicting limits",
+    );
+});